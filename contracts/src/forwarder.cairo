@@ -12,6 +12,14 @@ pub trait IForwarder<TContractState> {
         gas_token_address: ContractAddress,
         gas_amount: u256,
     ) -> bool;
+    fn execute_with_permit(
+        ref self: TContractState,
+        account_address: ContractAddress,
+        entrypoint: felt252,
+        calldata: Array<felt252>,
+        gas_token_address: ContractAddress,
+        gas_amount: u256,
+    ) -> bool;
     fn execute_sponsored(
         ref self: TContractState,
         account_address: ContractAddress,
@@ -119,6 +127,42 @@ pub mod Forwarder {
             true
         }
 
+        fn execute_with_permit(
+            ref self: ContractState,
+            account_address: ContractAddress,
+            entrypoint: felt252,
+            calldata: Array<felt252>,
+            gas_token_address: ContractAddress,
+            gas_amount: u256,
+        ) -> bool {
+            // Check if caller is whitelisted
+            let caller = get_caller_address();
+            assert(self.whitelist.is_whitelisted(caller), 'Caller is not whitelisted');
+
+            // Execute the call, which grants the forwarder an allowance on the gas token
+            call_contract_syscall(account_address, entrypoint, calldata.span()).unwrap_syscall();
+
+            // Pull the whole allowance so that none is left to the forwarder once the call returns, then collect
+            // gas fees and send the rest back to the account
+            let contract_address = get_contract_address();
+            let gas_token = IERC20Dispatcher { contract_address: gas_token_address };
+            let allowance = gas_token.allowance(account_address, contract_address);
+            let balance = gas_token.balanceOf(account_address);
+            let pulled = if allowance < balance {
+                allowance
+            } else {
+                balance
+            };
+            assert(pulled >= gas_amount, 'Allowance too low');
+            gas_token.transferFrom(account_address, contract_address, pulled);
+
+            let gas_fees_recipient = self.get_gas_fees_recipient();
+            gas_token.transfer(gas_fees_recipient, gas_amount);
+            gas_token.transfer(account_address, pulled - gas_amount);
+
+            true
+        }
+
         fn execute_sponsored(
             ref self: ContractState,
             account_address: ContractAddress,
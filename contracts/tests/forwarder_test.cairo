@@ -108,6 +108,84 @@ mod Execute {
     }
 }
 
+mod ExecuteWithPermit {
+    use avnu_lib::interfaces::erc20::IERC20DispatcherTrait;
+    use super::{
+        IForwarderDispatcherTrait, IOwnableDispatcherTrait, IWhitelistDispatcherTrait, contract_address_const, deploy_forwarder,
+        deploy_mock_account, deploy_mock_token, set_contract_address,
+    };
+
+    #[test]
+    #[available_gas(2000000000)]
+    fn should_execute() {
+        // Given
+        let (forwarder, ownable, whitelist) = deploy_forwarder();
+        let caller = contract_address_const::<0x999>();
+        set_contract_address(ownable.get_owner());
+        whitelist.set_whitelisted_address(caller, true);
+        let account = deploy_mock_account();
+        let account_address = account.contract_address;
+        let entrypoint: felt252 = 0x361458367e696363fbcc70777d07ebbd2394e89fd0adcaf147faccd1d294d60;
+        let calldata: Array<felt252> = array![];
+        let gas_token = deploy_mock_token(account_address, 10);
+        let gas_token_address = gas_token.contract_address;
+        let gas_amount: u256 = 1_u256;
+        set_contract_address(account_address);
+        gas_token.approve(forwarder.contract_address, 3_u256);
+        set_contract_address(caller);
+
+        // When
+        let result = forwarder.execute_with_permit(account_address, entrypoint, calldata, gas_token_address, gas_amount);
+
+        // Then
+        assert(result == true, 'invalid result');
+        assert(gas_token.balanceOf(account_address) == 9_u256, 'invalid account balance');
+        assert(gas_token.balanceOf(forwarder.get_gas_fees_recipient()) == 1_u256, 'invalid recipient balance');
+        assert(gas_token.allowance(account_address, forwarder.contract_address) == 0_u256, 'allowance left');
+        assert(gas_token.balanceOf(forwarder.contract_address) == 0_u256, 'invalid forwarder balance');
+    }
+
+    #[test]
+    #[available_gas(2000000000)]
+    #[should_panic(expected: ('Allowance too low', 'ENTRYPOINT_FAILED'))]
+    fn should_fail_when_allowance_is_below_gas_amount() {
+        // Given
+        let (forwarder, ownable, whitelist) = deploy_forwarder();
+        let caller = contract_address_const::<0x999>();
+        set_contract_address(ownable.get_owner());
+        whitelist.set_whitelisted_address(caller, true);
+        let account = deploy_mock_account();
+        let account_address = account.contract_address;
+        let entrypoint: felt252 = 0x361458367e696363fbcc70777d07ebbd2394e89fd0adcaf147faccd1d294d60;
+        let calldata: Array<felt252> = array![];
+        let gas_token = deploy_mock_token(account_address, 10);
+        let gas_token_address = gas_token.contract_address;
+        set_contract_address(account_address);
+        gas_token.approve(forwarder.contract_address, 1_u256);
+        set_contract_address(caller);
+
+        // When & Then
+        forwarder.execute_with_permit(account_address, entrypoint, calldata, gas_token_address, 2_u256);
+    }
+
+    #[test]
+    #[available_gas(2000000)]
+    #[should_panic(expected: ('Caller is not whitelisted', 'ENTRYPOINT_FAILED'))]
+    fn should_fail_when_caller_is_not_whitelisted() {
+        // Given
+        let (forwarder, _, _) = deploy_forwarder();
+        let account_address = contract_address_const::<0x1>();
+        let entrypoint: felt252 = 0x0;
+        let calldata: Array<felt252> = array![0x1, 0x2];
+        let gas_token_address = contract_address_const::<0x1>();
+        let gas_amount: u256 = 1_u256;
+        set_contract_address(contract_address_const::<0x1234>());
+
+        // When & Then
+        forwarder.execute_with_permit(account_address, entrypoint, calldata, gas_token_address, gas_amount);
+    }
+}
+
 mod ExecuteSponsored {
     use super::{
         IForwarderDispatcherTrait, IOwnableDispatcherTrait, IWhitelistDispatcherTrait, contract_address_const, deploy_forwarder,
@@ -0,0 +1,23 @@
+use clap::Parser;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+
+mod runner;
+mod stats;
+
+use crate::runner::{BenchParameters, Runner};
+
+#[tokio::main]
+async fn main() {
+    let logger = SimpleLogger::new().with_level(LevelFilter::Info);
+    log::set_boxed_logger(Box::new(logger)).unwrap();
+    log::set_max_level(LevelFilter::Info);
+
+    let parameters = BenchParameters::parse();
+    if parameters.qps <= 0.0 {
+        eprintln!("--qps must be positive");
+        std::process::exit(1);
+    }
+
+    Runner::new(parameters).run().await.print();
+}
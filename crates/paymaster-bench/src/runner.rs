@@ -0,0 +1,271 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use log::{info, warn};
+use paymaster::rpc::{
+    BuildTransactionRequest, BuildTransactionResponse, Client, ExecutableInvokeParameters, ExecutableTransactionParameters, ExecuteRequest, ExecutionParameters, FeeMode,
+    FinalityLevel, InvokeParameters, InvokeTransaction, TransactionParameters,
+};
+use paymaster_starknet::constants::ChainTokens;
+use paymaster_starknet::{ChainID, StarknetAccountConfiguration};
+use starknet::core::types::{Call, Felt};
+use starknet::macros::{felt, selector};
+use starknet::signers::SigningKey;
+use tokio::task::JoinSet;
+use tokio::time::{interval, MissedTickBehavior};
+
+use crate::stats::{LatencyStats, Stage};
+
+/// Argent account of the devnet used by the paymaster CI
+const DEVNET_USER: StarknetAccountConfiguration = StarknetAccountConfiguration {
+    address: felt!("0x021482d2d427705459ea21f1ed22a769ec6358d7024c17eddf3bbfdf083b8b80"),
+    private_key: felt!("0x0000000000000000000000000000000071d7bb07b9a64f6f78ac4c816aff4da9"),
+};
+
+#[derive(Parser, Clone)]
+#[command(about = "Drive build/execute requests against a running paymaster and report the latency of each stage")]
+pub struct BenchParameters {
+    #[clap(long, default_value = "http://localhost:12777")]
+    pub endpoint: String,
+
+    #[clap(long, default_value_t = 5.0, help = "Number of requests started per second")]
+    pub qps: f64,
+
+    #[clap(long, default_value_t = 60, help = "Duration of the run in seconds")]
+    pub duration: u64,
+
+    #[clap(long, default_value = "sepolia", value_parser = parse_chain_id, help = "Chain served by the paymaster, whose STRK pays the fees by default")]
+    pub chain_id: ChainID,
+
+    #[clap(long, help = "Token used to pay the fees, STRK by default")]
+    pub gas_token: Option<Felt>,
+
+    #[clap(
+        long = "user",
+        value_parser = parse_user,
+        help = "Account sending the transactions as <address>:<private_key>, can be repeated. Defaults to the devnet account of the paymaster CI"
+    )]
+    pub users: Vec<StarknetAccountConfiguration>,
+
+    #[clap(long, help = "Only build the transactions, without executing them")]
+    pub build_only: bool,
+}
+
+fn parse_chain_id(value: &str) -> Result<ChainID, String> {
+    ChainID::from_string(value).map_err(|e| e.to_string())
+}
+
+fn parse_user(value: &str) -> Result<StarknetAccountConfiguration, String> {
+    let (address, private_key) = value.split_once(':').ok_or("expected <address>:<private_key>")?;
+
+    Ok(StarknetAccountConfiguration {
+        address: Felt::from_hex(address).map_err(|e| e.to_string())?,
+        private_key: Felt::from_hex(private_key).map_err(|e| e.to_string())?,
+    })
+}
+
+/// Latencies observed by a single request
+#[derive(Default)]
+struct Sample {
+    latencies: Vec<(Stage, Duration)>,
+    failure: Option<Stage>,
+}
+
+/// Aggregated latencies of a run
+pub struct Report {
+    requests: usize,
+    elapsed: Duration,
+    stages: BTreeMap<Stage, LatencyStats>,
+}
+
+impl Report {
+    fn new() -> Self {
+        Self {
+            requests: 0,
+            elapsed: Duration::ZERO,
+            stages: BTreeMap::new(),
+        }
+    }
+
+    fn add(&mut self, sample: Sample) {
+        self.requests += 1;
+        for (stage, latency) in sample.latencies {
+            self.stages.entry(stage).or_default().record(latency);
+        }
+        if let Some(stage) = sample.failure {
+            self.stages.entry(stage).or_default().record_failure();
+        }
+    }
+
+    pub fn print(&self) {
+        let format = |x: Option<Duration>| x.map(|x| format!("{}ms", x.as_millis())).unwrap_or_else(|| "-".to_string());
+
+        println!(
+            "{} requests in {:.1}s ({:.2} qps)",
+            self.requests,
+            self.elapsed.as_secs_f64(),
+            self.requests as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+        );
+        println!("{:<18}{:>8}{:>10}{:>10}{:>10}{:>10}", "stage", "count", "p50", "p95", "p99", "failures");
+        for (stage, stats) in &self.stages {
+            println!(
+                "{:<18}{:>8}{:>10}{:>10}{:>10}{:>10}",
+                stage,
+                stats.count(),
+                format(stats.percentile(50.0)),
+                format(stats.percentile(95.0)),
+                format(stats.percentile(99.0)),
+                stats.failures()
+            );
+        }
+    }
+}
+
+pub struct Runner {
+    client: Arc<Client>,
+    parameters: BenchParameters,
+}
+
+impl Runner {
+    pub fn new(parameters: BenchParameters) -> Self {
+        Self {
+            client: Arc::new(Client::new(&parameters.endpoint)),
+            parameters,
+        }
+    }
+
+    pub async fn run(self) -> Report {
+        let users = if self.parameters.users.is_empty() {
+            vec![DEVNET_USER]
+        } else {
+            self.parameters.users.clone()
+        };
+        let gas_token = self
+            .parameters
+            .gas_token
+            .unwrap_or(ChainTokens::starknet(&self.parameters.chain_id).strk);
+
+        info!(
+            "Sending {} requests per second for {}s to {}",
+            self.parameters.qps, self.parameters.duration, self.parameters.endpoint
+        );
+
+        let mut ticker = interval(Duration::from_secs_f64(1.0 / self.parameters.qps));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        let started_at = Instant::now();
+        let mut requests = JoinSet::new();
+        for user in users.iter().cycle() {
+            ticker.tick().await;
+            if started_at.elapsed() >= Duration::from_secs(self.parameters.duration) {
+                break;
+            }
+
+            let client = self.client.clone();
+            let (user, build_only) = (*user, self.parameters.build_only);
+            requests.spawn(async move { send_request(&client, user, gas_token, build_only).await });
+        }
+
+        let mut report = Report::new();
+        while let Some(sample) = requests.join_next().await {
+            report.add(sample.unwrap_or_default());
+        }
+        report.elapsed = started_at.elapsed();
+
+        let has_timings = report.stages.contains_key(&Stage::LockAcquisition);
+        if !self.parameters.build_only && !has_timings {
+            warn!("The lock acquisition and submission latencies are only reported when the paymaster has its debug diagnostics enabled");
+        }
+
+        report
+    }
+}
+
+/// Build, sign and execute a transfer of 1 unit of `gas_token` from the user to itself
+async fn send_request(client: &Client, user: StarknetAccountConfiguration, gas_token: Felt, build_only: bool) -> Sample {
+    let mut sample = Sample::default();
+
+    let parameters = ExecutionParameters::V1 {
+        fee_mode: FeeMode::Default {
+            gas_token,
+            tip: Default::default(),
+        },
+        time_bounds: None,
+    };
+    let transfer = Call {
+        to: gas_token,
+        selector: selector!("transfer"),
+        calldata: vec![user.address, Felt::ONE, Felt::ZERO],
+    };
+
+    let build_request = BuildTransactionRequest {
+        transaction: TransactionParameters::Invoke {
+            invoke: InvokeParameters {
+                user_address: user.address,
+                calls: vec![transfer],
+                session: false,
+            },
+        },
+        parameters: parameters.clone(),
+        chain_id: None,
+    };
+
+    let started_at = Instant::now();
+    let typed_data = match client.build_transaction(build_request).await {
+        Ok(BuildTransactionResponse::Invoke(InvokeTransaction { typed_data, .. })) => typed_data,
+        Ok(_) | Err(_) => {
+            sample.failure = Some(Stage::Estimation);
+            return sample;
+        },
+    };
+    sample.latencies.push((Stage::Estimation, started_at.elapsed()));
+
+    if build_only {
+        return sample;
+    }
+
+    let Some(signature) = typed_data
+        .message_hash(user.address)
+        .ok()
+        .and_then(|x| SigningKey::from_secret_scalar(user.private_key).sign(&x).ok())
+    else {
+        sample.failure = Some(Stage::Execution);
+        return sample;
+    };
+
+    let execute_request = ExecuteRequest {
+        transaction: ExecutableTransactionParameters::Invoke {
+            invoke: ExecutableInvokeParameters {
+                user_address: user.address,
+                typed_data: Some(typed_data),
+                message_hash: None,
+                signature: vec![signature.r, signature.s],
+                session: None,
+            },
+        },
+        parameters,
+        finality: FinalityLevel::Submitted,
+        chain_id: None,
+        client_nonce: None,
+    };
+
+    let started_at = Instant::now();
+    match client.execute_transaction(execute_request).await {
+        Ok(response) => {
+            sample.latencies.push((Stage::Execution, started_at.elapsed()));
+            if let Some(timings) = response.timings {
+                sample
+                    .latencies
+                    .push((Stage::LockAcquisition, Duration::from_millis(timings.lock_acquisition_ms)));
+                sample
+                    .latencies
+                    .push((Stage::Submission, Duration::from_millis(timings.submission_ms)));
+            }
+        },
+        Err(_) => sample.failure = Some(Stage::Execution),
+    }
+
+    sample
+}
@@ -0,0 +1,91 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// Stage of a request whose latency is measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    /// Round trip of `paymaster_buildTransaction`, dominated by the fee estimation
+    Estimation,
+
+    /// Time the paymaster waited for an available relayer
+    LockAcquisition,
+
+    /// Time the paymaster spent sending the transaction with the relayer
+    Submission,
+
+    /// Round trip of `paymaster_executeTransaction`
+    Execution,
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Estimation => "estimation",
+            Self::LockAcquisition => "lock acquisition",
+            Self::Submission => "submission",
+            Self::Execution => "execution",
+        };
+
+        f.pad(name)
+    }
+}
+
+/// Latencies recorded for a stage
+#[derive(Debug, Clone, Default)]
+pub struct LatencyStats {
+    samples: Vec<Duration>,
+    failures: usize,
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, duration: Duration) {
+        self.samples.push(duration);
+    }
+
+    pub fn record_failure(&mut self) {
+        self.failures += 1;
+    }
+
+    pub fn count(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn failures(&self) -> usize {
+        self.failures
+    }
+
+    /// Returns the given percentile of the recorded latencies using the nearest-rank method
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        let mut samples = self.samples.clone();
+        samples.sort();
+
+        let rank = (percentile / 100.0 * samples.len() as f64).ceil() as usize;
+        Some(samples[rank.clamp(1, samples.len()) - 1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::stats::LatencyStats;
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let mut stats = LatencyStats::default();
+        assert_eq!(stats.percentile(50.0), None);
+
+        for millis in (1..=100).rev() {
+            stats.record(Duration::from_millis(millis));
+        }
+
+        assert_eq!(stats.percentile(50.0), Some(Duration::from_millis(50)));
+        assert_eq!(stats.percentile(95.0), Some(Duration::from_millis(95)));
+        assert_eq!(stats.percentile(99.0), Some(Duration::from_millis(99)));
+        assert_eq!(stats.percentile(0.0), Some(Duration::from_millis(1)));
+    }
+}
@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use log::info;
+use paymaster_rpc::client::Client;
+use paymaster_rpc::{AccountRole, AccountingSnapshotRequest, AccountingSnapshotResponse};
+use paymaster_starknet::math::denormalize_felt;
+
+use crate::core::Error;
+
+#[derive(Args, Clone)]
+pub struct AccountingSnapshotCommandParameters {
+    #[clap(long, help = "Endpoint of the running paymaster")]
+    pub endpoint: String,
+
+    #[clap(long, help = "Admin api key of the running paymaster")]
+    pub api_key: String,
+
+    #[clap(long, help = "Export the snapshot to this CSV file")]
+    pub output: Option<PathBuf>,
+}
+
+pub async fn command_accounting_snapshot(params: AccountingSnapshotCommandParameters) -> Result<(), Error> {
+    info!("📒 Taking accounting snapshot of {}", params.endpoint);
+
+    let client = Client::builder(&params.endpoint)
+        .with_api_key(params.api_key.clone())
+        .build()
+        .map_err(|e| Error::Execution(format!("Failed to create client: {}", e)))?;
+
+    let snapshot = client
+        .get_accounting_snapshot(AccountingSnapshotRequest::default())
+        .await
+        .map_err(|e| Error::Execution(format!("Failed to take snapshot: {}", e)))?;
+
+    info!(
+        "Snapshot taken at block {} with {} STRK in flight",
+        snapshot.block_number,
+        denormalize_felt(snapshot.in_flight_cost_in_strk, 18)
+    );
+
+    match params.output {
+        Some(path) => {
+            std::fs::write(&path, to_csv(&snapshot)).map_err(|e| Error::Execution(format!("Failed to write {}: {}", path.display(), e)))?;
+            info!("✅ Snapshot exported to {}", path.display());
+        },
+        None => print!("{}", to_csv(&snapshot)),
+    }
+
+    Ok(())
+}
+
+// Balances are written in the base unit of their token so that no precision is lost. The cost of the transactions
+// in flight is reported on its own line.
+fn to_csv(snapshot: &AccountingSnapshotResponse) -> String {
+    let mut csv = String::from("block_number,taken_at,role,address,token,balance\n");
+    for balance in &snapshot.balances {
+        let role = match balance.role {
+            AccountRole::GasTank => "gas_tank",
+            AccountRole::Relayer => "relayer",
+        };
+
+        csv.push_str(&format!(
+            "{},{},{},{:#x},{:#x},{}\n",
+            snapshot.block_number, snapshot.taken_at, role, balance.address, balance.token, balance.balance
+        ));
+    }
+
+    csv.push_str(&format!(
+        "{},{},in_flight,,{:#x},{}\n",
+        snapshot.block_number, snapshot.taken_at, snapshot.fee_token, snapshot.in_flight_cost_in_strk
+    ));
+    csv
+}
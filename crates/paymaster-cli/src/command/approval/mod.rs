@@ -0,0 +1,95 @@
+use clap::Args;
+use log::info;
+use paymaster_rpc::client::Client;
+use paymaster_rpc::{ApprovalRequest, PendingApprovalsRequest};
+
+use crate::core::Error;
+
+#[derive(Args, Clone)]
+pub struct ApprovalsCommandParameters {
+    #[clap(long, help = "Endpoint of the running paymaster")]
+    pub endpoint: String,
+
+    #[clap(long, help = "Admin api key of the running paymaster")]
+    pub api_key: String,
+
+    #[clap(long, conflicts_with = "reject", help = "Execute the entry with this id on behalf of its sponsor")]
+    pub approve: Option<String>,
+
+    #[clap(
+        long,
+        requires = "approve",
+        help = "Api key of the sponsor of the approved entry, required when the execution is sponsored"
+    )]
+    pub sponsor_api_key: Option<String>,
+
+    #[clap(long, help = "Remove the entry with this id without executing it")]
+    pub reject: Option<String>,
+
+    #[clap(long, default_value = "100", help = "Maximum number of entries listed")]
+    pub limit: usize,
+}
+
+pub async fn command_approvals(params: ApprovalsCommandParameters) -> Result<(), Error> {
+    let client = Client::builder(&params.endpoint)
+        .with_api_key(params.api_key.clone())
+        .build()
+        .map_err(|e| Error::Execution(format!("Failed to create client: {}", e)))?;
+
+    if let Some(id) = params.approve {
+        info!("✍️ Approving pending execution {}", id);
+
+        let response = client
+            .approve_execution(ApprovalRequest {
+                id,
+                sponsor_api_key: params.sponsor_api_key,
+                chain_id: None,
+            })
+            .await
+            .map_err(|e| Error::Execution(format!("Failed to approve execution: {}", e)))?;
+
+        info!("✅ Transaction submitted {:#x}", response.transaction_hash);
+        return Ok(());
+    }
+
+    if let Some(id) = params.reject {
+        info!("🗑️ Rejecting pending execution {}", id);
+
+        client
+            .reject_execution(ApprovalRequest {
+                id,
+                sponsor_api_key: None,
+                chain_id: None,
+            })
+            .await
+            .map_err(|e| Error::Execution(format!("Failed to reject execution: {}", e)))?;
+
+        info!("✅ Execution rejected");
+        return Ok(());
+    }
+
+    info!("📭 Fetching pending executions from {}", params.endpoint);
+
+    let response = client
+        .get_pending_approvals(PendingApprovalsRequest {
+            limit: Some(params.limit),
+            chain_id: None,
+        })
+        .await
+        .map_err(|e| Error::Execution(format!("Failed to fetch pending executions: {}", e)))?;
+
+    info!("{} executions waiting for an approval", response.entries.len());
+    for entry in response.entries {
+        println!(
+            "\n{} parked at {}\n  user: {:#x}\n  sponsor: {}\n  fee: {} FRI\n  reason: {}",
+            entry.id,
+            entry.parked_at,
+            entry.user_address,
+            entry.sponsor.as_deref().unwrap_or("-"),
+            entry.fee_in_strk,
+            entry.reason
+        );
+    }
+
+    Ok(())
+}
@@ -2,13 +2,14 @@ use clap::Args;
 use paymaster_common::concurrency::ConcurrentExecutor;
 use paymaster_common::service::Error as ServiceError;
 use paymaster_common::task;
+use paymaster_rpc::client::Client as PaymasterClient;
+use paymaster_rpc::TreasuryReportRequest;
 use paymaster_service::core::context::configuration::Configuration as ServiceConfiguration;
-use paymaster_starknet::constants::Token;
 use paymaster_starknet::{Client, Configuration};
 use starknet::core::types::Felt;
 use tracing::info;
 
-use crate::command::balance::utils::display_table;
+use crate::command::balance::utils::{display_table, display_treasury_report};
 use crate::core::Error;
 
 mod utils;
@@ -22,6 +23,16 @@ pub struct BalanceResult {
 pub struct BalancesCommandParameters {
     #[clap(long)]
     pub profile: String,
+
+    #[clap(
+        long,
+        requires = "api_key",
+        help = "Endpoint of the running paymaster, to report the value of the gas tank and its burn rate"
+    )]
+    pub endpoint: Option<String>,
+
+    #[clap(long, help = "Admin api key of the running paymaster")]
+    pub api_key: Option<String>,
 }
 
 async fn compute_table_for_accounts(account_name: &str, starknet: &Client, accounts: Vec<Felt>) {
@@ -30,7 +41,7 @@ async fn compute_table_for_accounts(account_name: &str, starknet: &Client, accou
     let mut executor = ConcurrentExecutor::new(starknet.clone(), nb_accounts);
     for &account in &accounts {
         executor.register(task!(|env| {
-            let balance = env.fetch_balance(Token::STRK_ADDRESS, account).await?;
+            let balance = env.fetch_balance(env.tokens().strk, account).await?;
 
             Ok::<BalanceResult, paymaster_starknet::Error>(BalanceResult { address: account, balance })
         }));
@@ -64,8 +75,12 @@ pub async fn command_balances(params: BalancesCommandParameters) -> Result<(), E
         endpoint: rpc_url,
         chain_id,
         fallbacks: vec![],
+        local_estimation: None,
+        endpoint_options: configuration.starknet.endpoint_options.clone(),
+        tokens: configuration.starknet.tokens,
         timeout: 10,
-    });
+    })
+    .map_err(|e| Error::Execution(format!("Failed to create Starknet client: {}", e)))?;
 
     // Display relayers balances
     compute_table_for_accounts("Relayer", &starknet, configuration.relayers.addresses).await;
@@ -76,5 +91,20 @@ pub async fn command_balances(params: BalancesCommandParameters) -> Result<(), E
     // Display estimate account balance
     compute_table_for_accounts("Estimate", &starknet, vec![configuration.estimate_account.address]).await;
 
+    // Display the treasury report of the running paymaster
+    if let (Some(endpoint), Some(api_key)) = (params.endpoint, params.api_key) {
+        let client = PaymasterClient::builder(&endpoint)
+            .with_api_key(api_key)
+            .build()
+            .map_err(|e| Error::Execution(format!("Failed to create client: {}", e)))?;
+
+        let report = client
+            .get_treasury_report(TreasuryReportRequest::default())
+            .await
+            .map_err(|e| Error::Execution(format!("Failed to fetch treasury report: {}", e)))?;
+
+        display_treasury_report(&report);
+    }
+
     Ok(())
 }
@@ -1,4 +1,6 @@
+use paymaster_rpc::TreasuryReportResponse;
 use paymaster_starknet::math::denormalize_felt;
+use starknet::core::types::Felt;
 
 use crate::command::balance::BalanceResult;
 
@@ -22,13 +24,11 @@ pub fn display_table(results: &Vec<Result<BalanceResult, paymaster_starknet::Err
     for result in results {
         match result {
             Ok(relayer_balance) => {
-                let addr_str = format!("{:x}", relayer_balance.address);
-                let cropped_addr = if addr_str.len() > 8 {
-                    format!("0x{}...{}", &addr_str[..4], &addr_str[addr_str.len() - 4..])
-                } else {
-                    format!("0x{}", addr_str)
-                };
-                println!("| {:<50} | {:<20} |", cropped_addr, format!("{}", denormalize_felt(relayer_balance.balance, 18)));
+                println!(
+                    "| {:<50} | {:<20} |",
+                    crop_address(relayer_balance.address),
+                    format!("{}", denormalize_felt(relayer_balance.balance, 18))
+                );
             },
             Err(e) => {
                 println!("| {:<50} | {:<20} |", "Error", format!("Failed: {}", e));
@@ -37,3 +37,54 @@ pub fn display_table(results: &Vec<Result<BalanceResult, paymaster_starknet::Err
     }
     println!("{}", "_".repeat(77));
 }
+
+// Display the holdings of the gas tank valued in STRK and USD followed by their evolution and the burn rate
+//
+// Example:
+// -----------------------------------------------------------------------------
+// Gas Tank Token         |           Value (STRK) |            Value (USD)    |
+// -----------------------------------------------------------------------------
+// 0x0471...938d          | 1000.00                | 500.00                    |
+// 0x053c...68a8          | 200.00                 | 100.00                    |
+// -----------------------------------------------------------------------------
+// Total: 1200.00 STRK (600.00 USD)
+// 7d delta: -800.00 STRK, 30d delta: -3000.00 STRK
+// Burn rate: 114.29 STRK/day, runway: 10.5 days
+//
+pub fn display_treasury_report(report: &TreasuryReportResponse) {
+    let usd = |value: Option<f64>| value.map(|x| format!("{:.2}", x)).unwrap_or_else(|| "-".to_string());
+    let strk = |value: Option<f64>| value.map(|x| format!("{:.2} STRK", x)).unwrap_or_else(|| "-".to_string());
+
+    println!("\n{}", "_".repeat(77));
+    println!("| {:^27} | {:^20} | {:^20} |", "Gas Tank Token", "Value (STRK)", "Value (USD)");
+    println!("|{}|{}|{}|", "-".repeat(29), "-".repeat(22), "-".repeat(22));
+    for holding in &report.holdings {
+        println!(
+            "| {:<27} | {:<20} | {:<20} |",
+            crop_address(holding.token),
+            format!("{:.2}", denormalize_felt(holding.value_in_strk, 18)),
+            usd(holding.value_in_usd)
+        );
+    }
+    println!("{}", "_".repeat(77));
+
+    println!("Total: {:.2} STRK ({} USD)", denormalize_felt(report.value_in_strk, 18), usd(report.value_in_usd));
+    println!("7d delta: {}, 30d delta: {}", strk(report.delta_7d_in_strk), strk(report.delta_30d_in_strk));
+    println!(
+        "Burn rate: {}/day, runway: {} days",
+        strk(report.daily_burn_in_strk),
+        report
+            .runway_days
+            .map(|x| format!("{:.1}", x))
+            .unwrap_or_else(|| "-".to_string())
+    );
+}
+
+fn crop_address(address: Felt) -> String {
+    let address = format!("{:x}", address);
+    if address.len() > 8 {
+        format!("0x{}...{}", &address[..4], &address[address.len() - 4..])
+    } else {
+        format!("0x{}", address)
+    }
+}
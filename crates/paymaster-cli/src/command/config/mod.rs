@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use clap::Args;
+use log::info;
+use paymaster_service::core::context::configuration::{Configuration as ServiceConfiguration, ConfigurationLayers};
+use paymaster_service::core::context::environment::{Variables, VariablesResolver};
+use serde_json::Value;
+use starknet::core::utils::starknet_keccak;
+
+use crate::core::Error;
+
+#[derive(Args, Clone)]
+pub struct ConfigCommandParameters {
+    #[clap(long, help = "Base profile, PAYMASTER_PROFILE when omitted")]
+    pub profile: Option<String>,
+
+    #[clap(long, help = "Overlay of the environment merged over the base profile, PAYMASTER_OVERLAY when omitted")]
+    pub overlay: Option<String>,
+
+    #[clap(long, help = "Ignore the PAYMASTER_* environment variables")]
+    pub ignore_environment: bool,
+
+    #[clap(long, help = "File in which the configuration is written, printed on the standard output when omitted")]
+    pub output: Option<PathBuf>,
+
+    #[clap(long, help = "Print the private keys, api keys, credentials and Redis urls instead of redacting them")]
+    pub reveal_secrets: bool,
+}
+
+const REDACTED: &str = "<redacted>";
+
+// Fields holding a secret, redacted wherever they appear in the configuration
+const SECRET_FIELDS: [&str; 6] = ["private_key", "api_key", "admin_api_key", "password", "secret", "response_signing_key"];
+
+// Maps indexed by the api keys of the sponsors, whose keys are replaced by their fingerprint unless they already are one
+const SPONSOR_MAPS: [&str; 1] = ["sponsors"];
+
+/// Print the configuration the service would run with, merging the base profile, the overlay and the environment
+/// variables in the same order as the service. The secrets are redacted unless `--reveal-secrets` is set.
+pub async fn command_config(params: ConfigCommandParameters) -> Result<(), Error> {
+    let resolver = VariablesResolver::initialize();
+    let mut layers = ConfigurationLayers::resolve(&resolver, Variables::from(HashMap::new())).map_err(|e| Error::Execution(e.to_string()))?;
+
+    if params.profile.is_some() {
+        layers.profile = params.profile;
+    }
+    if params.overlay.is_some() {
+        layers.overlay = params.overlay;
+    }
+    if params.ignore_environment {
+        layers.environment = Variables::from(HashMap::new());
+    }
+
+    let profile = layers.merge().map_err(|e| Error::Execution(e.to_string()))?;
+    let configuration = ServiceConfiguration::from_profile(&profile).map_err(|e| Error::Validation(e.to_string()))?;
+
+    let mut value = serde_json::to_value(&configuration).map_err(|e| Error::Execution(format!("Failed to serialize the configuration: {}", e)))?;
+    if !params.reveal_secrets {
+        redact(&mut value);
+    }
+
+    let data = serde_json::to_string_pretty(&value).map_err(|e| Error::Execution(format!("Failed to serialize the configuration: {}", e)))?;
+
+    match params.output {
+        Some(path) => {
+            std::fs::write(&path, data).map_err(|e| Error::Execution(format!("Failed to write {}: {}", path.display(), e)))?;
+            info!("📄 Effective configuration written to {}", path.display());
+        },
+        None => println!("{}", data),
+    }
+
+    Ok(())
+}
+
+// Redact the secrets of the configuration: the secret fields, the headers and the basic auth token sent to the
+// endpoints, the api keys indexing the sponsors and the Redis urls, which may carry a password
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                match (name.as_str(), field) {
+                    (name, field) if SECRET_FIELDS.contains(&name) && !field.is_null() => *field = Value::from(REDACTED),
+                    ("token", field @ Value::String(_)) if !field.as_str().is_some_and(|x| x.starts_with("0x")) => *field = Value::from(REDACTED),
+                    ("headers", Value::Object(headers)) => headers.values_mut().for_each(|x| *x = Value::from(REDACTED)),
+                    (name, Value::Object(sponsors)) if SPONSOR_MAPS.contains(&name) => {
+                        *sponsors = std::mem::take(sponsors)
+                            .into_iter()
+                            .map(|(key, mut x)| {
+                                redact(&mut x);
+                                match key.starts_with("0x") {
+                                    true => (key, x),
+                                    false => (starknet_keccak(key.as_bytes()).to_fixed_hex_string(), x),
+                                }
+                            })
+                            .collect();
+                    },
+                    (_, field) => redact(field),
+                }
+            }
+        },
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        Value::String(x) if x.starts_with("redis://") || x.starts_with("rediss://") => *x = REDACTED.to_string(),
+        _ => {},
+    }
+}
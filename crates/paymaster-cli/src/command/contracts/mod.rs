@@ -0,0 +1,105 @@
+use clap::Args;
+use paymaster_service::core::context::configuration::Configuration as ServiceConfiguration;
+use paymaster_starknet::{Client, Configuration, StarknetAccountConfiguration};
+use starknet::core::types::Felt;
+use tracing::{info, warn};
+
+use crate::constants::DEFAULT_MAX_CHECK_STATUS_ATTEMPTS;
+use crate::contracts::{ContractArtifact, ContractArtifacts};
+use crate::core::Error;
+
+#[derive(Args, Clone)]
+pub struct ContractsCommandParameters {
+    #[clap(long)]
+    pub profile: String,
+
+    #[clap(
+        long,
+        help = "File mapping the chain identifiers to the class hash and the Sierra and compiled classes of the forwarder, relayer account and gas tank account"
+    )]
+    pub artifacts: Option<String>,
+
+    #[clap(long, requires_all = ["master_address", "master_pk"], help = "Declare the classes which are not declared yet")]
+    pub declare: bool,
+
+    #[clap(long)]
+    pub master_address: Option<Felt>,
+
+    #[clap(long)]
+    pub master_pk: Option<Felt>,
+
+    #[clap(long, default_value_t = DEFAULT_MAX_CHECK_STATUS_ATTEMPTS)]
+    pub max_check_status_attempts: usize,
+}
+
+pub async fn command_contracts(params: ContractsCommandParameters) -> Result<(), Error> {
+    info!("📜 Checking contract classes for profile: {}", params.profile);
+
+    let configuration = ServiceConfiguration::from_file(&params.profile).map_err(|e| Error::Execution(format!("Failed to load profile: {}", e)))?;
+    let chain_id = configuration.starknet.chain_id;
+
+    let artifacts = match &params.artifacts {
+        Some(path) => ContractArtifacts::from_file(path, &chain_id)?,
+        None => ContractArtifacts::pinned(&chain_id),
+    };
+
+    let starknet = Client::new(&Configuration {
+        endpoint: configuration.starknet.endpoint.clone(),
+        chain_id,
+        fallbacks: vec![],
+        local_estimation: None,
+        endpoint_options: configuration.starknet.endpoint_options.clone(),
+        tokens: configuration.starknet.tokens,
+        timeout: configuration.starknet.timeout,
+    })
+    .map_err(|e| Error::Execution(format!("Failed to create Starknet client: {}", e)))?;
+
+    let mut undeclared: Vec<(&str, &ContractArtifact)> = vec![];
+    for (name, artifact) in artifacts.iter() {
+        artifact
+            .verify()
+            .map_err(|e| Error::Validation(format!("{} artifact: {}", name, e)))?;
+
+        let declared = artifact.is_declared(&starknet).await?;
+        match declared {
+            true => info!("✅ {} class {} is declared", name, artifact.class_hash.to_fixed_hex_string()),
+            false => warn!("❌ {} class {} is not declared", name, artifact.class_hash.to_fixed_hex_string()),
+        }
+
+        // Relayers and gas tank usually share the same account class
+        if !declared && !undeclared.iter().any(|(_, x)| x.class_hash == artifact.class_hash) {
+            undeclared.push((name, artifact));
+        }
+    }
+
+    if undeclared.is_empty() {
+        return Ok(());
+    }
+    if !params.declare {
+        return Err(Error::Validation(format!(
+            "{} classes are not declared, use --declare to declare them",
+            undeclared.len()
+        )));
+    }
+
+    let (Some(address), Some(private_key)) = (params.master_address, params.master_pk) else {
+        return Err(Error::Validation("master account is required to declare".to_string()));
+    };
+    let account = starknet.initialize_account(&StarknetAccountConfiguration { address, private_key });
+
+    for (name, artifact) in undeclared {
+        let transaction_hash = artifact
+            .declare(&starknet, &account, params.max_check_status_attempts)
+            .await
+            .map_err(|e| Error::Execution(format!("{} artifact: {}", name, e)))?;
+
+        info!(
+            "✅ {} class {} declared, tx hash: {}",
+            name,
+            artifact.class_hash.to_fixed_hex_string(),
+            transaction_hash.to_fixed_hex_string()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use clap::Args;
+use log::info;
+use paymaster_rpc::client::Client;
+use paymaster_rpc::{DeadLetterRequest, DeadLettersRequest};
+
+use crate::core::Error;
+
+#[derive(Args, Clone)]
+pub struct DeadLettersCommandParameters {
+    #[clap(long, help = "Endpoint of the running paymaster")]
+    pub endpoint: String,
+
+    #[clap(long, help = "Admin api key of the running paymaster")]
+    pub api_key: String,
+
+    #[clap(long, conflicts_with = "discard", help = "Execute again the entry with this id on behalf of its sponsor")]
+    pub retry: Option<String>,
+
+    #[clap(long, help = "Remove the entry with this id without executing it")]
+    pub discard: Option<String>,
+
+    #[clap(long, default_value = "100", help = "Maximum number of entries listed")]
+    pub limit: usize,
+}
+
+pub async fn command_dead_letters(params: DeadLettersCommandParameters) -> Result<(), Error> {
+    let client = Client::builder(&params.endpoint)
+        .with_api_key(params.api_key.clone())
+        .build()
+        .map_err(|e| Error::Execution(format!("Failed to create client: {}", e)))?;
+
+    if let Some(id) = params.retry {
+        info!("🔁 Retrying dead-letter entry {}", id);
+
+        let response = client
+            .retry_dead_letter(DeadLetterRequest { id, chain_id: None })
+            .await
+            .map_err(|e| Error::Execution(format!("Failed to retry entry: {}", e)))?;
+
+        info!("✅ Transaction submitted {:#x}", response.transaction_hash);
+        return Ok(());
+    }
+
+    if let Some(id) = params.discard {
+        info!("🗑️ Discarding dead-letter entry {}", id);
+
+        client
+            .discard_dead_letter(DeadLetterRequest { id, chain_id: None })
+            .await
+            .map_err(|e| Error::Execution(format!("Failed to discard entry: {}", e)))?;
+
+        info!("✅ Entry discarded");
+        return Ok(());
+    }
+
+    info!("📭 Fetching dead-letter entries from {}", params.endpoint);
+
+    let response = client
+        .get_dead_letters(DeadLettersRequest {
+            limit: Some(params.limit),
+            chain_id: None,
+        })
+        .await
+        .map_err(|e| Error::Execution(format!("Failed to fetch entries: {}", e)))?;
+
+    info!("{} entries in the dead-letter queue", response.entries.len());
+    for entry in response.entries {
+        println!(
+            "\n{} failed at {}\n  user: {:#x}\n  sponsor: {}\n  error: {}",
+            entry.id,
+            entry.failed_at,
+            entry.user_address,
+            entry.sponsor.as_deref().unwrap_or("-"),
+            entry.error
+        );
+    }
+
+    Ok(())
+}
@@ -2,12 +2,14 @@ use std::io::stdin;
 use std::time::Duration;
 
 use clap::Args;
+use paymaster_relayer::multisig::{MultisigConfiguration, ProposalStore};
 use paymaster_service::core::context::configuration::Configuration as ServiceConfiguration;
-use paymaster_starknet::constants::Token;
+use paymaster_starknet::transaction::multisig::MultisigProposal;
 use paymaster_starknet::transaction::{Calls, TimeBounds};
-use paymaster_starknet::{Client, Configuration, StarknetAccountConfiguration};
-use starknet::accounts::ConnectedAccount;
+use paymaster_starknet::{Client, Configuration, StarknetAccount, StarknetAccountConfiguration};
+use starknet::accounts::{Account, ConnectedAccount};
 use starknet::core::types::Felt;
+use starknet::signers::SigningKey;
 use tracing::{info, warn};
 
 use crate::core::starknet::transaction::transfer::Transfer;
@@ -55,7 +57,7 @@ pub async fn empty_paymaster_core(
     });
 
     // Create a transfer call from each relayer
-    let strk = Token::STRK_ADDRESS;
+    let strk = starknet.tokens().strk;
     let caller = master_address;
 
     let mut relayers_empty_calls_from_outside = Vec::new();
@@ -76,7 +78,9 @@ pub async fn empty_paymaster_core(
                 address: *address,
                 private_key: relayer_pk,
             });
-            let outside_call = relayer_empty_call.as_execute_from_outside_call(caller, relayer_account, relayer_pk, TimeBounds::valid_for(Duration::from_secs(3600)));
+            let outside_call = relayer_empty_call
+                .as_execute_from_outside_call(caller, relayer_account, relayer_pk, TimeBounds::valid_for(Duration::from_secs(3600)))
+                .map_err(|e| Error::Execution(format!("Failed to sign outside execution: {}", e)))?;
             relayers_empty_calls_from_outside.push(outside_call);
         }
     }
@@ -137,28 +141,48 @@ pub async fn empty_paymaster_core(
     if !relayers_empty_calls_from_outside.is_empty() || !gas_tank_empty_tokens_transfer.is_empty() || !estimate_account_empty_tokens_transfer.is_empty() {
         let mut all_calls = relayers_empty_calls_from_outside;
 
-        if !gas_tank_empty_tokens_transfer.is_empty() {
+        if let Some(multisig) = &configuration.gas_tank_multisig {
+            // A multisig gas tank cannot sign an outside execution on its own, its transfers are proposed to the signers instead
+            if !gas_tank_empty_tokens_transfer.is_empty() {
+                propose_gas_tank_empty(
+                    starknet,
+                    &gas_tank_account,
+                    multisig,
+                    Calls::new(gas_tank_empty_tokens_transfer),
+                    configuration.gas_tank.private_key,
+                )
+                .await?;
+            }
+        } else if !gas_tank_empty_tokens_transfer.is_empty() {
             let gas_tank_empty_tokens_calls = Calls::new(gas_tank_empty_tokens_transfer);
-            let gas_tank_empty_tokens_calls_from_outside = gas_tank_empty_tokens_calls.as_execute_from_outside_call(
-                caller,
-                gas_tank_account.clone(),
-                configuration.gas_tank.private_key,
-                TimeBounds::valid_for(Duration::from_secs(3600)),
-            );
+            let gas_tank_empty_tokens_calls_from_outside = gas_tank_empty_tokens_calls
+                .as_execute_from_outside_call(
+                    caller,
+                    gas_tank_account.clone(),
+                    configuration.gas_tank.private_key,
+                    TimeBounds::valid_for(Duration::from_secs(3600)),
+                )
+                .map_err(|e| Error::Execution(format!("Failed to sign outside execution: {}", e)))?;
             all_calls.push(gas_tank_empty_tokens_calls_from_outside);
         }
 
         if !estimate_account_empty_tokens_transfer.is_empty() {
             let estimate_account_empty_tokens_calls = Calls::new(estimate_account_empty_tokens_transfer);
-            let estimate_account_empty_tokens_calls_from_outside = estimate_account_empty_tokens_calls.as_execute_from_outside_call(
-                caller,
-                estimate_account_account.clone(),
-                configuration.estimate_account.private_key,
-                TimeBounds::valid_for(Duration::from_secs(3600)),
-            );
+            let estimate_account_empty_tokens_calls_from_outside = estimate_account_empty_tokens_calls
+                .as_execute_from_outside_call(
+                    caller,
+                    estimate_account_account.clone(),
+                    configuration.estimate_account.private_key,
+                    TimeBounds::valid_for(Duration::from_secs(3600)),
+                )
+                .map_err(|e| Error::Execution(format!("Failed to sign outside execution: {}", e)))?;
             all_calls.push(estimate_account_empty_tokens_calls_from_outside);
         }
 
+        if all_calls.is_empty() {
+            return Ok(Felt::ZERO);
+        }
+
         let multicall = Calls::new(all_calls);
         let nonce = master_account.get_nonce().await.unwrap();
         let result = multicall.execute(&master_account, nonce).await.unwrap();
@@ -172,6 +196,34 @@ pub async fn empty_paymaster_core(
     }
 }
 
+/// Propose the transfers emptying a multisig gas tank to its signers
+async fn propose_gas_tank_empty(starknet: &Client, gas_tank: &StarknetAccount, multisig: &MultisigConfiguration, calls: Calls, private_key: Felt) -> Result<(), Error> {
+    let nonce = gas_tank
+        .get_nonce()
+        .await
+        .map_err(|e| Error::Execution(format!("Failed to fetch gas tank nonce: {}", e)))?;
+    let estimated_calls = MultisigProposal::estimate(starknet, gas_tank.address(), &calls, nonce)
+        .await
+        .map_err(|e| Error::Execution(format!("Failed to estimate gas tank transfers: {}", e)))?;
+
+    let store = ProposalStore::new(multisig);
+    let nonce = store.next_nonce(nonce).map_err(|e| Error::Execution(e.to_string()))?;
+    let mut proposal = MultisigProposal::new(gas_tank, &estimated_calls, nonce, multisig.threshold).map_err(|e| Error::Execution(e.to_string()))?;
+    proposal
+        .sign(&SigningKey::from_secret_scalar(private_key))
+        .map_err(|e| Error::Execution(e.to_string()))?;
+    store.save(&proposal).map_err(|e| Error::Execution(e.to_string()))?;
+
+    info!(
+        "📝 Gas tank is a multisig, its transfers are proposed to the signers ({}/{} signatures), tx hash: {}",
+        proposal.signatures.len(),
+        multisig.threshold,
+        proposal.transaction_hash.to_fixed_hex_string()
+    );
+    info!("Approve it with the gas-tank-approve command");
+    Ok(())
+}
+
 /// CLI wrapper that uses the core empty paymaster logic
 pub async fn command_empty_paymaster(params: EmptyPaymasterParameters) -> Result<(), Error> {
     info!("Emptying paymaster for profile: {}", params.profile);
@@ -190,8 +242,12 @@ pub async fn command_empty_paymaster(params: EmptyPaymasterParameters) -> Result
         endpoint: rpc_url,
         chain_id: chain_id.clone(),
         fallbacks: vec![],
+        local_estimation: None,
+        endpoint_options: configuration.starknet.endpoint_options.clone(),
+        tokens: configuration.starknet.tokens,
         timeout: configuration.starknet.timeout,
-    });
+    })
+    .map_err(|e| Error::Execution(format!("Failed to create Starknet client: {}", e)))?;
 
     empty_paymaster_core(&starknet, &configuration, params.master_address, params.master_pk, params.force).await?;
 
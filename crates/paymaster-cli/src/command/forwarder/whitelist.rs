@@ -0,0 +1,144 @@
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+use clap::Args;
+use paymaster_service::core::context::configuration::Configuration as ServiceConfiguration;
+use paymaster_starknet::contract::forwarder::Forwarder;
+use paymaster_starknet::{Client, Configuration, StarknetAccountConfiguration};
+use starknet::accounts::ConnectedAccount;
+use starknet::core::types::Felt;
+use tracing::info;
+
+use crate::constants::DEFAULT_MAX_CHECK_STATUS_ATTEMPTS;
+use crate::core::starknet::transaction::status::wait_for_transaction_success;
+use crate::core::Error;
+
+#[derive(Args, Clone)]
+pub struct ForwarderWhitelistCommandParameters {
+    #[clap(long)]
+    pub master_address: Felt,
+
+    #[clap(long)]
+    pub master_pk: Felt,
+
+    #[clap(long)]
+    pub profile: String,
+
+    #[clap(long, value_delimiter = ',', help = "Addresses to whitelist on the forwarder")]
+    pub whitelist: Vec<Felt>,
+
+    #[clap(
+        long,
+        value_delimiter = ',',
+        help = "Addresses to remove from the whitelist. Relayers removed are also removed from the profile"
+    )]
+    pub blacklist: Vec<Felt>,
+
+    #[clap(long, help = "Whitelist the relayers and the estimate account of the profile which are not whitelisted yet")]
+    pub sync: bool,
+
+    #[clap(long, default_value_t = DEFAULT_MAX_CHECK_STATUS_ATTEMPTS)]
+    pub max_check_status_attempts: usize,
+
+    #[clap(short, long, help = "Update the whitelist without user confirmation")]
+    pub force: bool,
+}
+
+pub async fn command_forwarder_whitelist(params: ForwarderWhitelistCommandParameters) -> Result<(), Error> {
+    info!("🛡️ Updating forwarder whitelist for profile: {}", params.profile);
+
+    let mut configuration = ServiceConfiguration::from_file(&params.profile).map_err(|e| Error::Execution(format!("Failed to load profile: {}", e)))?;
+
+    let starknet = Client::new(&Configuration {
+        endpoint: configuration.starknet.endpoint.clone(),
+        chain_id: configuration.starknet.chain_id,
+        fallbacks: vec![],
+        local_estimation: None,
+        endpoint_options: configuration.starknet.endpoint_options.clone(),
+        tokens: configuration.starknet.tokens,
+        timeout: configuration.starknet.timeout,
+    })
+    .map_err(|e| Error::Execution(format!("Failed to create Starknet client: {}", e)))?;
+
+    let forwarder = Forwarder::new(configuration.forwarder);
+
+    let mut whitelist: BTreeSet<Felt> = params.whitelist.iter().copied().collect();
+    if params.sync {
+        let mut accounts = configuration.relayers.fleet_addresses();
+        accounts.push(configuration.estimate_account.address);
+
+        let missing = forwarder
+            .not_whitelisted(&starknet, &accounts)
+            .await
+            .map_err(|e| Error::Execution(format!("Failed to check whitelist: {}", e)))?;
+        whitelist.extend(missing);
+    }
+
+    whitelist.retain(|x| !params.blacklist.contains(x));
+    let whitelist: Vec<Felt> = whitelist.into_iter().collect();
+
+    if whitelist.is_empty() && params.blacklist.is_empty() {
+        info!("✅ Forwarder whitelist is already up to date");
+        return Ok(());
+    }
+
+    info!("Forwarder: {}", configuration.forwarder.to_fixed_hex_string());
+    for address in &whitelist {
+        info!("  + {}", address.to_fixed_hex_string());
+    }
+    for address in &params.blacklist {
+        info!("  - {}", address.to_fixed_hex_string());
+    }
+
+    // Ask user for confirmation before proceeding (unless force flag is used)
+    if !params.force {
+        print!("Do you want to proceed with the update of the whitelist? (y/N): ");
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        io::stdin()
+            .read_line(&mut input)
+            .map_err(|e| Error::Execution(format!("Failed to read user input: {}", e)))?;
+
+        let input = input.trim().to_lowercase();
+        if input != "y" && input != "yes" {
+            info!("Whitelist update cancelled by user.");
+            return Ok(());
+        }
+    }
+
+    let account = starknet.initialize_account(&StarknetAccountConfiguration {
+        address: params.master_address,
+        private_key: params.master_pk,
+    });
+
+    let calls = forwarder.update_whitelist(&whitelist, &params.blacklist);
+    let nonce = account
+        .get_nonce()
+        .await
+        .map_err(|e| Error::Execution(format!("Failed to fetch master nonce: {}", e)))?;
+    let result = calls
+        .execute(&account, nonce)
+        .await
+        .map_err(|e| Error::Execution(format!("Failed to update whitelist: {}", e)))?;
+
+    wait_for_transaction_success(&starknet, result.transaction_hash, params.max_check_status_attempts).await?;
+
+    info!("✅ Forwarder whitelist updated, tx hash: {}", result.transaction_hash.to_fixed_hex_string());
+
+    // Relayers which are no longer whitelisted cannot execute transactions anymore
+    let relayers_count = configuration.relayers.addresses.len();
+    configuration.relayers.addresses.retain(|x| !params.blacklist.contains(x));
+    if configuration.relayers.addresses.len() != relayers_count {
+        configuration
+            .write_to_file(&params.profile)
+            .map_err(|e| Error::Execution(format!("Failed to update profile: {}", e)))?;
+        info!(
+            "📝 Configuration file is updated with {} total relayers, see {}",
+            configuration.relayers.addresses.len(),
+            params.profile
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use clap::Args;
+use paymaster_relayer::multisig::ProposalStore;
+use paymaster_service::core::context::configuration::Configuration as ServiceConfiguration;
+use paymaster_starknet::{Client, Configuration};
+use starknet::core::types::Felt;
+use starknet::signers::SigningKey;
+use tracing::info;
+
+use crate::constants::DEFAULT_MAX_CHECK_STATUS_ATTEMPTS;
+use crate::core::starknet::transaction::status::wait_for_transaction_success;
+use crate::core::Error;
+
+#[derive(Args, Clone)]
+pub struct GasTankApproveCommandParameters {
+    #[clap(long)]
+    pub profile: String,
+
+    #[clap(long, help = "Private key of the signer approving the proposals")]
+    pub signer_pk: Felt,
+
+    #[clap(long, help = "Only approve the proposal with this transaction hash")]
+    pub proposal: Option<Felt>,
+
+    #[clap(long, default_value_t = DEFAULT_MAX_CHECK_STATUS_ATTEMPTS)]
+    pub max_check_status_attempts: usize,
+}
+
+pub async fn command_gas_tank_approve(params: GasTankApproveCommandParameters) -> Result<(), Error> {
+    info!("✍️ Approving gas tank proposals for profile: {}", params.profile);
+
+    let configuration = ServiceConfiguration::from_file(&params.profile).map_err(|e| Error::Execution(format!("Failed to load profile: {}", e)))?;
+    let Some(multisig) = &configuration.gas_tank_multisig else {
+        return Err(Error::Validation("gas tank is not configured as a multisig".to_string()));
+    };
+
+    let starknet = Client::new(&Configuration {
+        endpoint: configuration.starknet.endpoint.clone(),
+        chain_id: configuration.starknet.chain_id,
+        fallbacks: vec![],
+        local_estimation: None,
+        endpoint_options: configuration.starknet.endpoint_options.clone(),
+        tokens: configuration.starknet.tokens,
+        timeout: configuration.starknet.timeout,
+    })
+    .map_err(|e| Error::Execution(format!("Failed to create Starknet client: {}", e)))?;
+
+    let gas_tank = starknet.initialize_account(&configuration.gas_tank);
+    let mut nonce = starknet
+        .fetch_nonce(configuration.gas_tank.address)
+        .await
+        .map_err(|e| Error::Execution(format!("Failed to fetch gas tank nonce: {}", e)))?;
+
+    let store = ProposalStore::new(multisig);
+    let proposals: Vec<_> = store
+        .prune(nonce)
+        .map_err(|e| Error::Execution(e.to_string()))?
+        .into_iter()
+        .filter(|x| params.proposal.is_none_or(|hash| hash == x.transaction_hash))
+        .collect();
+
+    if proposals.is_empty() {
+        info!("ℹ️ No pending proposal to approve");
+        return Ok(());
+    }
+
+    let key = SigningKey::from_secret_scalar(params.signer_pk);
+    for mut proposal in proposals {
+        proposal.sign(&key).map_err(|e| Error::Execution(e.to_string()))?;
+        info!(
+            "Proposal {} signed ({}/{} signatures)",
+            proposal.transaction_hash.to_fixed_hex_string(),
+            proposal.signatures.len(),
+            multisig.threshold
+        );
+
+        // Only the proposal using the current nonce of the gas tank can be executed, the queued ones follow it
+        if !proposal.is_approved(multisig.threshold) || proposal.nonce != nonce {
+            store.save(&proposal).map_err(|e| Error::Execution(e.to_string()))?;
+            continue;
+        }
+
+        let result = proposal
+            .submit(&gas_tank)
+            .await
+            .map_err(|e| Error::Execution(format!("Failed to submit proposal: {}", e)))?;
+        wait_for_transaction_success(&starknet, result.transaction_hash, params.max_check_status_attempts).await?;
+        store
+            .remove(proposal.transaction_hash)
+            .map_err(|e| Error::Execution(e.to_string()))?;
+        nonce += Felt::ONE;
+
+        info!("✅ Proposal executed, tx hash: {}", result.transaction_hash.to_fixed_hex_string());
+    }
+
+    Ok(())
+}
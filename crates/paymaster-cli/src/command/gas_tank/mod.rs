@@ -1 +1,2 @@
+pub mod approve;
 pub mod build;
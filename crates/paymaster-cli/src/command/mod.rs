@@ -1,7 +1,15 @@
+pub mod accounting;
+pub mod approval;
 pub mod balance;
+pub mod config;
+pub mod contracts;
+pub mod dead_letter;
 pub mod empty;
 pub mod forwarder;
 pub mod gas_tank;
+pub mod openrpc;
+pub mod pricing;
 pub mod quick_setup;
+pub mod refund;
 pub mod relayer;
 pub mod setup;
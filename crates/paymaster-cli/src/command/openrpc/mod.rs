@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use log::info;
+
+use crate::core::Error;
+
+#[derive(Args, Clone)]
+pub struct OpenRpcCommandParameters {
+    #[clap(long, help = "File in which the specification is written, printed on the standard output when omitted")]
+    pub output: Option<PathBuf>,
+}
+
+pub async fn command_openrpc(params: OpenRpcCommandParameters) -> Result<(), Error> {
+    let specification =
+        serde_json::to_string_pretty(&paymaster_rpc::openrpc::specification()).map_err(|e| Error::Execution(format!("Failed to serialize the specification: {}", e)))?;
+
+    match params.output {
+        Some(path) => {
+            std::fs::write(&path, specification).map_err(|e| Error::Execution(format!("Failed to write {}: {}", path.display(), e)))?;
+            info!("📄 OpenRPC specification written to {}", path.display());
+        },
+        None => println!("{}", specification),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use clap::Args;
+use log::info;
+use paymaster_rpc::client::Client;
+use paymaster_rpc::{PricingOutcome, SimulatePricingRequest};
+use paymaster_starknet::math::denormalize_felt;
+
+use crate::core::Error;
+
+#[derive(Args, Clone)]
+pub struct SimulatePricingCommandParameters {
+    #[clap(long, help = "Endpoint of the running paymaster")]
+    pub endpoint: String,
+
+    #[clap(long, help = "Admin api key of the running paymaster")]
+    pub api_key: String,
+
+    #[clap(long, help = "Proposed max fee multiplier")]
+    pub max_fee_multiplier: f32,
+
+    #[clap(long, help = "Proposed provider fee overhead")]
+    pub provider_fee_overhead: f32,
+
+    #[clap(long, help = "Number of recent transactions to replay")]
+    pub limit: Option<usize>,
+}
+
+pub async fn command_simulate_pricing(params: SimulatePricingCommandParameters) -> Result<(), Error> {
+    info!("🧮 Simulating pricing on the recent transactions of {}", params.endpoint);
+
+    let client = Client::builder(&params.endpoint)
+        .with_api_key(params.api_key.clone())
+        .build()
+        .map_err(|e| Error::Execution(format!("Failed to create client: {}", e)))?;
+    let report = client
+        .simulate_pricing(SimulatePricingRequest {
+            max_fee_multiplier: params.max_fee_multiplier,
+            provider_fee_overhead: params.provider_fee_overhead,
+            limit: params.limit,
+            chain_id: None,
+        })
+        .await
+        .map_err(|e| Error::Execution(format!("Failed to simulate pricing: {}", e)))?;
+
+    if report.transactions == 0 {
+        info!("No confirmed transaction to replay");
+        return Ok(());
+    }
+
+    info!(
+        "{} transactions replayed, {} STRK paid by the relayers",
+        report.transactions,
+        denormalize_felt(report.paid_in_strk, 18)
+    );
+
+    println!("\n{}", "_".repeat(89));
+    println!(
+        "| {:^10} | {:^10} | {:^10} | {:^16} | {:^16} | {:^10} |",
+        "", "Multiplier", "Overhead", "Charged (STRK)", "Users (STRK)", "Uncovered"
+    );
+    println!(
+        "|{}|{}|{}|{}|{}|{}|",
+        "-".repeat(12),
+        "-".repeat(12),
+        "-".repeat(12),
+        "-".repeat(18),
+        "-".repeat(18),
+        "-".repeat(12)
+    );
+    print_outcome("Current", &report.current);
+    print_outcome("Proposed", &report.proposed);
+    println!("{}", "_".repeat(89));
+
+    println!("\nRevenue delta: {:+} STRK", report.revenue_delta as f64 / 1e18);
+    println!("User cost delta: {:+} STRK", report.user_cost_delta as f64 / 1e18);
+
+    Ok(())
+}
+
+fn print_outcome(name: &str, outcome: &PricingOutcome) {
+    println!(
+        "| {:<10} | {:<10} | {:<10} | {:<16} | {:<16} | {:<10} |",
+        name,
+        outcome.max_fee_multiplier,
+        outcome.provider_fee_overhead,
+        denormalize_felt(outcome.charged_in_strk, 18),
+        denormalize_felt(outcome.user_cost_in_strk, 18),
+        outcome.uncovered_transactions
+    );
+}
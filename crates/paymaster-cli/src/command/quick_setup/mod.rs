@@ -39,6 +39,7 @@ pub async fn command_quick_setup(params: QuickSetupParameters) -> Result<(), Err
     let setup_params = SetupParameters {
         rpc_url: params.rpc_url,
         rpc_timeout: DEFAULT_STARKNET_TIMEOUT,
+        rpc_headers: vec![],
         rpc_port: DEFAULT_RPC_PORT,
         chain_id: params.chain_id,
         master_address: params.master_address,
@@ -0,0 +1,79 @@
+use clap::Args;
+use log::info;
+use paymaster_rpc::client::Client;
+use paymaster_rpc::RefundsRequest;
+use paymaster_starknet::math::denormalize_felt;
+use starknet::core::types::Felt;
+
+use crate::core::Error;
+
+#[derive(Args, Clone)]
+pub struct RefundsCommandParameters {
+    #[clap(long, help = "Endpoint of the running paymaster")]
+    pub endpoint: String,
+
+    #[clap(long, help = "Only report the refunds of this user")]
+    pub user: Option<Felt>,
+
+    #[clap(long, default_value = "false", help = "Also report the refunds already sent")]
+    pub all: bool,
+}
+
+pub async fn command_refunds(params: RefundsCommandParameters) -> Result<(), Error> {
+    info!("💸 Fetching refunds from {}", params.endpoint);
+
+    let client = Client::new(&params.endpoint);
+    let report = client
+        .get_refunds(RefundsRequest {
+            user_address: params.user,
+            chain_id: None,
+        })
+        .await
+        .map_err(|e| Error::Execution(format!("Failed to fetch refunds: {}", e)))?;
+
+    if !report.enabled {
+        info!("Refunds are not enabled on this paymaster");
+        return Ok(());
+    }
+
+    info!(
+        "{} outstanding refunds for a total of {} STRK",
+        report.outstanding,
+        denormalize_felt(report.outstanding_in_strk, 18)
+    );
+
+    println!("\n{}", "_".repeat(102));
+    println!(
+        "| {:^20} | {:^20} | {:^20} | {:^16} | {:^12} |",
+        "User", "Transaction", "Token", "Amount (STRK)", "Status"
+    );
+    println!("|{}|{}|{}|{}|{}|", "-".repeat(22), "-".repeat(22), "-".repeat(22), "-".repeat(18), "-".repeat(14));
+
+    for refund in report.refunds.iter().filter(|x| params.all || !x.confirmed) {
+        let status = match (refund.refund_transaction_hash, refund.confirmed) {
+            (_, true) => "refunded",
+            (Some(_), false) => "submitted",
+            (None, false) => "outstanding",
+        };
+        println!(
+            "| {:<20} | {:<20} | {:<20} | {:<16} | {:<12} |",
+            crop(refund.user_address),
+            crop(refund.transaction_hash),
+            crop(refund.token_address),
+            denormalize_felt(refund.amount_in_strk, 18),
+            status
+        );
+    }
+    println!("{}", "_".repeat(102));
+
+    Ok(())
+}
+
+fn crop(value: Felt) -> String {
+    let value = format!("{:x}", value);
+    if value.len() > 8 {
+        format!("0x{}...{}", &value[..4], &value[value.len() - 4..])
+    } else {
+        format!("0x{}", value)
+    }
+}
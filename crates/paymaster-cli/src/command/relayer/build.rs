@@ -1,14 +1,16 @@
-use paymaster_starknet::constants::Token;
-use paymaster_starknet::transaction::{CalldataBuilder, Calls};
-use paymaster_starknet::{Client, ContractAddress};
+use paymaster_starknet::contract::forwarder::Forwarder;
+use paymaster_starknet::transaction::Calls;
+use paymaster_starknet::{Client, ContractAddress, Error as StarknetError};
+use starknet::core::crypto::pedersen_hash;
 use starknet::core::types::Felt;
-use starknet::macros::selector;
 
 use crate::core::starknet::transaction::deploy::DeployArgentAccount;
-use crate::core::starknet::transaction::invoke::InvokeTransaction;
 use crate::core::starknet::transaction::transfer::Transfer;
 use crate::core::Error;
 
+/// Number of consecutive undeployed relayers after which the scan of the deterministic addresses stops
+pub const RECOVERY_GAP_LIMIT: usize = 20;
+
 pub struct RelayerDeployment {
     pub addresses: Vec<ContractAddress>,
     pub calls: Calls,
@@ -19,28 +21,40 @@ pub struct SingleRelayerDeployment {
     pub calls: Calls,
 }
 
+/// Relayer found at a deterministic address
+pub struct DeployedRelayer {
+    pub index: usize,
+    pub address: ContractAddress,
+}
+
 impl RelayerDeployment {
-    pub async fn build_one(starknet: &Client, forwarder: Felt, private_key: Felt, fund: Felt) -> Result<SingleRelayerDeployment, Error> {
-        let deploy_relayer = DeployArgentAccount::initialize(starknet, private_key).await;
-
-        let whitelist = InvokeTransaction {
-            to: forwarder,
-            selector: selector!("set_whitelisted_address"),
-            calldata: CalldataBuilder::new()
-                .encode(&deploy_relayer.address)
-                .encode(&Felt::ONE)
-                .build(),
-        };
+    /// Salt of the relayer at `index`. Relayers deployed with the same seed and private key always
+    /// get the same addresses, which allows to recover them from the seed alone.
+    pub fn salt(seed: Felt, index: usize) -> Felt {
+        pedersen_hash(&seed, &Felt::from(index))
+    }
+
+    /// Returns the address of the relayer at `index`
+    pub async fn address(starknet: &Client, private_key: Felt, seed: Felt, index: usize) -> Result<ContractAddress, Error> {
+        Ok(DeployArgentAccount::initialize_with_salt(starknet, private_key, Self::salt(seed, index))
+            .await?
+            .address)
+    }
+
+    pub async fn build_one(starknet: &Client, forwarder: Felt, private_key: Felt, salt: Felt, fund: Felt) -> Result<SingleRelayerDeployment, Error> {
+        let deploy_relayer = DeployArgentAccount::initialize_with_salt(starknet, private_key, salt).await?;
+
+        let whitelist = Forwarder::new(forwarder).set_whitelisted_address(deploy_relayer.address, true);
 
         let fund_transfer = Transfer {
             recipient: deploy_relayer.address,
-            token: Token::STRK_ADDRESS,
+            token: starknet.tokens().strk,
             amount: fund,
         };
 
         let mut calls = Calls::empty();
         calls.push(deploy_relayer.as_call());
-        calls.push(whitelist.as_call());
+        calls.push(whitelist);
         if fund != Felt::ZERO {
             calls.push(fund_transfer.as_call());
         }
@@ -51,10 +65,11 @@ impl RelayerDeployment {
         })
     }
 
-    pub async fn build_many(starknet: &Client, forwarder: Felt, private_key: Felt, count: usize, fund: Felt) -> Result<Self, Error> {
+    /// Build the deployment of the relayers at the given `indexes`
+    pub async fn build_many(starknet: &Client, forwarder: Felt, private_key: Felt, seed: Felt, indexes: &[usize], fund: Felt) -> Result<Self, Error> {
         let mut deployment = vec![];
-        for _ in 0..count {
-            deployment.push(RelayerDeployment::build_one(&starknet, forwarder, private_key, fund).await?);
+        for index in indexes {
+            deployment.push(RelayerDeployment::build_one(starknet, forwarder, private_key, Self::salt(seed, *index), fund).await?);
         }
 
         let calls = deployment.iter().fold(Calls::empty(), |mut calls, x| {
@@ -65,4 +80,37 @@ impl RelayerDeployment {
         let addresses = deployment.into_iter().map(|x| x.address).collect();
         Ok(Self { addresses, calls })
     }
+
+    /// Scan the deterministic addresses and return the relayers already deployed. The scan stops after
+    /// [`RECOVERY_GAP_LIMIT`] consecutive addresses without contract.
+    pub async fn scan(starknet: &Client, private_key: Felt, seed: Felt) -> Result<Vec<DeployedRelayer>, Error> {
+        let mut deployed = vec![];
+        let mut gap = 0;
+        let mut index = 0;
+        while gap < RECOVERY_GAP_LIMIT {
+            let address = Self::address(starknet, private_key, seed, index).await?;
+            match starknet.fetch_class_hash_at(address).await {
+                Ok(_) => {
+                    deployed.push(DeployedRelayer { index, address });
+                    gap = 0;
+                },
+                Err(StarknetError::ContractNotFound) => gap += 1,
+                Err(e) => return Err(Error::Execution(format!("could not check relayer {}: {}", address.to_fixed_hex_string(), e))),
+            }
+            index += 1;
+        }
+
+        Ok(deployed)
+    }
+
+    /// Returns the `count` lowest indexes whose relayer is not deployed yet
+    pub async fn free_indexes(starknet: &Client, private_key: Felt, seed: Felt, count: usize) -> Result<Vec<usize>, Error> {
+        let deployed: Vec<usize> = Self::scan(starknet, private_key, seed)
+            .await?
+            .into_iter()
+            .map(|x| x.index)
+            .collect();
+
+        Ok((0..).filter(|x| !deployed.contains(x)).take(count).collect())
+    }
 }
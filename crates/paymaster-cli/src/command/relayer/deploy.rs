@@ -2,7 +2,6 @@ use std::io::{self, Write};
 
 use clap::Args;
 use paymaster_service::core::context::configuration::Configuration as ServiceConfiguration;
-use paymaster_starknet::constants::Token;
 use paymaster_starknet::math::{denormalize_felt, normalize_felt};
 use paymaster_starknet::transaction::Calls;
 use paymaster_starknet::{Client, Configuration, StarknetAccountConfiguration};
@@ -28,6 +27,9 @@ pub struct RelayersDeployCommandParameters {
     #[clap(long)]
     pub num_relayers: usize,
 
+    #[clap(long, help = "Seed the relayer addresses are derived from, defaults to the relayers private key")]
+    pub seed: Option<Felt>,
+
     #[clap(long, default_value_t = 0.0)]
     pub fund: f64,
 
@@ -64,8 +66,12 @@ pub async fn command_relayers_deploy(params: RelayersDeployCommandParameters) ->
         endpoint: rpc_url.clone(),
         chain_id,
         fallbacks: vec![],
+        local_estimation: None,
+        endpoint_options: configuration.starknet.endpoint_options.clone(),
+        tokens: configuration.starknet.tokens,
         timeout: configuration.starknet.timeout,
-    });
+    })
+    .map_err(|e| Error::Execution(format!("Failed to create Starknet client: {}", e)))?;
 
     // Assert the balance of master is greater than the amount of STRK needed for the deployment
     // If not, stop the deployment execution
@@ -101,18 +107,24 @@ pub async fn command_relayers_deploy(params: RelayersDeployCommandParameters) ->
         private_key: params.master_pk,
     });
 
+    // Relayers are deployed at the first deterministic addresses which are still free
+    let seed = params.seed.unwrap_or(configuration.relayers.private_key);
+    let indexes = RelayerDeployment::free_indexes(&starknet, configuration.relayers.private_key, seed, num_relayers).await?;
+    info!("Deploying relayers at indexes {:?}", indexes);
+
     let relayers_deployment = RelayerDeployment::build_many(
         &starknet,
         configuration.forwarder,
         configuration.relayers.private_key,
-        num_relayers,
+        seed,
+        &indexes,
         Felt::ZERO, // We don't fund the relayers with STRK, we load the gas tank instead
     )
     .await?;
 
     let fund_gas_tank_transfer = Transfer {
         recipient: configuration.gas_tank.address,
-        token: Token::STRK_ADDRESS,
+        token: starknet.tokens().strk,
         amount: fund_gas_tank_in_fri,
     };
 
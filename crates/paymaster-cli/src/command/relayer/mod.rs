@@ -1,3 +1,4 @@
 pub mod build;
 pub mod deploy;
 pub mod rebalance;
+pub mod recover;
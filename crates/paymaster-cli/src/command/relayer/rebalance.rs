@@ -6,10 +6,9 @@ use log::{error, info};
 use paymaster_common::service::Service;
 use paymaster_relayer::{Context, RelayerManagerConfiguration, RelayerRebalancingService};
 use paymaster_service::core::context::configuration::Configuration as ServiceConfiguration;
-use paymaster_starknet::constants::Token;
 use paymaster_starknet::math::{denormalize_felt, normalize_felt};
 use paymaster_starknet::transaction::{Calls, TimeBounds};
-use paymaster_starknet::{Client, Configuration};
+use paymaster_starknet::Client;
 use starknet::accounts::{Account, ConnectedAccount};
 use starknet::core::types::Felt;
 
@@ -51,12 +50,7 @@ pub async fn command_relayers_rebalance(params: RelayersRebalanceCommandParamete
     info!("Using RPC URL: {}", rpc_url);
     info!("Profile path: {}", params.profile);
 
-    let starknet = Client::new(&Configuration {
-        endpoint: rpc_url,
-        chain_id: chain_id.clone(),
-        fallbacks: vec![],
-        timeout: configuration.starknet.timeout,
-    });
+    let starknet = Client::new(&configuration.starknet).map_err(|e| Error::Execution(format!("Failed to create Starknet client: {}", e)))?;
 
     // How much STRK to refund the gas tank with from the master account
     let additional_strk_balance = normalize_felt(params.fund, 18);
@@ -71,13 +65,17 @@ pub async fn command_relayers_rebalance(params: RelayersRebalanceCommandParamete
     let gas_tank_private_key = configuration.gas_tank.private_key;
 
     // Initialize the rebalancing service
-    let rebalancing_service = RelayerRebalancingService::new(Context::new(RelayerManagerConfiguration {
-        starknet: configuration.starknet.clone(),
-        gas_tank: configuration.gas_tank.clone(),
-        relayers: configuration.relayers.clone(),
-        supported_tokens: configuration.supported_tokens.clone(),
-        price: configuration.clone().into(),
-    }))
+    let rebalancing_service = RelayerRebalancingService::new(
+        Context::new(RelayerManagerConfiguration {
+            starknet: configuration.starknet.clone(),
+            gas_tank: configuration.gas_tank.clone(),
+            gas_tank_multisig: configuration.gas_tank_multisig.clone(),
+            relayers: configuration.relayers.clone(),
+            supported_tokens: configuration.supported_tokens.clone(),
+            price: configuration.clone().into(),
+        })
+        .map_err(|e| Error::Execution(format!("Failed to initialize relayers: {}", e)))?,
+    )
     .await;
 
     // If swap is enabled, swap the supported tokens balance to STRK (in gas tank)
@@ -125,7 +123,7 @@ pub async fn command_relayers_rebalance(params: RelayersRebalanceCommandParamete
 
         refund_call = Some(
             Transfer {
-                token: Token::STRK_ADDRESS,
+                token: starknet.tokens().strk,
                 recipient: gas_tank.address(),
                 amount: additional_strk_balance,
             }
@@ -144,12 +142,14 @@ pub async fn command_relayers_rebalance(params: RelayersRebalanceCommandParamete
     }
 
     // Create the execute_from_outside call to call rebalancing on the gas tank account
-    let execute_from_outside_call = refilling_calls_from_gas_tank.as_execute_from_outside_call(
-        params.master_address,
-        gas_tank,
-        gas_tank_private_key,
-        TimeBounds::valid_for(Duration::from_secs(3600)),
-    );
+    let execute_from_outside_call = refilling_calls_from_gas_tank
+        .as_execute_from_outside_call(
+            params.master_address,
+            gas_tank,
+            gas_tank_private_key,
+            TimeBounds::valid_for(Duration::from_secs(3600)),
+        )
+        .map_err(|e| Error::Execution(format!("Failed to sign outside execution: {}", e)))?;
 
     // Create the final transaction that calls execute_from_outside on the gas tank (+ refund first if necessary)
     let multi_calls = if let Some(refund_call) = refund_call {
@@ -0,0 +1,73 @@
+use clap::Args;
+use paymaster_service::core::context::configuration::Configuration as ServiceConfiguration;
+use paymaster_starknet::{Client, Configuration};
+use starknet::core::types::Felt;
+use tracing::info;
+
+use crate::command::relayer::build::{RelayerDeployment, RECOVERY_GAP_LIMIT};
+use crate::core::Error;
+
+#[derive(Args, Clone)]
+pub struct RelayersRecoverCommandParameters {
+    #[clap(long)]
+    pub profile: String,
+
+    #[clap(long, help = "Seed the relayer addresses were derived from, defaults to the relayers private key")]
+    pub seed: Option<Felt>,
+}
+
+pub async fn command_relayers_recover(params: RelayersRecoverCommandParameters) -> Result<(), Error> {
+    info!("🔎 Starting relayers recovery for profile: {}", params.profile);
+
+    // Load the configuration
+    let mut configuration = ServiceConfiguration::from_file(&params.profile).map_err(|e| Error::Execution(e.to_string()))?;
+    let chain_id = configuration.starknet.chain_id;
+
+    info!("Using chain-id: {}", chain_id.as_identifier());
+    info!("Using RPC URL: {}", configuration.starknet.endpoint);
+
+    let starknet = Client::new(&Configuration {
+        endpoint: configuration.starknet.endpoint.clone(),
+        chain_id,
+        fallbacks: vec![],
+        local_estimation: None,
+        endpoint_options: configuration.starknet.endpoint_options.clone(),
+        tokens: configuration.starknet.tokens,
+        timeout: configuration.starknet.timeout,
+    })
+    .map_err(|e| Error::Execution(format!("Failed to create Starknet client: {}", e)))?;
+
+    // Scan the deterministic addresses until enough consecutive addresses are free
+    let seed = params.seed.unwrap_or(configuration.relayers.private_key);
+    info!("Scanning relayer addresses (stopping after {} consecutive free addresses)...", RECOVERY_GAP_LIMIT);
+    let deployed = RelayerDeployment::scan(&starknet, configuration.relayers.private_key, seed).await?;
+
+    let mut recovered = 0;
+    for relayer in deployed {
+        if configuration.relayers.addresses.contains(&relayer.address) {
+            continue;
+        }
+
+        info!("Recovered relayer #{} at {}", relayer.index, relayer.address.to_fixed_hex_string());
+        configuration.relayers.addresses.push(relayer.address);
+        recovered += 1;
+    }
+
+    if recovered == 0 {
+        info!("✅ No relayer to recover, the configuration is up to date");
+        return Ok(());
+    }
+
+    configuration
+        .write_to_file(&params.profile)
+        .map_err(|e| Error::Execution(e.to_string()))?;
+
+    info!(
+        "📝 {} relayers recovered, configuration file is updated with {} total relayers, see {}",
+        recovered,
+        configuration.relayers.addresses.len(),
+        params.profile
+    );
+
+    Ok(())
+}
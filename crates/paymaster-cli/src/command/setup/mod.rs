@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::io::{self, Write};
 use std::str::FromStr;
 use std::time::Duration;
@@ -12,6 +12,7 @@ use crate::constants::{
     DEFAULT_RELAYERS_LOCK_MODE, DEFAULT_RELAYERS_NUM, DEFAULT_RELAYERS_REBALANCE_TRIGGER_AMOUNT, DEFAULT_RPC_PORT, DEFAULT_SPONSORING_MODE, DEFAULT_STARKNET_TIMEOUT,
     DEFAULT_SWAP_INTERVAL, DEFAULT_SWAP_SLIPPAGE, DEFAULT_VERBOSITY,
 };
+use crate::contracts::ContractArtifacts;
 use crate::core::starknet::transaction::status::wait_for_transaction_success;
 use crate::core::Error;
 use crate::validation::{assert_rebalancing_configuration, assert_strk_balance};
@@ -22,13 +23,14 @@ use paymaster_relayer::rebalancing::{OptionalRebalancingConfiguration, Rebalanci
 use paymaster_relayer::swap::client::SwapClientConfiguration;
 use paymaster_relayer::swap::{SwapClientConfigurator, SwapConfiguration};
 use paymaster_relayer::{Context as RelayerContext, RelayerManagerConfiguration, RelayerRebalancingService, RelayersConfiguration};
-use paymaster_rpc::RPCConfiguration;
+use paymaster_rpc::{HooksConfiguration, RPCConfiguration};
 use paymaster_service::core::context::configuration::{Configuration as ServiceConfiguration, PriceConfiguration, PriceOracleConfiguration, VerbosityConfiguration};
 use paymaster_starknet::constants::Token;
 use paymaster_starknet::math::{denormalize_felt, normalize_felt};
 use paymaster_starknet::transaction::{Calls, TimeBounds};
 use paymaster_starknet::{
-    ChainID, Client, Configuration as StarknetConfiguration, Configuration, StarknetAccountConfiguration, DEFAULT_MAINNET_RPC_ENDPOINT, DEFAULT_SEPOLIA_RPC_ENDPOINT,
+    ChainID, Client, Configuration as StarknetConfiguration, Configuration, EndpointOptions, StarknetAccountConfiguration, DEFAULT_MAINNET_RPC_ENDPOINT,
+    DEFAULT_SEPOLIA_RPC_ENDPOINT,
 };
 use starknet::accounts::ConnectedAccount;
 use starknet::core::types::{Call, Felt};
@@ -43,6 +45,9 @@ pub struct SetupParameters {
     #[clap(long, default_value_t = DEFAULT_STARKNET_TIMEOUT)]
     pub rpc_timeout: u64,
 
+    #[clap(long = "rpc-header", value_parser = parse_header, help = "Header sent to the RPC endpoint, as NAME:VALUE. Can be repeated")]
+    pub rpc_headers: Vec<(String, String)>,
+
     #[clap(long, default_value_t = DEFAULT_RPC_PORT)]
     pub rpc_port: u64,
 
@@ -104,6 +109,13 @@ pub struct SetupParameters {
     pub force: bool,
 }
 
+fn parse_header(value: &str) -> Result<(String, String), String> {
+    match value.split_once(':') {
+        Some((name, value)) => Ok((name.trim().to_string(), value.trim().to_string())),
+        None => Err(format!("invalid header {}, expected NAME:VALUE", value)),
+    }
+}
+
 // Generate a random private key, from the starknet library
 fn generate_private_key() -> Felt {
     SigningKey::from_random().secret_scalar()
@@ -129,6 +141,17 @@ pub async fn deploy_paymaster_core(params: SetupParameters, skip_user_confirmati
             )))
         },
     };
+    // Options of the RPC endpoint, kept in the profile so that the service reaches it the same way
+    let endpoint_options = match params.rpc_headers.is_empty() {
+        true => HashMap::new(),
+        false => HashMap::from([(
+            rpc_url.clone(),
+            EndpointOptions {
+                headers: params.rpc_headers.iter().cloned().collect(),
+                ..Default::default()
+            },
+        )]),
+    };
     let gas_tank_fund_in_fri = normalize_felt(params.fund, 18);
     let estimate_account_fund_in_fri = normalize_felt(params.estimate_account_fund, 18);
     let num_relayers = params.num_relayers;
@@ -167,8 +190,12 @@ pub async fn deploy_paymaster_core(params: SetupParameters, skip_user_confirmati
         endpoint: rpc_url.clone(),
         chain_id,
         fallbacks: vec![],
+        local_estimation: None,
+        endpoint_options: endpoint_options.clone(),
+        tokens: None,
         timeout: 10,
-    });
+    })
+    .map_err(|e| Error::Execution(format!("Failed to create Starknet client: {}", e)))?;
 
     // Check that the initial funding is enough for rebalancing to work properly
     assert_rebalancing_configuration(
@@ -179,6 +206,9 @@ pub async fn deploy_paymaster_core(params: SetupParameters, skip_user_confirmati
     )
     .await?;
 
+    // The forwarder and the accounts are deployed from classes which must already be declared
+    ContractArtifacts::pinned(&chain_id).ensure_declared(&starknet).await?;
+
     // Assert the balance of master is greater than the amount of STRK needed for the deployment (Relayers + Estimate Account)
     // If not, stop the setup execution
     assert_strk_balance(&starknet, params.master_address, total_funding_amount)
@@ -226,13 +256,30 @@ pub async fn deploy_paymaster_core(params: SetupParameters, skip_user_confirmati
 
     // Get Estimate Account deployment calls (represented as an account relayer -> must be whitelisted by the forwarder)
     // Always fund the estimate account with the default amount of STRK
-    let estimate_account_deployment = RelayerDeployment::build_one(&starknet, forwarder_deployment.address, estimate_account_pk, estimate_account_fund_in_fri).await?;
+    let estimate_account_deployment = RelayerDeployment::build_one(
+        &starknet,
+        forwarder_deployment.address,
+        estimate_account_pk,
+        RelayerDeployment::salt(estimate_account_pk, 0),
+        estimate_account_fund_in_fri,
+    )
+    .await?;
     // We only deployed 1 estimate account (with a relayer behaviour)
     let estimate_account_address = estimate_account_deployment.address;
 
     // Get all relayers deployment calls
-    // We don't fund the relayers with STRK, we load the gas tank instead
-    let relayers_deployment = RelayerDeployment::build_many(&starknet, forwarder_deployment.address, shared_relayers_pk, num_relayers, Felt::ZERO).await?;
+    // We don't fund the relayers with STRK, we load the gas tank instead. Their addresses are derived from
+    // their private key so that they can be recovered with the `relayers-recover` command
+    let relayer_indexes: Vec<usize> = (0..num_relayers).collect();
+    let relayers_deployment = RelayerDeployment::build_many(
+        &starknet,
+        forwarder_deployment.address,
+        shared_relayers_pk,
+        shared_relayers_pk,
+        &relayer_indexes,
+        Felt::ZERO,
+    )
+    .await?;
 
     // Update configuration with new values
     let configuration = ServiceConfiguration {
@@ -241,14 +288,40 @@ pub async fn deploy_paymaster_core(params: SetupParameters, skip_user_confirmati
             endpoint: rpc_url.clone(),
             chain_id,
             fallbacks: vec![],
+            local_estimation: None,
+            endpoint_options,
+            tokens: None,
             timeout: params.rpc_timeout,
         },
-        rpc: RPCConfiguration { port: params.rpc_port },
+        rpc: RPCConfiguration {
+            port: params.rpc_port,
+            maintenance: Default::default(),
+            debug_diagnostics: false,
+            dead_letter: None,
+            approval_queue: None,
+            build_cache: None,
+            trace_sampling: None,
+            replay_protection: None,
+            call_policy: Default::default(),
+            deprecated_methods: vec![],
+            response_signing_key: None,
+        },
         prometheus: None,
+        logging: Default::default(),
+        egress: Default::default(),
         max_fee_multiplier: params.max_fee_multiplier,
         provider_fee_overhead: params.fee_overhead,
+        fee_rounding: Default::default(),
         supported_tokens,
+        token_registry: None,
+        token_metadata: Default::default(),
+        permit_tokens: HashSet::new(),
+        direct_fee_payment: false,
+        profitability: None,
+        quote_ttl: None,
+        low_rpc: Default::default(),
         forwarder: forwarder_deployment.address,
+        forwarder_whitelist: None,
         estimate_account: StarknetAccountConfiguration {
             address: estimate_account_address,
             private_key: estimate_account_pk,
@@ -257,20 +330,38 @@ pub async fn deploy_paymaster_core(params: SetupParameters, skip_user_confirmati
             address: gas_tank_tx.address,
             private_key: gas_tank_pk,
         },
+        gas_tank_multisig: None,
+        fee_recipients: Default::default(),
         relayers: RelayersConfiguration {
             private_key: shared_relayers_pk,
             addresses: relayers_deployment.addresses,
             min_relayer_balance: Felt::from(normalize_felt(params.min_relayer_balance, 18)),
             lock: DEFAULT_RELAYERS_LOCK_MODE,
+            max_in_flight_transactions: RelayersConfiguration::default_max_in_flight_transactions(),
+            execution_concurrency_factor: RelayersConfiguration::default_execution_concurrency_factor(),
+            spend_caps: Default::default(),
+            secondary: None,
+            gas_tank_top_up: None,
+            staking: None,
+            journal: None,
+            watchdog: None,
+            dedicated: vec![],
+            deployment_relayers: vec![],
+            treasury_history: None,
+            messaging: None,
             rebalancing: OptionalRebalancingConfiguration::initialize(Some(RebalancingConfiguration {
                 check_interval: params.rebalancing_check_interval,
                 trigger_balance: Felt::from(normalize_felt(params.rebalancing_trigger_balance, 18)),
+                strategy: Default::default(),
+                maintenance_windows: vec![],
+                treasury_policy: None,
                 swap_config: SwapConfiguration {
                     slippage: params.swap_slippage,
                     swap_client_config: SwapClientConfigurator::AVNU(SwapClientConfiguration::default_from_chain(chain_id)),
                     max_price_impact: params.max_price_impact,
                     swap_interval: params.swap_interval,
                     min_usd_sell_amount: params.min_swap_sell_amount,
+                    tranche_price_impact: None,
                 },
             })),
         },
@@ -287,6 +378,14 @@ pub async fn deploy_paymaster_core(params: SetupParameters, skip_user_confirmati
             .collect(),
         }),
         sponsoring: DEFAULT_SPONSORING_MODE,
+        probe: Default::default(),
+        refund: None,
+        reorg: None,
+        hooks: HooksConfiguration::default(),
+        analytics: None,
+        cost_attribution: None,
+        callbacks: Default::default(),
+        chains: vec![],
     };
 
     // Perform rebalancing
@@ -327,13 +426,14 @@ async fn perform_rebalancing(starknet: &Client, configuration: &ServiceConfigura
     let relayer_manager_config = RelayerManagerConfiguration {
         starknet: configuration.starknet.clone(),
         gas_tank: configuration.gas_tank.clone(),
+        gas_tank_multisig: configuration.gas_tank_multisig.clone(),
         relayers: configuration.relayers.clone(),
         supported_tokens: configuration.supported_tokens.clone(),
         price: configuration.clone().into(),
     };
 
     // Create a relayer context and rebalancing service
-    let relayer_context = RelayerContext::new(relayer_manager_config);
+    let relayer_context = RelayerContext::new(relayer_manager_config).map_err(|e| Error::Execution(format!("Failed to initialize relayers: {}", e)))?;
     let rebalancing_service = RelayerRebalancingService::new(relayer_context.clone()).await;
 
     // Perform initial rebalancing to distribute funds to relayers
@@ -347,12 +447,14 @@ async fn perform_rebalancing(starknet: &Client, configuration: &ServiceConfigura
                 private_key: configuration.gas_tank.private_key,
             });
 
-            return Ok(rebalancing_calls.as_execute_from_outside_call(
-                master_address,
-                gas_tank_account,
-                configuration.gas_tank.private_key,
-                TimeBounds::valid_for(Duration::from_secs(3600)),
-            ));
+            return rebalancing_calls
+                .as_execute_from_outside_call(
+                    master_address,
+                    gas_tank_account,
+                    configuration.gas_tank.private_key,
+                    TimeBounds::valid_for(Duration::from_secs(3600)),
+                )
+                .map_err(|e| Error::Execution(format!("Failed to sign outside execution: {}", e)));
         }
     }
     Err(Error::Execution(
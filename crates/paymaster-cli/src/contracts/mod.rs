@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
+use paymaster_starknet::constants::ClassHash;
+use paymaster_starknet::{ChainID, Client, Error as StarknetError, StarknetAccount};
+use serde::{Deserialize, Serialize};
+use starknet::accounts::Account;
+use starknet::core::types::contract::{CompiledClass, SierraClass};
+use starknet::core::types::Felt;
+use tracing::info;
+
+use crate::core::starknet::transaction::status::wait_for_transaction_success;
+use crate::core::Error;
+
+/// Contract class the paymaster requires to be declared on the chain it runs on, pinned to its class hash
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContractArtifact {
+    pub class_hash: Felt,
+
+    /// Path of the Sierra class (`*.contract_class.json`), needed to verify or declare the class
+    #[serde(default)]
+    pub sierra: Option<String>,
+
+    /// Path of the compiled class (`*.compiled_contract_class.json`), needed to declare the class
+    #[serde(default)]
+    pub casm: Option<String>,
+}
+
+impl ContractArtifact {
+    pub fn pinned(class_hash: Felt) -> Self {
+        Self {
+            class_hash,
+            sierra: None,
+            casm: None,
+        }
+    }
+
+    /// Check that the Sierra class of the artifact, if any, hashes to the pinned class hash
+    pub fn verify(&self) -> Result<(), Error> {
+        let Some(sierra) = self.load_sierra()? else {
+            return Ok(());
+        };
+
+        let class_hash = sierra
+            .class_hash()
+            .map_err(|e| Error::Validation(format!("Failed to compute class hash: {}", e)))?;
+        if class_hash != self.class_hash {
+            return Err(Error::Validation(format!(
+                "Class hash mismatch, pinned {} but artifact hashes to {}",
+                self.class_hash.to_fixed_hex_string(),
+                class_hash.to_fixed_hex_string()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the class is declared on chain
+    pub async fn is_declared(&self, starknet: &Client) -> Result<bool, Error> {
+        match starknet.fetch_class(self.class_hash).await {
+            Ok(_) => Ok(true),
+            Err(StarknetError::ClassNotFound) => Ok(false),
+            Err(e) => Err(Error::Execution(format!("Failed to fetch class {}: {}", self.class_hash.to_fixed_hex_string(), e))),
+        }
+    }
+
+    /// Declare the class using the given account. Both the Sierra and the compiled class of the artifact are required.
+    pub async fn declare(&self, starknet: &Client, account: &StarknetAccount, max_check_status_attempts: usize) -> Result<Felt, Error> {
+        self.verify()?;
+
+        let sierra = self
+            .load_sierra()?
+            .ok_or(Error::Validation("Sierra class is required to declare".to_string()))?;
+        let casm = self
+            .load_casm()?
+            .ok_or(Error::Validation("compiled class is required to declare".to_string()))?;
+
+        let flattened = sierra
+            .flatten()
+            .map_err(|e| Error::Validation(format!("Failed to flatten Sierra class: {}", e)))?;
+        let compiled_class_hash = casm
+            .class_hash()
+            .map_err(|e| Error::Validation(format!("Failed to compute compiled class hash: {}", e)))?;
+
+        let result = account
+            .declare_v3(Arc::new(flattened), compiled_class_hash)
+            .send()
+            .await
+            .map_err(|e| Error::Execution(format!("Failed to declare class {}: {}", self.class_hash.to_fixed_hex_string(), e)))?;
+
+        wait_for_transaction_success(starknet, result.transaction_hash, max_check_status_attempts).await?;
+
+        Ok(result.transaction_hash)
+    }
+
+    fn load_sierra(&self) -> Result<Option<SierraClass>, Error> {
+        self.sierra.as_deref().map(load_json).transpose()
+    }
+
+    fn load_casm(&self) -> Result<Option<CompiledClass>, Error> {
+        self.casm.as_deref().map(load_json).transpose()
+    }
+}
+
+fn load_json<T: for<'de> Deserialize<'de>>(path: &str) -> Result<T, Error> {
+    let data = fs::read(path).map_err(|e| Error::Validation(format!("Failed to read {}: {}", path, e)))?;
+
+    serde_json::from_slice(&data).map_err(|e| Error::Validation(format!("Failed to parse {}: {}", path, e)))
+}
+
+/// Contract classes required by the paymaster on a chain
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContractArtifacts {
+    pub forwarder: ContractArtifact,
+    pub relayer_account: ContractArtifact,
+    pub gas_tank_account: ContractArtifact,
+}
+
+impl ContractArtifacts {
+    /// Classes used by default. They are declared with the same hash on Mainnet and Sepolia.
+    pub fn pinned(_: &ChainID) -> Self {
+        Self {
+            forwarder: ContractArtifact::pinned(ClassHash::FORWARDER),
+            relayer_account: ContractArtifact::pinned(ClassHash::ARGENT_ACCOUNT),
+            gas_tank_account: ContractArtifact::pinned(ClassHash::ARGENT_ACCOUNT),
+        }
+    }
+
+    /// Load the artifacts of the given chain from a file mapping the chain identifiers (e.g `SN_MAIN`) to their
+    /// artifacts. Chains missing from the file use the pinned classes.
+    pub fn from_file(path: &str, chain_id: &ChainID) -> Result<Self, Error> {
+        let mut artifacts: HashMap<String, Self> = load_json(path)?;
+
+        Ok(artifacts
+            .remove(&chain_id.as_identifier())
+            .unwrap_or_else(|| Self::pinned(chain_id)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &ContractArtifact)> {
+        [
+            ("forwarder", &self.forwarder),
+            ("relayer account", &self.relayer_account),
+            ("gas tank account", &self.gas_tank_account),
+        ]
+        .into_iter()
+    }
+
+    /// Fails if one of the classes is not declared on chain or does not match its artifact
+    pub async fn ensure_declared(&self, starknet: &Client) -> Result<(), Error> {
+        for (name, artifact) in self.iter() {
+            artifact
+                .verify()
+                .map_err(|e| Error::Validation(format!("{} artifact: {}", name, e)))?;
+
+            if !artifact.is_declared(starknet).await? {
+                return Err(Error::Validation(format!(
+                    "{} class {} is not declared on chain, declare it with the `contracts` command",
+                    name,
+                    artifact.class_hash.to_fixed_hex_string()
+                )));
+            }
+        }
+
+        info!("✅ Required contract classes are declared");
+        Ok(())
+    }
+}
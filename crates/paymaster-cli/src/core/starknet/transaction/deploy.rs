@@ -62,18 +62,25 @@ pub struct DeployArgentAccount {
 }
 
 impl DeployArgentAccount {
-    pub async fn initialize(starknet: &Client, private_key: Felt) -> Self {
-        let account = starknet.initialize_argent_account(private_key).await;
-        let salt = Felt::from(Uuid::new_v4().as_u128());
+    pub async fn initialize(starknet: &Client, private_key: Felt) -> Result<Self, Error> {
+        Self::initialize_with_salt(starknet, private_key, Felt::from(Uuid::new_v4().as_u128())).await
+    }
+
+    /// Initialize the deployment of an account whose address is fully determined by its private key and `salt`
+    pub async fn initialize_with_salt(starknet: &Client, private_key: Felt, salt: Felt) -> Result<Self, Error> {
+        let account = starknet
+            .initialize_argent_account(private_key)
+            .await
+            .map_err(|e| Error::Execution(format!("Failed to initialize account: {}", e)))?;
         let deploy = account.deploy_v3(salt);
 
-        Self {
+        Ok(Self {
             private_key,
             address: deploy.address(),
             class_hash: account.class_hash(),
             salt,
             calldata: account.calldata(),
-        }
+        })
     }
 
     pub async fn deploy(self, account: &StarknetAccount) -> Result<Felt, Error> {
@@ -0,0 +1,5 @@
+pub mod command;
+pub mod constants;
+pub mod contracts;
+pub mod core;
+pub mod validation;
@@ -1,20 +1,25 @@
 use log::LevelFilter;
 use simple_logger::SimpleLogger;
 
-mod command;
-pub mod constants;
-pub mod core;
-pub mod validation;
-
 use clap::{Parser, Subcommand};
-
-use crate::command::balance::{command_balances, BalancesCommandParameters};
-use crate::command::empty::{command_empty_paymaster, EmptyPaymasterParameters};
-use crate::command::quick_setup::{command_quick_setup, QuickSetupParameters};
-use crate::command::relayer::deploy::{command_relayers_deploy, RelayersDeployCommandParameters};
-use crate::command::relayer::rebalance::{command_relayers_rebalance, RelayersRebalanceCommandParameters};
-use crate::command::setup::{command_setup, SetupParameters};
-use crate::core::Error;
+use paymaster_cli::command::accounting::{command_accounting_snapshot, AccountingSnapshotCommandParameters};
+use paymaster_cli::command::approval::{command_approvals, ApprovalsCommandParameters};
+use paymaster_cli::command::balance::{command_balances, BalancesCommandParameters};
+use paymaster_cli::command::config::{command_config, ConfigCommandParameters};
+use paymaster_cli::command::contracts::{command_contracts, ContractsCommandParameters};
+use paymaster_cli::command::dead_letter::{command_dead_letters, DeadLettersCommandParameters};
+use paymaster_cli::command::empty::{command_empty_paymaster, EmptyPaymasterParameters};
+use paymaster_cli::command::forwarder::whitelist::{command_forwarder_whitelist, ForwarderWhitelistCommandParameters};
+use paymaster_cli::command::gas_tank::approve::{command_gas_tank_approve, GasTankApproveCommandParameters};
+use paymaster_cli::command::openrpc::{command_openrpc, OpenRpcCommandParameters};
+use paymaster_cli::command::pricing::{command_simulate_pricing, SimulatePricingCommandParameters};
+use paymaster_cli::command::quick_setup::{command_quick_setup, QuickSetupParameters};
+use paymaster_cli::command::refund::{command_refunds, RefundsCommandParameters};
+use paymaster_cli::command::relayer::deploy::{command_relayers_deploy, RelayersDeployCommandParameters};
+use paymaster_cli::command::relayer::rebalance::{command_relayers_rebalance, RelayersRebalanceCommandParameters};
+use paymaster_cli::command::relayer::recover::{command_relayers_recover, RelayersRecoverCommandParameters};
+use paymaster_cli::command::setup::{command_setup, SetupParameters};
+use paymaster_cli::core::Error;
 
 #[derive(Parser)]
 struct Cli {
@@ -36,11 +41,44 @@ pub enum Commands {
     #[command(about = "Refund & rebalance STRK funds across relayers")]
     RelayersRebalance(RelayersRebalanceCommandParameters),
 
+    #[command(about = "Scan the deterministic relayer addresses and register the deployed relayers in the configuration")]
+    RelayersRecover(RelayersRecoverCommandParameters),
+
+    #[command(about = "Whitelist or blacklist relayers on the forwarder of an existing paymaster")]
+    ForwarderWhitelist(ForwarderWhitelistCommandParameters),
+
+    #[command(about = "Approve the pending proposals of a multisig gas tank and execute the approved ones")]
+    GasTankApprove(GasTankApproveCommandParameters),
+
+    #[command(about = "Verify that the contract classes required by the paymaster are declared, optionally declaring them")]
+    Contracts(ContractsCommandParameters),
+
     #[command(about = "Check balances of paymaster accounts")]
     Balances(BalancesCommandParameters),
 
     #[command(about = "Empty paymaster funds back to master account")]
     Empty(EmptyPaymasterParameters),
+
+    #[command(about = "Report the refunds owed to users by a running paymaster")]
+    Refunds(RefundsCommandParameters),
+
+    #[command(about = "Replay the recent transactions of a running paymaster against proposed pricing parameters")]
+    SimulatePricing(SimulatePricingCommandParameters),
+
+    #[command(about = "Record the balances of the gas tank and of the relayers of a running paymaster, optionally to a CSV file")]
+    AccountingSnapshot(AccountingSnapshotCommandParameters),
+
+    #[command(about = "Inspect, retry or discard the sponsored executions which failed on a running paymaster")]
+    DeadLetters(DeadLettersCommandParameters),
+
+    #[command(about = "Inspect, approve or reject the sponsored executions waiting for an approval on a running paymaster")]
+    Approvals(ApprovalsCommandParameters),
+
+    #[command(about = "Print the effective configuration merged from the base profile, the overlay and the environment variables")]
+    Config(ConfigCommandParameters),
+
+    #[command(about = "Generate the OpenRPC specification of the paymaster API")]
+    Openrpc(OpenRpcCommandParameters),
 }
 
 #[tokio::main]
@@ -56,8 +94,19 @@ async fn main() -> Result<(), Error> {
         Commands::Setup(params) => command_setup(params).await?,
         Commands::RelayersDeploy(params) => command_relayers_deploy(params).await?,
         Commands::RelayersRebalance(params) => command_relayers_rebalance(params).await?,
+        Commands::RelayersRecover(params) => command_relayers_recover(params).await?,
+        Commands::ForwarderWhitelist(params) => command_forwarder_whitelist(params).await?,
+        Commands::GasTankApprove(params) => command_gas_tank_approve(params).await?,
+        Commands::Contracts(params) => command_contracts(params).await?,
         Commands::Balances(params) => command_balances(params).await?,
         Commands::Empty(params) => command_empty_paymaster(params).await?,
+        Commands::Refunds(params) => command_refunds(params).await?,
+        Commands::SimulatePricing(params) => command_simulate_pricing(params).await?,
+        Commands::AccountingSnapshot(params) => command_accounting_snapshot(params).await?,
+        Commands::DeadLetters(params) => command_dead_letters(params).await?,
+        Commands::Approvals(params) => command_approvals(params).await?,
+        Commands::Config(params) => command_config(params).await?,
+        Commands::Openrpc(params) => command_openrpc(params).await?,
     }
 
     Ok(())
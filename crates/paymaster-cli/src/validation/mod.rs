@@ -1,4 +1,3 @@
-use paymaster_starknet::constants::Token;
 use paymaster_starknet::math::denormalize_felt;
 use paymaster_starknet::Client;
 use starknet::core::types::Felt;
@@ -7,7 +6,7 @@ use crate::core::Error;
 
 pub async fn assert_strk_balance(client: &Client, contract_address: Felt, amount: Felt) -> Result<(), Error> {
     let balance = client
-        .fetch_balance(Token::STRK_ADDRESS, contract_address)
+        .fetch_balance(client.tokens().strk, contract_address)
         .await
         .map_err(|e| Error::Validation(e.to_string()))?;
     if balance < amount {
@@ -4,6 +4,9 @@ use std::time::{Duration, Instant};
 
 use moka::sync::Cache;
 
+mod refresh;
+pub use refresh::{RefreshAheadCache, RefreshAheadValue};
+
 /// Represents data that becomes stale or expired after a given validity period.
 ///
 /// - After `validity`, the value is considered **stale** (may still be usable).
@@ -108,6 +111,10 @@ where
     pub fn insert(&self, key: K, value: V, validity: Duration) {
         self.cache.insert(key, Expirable::new(value, validity));
     }
+
+    pub fn remove(&self, key: &K) {
+        self.cache.invalidate(key);
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,301 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use futures_core::future::BoxFuture;
+use moka::sync::Cache;
+use tracing::warn;
+
+use crate::cache::Expirable;
+
+/// Fetches the value of a key again, its error being only logged
+type Refresher<V> = Arc<dyn Fn() -> BoxFuture<'static, Result<V, String>> + Send + Sync>;
+
+/// A cache that refreshes its entries ahead of their expiration.
+///
+/// - While an entry is fresh, it is returned directly.
+/// - The entries read since the last refresh are hot and refreshed in the background every `validity / 2`, so
+///   that the entries read regularly never become stale.
+/// - Once an entry is stale (after `validity`), it is still returned immediately while a single
+///   background task refreshes it. Callers never wait for the refresh.
+/// - Once an entry is expired (after `2 * validity`) or missing, the value is fetched synchronously. Concurrent
+///   callers of the same key wait for a single fetch.
+#[derive(Clone)]
+pub struct RefreshAheadCache<K, V> {
+    validity: Duration,
+
+    cache: Cache<K, Expirable<V>>,
+    refreshing: Arc<Mutex<HashSet<K>>>,
+
+    /// Keys being fetched synchronously, on which the concurrent callers wait
+    fetching: Cache<K, Arc<tokio::sync::Mutex<()>>>,
+
+    /// Keys read since the last background refresh, along with the way to fetch them
+    hot: Arc<Mutex<HashMap<K, Refresher<V>>>>,
+    refresher_started: Arc<AtomicBool>,
+}
+
+impl<K, V> RefreshAheadCache<K, V>
+where
+    K: 'static + Clone + Eq + Hash + Send + Sync,
+    V: 'static + Clone + Send + Sync,
+{
+    pub fn new(capacity: u64, validity: Duration) -> Self {
+        Self {
+            validity,
+
+            cache: Cache::new(capacity),
+            refreshing: Arc::default(),
+
+            fetching: Cache::new(capacity),
+
+            hot: Arc::default(),
+            refresher_started: Arc::default(),
+        }
+    }
+
+    /// Returns the value if it exists and is not expired, without triggering any refresh.
+    pub fn get_if_not_expired(&self, key: &K) -> Option<V> {
+        self.cache.get(key).filter(|x| !x.is_expired()).map(|x| x.take())
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        self.cache.insert(key, Expirable::new(value, self.validity));
+    }
+
+    /// Returns the value associated to the given key, using the provided closure to fetch it when needed.
+    ///
+    /// - If the value is fresh, returns it.
+    /// - If the value is stale, returns it and refreshes it in the background. Errors of the background
+    ///   refresh are logged and the stale value is kept until it expires.
+    /// - If the value is expired or missing, fetches it and returns the result of the fetch. Concurrent callers
+    ///   wait for the fetch of the first one and only fetch again if it failed.
+    ///
+    /// In every case, the key is marked as hot so that it is refreshed in the background before it becomes stale.
+    pub async fn get_or_refresh<E>(&self, key: K, fetch_value: impl Fn() -> BoxFuture<'static, Result<V, E>> + Send + Sync + 'static) -> Result<V, E>
+    where
+        E: 'static + Display + Send,
+    {
+        let fetch_value = Arc::new(fetch_value);
+        self.mark_hot(key.clone(), fetch_value.clone());
+
+        match self.cache.get(&key) {
+            Some(entry) if !entry.is_stale() => return Ok(entry.take()),
+            Some(entry) if !entry.is_expired() => {
+                self.refresh_in_background(key, fetch_value());
+                return Ok(entry.take());
+            },
+            _ => {},
+        }
+
+        let fetching = self.fetching.get_with(key.clone(), Arc::default);
+        let _guard = fetching.lock().await;
+
+        // The value may have been fetched by another caller in the meantime
+        if let Some(entry) = self.cache.get(&key).filter(|x| !x.is_stale()) {
+            return Ok(entry.take());
+        }
+
+        let value = fetch_value().await?;
+        self.insert(key, value.clone());
+
+        Ok(value)
+    }
+
+    fn refresh_in_background<E>(&self, key: K, refresh: BoxFuture<'static, Result<V, E>>)
+    where
+        E: 'static + Display + Send,
+    {
+        // Only one refresh per key at a time
+        if !self.refreshing.lock().unwrap_or_else(|e| e.into_inner()).insert(key.clone()) {
+            return;
+        }
+
+        let this = self.clone();
+        tokio::spawn(async move {
+            match refresh.await {
+                Ok(value) => this.insert(key.clone(), value),
+                Err(e) => warn!("could not refresh cache entry in background: {}", e),
+            }
+
+            this.refreshing.lock().unwrap_or_else(|e| e.into_inner()).remove(&key);
+        });
+    }
+
+    fn mark_hot<E, F>(&self, key: K, fetch_value: Arc<F>)
+    where
+        E: 'static + Display + Send,
+        F: Fn() -> BoxFuture<'static, Result<V, E>> + Send + Sync + 'static,
+    {
+        let refresher: Refresher<V> = Arc::new(move || {
+            let refresh = fetch_value();
+            Box::pin(async move { refresh.await.map_err(|e| e.to_string()) })
+        });
+        self.hot.lock().unwrap_or_else(|e| e.into_inner()).insert(key, refresher);
+
+        if !self.refresher_started.swap(true, Ordering::SeqCst) {
+            self.start_refresher();
+        }
+    }
+
+    // Refreshes the hot keys every half validity period. The task stops once every clone of the cache is dropped.
+    fn start_refresher(&self) {
+        let hot_keys = Arc::downgrade(&self.hot);
+        let this = Self {
+            hot: Arc::default(),
+            ..self.clone()
+        };
+        let period = (self.validity / 2).max(Duration::from_millis(1));
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+
+                let Some(hot) = Weak::upgrade(&hot_keys) else {
+                    break;
+                };
+                let keys = std::mem::take(&mut *hot.lock().unwrap_or_else(|e| e.into_inner()));
+                drop(hot);
+
+                for (key, refresher) in keys {
+                    this.refresh_in_background(key, refresher());
+                }
+            }
+        });
+    }
+}
+
+/// A single value refreshed ahead of its expiration. See [`RefreshAheadCache`] for the refresh policy.
+#[derive(Clone)]
+pub struct RefreshAheadValue<T>(RefreshAheadCache<(), T>);
+
+impl<T> RefreshAheadValue<T>
+where
+    T: 'static + Clone + Send + Sync,
+{
+    pub fn new(validity: Duration) -> Self {
+        Self(RefreshAheadCache::new(1, validity))
+    }
+
+    /// Reads the value, using the provided closure to fetch it when needed. See [`RefreshAheadCache::get_or_refresh`]
+    pub async fn read_or_refresh<E>(&self, fetch_value: impl Fn() -> BoxFuture<'static, Result<T, E>> + Send + Sync + 'static) -> Result<T, E>
+    where
+        E: 'static + Display + Send,
+    {
+        self.0.get_or_refresh((), fetch_value).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use futures_core::future::BoxFuture;
+
+    use crate::cache::{RefreshAheadCache, RefreshAheadValue};
+
+    #[tokio::test]
+    async fn missing_value_is_fetched() {
+        let cache = RefreshAheadCache::<u8, u8>::new(10, Duration::from_secs(10));
+
+        let result = cache.get_or_refresh(1, || Box::pin(async { Ok::<u8, String>(42) })).await;
+        assert_eq!(result, Ok(42));
+        assert_eq!(cache.get_if_not_expired(&1), Some(42));
+    }
+
+    #[tokio::test]
+    async fn fresh_value_is_not_refreshed() {
+        let value = RefreshAheadValue::<u8>::new(Duration::from_secs(10));
+
+        value
+            .read_or_refresh(|| Box::pin(async { Ok::<u8, String>(42) }))
+            .await
+            .unwrap();
+        let result = value.read_or_refresh(|| Box::pin(async { Ok::<u8, String>(84) })).await;
+
+        assert_eq!(result, Ok(42));
+    }
+
+    #[tokio::test]
+    async fn stale_value_is_returned_and_refreshed_in_background() {
+        // Inserted directly so that the entry is not hot and becomes stale
+        let cache = RefreshAheadCache::<u8, u8>::new(10, Duration::from_millis(50));
+        cache.insert(1, 42);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        let fetches = Arc::new(AtomicUsize::new(0));
+        for _ in 0..5 {
+            let result = cache.get_or_refresh(1, counting_fetch(fetches.clone(), 84)).await;
+
+            // Stale value is served without waiting for the refresh
+            assert_eq!(result, Ok(42));
+        }
+
+        tokio::time::sleep(Duration::from_millis(15)).await;
+
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.get_if_not_expired(&1), Some(84));
+    }
+
+    #[tokio::test]
+    async fn expired_value_is_fetched_synchronously() {
+        let cache = RefreshAheadCache::<u8, u8>::new(10, Duration::from_millis(1));
+        cache.insert(1, 42);
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        let result = cache
+            .get_or_refresh(1, || Box::pin(async { Err::<u8, String>("error".to_string()) }))
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn concurrent_misses_are_fetched_once() {
+        let cache = RefreshAheadCache::<u8, u8>::new(10, Duration::from_secs(10));
+
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let results = futures::future::join_all((0..5).map(|_| cache.get_or_refresh(1, counting_fetch(fetches.clone(), 42)))).await;
+
+        assert!(results.into_iter().all(|x| x == Ok(42)));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn hot_values_are_refreshed_before_they_become_stale() {
+        let value = RefreshAheadValue::<u8>::new(Duration::from_millis(100));
+
+        let fetches = Arc::new(AtomicUsize::new(0));
+        value.read_or_refresh(counting_fetch(fetches.clone(), 42)).await.unwrap();
+
+        // Read regularly, the value is refreshed in the background and never served stale
+        for _ in 0..4 {
+            tokio::time::sleep(Duration::from_millis(40)).await;
+            value.read_or_refresh(counting_fetch(fetches.clone(), 42)).await.unwrap();
+            assert!(value.0.cache.get(&()).is_some_and(|x| !x.is_stale()));
+        }
+
+        assert!(fetches.load(Ordering::SeqCst) > 1);
+    }
+
+    // Returns a fetch of `value` which counts its calls
+    fn counting_fetch(fetches: Arc<AtomicUsize>, value: u8) -> impl Fn() -> BoxFuture<'static, Result<u8, String>> + Send + Sync + 'static {
+        move || {
+            let fetches = fetches.clone();
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                fetches.fetch_add(1, Ordering::SeqCst);
+                Ok(value)
+            })
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+
+#[derive(Default)]
+struct CancellationTokenInner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+/// Token used to cancel the tasks running on a [`crate::concurrency::ConcurrentExecutor`].
+/// The token can be cloned and shared with other entities, cancelling one clone cancels all of them.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<CancellationTokenInner>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the token and wake up every entity waiting on [`Self::cancelled`]
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the token is cancelled. Returns immediately if it is already the case.
+    pub async fn cancelled(&self) {
+        loop {
+            // The future must be created before checking the flag so that a concurrent
+            // call to cancel cannot be missed.
+            let notified = self.0.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+
+            notified.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::concurrency::CancellationToken;
+
+    #[tokio::test]
+    async fn cancel_wakes_up_waiters() {
+        let token = CancellationToken::new();
+
+        let waiter = token.clone();
+        let handle = tokio::spawn(async move { waiter.cancelled().await });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        token.cancel();
+
+        tokio::time::timeout(Duration::from_secs(1), handle).await.unwrap().unwrap();
+        assert!(token.is_cancelled());
+    }
+}
@@ -1,10 +1,14 @@
+mod cancellation;
 mod shared;
 mod workers;
 
+use std::time::Duration;
+
+pub use cancellation::CancellationToken;
 pub use shared::SyncValue;
 use thiserror::Error;
 use tokio::task::JoinError;
-pub use workers::ConcurrentExecutor;
+pub use workers::{ConcurrentExecutor, RetryPolicy};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -15,4 +19,12 @@ pub enum Error {
     /// Indicates that no workers have been registered
     #[error("no workers")]
     NoWorkers,
+
+    /// Indicates that the task did not complete before the configured timeout
+    #[error("task timed out after {0:?}")]
+    Timeout(Duration),
+
+    /// Indicates that the task was cancelled before completing
+    #[error("task cancelled")]
+    Cancelled,
 }
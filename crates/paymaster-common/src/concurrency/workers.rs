@@ -1,8 +1,10 @@
+use std::time::Duration;
+
 use futures_core::future::BoxFuture;
 use tokio::task::JoinSet;
 use tracing::Instrument;
 
-use crate::concurrency::Error;
+use crate::concurrency::{CancellationToken, Error};
 
 /// Convenience macro to create task that can be registered in the [`ConcurrentExecutor`].
 /// It wraps the given block into a BoxFuture and move the environment into the block
@@ -16,13 +18,31 @@ macro_rules! task {
     };
 }
 
+type Task<C, S> = Box<dyn FnOnce(C) -> BoxFuture<'static, S> + Send + Sync>;
+
+/// Bounded retry policy applied to tasks registered with [`ConcurrentExecutor::register_with_retry`].
+/// A task is attempted at most `max_attempts` times, waiting `backoff * attempt` between two attempts.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: usize,
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: usize, backoff: Duration) -> Self {
+        Self { max_attempts, backoff }
+    }
+}
+
 /// Concurrent task queue that can be used to run multiple tasks in parallel
 /// Example
 /// ```rust
+///  use std::time::Duration;
+///
 ///  use paymaster_common::concurrency::ConcurrentExecutor;
 ///  use paymaster_common::task;
 ///
-///  let mut executor = ConcurrentExecutor::new((), 8);
+///  let mut executor = ConcurrentExecutor::new((), 8).with_timeout(Duration::from_secs(5));
 ///  executor.register(task!(|_| { 1 }));
 ///  executor.register(task!(|_| { 1 }));
 ///
@@ -32,8 +52,11 @@ pub struct ConcurrentExecutor<C, S> {
     context: C,
     n_workers: usize,
 
-    workers: JoinSet<S>,
-    queue: Vec<Box<dyn FnOnce(C) -> BoxFuture<'static, S> + Send + Sync>>,
+    timeout: Option<Duration>,
+    cancellation: CancellationToken,
+
+    workers: JoinSet<Result<S, Error>>,
+    queue: Vec<Task<C, S>>,
 }
 
 impl<C: Clone, S: 'static + Send + Sync> ConcurrentExecutor<C, S> {
@@ -45,11 +68,39 @@ impl<C: Clone, S: 'static + Send + Sync> ConcurrentExecutor<C, S> {
             context,
             n_workers,
 
+            timeout: None,
+            cancellation: CancellationToken::new(),
+
             workers: JoinSet::new(),
             queue: Vec::new(),
         }
     }
 
+    /// Bound the execution time of each task. A task that does not complete in time is
+    /// dropped and [`Error::Timeout`] is returned in place of its result. The timeout applies to
+    /// the tasks registered after this call.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Use the given token to cancel the execution. Once cancelled, the running tasks are dropped,
+    /// [`Error::Cancelled`] is returned in place of their result and the waiting tasks are discarded.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Returns the token used to cancel the execution
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    /// Cancel all the running and waiting tasks
+    pub fn cancel(&self) {
+        self.cancellation.cancel()
+    }
+
     /// Register a new task. Each task must have the same type signature. The macro
     /// [`task!`] can be used to improve readability. Note if there is a worker available
     /// the task will immediately start
@@ -61,7 +112,7 @@ impl<C: Clone, S: 'static + Send + Sync> ConcurrentExecutor<C, S> {
         if self.workers.len() >= self.n_workers {
             self.queue.push(Box::new(task));
         } else {
-            self.workers.spawn(task(self.context.clone()).in_current_span());
+            self.spawn(Box::new(task));
         }
 
         self
@@ -72,19 +123,27 @@ impl<C: Clone, S: 'static + Send + Sync> ConcurrentExecutor<C, S> {
     /// Errors
     ///  - [`Error::Join`] indicates that the task could not be joined properly
     ///  - [`Error::NoWorkers`] indicates that n_workers was set to 0
+    ///  - [`Error::Timeout`] indicates that the task did not complete in time
+    ///  - [`Error::Cancelled`] indicates that the execution was cancelled
     pub async fn next(&mut self) -> Option<Result<S, Error>> {
         if self.n_workers == 0 {
             return Some(Err(Error::NoWorkers));
         }
 
+        if self.cancellation.is_cancelled() {
+            self.queue.clear();
+        }
+
         let value = match self.workers.join_next().await {
-            Some(Ok(value)) => Some(Ok(value)),
+            Some(Ok(value)) => Some(value),
             None => None,
             Some(Err(e)) => return Some(Err(Error::Join(e))),
         };
 
-        if let Some(task) = self.queue.pop() {
-            self.workers.spawn(task(self.context.clone()).in_current_span());
+        if self.cancellation.is_cancelled() {
+            self.queue.clear();
+        } else if let Some(task) = self.queue.pop() {
+            self.spawn(task);
         }
 
         value
@@ -102,11 +161,86 @@ impl<C: Clone, S: 'static + Send + Sync> ConcurrentExecutor<C, S> {
 
         Ok(results)
     }
+
+    /// Execute all the registered tasks and return the result of each of them, including the
+    /// ones that failed, timed out or were cancelled. Unlike [`Self::execute`] a single failing
+    /// task does not prevent collecting the results of the others.
+    pub async fn execute_all(&mut self) -> Vec<Result<S, Error>> {
+        let mut results = Vec::with_capacity(self.n_workers);
+        while let Some(value) = self.next().await {
+            let no_workers = matches!(value, Err(Error::NoWorkers));
+            results.push(value);
+
+            if no_workers {
+                break;
+            }
+        }
+
+        results
+    }
+
+    fn spawn(&mut self, task: Task<C, S>) {
+        let future = task(self.context.clone());
+        let timeout = self.timeout;
+        let cancellation = self.cancellation.clone();
+
+        let future = async move {
+            let execution = async move {
+                match timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, future).await.map_err(|_| Error::Timeout(timeout)),
+                    None => Ok(future.await),
+                }
+            };
+
+            tokio::select! {
+                result = execution => result,
+                _ = cancellation.cancelled() => Err(Error::Cancelled),
+            }
+        };
+
+        self.workers.spawn(future.in_current_span());
+    }
+}
+
+impl<C, T, E> ConcurrentExecutor<C, Result<T, E>>
+where
+    C: 'static + Clone + Send,
+    T: 'static + Send + Sync,
+    E: 'static + Send + Sync,
+{
+    /// Register a new task that is retried according to the given [`RetryPolicy`] as long as
+    /// it returns an error. Since the task can be executed multiple times it must implement [`Fn`].
+    /// When a timeout is configured, it bounds the whole task including its retries.
+    pub fn register_with_retry<F>(&mut self, policy: RetryPolicy, task: F) -> &mut Self
+    where
+        F: 'static + Fn(C) -> BoxFuture<'static, Result<T, E>>,
+        F: Send + Sync,
+    {
+        self.register(move |context: C| -> BoxFuture<'static, Result<T, E>> {
+            Box::pin(async move {
+                let mut attempt = 1;
+                loop {
+                    match task(context.clone()).await {
+                        Ok(value) => return Ok(value),
+                        Err(e) if attempt >= policy.max_attempts => return Err(e),
+                        Err(_) => {
+                            tokio::time::sleep(policy.backoff * attempt as u32).await;
+                            attempt += 1;
+                        },
+                    }
+                }
+            })
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::concurrency::ConcurrentExecutor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::concurrency::{CancellationToken, ConcurrentExecutor, Error, RetryPolicy};
 
     #[tokio::test]
     pub async fn empty_executor() {
@@ -178,4 +312,75 @@ mod tests {
         values.sort();
         assert_eq!(values, vec![5, 6, 7, 8, 9, 10, 11, 12]);
     }
+
+    #[tokio::test]
+    pub async fn stuck_task_times_out() {
+        let mut executor = ConcurrentExecutor::new((), 5).with_timeout(Duration::from_millis(50));
+        executor.register(task!(|_| { 5 }));
+        executor.register(task!(|_| {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            6
+        }));
+
+        let results = executor.execute_all().await;
+
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().any(|x| matches!(x, Ok(5))));
+        assert!(results.iter().any(|x| matches!(x, Err(Error::Timeout(_)))));
+    }
+
+    #[tokio::test]
+    pub async fn cancellation_stops_running_and_waiting_tasks() {
+        let token = CancellationToken::new();
+        let mut executor = ConcurrentExecutor::new((), 1).with_cancellation(token.clone());
+        executor.register(task!(|_| {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            5
+        }));
+        executor.register(task!(|_| { 6 }));
+
+        token.cancel();
+        let results = executor.execute_all().await;
+
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0], Err(Error::Cancelled)));
+    }
+
+    #[tokio::test]
+    pub async fn failing_task_is_retried() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+
+        let mut executor = ConcurrentExecutor::<_, Result<u8, ()>>::new(attempts.clone(), 5);
+        executor.register_with_retry(RetryPolicy::new(3, Duration::from_millis(1)), |attempts| {
+            Box::pin(async move {
+                match attempts.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err(()),
+                    _ => Ok(5),
+                }
+            })
+        });
+
+        let values = executor.execute().await.unwrap();
+
+        assert_eq!(values, vec![Ok(5)]);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    pub async fn retries_are_bounded() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+
+        let mut executor = ConcurrentExecutor::<_, Result<u8, ()>>::new(attempts.clone(), 5);
+        executor.register_with_retry(RetryPolicy::new(2, Duration::from_millis(1)), |attempts| {
+            Box::pin(async move {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(())
+            })
+        });
+
+        let values = executor.execute().await.unwrap();
+
+        assert_eq!(values, vec![Err(())]);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
 }
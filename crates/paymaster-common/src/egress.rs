@@ -0,0 +1,182 @@
+use std::sync::OnceLock;
+
+use reqwest::redirect::Policy;
+use reqwest::{ClientBuilder, Proxy, Url};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::validation::{Validate, ValidationReport};
+
+/// Maximum number of redirections followed by the outbound clients, as done by default by reqwest
+const MAX_REDIRECTIONS: usize = 10;
+
+static EGRESS: OnceLock<Egress> = OnceLock::new();
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("invalid egress proxy {0}")]
+    InvalidProxy(String),
+
+    #[error("egress is already configured")]
+    AlreadyConfigured,
+
+    #[error("host of {0} is not in the egress allowlist")]
+    NotAllowed(String),
+}
+
+/// Egress of the outbound clients (Starknet RPC, price oracles, swaps, webhooks and callbacks), needed when the
+/// paymaster runs in a restricted network
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EgressConfiguration {
+    /// Proxy through which every outbound request is sent (`http://`, `https://` or `socks5://`)
+    #[serde(default)]
+    pub proxy: Option<String>,
+
+    /// Hosts the outbound requests may reach, `*.example.com` allowing every subdomain of `example.com`. Every host
+    /// is allowed when empty.
+    #[serde(default)]
+    pub allowlist: Vec<String>,
+}
+
+impl Validate for EgressConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        if let Some(proxy) = &self.proxy {
+            report.ensure_url("proxy", proxy);
+        }
+        for (i, host) in self.allowlist.iter().enumerate() {
+            let host = host.strip_prefix("*.").unwrap_or(host);
+            let is_valid = !host.is_empty() && !host.contains(['/', ':', '*']);
+            report.ensure(is_valid, &format!("allowlist[{}]", i), "must be a host name");
+        }
+    }
+}
+
+impl EgressConfiguration {
+    /// Apply this configuration to every outbound client built afterwards. Can only be done once.
+    pub fn install(&self) -> Result<(), Error> {
+        let egress = Egress::new(self)?;
+        EGRESS.set(egress).map_err(|_| Error::AlreadyConfigured)
+    }
+}
+
+struct Egress {
+    proxy: Option<Proxy>,
+    allowlist: Vec<String>,
+}
+
+impl Egress {
+    fn new(configuration: &EgressConfiguration) -> Result<Self, Error> {
+        let proxy = match &configuration.proxy {
+            Some(proxy) => Some(Proxy::all(proxy).map_err(|e| Error::InvalidProxy(format!("{}: {}", proxy, e)))?),
+            None => None,
+        };
+
+        Ok(Self {
+            proxy,
+            allowlist: configuration.allowlist.iter().map(|x| x.to_ascii_lowercase()).collect(),
+        })
+    }
+
+    fn is_allowed(&self, url: &Url) -> bool {
+        if self.allowlist.is_empty() {
+            return true;
+        }
+
+        let Some(host) = url.host_str().map(|x| x.to_ascii_lowercase()) else {
+            return false;
+        };
+
+        self.allowlist.iter().any(|allowed| match allowed.strip_prefix("*.") {
+            Some(domain) => host.strip_suffix(domain).is_some_and(|x| x.ends_with('.')),
+            None => *allowed == host,
+        })
+    }
+}
+
+/// Apply the egress proxy and allowlist to the given client. Redirections towards hosts outside the allowlist are
+/// refused. Proxies added to the builder beforehand take precedence over the egress proxy.
+pub fn apply(builder: ClientBuilder) -> ClientBuilder {
+    let Some(egress) = EGRESS.get() else {
+        return builder;
+    };
+
+    let builder = match &egress.proxy {
+        Some(proxy) => builder.proxy(proxy.clone()),
+        None => builder,
+    };
+
+    builder.redirect(Policy::custom(|attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTIONS {
+            attempt.error("too many redirections")
+        } else if EGRESS.get().is_some_and(|egress| !egress.is_allowed(attempt.url())) {
+            let url = attempt.url().to_string();
+            attempt.error(Error::NotAllowed(url))
+        } else {
+            attempt.follow()
+        }
+    }))
+}
+
+/// Fails if the host of the given url is not in the egress allowlist
+pub fn ensure_allowed(url: &str) -> Result<(), Error> {
+    let Some(egress) = EGRESS.get() else {
+        return Ok(());
+    };
+
+    match Url::parse(url) {
+        Ok(parsed) if egress.is_allowed(&parsed) => Ok(()),
+        _ => Err(Error::NotAllowed(url.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use crate::egress::{Egress, EgressConfiguration};
+    use crate::validation::Validate;
+
+    fn egress(allowlist: &[&str]) -> Egress {
+        Egress::new(&EgressConfiguration {
+            proxy: None,
+            allowlist: allowlist.iter().map(|x| x.to_string()).collect(),
+        })
+        .unwrap()
+    }
+
+    fn url(value: &str) -> Url {
+        Url::parse(value).unwrap()
+    }
+
+    #[test]
+    fn every_host_is_allowed_without_allowlist() {
+        assert!(egress(&[]).is_allowed(&url("https://starknet.example.com/rpc")));
+    }
+
+    #[test]
+    fn hosts_are_matched_against_the_allowlist() {
+        let egress = egress(&["api.avnu.fi", "*.infura.io"]);
+
+        assert!(egress.is_allowed(&url("https://api.avnu.fi/swap/v2/quotes")));
+        assert!(egress.is_allowed(&url("https://API.AVNU.FI")));
+        assert!(egress.is_allowed(&url("https://starknet-mainnet.infura.io/v3/key")));
+
+        assert!(!egress.is_allowed(&url("https://sepolia.api.avnu.fi")));
+        assert!(!egress.is_allowed(&url("https://infura.io")));
+        assert!(!egress.is_allowed(&url("https://notinfura.io")));
+        assert!(!egress.is_allowed(&url("https://api.coingecko.com")));
+    }
+
+    #[test]
+    fn invalid_configuration_is_reported() {
+        let configuration = EgressConfiguration {
+            proxy: Some("not a proxy".to_string()),
+            allowlist: vec!["https://api.avnu.fi".to_string(), "*.".to_string()],
+        };
+
+        let errors = configuration.validate_all().unwrap_err();
+        assert!(errors.contains("proxy"));
+        assert!(errors.contains("allowlist[0]"));
+        assert!(errors.contains("allowlist[1]"));
+    }
+}
@@ -1,6 +1,12 @@
+#[cfg(feature = "native")]
 pub mod cache;
+#[cfg(feature = "native")]
 pub mod concurrency;
+#[cfg(feature = "native")]
+pub mod egress;
+#[cfg(feature = "native")]
 pub mod service;
+pub mod validation;
 
 mod macros;
 
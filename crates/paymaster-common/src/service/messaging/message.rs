@@ -59,7 +59,13 @@ where
 
     /// Publish a new message using the given [`MessageIdentity`]
     pub async fn publish<S: MessageIdentity>(&self, message: T) {
-        self.0.read().await.publish::<S>(message).await
+        self.0.read().await.publish(S::NAME, message).await
+    }
+
+    /// Publish a new message on behalf of the identity with the given name. This is used
+    /// by remote backends which only know the identity name of the original sender
+    pub(crate) async fn publish_as(&self, name: &str, message: T) {
+        self.0.read().await.publish(name, message).await
     }
 
     /// Returns a builder to create a new [`MessageReceiver`] bound to the given [`MessageIdentity`]
@@ -88,10 +94,10 @@ where
     T: Clone,
     T: Send + Sync,
 {
-    /// Publish a message to all the listener of the given [`MessageIdentity`]. In the case
+    /// Publish a message to all the listener of the [`MessageIdentity`] with the given name. In the case
     /// where a channel is closed or full, the corresponding message is dropped.
-    pub async fn publish<S: MessageIdentity>(&self, message: T) {
-        let registrations = self.registrations.get(S::NAME);
+    pub async fn publish(&self, name: &str, message: T) {
+        let registrations = self.registrations.get(name);
         if let Some(registrations) = registrations {
             for r in registrations.values() {
                 // avoid slow-receiver bottleneck
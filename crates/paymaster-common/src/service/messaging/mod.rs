@@ -1,6 +1,8 @@
 mod message;
 pub use message::{AsMessage, MessageIdentity, MessageReceiver, MessageReceiverBuilder, Messages};
 
+pub mod redis;
+
 /// Convenience macros to declare a [`MessageIdentity`] which allow to send/receive
 /// message using [`Messages`]
 /// Example
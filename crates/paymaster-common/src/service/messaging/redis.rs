@@ -0,0 +1,258 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use deadpool_redis::redis::{AsyncCommands, Client, RedisError};
+use deadpool_redis::{Config, CreatePoolError, Pool, PoolError, Runtime};
+use futures::StreamExt;
+use log::{debug, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::service::messaging::{MessageIdentity, Messages};
+use crate::validation::{Validate, ValidationReport};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Redis(#[from] RedisError),
+
+    #[error(transparent)]
+    Connection(#[from] PoolError),
+
+    #[error(transparent)]
+    Pool(#[from] CreatePoolError),
+
+    #[error("invalid message format {0}")]
+    Format(String),
+
+    #[error("subscription to {0} closed")]
+    SubscriptionClosed(String),
+}
+
+fn default_namespace() -> String {
+    "paymaster-messages".to_string()
+}
+
+/// Configuration of the Redis messaging backend. All the instances sharing the same
+/// `endpoint` and `namespace` will receive the messages published by each other.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedisMessagingConfiguration {
+    pub endpoint: String,
+
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+}
+
+impl Validate for RedisMessagingConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure_url("endpoint", &self.endpoint);
+        report.ensure(!self.namespace.is_empty(), "namespace", "must not be empty");
+    }
+}
+
+/// Envelope transmitted through Redis. The origin is used to discard the messages
+/// published by the instance itself since those were already delivered locally.
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    origin: String,
+    identity: String,
+    message: T,
+}
+
+/// Messaging backend that propagates messages published on a local [`Messages`] layer to every
+/// other instance connected to the same Redis server using pub/sub. Messages are always delivered
+/// locally first and then forwarded to Redis, which means local receivers work exactly as with
+/// the in-process layer.
+///
+/// Remote messages are only received while [`RedisMessages::listen`] is running. In general one
+/// runs it inside a dedicated service.
+#[derive(Clone)]
+pub struct RedisMessages<T>
+where
+    T: Clone,
+    T: Send + Sync,
+{
+    origin: String,
+    namespace: String,
+
+    client: Client,
+    redis: Pool,
+
+    messages: Messages<T>,
+}
+
+impl<T> RedisMessages<T>
+where
+    T: Clone + Serialize + DeserializeOwned,
+    T: Send + Sync + 'static,
+{
+    /// Creates a new Redis backend bound to the given local [`Messages`] layer
+    pub fn new(configuration: &RedisMessagingConfiguration, messages: Messages<T>) -> Result<Self, Error> {
+        let origin = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| format!("{}-{}", std::process::id(), x.as_nanos()))
+            .unwrap_or_else(|_| std::process::id().to_string());
+
+        Ok(Self {
+            origin,
+            namespace: configuration.namespace.clone(),
+
+            client: Client::open(configuration.endpoint.as_str())?,
+            redis: Config::from_url(&configuration.endpoint).create_pool(Some(Runtime::Tokio1))?,
+
+            messages,
+        })
+    }
+
+    /// Returns the local messaging layer
+    pub fn messages(&self) -> &Messages<T> {
+        &self.messages
+    }
+
+    /// Publish a message using the given [`MessageIdentity`] to the local receivers and to all the
+    /// other instances. Failing to reach Redis does not prevent the local delivery.
+    pub async fn publish<S: MessageIdentity>(&self, message: T) -> Result<(), Error> {
+        self.messages.publish::<S>(message.clone()).await;
+
+        let envelope = Envelope {
+            origin: self.origin.clone(),
+            identity: S::NAME.to_string(),
+            message,
+        };
+
+        let payload = serde_json::to_string(&envelope).map_err(|e| Error::Format(e.to_string()))?;
+
+        let mut connection = self.redis.get().await?;
+        let _: () = connection.publish(self.channel(S::NAME), payload).await?;
+
+        Ok(())
+    }
+
+    /// Listen to the messages published by the other instances and forward them to the local
+    /// receivers. This function only returns when the subscription fails.
+    pub async fn listen(&self) -> Result<(), Error> {
+        let pattern = self.channel("*");
+
+        let mut pubsub = self.client.get_async_pubsub().await?;
+        pubsub.psubscribe(&pattern).await?;
+
+        let mut stream = pubsub.on_message();
+        while let Some(message) = stream.next().await {
+            let payload: String = match message.get_payload() {
+                Ok(payload) => payload,
+                Err(e) => {
+                    warn!("could not read message payload {}", e);
+                    continue;
+                },
+            };
+
+            let envelope: Envelope<T> = match serde_json::from_str(&payload) {
+                Ok(envelope) => envelope,
+                Err(e) => {
+                    warn!("could not decode message from {} {}", message.get_channel_name(), e);
+                    continue;
+                },
+            };
+
+            if envelope.origin == self.origin {
+                continue;
+            }
+
+            debug!("received remote message from {} for {}", envelope.origin, envelope.identity);
+            self.messages.publish_as(&envelope.identity, envelope.message).await;
+        }
+
+        Err(Error::SubscriptionClosed(pattern))
+    }
+
+    fn channel(&self, identity: &str) -> String {
+        format!("{}:{}", self.namespace, identity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Serialize};
+    use testcontainers::core::{ContainerPort, WaitFor};
+    use testcontainers::runners::AsyncRunner;
+    use testcontainers::{ContainerAsync, GenericImage};
+
+    use crate::declare_message_identity;
+    use crate::service::messaging::redis::{RedisMessages, RedisMessagingConfiguration};
+    use crate::service::messaging::Messages;
+
+    type RedisContainer = ContainerAsync<GenericImage>;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum Message {
+        RelayerDisabled(u64),
+    }
+
+    struct Sender;
+    declare_message_identity!(Sender);
+
+    struct Receiver;
+    declare_message_identity!(Receiver);
+
+    async fn redis_container() -> RedisContainer {
+        GenericImage::new("redis", "7")
+            .with_exposed_port(ContainerPort::Tcp(6379))
+            .with_wait_for(WaitFor::message_on_stdout("Ready to accept connections"))
+            .start()
+            .await
+            .unwrap()
+    }
+
+    async fn configuration(container: &RedisContainer) -> RedisMessagingConfiguration {
+        let port = container.get_host_port_ipv4(6379).await.unwrap();
+
+        RedisMessagingConfiguration {
+            endpoint: format!("redis://127.0.0.1:{}", port),
+            namespace: "test".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn messages_are_propagated_to_other_instances() {
+        let container = redis_container().await;
+        let configuration = configuration(&container).await;
+
+        let instance_a = RedisMessages::new(&configuration, Messages::<Message>::new()).unwrap();
+
+        let mut messages_b = Messages::<Message>::new();
+        let mut receiver_b = messages_b.receiver::<Receiver>().subscribe_to::<Sender>().build().await;
+        let instance_b = RedisMessages::new(&configuration, messages_b).unwrap();
+
+        let listener = instance_b.clone();
+        tokio::spawn(async move { listener.listen().await });
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        instance_a.publish::<Sender>(Message::RelayerDisabled(42)).await.unwrap();
+
+        let message = tokio::time::timeout(Duration::from_secs(5), receiver_b.receive())
+            .await
+            .unwrap();
+        assert_eq!(message, Some(Message::RelayerDisabled(42)));
+    }
+
+    #[tokio::test]
+    async fn own_messages_are_delivered_only_once() {
+        let container = redis_container().await;
+        let configuration = configuration(&container).await;
+
+        let mut messages = Messages::<Message>::new();
+        let mut receiver = messages.receiver::<Receiver>().subscribe_to::<Sender>().build().await;
+        let instance = RedisMessages::new(&configuration, messages).unwrap();
+
+        let listener = instance.clone();
+        tokio::spawn(async move { listener.listen().await });
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        instance.publish::<Sender>(Message::RelayerDisabled(1)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        assert_eq!(receiver.receive_all().await, vec![Message::RelayerDisabled(1)]);
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+use tracing::Subscriber;
+use tracing_subscriber::{reload, EnvFilter};
+
+type Reload = Box<dyn Fn(EnvFilter) -> Result<(), String> + Send + Sync>;
+
+static FILTER: OnceLock<LogFilter> = OnceLock::new();
+
+/// Filter directives of the logs (e.g. `info,paymaster_relayer::lock=debug`), which can be changed at
+/// runtime once the layer returned by [`LogFilter::layer`] is installed on the subscriber.
+pub struct LogFilter {
+    initial: String,
+    current: RwLock<String>,
+
+    /// Incremented on every change so that a temporary change only reverts itself when it is still applied
+    generation: AtomicU64,
+
+    reload: Reload,
+}
+
+impl LogFilter {
+    /// Build the filter layer using the given directives. Only the first layer built can be reloaded.
+    pub fn layer<S>(directives: &str) -> Result<reload::Layer<EnvFilter, S>, String>
+    where
+        S: Subscriber + 'static,
+    {
+        let filter = EnvFilter::try_new(directives).map_err(|e| e.to_string())?;
+        let (layer, handle) = reload::Layer::new(filter);
+
+        let _ = FILTER.set(LogFilter {
+            initial: directives.to_string(),
+            current: RwLock::new(directives.to_string()),
+            generation: AtomicU64::new(0),
+            reload: Box::new(move |filter| handle.reload(filter).map_err(|e| e.to_string())),
+        });
+
+        Ok(layer)
+    }
+
+    fn apply(&self, directives: &str) -> Result<u64, String> {
+        let filter = EnvFilter::try_new(directives).map_err(|e| e.to_string())?;
+        (self.reload)(filter)?;
+
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = directives.to_string();
+        Ok(self.generation.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+}
+
+/// Returns the filter directives currently applied, if the filter can be changed at runtime
+pub fn log_filter() -> Option<String> {
+    FILTER
+        .get()
+        .map(|filter| filter.current.read().unwrap_or_else(|e| e.into_inner()).clone())
+}
+
+/// Replace the filter directives of the logs, or restore the ones used at startup when no directives are
+/// given. When a `duration` is given, the startup directives are restored once it has elapsed unless
+/// the filter has been changed again in the meantime. Returns the directives applied.
+pub fn set_log_filter(directives: Option<&str>, duration: Option<Duration>) -> Result<String, String> {
+    let filter = FILTER.get().ok_or("log filter cannot be changed at runtime")?;
+
+    let directives = directives.unwrap_or(&filter.initial).to_string();
+    let generation = filter.apply(&directives)?;
+
+    if let Some(duration) = duration {
+        tokio::spawn(async move {
+            tokio::time::sleep(duration).await;
+            if filter.generation.load(Ordering::SeqCst) == generation {
+                let _ = filter.apply(&filter.initial);
+            }
+        });
+    }
+
+    Ok(directives)
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::Registry;
+
+    use crate::service::monitoring::filter::{log_filter, set_log_filter, LogFilter};
+
+    #[test]
+    fn log_filter_can_be_changed_and_restored() {
+        let _layer = LogFilter::layer::<Registry>("info").unwrap();
+
+        assert_eq!(
+            set_log_filter(Some("info,paymaster_relayer::lock=debug"), None).unwrap(),
+            "info,paymaster_relayer::lock=debug"
+        );
+        assert_eq!(log_filter().unwrap(), "info,paymaster_relayer::lock=debug");
+
+        assert!(set_log_filter(Some("paymaster_relayer=loud"), None).is_err());
+        assert_eq!(log_filter().unwrap(), "info,paymaster_relayer::lock=debug");
+
+        assert_eq!(set_log_filter(None, None).unwrap(), "info");
+    }
+}
@@ -9,6 +9,10 @@ use tower_http::classify::{ServerErrorsAsFailures, SharedClassifier};
 use tower_http::trace::{MakeSpan, TraceLayer};
 use tracing::Span;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+
+/// Header carrying the identifier of the request, generated when the caller does not provide one
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
 
 /// `tower-http` `MakeSpan` that extracts the W3C trace context from inbound
 /// HTTP headers and sets it as the parent of the new request span.
@@ -16,16 +20,28 @@ use tracing_opentelemetry::OpenTelemetrySpanExt;
 /// If no propagator is globally installed or the headers contain no
 /// `traceparent`, the extracted context is empty and the span starts as
 /// a fresh root — no panic, no-op.
+///
+/// The span carries the `request_id` of the request and an empty `sponsor`
+/// field, recorded once the caller is authenticated.
 #[derive(Debug, Clone, Default)]
 pub struct OtelMakeSpan;
 
 impl<B> MakeSpan<B> for OtelMakeSpan {
     fn make_span(&mut self, request: &http::Request<B>) -> Span {
         let cx = opentelemetry::global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(request.headers())));
+        let request_id = request
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|x| x.to_str().ok())
+            .map(|x| x.to_string())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
         let span = tracing::info_span!(
             "http_request",
             method = %request.method(),
             uri = %request.uri(),
+            request_id = %request_id,
+            sponsor = tracing::field::Empty,
         );
         span.set_parent(cx);
         span
@@ -33,6 +33,9 @@ macro_rules! metric {
             tracing::debug!(counter.$label = $i, $($field = $value,)* error = e.to_string());
         }
     };
+    (updown_counter [ $label: ident ] = $i: expr $(,$field: ident = $value: expr)*) => {
+        tracing::debug!(counter.$label = $i, $($field = $value),*)
+    };
     (gauge [ $label: ident ] = $i: expr $(,$field: ident = $value: expr)*) => {
         tracing::debug!(gauge.$label = $i, $($field = $value),*)
     };
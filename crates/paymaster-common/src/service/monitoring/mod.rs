@@ -5,11 +5,14 @@ use serde::{Deserialize, Serialize};
 mod tracer;
 pub use tracer::{shutdown, Tracer};
 
+mod filter;
+pub use filter::{log_filter, set_log_filter, LogFilter};
+
 mod metric;
 pub use metric::Metric;
 
 mod http;
-pub use http::{trace_layer, OtelMakeSpan};
+pub use http::{trace_layer, OtelMakeSpan, REQUEST_ID_HEADER};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Configuration {
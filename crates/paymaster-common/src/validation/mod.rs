@@ -0,0 +1,215 @@
+use std::fmt::{Display, Formatter};
+
+use http::Uri;
+use thiserror::Error;
+
+/// Configuration that can be validated. Implementations must report every problem they find
+/// in the given [`ValidationReport`] instead of stopping at the first one, so that all the
+/// errors can be shown to the user at once.
+///
+/// Example
+/// ```rust
+/// use paymaster_common::validation::{Validate, ValidationReport};
+///
+/// struct Configuration {
+///     endpoint: String,
+///     timeout: u64,
+/// }
+///
+/// impl Validate for Configuration {
+///     fn validate_into(&self, report: &mut ValidationReport) {
+///         report.ensure_url("endpoint", &self.endpoint);
+///         report.ensure(self.timeout > 0, "timeout", "must be greater than 0");
+///     }
+/// }
+/// ```
+pub trait Validate {
+    /// Report every problem of the value in the given [`ValidationReport`]
+    fn validate_into(&self, report: &mut ValidationReport);
+
+    /// Validate the value and returns all the problems found, if any
+    fn validate_all(&self) -> Result<(), ValidationErrors> {
+        let mut report = ValidationReport::new();
+        self.validate_into(&mut report);
+
+        report.into_result()
+    }
+}
+
+/// A single validation problem along with the path of the field that caused it (e.g `relayers.lock.redis.endpoint`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// All the problems found while validating a value
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub struct ValidationErrors(pub Vec<ValidationError>);
+
+impl Display for ValidationErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} validation error(s)", self.0.len())?;
+        for error in &self.0 {
+            write!(f, "\n  - {}", error)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ValidationErrors {
+    pub fn errors(&self) -> &[ValidationError] {
+        &self.0
+    }
+
+    /// Returns true if one of the error concerns the given path
+    pub fn contains(&self, path: &str) -> bool {
+        self.0.iter().any(|x| x.path == path)
+    }
+}
+
+/// Collects the problems found while validating a value. Nested values are validated using
+/// [`Self::field`] or [`Self::nested`] which prefix the path of the reported errors.
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    path: Vec<String>,
+    errors: Vec<ValidationError>,
+}
+
+impl ValidationReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validate the given nested value under the field `name`
+    pub fn field<V: Validate + ?Sized>(&mut self, name: &str, value: &V) -> &mut Self {
+        self.nested(name, |report| value.validate_into(report))
+    }
+
+    /// Execute the given closure with all the paths prefixed by `name`
+    pub fn nested(&mut self, name: &str, f: impl FnOnce(&mut Self)) -> &mut Self {
+        self.path.push(name.to_string());
+        f(self);
+        self.path.pop();
+
+        self
+    }
+
+    /// Report an error on the given field. An empty field reports the error on the current value.
+    pub fn error(&mut self, field: &str, message: impl Into<String>) -> &mut Self {
+        let path = self
+            .path
+            .iter()
+            .map(String::as_str)
+            .chain(Some(field).filter(|x| !x.is_empty()))
+            .collect::<Vec<_>>()
+            .join(".");
+
+        self.errors.push(ValidationError { path, message: message.into() });
+        self
+    }
+
+    /// Report an error on the given field if the condition does not hold
+    pub fn ensure(&mut self, condition: bool, field: &str, message: impl Into<String>) -> &mut Self {
+        if !condition {
+            self.error(field, message);
+        }
+
+        self
+    }
+
+    /// Report an error on the given field if the value is not a valid absolute url
+    pub fn ensure_url(&mut self, field: &str, value: &str) -> &mut Self {
+        match value.parse::<Uri>() {
+            Ok(uri) if uri.scheme().is_some() && uri.host().is_some() => self,
+            _ => self.error(field, format!("invalid url '{}'", value)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn into_result(self) -> Result<(), ValidationErrors> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors(self.errors))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::validation::{Validate, ValidationReport};
+
+    struct Inner {
+        endpoint: String,
+    }
+
+    impl Validate for Inner {
+        fn validate_into(&self, report: &mut ValidationReport) {
+            report.ensure_url("endpoint", &self.endpoint);
+        }
+    }
+
+    struct Outer {
+        port: u64,
+        inner: Inner,
+        fallbacks: Vec<Inner>,
+    }
+
+    impl Validate for Outer {
+        fn validate_into(&self, report: &mut ValidationReport) {
+            report.ensure(self.port > 0, "port", "must be greater than 0");
+            report.field("inner", &self.inner);
+            for (i, fallback) in self.fallbacks.iter().enumerate() {
+                report.field(&format!("fallbacks[{}]", i), fallback);
+            }
+        }
+    }
+
+    #[test]
+    fn valid_value_returns_ok() {
+        let value = Outer {
+            port: 8080,
+            inner: Inner {
+                endpoint: "http://localhost:5050".to_string(),
+            },
+            fallbacks: vec![Inner {
+                endpoint: "redis://127.0.0.1:6379".to_string(),
+            }],
+        };
+
+        assert!(value.validate_all().is_ok());
+    }
+
+    #[test]
+    fn all_errors_are_reported_with_their_path() {
+        let value = Outer {
+            port: 0,
+            inner: Inner {
+                endpoint: "not an url".to_string(),
+            },
+            fallbacks: vec![Inner { endpoint: "".to_string() }],
+        };
+
+        let errors = value.validate_all().unwrap_err();
+
+        assert_eq!(errors.errors().len(), 3);
+        assert!(errors.contains("port"));
+        assert!(errors.contains("inner.endpoint"));
+        assert!(errors.contains("fallbacks[0].endpoint"));
+    }
+}
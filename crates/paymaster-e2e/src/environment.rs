@@ -0,0 +1,172 @@
+use std::net::TcpListener;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use jsonrpsee::server::ServerHandle;
+use paymaster::rpc::Client as PaymasterClient;
+use paymaster_cli::command::setup::{deploy_paymaster_core, SetupParameters};
+use paymaster_cli::constants::{
+    DEFAULT_INITIAL_ESTIMATE_ACCOUNT_FUND_AMOUNT, DEFAULT_INITIAL_GAS_TANK_FUND_AMOUNT, DEFAULT_MAX_CHECK_STATUS_ATTEMPTS, DEFAULT_MAX_FEE_MULTIPLIER,
+    DEFAULT_MAX_PRICE_IMPACT, DEFAULT_MIN_RELAYER_BALANCE, DEFAULT_MIN_SWAP_SELL_AMOUNT, DEFAULT_PROVIDER_FEE_OVERHEAD, DEFAULT_REBALANCING_CHECK_INTERVAL,
+    DEFAULT_RELAYERS_NUM, DEFAULT_RELAYERS_REBALANCE_TRIGGER_AMOUNT, DEFAULT_RELAYERS_RETRY_TIMEOUT, DEFAULT_STARKNET_TIMEOUT, DEFAULT_SWAP_INTERVAL,
+    DEFAULT_SWAP_SLIPPAGE, DEFAULT_VERBOSITY,
+};
+use paymaster_prices::mock::MockPriceOracle;
+use paymaster_prices::{PriceConfiguration, TokenPrice};
+use paymaster_relayer::lock::shared::RedisParameters;
+use paymaster_relayer::lock::LockLayerConfiguration;
+use paymaster_relayer::rebalancing::OptionalRebalancingConfiguration;
+use paymaster_rpc::server::PaymasterServer;
+use paymaster_service::core::context::configuration::Configuration as ServiceConfiguration;
+use paymaster_service::core::context::Context as ServiceContext;
+use paymaster_starknet::testing::TestEnvironment as StarknetTestEnvironment;
+use starknet::core::types::Felt;
+use testcontainers::core::{IntoContainerPort, WaitFor};
+use testcontainers::runners::AsyncRunner;
+use testcontainers::{ContainerAsync, GenericImage};
+
+pub type RedisContainer = ContainerAsync<GenericImage>;
+
+/// Price oracle quoting every token at 1 STRK so that the scenarios do not depend on the market
+#[derive(Debug, Clone)]
+struct FixedPriceOracle;
+
+#[async_trait]
+impl MockPriceOracle for FixedPriceOracle {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self
+    }
+
+    async fn fetch_token(&self, address: Felt) -> Result<TokenPrice, paymaster_prices::Error> {
+        Ok(TokenPrice {
+            address,
+            price_in_strk: Felt::from(1e18 as u128),
+            decimals: 18,
+        })
+    }
+}
+
+/// Paymaster deployed with the CLI setup on a devnet, whose relayers are coordinated through Redis, and
+/// served over JSON-RPC. Scenarios only talk to it through the public client, like an integrator would.
+pub struct E2EEnvironment {
+    pub starknet: StarknetTestEnvironment,
+
+    /// Client of the running paymaster
+    pub paymaster: PaymasterClient,
+
+    /// Configuration produced by the CLI setup
+    pub configuration: ServiceConfiguration,
+
+    server: ServerHandle,
+
+    #[allow(dead_code)]
+    redis: RedisContainer,
+}
+
+impl Drop for E2EEnvironment {
+    fn drop(&mut self) {
+        let _ = self.server.stop();
+    }
+}
+
+impl E2EEnvironment {
+    /// Starts the environment when `PAYMASTER_E2E` is set. Returns `None` otherwise so that the scenarios, which
+    /// need Docker to run the devnet and Redis, are skipped by a plain `cargo test`.
+    pub async fn from_env() -> Option<Self> {
+        std::env::var_os("PAYMASTER_E2E")?;
+
+        Some(Self::start().await)
+    }
+
+    pub async fn start() -> Self {
+        let starknet = StarknetTestEnvironment::new().await;
+        let redis = Self::start_redis().await;
+        let redis_endpoint = format!("redis://127.0.0.1:{}", redis.get_host_port_ipv4(6379).await.unwrap());
+
+        let port = Self::available_port();
+        let mut configuration = deploy_paymaster_core(Self::setup_parameters(&starknet, port), true)
+            .await
+            .expect("failed to deploy the paymaster");
+
+        // Relayers are coordinated through Redis like in production
+        configuration.relayers.lock = LockLayerConfiguration::Shared {
+            retry_timeout: Duration::from_secs(DEFAULT_RELAYERS_RETRY_TIMEOUT),
+            redis: RedisParameters::new(&redis_endpoint),
+            fallback: None,
+        };
+        configuration
+            .supported_tokens
+            .extend([StarknetTestEnvironment::ETH, StarknetTestEnvironment::STRK]);
+
+        // Prices and swaps rely on external APIs which are not reachable from the devnet
+        let mut rpc_configuration: paymaster_rpc::Configuration = ServiceContext::new(configuration.clone()).into();
+        rpc_configuration.price = PriceConfiguration::mock::<FixedPriceOracle>();
+        rpc_configuration.relayers.rebalancing = OptionalRebalancingConfiguration::initialize(None);
+
+        let server = PaymasterServer::new(&rpc_configuration)
+            .expect("failed to create the paymaster")
+            .start()
+            .await
+            .expect("failed to start the paymaster");
+
+        Self {
+            starknet,
+            paymaster: PaymasterClient::new(&format!("http://127.0.0.1:{}", port)),
+            configuration,
+            server,
+            redis,
+        }
+    }
+
+    /// Balance of `account` in the given `token`
+    pub async fn balance(&self, token: Felt, account: Felt) -> Felt {
+        self.starknet.fetch_balance(token, account).await.unwrap()
+    }
+
+    async fn start_redis() -> RedisContainer {
+        GenericImage::new("redis", "7")
+            .with_exposed_port(6379.tcp())
+            .with_wait_for(WaitFor::message_on_stdout("Ready to accept connections"))
+            .start()
+            .await
+            .unwrap()
+    }
+
+    fn available_port() -> u64 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port() as u64
+    }
+
+    fn setup_parameters(starknet: &StarknetTestEnvironment, port: u64) -> SetupParameters {
+        let profile = std::env::temp_dir().join(format!("paymaster-e2e-{}.json", port));
+
+        SetupParameters {
+            rpc_url: Some(starknet.configuration().endpoint),
+            rpc_timeout: DEFAULT_STARKNET_TIMEOUT,
+            rpc_headers: vec![],
+            rpc_port: port,
+            chain_id: StarknetTestEnvironment::NETWORK.to_string(),
+            master_address: StarknetTestEnvironment::ACCOUNT_1.address,
+            master_pk: StarknetTestEnvironment::ACCOUNT_1.private_key,
+            num_relayers: DEFAULT_RELAYERS_NUM,
+            fund: DEFAULT_INITIAL_GAS_TANK_FUND_AMOUNT,
+            estimate_account_fund: DEFAULT_INITIAL_ESTIMATE_ACCOUNT_FUND_AMOUNT,
+            profile: profile.to_string_lossy().to_string(),
+            max_check_status_attempts: DEFAULT_MAX_CHECK_STATUS_ATTEMPTS,
+            min_swap_sell_amount: DEFAULT_MIN_SWAP_SELL_AMOUNT,
+            max_fee_multiplier: DEFAULT_MAX_FEE_MULTIPLIER,
+            fee_overhead: DEFAULT_PROVIDER_FEE_OVERHEAD,
+            min_relayer_balance: DEFAULT_MIN_RELAYER_BALANCE,
+            rebalancing_check_interval: DEFAULT_REBALANCING_CHECK_INTERVAL,
+            rebalancing_trigger_balance: DEFAULT_RELAYERS_REBALANCE_TRIGGER_AMOUNT,
+            swap_slippage: DEFAULT_SWAP_SLIPPAGE,
+            swap_interval: DEFAULT_SWAP_INTERVAL,
+            max_price_impact: DEFAULT_MAX_PRICE_IMPACT,
+            verbosity: DEFAULT_VERBOSITY.to_string(),
+            force: true,
+        }
+    }
+}
@@ -0,0 +1,8 @@
+mod environment;
+pub use environment::E2EEnvironment;
+
+mod scenario;
+pub use scenario::{BalanceChange, Outcome, Scenario};
+
+mod session;
+pub use session::SessionKey;
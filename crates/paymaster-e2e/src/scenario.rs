@@ -0,0 +1,319 @@
+use std::time::Duration;
+
+use paymaster::rpc::{
+    BuildTransactionRequest, BuildTransactionResponse, ExecutableInvokeParameters, ExecutableTransactionParameters, ExecuteRequest, ExecutionParameters, FeeMode,
+    FinalityLevel, InvokeParameters, InvokeTransaction, TimeBounds, TransactionParameters,
+};
+use paymaster_starknet::testing::TestEnvironment as StarknetTestEnvironment;
+use paymaster_starknet::StarknetAccountConfiguration;
+use starknet::core::types::{Call, ExecutionResult, Felt};
+use starknet::signers::SigningKey;
+use tokio::time;
+
+use crate::{E2EEnvironment, SessionKey};
+
+/// Number of times the receipt of the executed transaction is polled before failing the scenario
+const MAX_RECEIPT_ATTEMPTS: usize = 30;
+
+/// Expected variation of a balance between the start and the end of a scenario
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BalanceChange {
+    Unchanged,
+    Increased,
+    Decreased,
+    IncreasedBy(Felt),
+    DecreasedBy(Felt),
+}
+
+impl BalanceChange {
+    pub fn matches(&self, before: Felt, after: Felt) -> bool {
+        match self {
+            Self::Unchanged => after == before,
+            Self::Increased => after > before,
+            Self::Decreased => after < before,
+            Self::IncreasedBy(amount) => after >= before && after - before == *amount,
+            Self::DecreasedBy(amount) => after <= before && before - after == *amount,
+        }
+    }
+}
+
+/// Expected outcome of the transaction executed by a scenario
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Succeeded,
+    Reverted,
+}
+
+#[derive(Debug, Clone)]
+struct BalanceExpectation {
+    token: Felt,
+    account: Felt,
+    change: BalanceChange,
+}
+
+/// Build/execute flow of a user against the paymaster of an [`E2EEnvironment`], with the expectations on
+/// its receipt and on the balances it affects.
+///
+/// ```ignore
+/// Scenario::new("transfer paying fees in STRK", StarknetTestEnvironment::ACCOUNT_ARGENT_1)
+///     .call(an_eth_transfer(StarknetTestEnvironment::ACCOUNT_2.address, Felt::ONE))
+///     .paying_with(StarknetTestEnvironment::STRK)
+///     .expect_balance(StarknetTestEnvironment::ETH, StarknetTestEnvironment::ACCOUNT_2.address, BalanceChange::IncreasedBy(Felt::ONE))
+///     .run(&environment)
+///     .await;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    name: String,
+    user: StarknetAccountConfiguration,
+    session: Option<SessionKey>,
+
+    calls: Vec<Call>,
+    fee_mode: FeeMode,
+    time_bounds: Option<TimeBounds>,
+
+    outcome: Outcome,
+    balances: Vec<BalanceExpectation>,
+}
+
+impl Scenario {
+    pub fn new(name: &str, user: StarknetAccountConfiguration) -> Self {
+        Self {
+            name: name.to_string(),
+            user,
+            session: None,
+
+            calls: vec![],
+            fee_mode: FeeMode::Default {
+                gas_token: StarknetTestEnvironment::STRK,
+                tip: Default::default(),
+            },
+            time_bounds: None,
+
+            outcome: Outcome::Succeeded,
+            balances: vec![],
+        }
+    }
+
+    pub fn call(mut self, call: Call) -> Self {
+        self.calls.push(call);
+        self
+    }
+
+    /// Sign the transaction with the session key of a session-based account instead of its owner
+    pub fn with_session(name: &str, session: SessionKey) -> Self {
+        Self {
+            session: Some(session.clone()),
+            ..Self::new(name, session.user())
+        }
+    }
+
+    /// Pay the fees in the given token
+    pub fn paying_with(self, gas_token: Felt) -> Self {
+        self.fee_mode(FeeMode::Default {
+            gas_token,
+            tip: Default::default(),
+        })
+    }
+
+    pub fn fee_mode(mut self, fee_mode: FeeMode) -> Self {
+        self.fee_mode = fee_mode;
+        self
+    }
+
+    pub fn time_bounds(mut self, time_bounds: TimeBounds) -> Self {
+        self.time_bounds = Some(time_bounds);
+        self
+    }
+
+    pub fn expect_outcome(mut self, outcome: Outcome) -> Self {
+        self.outcome = outcome;
+        self
+    }
+
+    /// Expect the balance of `account` in `token` to change as given once the transaction is included
+    pub fn expect_balance(mut self, token: Felt, account: Felt, change: BalanceChange) -> Self {
+        self.balances.push(BalanceExpectation { token, account, change });
+        self
+    }
+
+    /// Build, sign and execute the transaction, then check the expectations once it is included.
+    /// Panics on the first unmet expectation. Returns the hash of the executed transaction.
+    pub async fn run(self, environment: &E2EEnvironment) -> Felt {
+        let mut balances_before = vec![];
+        for expectation in &self.balances {
+            balances_before.push(environment.balance(expectation.token, expectation.account).await);
+        }
+
+        let transaction_hash = self.build_and_execute(environment).await;
+        self.check_outcome(environment, transaction_hash).await;
+
+        for (expectation, before) in self.balances.iter().zip(balances_before) {
+            let after = environment.balance(expectation.token, expectation.account).await;
+            assert!(
+                expectation.change.matches(before, after),
+                "[{}] balance of {} in {} went from {} to {}, expected {:?}",
+                self.name,
+                expectation.account.to_fixed_hex_string(),
+                expectation.token.to_fixed_hex_string(),
+                before,
+                after,
+                expectation.change
+            );
+        }
+
+        transaction_hash
+    }
+
+    async fn build_and_execute(&self, environment: &E2EEnvironment) -> Felt {
+        let build_request = BuildTransactionRequest {
+            transaction: TransactionParameters::Invoke {
+                invoke: InvokeParameters {
+                    user_address: self.user.address,
+                    calls: self.calls.clone(),
+                    session: self.session.is_some(),
+                },
+            },
+            parameters: self.parameters(),
+            chain_id: None,
+        };
+
+        let build_response = environment
+            .paymaster
+            .build_transaction(build_request)
+            .await
+            .unwrap_or_else(|e| panic!("[{}] build failed: {}", self.name, e));
+        let BuildTransactionResponse::Invoke(InvokeTransaction { typed_data, .. }) = build_response else {
+            panic!("[{}] expected an invoke transaction", self.name)
+        };
+
+        let message_hash = typed_data.message_hash(self.user.address).unwrap();
+        let (signature, session) = match &self.session {
+            Some(session) => {
+                let (signature, session) = session.sign(message_hash);
+                (signature, Some(session))
+            },
+            None => {
+                let signature = SigningKey::from_secret_scalar(self.user.private_key)
+                    .sign(&message_hash)
+                    .unwrap();
+                (vec![signature.r, signature.s], None)
+            },
+        };
+
+        let execute_request = ExecuteRequest {
+            transaction: ExecutableTransactionParameters::Invoke {
+                invoke: ExecutableInvokeParameters {
+                    user_address: self.user.address,
+                    typed_data: Some(typed_data),
+                    message_hash: None,
+                    signature,
+                    session,
+                },
+            },
+            parameters: self.parameters(),
+            finality: FinalityLevel::Submitted,
+            chain_id: None,
+            client_nonce: None,
+        };
+
+        environment
+            .paymaster
+            .execute_transaction(execute_request)
+            .await
+            .unwrap_or_else(|e| panic!("[{}] execution failed: {}", self.name, e))
+            .transaction_hash
+    }
+
+    async fn check_outcome(&self, environment: &E2EEnvironment, transaction_hash: Felt) {
+        for _ in 0..MAX_RECEIPT_ATTEMPTS {
+            let Ok(receipt) = environment.starknet.get_transaction_receipt(transaction_hash).await else {
+                time::sleep(Duration::from_secs(1)).await;
+                continue;
+            };
+
+            let outcome = match receipt.receipt.execution_result() {
+                ExecutionResult::Succeeded => Outcome::Succeeded,
+                ExecutionResult::Reverted { .. } => Outcome::Reverted,
+            };
+
+            assert_eq!(
+                outcome,
+                self.outcome,
+                "[{}] unexpected outcome of {}: {:?}",
+                self.name,
+                transaction_hash.to_fixed_hex_string(),
+                receipt.receipt.execution_result()
+            );
+            return;
+        }
+
+        panic!("[{}] transaction {} was never included", self.name, transaction_hash.to_fixed_hex_string())
+    }
+
+    fn parameters(&self) -> ExecutionParameters {
+        ExecutionParameters::V1 {
+            fee_mode: self.fee_mode.clone(),
+            time_bounds: self.time_bounds.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use paymaster_starknet::testing::transaction::an_eth_transfer;
+    use paymaster_starknet::testing::TestEnvironment as StarknetTestEnvironment;
+    use starknet::core::types::Felt;
+
+    use crate::{BalanceChange, E2EEnvironment, Scenario, SessionKey};
+
+    #[test]
+    fn balance_changes_are_matched() {
+        let (low, high) = (Felt::from(10), Felt::from(15));
+
+        assert!(BalanceChange::Unchanged.matches(low, low));
+        assert!(BalanceChange::Increased.matches(low, high));
+        assert!(!BalanceChange::Increased.matches(high, low));
+        assert!(BalanceChange::Decreased.matches(high, low));
+        assert!(BalanceChange::IncreasedBy(Felt::from(5)).matches(low, high));
+        assert!(!BalanceChange::IncreasedBy(Felt::from(4)).matches(low, high));
+        assert!(BalanceChange::DecreasedBy(Felt::from(5)).matches(high, low));
+        assert!(!BalanceChange::DecreasedBy(Felt::from(5)).matches(low, high));
+    }
+
+    #[tokio::test]
+    async fn user_transfers_paying_fees_in_strk() {
+        let Some(environment) = E2EEnvironment::from_env().await else { return };
+
+        let user = StarknetTestEnvironment::ACCOUNT_ARGENT_1;
+        let recipient = StarknetTestEnvironment::ACCOUNT_2.address;
+
+        Scenario::new("transfer paying fees in STRK", user)
+            .call(an_eth_transfer(recipient, Felt::ONE))
+            .paying_with(StarknetTestEnvironment::STRK)
+            .expect_balance(StarknetTestEnvironment::ETH, recipient, BalanceChange::IncreasedBy(Felt::ONE))
+            .expect_balance(StarknetTestEnvironment::STRK, user.address, BalanceChange::Decreased)
+            .expect_balance(StarknetTestEnvironment::STRK, environment.configuration.gas_tank.address, BalanceChange::Increased)
+            .run(&environment)
+            .await;
+    }
+
+    // Requires the devnet image to contain a Cartridge Controller or an Argent account which registered a session
+    // allowing the ETH and STRK transfers, described by the `PAYMASTER_E2E_SESSION_*` variables
+    #[tokio::test]
+    async fn user_transfers_with_a_session_key() {
+        let Some(session) = SessionKey::from_env() else { return };
+        let Some(environment) = E2EEnvironment::from_env().await else { return };
+
+        let user = session.account;
+        let recipient = StarknetTestEnvironment::ACCOUNT_2.address;
+
+        Scenario::with_session("transfer signed with a session key", session)
+            .call(an_eth_transfer(recipient, Felt::ONE))
+            .paying_with(StarknetTestEnvironment::STRK)
+            .expect_balance(StarknetTestEnvironment::ETH, recipient, BalanceChange::IncreasedBy(Felt::ONE))
+            .expect_balance(StarknetTestEnvironment::STRK, user, BalanceChange::Decreased)
+            .run(&environment)
+            .await;
+    }
+}
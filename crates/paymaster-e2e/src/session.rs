@@ -0,0 +1,105 @@
+use paymaster::rpc::{Session, SessionAuthorization, SignerSignature};
+use paymaster_starknet::transaction::session_message_hash;
+use paymaster_starknet::StarknetAccountConfiguration;
+use starknet::core::types::Felt;
+use starknet::signers::SigningKey;
+
+/// Session registered on a session-based account deployed on the devnet, with the keys allowed to sign for it.
+/// The session, its hash and the authorization of the owner are computed by the tooling of the account when the
+/// session is registered, the scenario only signs the transactions with the session key and the guardian.
+#[derive(Debug, Clone)]
+pub struct SessionKey {
+    pub account: Felt,
+    pub session: Session,
+
+    /// Off-chain message hash of the session as computed by the account
+    pub session_hash: Felt,
+    pub cache_authorization: bool,
+    pub authorization: Vec<Felt>,
+
+    pub session_key: Felt,
+    pub guardian_key: Felt,
+
+    /// Merkle proofs of the calls of the scenario followed by the one of the fee transfer
+    pub proofs: Vec<Vec<Felt>>,
+}
+
+impl SessionKey {
+    /// Read the session from the `PAYMASTER_E2E_SESSION_*` variables, felts being separated by commas and the proofs
+    /// by semicolons. Returns `None` when no session account is configured.
+    pub fn from_env() -> Option<Self> {
+        let account = std::env::var("PAYMASTER_E2E_SESSION_ACCOUNT").ok()?;
+
+        let session = Self::felts("PAYMASTER_E2E_SESSION");
+        Some(Self {
+            account: Self::parse(&account),
+            session: Session {
+                expires_at: session[0].try_into().expect("invalid session expiry"),
+                policies_root: session[1],
+                metadata_hash: session[2],
+                session_key_guid: session[3],
+                guardian_key_guid: session.get(4).copied(),
+            },
+            session_hash: Self::felt("PAYMASTER_E2E_SESSION_HASH"),
+            cache_authorization: false,
+            authorization: Self::felts("PAYMASTER_E2E_SESSION_AUTHORIZATION"),
+            session_key: Self::felt("PAYMASTER_E2E_SESSION_KEY"),
+            guardian_key: Self::felt("PAYMASTER_E2E_GUARDIAN_KEY"),
+            proofs: Self::var("PAYMASTER_E2E_SESSION_PROOFS")
+                .split(';')
+                .map(|x| x.split(',').filter(|x| !x.is_empty()).map(Self::parse).collect())
+                .collect(),
+        })
+    }
+
+    /// Account the session is registered on. Its owner key is never used by the scenarios.
+    pub fn user(&self) -> StarknetAccountConfiguration {
+        StarknetAccountConfiguration {
+            address: self.account,
+            private_key: Felt::ZERO,
+        }
+    }
+
+    /// Sign the outside execution with `message_hash`, returning the signature of the session key along with the
+    /// session to send to the paymaster
+    pub fn sign(&self, message_hash: Felt) -> (Vec<Felt>, SessionAuthorization) {
+        let hash = session_message_hash(message_hash, self.session_hash, self.cache_authorization);
+
+        let session_key = SigningKey::from_secret_scalar(self.session_key);
+        let signature = session_key.sign(&hash).unwrap();
+
+        let guardian_key = SigningKey::from_secret_scalar(self.guardian_key);
+        let guardian_signature = guardian_key.sign(&hash).unwrap();
+
+        let session = SessionAuthorization {
+            session: self.session.clone(),
+            cache_authorization: self.cache_authorization,
+            authorization: self.authorization.clone(),
+            session_key: session_key.verifying_key().scalar(),
+            guardian_signature: SignerSignature {
+                signer: guardian_key.verifying_key().scalar(),
+                r: guardian_signature.r,
+                s: guardian_signature.s,
+            },
+            proofs: self.proofs.clone(),
+        };
+
+        (vec![signature.r, signature.s], session)
+    }
+
+    fn var(name: &str) -> String {
+        std::env::var(name).unwrap_or_else(|_| panic!("{} is required by the session scenarios", name))
+    }
+
+    fn felt(name: &str) -> Felt {
+        Self::parse(&Self::var(name))
+    }
+
+    fn felts(name: &str) -> Vec<Felt> {
+        Self::var(name).split(',').map(Self::parse).collect()
+    }
+
+    fn parse(value: &str) -> Felt {
+        Felt::from_hex(value.trim()).unwrap_or_else(|_| panic!("invalid felt {}", value))
+    }
+}
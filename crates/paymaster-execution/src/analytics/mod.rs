@@ -0,0 +1,464 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use paymaster_common::metric;
+use paymaster_common::service::TokioServiceManager;
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+use starknet::core::utils::starknet_keccak;
+
+use crate::analytics::service::{AnalyticsContext, AnalyticsService};
+use crate::clock::now;
+use crate::execution::FeeQuote;
+use crate::Client;
+
+mod service;
+mod sink;
+
+/// Duration after which an executed transaction which is still not confirmed is not tracked anymore
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// Maximum number of receipts fetched in a single round when checking the confirmation of the executed transactions
+const CONFIRMATION_CHECKS_PER_ROUND: usize = 50;
+
+/// Maximum delay between two checks of the confirmation of a transaction. The delay doubles after every check
+/// which does not find the receipt, starting at one second.
+const CONFIRMATION_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Configuration of the stream of analytics events emitted for every transaction built, executed and confirmed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AnalyticsConfiguration {
+    pub sink: AnalyticsSinkConfiguration,
+
+    /// Maximum number of events sent in a single request
+    #[serde(default = "AnalyticsConfiguration::default_batch_size")]
+    pub batch_size: usize,
+
+    /// Interval in seconds between two deliveries of the pending events
+    #[serde(default = "AnalyticsConfiguration::default_flush_interval")]
+    pub flush_interval: u64,
+
+    /// Maximum number of events waiting to be delivered. Events published while the queue is full are dropped.
+    #[serde(default = "AnalyticsConfiguration::default_queue_capacity")]
+    pub queue_capacity: usize,
+}
+
+impl AnalyticsConfiguration {
+    fn default_batch_size() -> usize {
+        100
+    }
+
+    fn default_flush_interval() -> u64 {
+        5
+    }
+
+    fn default_queue_capacity() -> usize {
+        10_000
+    }
+}
+
+impl Validate for AnalyticsConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.field("sink", &self.sink);
+        report.ensure(self.batch_size > 0, "batch_size", "must be greater than 0");
+        report.ensure(self.flush_interval > 0, "flush_interval", "must be greater than 0");
+        report.ensure(
+            self.queue_capacity >= self.batch_size,
+            "queue_capacity",
+            "must be greater than or equal to batch_size",
+        );
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnalyticsSinkConfiguration {
+    /// Events are posted as a JSON array to the given endpoint
+    Webhook {
+        endpoint: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+
+    /// Events are produced to the given topic through a Kafka REST proxy
+    Kafka {
+        rest_proxy: String,
+        topic: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+}
+
+impl Validate for AnalyticsSinkConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        match self {
+            Self::Webhook { endpoint, .. } => report.ensure(!endpoint.is_empty(), "endpoint", "must not be empty"),
+            Self::Kafka { rest_proxy, topic, .. } => {
+                report.ensure(!rest_proxy.is_empty(), "rest_proxy", "must not be empty");
+                report.ensure(!topic.is_empty(), "topic", "must not be empty");
+            },
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AnalyticsEventKind {
+    Build,
+    Execute,
+    Confirmation {
+        reverted: bool,
+        #[serde_as(as = "UfeHex")]
+        actual_fee_in_strk: Felt,
+    },
+}
+
+/// Normalized event describing the activity of the paymaster for a transaction
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnalyticsEvent {
+    #[serde(flatten)]
+    pub kind: AnalyticsEventKind,
+
+    pub chain_id: String,
+
+    #[serde_as(as = "UfeHex")]
+    pub user: Felt,
+
+    /// Fingerprint of the api key of the sponsor, the api key itself is never published
+    pub sponsor: Option<String>,
+
+    #[serde_as(as = "UfeHex")]
+    pub gas_token: Felt,
+    #[serde_as(as = "UfeHex")]
+    pub fee_in_token: Felt,
+    #[serde_as(as = "UfeHex")]
+    pub fee_in_strk: Felt,
+
+    #[serde_as(as = "Option<UfeHex>")]
+    pub transaction_hash: Option<Felt>,
+
+    /// Time taken by the request or, for confirmations, between the execution and the inclusion of the transaction
+    pub latency_ms: u64,
+
+    /// Unix timestamp (in seconds) at which the event was emitted
+    pub timestamp: u64,
+}
+
+impl AnalyticsEvent {
+    pub fn new(kind: AnalyticsEventKind, chain_id: ChainID, user: Felt, quote: FeeQuote, latency: Duration) -> Self {
+        Self {
+            kind,
+            chain_id: chain_id.as_identifier(),
+            user,
+            sponsor: None,
+            gas_token: quote.gas_token,
+            fee_in_token: quote.fee_in_token,
+            fee_in_strk: quote.fee_in_strk,
+            transaction_hash: None,
+            latency_ms: latency.as_millis() as u64,
+            timestamp: now(),
+        }
+    }
+
+    /// Attribute the event to the sponsor with the given api key
+    pub fn with_sponsor(mut self, api_key: &str) -> Self {
+        self.sponsor = Some(starknet_keccak(api_key.as_bytes()).to_fixed_hex_string());
+        self
+    }
+
+    pub fn with_transaction_hash(mut self, transaction_hash: Felt) -> Self {
+        self.transaction_hash = Some(transaction_hash);
+        self
+    }
+
+    fn quote(&self) -> FeeQuote {
+        FeeQuote {
+            gas_token: self.gas_token,
+            fee_in_token: self.fee_in_token,
+            fee_in_strk: self.fee_in_strk,
+        }
+    }
+
+    /// Returns the confirmation event of this execution event given the outcome of the transaction
+    pub fn confirmation(&self, reverted: bool, actual_fee_in_strk: Felt, latency: Duration) -> Self {
+        Self {
+            kind: AnalyticsEventKind::Confirmation { reverted, actual_fee_in_strk },
+            latency_ms: latency.as_millis() as u64,
+            timestamp: now(),
+            ..self.clone()
+        }
+    }
+}
+
+struct PendingConfirmation {
+    event: AnalyticsEvent,
+    executed_at: Instant,
+
+    next_check: Instant,
+    checks: u32,
+}
+
+#[derive(Default)]
+struct Queue {
+    events: VecDeque<AnalyticsEvent>,
+
+    // Executions waiting for their confirmation
+    pending: HashMap<Felt, PendingConfirmation>,
+}
+
+/// In-memory queue of the events waiting to be delivered
+#[derive(Clone)]
+pub struct AnalyticsQueue {
+    capacity: usize,
+    inner: Arc<RwLock<Queue>>,
+}
+
+impl AnalyticsQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, inner: Arc::default() }
+    }
+
+    /// Add an event to the queue. Returns false if the queue is full in which case the event is dropped.
+    pub fn push(&self, event: AnalyticsEvent) -> bool {
+        let mut queue = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        if queue.events.len() >= self.capacity {
+            return false;
+        }
+
+        queue.events.push_back(event);
+        true
+    }
+
+    /// Remove and return up to `count` events, oldest first
+    pub fn take(&self, count: usize) -> Vec<AnalyticsEvent> {
+        let mut queue = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let count = count.min(queue.events.len());
+
+        queue.events.drain(..count).collect()
+    }
+
+    /// Put back at the front of the queue events which could not be delivered, as long as there is room for them
+    pub fn restore(&self, events: Vec<AnalyticsEvent>) {
+        let mut queue = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let room = self.capacity.saturating_sub(queue.events.len());
+        for event in events.into_iter().take(room).rev() {
+            queue.events.push_front(event);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap_or_else(|e| e.into_inner()).events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Track an execution so its confirmation event is emitted once the transaction is included
+    fn track(&self, transaction_hash: Felt, event: AnalyticsEvent) {
+        let now = Instant::now();
+
+        let mut queue = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        queue.pending.insert(
+            transaction_hash,
+            PendingConfirmation {
+                event,
+                executed_at: now,
+                next_check: now,
+                checks: 0,
+            },
+        );
+    }
+
+    /// Returns up to `limit` executions whose confirmation is due to be checked at `now`, the ones waiting for the
+    /// longest first. Executions tracked for too long are forgotten.
+    fn due(&self, now: Instant, limit: usize) -> Vec<(Felt, AnalyticsEvent, Instant)> {
+        let mut queue = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        queue
+            .pending
+            .retain(|_, x| now.saturating_duration_since(x.executed_at) < CONFIRMATION_TIMEOUT);
+
+        let mut due: Vec<_> = queue.pending.iter().filter(|(_, x)| x.next_check <= now).collect();
+        due.sort_by_key(|(_, x)| x.next_check);
+        due.into_iter()
+            .take(limit)
+            .map(|(hash, x)| (*hash, x.event.clone(), x.executed_at))
+            .collect()
+    }
+
+    /// Delays the next check of an execution whose confirmation was not found at `now`
+    fn postpone(&self, transaction_hash: Felt, now: Instant) {
+        let mut queue = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(pending) = queue.pending.get_mut(&transaction_hash) {
+            let backoff = Duration::from_secs(1 << pending.checks.min(6)).min(CONFIRMATION_MAX_BACKOFF);
+
+            pending.checks += 1;
+            pending.next_check = now + backoff;
+        }
+    }
+
+    fn confirm(&self, transaction_hash: Felt) {
+        let mut queue = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        queue.pending.remove(&transaction_hash);
+    }
+}
+
+/// Publishes the analytics events to the configured sink. When no configuration is given, events are discarded.
+#[derive(Clone)]
+pub struct AnalyticsPublisher {
+    queue: Option<AnalyticsQueue>,
+
+    #[allow(dead_code)]
+    services: Option<Arc<TokioServiceManager<AnalyticsContext>>>,
+}
+
+impl AnalyticsPublisher {
+    pub fn new(client: &Client, configuration: Option<&AnalyticsConfiguration>) -> Self {
+        let Some(configuration) = configuration else {
+            return Self { queue: None, services: None };
+        };
+
+        let queue = AnalyticsQueue::new(configuration.queue_capacity);
+
+        let mut services = TokioServiceManager::new(AnalyticsContext {
+            client: client.clone(),
+            queue: queue.clone(),
+            configuration: configuration.clone(),
+        });
+        services.spawn::<AnalyticsService>();
+
+        Self {
+            queue: Some(queue),
+            services: Some(Arc::new(services)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.queue.is_some()
+    }
+
+    /// Publish an event. Execution events are also tracked so that their confirmation is published once the
+    /// transaction is included.
+    pub fn publish(&self, event: AnalyticsEvent) {
+        let Some(queue) = &self.queue else { return };
+
+        if let (AnalyticsEventKind::Execute, Some(transaction_hash)) = (&event.kind, event.transaction_hash) {
+            queue.track(transaction_hash, event.clone());
+        }
+
+        if !queue.push(event) {
+            metric!(counter[analytics_event_dropped] = 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use paymaster_starknet::ChainID;
+    use starknet::core::types::Felt;
+
+    use crate::analytics::{AnalyticsEvent, AnalyticsEventKind, AnalyticsQueue, CONFIRMATION_CHECKS_PER_ROUND};
+    use crate::execution::FeeQuote;
+
+    fn an_event(user: u64) -> AnalyticsEvent {
+        let quote = FeeQuote {
+            gas_token: Felt::ONE,
+            fee_in_token: Felt::from(10),
+            fee_in_strk: Felt::from(20),
+        };
+
+        AnalyticsEvent::new(AnalyticsEventKind::Build, ChainID::Sepolia, Felt::from(user), quote, Duration::from_millis(150))
+    }
+
+    #[test]
+    fn event_is_normalized() {
+        let event = an_event(1).with_sponsor("my-api-key").with_transaction_hash(Felt::TWO);
+
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["event"], "build");
+        assert_eq!(value["chain_id"], "SN_SEPOLIA");
+        assert_eq!(value["transaction_hash"], "0x2");
+        assert_eq!(value["latency_ms"], 150);
+        assert!(!value["sponsor"].as_str().unwrap().contains("my-api-key"));
+
+        let confirmation = event.confirmation(true, Felt::from(15), Duration::from_secs(3));
+        let value = serde_json::to_value(&confirmation).unwrap();
+        assert_eq!(value["event"], "confirmation");
+        assert_eq!(value["reverted"], true);
+        assert_eq!(value["actual_fee_in_strk"], "0xf");
+        assert_eq!(value["fee_in_strk"], "0x14");
+    }
+
+    #[test]
+    fn queue_drops_events_when_full_and_restores_undelivered_ones() {
+        let queue = AnalyticsQueue::new(3);
+        assert!(queue.push(an_event(1)));
+        assert!(queue.push(an_event(2)));
+        assert!(queue.push(an_event(3)));
+        assert!(!queue.push(an_event(4)));
+
+        let batch = queue.take(2);
+        assert_eq!(batch.iter().map(|x| x.user).collect::<Vec<_>>(), vec![Felt::from(1), Felt::from(2)]);
+
+        // Only one of the events can be restored as the queue received a new one in the meantime
+        assert!(queue.push(an_event(5)));
+        queue.restore(batch);
+
+        let events = queue.take(10);
+        assert_eq!(events.iter().map(|x| x.user).collect::<Vec<_>>(), vec![Felt::from(1), Felt::from(3), Felt::from(5)]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn confirmations_not_found_are_checked_again_with_backoff() {
+        let queue = AnalyticsQueue::new(10);
+        queue.track(Felt::ONE, an_event(1));
+
+        let now = Instant::now();
+        assert_eq!(queue.due(now, CONFIRMATION_CHECKS_PER_ROUND).len(), 1);
+
+        queue.postpone(Felt::ONE, now);
+        assert!(queue.due(now, CONFIRMATION_CHECKS_PER_ROUND).is_empty());
+        assert_eq!(queue.due(now + Duration::from_secs(1), CONFIRMATION_CHECKS_PER_ROUND).len(), 1);
+
+        // The delay doubles after every miss
+        let now = now + Duration::from_secs(1);
+        queue.postpone(Felt::ONE, now);
+        assert!(queue
+            .due(now + Duration::from_secs(1), CONFIRMATION_CHECKS_PER_ROUND)
+            .is_empty());
+        assert_eq!(queue.due(now + Duration::from_secs(2), CONFIRMATION_CHECKS_PER_ROUND).len(), 1);
+
+        queue.confirm(Felt::ONE);
+        assert!(queue
+            .due(now + Duration::from_secs(2), CONFIRMATION_CHECKS_PER_ROUND)
+            .is_empty());
+    }
+
+    #[test]
+    fn confirmation_checks_are_capped_per_round() {
+        let queue = AnalyticsQueue::new(10);
+        for hash in 0..5u64 {
+            queue.track(Felt::from(hash), an_event(hash));
+        }
+
+        let now = Instant::now();
+        let due = queue.due(now, 3);
+        assert_eq!(due.len(), 3);
+
+        // The executions checked are postponed so the next round checks the others
+        for (hash, _, _) in due {
+            queue.postpone(hash, now);
+        }
+        assert_eq!(queue.due(now, 3).len(), 2);
+    }
+}
@@ -0,0 +1,102 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use paymaster_common::metric;
+use paymaster_common::service::{Error as ServiceError, Service};
+use tokio::time::interval;
+use tracing::error;
+
+use crate::analytics::sink::AnalyticsSink;
+use crate::analytics::{AnalyticsConfiguration, AnalyticsQueue, CONFIRMATION_CHECKS_PER_ROUND};
+use crate::Client;
+
+#[derive(Clone)]
+pub struct AnalyticsContext {
+    pub client: Client,
+    pub queue: AnalyticsQueue,
+
+    pub configuration: AnalyticsConfiguration,
+}
+
+/// Service which periodically publishes the confirmation of the executed transactions and delivers the
+/// queued events to the sink in batches. Events which cannot be delivered are retried on the next round.
+pub struct AnalyticsService {
+    context: AnalyticsContext,
+    sink: AnalyticsSink,
+}
+
+#[async_trait]
+impl Service for AnalyticsService {
+    type Context = AnalyticsContext;
+
+    const NAME: &'static str = "AnalyticsService";
+
+    async fn new(context: AnalyticsContext) -> Self {
+        Self {
+            sink: AnalyticsSink::new(&context.configuration.sink),
+            context,
+        }
+    }
+
+    async fn run(self) -> Result<(), ServiceError> {
+        let mut ticker = interval(Duration::from_secs(self.context.configuration.flush_interval));
+        loop {
+            ticker.tick().await;
+
+            self.publish_confirmations().await;
+            self.flush().await;
+        }
+    }
+}
+
+impl AnalyticsService {
+    // Only a bounded number of receipts is fetched per round, the transactions whose receipt is not found yet are
+    // checked again with an increasing delay
+    async fn publish_confirmations(&self) {
+        let due = self.context.queue.due(Instant::now(), CONFIRMATION_CHECKS_PER_ROUND);
+        for (transaction_hash, event, executed_at) in due {
+            let receipt = match self
+                .context
+                .client
+                .fetch_execution_receipt(transaction_hash, event.quote())
+                .await
+            {
+                Ok(Some(receipt)) => receipt,
+                Ok(None) => {
+                    self.context.queue.postpone(transaction_hash, Instant::now());
+                    continue;
+                },
+                Err(e) => {
+                    error!("Failed to fetch receipt of {}: {}", transaction_hash.to_fixed_hex_string(), e);
+                    self.context.queue.postpone(transaction_hash, Instant::now());
+                    continue;
+                },
+            };
+
+            self.context.queue.confirm(transaction_hash);
+
+            let confirmation = event.confirmation(receipt.is_reverted(), receipt.actual_fee_in_strk, executed_at.elapsed());
+            if !self.context.queue.push(confirmation) {
+                metric!(counter[analytics_event_dropped] = 1);
+            }
+        }
+    }
+
+    async fn flush(&self) {
+        while !self.context.queue.is_empty() {
+            let events = self.context.queue.take(self.context.configuration.batch_size);
+            match self.sink.deliver(&events).await {
+                Ok(()) => {
+                    metric!(counter[analytics_event_delivered] = events.len() as u64);
+                },
+                Err(e) => {
+                    error!("Failed to deliver {} analytics events, retrying next round: {}", events.len(), e);
+                    metric!(counter[analytics_delivery_failure] = 1);
+
+                    self.context.queue.restore(events);
+                    return;
+                },
+            }
+        }
+    }
+}
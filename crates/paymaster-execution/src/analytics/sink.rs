@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use paymaster_common::egress;
+use paymaster_common::service::Error as ServiceError;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::Client;
+use serde_json::json;
+
+use crate::analytics::{AnalyticsEvent, AnalyticsSinkConfiguration};
+
+/// Content type of the records produced through the Kafka REST proxy
+const KAFKA_JSON_CONTENT_TYPE: &str = "application/vnd.kafka.json.v2+json";
+
+/// Delivers batches of analytics events to a webhook or to a Kafka topic through its REST proxy
+pub struct AnalyticsSink {
+    client: Client,
+    configuration: AnalyticsSinkConfiguration,
+    headers: HeaderMap,
+}
+
+impl AnalyticsSink {
+    pub fn new(configuration: &AnalyticsSinkConfiguration) -> Self {
+        let client = egress::apply(Client::builder().timeout(Duration::from_secs(10)))
+            .build()
+            .expect("Failed to build HTTP client");
+
+        let headers = match configuration {
+            AnalyticsSinkConfiguration::Webhook { headers, .. } | AnalyticsSinkConfiguration::Kafka { headers, .. } => headers
+                .iter()
+                .filter_map(|(k, v)| {
+                    let name = HeaderName::from_bytes(k.as_bytes()).ok()?;
+                    let value = HeaderValue::from_str(v).ok()?;
+                    Some((name, value))
+                })
+                .collect::<HeaderMap>(),
+        };
+
+        Self {
+            client,
+            configuration: configuration.clone(),
+            headers,
+        }
+    }
+
+    pub async fn deliver(&self, events: &[AnalyticsEvent]) -> Result<(), ServiceError> {
+        let request = match &self.configuration {
+            AnalyticsSinkConfiguration::Webhook { endpoint, .. } => self.client.post(endpoint).json(events),
+            AnalyticsSinkConfiguration::Kafka { rest_proxy, topic, .. } => {
+                // Records are keyed by user so that the events of a user keep their order within a partition
+                let records: Vec<_> = events
+                    .iter()
+                    .map(|event| json!({ "key": event.user.to_fixed_hex_string(), "value": event }))
+                    .collect();
+
+                self.client
+                    .post(format!("{}/topics/{}", rest_proxy.trim_end_matches('/'), topic))
+                    .header(CONTENT_TYPE, KAFKA_JSON_CONTENT_TYPE)
+                    .body(json!({ "records": records }).to_string())
+            },
+        };
+
+        let request = request
+            .headers(self.headers.clone())
+            .build()
+            .map_err(|e| ServiceError::new(&e.to_string()))?;
+        egress::ensure_allowed(request.url().as_str()).map_err(|e| ServiceError::new(&e.to_string()))?;
+
+        let response = self
+            .client
+            .execute(request)
+            .await
+            .map_err(|e| ServiceError::new(&e.to_string()))?;
+
+        match response.error_for_status() {
+            Ok(_) => Ok(()),
+            Err(e) => Err(ServiceError::new(&e.to_string())),
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use hmac::{Hmac, Mac};
+use paymaster_common::service::TokioServiceManager;
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use sha2::Sha256;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+
+use crate::callback::service::{CallbackContext, CallbackService};
+use crate::clock::now;
+use crate::execution::ExecutionReceipt;
+use crate::Client;
+
+mod service;
+
+/// Header carrying the HMAC-SHA256 signature of the payload, computed with the secret of the sponsor
+pub const SIGNATURE_HEADER: &str = "X-Paymaster-Signature";
+
+/// Delay before the first retry of a failed delivery, doubled after each attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Maximum delay between two attempts of a delivery
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Duration after which a submitted transaction which is still not confirmed is not tracked anymore
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// Configuration of the callbacks notifying the sponsors of the lifecycle of their sponsored transactions
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CallbacksConfiguration {
+    /// Callbacks of the sponsors indexed by api key
+    #[serde(default)]
+    pub sponsors: HashMap<String, SponsorCallbackConfiguration>,
+
+    /// Number of attempts after which a notification which cannot be delivered is dropped
+    #[serde(default = "CallbacksConfiguration::default_max_attempts")]
+    pub max_attempts: u32,
+
+    /// Interval in seconds between two checks of the submitted transactions and of the pending notifications
+    #[serde(default = "CallbacksConfiguration::default_check_interval")]
+    pub check_interval: u64,
+}
+
+impl CallbacksConfiguration {
+    fn default_max_attempts() -> u32 {
+        8
+    }
+
+    fn default_check_interval() -> u64 {
+        2
+    }
+}
+
+impl Default for CallbacksConfiguration {
+    fn default() -> Self {
+        Self {
+            sponsors: HashMap::new(),
+            max_attempts: Self::default_max_attempts(),
+            check_interval: Self::default_check_interval(),
+        }
+    }
+}
+
+impl Validate for CallbacksConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.max_attempts > 0, "max_attempts", "must be greater than 0");
+        report.ensure(self.check_interval > 0, "check_interval", "must be greater than 0");
+
+        // Sponsors are indexed by api key which must not leak in the report
+        report.nested("sponsors", |report| {
+            for callback in self.sponsors.values() {
+                report.field("callback", callback);
+            }
+        });
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SponsorCallbackConfiguration {
+    /// URL to which the notifications are posted
+    pub url: String,
+
+    /// Secret used to sign the notifications
+    pub secret: String,
+}
+
+impl Validate for SponsorCallbackConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure_url("url", &self.url);
+        report.ensure(!self.secret.is_empty(), "secret", "must not be empty");
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionLifecycleEvent {
+    Submitted,
+    Confirmed,
+    Reverted,
+}
+
+/// Payload posted to the callback of a sponsor
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallbackPayload {
+    pub event: TransactionLifecycleEvent,
+    pub chain_id: String,
+
+    #[serde_as(as = "UfeHex")]
+    pub transaction_hash: Felt,
+    #[serde_as(as = "UfeHex")]
+    pub user: Felt,
+
+    /// Fee charged to the sponsor in STRK
+    #[serde_as(as = "UfeHex")]
+    pub fee_in_strk: Felt,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+
+    /// Unix timestamp (in seconds) at which the event occurred
+    pub timestamp: u64,
+}
+
+impl CallbackPayload {
+    /// Returns the payload notifying the inclusion of the submitted transaction
+    fn included(&self, receipt: &ExecutionReceipt) -> Self {
+        Self {
+            event: if receipt.is_reverted() {
+                TransactionLifecycleEvent::Reverted
+            } else {
+                TransactionLifecycleEvent::Confirmed
+            },
+            block_number: Some(receipt.block_number),
+            revert_reason: receipt.revert_reason.clone(),
+            timestamp: now(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Returns the hex encoded HMAC-SHA256 signature of `body` using `secret`, as sent in [`SIGNATURE_HEADER`]
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+
+    let signature: String = mac.finalize().into_bytes().iter().map(|x| format!("{:02x}", x)).collect();
+    format!("sha256={}", signature)
+}
+
+/// Notification waiting to be delivered to the callback of a sponsor
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    pub callback: SponsorCallbackConfiguration,
+    pub payload: CallbackPayload,
+
+    pub attempts: u32,
+    pub next_attempt: Instant,
+}
+
+impl Delivery {
+    fn new(callback: SponsorCallbackConfiguration, payload: CallbackPayload) -> Self {
+        Self {
+            callback,
+            payload,
+            attempts: 0,
+            next_attempt: Instant::now(),
+        }
+    }
+
+    /// Record a failed attempt and schedule the next one with an exponential backoff
+    pub fn retry_later(&mut self) {
+        self.attempts += 1;
+
+        let backoff = INITIAL_BACKOFF
+            .saturating_mul(2u32.saturating_pow(self.attempts - 1))
+            .min(MAX_BACKOFF);
+        self.next_attempt = Instant::now() + backoff;
+    }
+}
+
+#[derive(Default)]
+struct Outbox {
+    deliveries: Vec<Delivery>,
+
+    // Submitted transactions waiting for their inclusion
+    pending: HashMap<Felt, (SponsorCallbackConfiguration, CallbackPayload, Instant)>,
+}
+
+/// In-memory outbox of the notifications owed to the sponsors
+#[derive(Clone, Default)]
+pub struct CallbackOutbox {
+    inner: Arc<RwLock<Outbox>>,
+}
+
+impl CallbackOutbox {
+    /// Enqueue the notification of a submitted transaction and track it until it is included
+    pub fn submit(&self, callback: SponsorCallbackConfiguration, payload: CallbackPayload) {
+        let mut outbox = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        outbox
+            .pending
+            .insert(payload.transaction_hash, (callback.clone(), payload.clone(), Instant::now()));
+        outbox.deliveries.push(Delivery::new(callback, payload));
+    }
+
+    /// Returns the submitted transactions waiting for their inclusion, forgetting the ones tracked for too long
+    pub fn pending(&self) -> Vec<Felt> {
+        let mut outbox = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        outbox
+            .pending
+            .retain(|_, (_, _, submitted_at)| submitted_at.elapsed() < CONFIRMATION_TIMEOUT);
+        outbox.pending.keys().copied().collect()
+    }
+
+    /// Stop tracking the given transaction and enqueue the notification of its inclusion
+    pub fn include(&self, receipt: &ExecutionReceipt) {
+        let mut outbox = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        if let Some((callback, payload, _)) = outbox.pending.remove(&receipt.transaction_hash) {
+            outbox.deliveries.push(Delivery::new(callback, payload.included(receipt)));
+        }
+    }
+
+    /// Remove and return the deliveries whose next attempt is due
+    pub fn take_due(&self) -> Vec<Delivery> {
+        let mut outbox = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+
+        let (due, waiting) = std::mem::take(&mut outbox.deliveries)
+            .into_iter()
+            .partition(|x| x.next_attempt <= now);
+        outbox.deliveries = waiting;
+
+        due
+    }
+
+    /// Put back a delivery which must be attempted again
+    pub fn reschedule(&self, delivery: Delivery) {
+        let mut outbox = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        outbox.deliveries.push(delivery);
+    }
+}
+
+/// Notifies the sponsors who registered a callback when their sponsored transactions are submitted,
+/// confirmed or reverted. When no sponsor registered a callback, nothing is tracked.
+#[derive(Clone)]
+pub struct SponsorCallbacks {
+    chain_id: ChainID,
+    sponsors: HashMap<String, SponsorCallbackConfiguration>,
+    outbox: CallbackOutbox,
+
+    #[allow(dead_code)]
+    services: Option<Arc<TokioServiceManager<CallbackContext>>>,
+}
+
+impl SponsorCallbacks {
+    pub fn new(client: &Client, chain_id: ChainID, configuration: &CallbacksConfiguration) -> Self {
+        let outbox = CallbackOutbox::default();
+
+        let services = (!configuration.sponsors.is_empty()).then(|| {
+            let mut services = TokioServiceManager::new(CallbackContext {
+                client: client.clone(),
+                outbox: outbox.clone(),
+                configuration: configuration.clone(),
+            });
+            services.spawn::<CallbackService>();
+
+            Arc::new(services)
+        });
+
+        Self {
+            chain_id,
+            sponsors: configuration.sponsors.clone(),
+            outbox,
+            services,
+        }
+    }
+
+    /// Notify the sponsor with the given api key, if they registered a callback, that a transaction they
+    /// sponsored was submitted
+    pub fn notify_submitted(&self, api_key: &str, user: Felt, transaction_hash: Felt, fee_in_strk: Felt) {
+        let Some(callback) = self.sponsors.get(api_key) else { return };
+
+        let payload = CallbackPayload {
+            event: TransactionLifecycleEvent::Submitted,
+            chain_id: self.chain_id.as_identifier(),
+            transaction_hash,
+            user,
+            fee_in_strk,
+            block_number: None,
+            revert_reason: None,
+            timestamp: now(),
+        };
+        self.outbox.submit(callback.clone(), payload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use paymaster_starknet::constants::ChainTokens;
+    use paymaster_starknet::ChainID;
+    use starknet::core::types::Felt;
+
+    use crate::callback::{sign, CallbackOutbox, CallbackPayload, SponsorCallbackConfiguration, TransactionLifecycleEvent, MAX_BACKOFF};
+    use crate::execution::{ExecutionReceipt, FeeQuote, GasConsumed};
+
+    fn a_payload(transaction_hash: Felt) -> CallbackPayload {
+        CallbackPayload {
+            event: TransactionLifecycleEvent::Submitted,
+            chain_id: "SN_SEPOLIA".to_string(),
+            transaction_hash,
+            user: Felt::ONE,
+            fee_in_strk: Felt::from(100),
+            block_number: None,
+            revert_reason: None,
+            timestamp: 0,
+        }
+    }
+
+    fn a_callback() -> SponsorCallbackConfiguration {
+        SponsorCallbackConfiguration {
+            url: "https://sponsor.example/callback".to_string(),
+            secret: "secret".to_string(),
+        }
+    }
+
+    #[test]
+    fn signature_is_hmac_sha256() {
+        // Reference value from RFC 4231 test case 2
+        let signature = sign("Jefe", b"what do ya want for nothing?");
+
+        assert_eq!(signature, "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    }
+
+    #[test]
+    fn inclusion_is_notified_after_submission() {
+        let outbox = CallbackOutbox::default();
+        outbox.submit(a_callback(), a_payload(Felt::TWO));
+        assert_eq!(outbox.pending(), vec![Felt::TWO]);
+
+        let receipt = ExecutionReceipt {
+            transaction_hash: Felt::TWO,
+            block_number: 12,
+            revert_reason: Some("out of gas".to_string()),
+            gas_consumed: GasConsumed::default(),
+            actual_fee_in_strk: Felt::from(80),
+            quote: FeeQuote::sponsored(&ChainTokens::starknet(&ChainID::Sepolia), Felt::from(100)),
+        };
+        outbox.include(&receipt);
+        assert!(outbox.pending().is_empty());
+
+        let deliveries = outbox.take_due();
+        let events: Vec<_> = deliveries.iter().map(|x| x.payload.event).collect();
+        assert_eq!(events, vec![TransactionLifecycleEvent::Submitted, TransactionLifecycleEvent::Reverted]);
+        assert_eq!(deliveries[1].payload.block_number, Some(12));
+        assert!(outbox.take_due().is_empty());
+    }
+
+    #[test]
+    fn failed_delivery_is_retried_with_backoff() {
+        let outbox = CallbackOutbox::default();
+        outbox.submit(a_callback(), a_payload(Felt::TWO));
+
+        let mut delivery = outbox.take_due().remove(0);
+        delivery.retry_later();
+        assert_eq!(delivery.attempts, 1);
+        assert!(delivery.next_attempt > Instant::now() + Duration::from_secs(1));
+
+        outbox.reschedule(delivery.clone());
+        assert!(outbox.take_due().is_empty());
+
+        for _ in 0..20 {
+            delivery.retry_later();
+        }
+        assert!(delivery.next_attempt <= Instant::now() + MAX_BACKOFF);
+    }
+}
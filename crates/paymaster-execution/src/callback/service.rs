@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use paymaster_common::service::{Error as ServiceError, Service};
+use paymaster_common::{egress, metric};
+use reqwest::header::CONTENT_TYPE;
+use starknet::core::types::Felt;
+use tokio::time::interval;
+use tracing::{error, warn};
+
+use crate::callback::{sign, CallbackOutbox, CallbacksConfiguration, Delivery, SIGNATURE_HEADER};
+use crate::execution::FeeQuote;
+use crate::Client;
+
+#[derive(Clone)]
+pub struct CallbackContext {
+    pub client: Client,
+    pub outbox: CallbackOutbox,
+
+    pub configuration: CallbacksConfiguration,
+}
+
+/// Service which watches the submitted sponsored transactions until their inclusion and delivers the
+/// notifications to the callbacks of the sponsors, retrying the failed deliveries with an exponential backoff.
+pub struct CallbackService {
+    context: CallbackContext,
+    http: reqwest::Client,
+}
+
+#[async_trait]
+impl Service for CallbackService {
+    type Context = CallbackContext;
+
+    const NAME: &'static str = "CallbackService";
+
+    async fn new(context: CallbackContext) -> Self {
+        Self {
+            http: egress::apply(reqwest::Client::builder().timeout(Duration::from_secs(5)))
+                .build()
+                .expect("Failed to build HTTP client"),
+            context,
+        }
+    }
+
+    async fn run(self) -> Result<(), ServiceError> {
+        let mut ticker = interval(Duration::from_secs(self.context.configuration.check_interval));
+        loop {
+            ticker.tick().await;
+
+            self.watch_submitted_transactions().await;
+            self.deliver_due_notifications().await;
+        }
+    }
+}
+
+impl CallbackService {
+    async fn watch_submitted_transactions(&self) {
+        for transaction_hash in self.context.outbox.pending() {
+            // Only the inclusion matters here, the quote is not used
+            let quote = FeeQuote::sponsored(self.context.client.starknet.tokens(), Felt::ZERO);
+            match self.context.client.fetch_execution_receipt(transaction_hash, quote).await {
+                Ok(Some(receipt)) => self.context.outbox.include(&receipt),
+                Ok(None) => continue,
+                Err(e) => error!("Failed to fetch receipt of {}: {}", transaction_hash.to_fixed_hex_string(), e),
+            }
+        }
+    }
+
+    async fn deliver_due_notifications(&self) {
+        for mut delivery in self.context.outbox.take_due() {
+            match self.deliver(&delivery).await {
+                Ok(()) => metric!(counter[sponsor_callback] = 1, status = "delivered"),
+                Err(e) => {
+                    delivery.retry_later();
+                    if delivery.attempts >= self.context.configuration.max_attempts {
+                        error!(
+                            "Dropping notification of {} after {} attempts: {}",
+                            delivery.payload.transaction_hash.to_fixed_hex_string(),
+                            delivery.attempts,
+                            e
+                        );
+                        metric!(counter[sponsor_callback] = 1, status = "dropped");
+                        continue;
+                    }
+
+                    warn!("Failed to notify {}, retrying later: {}", delivery.payload.transaction_hash.to_fixed_hex_string(), e);
+                    metric!(counter[sponsor_callback] = 1, status = "retried");
+                    self.context.outbox.reschedule(delivery);
+                },
+            }
+        }
+    }
+
+    async fn deliver(&self, delivery: &Delivery) -> Result<(), ServiceError> {
+        egress::ensure_allowed(&delivery.callback.url).map_err(|e| ServiceError::new(&e.to_string()))?;
+        let body = serde_json::to_vec(&delivery.payload).map_err(|e| ServiceError::new(&e.to_string()))?;
+
+        let response = self
+            .http
+            .post(&delivery.callback.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, sign(&delivery.callback.secret, &body))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| ServiceError::new(&e.to_string()))?;
+
+        match response.error_for_status() {
+            Ok(_) => Ok(()),
+            Err(e) => Err(ServiceError::new(&e.to_string())),
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use paymaster_common::metric;
+use paymaster_common::service::TokioServiceManager;
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_starknet::math::denormalize_felt;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+use starknet::core::utils::starknet_keccak;
+
+use crate::cost::service::{CostAttributionContext, CostAttributionService};
+use crate::execution::{ExecutionReceipt, FeeQuote};
+use crate::Client;
+
+mod service;
+
+/// Duration after which an executed transaction which is still not confirmed is not tracked anymore
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// Label of the transactions whose fee is paid by the user
+const NO_SPONSOR: &str = "none";
+
+/// Configuration of the metrics attributing the fees collected, the STRK spent and the margin realized
+/// to the sponsors and gas tokens
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CostAttributionConfiguration {
+    /// Interval in seconds between two checks of the executed transactions
+    #[serde(default = "CostAttributionConfiguration::default_check_interval")]
+    pub check_interval: u64,
+}
+
+impl CostAttributionConfiguration {
+    fn default_check_interval() -> u64 {
+        10
+    }
+}
+
+impl Validate for CostAttributionConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.check_interval > 0, "check_interval", "must be greater than 0");
+    }
+}
+
+/// Labels under which the cost of a transaction is reported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CostLabels {
+    /// Fingerprint of the api key of the sponsor, the api key itself is never exported
+    pub sponsor: String,
+    pub gas_token: String,
+}
+
+impl CostLabels {
+    pub fn new(api_key: Option<&str>, quote: &FeeQuote) -> Self {
+        Self {
+            sponsor: api_key
+                .map(|x| starknet_keccak(x.as_bytes()).to_fixed_hex_string())
+                .unwrap_or(NO_SPONSOR.to_string()),
+            gas_token: quote.gas_token.to_fixed_hex_string(),
+        }
+    }
+}
+
+/// Executed transactions waiting for their confirmation along with their labels and the instant they were executed
+#[derive(Clone, Default)]
+pub struct PendingCosts {
+    inner: Arc<RwLock<HashMap<Felt, (CostLabels, FeeQuote, Instant)>>>,
+}
+
+impl PendingCosts {
+    pub fn track(&self, transaction_hash: Felt, labels: CostLabels, quote: FeeQuote) {
+        let mut pending = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        pending.insert(transaction_hash, (labels, quote, Instant::now()));
+    }
+
+    /// Returns the transactions waiting for their confirmation, forgetting the ones tracked for too long
+    pub fn pending(&self) -> Vec<(Felt, FeeQuote)> {
+        let mut pending = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        pending.retain(|_, (_, _, executed_at)| executed_at.elapsed() < CONFIRMATION_TIMEOUT);
+        pending.iter().map(|(hash, (_, quote, _))| (*hash, *quote)).collect()
+    }
+
+    /// Stop tracking the transaction of the given receipt and returns its labels
+    pub fn confirm(&self, receipt: &ExecutionReceipt) -> Option<CostLabels> {
+        let mut pending = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        pending.remove(&receipt.transaction_hash).map(|(labels, _, _)| labels)
+    }
+}
+
+/// Reports the fees collected when the transactions are executed, then the STRK actually spent and the margin
+/// realized once they are confirmed, labeled by sponsor and gas token. When no configuration is given, nothing is reported.
+#[derive(Clone)]
+pub struct CostAttribution {
+    pending: Option<PendingCosts>,
+
+    #[allow(dead_code)]
+    services: Option<Arc<TokioServiceManager<CostAttributionContext>>>,
+}
+
+impl CostAttribution {
+    pub fn new(client: &Client, configuration: Option<&CostAttributionConfiguration>) -> Self {
+        let Some(configuration) = configuration else {
+            return Self { pending: None, services: None };
+        };
+
+        let pending = PendingCosts::default();
+
+        let mut services = TokioServiceManager::new(CostAttributionContext {
+            client: client.clone(),
+            pending: pending.clone(),
+            configuration: configuration.clone(),
+        });
+        services.spawn::<CostAttributionService>();
+
+        Self {
+            pending: Some(pending),
+            services: Some(Arc::new(services)),
+        }
+    }
+
+    /// Report the fee collected for a transaction executed with the given `quote`, sponsored by the sponsor with
+    /// the given api key if any, and track it until its confirmation
+    pub fn record(&self, transaction_hash: Felt, api_key: Option<&str>, quote: FeeQuote) {
+        let Some(pending) = &self.pending else { return };
+
+        let labels = CostLabels::new(api_key, &quote);
+        metric!(
+            counter[cost_fee_collected_in_strk] = denormalize_felt(quote.fee_in_strk, 18),
+            sponsor = labels.sponsor.as_str(),
+            gas_token = labels.gas_token.as_str()
+        );
+
+        pending.track(transaction_hash, labels, quote);
+    }
+}
+
+/// Report the STRK spent and the margin realized for a confirmed transaction
+fn report_confirmation(labels: &CostLabels, receipt: &ExecutionReceipt) {
+    metric!(
+        counter[cost_strk_spent] = denormalize_felt(receipt.actual_fee_in_strk, 18),
+        sponsor = labels.sponsor.as_str(),
+        gas_token = labels.gas_token.as_str()
+    );
+    metric!(
+        updown_counter[cost_margin_in_strk] = receipt.provider_margin_in_strk() as f64 / 1e18,
+        sponsor = labels.sponsor.as_str(),
+        gas_token = labels.gas_token.as_str()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use crate::cost::{CostLabels, PendingCosts, NO_SPONSOR};
+    use crate::execution::{ExecutionReceipt, FeeQuote, GasConsumed};
+
+    fn a_quote() -> FeeQuote {
+        FeeQuote {
+            gas_token: Felt::TWO,
+            fee_in_token: Felt::from(42),
+            fee_in_strk: Felt::from(100),
+        }
+    }
+
+    fn a_receipt(transaction_hash: Felt) -> ExecutionReceipt {
+        ExecutionReceipt {
+            transaction_hash,
+            block_number: 1,
+            revert_reason: None,
+            gas_consumed: GasConsumed::default(),
+            actual_fee_in_strk: Felt::from(80),
+            quote: a_quote(),
+        }
+    }
+
+    #[test]
+    fn sponsors_are_labeled_by_fingerprint() {
+        let labels = CostLabels::new(Some("paymaster_123"), &a_quote());
+        assert_ne!(labels.sponsor, "paymaster_123");
+        assert_eq!(labels.gas_token, Felt::TWO.to_fixed_hex_string());
+
+        let labels = CostLabels::new(None, &a_quote());
+        assert_eq!(labels.sponsor, NO_SPONSOR);
+    }
+
+    #[test]
+    fn confirmed_transactions_are_not_tracked_anymore() {
+        let pending = PendingCosts::default();
+        pending.track(Felt::ONE, CostLabels::new(None, &a_quote()), a_quote());
+        pending.track(Felt::TWO, CostLabels::new(Some("paymaster_123"), &a_quote()), a_quote());
+
+        assert_eq!(pending.pending().len(), 2);
+        assert_eq!(pending.confirm(&a_receipt(Felt::ONE)), Some(CostLabels::new(None, &a_quote())));
+        assert_eq!(pending.confirm(&a_receipt(Felt::ONE)), None);
+        assert_eq!(pending.pending(), vec![(Felt::TWO, a_quote())]);
+    }
+}
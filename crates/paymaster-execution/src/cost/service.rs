@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use paymaster_common::service::{Error as ServiceError, Service};
+use tokio::time::interval;
+use tracing::error;
+
+use crate::cost::{report_confirmation, CostAttributionConfiguration, PendingCosts};
+use crate::Client;
+
+#[derive(Clone)]
+pub struct CostAttributionContext {
+    pub client: Client,
+    pub pending: PendingCosts,
+
+    pub configuration: CostAttributionConfiguration,
+}
+
+/// Service which periodically checks the executed transactions and reports the cost of the ones confirmed
+pub struct CostAttributionService {
+    context: CostAttributionContext,
+}
+
+#[async_trait]
+impl Service for CostAttributionService {
+    type Context = CostAttributionContext;
+
+    const NAME: &'static str = "CostAttributionService";
+
+    async fn new(context: CostAttributionContext) -> Self {
+        Self { context }
+    }
+
+    async fn run(self) -> Result<(), ServiceError> {
+        let mut ticker = interval(Duration::from_secs(self.context.configuration.check_interval));
+        loop {
+            ticker.tick().await;
+
+            self.report_confirmed_transactions().await;
+        }
+    }
+}
+
+impl CostAttributionService {
+    async fn report_confirmed_transactions(&self) {
+        for (transaction_hash, quote) in self.context.pending.pending() {
+            let receipt = match self.context.client.fetch_execution_receipt(transaction_hash, quote).await {
+                Ok(Some(receipt)) => receipt,
+                Ok(None) => continue,
+                Err(e) => {
+                    error!("Failed to fetch receipt of {}: {}", transaction_hash.to_fixed_hex_string(), e);
+                    continue;
+                },
+            };
+
+            if let Some(labels) = self.context.pending.confirm(&receipt) {
+                report_confirmation(&labels, &receipt);
+            }
+        }
+    }
+}
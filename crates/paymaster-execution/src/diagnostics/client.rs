@@ -3,9 +3,11 @@
 use super::context::DiagnosticContext;
 use super::extractor::{CallDiagnostic, CallMetadataExtractor};
 use super::extractors::{AvnuExtractor, AVNU_EXCHANGE_ADDRESS_MAINNET, AVNU_EXCHANGE_ADDRESS_SEPOLIA};
+use super::inspector::{ExecutionDiagnosis, ExecutionInspector};
+use crate::starknet::Client as Starknet;
 use crate::tokens::TokenClient;
 use paymaster_common::metric;
-use paymaster_starknet::transaction::Calls;
+use paymaster_starknet::transaction::{Calls, TokenTransfer};
 use paymaster_starknet::ChainID;
 use starknet::core::types::Felt;
 use std::sync::Arc;
@@ -48,6 +50,20 @@ impl DiagnosticClient {
         }
     }
 
+    /// Inspects the state of the user of a failed execution: gas token balance and allowance against the fee
+    /// it signed for, deployment of its account, nonce and support of its class hash.
+    pub async fn diagnose(&self, starknet: &Starknet, forwarder: Felt, user_address: Felt, fee_transfer: Option<TokenTransfer>) -> ExecutionDiagnosis {
+        let diagnosis = ExecutionInspector::new(starknet, forwarder)
+            .inspect(user_address, fee_transfer)
+            .await;
+
+        if let Ok(json) = serde_json::to_string(&diagnosis) {
+            warn!(diagnosis = %json, "Transaction execution failed");
+        }
+
+        diagnosis
+    }
+
     async fn analyze(&self, context: &DiagnosticContext) -> Vec<CallDiagnostic> {
         let mut diagnostics = Vec::new();
         for extractor in &self.extractors {
@@ -0,0 +1,136 @@
+//! Inspection of the on-chain state of a user whose execution failed.
+
+use paymaster_starknet::transaction::TokenTransfer;
+use serde::Serialize;
+use starknet::core::types::{Felt, FunctionCall};
+use starknet::macros::selector;
+
+use crate::starknet::Client as Starknet;
+
+/// Snapshot of the state of a user explaining, in most cases, why its transaction failed.
+/// Fields are not set when the corresponding value could not be fetched.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionDiagnosis {
+    /// The user's account address
+    pub user_address: Felt,
+
+    /// Whether an account is deployed at the user's address
+    pub deployed: bool,
+
+    /// Class hash of the user's account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class_hash: Option<Felt>,
+
+    /// Whether the class of the user's account supports the paymaster
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class_hash_supported: Option<bool>,
+
+    /// Current nonce of the user's account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<Felt>,
+
+    /// State of the gas token, for the transactions which are not sponsored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_token: Option<GasTokenDiagnosis>,
+}
+
+/// Balance and allowance of the user in the gas token compared to the fee it signed for
+#[derive(Debug, Clone, Serialize)]
+pub struct GasTokenDiagnosis {
+    pub token: Felt,
+
+    /// Amount of gas token transferred, or approved, to the forwarder by the user
+    pub required: Felt,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<Felt>,
+
+    /// Allowance granted by the user to the forwarder
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forwarder_allowance: Option<Felt>,
+}
+
+impl GasTokenDiagnosis {
+    /// Returns true if the balance of the user covers the fee it signed for
+    pub fn has_sufficient_balance(&self) -> Option<bool> {
+        self.balance.map(|balance| balance >= self.required)
+    }
+}
+
+/// Inspects the state of a user given its address, the forwarder and the fee transfer it signed for
+pub(crate) struct ExecutionInspector<'a> {
+    starknet: &'a Starknet,
+    forwarder: Felt,
+}
+
+impl<'a> ExecutionInspector<'a> {
+    pub fn new(starknet: &'a Starknet, forwarder: Felt) -> Self {
+        Self { starknet, forwarder }
+    }
+
+    pub async fn inspect(&self, user_address: Felt, fee_transfer: Option<TokenTransfer>) -> ExecutionDiagnosis {
+        let class_hash = self.starknet.fetch_class_hash_at(user_address).await.ok();
+        let class_hash_supported = match class_hash {
+            Some(class_hash) => Some(self.starknet.resolve_paymaster_version_from_class(class_hash).await.is_ok()),
+            None => None,
+        };
+
+        let gas_token = match fee_transfer {
+            Some(transfer) => Some(self.inspect_gas_token(user_address, transfer).await),
+            None => None,
+        };
+
+        ExecutionDiagnosis {
+            user_address,
+            deployed: class_hash.is_some(),
+            class_hash,
+            class_hash_supported,
+            nonce: self.starknet.fetch_nonce(user_address).await.ok(),
+            gas_token,
+        }
+    }
+
+    async fn inspect_gas_token(&self, user_address: Felt, transfer: TokenTransfer) -> GasTokenDiagnosis {
+        let allowance = self
+            .starknet
+            .call(&FunctionCall {
+                contract_address: transfer.token(),
+                entry_point_selector: selector!("allowance"),
+                calldata: vec![user_address, self.forwarder],
+            })
+            .await
+            .ok()
+            .and_then(|x| x.first().cloned());
+
+        GasTokenDiagnosis {
+            token: transfer.token(),
+            required: transfer.amount(),
+            balance: self.starknet.fetch_balance(transfer.token(), user_address).await.ok(),
+            forwarder_allowance: allowance,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use crate::diagnostics::GasTokenDiagnosis;
+
+    #[test]
+    fn insufficient_balance_is_detected() {
+        let mut diagnosis = GasTokenDiagnosis {
+            token: Felt::ONE,
+            required: Felt::from(100),
+            balance: None,
+            forwarder_allowance: None,
+        };
+        assert_eq!(diagnosis.has_sufficient_balance(), None);
+
+        diagnosis.balance = Some(Felt::from(99));
+        assert_eq!(diagnosis.has_sufficient_balance(), Some(false));
+
+        diagnosis.balance = Some(Felt::from(100));
+        assert_eq!(diagnosis.has_sufficient_balance(), Some(true));
+    }
+}
@@ -13,6 +13,7 @@
 //! - [`CallMetadataExtractor`]: Trait for implementing contract-specific extractors
 //! - [`DiagnosticClient`]: Registry that manages extractors and orchestrates analysis
 //! - [`CallDiagnostic`]: The output containing extracted metadata for logging
+//! - [`ExecutionDiagnosis`]: Balance, allowance, deployment and nonce state of the user of a failed execution
 //!
 //! # Usage
 //!
@@ -34,9 +35,11 @@
 mod client;
 mod context;
 mod extractor;
+mod inspector;
 
 pub mod extractors;
 
 pub use client::DiagnosticClient;
 pub use context::DiagnosticContext;
 pub use extractor::{CallDiagnostic, CallMetadataExtractor, DiagnosticValue};
+pub use inspector::{ExecutionDiagnosis, GasTokenDiagnosis};
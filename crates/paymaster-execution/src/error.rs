@@ -14,6 +14,9 @@ pub enum Error {
     #[error("invalid time bounds")]
     InvalidTimeBound,
 
+    #[error("transaction expires before it can be included")]
+    TransactionExpired,
+
     #[error("no calls specified in invoke")]
     NoCalls,
 
@@ -23,14 +26,27 @@ pub enum Error {
     #[error("max amount of gas token too low. Expected at least {0}")]
     MaxAmountTooLow(String),
 
+    #[error("fee does not cover the execution cost with the minimum margin. Expected at least {0}")]
+    Unprofitable(String),
+
     #[error("execution error {0}")]
     Execution(String),
+
+    #[error("call hook violation {0}")]
+    HookViolation(String),
+
+    #[error("too many executions in progress, retry after {0} seconds")]
+    Busy(u64),
+
+    #[error("account already deployed {0}")]
+    AlreadyDeployed(String),
 }
 
 impl From<paymaster_starknet::Error> for Error {
     fn from(value: paymaster_starknet::Error) -> Self {
         match value {
             paymaster_starknet::Error::InvalidNonce(_) => Self::InvalidVersion,
+            e if e.is_deployment_conflict() => Self::AlreadyDeployed(e.to_string()),
             e => Self::Execution(e.to_string()),
         }
     }
@@ -0,0 +1,96 @@
+use paymaster_starknet::transaction::PaymasterVersion;
+use starknet::core::types::Felt;
+
+use crate::starknet::Client as Starknet;
+use crate::Error;
+
+/// Deployment status of an account and its support of the outside execution (SNIP-9)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountStatus {
+    pub address: Felt,
+
+    /// Class hash of the account, not set when the account is not deployed
+    pub class_hash: Option<Felt>,
+
+    /// Highest SNIP-9 version supported by the account, not set when the account is not deployed
+    /// or does not support outside execution
+    pub outside_execution_version: Option<PaymasterVersion>,
+
+    /// Whether the account is a Cartridge Controller, which also supports concurrent outside executions
+    pub controller: bool,
+}
+
+impl AccountStatus {
+    /// Fetch the status of the account deployed at `address`
+    pub(crate) async fn fetch(starknet: &Starknet, address: Felt) -> Result<Self, Error> {
+        let class_hash = match starknet.fetch_class_hash_at(address).await {
+            Ok(class_hash) => class_hash,
+            Err(paymaster_starknet::Error::ContractNotFound) => return Ok(Self::undeployed(address)),
+            Err(e) => return Err(e.into()),
+        };
+
+        let (outside_execution_version, controller) = tokio::join!(starknet.resolve_paymaster_version_from_account(address), starknet.is_controller_class(class_hash));
+
+        Ok(Self {
+            address,
+            class_hash: Some(class_hash),
+            outside_execution_version: outside_execution_version.ok(),
+            controller: controller.unwrap_or_default(),
+        })
+    }
+
+    fn undeployed(address: Felt) -> Self {
+        Self {
+            address,
+            class_hash: None,
+            outside_execution_version: None,
+            controller: false,
+        }
+    }
+
+    /// Returns true if the account is deployed
+    pub fn is_deployed(&self) -> bool {
+        self.class_hash.is_some()
+    }
+
+    /// Returns true if the account can execute transactions through the paymaster
+    pub fn is_supported(&self) -> bool {
+        self.outside_execution_version.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use paymaster_starknet::transaction::PaymasterVersion;
+    use starknet::core::types::Felt;
+
+    use crate::execution::AccountStatus;
+
+    #[test]
+    fn undeployed_account_is_not_supported() {
+        let status = AccountStatus::undeployed(Felt::ONE);
+
+        assert!(!status.is_deployed());
+        assert!(!status.is_supported());
+    }
+
+    #[test]
+    fn deployed_account_is_supported_when_outside_execution_is() {
+        let status = AccountStatus {
+            address: Felt::ONE,
+            class_hash: Some(Felt::TWO),
+            outside_execution_version: None,
+            controller: false,
+        };
+
+        assert!(status.is_deployed());
+        assert!(!status.is_supported());
+
+        let status = AccountStatus {
+            outside_execution_version: Some(PaymasterVersion::V2),
+            ..status
+        };
+
+        assert!(status.is_supported());
+    }
+}
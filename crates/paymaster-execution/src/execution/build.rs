@@ -1,14 +1,15 @@
-use paymaster_prices::math::convert_strk_to_token;
-use paymaster_starknet::transaction::{Calls, ExecuteFromOutsideMessage, ExecuteFromOutsideParameters, PaymasterVersion, TokenTransfer};
+use paymaster_starknet::transaction::{Calls, ExecuteFromOutsideMessage, ExecuteFromOutsideParameters, PaymasterVersion, TokenPermit, TokenTransfer};
 use paymaster_starknet::{ChainID, ContractAddress};
-use starknet::core::types::{BroadcastedTransaction, Felt};
+use starknet::core::types::{BroadcastedTransaction, Call, Felt};
 use starknet::macros::felt;
 use uuid::Uuid;
 
 use crate::diagnostics::DiagnosticClient;
 use crate::execution::deploy::DeploymentParameters;
 use crate::execution::fee::FeeEstimate;
-use crate::execution::ExecutionParameters;
+use crate::execution::{ExecutionParameters, FeeCollection};
+use crate::low_rpc::CallShape;
+use crate::stage::{measure_stage, Stage};
 use crate::{Client, Error};
 
 /// Paymaster transaction parameters to be used for building an executable transaction.
@@ -51,6 +52,9 @@ impl TransactionParameters {
 pub struct InvokeParameters {
     pub user_address: Felt,
     pub calls: Calls,
+
+    /// Whether the transaction will be signed with a session key, in which case the cost of validating the session is added to the estimate
+    pub session: bool,
 }
 
 impl Transaction {
@@ -61,33 +65,72 @@ impl Transaction {
     /// to the fee that should be used to guarantee a valid execution.
     ///
     /// The client's `diagnostic_client` is used to extract and log diagnostic information from simulation errors.
-    pub async fn estimate(self, client: &Client) -> Result<EstimatedTransaction, Error> {
+    pub async fn estimate(mut self, client: &Client) -> Result<EstimatedTransaction, Error> {
         self.check_parameters_valid()?;
 
-        let transactions = self.build_transactions(client).await?;
-        let token = client.price.fetch_token(self.parameters.gas_token()).await?;
+        // The quote of a transaction paid in a volatile token expires sooner unless the user set the time bounds
+        let gas_token = self.parameters.gas_token(client.starknet.tokens());
+        if !self.parameters.fee_mode().is_sponsored() {
+            if let Some(ttl) = client.quote_ttl(gas_token) {
+                self.parameters = self.parameters.with_default_validity(ttl);
+            }
+        }
+
+        let tip = client.resolve_tip(self.parameters.tip()).await?;
+        let fee_collection = client.fee_collection(gas_token);
 
-        let fee_estimate_result = client.starknet.estimate_transactions(&transactions).await;
-        let estimated_fee_in_strk: u128 = match fee_estimate_result {
-            Ok(estimates) => estimates.into_iter().map(|x| x.overall_fee).sum(),
-            Err(e) => {
-                // Extract diagnostic information from the failed simulation
-                self.report_simulation_error(&client.diagnostic_client, &e).await;
-                return Err(e.into());
-            },
+        // When the fee is paid directly to a relayer, the transaction must be executed by that relayer
+        let relayer = match fee_collection {
+            FeeCollection::Direct if !self.parameters.fee_mode().is_sponsored() => Some(client.assign_relayer().await?),
+            _ => None,
         };
+        let fee_recipient = relayer.unwrap_or(self.forwarder);
+
+        // The low-RPC mode reuses the recent estimate of the transactions with the same shape
+        let shape = self.call_shape(gas_token);
+        let estimate = async {
+            if let Some(estimate) = client.low_rpc.reusable_estimate(shape) {
+                return Ok(estimate);
+            }
+
+            let transactions = self.build_transactions(client, tip.tip, fee_collection, fee_recipient).await?;
+
+            // Deployment and invoke are estimated in a single batched request
+            let fee_estimate_result = measure_stage("build", Stage::Estimation, client.starknet.estimate_transactions(&transactions)).await;
+            let estimated_fee_in_strk: u128 = match fee_estimate_result {
+                Ok(estimates) => estimates.into_iter().map(|x| x.overall_fee).sum(),
+                Err(e) => {
+                    // Extract diagnostic information from the failed simulation
+                    self.report_simulation_error(&client.diagnostic_client, &e).await;
+                    return Err(e.into());
+                },
+            };
+            client.low_rpc.record_estimate(shape, estimated_fee_in_strk);
+
+            Ok::<_, Error>(estimated_fee_in_strk)
+        };
+
+        let (estimated_fee_in_strk, token) = tokio::try_join!(estimate, async {
+            let token = measure_stage("build", Stage::PriceFetch, client.price.fetch_token(gas_token)).await;
+            Ok::<_, Error>(token?)
+        })?;
 
         // TODO: update this
-        let estimated_fee_in_strk = Felt::from(estimated_fee_in_strk);
+        let estimated_fee_in_strk = Felt::from(estimated_fee_in_strk) + self.compute_session_overhead_in_strk(client).await?;
 
-        let estimated_fee_in_gas_token = convert_strk_to_token(&token, estimated_fee_in_strk, true)?;
+        let estimated_fee_in_gas_token = client.apply_min_fee(token.address, client.convert_fee_to_token(&token, estimated_fee_in_strk)?);
 
-        let suggested_max_fee_in_strk = self.compute_max_fee_in_strk(client, estimated_fee_in_strk).await?;
-        let suggested_max_fee_in_gas_token = convert_strk_to_token(&token, suggested_max_fee_in_strk, true)?;
+        let suggested_max_fee_in_strk = client
+            .low_rpc
+            .widen(self.compute_max_fee_in_strk(client, estimated_fee_in_strk).await?);
+        let suggested_max_fee_in_gas_token = client.apply_min_fee(token.address, client.convert_fee_to_token(&token, suggested_max_fee_in_strk)?);
 
         Ok(EstimatedTransaction {
             chain_id: *client.starknet.chain_id(),
             forwarder: self.forwarder,
+            gas_token,
+            fee_collection,
+            relayer,
             transaction: self.transaction,
             parameters: self.parameters,
 
@@ -97,10 +140,21 @@ impl Transaction {
                 estimated_fee_in_gas_token,
                 suggested_max_fee_in_strk,
                 suggested_max_fee_in_gas_token,
+                tip,
+                low_rpc: client.low_rpc.is_enabled(),
             },
         })
     }
 
+    /// Shape of the transaction used to reuse its estimate in low-RPC mode. The sponsored transactions do not carry the
+    /// fee transfer so they do not share the shape of the ones paid in a gas token.
+    fn call_shape(&self, gas_token: Felt) -> CallShape {
+        let gas_token = if self.parameters.fee_mode().is_sponsored() { Felt::ZERO } else { gas_token };
+        let deployment = matches!(self.transaction, TransactionParameters::Deploy { .. } | TransactionParameters::DeployAndInvoke { .. });
+
+        CallShape::new(gas_token, deployment, &self.transaction.calls())
+    }
+
     /// Analyzes a simulation error and logs diagnostic information.
     async fn report_simulation_error(&self, diagnostic_client: &DiagnosticClient, error: &paymaster_starknet::Error) {
         let calls = self.transaction.calls();
@@ -126,6 +180,17 @@ impl Transaction {
         }
     }
 
+    // Compute the cost of validating the session token when the invoke is signed with a session key, since the typed data
+    // is estimated before being signed
+    async fn compute_session_overhead_in_strk(&self, client: &Client) -> Result<Felt, Error> {
+        match &self.transaction {
+            TransactionParameters::Invoke { invoke } | TransactionParameters::DeployAndInvoke { invoke, .. } if invoke.session => {
+                client.compute_session_overhead_in_strk().await
+            },
+            _ => Ok(Felt::ZERO),
+        }
+    }
+
     // Compute the max fee estimate that we will suggest to use to guarantee execution. This amount will be approved by the user but should be understood
     // as an upper bound on the real amount that will be paid. A second estimate will be done just before execution and this amount will be the one actually paid
     // so here we just need to ensure that the user has approved enough to compensate for the volatility.
@@ -146,53 +211,52 @@ impl Transaction {
     }
 
     // Convert the transaction into a Starknet transaction type to perform the estimate
-    async fn build_transactions(&self, client: &Client) -> Result<Vec<BroadcastedTransaction>, Error> {
+    async fn build_transactions(&self, client: &Client, tip: u64, fee_collection: FeeCollection, fee_recipient: Felt) -> Result<Vec<BroadcastedTransaction>, Error> {
+        let gas_token = self.parameters.gas_token(client.starknet.tokens());
+
         Ok(match &self.transaction {
             // A sponsored transaction only has a deployment without any invoke to pay gas token
             TransactionParameters::Deploy { deployment } if self.parameters.fee_mode().is_sponsored() => {
-                let deploy_tx = deployment.build_transaction(client, self.parameters.fee_mode().tip()).await?;
+                let deploy_tx = deployment.build_transaction(client, tip).await?;
 
                 vec![deploy_tx]
             },
             // A non-sponsored deploy transaction also contains a gas token transfer to pay for the gas
             TransactionParameters::Deploy { deployment } => {
-                let deploy_tx = deployment.build_transaction(client, self.parameters.fee_mode().tip()).await?;
-                let tip = client.get_tip(self.parameters.tip()).await?;
-                let invoke_tx = self.build_invoke(deployment.address, felt!("0x0"), tip);
+                let deploy_tx = deployment.build_transaction(client, tip).await?;
+                let invoke_tx = self.build_invoke(deployment.address, felt!("0x0"), tip, gas_token, fee_collection, fee_recipient);
 
                 vec![deploy_tx, invoke_tx]
             },
             TransactionParameters::Invoke { invoke } => {
                 let nonce = client.starknet.fetch_nonce(invoke.user_address).await?;
-                let tip = client.get_tip(self.parameters.tip()).await?;
-                let invoke_tx = self.build_invoke(invoke.user_address, nonce, tip);
+                let invoke_tx = self.build_invoke(invoke.user_address, nonce, tip, gas_token, fee_collection, fee_recipient);
 
                 vec![invoke_tx]
             },
             TransactionParameters::DeployAndInvoke { deployment, invoke } if deployment.address == invoke.user_address => {
-                let deploy_tx = deployment.build_transaction(client, self.parameters.fee_mode().tip()).await?;
-                let tip = client.get_tip(self.parameters.tip()).await?;
-                let invoke_tx = self.build_invoke(deployment.address, felt!("0x0"), tip);
+                let deploy_tx = deployment.build_transaction(client, tip).await?;
+                let invoke_tx = self.build_invoke(deployment.address, felt!("0x0"), tip, gas_token, fee_collection, fee_recipient);
 
                 vec![deploy_tx, invoke_tx]
             },
+            // The deployment and the invoke are independent, their state is fetched concurrently
             TransactionParameters::DeployAndInvoke { deployment, invoke } => {
-                let deploy_tx = deployment.build_transaction(client, self.parameters.fee_mode().tip()).await?;
-
-                let nonce = client.starknet.fetch_nonce(invoke.user_address).await?;
-                let tip = client.get_tip(self.parameters.tip()).await?;
-                let invoke_tx = self.build_invoke(invoke.user_address, nonce, tip);
+                let (deploy_tx, nonce) = tokio::try_join!(deployment.build_transaction(client, tip), async {
+                    Ok::<_, Error>(client.starknet.fetch_nonce(invoke.user_address).await?)
+                })?;
+                let invoke_tx = self.build_invoke(invoke.user_address, nonce, tip, gas_token, fee_collection, fee_recipient);
 
                 vec![deploy_tx, invoke_tx]
             },
         })
     }
 
-    fn build_invoke(&self, sender: Felt, nonce: Felt, tip: u64) -> BroadcastedTransaction {
+    fn build_invoke(&self, sender: Felt, nonce: Felt, tip: u64, gas_token: Felt, fee_collection: FeeCollection, fee_recipient: Felt) -> BroadcastedTransaction {
         let calls = if self.parameters.fee_mode().is_sponsored() {
             self.build_sponsored_calls()
         } else {
-            self.build_unsponsored_calls()
+            self.build_unsponsored_calls(gas_token, fee_collection, fee_recipient)
         };
 
         calls.as_transaction(sender, nonce, tip)
@@ -204,10 +268,10 @@ impl Transaction {
     }
 
     // Build the call for an unsponsored transaction which means that we inject a transfer of the gas token
-    // by the user to our forwarder
-    pub fn build_unsponsored_calls(&self) -> Calls {
+    // by the user to the fee recipient, or an allowance to our forwarder when the fee is collected through a permit
+    pub fn build_unsponsored_calls(&self, gas_token: Felt, fee_collection: FeeCollection, fee_recipient: Felt) -> Calls {
         let mut calls = self.transaction.calls();
-        calls.push(TokenTransfer::new(self.parameters.gas_token(), self.forwarder, Felt::ONE).to_call());
+        calls.push(build_fee_call(fee_collection, gas_token, fee_recipient, Felt::ONE));
 
         calls
     }
@@ -219,6 +283,14 @@ impl Transaction {
 pub struct EstimatedTransaction {
     chain_id: ChainID,
     forwarder: ContractAddress,
+
+    /// Token in which the fee is paid, the STRK token of the chain for the sponsored transactions
+    gas_token: Felt,
+    fee_collection: FeeCollection,
+
+    /// Relayer receiving the fee and executing the transaction, when the fee is paid directly to it
+    relayer: Option<Felt>,
+
     pub transaction: TransactionParameters,
     pub parameters: ExecutionParameters,
     pub fee_estimate: FeeEstimate,
@@ -248,6 +320,9 @@ impl EstimatedTransaction {
         Ok(VersionedTransaction {
             chain_id: self.chain_id,
             forwarder: self.forwarder,
+            gas_token: self.gas_token,
+            fee_collection: self.fee_collection,
+            relayer: self.relayer,
             version,
             transaction: self.transaction,
             parameters: self.parameters,
@@ -261,6 +336,9 @@ impl EstimatedTransaction {
 pub struct VersionedTransaction {
     chain_id: ChainID,
     forwarder: Felt,
+    gas_token: Felt,
+    fee_collection: FeeCollection,
+    relayer: Option<Felt>,
     pub version: PaymasterVersion,
     pub transaction: TransactionParameters,
     pub parameters: ExecutionParameters,
@@ -276,7 +354,7 @@ impl VersionedTransaction {
             self.version,
             ExecuteFromOutsideParameters {
                 chain_id: self.chain_id,
-                caller: self.forwarder,
+                caller: self.caller(),
                 nonce: Felt::from(Uuid::new_v4().to_u128_le()),
                 calls,
                 time_bounds: self.parameters.time_bounds(),
@@ -284,6 +362,17 @@ impl VersionedTransaction {
         )
     }
 
+    /// Returns the relayer which will execute the transaction, when the fee is paid directly to it
+    pub fn relayer(&self) -> Option<Felt> {
+        self.relayer
+    }
+
+    // The transaction is executed through the forwarder unless the fee is paid directly to a relayer, in which
+    // case the relayer receiving the fee is the only one allowed to execute it
+    fn caller(&self) -> Felt {
+        self.relayer.unwrap_or(self.forwarder)
+    }
+
     fn build_calls(&self) -> Calls {
         if self.parameters.fee_mode().is_sponsored() {
             self.build_sponsored_calls()
@@ -298,15 +387,28 @@ impl VersionedTransaction {
     }
 
     // Build the call for an unsponsored transaction which means that we inject a transfer of the gas token
-    // by the user to our forwarder
+    // by the user to the fee recipient, or an allowance to our forwarder when the fee is collected through a permit
     pub fn build_unsponsored_calls(&self) -> Calls {
         let mut calls = self.transaction.calls();
-        calls.push(TokenTransfer::new(self.parameters.gas_token(), self.forwarder, self.fee_estimate.suggested_max_fee_in_gas_token).to_call());
+        calls.push(build_fee_call(
+            self.fee_collection,
+            self.gas_token,
+            self.caller(),
+            self.fee_estimate.suggested_max_fee_in_gas_token,
+        ));
 
         calls
     }
 }
 
+// Build the call through which the user pays the fee of an unsponsored transaction
+fn build_fee_call(fee_collection: FeeCollection, gas_token: Felt, recipient: Felt, amount: Felt) -> Call {
+    match fee_collection {
+        FeeCollection::Transfer | FeeCollection::Direct => TokenTransfer::new(gas_token, recipient, amount).to_call(),
+        FeeCollection::Permit => TokenPermit::new(gas_token, recipient, amount).to_call(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use paymaster_starknet::transaction::{Calls, PaymasterVersion, TokenTransfer};
@@ -334,6 +436,7 @@ mod tests {
                 invoke: InvokeParameters {
                     user_address: account.address(),
                     calls: Calls::new(vec![an_eth_transfer(account.address(), Felt::from(42))]),
+                    session: false,
                 },
             },
             parameters: ExecutionParameters::V1 {
@@ -370,7 +473,7 @@ mod tests {
     async fn build_deploy_works_properly() {
         let test = TestEnvironment::new().await;
         let account = test.starknet.initialize_account(&StarknetTestEnvironment::ACCOUNT_1);
-        let new_account = test.starknet.initialize_argent_account(Felt::ONE).await;
+        let new_account = test.starknet.initialize_argent_account(Felt::ONE).await.unwrap();
 
         let salt = Felt::from(rand::rng().random_range(1..1_000_000_000));
         let new_account_address = new_account.deploy_v3(salt).address();
@@ -423,7 +526,7 @@ mod tests {
     async fn build_deploy_sponsored_works_properly() {
         let test = TestEnvironment::new().await;
         let account = test.starknet.initialize_account(&StarknetTestEnvironment::ACCOUNT_1);
-        let new_account = test.starknet.initialize_argent_account(Felt::ONE).await;
+        let new_account = test.starknet.initialize_argent_account(Felt::ONE).await.unwrap();
 
         let salt = Felt::from(rand::rng().random_range(1..1_000_000_000));
         let new_account_address = new_account.deploy_v3(salt).address();
@@ -469,7 +572,7 @@ mod tests {
     async fn estimate_deploy_and_invoke_same_contract_works_properly() {
         let test = TestEnvironment::new().await;
         let account = test.starknet.initialize_account(&StarknetTestEnvironment::ACCOUNT_1);
-        let new_account = test.starknet.initialize_argent_account(Felt::ONE).await;
+        let new_account = test.starknet.initialize_argent_account(Felt::ONE).await.unwrap();
 
         let salt = Felt::from(rand::rng().random_range(1..1_000_000_000));
         let new_account_address = new_account.deploy_v3(salt).address();
@@ -493,6 +596,7 @@ mod tests {
                 invoke: InvokeParameters {
                     user_address: new_account_address,
                     calls: Calls::new(vec![an_eth_transfer(account.address(), Felt::ZERO)]),
+                    session: false,
                 },
             },
             parameters: ExecutionParameters::V1 {
@@ -531,7 +635,7 @@ mod tests {
         let test = TestEnvironment::new().await;
         let account_1 = test.starknet.initialize_account(&StarknetTestEnvironment::ACCOUNT_1);
         let account_2 = test.starknet.initialize_account(&StarknetTestEnvironment::ACCOUNT_ARGENT_1);
-        let new_account = test.starknet.initialize_argent_account(Felt::ONE).await;
+        let new_account = test.starknet.initialize_argent_account(Felt::ONE).await.unwrap();
 
         let salt = Felt::from(rand::rng().random_range(1..1_000_000_000));
         let new_account_address = new_account.deploy_v3(salt).address();
@@ -555,6 +659,7 @@ mod tests {
                 invoke: InvokeParameters {
                     user_address: account_2.address(),
                     calls: Calls::new(vec![an_eth_transfer(account_2.address(), Felt::ZERO)]),
+                    session: false,
                 },
             },
             parameters: ExecutionParameters::V1 {
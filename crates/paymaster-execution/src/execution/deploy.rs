@@ -8,7 +8,7 @@ use starknet::core::types::{BroadcastedInvokeTransactionV3, BroadcastedTransacti
 use starknet::macros::selector;
 use std::hash::{DefaultHasher, Hash, Hasher};
 
-use crate::{Client, Error, TipPriority};
+use crate::{Client, Error};
 
 /// Deployment parameters required to deploy a contract
 #[serde_as]
@@ -36,11 +36,10 @@ pub struct DeploymentParameters {
 }
 
 impl DeploymentParameters {
-    /// Convert the deployment parameters to a starknet transaction
-    pub(crate) async fn build_transaction(&self, client: &Client, tip: TipPriority) -> Result<BroadcastedTransaction, Error> {
+    /// Convert the deployment parameters to a starknet transaction using the given, already resolved, `tip`
+    pub(crate) async fn build_transaction(&self, client: &Client, tip: u64) -> Result<BroadcastedTransaction, Error> {
         let estimate_account = client.estimate_account.address();
-        let estimate_account_nonce = client.starknet.fetch_nonce(estimate_account).await?;
-        let tip = client.get_tip(tip).await?;
+        let estimate_account_nonce = client.estimate_account_nonce.fetch().await?;
 
         Ok(BroadcastedTransaction::Invoke(BroadcastedInvokeTransactionV3 {
             sender_address: estimate_account,
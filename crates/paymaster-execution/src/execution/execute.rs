@@ -1,14 +1,29 @@
-use paymaster_prices::math::convert_strk_to_token;
-use paymaster_starknet::transaction::{CalldataBuilder, Calls, EstimatedCalls, ExecuteFromOutsideMessage, SequentialCalldataDecoder, TokenTransfer};
+use paymaster_common::metric;
+use paymaster_prices::math::{convert_strk_to_token, convert_token_to_strk};
+use paymaster_prices::TokenPrice;
+use paymaster_relayer::RelayerSelection;
+use paymaster_starknet::transaction::{
+    CalldataBuilder, Calls, EstimatedCalls, ExecuteFromOutsideMessage, SequentialCalldataDecoder, SessionAuthorization, TimeBounds, TokenTransfer,
+};
 use paymaster_starknet::Signature;
 use starknet::core::types::{Call, Felt, InvokeTransactionResult, TypedData};
+use starknet::core::utils::starknet_keccak;
 use starknet::macros::selector;
 use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::Duration;
+use tracing::info;
 
 use crate::execution::deploy::DeploymentParameters;
-use crate::execution::ExecutionParameters;
+use crate::execution::receipt::FeeQuote;
+use crate::execution::{AppliedTip, ExecutionParameters, ExecutionTimings, FeeCollection};
+use crate::profitability::ProfitabilityConfiguration;
+use crate::stage::{measure_stage, Stage};
 use crate::{Client, Error};
 
+/// Expected time between the submission of a transaction and its inclusion. Transactions whose signed time bounds
+/// end within this delay are not submitted since they would most likely revert.
+pub const EXPECTED_INCLUSION_LATENCY: Duration = Duration::from_secs(30);
+
 #[derive(Debug, Hash)]
 pub enum ExecutableTransactionParameters {
     Deploy {
@@ -35,12 +50,42 @@ impl ExecutableTransactionParameters {
             ExecutableTransactionParameters::DirectInvoke { invoke } => invoke.get_unique_indentifier(),
         }
     }
+
+    /// Returns the address of the user on behalf of which the transaction is executed
+    pub fn user(&self) -> Felt {
+        match self {
+            ExecutableTransactionParameters::Deploy { deployment } => deployment.address,
+            ExecutableTransactionParameters::Invoke { invoke } => invoke.user,
+            ExecutableTransactionParameters::DeployAndInvoke { invoke, .. } => invoke.user,
+            ExecutableTransactionParameters::DirectInvoke { invoke } => invoke.user,
+        }
+    }
+
+    /// Returns the calls of the user, if they can be decoded. The calls of a direct invoke are wrapped in a raw
+    /// call to the account and are not decoded.
+    pub fn user_calls(&self) -> Option<Vec<Call>> {
+        match self {
+            ExecutableTransactionParameters::Deploy { .. } => Some(vec![]),
+            ExecutableTransactionParameters::Invoke { invoke } => Some(invoke.message.calls().to_vec()),
+            ExecutableTransactionParameters::DeployAndInvoke { invoke, .. } => Some(invoke.message.calls().to_vec()),
+            ExecutableTransactionParameters::DirectInvoke { .. } => None,
+        }
+    }
+
+    /// Returns true if the transaction deploys the account of the user
+    pub fn is_deployment(&self) -> bool {
+        matches!(
+            self,
+            ExecutableTransactionParameters::Deploy { .. } | ExecutableTransactionParameters::DeployAndInvoke { .. }
+        )
+    }
 }
 
 #[derive(Debug, Hash)]
 pub struct ExecutableInvokeParameters {
     user: Felt,
     signature: Signature,
+    session: Option<SessionAuthorization>,
 
     message: ExecuteFromOutsideMessage,
 }
@@ -50,27 +95,47 @@ impl ExecutableInvokeParameters {
         Ok(Self {
             user,
             signature,
+            session: None,
 
             message: ExecuteFromOutsideMessage::from_typed_data(&typed_data)?,
         })
     }
 
-    fn find_gas_token_transfer(&self, forwarder: Felt) -> Result<TokenTransfer, Error> {
-        let last_call = self.message.calls().last().ok_or(Error::InvalidTypedData)?;
-        if last_call.selector != selector!("transfer") {
-            return Err(Error::InvalidTypedData);
+    /// Mark the typed data as signed by a session key authorized by the given [`session`], which then carries the
+    /// signature sent to the account in place of the signature of the owner
+    pub fn with_session(mut self, session: SessionAuthorization) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Returns the signature expected by the account, which is the session token when signed with a session key
+    fn signature(&self) -> Signature {
+        match &self.session {
+            Some(session) => session.signature(),
+            None => self.signature.clone(),
         }
+    }
+
+    fn to_call(&self) -> Call {
+        self.message.to_call(self.user, &self.signature())
+    }
+
+    fn find_gas_token_transfer(&self, forwarder: Felt) -> Result<(TokenTransfer, FeeCollection), Error> {
+        self.find_gas_token_transfer_to(|recipient| recipient == forwarder)
+    }
+
+    fn find_gas_token_transfer_to(&self, is_fee_recipient: impl Fn(Felt) -> bool) -> Result<(TokenTransfer, FeeCollection), Error> {
+        let last_call = self.message.calls().last().ok_or(Error::InvalidTypedData)?;
+        let fee_collection = fee_collection_of(last_call.selector).ok_or(Error::InvalidTypedData)?;
 
         let transfer_recipient = last_call.calldata.first().ok_or(Error::InvalidTypedData)?;
-        if *transfer_recipient != forwarder {
+        if !is_fee_recipient(*transfer_recipient) {
             return Err(Error::InvalidTypedData);
         }
 
-        Ok(TokenTransfer::new(
-            last_call.to,
-            *transfer_recipient,
-            *last_call.calldata.get(1).ok_or(Error::InvalidTypedData)?,
-        ))
+        let transfer = TokenTransfer::new(last_call.to, *transfer_recipient, *last_call.calldata.get(1).ok_or(Error::InvalidTypedData)?);
+
+        Ok((transfer, fee_collection))
     }
 
     pub fn get_unique_identifier(&self) -> u64 {
@@ -96,14 +161,36 @@ impl ExecutableDirectInvokeParameters {
         hasher.finish()
     }
 
+    /// Check the raw call executes an outside execution on the account of the user, so that the calls decoded from
+    /// its calldata, the fee transfer included, are the ones executed
+    fn check_outside_execution_call(&self) -> Result<(), Error> {
+        let call = &self.execute_from_outside_call;
+        let is_outside_execution = [
+            selector!("execute_from_outside"),
+            selector!("execute_from_outside_v2"),
+            selector!("execute_from_outside_v3"),
+        ]
+        .contains(&call.selector);
+        if call.to != self.user || !is_outside_execution {
+            return Err(Error::InvalidTypedData);
+        }
+
+        Ok(())
+    }
+
     /// Extract gas transfer from a raw execute_from_outside call
     ///
     /// The execute_from_outside_call has calldata structure:
     /// [caller, nonce..., execute_after, execute_before, calls_len, ...calls, sig_len, sig...]
     /// where each call is [to, selector, calldata_len, ...calldata] and the nonce may be one or two felts.
     ///
-    /// For non-sponsored transactions, the last call should be a transfer of gas token to the forwarder.
-    fn find_gas_token_transfer(&self, forwarder: Felt) -> Result<TokenTransfer, Error> {
+    /// For non-sponsored transactions, the last call should be a transfer of gas token to the forwarder, or an
+    /// allowance to the forwarder when the fee is collected through a permit.
+    fn find_gas_token_transfer(&self, forwarder: Felt) -> Result<(TokenTransfer, FeeCollection), Error> {
+        self.find_gas_token_transfer_to(|recipient| recipient == forwarder)
+    }
+
+    fn find_gas_token_transfer_to(&self, is_fee_recipient: impl Fn(Felt) -> bool) -> Result<(TokenTransfer, FeeCollection), Error> {
         fn extract_calls_segment<'a>(calldata: &'a [Felt], calls_len_index: usize) -> Option<&'a [Felt]> {
             let calls_len_felt = calldata.get(calls_len_index)?;
             let calls_len: usize = (*calls_len_felt).try_into().ok()?;
@@ -145,10 +232,10 @@ impl ExecutableDirectInvokeParameters {
                 continue;
             };
 
-            // Validate the last call is a transfer to the forwarder.
-            if last_call.selector != selector!("transfer") {
+            // Validate the last call is a transfer, or an allowance, to the fee recipient.
+            let Some(fee_collection) = fee_collection_of(last_call.selector) else {
                 continue;
-            }
+            };
 
             if last_call.calldata.len() != 3 {
                 continue;
@@ -158,7 +245,7 @@ impl ExecutableDirectInvokeParameters {
                 continue;
             };
 
-            if *recipient != forwarder {
+            if !is_fee_recipient(*recipient) {
                 continue;
             }
 
@@ -166,13 +253,54 @@ impl ExecutableDirectInvokeParameters {
                 continue;
             };
 
-            return Ok(TokenTransfer::new(last_call.to, forwarder, *amount));
+            return Ok((TokenTransfer::new(last_call.to, *recipient, *amount), fee_collection));
         }
 
         Err(Error::InvalidTypedData)
     }
 }
 
+// Returns the fee in gas token to collect so that it covers the cost of the transaction with the minimum margin
+// at the current price of the gas token. A fee falling short is re-quoted within the amount signed by the user,
+// unless it is paid directly to the relayer in which case the user fixed it.
+fn guard_profitability(
+    profitability: &ProfitabilityConfiguration,
+    token_price: &TokenPrice,
+    cost_in_strk: Felt,
+    paid_fee_in_token: Felt,
+    transfer: &TokenTransfer,
+    fee_collection: FeeCollection,
+) -> Result<Felt, Error> {
+    let collected_in_token = match fee_collection {
+        FeeCollection::Direct => transfer.amount(),
+        _ => paid_fee_in_token,
+    };
+
+    if profitability.is_profitable(cost_in_strk, convert_token_to_strk(token_price, collected_in_token)?) {
+        return Ok(paid_fee_in_token);
+    }
+
+    let min_fee_in_token = convert_strk_to_token(token_price, profitability.min_fee_in_strk(cost_in_strk), true)?;
+    if fee_collection == FeeCollection::Direct || min_fee_in_token > transfer.amount() {
+        metric!(counter[execution_request_rejected] = 1, reason = "unprofitable");
+        return Err(Error::Unprofitable(min_fee_in_token.to_hex_string()));
+    }
+
+    metric!(counter[execution_fee_requoted] = 1, reason = "unprofitable");
+    Ok(min_fee_in_token)
+}
+
+// Returns how the fee is collected given the selector of the last call of the user
+fn fee_collection_of(selector: Felt) -> Option<FeeCollection> {
+    if selector == selector!("transfer") {
+        Some(FeeCollection::Transfer)
+    } else if selector == selector!("approve") {
+        Some(FeeCollection::Permit)
+    } else {
+        None
+    }
+}
+
 /// Paymaster transaction that contains the parameters to execute the transaction on Starknet
 pub struct ExecutableTransaction {
     /// The forwarder to use when executing the transaction
@@ -189,11 +317,100 @@ pub struct ExecutableTransaction {
 }
 
 impl ExecutableTransaction {
+    /// Returns the time bounds signed by the user, if any. Raw execute_from_outside calls are not decoded.
+    pub fn time_bounds(&self) -> Option<TimeBounds> {
+        match &self.transaction {
+            ExecutableTransactionParameters::Invoke { invoke } => Some(invoke.message.time_bounds().clone()),
+            ExecutableTransactionParameters::DeployAndInvoke { invoke, .. } => Some(invoke.message.time_bounds().clone()),
+            ExecutableTransactionParameters::Deploy { .. } | ExecutableTransactionParameters::DirectInvoke { .. } => None,
+        }
+    }
+
+    /// Returns the transfer, or allowance, of gas token to the forwarder signed by the user, if any
+    pub fn gas_token_transfer(&self) -> Option<TokenTransfer> {
+        let result = match &self.transaction {
+            ExecutableTransactionParameters::Invoke { invoke, .. } => invoke.find_gas_token_transfer(self.forwarder),
+            ExecutableTransactionParameters::DeployAndInvoke { invoke, .. } => invoke.find_gas_token_transfer(self.forwarder),
+            ExecutableTransactionParameters::DirectInvoke { invoke, .. } => invoke.find_gas_token_transfer(self.forwarder),
+            ExecutableTransactionParameters::Deploy { .. } => return None,
+        };
+
+        result.ok().map(|(transfer, _)| transfer)
+    }
+
+    // Find the transfer, or allowance, of gas token signed by the user to the recipient of the fee, which is the
+    // forwarder unless the fee is paid directly to the relayers
+    fn find_fee_transfer(&self, client: &Client) -> Result<(TokenTransfer, FeeCollection), Error> {
+        let is_fee_recipient = |recipient: Felt| {
+            if client.pays_fee_directly() {
+                client.is_relayer(recipient)
+            } else {
+                recipient == self.forwarder
+            }
+        };
+
+        let (transfer, fee_collection) = match &self.transaction {
+            ExecutableTransactionParameters::Invoke { invoke, .. } => invoke.find_gas_token_transfer_to(is_fee_recipient)?,
+            ExecutableTransactionParameters::DeployAndInvoke { invoke, .. } => invoke.find_gas_token_transfer_to(is_fee_recipient)?,
+            ExecutableTransactionParameters::DirectInvoke { invoke, .. } => {
+                // Without the forwarder nothing checks on-chain that the fee was received, the raw call must be the
+                // outside execution whose calls transfer it
+                if client.pays_fee_directly() {
+                    invoke.check_outside_execution_call()?;
+                }
+
+                invoke.find_gas_token_transfer_to(is_fee_recipient)?
+            },
+            _ => return Err(Error::InvalidTypedData),
+        };
+
+        match fee_collection {
+            FeeCollection::Transfer if client.pays_fee_directly() => Ok((transfer, FeeCollection::Direct)),
+            fee_collection => Ok((transfer, fee_collection)),
+        }
+    }
+
     /// Estimate a sponsored transaction which is a transaction that will be paid by the relayer
     pub async fn estimate_sponsored_transaction(self, client: &Client, sponsor_metadata: Vec<Felt>) -> Result<EstimatedExecutableTransaction, Error> {
+        match self.estimate_sponsored_transaction_once(client, sponsor_metadata.clone()).await {
+            Err(Error::AlreadyDeployed(e)) => {
+                self.without_deployment(e)?
+                    .estimate_sponsored_transaction_once(client, sponsor_metadata)
+                    .await
+            },
+            result => result,
+        }
+    }
+
+    pub async fn estimate_transaction(self, client: &Client) -> Result<EstimatedExecutableTransaction, Error> {
+        match self.estimate_transaction_once(client).await {
+            Err(Error::AlreadyDeployed(e)) => self.without_deployment(e)?.estimate_transaction_once(client).await,
+            result => result,
+        }
+    }
+
+    /// Drop the deployment of a transaction which deploys the account before invoking it. The account may have
+    /// been deployed by another flow since the transaction was built, in which case only the invoke is executed.
+    fn without_deployment(self, error: String) -> Result<Self, Error> {
+        let ExecutableTransactionParameters::DeployAndInvoke { deployment, invoke } = self.transaction else {
+            return Err(Error::AlreadyDeployed(error));
+        };
+
+        metric!(counter[deployment_conflict] = 1);
+        info!("Account {} already deployed, executing the invoke only", deployment.address.to_fixed_hex_string());
+
+        Ok(Self {
+            transaction: ExecutableTransactionParameters::Invoke { invoke },
+            ..self
+        })
+    }
+
+    async fn estimate_sponsored_transaction_once(&self, client: &Client, sponsor_metadata: Vec<Felt>) -> Result<EstimatedExecutableTransaction, Error> {
+        let time_bounds = self.time_bounds();
         let calls = self.build_sponsored_calls(sponsor_metadata);
 
-        let estimated_calls = client.estimate(&calls, self.parameters.tip()).await?;
+        let tip = client.resolve_tip(self.parameters.tip()).await?;
+        let estimated_calls = measure_stage("execute", Stage::Estimation, client.estimate_with_tip(&calls, tip.tip)).await?;
         let fee_estimate = estimated_calls.estimate();
 
         // We recompute the real estimate fee. Validation step is not included in the fee estimate
@@ -201,37 +418,69 @@ impl ExecutableTransaction {
         let final_fee_estimate = fee_estimate.update_overall_fee(paid_fee_in_strk);
 
         let estimated_final_calls = calls.with_estimate(final_fee_estimate);
-        Ok(EstimatedExecutableTransaction(estimated_final_calls))
+        Ok(EstimatedExecutableTransaction {
+            calls: estimated_final_calls,
+            quote: FeeQuote::sponsored(client.starknet.tokens(), paid_fee_in_strk),
+            tip,
+            time_bounds,
+            relayer: None,
+            selection: RelayerSelection::default().with_deployment(self.transaction.is_deployment()),
+        })
     }
 
-    pub async fn estimate_transaction(self, client: &Client) -> Result<EstimatedExecutableTransaction, Error> {
-        let transfer = match &self.transaction {
-            ExecutableTransactionParameters::Invoke { invoke, .. } => invoke.find_gas_token_transfer(self.forwarder)?,
-            ExecutableTransactionParameters::DeployAndInvoke { invoke, .. } => invoke.find_gas_token_transfer(self.forwarder)?,
-            ExecutableTransactionParameters::DirectInvoke { invoke, .. } => invoke.find_gas_token_transfer(self.forwarder)?,
-            _ => return Err(Error::InvalidTypedData),
-        };
+    async fn estimate_transaction_once(&self, client: &Client) -> Result<EstimatedExecutableTransaction, Error> {
+        let time_bounds = self.time_bounds();
+        let (transfer, fee_collection) = self.find_fee_transfer(client)?;
 
-        let calls = self.build_calls(transfer);
+        // The allowance can only be used by the forwarder for the tokens configured to be collected through a permit
+        if fee_collection != client.fee_collection(transfer.token()) {
+            return Err(Error::InvalidTypedData);
+        }
+
+        let calls = self.build_calls(transfer, fee_collection);
 
-        let estimated_calls = client.estimate(&calls, self.parameters.tip()).await?;
+        let tip = client.resolve_tip(self.parameters.tip()).await?;
+        let estimated_calls = measure_stage("execute", Stage::Estimation, client.estimate_with_tip(&calls, tip.tip)).await?;
         let fee_estimate = estimated_calls.estimate();
 
-        let paid_fee_in_strk = self.compute_paid_fee(client, Felt::from(fee_estimate.overall_fee)).await?;
+        let cost_in_strk = Felt::from(fee_estimate.overall_fee);
+        let paid_fee_in_strk = self.compute_paid_fee(client, cost_in_strk).await?;
         let final_fee_estimate = fee_estimate.update_overall_fee(paid_fee_in_strk);
 
-        let token_price = client.price.fetch_token(transfer.token()).await?;
-        let paid_fee_in_token = convert_strk_to_token(&token_price, paid_fee_in_strk, true)?;
+        let token_price = measure_stage("execute", Stage::PriceFetch, client.price.fetch_token(transfer.token())).await?;
+        let paid_fee_in_token = client.apply_min_fee(transfer.token(), client.convert_fee_to_token(&token_price, paid_fee_in_strk)?);
 
         if paid_fee_in_token > transfer.amount() {
             return Err(Error::MaxAmountTooLow(paid_fee_in_token.to_hex_string()));
         }
 
+        let paid_fee_in_token = match client.profitability() {
+            Some(profitability) => guard_profitability(profitability, &token_price, cost_in_strk, paid_fee_in_token, &transfer, fee_collection)?,
+            None => paid_fee_in_token,
+        };
+
         let fee_transfer = TokenTransfer::new(transfer.token(), self.gas_tank_address, paid_fee_in_token);
-        let final_calls = self.build_calls(fee_transfer);
+        let final_calls = self.build_calls(fee_transfer, fee_collection);
         let estimated_final_calls = final_calls.with_estimate(final_fee_estimate);
 
-        Ok(EstimatedExecutableTransaction(estimated_final_calls))
+        // Without the forwarder nothing is refunded, the relayer receives the whole amount signed by the user
+        let (fee_in_token, relayer) = match fee_collection {
+            FeeCollection::Direct => (transfer.amount(), Some(transfer.recipient())),
+            _ => (paid_fee_in_token, None),
+        };
+
+        Ok(EstimatedExecutableTransaction {
+            calls: estimated_final_calls,
+            quote: FeeQuote {
+                gas_token: transfer.token(),
+                fee_in_token,
+                fee_in_strk: paid_fee_in_strk,
+            },
+            tip,
+            time_bounds,
+            relayer,
+            selection: RelayerSelection::default().with_deployment(self.transaction.is_deployment()),
+        })
     }
 
     async fn compute_paid_fee(&self, client: &Client, base_estimate: Felt) -> Result<Felt, Error> {
@@ -244,8 +493,8 @@ impl ExecutableTransaction {
     }
 
     // Build the calls that needs to be performed
-    fn build_calls(&self, fee_transfer: TokenTransfer) -> Calls {
-        let calls = [self.build_deploy_call(), self.build_execute_call(fee_transfer)]
+    fn build_calls(&self, fee_transfer: TokenTransfer, fee_collection: FeeCollection) -> Calls {
+        let calls = [self.build_deploy_call(), self.build_execute_call(fee_transfer, fee_collection)]
             .into_iter()
             .flatten()
             .collect();
@@ -271,17 +520,23 @@ impl ExecutableTransaction {
         }
     }
 
-    fn build_execute_call(&self, fee_transfer: TokenTransfer) -> Option<Call> {
+    fn build_execute_call(&self, fee_transfer: TokenTransfer, fee_collection: FeeCollection) -> Option<Call> {
         let execute_from_outside_call = match &self.transaction {
-            ExecutableTransactionParameters::Invoke { invoke, .. } => invoke.message.to_call(invoke.user, &invoke.signature),
-            ExecutableTransactionParameters::DeployAndInvoke { invoke, .. } => invoke.message.to_call(invoke.user, &invoke.signature),
+            ExecutableTransactionParameters::Invoke { invoke, .. } => invoke.to_call(),
+            ExecutableTransactionParameters::DeployAndInvoke { invoke, .. } => invoke.to_call(),
             ExecutableTransactionParameters::DirectInvoke { invoke, .. } => invoke.execute_from_outside_call.clone(),
             _ => return None,
         };
 
+        let selector = match fee_collection {
+            FeeCollection::Transfer => selector!("execute"),
+            FeeCollection::Permit => selector!("execute_with_permit"),
+            FeeCollection::Direct => return Some(execute_from_outside_call),
+        };
+
         Some(Call {
             to: self.forwarder,
-            selector: selector!("execute"),
+            selector,
             calldata: CalldataBuilder::new()
                 .encode(&execute_from_outside_call)
                 .encode(&fee_transfer.token())
@@ -293,8 +548,8 @@ impl ExecutableTransaction {
 
     fn build_sponsored_execute_call(&self, sponsor_metadata: Vec<Felt>) -> Option<Call> {
         let execute_from_outside_call = match &self.transaction {
-            ExecutableTransactionParameters::Invoke { invoke, .. } => invoke.message.to_call(invoke.user, &invoke.signature),
-            ExecutableTransactionParameters::DeployAndInvoke { invoke, .. } => invoke.message.to_call(invoke.user, &invoke.signature),
+            ExecutableTransactionParameters::Invoke { invoke, .. } => invoke.to_call(),
+            ExecutableTransactionParameters::DeployAndInvoke { invoke, .. } => invoke.to_call(),
             ExecutableTransactionParameters::DirectInvoke { invoke, .. } => invoke.execute_from_outside_call.clone(),
             _ => return None,
         };
@@ -312,13 +567,67 @@ impl ExecutableTransaction {
 
 /// Paymaster executable transaction that can be sent to Starknet
 #[derive(Debug)]
-pub struct EstimatedExecutableTransaction(EstimatedCalls);
+pub struct EstimatedExecutableTransaction {
+    calls: EstimatedCalls,
+    quote: FeeQuote,
+    tip: AppliedTip,
+    time_bounds: Option<TimeBounds>,
+
+    /// Relayer which must execute the transaction since the fee was paid directly to it
+    relayer: Option<Felt>,
+
+    /// Relayers which may execute the transaction, given its sponsor and whether it deploys an account
+    selection: RelayerSelection,
+}
 
 impl EstimatedExecutableTransaction {
+    /// Returns the fee (in STRK) that will be paid for this transaction
+    pub fn overall_fee(&self) -> Felt {
+        Felt::from(self.calls.estimate().overall_fee)
+    }
+
+    /// Returns the fee quoted for this transaction
+    pub fn quote(&self) -> FeeQuote {
+        self.quote
+    }
+
+    /// Returns the tip applied to this transaction
+    pub fn tip(&self) -> AppliedTip {
+        self.tip
+    }
+
+    /// Execute the transaction with the relayers of the sponsor owning the given API key
+    pub fn with_sponsor(self, api_key: &str) -> Self {
+        Self {
+            selection: RelayerSelection {
+                sponsor: Some(starknet_keccak(api_key.as_bytes())),
+                ..self.selection
+            },
+            ..self
+        }
+    }
+
+    /// Submit the transaction, unless its time bounds end before it can be included in which case it fails with
+    /// [`Error::TransactionExpired`] rather than paying for a transaction which would revert
     pub async fn execute(self, client: &Client) -> Result<InvokeTransactionResult, Error> {
-        let result = client.execute(&self.0).await?;
+        self.execute_timed(client).await.map(|(result, _)| result)
+    }
 
-        Ok(result)
+    /// Same as [`execute`], also returning the time spent in each stage of the submission
+    pub async fn execute_timed(self, client: &Client) -> Result<(InvokeTransactionResult, ExecutionTimings), Error> {
+        if self
+            .time_bounds
+            .as_ref()
+            .is_some_and(|x| x.expires_within(EXPECTED_INCLUSION_LATENCY))
+        {
+            metric!(counter[execution_request_rejected] = 1, reason = "expired");
+            return Err(Error::TransactionExpired);
+        }
+
+        let (result, timings) = client.execute_timed_with(&self.calls, self.relayer, self.selection).await?;
+        client.track_inclusion(result.transaction_hash, self.tip.priority);
+
+        Ok((result, timings))
     }
 }
 
@@ -327,7 +636,8 @@ mod tests {
     use crate::execution::build::{InvokeParameters, Transaction, TransactionParameters};
     use crate::execution::deploy::DeploymentParameters;
     use crate::execution::execute::{ExecutableInvokeParameters, ExecutableTransaction, ExecutableTransactionParameters};
-    use crate::execution::{ExecutionParameters, FeeMode, TipPriority};
+    use crate::execution::receipt::FeeQuote;
+    use crate::execution::{ExecutionParameters, FeeCollection, FeeMode, TipPriority};
     use crate::testing::transaction::{an_eth_approve, an_eth_transfer};
     use crate::testing::{StarknetTestEnvironment, TestEnvironment};
     use crate::ExecutableDirectInvokeParameters;
@@ -383,10 +693,11 @@ mod tests {
         let result = parameters.find_gas_token_transfer(forwarder);
         assert!(result.is_ok());
 
-        let transfer = result.unwrap();
+        let (transfer, fee_collection) = result.unwrap();
         assert_eq!(transfer.token(), token);
         assert_eq!(transfer.recipient(), forwarder);
         assert_eq!(transfer.amount(), amount);
+        assert_eq!(fee_collection, FeeCollection::Transfer);
     }
 
     #[test]
@@ -434,10 +745,11 @@ mod tests {
         let result = parameters.find_gas_token_transfer(forwarder);
         assert!(result.is_ok());
 
-        let transfer = result.unwrap();
+        let (transfer, fee_collection) = result.unwrap();
         assert_eq!(transfer.token(), token);
         assert_eq!(transfer.recipient(), forwarder);
         assert_eq!(transfer.amount(), amount);
+        assert_eq!(fee_collection, FeeCollection::Transfer);
     }
 
     #[test]
@@ -451,11 +763,48 @@ mod tests {
             felt!("0x4"), // execute_before
             Felt::ONE,    // num_calls = 1
             // Call with wrong selector
-            felt!("0x456"),       // to
-            selector!("approve"), // wrong selector
+            felt!("0x456"),                  // to
+            selector!("increase_allowance"), // wrong selector
+            Felt::THREE,                     // calldata_len
+            forwarder,                       // recipient
+            felt!("0x789"),                  // amount_low
+            Felt::ZERO,                      // amount_high
+            Felt::TWO,                       // signature length
+            felt!("0xDEAD"),                 // signature part 1
+            felt!("0xBEEF"),                 // signature part 2
+        ];
+
+        let parameters = ExecutableDirectInvokeParameters {
+            user: Felt::ZERO,
+            execute_from_outside_call: Call {
+                to: felt!("0x999"),
+                selector: selector!("execute_from_outside"),
+                calldata,
+            },
+        };
+
+        let result = parameters.find_gas_token_transfer(forwarder);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn extract_gas_permit_from_raw_call_works() {
+        let forwarder = felt!("0x123");
+        let token = felt!("0x456");
+        let amount = felt!("0x789");
+
+        let calldata = vec![
+            felt!("0x1"), // caller
+            felt!("0x2"), // nonce
+            felt!("0x3"), // execute_after
+            felt!("0x4"), // execute_before
+            Felt::ONE,    // num_calls = 1
+            // Allowance to the forwarder
+            token,                // to (token address)
+            selector!("approve"), // selector
             Felt::THREE,          // calldata_len
-            forwarder,            // recipient
-            felt!("0x789"),       // amount_low
+            forwarder,            // spender (forwarder)
+            amount,               // amount_low
             Felt::ZERO,           // amount_high
             Felt::TWO,            // signature length
             felt!("0xDEAD"),      // signature part 1
@@ -471,8 +820,10 @@ mod tests {
             },
         };
 
-        let result = parameters.find_gas_token_transfer(forwarder);
-        assert!(result.is_err());
+        let (transfer, fee_collection) = parameters.find_gas_token_transfer(forwarder).unwrap();
+        assert_eq!(transfer.token(), token);
+        assert_eq!(transfer.amount(), amount);
+        assert_eq!(fee_collection, FeeCollection::Permit);
     }
 
     #[test]
@@ -511,6 +862,66 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn extract_gas_transfer_to_relayer_works() {
+        let relayers = [felt!("0x123"), felt!("0x124")];
+        let token = felt!("0x456");
+
+        let calldata = vec![
+            felt!("0x124"), // caller (relayer)
+            felt!("0x2"),   // nonce
+            felt!("0x3"),   // execute_after
+            felt!("0x4"),   // execute_before
+            Felt::ONE,      // num_calls = 1
+            // Gas transfer to the relayer
+            token,                 // to (token address)
+            selector!("transfer"), // selector
+            Felt::THREE,           // calldata_len
+            relayers[1],           // recipient (relayer)
+            felt!("0xAAA"),        // amount_low
+            Felt::ZERO,            // amount_high
+            Felt::ONE,             // signature length
+            felt!("0xDEAD"),       // signature
+        ];
+
+        let parameters = ExecutableDirectInvokeParameters {
+            user: Felt::ZERO,
+            execute_from_outside_call: Call {
+                to: felt!("0x999"),
+                selector: selector!("execute_from_outside"),
+                calldata,
+            },
+        };
+
+        let (transfer, _) = parameters
+            .find_gas_token_transfer_to(|recipient| relayers.contains(&recipient))
+            .unwrap();
+        assert_eq!(transfer.recipient(), relayers[1]);
+        assert_eq!(transfer.amount(), felt!("0xAAA"));
+
+        assert!(parameters.find_gas_token_transfer(relayers[0]).is_err());
+    }
+
+    #[test]
+    fn raw_call_must_be_an_outside_execution_on_the_user_account() {
+        let user = felt!("0x999");
+        let parameters = |to: Felt, selector: Felt| ExecutableDirectInvokeParameters {
+            user,
+            execute_from_outside_call: Call { to, selector, calldata: vec![] },
+        };
+
+        assert!(parameters(user, selector!("execute_from_outside"))
+            .check_outside_execution_call()
+            .is_ok());
+        assert!(parameters(user, selector!("execute_from_outside_v2"))
+            .check_outside_execution_call()
+            .is_ok());
+        assert!(parameters(felt!("0x456"), selector!("execute_from_outside_v2"))
+            .check_outside_execution_call()
+            .is_err());
+        assert!(parameters(user, selector!("transfer")).check_outside_execution_call().is_err());
+    }
+
     #[test]
     fn extract_gas_transfer_fails_when_no_calls() {
         let forwarder = felt!("0x123");
@@ -567,7 +978,7 @@ mod tests {
         let test = TestEnvironment::new().await;
         let account = test.starknet.initialize_account(&StarknetTestEnvironment::ACCOUNT_1);
 
-        let new_account = test.starknet.initialize_argent_account(Felt::ONE).await;
+        let new_account = test.starknet.initialize_argent_account(Felt::ONE).await.unwrap();
         let salt = Felt::from(rand::rng().random_range(1..1_000_000_000));
         let new_account_address = new_account.deploy_v3(salt).address();
 
@@ -622,6 +1033,7 @@ mod tests {
                 invoke: InvokeParameters {
                     user_address: user.address,
                     calls: Calls::new(vec![an_eth_transfer(account.address(), Felt::ONE)]),
+                    session: false,
                 },
             },
             parameters: ExecutionParameters::V1 {
@@ -673,7 +1085,7 @@ mod tests {
         let test = TestEnvironment::new().await;
         let account = test.starknet.initialize_account(&StarknetTestEnvironment::ACCOUNT_1);
 
-        let new_account = test.starknet.initialize_argent_account(Felt::ONE).await;
+        let new_account = test.starknet.initialize_argent_account(Felt::ONE).await.unwrap();
         let salt = Felt::from(rand::rng().random_range(1..1_000_000_000));
         let new_account_address = new_account.deploy_v3(salt).address();
 
@@ -702,6 +1114,7 @@ mod tests {
                 invoke: InvokeParameters {
                     user_address: new_account_address,
                     calls: Calls::new(vec![an_eth_approve(account.address(), Felt::ZERO)]),
+                    session: false,
                 },
             },
             parameters: ExecutionParameters::V1 {
@@ -1,5 +1,7 @@
 use starknet::core::types::Felt;
 
+use crate::AppliedTip;
+
 #[derive(Debug)]
 pub struct FeeEstimate {
     pub gas_token_price_in_strk: Felt,
@@ -7,4 +9,10 @@ pub struct FeeEstimate {
     pub estimated_fee_in_gas_token: Felt,
     pub suggested_max_fee_in_strk: Felt,
     pub suggested_max_fee_in_gas_token: Felt,
+
+    /// Tip applied when estimating the transaction
+    pub tip: AppliedTip,
+
+    /// Whether the estimate was made in low-RPC mode, in which case it may have been reused and its max fee widened
+    pub low_rpc: bool,
 }
@@ -39,6 +39,19 @@ impl ValidationGasOverhead {
         }
     }
 
+    /// Additional cost induced by the validation of a session token, which the estimate of the build cannot include
+    /// since the typed data is not signed yet. It bounds the first transaction of a session, for which the account
+    /// checks the owner authorization before caching it, on top of the signatures of the session key and of the
+    /// guardian and of the Merkle proofs of the calls. The estimate of the execution runs the outside execution with
+    /// the actual session token so it does not rely on this bound.
+    pub fn session() -> Self {
+        Self {
+            l1_gas: Felt::ZERO,
+            l1_data_gas: Felt::ZERO,
+            l2_gas: felt!("0x03938700"),
+        }
+    }
+
     /// Returns the overhead approximation given the [`user`] address
     pub async fn fetch(client: &Client, user: ContractAddress) -> Result<Self, Error> {
         let call = FunctionCall {
@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use deadpool_redis::redis::cmd;
+use deadpool_redis::Pool;
+use paymaster_relayer::lock::LockLayerConfiguration;
+use starknet::core::types::{Felt, Hash256, ReceiptBlock, TransactionReceiptWithBlockInfo};
+
+use crate::Error;
+
+/// L2 transactions triggered by the messages of an L1 transaction once they have all been consumed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsumedMessages {
+    /// Hashes of the `l1_handler` transactions which consumed the messages
+    pub transactions: Vec<Felt>,
+
+    /// Payloads of the messages, as received by the `l1_handler` transactions
+    pub payloads: Vec<Vec<Felt>>,
+
+    /// Fee paid on L1 (in wei) for the execution of the `l1_handler` transactions
+    pub fee_in_wei: Felt,
+}
+
+impl ConsumedMessages {
+    /// Aggregate the receipts of the `l1_handler` transactions. Returns None if one of them is not
+    /// included in a block yet or was reverted since its fee cannot be sponsored in that case.
+    pub fn from_receipts(receipts: &[TransactionReceiptWithBlockInfo]) -> Option<Self> {
+        let mut consumed = Self {
+            transactions: vec![],
+            payloads: vec![],
+            fee_in_wei: Felt::ZERO,
+        };
+
+        for receipt in receipts {
+            if !matches!(receipt.block, ReceiptBlock::Block { .. }) || receipt.receipt.execution_result().revert_reason().is_some() {
+                return None;
+            }
+
+            consumed.transactions.push(*receipt.receipt.transaction_hash());
+            consumed.fee_in_wei += receipt.receipt.actual_fee().amount;
+        }
+
+        if consumed.transactions.is_empty() {
+            return None;
+        }
+
+        Some(consumed)
+    }
+
+    pub fn with_payloads(self, payloads: Vec<Vec<Felt>>) -> Self {
+        Self { payloads, ..self }
+    }
+
+    /// Returns true if `recipient` is part of the payload of every message, meaning the messages were sent to it
+    pub fn is_sent_to(&self, recipient: Felt) -> bool {
+        self.payloads.len() == self.transactions.len() && self.payloads.iter().all(|x| x.contains(&recipient))
+    }
+}
+
+/// Registry of the L1 transactions whose messages fee has been sponsored, ensuring each is only sponsored once.
+/// Shared by the instances through the Redis of the shared lock layer, kept in memory otherwise.
+#[derive(Clone, Default)]
+pub struct SponsoredMessages {
+    redis: Option<Pool>,
+    inner: Arc<Mutex<HashSet<[u8; 32]>>>,
+}
+
+impl SponsoredMessages {
+    pub fn new(lock: &LockLayerConfiguration) -> Result<Self, Error> {
+        let redis = match lock {
+            LockLayerConfiguration::Shared { redis, .. } => Some(redis.create_pool().map_err(|e| Error::Internal(e.to_string()))?),
+            _ => None,
+        };
+
+        Ok(Self { redis, ..Self::default() })
+    }
+
+    /// Reserve the given L1 transaction, before its fee is reimbursed. Returns false if it was already reserved
+    pub async fn reserve(&self, l1_transaction_hash: Hash256) -> Result<bool, Error> {
+        let Some(redis) = &self.redis else {
+            let mut reserved = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+            return Ok(reserved.insert(*l1_transaction_hash.as_bytes()));
+        };
+
+        let mut connection = redis.get().await.map_err(|e| Error::Internal(e.to_string()))?;
+        let result: Option<String> = cmd("SET")
+            .arg(Self::key(&l1_transaction_hash))
+            .arg(1)
+            .arg("NX")
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| Error::Internal(e.to_string()))?;
+
+        Ok(result.is_some())
+    }
+
+    /// Release the given L1 transaction, once its fee is known not to have been reimbursed
+    pub async fn release(&self, l1_transaction_hash: &Hash256) {
+        let Some(redis) = &self.redis else {
+            let mut reserved = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+            reserved.remove(l1_transaction_hash.as_bytes());
+            return;
+        };
+
+        if let Ok(mut connection) = redis.get().await {
+            let _: Result<usize, _> = cmd("DEL")
+                .arg(Self::key(l1_transaction_hash))
+                .query_async(&mut connection)
+                .await;
+        }
+    }
+
+    fn key(l1_transaction_hash: &Hash256) -> String {
+        format!("sponsored-message:{}", l1_transaction_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::{Felt, Hash256};
+
+    use crate::execution::message::{ConsumedMessages, SponsoredMessages};
+
+    #[tokio::test]
+    async fn message_is_only_sponsored_once() {
+        let messages = SponsoredMessages::default();
+        let hash = Hash256::from_bytes([1; 32]);
+
+        assert!(messages.reserve(hash).await.unwrap());
+        assert!(!messages.reserve(hash).await.unwrap());
+
+        messages.release(&hash).await;
+        assert!(messages.reserve(hash).await.unwrap());
+    }
+
+    #[test]
+    fn messages_are_only_reimbursed_to_their_recipient() {
+        let consumed = ConsumedMessages {
+            transactions: vec![Felt::ONE, Felt::TWO],
+            payloads: vec![],
+            fee_in_wei: Felt::from(100),
+        };
+        assert!(!consumed.is_sent_to(Felt::THREE));
+
+        let consumed = consumed.with_payloads(vec![vec![Felt::from(9), Felt::THREE, Felt::from(5)], vec![Felt::THREE]]);
+        assert!(consumed.is_sent_to(Felt::THREE));
+        assert!(!consumed.is_sent_to(Felt::from(9)));
+    }
+}
@@ -1,3 +1,6 @@
+mod account;
+pub use account::AccountStatus;
+
 mod build;
 pub use build::{EstimatedTransaction, InvokeParameters, Transaction, TransactionParameters, VersionedTransaction};
 
@@ -7,10 +10,16 @@ pub use deploy::DeploymentParameters;
 mod execute;
 pub use execute::{EstimatedExecutableTransaction, ExecutableDirectInvokeParameters, ExecutableInvokeParameters, ExecutableTransaction, ExecutableTransactionParameters};
 
+mod message;
+pub use message::{ConsumedMessages, SponsoredMessages};
+
+mod receipt;
+pub use receipt::{ExecutionReceipt, FeeQuote, GasConsumed};
+
 mod fee;
 pub use fee::{FeeEstimate, ValidationGasOverhead};
 use jsonrpsee::core::Serialize;
-use paymaster_starknet::constants::Token;
+use paymaster_starknet::constants::ChainTokens;
 pub use paymaster_starknet::transaction::TimeBounds;
 use serde::Deserialize;
 use starknet::core::types::Felt;
@@ -29,9 +38,9 @@ impl ExecutionParameters {
         }
     }
 
-    pub fn gas_token(&self) -> Felt {
+    pub fn gas_token(&self, tokens: &ChainTokens) -> Felt {
         match self {
-            Self::V1 { fee_mode, .. } => fee_mode.gas_token(),
+            Self::V1 { fee_mode, .. } => fee_mode.gas_token(tokens),
         }
     }
 
@@ -48,9 +57,39 @@ impl ExecutionParameters {
 
         time_bounds.unwrap_or(TimeBounds::valid_for(Duration::from_secs(3600)))
     }
+
+    /// Returns the parameters with time bounds valid for the given duration when the user did not set them
+    pub fn with_default_validity(self, validity: Duration) -> Self {
+        match self {
+            Self::V1 { fee_mode, time_bounds } => Self::V1 {
+                fee_mode,
+                time_bounds: time_bounds.or_else(|| Some(TimeBounds::valid_for(validity))),
+            },
+        }
+    }
+}
+
+/// Time spent in each stage of the submission of a transaction, in milliseconds
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecutionTimings {
+    /// Time spent waiting for an available relayer
+    pub lock_acquisition_ms: u64,
+
+    /// Time spent sending the transaction with the relayer, nonce retries included
+    pub submission_ms: u64,
+}
+
+impl ExecutionTimings {
+    pub fn add_lock_acquisition(&mut self, duration: Duration) {
+        self.lock_acquisition_ms += duration.as_millis() as u64;
+    }
+
+    pub fn add_submission(&mut self, duration: Duration) {
+        self.submission_ms += duration.as_millis() as u64;
+    }
 }
 
-#[derive(Serialize, Deserialize, Copy, Debug, Clone)]
+#[derive(Serialize, Deserialize, Copy, Debug, Clone, PartialEq, Eq)]
 pub enum TipPriority {
     Slow,
     Normal,
@@ -58,6 +97,71 @@ pub enum TipPriority {
     Custom(u64),
 }
 
+impl TipPriority {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Slow => "slow",
+            Self::Normal => "normal",
+            Self::Fast => "fast",
+            Self::Custom(_) => "custom",
+        }
+    }
+}
+
+/// Offset applied to the median tip of the last block for the slow and fast priorities
+pub const TIP_PRIORITY_OFFSET: u64 = 5;
+
+/// Tip applied to a transaction along with how it was derived from the requested priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppliedTip {
+    pub priority: TipPriority,
+
+    /// Median tip of the last block the tip was derived from. Not set for custom tips
+    pub median_tip: Option<u64>,
+
+    pub tip: u64,
+}
+
+impl AppliedTip {
+    pub fn new(priority: TipPriority, median_tip: u64) -> Self {
+        let tip = match priority {
+            TipPriority::Slow => median_tip.saturating_sub(TIP_PRIORITY_OFFSET),
+            TipPriority::Normal => median_tip,
+            TipPriority::Fast => median_tip + TIP_PRIORITY_OFFSET,
+            TipPriority::Custom(tip) => return Self { priority, median_tip: None, tip },
+        };
+
+        Self {
+            priority,
+            median_tip: Some(median_tip),
+            tip,
+        }
+    }
+
+    /// Returns the strategy used to compute the tip
+    pub fn strategy(&self) -> &'static str {
+        match self.priority {
+            TipPriority::Slow => "median_minus_offset",
+            TipPriority::Normal => "median",
+            TipPriority::Fast => "median_plus_offset",
+            TipPriority::Custom(_) => "custom",
+        }
+    }
+}
+
+/// How the fee in gas token is collected from the user of a non-sponsored transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FeeCollection {
+    /// The user transfers the maximum fee to the forwarder which refunds the excess after the execution
+    Transfer,
+
+    /// The user grants the forwarder an allowance of the maximum fee from which the forwarder pulls the fee
+    Permit,
+
+    /// The user transfers the fee straight to the relayer submitting the transaction, without the forwarder
+    Direct,
+}
+
 #[derive(Debug, Clone)]
 pub enum FeeMode {
     /// Standard fee mode when the user pays in the given token
@@ -72,11 +176,11 @@ impl FeeMode {
     }
 
     /// Returns the gas token corresponding to the  [`FeeMode`]. In the case where the transaction is sponsored
-    /// the gas token is set as the STRK token
-    pub fn gas_token(&self) -> Felt {
+    /// the gas token is set as the STRK token of the chain
+    pub fn gas_token(&self, tokens: &ChainTokens) -> Felt {
         match self {
             Self::Default { gas_token, tip: _ } => *gas_token,
-            Self::Sponsored { tip: _ } => Token::STRK_ADDRESS,
+            Self::Sponsored { tip: _ } => tokens.strk,
         }
     }
 
@@ -87,3 +191,20 @@ impl FeeMode {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::execution::{AppliedTip, TipPriority};
+
+    #[test]
+    fn applied_tip_is_derived_from_median() {
+        assert_eq!(AppliedTip::new(TipPriority::Normal, 10).tip, 10);
+        assert_eq!(AppliedTip::new(TipPriority::Fast, 10).tip, 15);
+        assert_eq!(AppliedTip::new(TipPriority::Slow, 3).tip, 0);
+
+        let custom = AppliedTip::new(TipPriority::Custom(42), 10);
+        assert_eq!(custom.tip, 42);
+        assert_eq!(custom.median_tip, None);
+        assert_eq!(custom.strategy(), "custom");
+    }
+}
@@ -0,0 +1,127 @@
+use paymaster_starknet::constants::ChainTokens;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{Felt, ReceiptBlock, TransactionReceiptWithBlockInfo};
+
+/// Fee quoted for a transaction when it was estimated right before being executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeQuote {
+    /// Token in which the user paid the fee. Set as the STRK token for sponsored transactions
+    pub gas_token: Felt,
+
+    /// Fee taken from the user in gas token. Zero for sponsored transactions
+    pub fee_in_token: Felt,
+
+    /// Fee charged in STRK, including the provider overhead. For sponsored transactions this
+    /// is the amount charged to the sponsor
+    pub fee_in_strk: Felt,
+}
+
+impl FeeQuote {
+    pub fn sponsored(tokens: &ChainTokens, fee_in_strk: Felt) -> Self {
+        Self {
+            gas_token: tokens.strk,
+            fee_in_token: Felt::ZERO,
+            fee_in_strk,
+        }
+    }
+}
+
+/// Gas actually consumed by a transaction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GasConsumed {
+    pub l1_gas: u64,
+    pub l1_data_gas: u64,
+    pub l2_gas: u64,
+}
+
+/// Receipt of a confirmed paymaster transaction, obtained by cross-referencing the on-chain
+/// receipt with the quote used when executing the transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionReceipt {
+    pub transaction_hash: Felt,
+    pub block_number: u64,
+
+    /// Reason of the revert if the transaction was reverted
+    pub revert_reason: Option<String>,
+
+    pub gas_consumed: GasConsumed,
+
+    /// Fee actually paid by the relayer in STRK
+    pub actual_fee_in_strk: Felt,
+
+    pub quote: FeeQuote,
+}
+
+impl ExecutionReceipt {
+    /// Build the receipt of a transaction given its on-chain receipt. Returns None when the transaction
+    /// is not yet included in a block.
+    pub fn new(receipt: &TransactionReceiptWithBlockInfo, quote: FeeQuote) -> Option<Self> {
+        let ReceiptBlock::Block { block_number, .. } = receipt.block else {
+            return None;
+        };
+
+        let resources = receipt.receipt.execution_resources();
+
+        Some(Self {
+            transaction_hash: *receipt.receipt.transaction_hash(),
+            block_number,
+
+            revert_reason: receipt.receipt.execution_result().revert_reason().map(str::to_string),
+
+            gas_consumed: GasConsumed {
+                l1_gas: resources.l1_gas,
+                l1_data_gas: resources.l1_data_gas,
+                l2_gas: resources.l2_gas,
+            },
+
+            actual_fee_in_strk: receipt.receipt.actual_fee().amount,
+            quote,
+        })
+    }
+
+    pub fn is_reverted(&self) -> bool {
+        self.revert_reason.is_some()
+    }
+
+    /// Margin made by the provider in STRK which is the fee charged minus the fee actually paid by the relayer.
+    /// The margin is negative when the transaction cost more than what was charged.
+    pub fn provider_margin_in_strk(&self) -> i128 {
+        to_i128(self.quote.fee_in_strk) - to_i128(self.actual_fee_in_strk)
+    }
+}
+
+// Fee amounts always fit in a u128, we saturate otherwise
+fn to_i128(value: Felt) -> i128 {
+    u128::try_from(value)
+        .ok()
+        .and_then(|x| i128::try_from(x).ok())
+        .unwrap_or(i128::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use crate::execution::receipt::{ExecutionReceipt, FeeQuote, GasConsumed};
+
+    fn a_receipt(fee_in_strk: u64, actual_fee_in_strk: u64) -> ExecutionReceipt {
+        ExecutionReceipt {
+            transaction_hash: Felt::ONE,
+            block_number: 1,
+            revert_reason: None,
+            gas_consumed: GasConsumed::default(),
+            actual_fee_in_strk: Felt::from(actual_fee_in_strk),
+            quote: FeeQuote {
+                gas_token: Felt::TWO,
+                fee_in_token: Felt::from(42),
+                fee_in_strk: Felt::from(fee_in_strk),
+            },
+        }
+    }
+
+    #[test]
+    fn provider_margin_is_computed_properly() {
+        assert_eq!(a_receipt(120, 100).provider_margin_in_strk(), 20);
+        assert_eq!(a_receipt(100, 120).provider_margin_in_strk(), -20);
+    }
+}
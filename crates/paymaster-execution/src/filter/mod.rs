@@ -29,4 +29,9 @@ impl TransactionDuplicateFilter {
 
         Ok(())
     }
+
+    /// Let the transaction through again, used when it was rejected before being sent
+    pub fn release(&self, transaction: &ExecutableTransactionParameters) {
+        self.duplicate_cache.remove(&transaction.get_unique_identifier());
+    }
 }
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use paymaster_common::metric;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{ExecutionResult, Felt, TransactionStatus};
+use tracing::debug;
+
+use crate::starknet::Client as Starknet;
+
+/// Maximum duration an execution waits for its transaction to reach the requested finality
+pub const FINALITY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Interval between two checks of the status of a transaction
+const FINALITY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Duration after which the state of a transaction is no longer tracked
+const FINALITY_RETENTION: Duration = Duration::from_secs(600);
+
+/// Guarantee an execution waits for before returning
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FinalityLevel {
+    /// Return as soon as the transaction is submitted
+    #[default]
+    Submitted,
+
+    /// Return once the transaction is part of a pre-confirmed block
+    PreConfirmed,
+
+    /// Return once the transaction is accepted on L2
+    AcceptedOnL2,
+}
+
+impl FinalityLevel {
+    fn status(&self) -> FinalityStatus {
+        match self {
+            Self::Submitted => FinalityStatus::Submitted,
+            Self::PreConfirmed => FinalityStatus::PreConfirmed,
+            Self::AcceptedOnL2 => FinalityStatus::AcceptedOnL2,
+        }
+    }
+}
+
+/// State of a submitted transaction, from the weakest to the strongest guarantee
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum FinalityStatus {
+    Submitted,
+    Received,
+    Candidate,
+    PreConfirmed,
+    AcceptedOnL2,
+    AcceptedOnL1,
+}
+
+impl FinalityStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Submitted => "submitted",
+            Self::Received => "received",
+            Self::Candidate => "candidate",
+            Self::PreConfirmed => "pre_confirmed",
+            Self::AcceptedOnL2 => "accepted_on_l2",
+            Self::AcceptedOnL1 => "accepted_on_l1",
+        }
+    }
+}
+
+/// Finality reached by a transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finality {
+    pub status: FinalityStatus,
+
+    /// Set when the transaction was executed and reverted
+    pub revert_reason: Option<String>,
+}
+
+impl Finality {
+    fn submitted() -> Self {
+        Self {
+            status: FinalityStatus::Submitted,
+            revert_reason: None,
+        }
+    }
+
+    fn from_status(status: &TransactionStatus) -> Self {
+        let (status, execution) = match status {
+            TransactionStatus::Received => (FinalityStatus::Received, None),
+            TransactionStatus::Candidate => (FinalityStatus::Candidate, None),
+            TransactionStatus::PreConfirmed(x) => (FinalityStatus::PreConfirmed, Some(x)),
+            TransactionStatus::AcceptedOnL2(x) => (FinalityStatus::AcceptedOnL2, Some(x)),
+            TransactionStatus::AcceptedOnL1(x) => (FinalityStatus::AcceptedOnL1, Some(x)),
+        };
+
+        Self {
+            status,
+            revert_reason: match execution {
+                Some(ExecutionResult::Reverted { reason }) => Some(reason.clone()),
+                _ => None,
+            },
+        }
+    }
+
+    /// Returns true if there is no point in waiting further for the given level
+    fn satisfies(&self, level: FinalityLevel) -> bool {
+        self.status >= level.status() || self.revert_reason.is_some()
+    }
+}
+
+/// Waits for the submitted transactions to reach the finality requested by the users and keeps track of
+/// the last state observed for each of them
+#[derive(Clone, Default)]
+pub struct FinalityWatcher {
+    states: Arc<RwLock<HashMap<Felt, (Finality, Instant)>>>,
+}
+
+impl FinalityWatcher {
+    /// Returns the last state observed for the given transaction, if it is still tracked
+    pub fn get(&self, transaction_hash: Felt) -> Option<Finality> {
+        let states = self.states.read().unwrap_or_else(|e| e.into_inner());
+        states.get(&transaction_hash).map(|(finality, _)| finality.clone())
+    }
+
+    /// Record the status of the transaction observed outside of [`wait`](Self::wait), only when it is still tracked
+    pub(crate) fn record_status(&self, transaction_hash: Felt, status: &TransactionStatus) {
+        if self.get(transaction_hash).is_some() {
+            self.record(transaction_hash, Finality::from_status(status));
+        }
+    }
+
+    fn record(&self, transaction_hash: Felt, finality: Finality) {
+        let now = Instant::now();
+        let mut states = self.states.write().unwrap_or_else(|e| e.into_inner());
+
+        let changed = states.get(&transaction_hash).is_none_or(|(x, _)| *x != finality);
+        if changed {
+            debug!(transaction_hash = %transaction_hash.to_fixed_hex_string(), status = finality.status.name(), "transaction state changed");
+            metric!(counter[execution_finality_transition] = 1, status = finality.status.name());
+        }
+
+        states.insert(transaction_hash, (finality, now));
+        states.retain(|_, (_, at)| now.duration_since(*at) < FINALITY_RETENTION);
+    }
+
+    /// Wait until the given transaction reaches the `level` of finality, is reverted or the `timeout` elapses,
+    /// and returns the last state observed. Errors while fetching the status are retried until the timeout.
+    pub async fn wait(&self, starknet: &Starknet, transaction_hash: Felt, level: FinalityLevel, timeout: Duration) -> Finality {
+        let started_at = Instant::now();
+
+        let mut finality = Finality::submitted();
+        self.record(transaction_hash, finality.clone());
+
+        while !finality.satisfies(level) {
+            if started_at.elapsed() >= timeout {
+                metric!(counter[execution_finality_timeout] = 1, level = level.status().name());
+                break;
+            }
+
+            tokio::time::sleep(FINALITY_POLL_INTERVAL).await;
+
+            if let Ok(status) = starknet.get_transaction_status(transaction_hash).await {
+                finality = Finality::from_status(&status);
+                self.record(transaction_hash, finality.clone());
+            }
+        }
+
+        metric!(
+            histogram[execution_finality_duration_milliseconds] = started_at.elapsed().as_millis(),
+            level = level.status().name()
+        );
+
+        finality
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::{ExecutionResult, Felt, TransactionStatus};
+
+    use crate::finality::{Finality, FinalityLevel, FinalityStatus, FinalityWatcher};
+
+    #[test]
+    fn finality_satisfies_weaker_levels() {
+        let finality = Finality::from_status(&TransactionStatus::AcceptedOnL2(ExecutionResult::Succeeded));
+
+        assert!(finality.satisfies(FinalityLevel::Submitted));
+        assert!(finality.satisfies(FinalityLevel::PreConfirmed));
+        assert!(finality.satisfies(FinalityLevel::AcceptedOnL2));
+
+        let finality = Finality::from_status(&TransactionStatus::Received);
+        assert!(!finality.satisfies(FinalityLevel::PreConfirmed));
+    }
+
+    #[test]
+    fn reverted_transactions_are_not_waited_for() {
+        let finality = Finality::from_status(&TransactionStatus::PreConfirmed(ExecutionResult::Reverted { reason: "boom".to_string() }));
+
+        assert_eq!(finality.status, FinalityStatus::PreConfirmed);
+        assert_eq!(finality.revert_reason.as_deref(), Some("boom"));
+        assert!(finality.satisfies(FinalityLevel::AcceptedOnL2));
+    }
+
+    #[test]
+    fn watcher_keeps_the_last_state() {
+        let watcher = FinalityWatcher::default();
+
+        watcher.record(Felt::ONE, Finality::submitted());
+        watcher.record(Felt::ONE, Finality::from_status(&TransactionStatus::Candidate));
+
+        assert_eq!(watcher.get(Felt::ONE).unwrap().status, FinalityStatus::Candidate);
+        assert_eq!(watcher.get(Felt::TWO), None);
+    }
+}
@@ -0,0 +1,62 @@
+use starknet::core::types::{Call, Felt};
+use starknet::macros::selector;
+
+use crate::hook::{CallHook, CallRewriter};
+use crate::Error;
+
+/// Append a call configured by the operator after the calls of the user
+pub struct AppendCallHook {
+    pub to: Felt,
+    pub selector: Felt,
+    pub calldata: Vec<Felt>,
+    pub with_user_address: bool,
+}
+
+impl CallHook for AppendCallHook {
+    fn name(&self) -> &'static str {
+        "append_call"
+    }
+
+    fn apply(&self, user: Felt, rewriter: &mut CallRewriter) -> Result<(), Error> {
+        let calldata = if self.with_user_address {
+            [vec![user], self.calldata.clone()].concat()
+        } else {
+            self.calldata.clone()
+        };
+
+        rewriter.append(Call {
+            to: self.to,
+            selector: self.selector,
+            calldata,
+        })
+    }
+}
+
+/// Lower the amount of the `approve` calls of the user. The amount is encoded as an u256 (low, high)
+/// following the spender in the calldata.
+pub struct CapApprovalsHook {
+    pub max_amount: Felt,
+}
+
+impl CallHook for CapApprovalsHook {
+    fn name(&self) -> &'static str {
+        "cap_approvals"
+    }
+
+    fn apply(&self, _: Felt, rewriter: &mut CallRewriter) -> Result<(), Error> {
+        let approvals: Vec<(usize, Felt)> = rewriter
+            .user_calls()
+            .iter()
+            .enumerate()
+            .filter(|(_, call)| call.selector == selector!("approve") && call.calldata.len() == 3)
+            .filter(|(_, call)| call.calldata[2] != Felt::ZERO || call.calldata[1] > self.max_amount)
+            .map(|(i, call)| (i, call.calldata[0]))
+            .collect();
+
+        for (index, spender) in approvals {
+            rewriter.rewrite_calldata(index, vec![spender, self.max_amount, Felt::ZERO])?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{Call, Felt};
+
+use crate::hook::builtin::{AppendCallHook, CapApprovalsHook};
+use crate::Error;
+
+mod builtin;
+
+/// Configuration of the hooks applied to the calls of the users before their transaction is built
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HooksConfiguration {
+    /// Hooks applied to the transactions of the requests without a sponsor specific configuration
+    #[serde(default)]
+    pub default: Vec<HookConfiguration>,
+
+    /// Hooks applied to the transactions of a sponsor, indexed by fingerprint of its api key (keccak of the key).
+    /// They replace the default hooks.
+    #[serde(default)]
+    pub sponsors: HashMap<Felt, Vec<HookConfiguration>>,
+
+    /// Maximum number of calls the hooks may append to the calls of a user
+    #[serde(default = "HooksConfiguration::default_max_appended_calls")]
+    pub max_appended_calls: usize,
+}
+
+impl HooksConfiguration {
+    fn default_max_appended_calls() -> usize {
+        2
+    }
+}
+
+impl Default for HooksConfiguration {
+    fn default() -> Self {
+        Self {
+            default: vec![],
+            sponsors: HashMap::new(),
+            max_appended_calls: Self::default_max_appended_calls(),
+        }
+    }
+}
+
+impl Validate for HooksConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        for (i, hook) in self.default.iter().enumerate() {
+            report.field(&format!("default[{}]", i), hook);
+        }
+
+        for (sponsor, hooks) in &self.sponsors {
+            for (i, hook) in hooks.iter().enumerate() {
+                report.field(&format!("sponsors.{}[{}]", sponsor.to_hex_string(), i), hook);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HookConfiguration {
+    /// Append a call after the calls of the user (e.g. to register a referral or emit an analytics event).
+    /// When `with_user_address` is set, the address of the user is prepended to the calldata.
+    AppendCall {
+        to: Felt,
+        selector: Felt,
+        calldata: Vec<Felt>,
+
+        #[serde(default)]
+        with_user_address: bool,
+    },
+
+    /// Lower the amount of the `approve` calls of the user to `max_amount`
+    CapApprovals { max_amount: Felt },
+}
+
+impl Validate for HookConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        match self {
+            Self::AppendCall { to, .. } => report.ensure(*to != Felt::ZERO, "to", "must not be zero"),
+            Self::CapApprovals { max_amount } => report.ensure(*max_amount != Felt::ZERO, "max_amount", "must not be zero"),
+        };
+    }
+}
+
+impl HookConfiguration {
+    fn build(&self) -> Arc<dyn CallHook> {
+        match self {
+            Self::AppendCall {
+                to,
+                selector,
+                calldata,
+                with_user_address,
+            } => Arc::new(AppendCallHook {
+                to: *to,
+                selector: *selector,
+                calldata: calldata.clone(),
+                with_user_address: *with_user_address,
+            }),
+            Self::CapApprovals { max_amount } => Arc::new(CapApprovalsHook { max_amount: *max_amount }),
+        }
+    }
+}
+
+/// Hook which inspects and rewrites the calls of a user before the transaction is built. Hooks can only act
+/// through the [`CallRewriter`] which bounds what they are allowed to modify.
+pub trait CallHook: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn apply(&self, user: Felt, rewriter: &mut CallRewriter) -> Result<(), Error>;
+}
+
+/// Restricted view over the calls of a user given to the hooks. Hooks can append a bounded number of calls
+/// after the calls of the user and rewrite the calldata of the calls of the user as long as the target, the
+/// selector and the length of the calldata are unchanged. They can never remove nor reorder calls.
+pub struct CallRewriter {
+    calls: Vec<Call>,
+    user_calls: usize,
+    max_appended_calls: usize,
+}
+
+impl CallRewriter {
+    fn new(calls: Vec<Call>, max_appended_calls: usize) -> Self {
+        Self {
+            user_calls: calls.len(),
+            calls,
+            max_appended_calls,
+        }
+    }
+
+    /// Returns the calls of the user
+    pub fn user_calls(&self) -> &[Call] {
+        &self.calls[..self.user_calls]
+    }
+
+    /// Append a call after the calls of the user
+    pub fn append(&mut self, call: Call) -> Result<(), Error> {
+        if self.calls.len() - self.user_calls >= self.max_appended_calls {
+            return Err(Error::HookViolation(format!("cannot append more than {} calls", self.max_appended_calls)));
+        }
+
+        self.calls.push(call);
+        Ok(())
+    }
+
+    /// Replace the calldata of the call of the user at `index`
+    pub fn rewrite_calldata(&mut self, index: usize, calldata: Vec<Felt>) -> Result<(), Error> {
+        if index >= self.user_calls {
+            return Err(Error::HookViolation(format!("call {} is not a call of the user", index)));
+        }
+
+        let call = &mut self.calls[index];
+        if call.calldata.len() != calldata.len() {
+            return Err(Error::HookViolation(format!("calldata length of call {} cannot change", index)));
+        }
+
+        call.calldata = calldata;
+        Ok(())
+    }
+
+    fn into_calls(self) -> Vec<Call> {
+        self.calls
+    }
+}
+
+/// Hooks applied to the calls of the users, either the default ones or the ones of the sponsor
+#[derive(Clone, Default)]
+pub struct CallHooks {
+    default: Vec<Arc<dyn CallHook>>,
+    sponsors: HashMap<Felt, Vec<Arc<dyn CallHook>>>,
+
+    max_appended_calls: usize,
+}
+
+impl CallHooks {
+    pub fn new(configuration: &HooksConfiguration) -> Self {
+        Self {
+            default: configuration.default.iter().map(|x| x.build()).collect(),
+            sponsors: configuration
+                .sponsors
+                .iter()
+                .map(|(sponsor, hooks)| (*sponsor, hooks.iter().map(|x| x.build()).collect()))
+                .collect(),
+
+            max_appended_calls: configuration.max_appended_calls,
+        }
+    }
+
+    /// Register a custom hook applied to the transactions of the given sponsor or by default if no sponsor is given
+    pub fn register(&mut self, sponsor: Option<Felt>, hook: Arc<dyn CallHook>) {
+        match sponsor {
+            Some(sponsor) => self.sponsors.entry(sponsor).or_default().push(hook),
+            None => self.default.push(hook),
+        }
+    }
+
+    /// Returns true if the given sponsor has its own hooks
+    pub fn has_sponsor(&self, sponsor: Felt) -> bool {
+        self.sponsors.contains_key(&sponsor)
+    }
+
+    /// Apply the hooks of the `sponsor`, given by the fingerprint of its api key, or the default ones if the sponsor
+    /// has no hooks, to the calls of the `user`
+    pub fn apply(&self, sponsor: Option<Felt>, user: Felt, calls: Vec<Call>) -> Result<Vec<Call>, Error> {
+        let hooks = sponsor.and_then(|x| self.sponsors.get(&x)).unwrap_or(&self.default);
+        if hooks.is_empty() {
+            return Ok(calls);
+        }
+
+        let mut rewriter = CallRewriter::new(calls, self.max_appended_calls);
+        for hook in hooks {
+            hook.apply(user, &mut rewriter).map_err(|e| match e {
+                Error::HookViolation(message) => Error::HookViolation(format!("{}: {}", hook.name(), message)),
+                e => e,
+            })?;
+        }
+
+        Ok(rewriter.into_calls())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::{Call, Felt};
+    use starknet::core::utils::starknet_keccak;
+    use starknet::macros::selector;
+
+    use crate::hook::{CallHooks, HookConfiguration, HooksConfiguration};
+
+    fn an_approve(amount: u64) -> Call {
+        Call {
+            to: Felt::ONE,
+            selector: selector!("approve"),
+            calldata: vec![Felt::TWO, Felt::from(amount), Felt::ZERO],
+        }
+    }
+
+    #[test]
+    fn sponsor_hooks_replace_default_hooks() {
+        let hooks = CallHooks::new(&HooksConfiguration {
+            default: vec![HookConfiguration::CapApprovals { max_amount: Felt::from(10) }],
+            sponsors: [(
+                starknet_keccak(b"sponsor"),
+                vec![HookConfiguration::AppendCall {
+                    to: Felt::THREE,
+                    selector: selector!("register_referral"),
+                    calldata: vec![Felt::from(42)],
+                    with_user_address: true,
+                }],
+            )]
+            .into(),
+            max_appended_calls: 1,
+        });
+
+        let calls = hooks.apply(None, Felt::from(7), vec![an_approve(100)]).unwrap();
+        assert_eq!(calls, vec![an_approve(10)]);
+
+        let calls = hooks
+            .apply(Some(starknet_keccak(b"sponsor")), Felt::from(7), vec![an_approve(100)])
+            .unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0], an_approve(100));
+        assert_eq!(calls[1].calldata, vec![Felt::from(7), Felt::from(42)]);
+    }
+
+    #[test]
+    fn hooks_cannot_exceed_appended_calls_bound() {
+        let append = HookConfiguration::AppendCall {
+            to: Felt::THREE,
+            selector: selector!("emit"),
+            calldata: vec![],
+            with_user_address: false,
+        };
+
+        let hooks = CallHooks::new(&HooksConfiguration {
+            default: vec![append.clone(), append],
+            max_appended_calls: 1,
+            ..Default::default()
+        });
+
+        assert!(hooks.apply(None, Felt::ONE, vec![an_approve(1)]).is_err());
+    }
+}
@@ -1,34 +1,74 @@
 mod execution;
 
-use std::cmp::max;
 use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use ::starknet::core::types::{Felt, InvokeTransactionResult, NonZeroFelt};
+use ::starknet::core::types::{Felt, Hash256, InvokeTransactionResult, MessageFeeEstimate, MsgFromL1, NonZeroFelt, Transaction};
 pub use execution::*;
 
+pub mod analytics;
+pub mod callback;
+pub mod cost;
 pub mod diagnostics;
+pub mod finality;
+pub mod hook;
+pub mod low_rpc;
+pub mod nonce;
+pub mod profitability;
+pub mod quote;
+pub mod recipient;
+pub mod refund;
+pub mod reorg;
+pub mod simulation;
+pub mod stage;
 pub mod tokens;
+pub mod whitelist;
 
 #[cfg(feature = "testing")]
 pub mod testing;
 
+mod clock;
 mod error;
 mod starknet;
 
-use diagnostics::DiagnosticClient;
+use diagnostics::{DiagnosticClient, ExecutionDiagnosis};
 pub use error::Error;
+use finality::{Finality, FinalityLevel, FinalityWatcher, FINALITY_TIMEOUT};
+use futures::{stream, StreamExt};
+use low_rpc::{LowRpcConfiguration, LowRpcMode};
+use nonce::{EstimateAccountNonce, NonceDriftContext, NonceDriftService};
+use paymaster_common::service::TokioServiceManager;
+use paymaster_common::validation::{Validate, ValidationReport};
 use paymaster_common::{measure_duration, metric};
-use paymaster_prices::{Client as PriceClient, PriceConfiguration};
-use paymaster_relayer::{LockedRelayer, RelayerManager, RelayerManagerConfiguration, RelayersConfiguration};
-use paymaster_starknet::transaction::{Calls, EstimatedCalls};
+use paymaster_prices::math::{convert_strk_to_token_rounded, RoundingPolicy};
+use paymaster_prices::{Client as PriceClient, PriceConfiguration, TokenPrice};
+use paymaster_relayer::multisig::MultisigConfiguration;
+use paymaster_relayer::{LockedRelayer, RelayerManager, RelayerManagerConfiguration, RelayerSelection, RelayersConfiguration};
+use paymaster_starknet::transaction::{Calls, EstimatedCalls, TokenTransfer};
 use paymaster_starknet::{Configuration as StarknetConfiguration, ContractAddress, StarknetAccount, StarknetAccountConfiguration};
+use profitability::ProfitabilityConfiguration;
+use quote::QuoteTtlConfiguration;
+use reorg::ExecutedTransactions;
+use simulation::{ExecutionLedger, PricingParameters, PricingSimulation};
+use stage::{measure_stage, Stage};
 use thiserror::Error;
+use tokens::metadata::TokenMetadataOverrides;
+use tracing::instrument;
 mod filter;
+mod limiter;
 
 pub use filter::TransactionDuplicateFilter;
+pub use limiter::{ExecutionLimiter, EXECUTION_RETRY_AFTER};
 
 use crate::starknet::Client as Starknet;
 
+/// Duration after which the inclusion of an executed transaction is no longer tracked
+const INCLUSION_TRACKING_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Number of receipts fetched at the same time when simulating the pricing
+const RECEIPT_FETCH_CONCURRENCY: usize = 8;
+
 /// Execution client configuration
 #[derive(Clone, Debug)]
 pub struct Configuration {
@@ -41,6 +81,9 @@ pub struct Configuration {
     /// Account used to receive the fee in gas token.
     pub gas_tank: StarknetAccountConfiguration,
 
+    /// Set when the gas tank is a multisig, in which case its transactions are proposed to the signers.
+    pub gas_tank_multisig: Option<MultisigConfiguration>,
+
     /// Multiply the estimated fee by this factor to produce the maximum amount of fee
     /// we expect the user to pay. When the transaction is built, the user must approve
     /// the maximum fee amount the larger the multiplier the larger the approve.
@@ -52,19 +95,74 @@ pub struct Configuration {
     /// are computed as (1.0 + provider_overhead) * fee_estimate.
     pub provider_fee_overhead: f32,
 
+    /// Rounding of the fees converted from STRK to the gas token, rounded up by default
+    pub fee_rounding: RoundingPolicy,
+
     pub supported_tokens: HashSet<Felt>,
 
+    /// Metadata of the supported tokens set by the operator. The fee of a transaction is raised to the minimum
+    /// fee configured for its gas token, if any.
+    pub token_metadata: TokenMetadataOverrides,
+
+    /// Gas tokens for which the fee is collected through an allowance granted in the *execute_from_outside* message
+    /// rather than a transfer. Requires a forwarder exposing `execute_with_permit`.
+    pub permit_tokens: HashSet<Felt>,
+
+    /// Have the users transfer the fee straight to the relayer submitting their transaction rather than to the
+    /// forwarder. Meant for small deployments, the fee is not refunded and does not reach the gas tank.
+    pub direct_fee_payment: bool,
+
+    /// Reject the transactions whose fee does not cover their cost with a minimum margin when set
+    pub profitability: Option<ProfitabilityConfiguration>,
+
+    /// Derive the validity of the quotes from the volatility of the gas token when set. Quotes are otherwise valid for an hour.
+    pub quote_ttl: Option<QuoteTtlConfiguration>,
+
+    /// Degraded mode reusing the recent estimates and refreshing the gas price less frequently, toggled at runtime
+    pub low_rpc: LowRpcConfiguration,
+
     pub starknet: StarknetConfiguration,
     pub price: PriceConfiguration,
 
     pub relayers: RelayersConfiguration,
 }
 
+impl Validate for Configuration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.field("estimate_account", &self.estimate_account);
+        report.ensure(self.max_fee_multiplier >= 1.0, "max_fee_multiplier", "must be greater than or equal to 1.0");
+        report.ensure(self.provider_fee_overhead >= 0.0, "provider_fee_overhead", "must be positive");
+        report.field("fee_rounding", &self.fee_rounding);
+        report.field("token_metadata", &self.token_metadata);
+        report.ensure(
+            self.permit_tokens.is_subset(&self.supported_tokens),
+            "permit_tokens",
+            "must only contain supported tokens",
+        );
+        report.ensure(
+            !self.direct_fee_payment || self.permit_tokens.is_empty(),
+            "permit_tokens",
+            "must be empty when the fee is paid directly to the relayers",
+        );
+        if let Some(profitability) = &self.profitability {
+            report.field("profitability", profitability);
+        }
+        if let Some(quote_ttl) = &self.quote_ttl {
+            report.field("quote_ttl", quote_ttl);
+        }
+        report.field("low_rpc", &self.low_rpc);
+
+        // Remaining fields are shared with the relayer manager configuration and validated there
+        RelayerManagerConfiguration::from(self.clone()).validate_into(report);
+    }
+}
+
 impl From<Configuration> for RelayerManagerConfiguration {
     fn from(value: Configuration) -> Self {
         RelayerManagerConfiguration {
             starknet: value.starknet,
             gas_tank: value.gas_tank,
+            gas_tank_multisig: value.gas_tank_multisig,
             supported_tokens: value.supported_tokens,
             relayers: value.relayers,
             price: value.price,
@@ -78,64 +176,178 @@ pub struct Client {
     pub starknet: Starknet,
     pub price: PriceClient,
 
+    /// Degraded mode used when the quota of the Starknet RPC is constrained
+    pub low_rpc: LowRpcMode,
+
     max_fee_multiplier: f32,
     provider_fee_multiplier: f32,
+    fee_rounding: RoundingPolicy,
+
+    permit_tokens: HashSet<Felt>,
+    token_metadata: TokenMetadataOverrides,
+    direct_fee_payment: bool,
+    profitability: Option<ProfitabilityConfiguration>,
+    quote_ttl: Option<QuoteTtlConfiguration>,
 
     estimate_account: StarknetAccount,
+    estimate_account_nonce: NonceDriftContext,
     relayers: RelayerManager,
+    executions: ExecutionLimiter,
+    finality: FinalityWatcher,
+    executed: ExecutedTransactions,
 
     pub diagnostic_client: DiagnosticClient,
+
+    #[allow(dead_code)]
+    services: Arc<TokioServiceManager<NonceDriftContext>>,
 }
 
 impl Client {
-    /// Creates a new client given a configuration
-    pub fn new(configuration: &Configuration) -> Self {
-        Self {
-            starknet: Starknet::new(&configuration.starknet),
+    /// Creates a new client given a configuration. Fails when the configuration cannot be used to reach
+    /// Starknet or to coordinate the relayers
+    pub fn new(configuration: &Configuration) -> Result<Self, Error> {
+        let low_rpc = LowRpcMode::new(&configuration.low_rpc);
+        let starknet = Starknet::new(&configuration.starknet)?.with_low_rpc(low_rpc.clone());
+
+        let estimate_account_nonce = NonceDriftContext::new(starknet.clone(), configuration.estimate_account.address, EstimateAccountNonce::default());
+        let mut services = TokioServiceManager::new(estimate_account_nonce.clone());
+        services.spawn::<NonceDriftService>();
+
+        Ok(Self {
+            low_rpc,
             price: PriceClient::new(&configuration.price),
 
             max_fee_multiplier: configuration.max_fee_multiplier,
             provider_fee_multiplier: 1.0 + configuration.provider_fee_overhead,
-
-            estimate_account: Starknet::new(&configuration.starknet).initialize_account(&configuration.estimate_account),
-            relayers: RelayerManager::new(&configuration.clone().into()),
+            fee_rounding: configuration.fee_rounding,
+
+            permit_tokens: configuration.permit_tokens.clone(),
+            token_metadata: configuration.token_metadata.clone(),
+            direct_fee_payment: configuration.direct_fee_payment,
+            profitability: configuration.profitability.clone(),
+            quote_ttl: configuration.quote_ttl.clone(),
+
+            estimate_account: starknet.initialize_account(&configuration.estimate_account),
+            estimate_account_nonce,
+            relayers: RelayerManager::new(&configuration.clone().into())?,
+            executions: ExecutionLimiter::with_reserve(
+                configuration.relayers.max_concurrent_executions(),
+                configuration.relayers.reserved_deployment_executions(),
+            ),
+            finality: FinalityWatcher::default(),
+            executed: ExecutedTransactions::default(),
 
             diagnostic_client: DiagnosticClient::new(configuration.starknet.chain_id),
-        }
+            starknet,
+            services: Arc::new(services),
+        })
     }
 
-    /// Execute the calls after they have been estimated. See method [`estimate`]
+    /// Execute the calls after they have been estimated. See method [`estimate`]. Fails with [`Error::Busy`]
+    /// when too many executions are already in progress.
     pub async fn execute(&self, calls: &EstimatedCalls) -> Result<InvokeTransactionResult, Error> {
-        let mut relayer = self.relayers.lock_relayer().await?;
+        self.execute_timed(calls).await.map(|(result, _)| result)
+    }
+
+    /// Same as [`execute`], also returning the time spent in each stage of the submission
+    pub async fn execute_timed(&self, calls: &EstimatedCalls) -> Result<(InvokeTransactionResult, ExecutionTimings), Error> {
+        self.execute_timed_with(calls, None, RelayerSelection::default()).await
+    }
 
-        let (result, duration) = measure_duration!(self.execute_with_retries(&mut relayer, calls, 3).await);
+    /// Same as [`execute_timed`], submitting the calls with the given relayer when one is given, otherwise with a
+    /// relayer matching the selection. Deployments may use the execution slots reserved to them.
+    pub async fn execute_timed_with(
+        &self,
+        calls: &EstimatedCalls,
+        relayer: Option<Felt>,
+        selection: RelayerSelection,
+    ) -> Result<(InvokeTransactionResult, ExecutionTimings), Error> {
+        let _permit = match selection.deployment {
+            true => self.executions.try_acquire_deployment()?,
+            false => self.executions.try_acquire()?,
+        };
+
+        let mut timings = ExecutionTimings::default();
+        let (result, duration) = measure_duration!(
+            self.execute_on_alternative_relayers(calls, relayer, selection, &mut timings)
+                .await
+        );
         metric!(counter[execution_request] = 1, method = "execute");
         metric!(histogram[execution_request_duration_milliseconds] = duration.as_millis(), method = "execute");
 
         match result {
             Ok(result) => {
-                let _ = self.relayers.release_relayer(relayer).await;
-
-                Ok(result)
+                self.executed.track(result.transaction_hash, calls.calls().clone());
+                Ok((result, timings))
             },
             Err(Error::InvalidNonce) => {
                 metric!(counter[execution_request_error] = 1, method = "execute", error = "invalid_nonce");
-                let _ = self.relayers.release_relayer_delayed(relayer, 20).await;
-
                 Err(Error::InvalidNonce)
             },
             Err(e) => {
                 metric!(counter[execution_request_error] = 1, method = "execute", error = e.to_string());
-                let _ = self.relayers.release_relayer(relayer).await;
-
                 Err(e)
             },
         }
     }
 
+    // Execute the transaction on a relayer and, if it keeps failing because of an invalid nonce, once more on
+    // another relayer. The failing relayer is released with a delay so that it cannot be picked again right away.
+    // A transaction assigned to a relayer is only ever executed on it.
+    async fn execute_on_alternative_relayers(
+        &self,
+        calls: &EstimatedCalls,
+        relayer: Option<Felt>,
+        selection: RelayerSelection,
+        timings: &mut ExecutionTimings,
+    ) -> Result<InvokeTransactionResult, Error> {
+        match self.execute_on_relayer(calls, relayer, selection, timings).await {
+            Err(Error::InvalidNonce) if relayer.is_none() => {
+                metric!(counter[execution_relayer_switch] = 1, reason = "invalid_nonce");
+                self.execute_on_relayer(calls, relayer, selection, timings).await
+            },
+            result => result,
+        }
+    }
+
+    async fn execute_on_relayer(
+        &self,
+        calls: &EstimatedCalls,
+        relayer: Option<Felt>,
+        selection: RelayerSelection,
+        timings: &mut ExecutionTimings,
+    ) -> Result<InvokeTransactionResult, Error> {
+        let (relayer, duration) = measure_duration!(
+            measure_stage("execute", Stage::LockAcquisition, async {
+                match relayer {
+                    Some(address) => self.relayers.lock_relayer_at(address).await,
+                    None => self.relayers.lock_relayer_for(selection).await,
+                }
+            })
+            .await
+        );
+        timings.add_lock_acquisition(duration);
+        let mut relayer = relayer?;
+
+        let (result, duration) = measure_duration!(measure_stage("execute", Stage::Submission, self.execute_with_retries(&mut relayer, calls, 3)).await);
+        timings.add_submission(duration);
+
+        match result {
+            Err(Error::InvalidNonce) => {
+                let _ = self.relayers.release_relayer_delayed(relayer, 20).await;
+                Err(Error::InvalidNonce)
+            },
+            result => {
+                let _ = self.relayers.release_relayer(relayer).await;
+                result
+            },
+        }
+    }
+
     // Execute the transaction at most n times in the case where it fails because of an invalid nonce.
     // Note that if the transaction fails for a differant reason than an invalid nonce, this function returns the
     // error.
+    #[instrument(name = "execute_with_relayer", skip(self, relayer, calls), fields(relayer = %relayer.address().to_hex_string()))]
     async fn execute_with_retries(&self, relayer: &mut LockedRelayer, calls: &EstimatedCalls, n_retries: usize) -> Result<InvokeTransactionResult, Error> {
         for _ in 0..n_retries {
             match relayer.execute(calls).await {
@@ -148,23 +360,201 @@ impl Client {
         Err(Error::InvalidNonce)
     }
 
+    /// Fetch the receipt of a transaction that was executed with the given `quote`. Returns None while the
+    /// transaction is not confirmed.
+    pub async fn fetch_execution_receipt(&self, transaction_hash: Felt, quote: FeeQuote) -> Result<Option<ExecutionReceipt>, Error> {
+        match self.starknet.get_transaction_receipt(transaction_hash).await {
+            Ok(receipt) => Ok(ExecutionReceipt::new(&receipt, quote)),
+            Err(paymaster_starknet::Error::TransactionNotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Wait for the given transaction to reach the `level` of finality requested by the user. Returns the last
+    /// state observed, which is below the requested level if it was not reached in time.
+    pub async fn wait_for_finality(&self, transaction_hash: Felt, level: FinalityLevel) -> Finality {
+        self.finality
+            .wait(&self.starknet, transaction_hash, level, FINALITY_TIMEOUT)
+            .await
+    }
+
+    /// Returns the last state observed for a transaction whose finality was waited for, if it is still tracked
+    pub fn finality(&self, transaction_hash: Felt) -> Option<Finality> {
+        self.finality.get(transaction_hash)
+    }
+
+    /// Fetch the deployment status of the account at `address` and its support of the outside execution
+    pub async fn fetch_account_status(&self, address: Felt) -> Result<AccountStatus, Error> {
+        AccountStatus::fetch(&self.starknet, address).await
+    }
+
+    /// Replay the `limit` most recent transactions of the `ledger` against the `proposed` pricing parameters. The fee
+    /// actually paid for the transactions which are not settled yet is fetched beforehand, a few at a time.
+    pub async fn simulate_pricing(&self, ledger: &ExecutionLedger, proposed: PricingParameters, limit: usize) -> Result<PricingSimulation, Error> {
+        let mut records = ledger.recent(limit).await?;
+
+        let receipts: Vec<_> = stream::iter(records.iter().filter(|x| x.actual_fee_in_strk.is_none()))
+            .map(|record| self.fetch_execution_receipt(record.transaction_hash, record.quote))
+            .buffer_unordered(RECEIPT_FETCH_CONCURRENCY)
+            .collect()
+            .await;
+        for receipt in receipts {
+            let Some(receipt) = receipt? else { continue };
+
+            ledger.settle(receipt.transaction_hash, receipt.actual_fee_in_strk).await?;
+            if let Some(record) = records.iter_mut().find(|x| x.transaction_hash == receipt.transaction_hash) {
+                record.actual_fee_in_strk = Some(receipt.actual_fee_in_strk);
+            }
+        }
+
+        let current = PricingParameters {
+            max_fee_multiplier: self.max_fee_multiplier,
+            provider_fee_overhead: self.provider_fee_multiplier - 1.0,
+        };
+
+        Ok(PricingSimulation::run(&records, current, proposed))
+    }
+
+    /// Estimate the fee of the `l1_handler` triggered by the given L1 `message`
+    pub async fn estimate_message(&self, message: &MsgFromL1) -> Result<MessageFeeEstimate, Error> {
+        let result = self.starknet.estimate_message_fee(message).await?;
+
+        Ok(result)
+    }
+
+    /// Fetch the `l1_handler` transactions triggered by the messages of the L1 transaction with hash
+    /// `l1_transaction_hash`. Returns None while they are not all confirmed.
+    pub async fn fetch_consumed_messages(&self, l1_transaction_hash: Hash256) -> Result<Option<ConsumedMessages>, Error> {
+        let statuses = self.starknet.get_messages_status(l1_transaction_hash).await?;
+
+        let mut receipts = vec![];
+        let mut payloads = vec![];
+        for status in statuses {
+            match self.starknet.get_transaction_receipt(status.transaction_hash).await {
+                Ok(receipt) => receipts.push(receipt),
+                Err(paymaster_starknet::Error::TransactionNotFound) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+
+            // The calldata of an `l1_handler` transaction is the L1 sender followed by the payload of the message
+            if let Transaction::L1Handler(transaction) = self.starknet.get_transaction(status.transaction_hash).await? {
+                payloads.push(transaction.calldata.into_iter().skip(1).collect());
+            }
+        }
+
+        Ok(ConsumedMessages::from_receipts(&receipts).map(|x| x.with_payloads(payloads)))
+    }
+
     /// Estimate the gas cost of a sequence of calls using the account configured for estimation
     pub async fn estimate(&self, calls: &Calls, tip: TipPriority) -> Result<EstimatedCalls, Error> {
         let tip = self.get_tip(tip).await?;
+        self.estimate_with_tip(calls, tip).await
+    }
+
+    /// Estimate the gas cost of a sequence of calls with the given tip using the account configured for estimation
+    pub async fn estimate_with_tip(&self, calls: &Calls, tip: u64) -> Result<EstimatedCalls, Error> {
         let result = calls.estimate(&self.estimate_account, Some(tip)).await?;
 
         Ok(result)
     }
 
+    /// Returns how the fee is collected when paid with the given gas token
+    pub fn fee_collection(&self, gas_token: Felt) -> FeeCollection {
+        if self.direct_fee_payment {
+            FeeCollection::Direct
+        } else if self.permit_tokens.contains(&gas_token) {
+            FeeCollection::Permit
+        } else {
+            FeeCollection::Transfer
+        }
+    }
+
+    /// Returns true if the users pay the fee directly to the relayer executing their transaction
+    pub fn pays_fee_directly(&self) -> bool {
+        self.direct_fee_payment
+    }
+
+    /// Convert a fee from STRK to the gas token following the rounding policy of the instance
+    pub fn convert_fee_to_token(&self, token_price: &TokenPrice, fee_in_strk: Felt) -> Result<Felt, Error> {
+        Ok(convert_strk_to_token_rounded(token_price, fee_in_strk, &self.fee_rounding)?)
+    }
+
+    /// Returns the fee in gas token raised to the minimum fee configured for the token, if any
+    pub fn apply_min_fee(&self, gas_token: Felt, fee_in_token: Felt) -> Felt {
+        self.token_metadata.apply_min_fee(&gas_token, fee_in_token)
+    }
+
+    /// Returns the profitability guard applied before executing a transaction, if any
+    pub fn profitability(&self) -> Option<&ProfitabilityConfiguration> {
+        self.profitability.as_ref()
+    }
+
+    /// Returns how long a quote in the given gas token remains valid given its recent volatility, when the
+    /// validity of the quotes is derived from it
+    pub fn quote_ttl(&self, gas_token: Felt) -> Option<Duration> {
+        let ttl = self.quote_ttl.as_ref()?.ttl(self.price.volatility(gas_token));
+        metric!(histogram[execution_quote_ttl_seconds] = ttl.as_secs());
+
+        Some(ttl)
+    }
+
+    /// Returns true if the given address is one of the relayers submitting the transactions
+    pub fn is_relayer(&self, address: Felt) -> bool {
+        self.relayers.is_relayer(address)
+    }
+
+    /// Pick the relayer which will submit a transaction whose fee is paid directly to it
+    pub async fn assign_relayer(&self) -> Result<Felt, Error> {
+        Ok(self.relayers.assign_relayer().await?)
+    }
+
     /// Get the tip value given a priority
     pub async fn get_tip(&self, tip: TipPriority) -> Result<u64, Error> {
-        let tip: u64 = match tip {
-            TipPriority::Slow => max(self.starknet.fetch_median_tip().await? - 5, 0),
-            TipPriority::Normal => self.starknet.fetch_median_tip().await?,
-            TipPriority::Fast => self.starknet.fetch_median_tip().await? + 5,
-            TipPriority::Custom(tip) => tip,
+        Ok(self.resolve_tip(tip).await?.tip)
+    }
+
+    /// Resolve the tip to apply given a priority
+    pub async fn resolve_tip(&self, priority: TipPriority) -> Result<AppliedTip, Error> {
+        let median_tip = match priority {
+            TipPriority::Custom(_) => 0,
+            _ => self.starknet.fetch_median_tip().await?,
         };
-        Ok(tip)
+
+        Ok(AppliedTip::new(priority, median_tip))
+    }
+
+    /// Record the time it takes for the given transaction to be included, per tip priority, so that integrators
+    /// can check that the priority actually changes the inclusion behavior
+    pub fn track_inclusion(&self, transaction_hash: Felt, priority: TipPriority) {
+        let starknet = self.starknet.clone();
+        let submitted_at = Instant::now();
+
+        tokio::spawn(async move {
+            while submitted_at.elapsed() < INCLUSION_TRACKING_TIMEOUT {
+                match starknet.get_transaction_receipt(transaction_hash).await {
+                    Ok(_) => {
+                        metric!(
+                            histogram[transaction_inclusion_latency_milliseconds] = submitted_at.elapsed().as_millis(),
+                            priority = priority.name()
+                        );
+                        return;
+                    },
+                    Err(paymaster_starknet::Error::TransactionNotFound) => {},
+                    Err(_) => return,
+                }
+
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+
+            metric!(counter[transaction_inclusion_timeout] = 1, priority = priority.name());
+        });
+    }
+
+    /// Inspect the state of the user of a failed execution. See [`DiagnosticClient::diagnose`]
+    pub async fn diagnose(&self, forwarder: Felt, user_address: Felt, fee_transfer: Option<TokenTransfer>) -> ExecutionDiagnosis {
+        self.diagnostic_client
+            .diagnose(&self.starknet, forwarder, user_address, fee_transfer)
+            .await
     }
 
     pub fn compute_max_fee_in_strk(&self, base_estimate: Felt) -> Felt {
@@ -187,6 +577,14 @@ impl Client {
             .map(|x| self.apply_provider_fee_multiplier(x))
     }
 
+    /// Compute the fee in strk induced by the validation of a session token, added on top of the estimate of the
+    /// build for the transactions signed with a session key
+    pub async fn compute_session_overhead_in_strk(&self) -> Result<Felt, Error> {
+        let gas_price = self.starknet.fetch_block_gas_price().await?;
+
+        Ok(gas_price * ValidationGasOverhead::session())
+    }
+
     /// Compute the fee in strk given [`base_estimate`] which corresponds to the original estimate in strk
     fn compute_fee_in_strk(&self, base_estimate: Felt) -> Felt {
         base_estimate
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use paymaster_common::metric;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::Error;
+
+/// Delay suggested to the clients whose execution is rejected because the paymaster is saturated
+pub const EXECUTION_RETRY_AFTER: Duration = Duration::from_secs(2);
+
+/// Bounds the number of executions in progress. Executions beyond the limit are rejected right away
+/// rather than queuing on the relayer locks until they time out.
+#[derive(Clone)]
+pub struct ExecutionLimiter {
+    permits: Arc<Semaphore>,
+
+    /// Slots only used by the deployments of accounts once the other slots are taken
+    reserved: Arc<Semaphore>,
+}
+
+impl ExecutionLimiter {
+    pub fn new(capacity: usize) -> Self {
+        Self::with_reserve(capacity, 0)
+    }
+
+    /// Same as [`new`], `reserved` of the slots being reserved to the deployments of accounts
+    pub fn with_reserve(capacity: usize, reserved: usize) -> Self {
+        let reserved = reserved.min(capacity);
+
+        Self {
+            permits: Arc::new(Semaphore::new(capacity - reserved)),
+            reserved: Arc::new(Semaphore::new(reserved)),
+        }
+    }
+
+    /// Reserve a slot for an execution, released when the permit is dropped. Fails with [`Error::Busy`] when
+    /// the limit is reached.
+    pub fn try_acquire(&self) -> Result<OwnedSemaphorePermit, Error> {
+        self.permits.clone().try_acquire_owned().map_err(|_| Self::busy())
+    }
+
+    /// Same as [`try_acquire`] for an execution deploying an account, which falls back on the reserved slots
+    pub fn try_acquire_deployment(&self) -> Result<OwnedSemaphorePermit, Error> {
+        if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+
+        let permit = self.reserved.clone().try_acquire_owned().map_err(|_| Self::busy())?;
+        metric!(counter[execution_reserved_slot] = 1);
+
+        Ok(permit)
+    }
+
+    fn busy() -> Error {
+        metric!(counter[execution_request_rejected] = 1, reason = "busy");
+        Error::Busy(EXECUTION_RETRY_AFTER.as_secs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::limiter::{ExecutionLimiter, EXECUTION_RETRY_AFTER};
+    use crate::Error;
+
+    #[test]
+    fn executions_beyond_capacity_are_rejected() {
+        let limiter = ExecutionLimiter::new(2);
+
+        let first = limiter.try_acquire().unwrap();
+        let _second = limiter.try_acquire().unwrap();
+        assert!(matches!(limiter.try_acquire(), Err(Error::Busy(x)) if x == EXECUTION_RETRY_AFTER.as_secs()));
+
+        drop(first);
+        assert!(limiter.try_acquire().is_ok());
+    }
+
+    #[test]
+    fn reserved_slots_are_only_used_by_deployments() {
+        let limiter = ExecutionLimiter::with_reserve(2, 1);
+
+        let _invoke = limiter.try_acquire().unwrap();
+        assert!(matches!(limiter.try_acquire(), Err(Error::Busy(_))));
+
+        let _deployment = limiter.try_acquire_deployment().unwrap();
+        assert!(matches!(limiter.try_acquire_deployment(), Err(Error::Busy(_))));
+    }
+}
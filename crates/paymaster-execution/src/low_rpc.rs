@@ -0,0 +1,192 @@
+//! Degraded mode used when the quota of the Starknet RPC is constrained.
+//!
+//! While enabled, the builds reuse the recent estimate of the transactions with the same call shape instead of
+//! estimating them again, the gas price is refreshed less frequently and the suggested max fee is widened to
+//! absorb the inaccuracy of the reused values. The executions are still estimated before being submitted.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use paymaster_common::cache::ExpirableCache;
+use paymaster_common::metric;
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{Call, Felt, NonZeroFelt};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LowRpcConfiguration {
+    /// Start the instance with the low-RPC mode enabled. It can be toggled at runtime through the admin api.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Duration during which the estimate of a call shape is reused (in seconds)
+    #[serde(default = "LowRpcConfiguration::default_estimate_reuse")]
+    pub estimate_reuse: u64,
+
+    /// Interval between two refreshes of the gas price (in seconds)
+    #[serde(default = "LowRpcConfiguration::default_gas_price_refresh")]
+    pub gas_price_refresh: u64,
+
+    /// Margin added to the suggested max fee (0.5 adds 50%)
+    #[serde(default = "LowRpcConfiguration::default_safety_margin")]
+    pub safety_margin: f32,
+}
+
+impl Default for LowRpcConfiguration {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            estimate_reuse: Self::default_estimate_reuse(),
+            gas_price_refresh: Self::default_gas_price_refresh(),
+            safety_margin: Self::default_safety_margin(),
+        }
+    }
+}
+
+impl LowRpcConfiguration {
+    fn default_estimate_reuse() -> u64 {
+        120
+    }
+
+    fn default_gas_price_refresh() -> u64 {
+        60
+    }
+
+    fn default_safety_margin() -> f32 {
+        0.5
+    }
+}
+
+impl Validate for LowRpcConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.estimate_reuse > 0, "estimate_reuse", "must be greater than 0");
+        report.ensure(self.gas_price_refresh >= 10, "gas_price_refresh", "must be at least 10 seconds");
+        report.ensure(self.safety_margin >= 0.0, "safety_margin", "must be positive");
+    }
+}
+
+/// Shape of a transaction: the contracts and entrypoints it calls along with the length of their calldata, but not
+/// the calldata itself. Transactions sharing a shape have close estimates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CallShape(u64);
+
+impl CallShape {
+    pub fn new(gas_token: Felt, deployment: bool, calls: &[Call]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        gas_token.hash(&mut hasher);
+        deployment.hash(&mut hasher);
+        for call in calls {
+            call.to.hash(&mut hasher);
+            call.selector.hash(&mut hasher);
+            call.calldata.len().hash(&mut hasher);
+        }
+
+        Self(hasher.finish())
+    }
+}
+
+/// Switch of the low-RPC mode, shared by the clones of the execution client
+#[derive(Clone)]
+pub struct LowRpcMode {
+    configuration: LowRpcConfiguration,
+    enabled: Arc<AtomicBool>,
+
+    // Latest estimate (in STRK) of each call shape
+    estimates: ExpirableCache<CallShape, u128>,
+}
+
+impl LowRpcMode {
+    pub fn new(configuration: &LowRpcConfiguration) -> Self {
+        Self {
+            configuration: configuration.clone(),
+            enabled: Arc::new(AtomicBool::new(configuration.enabled)),
+            estimates: ExpirableCache::new(10_000),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        metric!(gauge[low_rpc_mode] = enabled as u64);
+    }
+
+    /// Interval between two refreshes of the gas price while the mode is enabled
+    pub fn gas_price_refresh(&self) -> Duration {
+        Duration::from_secs(self.configuration.gas_price_refresh)
+    }
+
+    /// Returns the recent estimate of the shape, only while the mode is enabled
+    pub fn reusable_estimate(&self, shape: CallShape) -> Option<u128> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let estimate = self.estimates.get_if_not_stale(&shape);
+        let result = if estimate.is_some() { "reused" } else { "estimated" };
+        metric!(counter[low_rpc_estimate] = 1, result = result);
+
+        estimate
+    }
+
+    /// Record the estimate of the shape. Estimates are recorded even while the mode is disabled so that they can
+    /// be reused as soon as it is enabled.
+    pub fn record_estimate(&self, shape: CallShape, estimate: u128) {
+        self.estimates
+            .insert(shape, estimate, Duration::from_secs(self.configuration.estimate_reuse));
+    }
+
+    /// Returns the max fee widened by the safety margin while the mode is enabled
+    pub fn widen(&self, max_fee: Felt) -> Felt {
+        if !self.is_enabled() {
+            return max_fee;
+        }
+
+        let multiplier = Felt::from(((1.0 + self.configuration.safety_margin) * 1000.0) as u32);
+        let divisor = NonZeroFelt::from_felt_unchecked(Felt::from(1000));
+
+        (multiplier * max_fee).floor_div(&divisor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::{Call, Felt};
+
+    use crate::low_rpc::{CallShape, LowRpcConfiguration, LowRpcMode};
+
+    fn call(calldata: Vec<Felt>) -> Call {
+        Call {
+            to: Felt::ONE,
+            selector: Felt::TWO,
+            calldata,
+        }
+    }
+
+    #[test]
+    fn shape_ignores_the_value_of_the_calldata() {
+        let shape = CallShape::new(Felt::ONE, false, &[call(vec![Felt::ONE])]);
+
+        assert_eq!(shape, CallShape::new(Felt::ONE, false, &[call(vec![Felt::TWO])]));
+        assert_ne!(shape, CallShape::new(Felt::ONE, false, &[call(vec![Felt::ONE, Felt::TWO])]));
+        assert_ne!(shape, CallShape::new(Felt::TWO, false, &[call(vec![Felt::ONE])]));
+    }
+
+    #[test]
+    fn estimates_are_only_reused_while_enabled() {
+        let mode = LowRpcMode::new(&LowRpcConfiguration::default());
+        let shape = CallShape::new(Felt::ONE, false, &[call(vec![])]);
+
+        mode.record_estimate(shape, 1000);
+        assert_eq!(mode.reusable_estimate(shape), None);
+        assert_eq!(mode.widen(Felt::from(1000)), Felt::from(1000));
+
+        mode.set_enabled(true);
+        assert_eq!(mode.reusable_estimate(shape), Some(1000));
+        assert_eq!(mode.widen(Felt::from(1000)), Felt::from(1500));
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use paymaster_common::metric;
+use paymaster_common::service::{Error as ServiceError, Service};
+use starknet::core::types::Felt;
+use tokio::time::interval;
+use tracing::{error, warn};
+
+use crate::starknet::Client as Starknet;
+use crate::Error;
+
+/// Interval between two checks of the nonce of the estimate account
+pub const NONCE_DRIFT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Nonce of the account used for the estimations. The account never sends any transaction, so its nonce is fetched
+/// once and only changes when the account is used elsewhere, which makes the estimations fail.
+#[derive(Clone, Default)]
+pub struct EstimateAccountNonce(Arc<RwLock<Option<Felt>>>);
+
+impl EstimateAccountNonce {
+    pub fn get(&self) -> Option<Felt> {
+        *self.0.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Replace the cached nonce with the one observed on chain. Returns the previous nonce when it drifted
+    pub fn sync(&self, observed: Felt) -> Option<Felt> {
+        let mut nonce = self.0.write().unwrap_or_else(|e| e.into_inner());
+        let previous = nonce.replace(observed);
+
+        previous.filter(|x| *x != observed)
+    }
+}
+
+#[derive(Clone)]
+pub struct NonceDriftContext {
+    starknet: Starknet,
+    address: Felt,
+    nonce: EstimateAccountNonce,
+}
+
+impl NonceDriftContext {
+    pub(crate) fn new(starknet: Starknet, address: Felt, nonce: EstimateAccountNonce) -> Self {
+        Self { starknet, address, nonce }
+    }
+
+    /// Returns the cached nonce of the estimate account, fetching it when not known yet
+    pub async fn fetch(&self) -> Result<Felt, Error> {
+        if let Some(nonce) = self.nonce.get() {
+            return Ok(nonce);
+        }
+
+        let nonce = self.starknet.fetch_nonce(self.address).await?;
+        self.nonce.sync(nonce);
+
+        Ok(nonce)
+    }
+}
+
+/// Periodically compares the nonce of the estimate account with the cached one, alerting and re-syncing the cache
+/// when it changed
+pub struct NonceDriftService {
+    context: NonceDriftContext,
+}
+
+#[async_trait]
+impl Service for NonceDriftService {
+    type Context = NonceDriftContext;
+
+    const NAME: &'static str = "NonceDriftService";
+
+    async fn new(context: NonceDriftContext) -> Self {
+        Self { context }
+    }
+
+    async fn run(self) -> Result<(), ServiceError> {
+        let mut ticker = interval(NONCE_DRIFT_CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+
+            let observed = match self.context.starknet.fetch_nonce(self.context.address).await {
+                Ok(nonce) => nonce,
+                Err(e) => {
+                    warn!("Could not fetch the nonce of the estimate account: {}", e);
+                    continue;
+                },
+            };
+
+            if let Some(previous) = self.context.nonce.sync(observed) {
+                metric!(counter[estimate_account_nonce_drift] = 1);
+                error!(
+                    "Nonce of the estimate account {} changed from {} to {}. The estimate account must not be used to send transactions.",
+                    self.context.address.to_hex_string(),
+                    previous,
+                    observed
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use crate::nonce::EstimateAccountNonce;
+
+    #[test]
+    fn drift_is_reported_once_and_resynced() {
+        let nonce = EstimateAccountNonce::default();
+
+        assert_eq!(nonce.sync(Felt::ONE), None);
+        assert_eq!(nonce.sync(Felt::ONE), None);
+
+        assert_eq!(nonce.sync(Felt::TWO), Some(Felt::ONE));
+        assert_eq!(nonce.get(), Some(Felt::TWO));
+        assert_eq!(nonce.sync(Felt::TWO), None);
+    }
+}
@@ -0,0 +1,57 @@
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{Felt, NonZeroFelt};
+
+/// Guard preventing the execution of a transaction whose fee, converted to STRK at the time of the execution,
+/// does not cover its estimated cost plus a minimum margin. The fee can move away from the cost when the price
+/// of the gas token changes between the build and the execution of the transaction.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProfitabilityConfiguration {
+    /// Minimum margin over the estimated cost, expressed as a ratio of the cost (e.g. 0.05 for 5%)
+    pub min_margin: f32,
+}
+
+impl ProfitabilityConfiguration {
+    /// Returns the minimum fee in STRK to collect for a transaction whose estimated cost is `cost_in_strk`
+    pub fn min_fee_in_strk(&self, cost_in_strk: Felt) -> Felt {
+        let multiplier = Felt::from(((1.0 + self.min_margin) * 1000.0).round() as u32);
+        let divisor = NonZeroFelt::from_felt_unchecked(Felt::from(1000));
+
+        (multiplier * cost_in_strk).floor_div(&divisor)
+    }
+
+    /// Returns true if collecting `fee_in_strk` for a transaction whose estimated cost is `cost_in_strk` is profitable
+    pub fn is_profitable(&self, cost_in_strk: Felt, fee_in_strk: Felt) -> bool {
+        fee_in_strk >= self.min_fee_in_strk(cost_in_strk)
+    }
+}
+
+impl Validate for ProfitabilityConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.min_margin >= 0.0, "min_margin", "must be positive");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use crate::profitability::ProfitabilityConfiguration;
+
+    #[test]
+    fn fee_must_cover_cost_and_margin() {
+        let configuration = ProfitabilityConfiguration { min_margin: 0.05 };
+
+        assert_eq!(configuration.min_fee_in_strk(Felt::from(1000)), Felt::from(1050));
+        assert!(configuration.is_profitable(Felt::from(1000), Felt::from(1050)));
+        assert!(!configuration.is_profitable(Felt::from(1000), Felt::from(1049)));
+    }
+
+    #[test]
+    fn zero_margin_only_requires_the_cost() {
+        let configuration = ProfitabilityConfiguration { min_margin: 0.0 };
+
+        assert!(configuration.is_profitable(Felt::from(1000), Felt::from(1000)));
+        assert!(!configuration.is_profitable(Felt::from(1000), Felt::from(999)));
+    }
+}
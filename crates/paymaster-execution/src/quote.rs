@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+
+/// Validity of the quotes, derived from the volatility of the gas token so that the quotes in volatile tokens
+/// expire sooner than the quotes in stable ones. Only applies when the user does not set the time bounds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QuoteTtlConfiguration {
+    /// Validity in seconds of the quotes in the most volatile tokens, or in tokens whose volatility is unknown
+    #[serde(default = "QuoteTtlConfiguration::default_min_ttl")]
+    pub min_ttl: u64,
+
+    /// Validity in seconds of the quotes in tokens whose price does not move
+    #[serde(default = "QuoteTtlConfiguration::default_max_ttl")]
+    pub max_ttl: u64,
+
+    /// Volatility, measured as the relative price range over the last minutes, from which quotes get the minimum validity
+    #[serde(default = "QuoteTtlConfiguration::default_max_volatility")]
+    pub max_volatility: f64,
+}
+
+impl Default for QuoteTtlConfiguration {
+    fn default() -> Self {
+        Self {
+            min_ttl: Self::default_min_ttl(),
+            max_ttl: Self::default_max_ttl(),
+            max_volatility: Self::default_max_volatility(),
+        }
+    }
+}
+
+impl QuoteTtlConfiguration {
+    fn default_min_ttl() -> u64 {
+        60
+    }
+
+    fn default_max_ttl() -> u64 {
+        3600
+    }
+
+    fn default_max_volatility() -> f64 {
+        0.05
+    }
+
+    /// Returns the validity of a quote in a token with the given volatility, decreasing linearly from the
+    /// maximum validity for a stable token to the minimum validity at the maximum volatility
+    pub fn ttl(&self, volatility: Option<f64>) -> Duration {
+        let Some(volatility) = volatility else {
+            return Duration::from_secs(self.min_ttl);
+        };
+
+        let ratio = (volatility / self.max_volatility).clamp(0.0, 1.0);
+        let ttl = self.max_ttl as f64 - (self.max_ttl - self.min_ttl) as f64 * ratio;
+
+        Duration::from_secs(ttl as u64)
+    }
+}
+
+impl Validate for QuoteTtlConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.min_ttl > 0, "min_ttl", "must be greater than 0");
+        report.ensure(self.max_ttl >= self.min_ttl, "max_ttl", "must be greater than or equal to min_ttl");
+        report.ensure(self.max_volatility > 0.0, "max_volatility", "must be greater than 0");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::quote::QuoteTtlConfiguration;
+
+    #[test]
+    fn ttl_decreases_with_volatility() {
+        let configuration = QuoteTtlConfiguration {
+            min_ttl: 60,
+            max_ttl: 660,
+            max_volatility: 0.1,
+        };
+
+        assert_eq!(configuration.ttl(Some(0.0)), Duration::from_secs(660));
+        assert_eq!(configuration.ttl(Some(0.05)), Duration::from_secs(360));
+        assert_eq!(configuration.ttl(Some(0.2)), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn unknown_volatility_gets_the_minimum_ttl() {
+        let configuration = QuoteTtlConfiguration::default();
+
+        assert_eq!(configuration.ttl(None), Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+/// Addresses receiving the fee collected from the users in place of the gas tank, to keep the fee of some
+/// sponsors or of some tokens segregated.
+///
+/// The fee overcharged to the users is always refunded from the gas tank, including the fee received by a
+/// recipient. The gas tank is not credited with that fee, so the funds must be moved back from the recipients
+/// to cover the refunds of their transactions.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FeeRecipientsConfiguration {
+    /// Recipient of the fee paid by the users of a sponsor, indexed by api key. Takes precedence over the token recipients.
+    #[serde(default)]
+    pub sponsors: HashMap<String, Felt>,
+
+    /// Recipient of the fee paid in a given gas token
+    #[serde(default)]
+    pub tokens: HashMap<Felt, Felt>,
+}
+
+impl FeeRecipientsConfiguration {
+    /// Returns true if the fee of the users of the sponsor with the given api key goes to a recipient of its own
+    pub fn has_sponsor(&self, api_key: &str) -> bool {
+        self.sponsors.contains_key(api_key)
+    }
+
+    /// Returns the address receiving the fee paid in `gas_token` by a user of the sponsor with the given api key,
+    /// or the `gas_tank` when no recipient is configured
+    pub fn resolve(&self, gas_tank: Felt, api_key: Option<&str>, gas_token: Felt) -> Felt {
+        api_key
+            .and_then(|x| self.sponsors.get(x))
+            .or_else(|| self.tokens.get(&gas_token))
+            .copied()
+            .unwrap_or(gas_tank)
+    }
+}
+
+impl Validate for FeeRecipientsConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        // Sponsors are indexed by api key which must not leak in the report
+        let sponsors_are_valid = self.sponsors.values().all(|x| *x != Felt::ZERO);
+        report.ensure(sponsors_are_valid, "sponsors", "recipients must not be zero");
+
+        for (token, recipient) in &self.tokens {
+            report.ensure(*recipient != Felt::ZERO, &format!("tokens.{}", token.to_hex_string()), "must not be zero");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use paymaster_common::validation::Validate;
+    use starknet::core::types::Felt;
+
+    use crate::recipient::FeeRecipientsConfiguration;
+
+    const GAS_TANK: Felt = Felt::ONE;
+    const TOKEN: Felt = Felt::TWO;
+
+    fn recipients() -> FeeRecipientsConfiguration {
+        FeeRecipientsConfiguration {
+            sponsors: HashMap::from([("paymaster_123".to_string(), Felt::from(10))]),
+            tokens: HashMap::from([(TOKEN, Felt::from(20))]),
+        }
+    }
+
+    #[test]
+    fn sponsor_recipient_takes_precedence_over_token_recipient() {
+        let recipients = recipients();
+
+        assert_eq!(recipients.resolve(GAS_TANK, Some("paymaster_123"), TOKEN), Felt::from(10));
+        assert_eq!(recipients.resolve(GAS_TANK, Some("paymaster_456"), TOKEN), Felt::from(20));
+        assert_eq!(recipients.resolve(GAS_TANK, None, TOKEN), Felt::from(20));
+        assert_eq!(recipients.resolve(GAS_TANK, None, Felt::THREE), GAS_TANK);
+
+        assert!(recipients.has_sponsor("paymaster_123"));
+        assert!(!recipients.has_sponsor("paymaster_456"));
+    }
+
+    #[test]
+    fn zero_recipients_are_rejected() {
+        let mut recipients = recipients();
+        assert!(recipients.validate_all().is_ok());
+
+        recipients.tokens.insert(Felt::THREE, Felt::ZERO);
+        assert!(recipients.validate_all().is_err());
+    }
+}
@@ -0,0 +1,515 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+
+use deadpool_redis::redis::{pipe, AsyncCommands, RedisWrite, ToRedisArgs};
+use deadpool_redis::{Connection, Pool};
+use futures::StreamExt;
+use paymaster_common::service::TokioServiceManager;
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_relayer::lock::leader::LeaderLock;
+use paymaster_relayer::lock::LockLayerConfiguration;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{Felt, NonZeroFelt};
+use tracing::error;
+
+use crate::clock::now;
+use crate::execution::{ExecutionReceipt, FeeQuote};
+use crate::refund::service::{RefundContext, RefundService};
+use crate::{Client, Error};
+
+mod service;
+
+/// Transactions are no longer tracked after a day, their receipt is available by then
+const PENDING_EXPIRY: u64 = 86400;
+
+/// Refunds are kept for 30 days once confirmed
+const REFUNDED_RETENTION: u64 = 30 * 86400;
+
+/// Configuration of the refund service which gives back to the users the fee charged in excess
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RefundConfiguration {
+    /// Minimum amount overcharged (in STRK) for a transaction to be refunded. Below this
+    /// threshold, the cost of the refund transfer is not worth it.
+    pub threshold_in_strk: Felt,
+
+    /// Interval in seconds between two batches of refunds
+    pub check_interval: u64,
+}
+
+impl Validate for RefundConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.threshold_in_strk != Felt::ZERO, "threshold_in_strk", "must not be zero");
+        report.ensure(self.check_interval > 0, "check_interval", "must be greater than 0");
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RefundStatus {
+    Outstanding,
+
+    /// Sent or proposed from the gas tank at `nonce`, refunded once the transaction is accepted
+    Submitted {
+        transaction_hash: Felt,
+        nonce: Felt,
+    },
+
+    /// Sent in the given transaction, accepted on chain
+    Refunded {
+        transaction_hash: Felt,
+    },
+}
+
+/// Accounting entry of a refund owed to a user for a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Refund {
+    pub user: Felt,
+
+    /// Hash of the transaction for which the user was overcharged
+    pub transaction_hash: Felt,
+
+    pub token: Felt,
+    pub amount: Felt,
+    pub amount_in_strk: Felt,
+
+    pub status: RefundStatus,
+
+    /// Unix timestamp (in seconds) at which the refund was recorded
+    pub timestamp: u64,
+}
+
+impl Refund {
+    /// Compute the refund owed for the given confirmed transaction. The user is owed the difference between
+    /// the fee charged and the actual fee to which the provider fee overhead is applied, in the token used to pay.
+    /// Returns None when the difference is below the threshold or when the transaction was reverted since the user
+    /// did not pay for it in that case.
+    pub fn compute(user: Felt, receipt: &ExecutionReceipt, expected_fee_in_strk: Felt, threshold_in_strk: Felt) -> Option<Self> {
+        let FeeQuote {
+            gas_token,
+            fee_in_token,
+            fee_in_strk,
+        } = receipt.quote;
+
+        if receipt.is_reverted() || fee_in_token == Felt::ZERO || fee_in_strk <= expected_fee_in_strk {
+            return None;
+        }
+
+        let amount_in_strk = fee_in_strk - expected_fee_in_strk;
+        if amount_in_strk < threshold_in_strk {
+            return None;
+        }
+
+        // Convert using the rate of the quote so the user is refunded at the price they paid
+        let amount = (fee_in_token * amount_in_strk).floor_div(&NonZeroFelt::from_felt_unchecked(fee_in_strk));
+
+        Some(Self {
+            user,
+            transaction_hash: receipt.transaction_hash,
+            token: gas_token,
+            amount,
+            amount_in_strk,
+            status: RefundStatus::Outstanding,
+            timestamp: now(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct PendingTransaction {
+    user: Felt,
+    quote: FeeQuote,
+}
+
+enum RefundKey {
+    All,
+    Refund(Felt),
+    AllPending,
+    Pending(Felt),
+}
+
+impl ToRedisArgs for RefundKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + RedisWrite,
+    {
+        match self {
+            Self::All => out.write_arg_fmt("refund:*"),
+            Self::Refund(x) => out.write_arg_fmt(format!("refund:{}", x.to_fixed_hex_string())),
+            Self::AllPending => out.write_arg_fmt("refund-pending:*"),
+            Self::Pending(x) => out.write_arg_fmt(format!("refund-pending:{}", x.to_fixed_hex_string())),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Ledger {
+    // Transactions executed but not confirmed yet
+    pending: HashMap<Felt, PendingTransaction>,
+
+    // Refunds indexed by the hash of the transaction overcharged
+    refunds: HashMap<Felt, Refund>,
+}
+
+/// Ledger of the refunds owed to the users, indexed by the hash of the transaction overcharged. Shared by the
+/// instances through the Redis of the shared lock layer, kept in memory otherwise.
+#[derive(Clone, Default)]
+pub struct RefundLedger {
+    redis: Option<Pool>,
+    memory: Arc<RwLock<Ledger>>,
+}
+
+impl RefundLedger {
+    pub fn new(lock: &LockLayerConfiguration) -> Result<Self, Error> {
+        let redis = match lock {
+            LockLayerConfiguration::Shared { redis, .. } => Some(redis.create_pool().map_err(|e| Error::Internal(e.to_string()))?),
+            _ => None,
+        };
+
+        Ok(Self { redis, ..Self::default() })
+    }
+
+    /// Track a transaction executed on behalf of `user` so it can be refunded once confirmed
+    pub async fn track(&self, user: Felt, transaction_hash: Felt, quote: FeeQuote) -> Result<(), Error> {
+        let transaction = PendingTransaction { user, quote };
+        let Some(redis) = &self.redis else {
+            self.write().pending.insert(transaction_hash, transaction);
+            return Ok(());
+        };
+
+        let mut connection = Self::connection(redis).await?;
+        connection
+            .set_ex(RefundKey::Pending(transaction_hash), Self::encode(&transaction)?, PENDING_EXPIRY)
+            .await
+            .map_err(|e| Error::Internal(e.to_string()))
+    }
+
+    /// Returns the transactions waiting for confirmation along with their user and quote
+    pub async fn pending(&self) -> Result<Vec<(Felt, Felt, FeeQuote)>, Error> {
+        let Some(redis) = &self.redis else {
+            return Ok(self.read().pending.iter().map(|(hash, x)| (*hash, x.user, x.quote)).collect());
+        };
+
+        let mut pending = vec![];
+        for (key, transaction) in Self::scan::<PendingTransaction>(redis, RefundKey::AllPending).await? {
+            if let Some(hash) = key.strip_prefix("refund-pending:").and_then(|x| Felt::from_hex(x).ok()) {
+                pending.push((hash, transaction.user, transaction.quote));
+            }
+        }
+
+        Ok(pending)
+    }
+
+    /// Stop tracking the given transaction and record the refund owed for it if any. A refund already recorded
+    /// for the transaction is left untouched.
+    pub async fn settle(&self, transaction_hash: Felt, refund: Option<Refund>) -> Result<(), Error> {
+        let Some(redis) = &self.redis else {
+            let mut ledger = self.write();
+            ledger.pending.remove(&transaction_hash);
+            if let Some(refund) = refund {
+                ledger.refunds.entry(transaction_hash).or_insert(refund);
+            }
+            return Ok(());
+        };
+
+        let mut connection = Self::connection(redis).await?;
+        let mut pipeline = pipe();
+        pipeline.atomic();
+        if let Some(refund) = &refund {
+            pipeline
+                .set_nx(RefundKey::Refund(transaction_hash), Self::encode(refund)?)
+                .ignore();
+        }
+        pipeline.del(RefundKey::Pending(transaction_hash)).ignore();
+
+        pipeline
+            .query_async::<()>(&mut connection)
+            .await
+            .map_err(|e| Error::Internal(e.to_string()))
+    }
+
+    /// Returns the refunds that have not been sent yet
+    pub async fn outstanding(&self) -> Result<Vec<Refund>, Error> {
+        Ok(self
+            .refunds(None)
+            .await?
+            .into_iter()
+            .filter(|x| x.status == RefundStatus::Outstanding)
+            .collect())
+    }
+
+    /// Set the status of the refunds of the given transactions. Confirmed refunds are dropped after the retention.
+    pub async fn set_status(&self, transaction_hashes: &[Felt], status: RefundStatus) -> Result<(), Error> {
+        let Some(redis) = &self.redis else {
+            let mut ledger = self.write();
+            for transaction_hash in transaction_hashes {
+                if let Some(refund) = ledger.refunds.get_mut(transaction_hash) {
+                    refund.status = status.clone();
+                }
+            }
+            return Ok(());
+        };
+
+        let refunds: Vec<Refund> = self
+            .refunds(None)
+            .await?
+            .into_iter()
+            .filter(|x| transaction_hashes.contains(&x.transaction_hash))
+            .collect();
+
+        let mut connection = Self::connection(redis).await?;
+        let mut pipeline = pipe();
+        pipeline.atomic();
+        for mut refund in refunds {
+            refund.status = status.clone();
+            let value = Self::encode(&refund)?;
+            match status {
+                RefundStatus::Refunded { .. } => pipeline
+                    .set_ex(RefundKey::Refund(refund.transaction_hash), value, REFUNDED_RETENTION)
+                    .ignore(),
+                _ => pipeline.set(RefundKey::Refund(refund.transaction_hash), value).ignore(),
+            };
+        }
+
+        pipeline
+            .query_async::<()>(&mut connection)
+            .await
+            .map_err(|e| Error::Internal(e.to_string()))
+    }
+
+    /// Drop the refunds confirmed and recorded for longer than the retention, Redis expires them on its own
+    pub fn prune(&self) {
+        if self.redis.is_none() {
+            let expiry = now().saturating_sub(REFUNDED_RETENTION);
+            self.write()
+                .refunds
+                .retain(|_, x| !matches!(x.status, RefundStatus::Refunded { .. }) || x.timestamp > expiry);
+        }
+    }
+
+    /// Returns every refund recorded, optionally restricted to the given user
+    pub async fn refunds(&self, user: Option<Felt>) -> Result<Vec<Refund>, Error> {
+        let refunds = match &self.redis {
+            Some(redis) => Self::scan::<Refund>(redis, RefundKey::All)
+                .await?
+                .into_iter()
+                .map(|(_, x)| x)
+                .collect(),
+            None => self.read().refunds.values().cloned().collect(),
+        };
+
+        let mut refunds: Vec<Refund> = refunds.into_iter().filter(|x| user.is_none_or(|user| x.user == user)).collect();
+        refunds.sort_by_key(|x| x.timestamp);
+
+        Ok(refunds)
+    }
+
+    async fn scan<T: for<'de> Deserialize<'de>>(redis: &Pool, pattern: RefundKey) -> Result<Vec<(String, T)>, Error> {
+        let mut connection = Self::connection(redis).await?;
+        let keys: Vec<String> = connection
+            .scan_match(pattern)
+            .await
+            .map_err(|e| Error::Internal(e.to_string()))?
+            .collect()
+            .await;
+
+        let mut values = vec![];
+        for key in keys {
+            let value: Option<Vec<u8>> = connection.get(&key).await.map_err(|e| Error::Internal(e.to_string()))?;
+            if let Some(value) = value.and_then(|x| serde_json::from_slice(&x).ok()) {
+                values.push((key, value));
+            }
+        }
+
+        Ok(values)
+    }
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, Error> {
+        serde_json::to_vec(value).map_err(|e| Error::Internal(e.to_string()))
+    }
+
+    async fn connection(redis: &Pool) -> Result<Connection, Error> {
+        redis.get().await.map_err(|e| Error::Internal(e.to_string()))
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Ledger> {
+        self.memory.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Ledger> {
+        self.memory.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Manages the refunds of the users. When no configuration is given, transactions are not tracked
+/// and no refund is ever issued.
+#[derive(Clone)]
+pub struct RefundManager {
+    ledger: RefundLedger,
+    enabled: bool,
+
+    #[allow(dead_code)]
+    services: Option<Arc<TokioServiceManager<RefundContext>>>,
+}
+
+impl RefundManager {
+    pub fn new(client: &Client, configuration: Option<&RefundConfiguration>, lock: &LockLayerConfiguration) -> Result<Self, Error> {
+        let ledger = RefundLedger::new(lock)?;
+
+        let services = match configuration {
+            Some(configuration) => {
+                // The lease outlives the interval so that the leader keeps it between two batches
+                let lease = Duration::from_secs(configuration.check_interval * 2);
+                let leader = LeaderLock::new(lock, "refunds", lease).map_err(paymaster_relayer::Error::from)?;
+
+                let mut services = TokioServiceManager::new(RefundContext {
+                    client: client.clone(),
+                    ledger: ledger.clone(),
+                    leader,
+                    configuration: configuration.clone(),
+                });
+                services.spawn::<RefundService>();
+
+                Some(Arc::new(services))
+            },
+            None => None,
+        };
+
+        Ok(Self {
+            ledger,
+            enabled: services.is_some(),
+            services,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Track a transaction paid by `user` so they can be refunded if overcharged
+    pub async fn track(&self, user: Felt, transaction_hash: Felt, quote: FeeQuote) {
+        if !self.enabled {
+            return;
+        }
+
+        if let Err(e) = self.ledger.track(user, transaction_hash, quote).await {
+            error!("Failed to track the refund of {}: {}", transaction_hash.to_fixed_hex_string(), e);
+        }
+    }
+
+    pub fn ledger(&self) -> &RefundLedger {
+        &self.ledger
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use paymaster_relayer::lock::shared::RedisParameters;
+    use paymaster_relayer::lock::LockLayerConfiguration;
+    use starknet::core::types::Felt;
+    use testcontainers::core::{IntoContainerPort, WaitFor};
+    use testcontainers::runners::AsyncRunner;
+    use testcontainers::GenericImage;
+
+    use crate::execution::{ExecutionReceipt, FeeQuote, GasConsumed};
+    use crate::refund::{Refund, RefundLedger, RefundStatus};
+
+    fn a_receipt(fee_in_token: u64, fee_in_strk: u64) -> ExecutionReceipt {
+        ExecutionReceipt {
+            transaction_hash: Felt::ONE,
+            block_number: 1,
+            revert_reason: None,
+            gas_consumed: GasConsumed::default(),
+            actual_fee_in_strk: Felt::from(10),
+            quote: FeeQuote {
+                gas_token: Felt::TWO,
+                fee_in_token: Felt::from(fee_in_token),
+                fee_in_strk: Felt::from(fee_in_strk),
+            },
+        }
+    }
+
+    #[test]
+    fn refund_is_computed_in_gas_token() {
+        let refund = Refund::compute(Felt::THREE, &a_receipt(200, 100), Felt::from(40), Felt::from(10)).unwrap();
+
+        assert_eq!(refund.amount_in_strk, Felt::from(60));
+        assert_eq!(refund.amount, Felt::from(120));
+        assert_eq!(refund.token, Felt::TWO);
+        assert_eq!(refund.status, RefundStatus::Outstanding);
+    }
+
+    #[test]
+    fn no_refund_below_threshold() {
+        assert!(Refund::compute(Felt::THREE, &a_receipt(200, 100), Felt::from(95), Felt::from(10)).is_none());
+        assert!(Refund::compute(Felt::THREE, &a_receipt(200, 100), Felt::from(120), Felt::from(10)).is_none());
+    }
+
+    async fn refunds_are_confirmed_once_accepted(ledger: &RefundLedger) {
+        ledger.track(Felt::THREE, Felt::ONE, a_receipt(200, 100).quote).await.unwrap();
+        assert_eq!(ledger.pending().await.unwrap(), vec![(Felt::ONE, Felt::THREE, a_receipt(200, 100).quote)]);
+
+        let refund = Refund::compute(Felt::THREE, &a_receipt(200, 100), Felt::from(40), Felt::from(10));
+        ledger.settle(Felt::ONE, refund.clone()).await.unwrap();
+        assert!(ledger.pending().await.unwrap().is_empty());
+        assert_eq!(ledger.outstanding().await.unwrap().len(), 1);
+
+        let submitted = RefundStatus::Submitted {
+            transaction_hash: Felt::from(42),
+            nonce: Felt::from(7),
+        };
+        ledger.set_status(&[Felt::ONE], submitted.clone()).await.unwrap();
+        assert!(ledger.outstanding().await.unwrap().is_empty());
+
+        // Settling the transaction again does not reset the refund already sent
+        ledger.settle(Felt::ONE, refund).await.unwrap();
+        assert_eq!(ledger.refunds(None).await.unwrap()[0].status, submitted);
+
+        ledger
+            .set_status(
+                &[Felt::ONE],
+                RefundStatus::Refunded {
+                    transaction_hash: Felt::from(42),
+                },
+            )
+            .await
+            .unwrap();
+        assert!(ledger.outstanding().await.unwrap().is_empty());
+        assert_eq!(
+            ledger.refunds(Some(Felt::THREE)).await.unwrap()[0].status,
+            RefundStatus::Refunded {
+                transaction_hash: Felt::from(42)
+            }
+        );
+        assert!(ledger.refunds(Some(Felt::TWO)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn refunds_are_confirmed_once_accepted_in_memory() {
+        refunds_are_confirmed_once_accepted(&RefundLedger::default()).await;
+    }
+
+    #[tokio::test]
+    async fn refunds_are_shared_through_redis() {
+        let container = GenericImage::new("redis", "7")
+            .with_exposed_port(6379.tcp())
+            .with_wait_for(WaitFor::message_on_stdout("Ready to accept connections"))
+            .start()
+            .await
+            .unwrap();
+        let port = container.get_host_port_ipv4(6379).await.unwrap();
+        let lock = LockLayerConfiguration::Shared {
+            retry_timeout: Duration::from_secs(5),
+            redis: RedisParameters::new(&format!("redis://127.0.0.1:{}", port)),
+            fallback: None,
+        };
+
+        let ledger = RefundLedger::new(&lock).unwrap();
+        refunds_are_confirmed_once_accepted(&ledger).await;
+
+        // Another instance sees the refunds
+        let other = RefundLedger::new(&lock).unwrap();
+        assert_eq!(other.refunds(None).await.unwrap(), ledger.refunds(None).await.unwrap());
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use paymaster_common::metric;
+use paymaster_common::service::{Error as ServiceError, Service};
+use paymaster_relayer::gas_tank::GasTankSubmission;
+use paymaster_relayer::lock::leader::LeaderLock;
+use paymaster_starknet::math::denormalize_felt;
+use paymaster_starknet::transaction::{Calls, TokenTransfer};
+use starknet::core::types::Felt;
+use tokio::time::interval;
+use tracing::{error, info, warn};
+
+use crate::refund::{Refund, RefundConfiguration, RefundLedger, RefundStatus};
+use crate::Client;
+
+#[derive(Clone)]
+pub struct RefundContext {
+    pub client: Client,
+    pub ledger: RefundLedger,
+    pub leader: LeaderLock,
+
+    pub configuration: RefundConfiguration,
+}
+
+/// Service which periodically cross-references the confirmed transactions with their quote and
+/// sends back, in a single batch, the fee overcharged to the users through the gas tank sender. A refund is
+/// only marked as refunded once its transaction is accepted. Runs on a single instance at a time.
+pub struct RefundService {
+    context: RefundContext,
+}
+
+#[async_trait]
+impl Service for RefundService {
+    type Context = RefundContext;
+
+    const NAME: &'static str = "RefundService";
+
+    async fn new(context: RefundContext) -> Self {
+        Self { context }
+    }
+
+    async fn run(self) -> Result<(), ServiceError> {
+        let mut ticker = interval(Duration::from_secs(self.context.configuration.check_interval));
+        loop {
+            ticker.tick().await;
+
+            if !self.context.leader.acquire().await {
+                continue;
+            }
+
+            self.context.ledger.prune();
+            if let Err(e) = self.settle_confirmed_transactions().await {
+                error!("Failed to settle the confirmed transactions, retrying next round: {}", e);
+            }
+            if let Err(e) = self.confirm_submitted_refunds().await {
+                error!("Failed to confirm the refunds sent, retrying next round: {}", e);
+            }
+            if let Err(e) = self.send_outstanding_refunds().await {
+                error!("Failed to send refunds, retrying next round: {}", e);
+            }
+        }
+    }
+}
+
+impl RefundService {
+    async fn settle_confirmed_transactions(&self) -> Result<(), ServiceError> {
+        let threshold = self.context.configuration.threshold_in_strk;
+
+        for (transaction_hash, user, quote) in self.context.ledger.pending().await.map_err(ServiceError::from)? {
+            let receipt = match self.context.client.fetch_execution_receipt(transaction_hash, quote).await {
+                Ok(Some(receipt)) => receipt,
+                Ok(None) => continue,
+                Err(e) => {
+                    error!("Failed to fetch receipt of {}: {}", transaction_hash.to_fixed_hex_string(), e);
+                    continue;
+                },
+            };
+
+            let expected_fee = self.context.client.compute_paid_fee_in_strk(receipt.actual_fee_in_strk);
+            let refund = Refund::compute(user, &receipt, expected_fee, threshold);
+            if let Some(refund) = &refund {
+                metric!(counter[refund_recorded] = 1);
+                metric!(counter[refund_recorded_in_strk] = denormalize_felt(refund.amount_in_strk, 18));
+            }
+
+            self.context
+                .ledger
+                .settle(transaction_hash, refund)
+                .await
+                .map_err(ServiceError::from)?;
+        }
+
+        Ok(())
+    }
+
+    /// Mark the refunds sent as refunded once their transaction is accepted, and as outstanding again once it
+    /// reverted or can no longer be included
+    async fn confirm_submitted_refunds(&self) -> Result<(), ServiceError> {
+        let mut submitted: HashMap<(Felt, Felt), Vec<Felt>> = HashMap::new();
+        for refund in self.context.ledger.refunds(None).await.map_err(ServiceError::from)? {
+            if let RefundStatus::Submitted { transaction_hash, nonce } = refund.status {
+                submitted
+                    .entry((transaction_hash, nonce))
+                    .or_default()
+                    .push(refund.transaction_hash);
+            }
+        }
+
+        let gas_tank = self.context.client.get_relayer_manager().gas_tank();
+        for ((transaction_hash, nonce), refunds) in submitted {
+            let status = match gas_tank.outcome(transaction_hash, nonce).await.map_err(ServiceError::from)? {
+                Some(true) => RefundStatus::Refunded { transaction_hash },
+                Some(false) => {
+                    warn!(
+                        "Refund transaction {} was not executed, {} refunds will be sent again",
+                        transaction_hash.to_fixed_hex_string(),
+                        refunds.len()
+                    );
+                    RefundStatus::Outstanding
+                },
+                None => continue,
+            };
+
+            self.context
+                .ledger
+                .set_status(&refunds, status)
+                .await
+                .map_err(ServiceError::from)?;
+        }
+
+        Ok(())
+    }
+
+    async fn send_outstanding_refunds(&self) -> Result<(), ServiceError> {
+        let refunds = self.context.ledger.outstanding().await.map_err(ServiceError::from)?;
+        if refunds.is_empty() {
+            return Ok(());
+        }
+
+        // Batch the refunds of the same user in the same token into a single transfer
+        let mut amounts: HashMap<(Felt, Felt), Felt> = HashMap::new();
+        for refund in &refunds {
+            *amounts.entry((refund.user, refund.token)).or_default() += refund.amount;
+        }
+
+        let calls = Calls::new(
+            amounts
+                .into_iter()
+                .map(|((user, token), amount)| TokenTransfer::new(token, user, amount).to_call())
+                .collect(),
+        );
+
+        let submission = self
+            .context
+            .client
+            .get_relayer_manager()
+            .gas_tank()
+            .send(&calls)
+            .await
+            .map_err(ServiceError::from)?;
+
+        let status = match submission {
+            GasTankSubmission::Executed { transaction_hash } => RefundStatus::Refunded { transaction_hash },
+            GasTankSubmission::Submitted { transaction_hash, nonce } | GasTankSubmission::Proposed { transaction_hash, nonce } => {
+                RefundStatus::Submitted { transaction_hash, nonce }
+            },
+        };
+
+        let transaction_hashes: Vec<Felt> = refunds.iter().map(|x| x.transaction_hash).collect();
+        self.context
+            .ledger
+            .set_status(&transaction_hashes, status)
+            .await
+            .map_err(ServiceError::from)?;
+
+        metric!(counter[refund_sent] = refunds.len() as u64, status = submission.status());
+        info!(
+            "Sent {} refunds in transaction {} ({})",
+            refunds.len(),
+            submission.transaction_hash().to_fixed_hex_string(),
+            submission.status()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use paymaster_common::service::TokioServiceManager;
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_starknet::transaction::Calls;
+use paymaster_starknet::BlockHeader;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use crate::reorg::service::{ReorgContext, ReorgWatcher};
+use crate::Client;
+
+mod service;
+
+/// Duration during which an executed transaction is checked again when a reorg is detected
+const REORG_TRACKING_RETENTION: Duration = Duration::from_secs(600);
+
+/// Configuration of the watcher detecting the reorgs of the chain
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReorgConfiguration {
+    /// Interval in seconds between two checks of the latest block
+    #[serde(default = "ReorgConfiguration::default_check_interval")]
+    pub check_interval: u64,
+
+    /// Number of blocks whose hash is kept to detect the reorgs
+    #[serde(default = "ReorgConfiguration::default_depth")]
+    pub depth: u64,
+
+    /// Submit again the transactions dropped by a reorg
+    #[serde(default = "ReorgConfiguration::default_resubmit")]
+    pub resubmit: bool,
+
+    /// Number of consecutive checks in which a transaction must be missing before it is considered dropped, so
+    /// that a lagging or fallback node does not get the transaction submitted twice
+    #[serde(default = "ReorgConfiguration::default_drop_confirmations")]
+    pub drop_confirmations: u32,
+}
+
+impl ReorgConfiguration {
+    fn default_check_interval() -> u64 {
+        5
+    }
+
+    fn default_depth() -> u64 {
+        64
+    }
+
+    fn default_resubmit() -> bool {
+        true
+    }
+
+    fn default_drop_confirmations() -> u32 {
+        3
+    }
+}
+
+impl Validate for ReorgConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.check_interval > 0, "check_interval", "must be greater than 0");
+        report.ensure(self.depth > 0 && self.depth <= 1000, "depth", "must be between 1 and 1000");
+        report.ensure(self.drop_confirmations > 0, "drop_confirmations", "must be greater than 0");
+    }
+}
+
+/// Hashes of the latest blocks, used to detect that the chain was reorganized
+#[derive(Debug)]
+pub struct BlockHistory {
+    depth: u64,
+    blocks: BTreeMap<u64, Felt>,
+}
+
+impl BlockHistory {
+    pub fn new(depth: u64) -> Self {
+        Self { depth, blocks: BTreeMap::new() }
+    }
+
+    /// Record the latest block. Returns the number of the first block replaced when the block does not extend the
+    /// blocks recorded before
+    pub fn observe(&mut self, header: BlockHeader) -> Option<u64> {
+        let highest = self.blocks.last_key_value().map(|(number, _)| *number);
+
+        let replaced = match self.blocks.get(&header.number) {
+            // Same block as before, blocks recorded above it were orphaned
+            Some(hash) if *hash == header.hash => highest.filter(|x| *x > header.number).map(|_| header.number + 1),
+            Some(_) => Some(header.number),
+            None => match header.number.checked_sub(1).and_then(|x| self.blocks.get(&x)) {
+                Some(parent) if *parent != header.parent_hash => Some(header.number - 1),
+                // Unknown block below the highest one recorded, the chain went back
+                _ => highest.filter(|x| *x > header.number).map(|_| header.number),
+            },
+        };
+
+        if let Some(replaced) = replaced {
+            self.blocks.retain(|number, _| *number < replaced);
+        }
+
+        self.blocks.insert(header.number, header.hash);
+        self.blocks.retain(|number, _| number + self.depth > header.number);
+
+        replaced
+    }
+}
+
+/// Transactions executed recently along with their calls, so that they can be submitted again if a reorg drops them
+#[derive(Clone, Default)]
+pub struct ExecutedTransactions {
+    inner: Arc<RwLock<HashMap<Felt, (Calls, Instant)>>>,
+}
+
+impl ExecutedTransactions {
+    pub fn track(&self, transaction_hash: Felt, calls: Calls) {
+        let mut executed = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        executed.retain(|_, (_, at)| at.elapsed() < REORG_TRACKING_RETENTION);
+        executed.insert(transaction_hash, (calls, Instant::now()));
+    }
+
+    /// Returns the transactions executed recently
+    pub fn recent(&self) -> Vec<(Felt, Calls)> {
+        let executed = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        executed
+            .iter()
+            .filter(|(_, (_, at))| at.elapsed() < REORG_TRACKING_RETENTION)
+            .map(|(hash, (calls, _))| (*hash, calls.clone()))
+            .collect()
+    }
+
+    pub fn forget(&self, transaction_hash: Felt) {
+        let mut executed = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        executed.remove(&transaction_hash);
+    }
+}
+
+/// Detects the reorgs of the chain and reconciles the state of the transactions executed recently, submitting
+/// again the ones that were dropped. When no configuration is given, the chain is not watched.
+#[derive(Clone)]
+pub struct ReorgMonitor {
+    #[allow(dead_code)]
+    services: Option<Arc<TokioServiceManager<ReorgContext>>>,
+}
+
+impl ReorgMonitor {
+    pub fn new(client: &Client, configuration: Option<&ReorgConfiguration>) -> Self {
+        let services = configuration.map(|configuration| {
+            let mut services = TokioServiceManager::new(ReorgContext {
+                client: client.clone(),
+                configuration: configuration.clone(),
+            });
+            services.spawn::<ReorgWatcher>();
+
+            Arc::new(services)
+        });
+
+        Self { services }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use paymaster_starknet::BlockHeader;
+    use starknet::core::types::Felt;
+
+    use crate::reorg::BlockHistory;
+
+    fn header(number: u64, hash: u64, parent_hash: u64) -> BlockHeader {
+        BlockHeader {
+            number,
+            hash: Felt::from(hash),
+            parent_hash: Felt::from(parent_hash),
+        }
+    }
+
+    #[test]
+    fn extending_blocks_are_not_reorgs() {
+        let mut history = BlockHistory::new(10);
+
+        assert_eq!(history.observe(header(1, 10, 0)), None);
+        assert_eq!(history.observe(header(2, 20, 10)), None);
+        assert_eq!(history.observe(header(2, 20, 10)), None);
+        assert_eq!(history.observe(header(4, 40, 30)), None);
+    }
+
+    #[test]
+    fn replaced_blocks_are_detected() {
+        let mut history = BlockHistory::new(10);
+        history.observe(header(1, 10, 0));
+        history.observe(header(2, 20, 10));
+
+        // Block 2 replaced by another one
+        assert_eq!(history.observe(header(2, 21, 10)), Some(2));
+
+        // Block 3 does not extend the block 2 recorded
+        assert_eq!(history.observe(header(3, 30, 20)), Some(2));
+
+        // Chain went back to block 1
+        history.observe(header(2, 20, 10));
+        assert_eq!(history.observe(header(1, 10, 0)), Some(2));
+    }
+
+    #[test]
+    fn only_the_latest_blocks_are_kept() {
+        let mut history = BlockHistory::new(2);
+        history.observe(header(1, 10, 0));
+        history.observe(header(2, 20, 10));
+        history.observe(header(3, 30, 20));
+
+        // Block 1 is no longer known, going back to it is still detected
+        assert_eq!(history.observe(header(1, 11, 0)), Some(1));
+        assert_eq!(history.blocks.len(), 1);
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use paymaster_common::metric;
+use paymaster_common::service::{Error as ServiceError, Service};
+use paymaster_starknet::transaction::Calls;
+use starknet::core::types::{Felt, TransactionStatus};
+use tokio::time::interval;
+use tracing::{error, info, warn};
+
+use crate::execution::TipPriority;
+use crate::reorg::{BlockHistory, ReorgConfiguration};
+use crate::{Client, Error};
+
+#[derive(Clone)]
+pub struct ReorgContext {
+    pub client: Client,
+    pub configuration: ReorgConfiguration,
+}
+
+/// Watches the latest block and, when the chain is reorganized, checks again the status of the transactions executed
+/// recently
+pub struct ReorgWatcher {
+    context: ReorgContext,
+}
+
+#[async_trait]
+impl Service for ReorgWatcher {
+    type Context = ReorgContext;
+
+    const NAME: &'static str = "ReorgWatcher";
+
+    async fn new(context: ReorgContext) -> Self {
+        Self { context }
+    }
+
+    async fn run(self) -> Result<(), ServiceError> {
+        let mut history = BlockHistory::new(self.context.configuration.depth);
+        let mut reconciliation = Reconciliation::new(&self.context.configuration);
+
+        let mut ticker = interval(Duration::from_secs(self.context.configuration.check_interval));
+        loop {
+            ticker.tick().await;
+
+            let header = match self.context.client.starknet.fetch_latest_block_header().await {
+                Ok(header) => header,
+                Err(e) => {
+                    warn!("Could not fetch the latest block: {}", e);
+                    continue;
+                },
+            };
+
+            match history.observe(header) {
+                Some(replaced) => {
+                    metric!(counter[starknet_reorg] = 1);
+                    warn!("Chain reorganized from block {}, latest block is {}", replaced, header.number);
+
+                    let executed = self.context.client.executed.recent();
+                    reconciliation.reconcile(&self.context.client, executed).await;
+                },
+                // The transactions missing since the reorg are checked again until they are found or dropped
+                None => reconciliation.confirm_missing(&self.context.client).await,
+            }
+        }
+    }
+}
+
+/// Chain on which the transactions executed recently are reconciled after a reorg
+#[async_trait]
+trait ReconciledChain: Send + Sync {
+    /// Fetches the status of the transaction, ignoring the statuses cached before the reorg
+    async fn fetch_status(&self, transaction_hash: Felt) -> Result<TransactionStatus, paymaster_starknet::Error>;
+
+    fn record_status(&self, transaction_hash: Felt, status: &TransactionStatus);
+
+    /// Stops tracking a transaction dropped by the reorg
+    fn forget(&self, transaction_hash: Felt);
+
+    /// Estimates and executes the calls again, returning the hash of the new transaction. The calls are estimated
+    /// again since the state they were estimated against may have been reorganized too.
+    async fn resubmit(&self, calls: &Calls) -> Result<Felt, Error>;
+}
+
+#[async_trait]
+impl ReconciledChain for Client {
+    async fn fetch_status(&self, transaction_hash: Felt) -> Result<TransactionStatus, paymaster_starknet::Error> {
+        // The statuses cached before the reorg may refer to the replaced blocks
+        self.starknet.invalidate_transaction_status(transaction_hash);
+        self.starknet.get_transaction_status(transaction_hash).await
+    }
+
+    fn record_status(&self, transaction_hash: Felt, status: &TransactionStatus) {
+        self.finality.record_status(transaction_hash, status);
+    }
+
+    fn forget(&self, transaction_hash: Felt) {
+        self.executed.forget(transaction_hash);
+    }
+
+    async fn resubmit(&self, calls: &Calls) -> Result<Felt, Error> {
+        let estimated = self.estimate(calls, TipPriority::Normal).await?;
+        let result = self.execute(&estimated).await?;
+
+        Ok(result.transaction_hash)
+    }
+}
+
+/// Transactions checked again after a reorg. A transaction is only considered dropped once it was missing in
+/// `drop_confirmations` consecutive checks, a single miss may come from a node lagging behind the reorg.
+struct Reconciliation {
+    drop_confirmations: u32,
+    resubmit: bool,
+
+    /// Transactions missing since the reorg along with the number of consecutive checks they were missing in
+    missing: HashMap<Felt, (Calls, u32)>,
+}
+
+impl Reconciliation {
+    fn new(configuration: &ReorgConfiguration) -> Self {
+        Self {
+            drop_confirmations: configuration.drop_confirmations,
+            resubmit: configuration.resubmit,
+            missing: HashMap::new(),
+        }
+    }
+
+    /// Checks again the status of the given transactions
+    async fn reconcile(&mut self, chain: &impl ReconciledChain, transactions: Vec<(Felt, Calls)>) {
+        for (transaction_hash, calls) in transactions {
+            match chain.fetch_status(transaction_hash).await {
+                Ok(status) => {
+                    self.missing.remove(&transaction_hash);
+                    chain.record_status(transaction_hash, &status);
+                },
+                Err(paymaster_starknet::Error::TransactionNotFound) => self.record_miss(chain, transaction_hash, calls).await,
+                Err(e) => warn!("Could not check the status of {}: {}", transaction_hash.to_fixed_hex_string(), e),
+            }
+        }
+    }
+
+    /// Checks again the transactions missing since the reorg
+    async fn confirm_missing(&mut self, chain: &impl ReconciledChain) {
+        if self.missing.is_empty() {
+            return;
+        }
+
+        let missing = self.missing.iter().map(|(hash, (calls, _))| (*hash, calls.clone())).collect();
+        self.reconcile(chain, missing).await;
+    }
+
+    async fn record_miss(&mut self, chain: &impl ReconciledChain, transaction_hash: Felt, calls: Calls) {
+        let misses = self
+            .missing
+            .get(&transaction_hash)
+            .map(|(_, misses)| *misses)
+            .unwrap_or_default()
+            + 1;
+        if misses < self.drop_confirmations {
+            info!("Transaction {} not found after the reorg, checking again", transaction_hash.to_fixed_hex_string());
+            self.missing.insert(transaction_hash, (calls, misses));
+            return;
+        }
+
+        self.missing.remove(&transaction_hash);
+
+        metric!(counter[starknet_reorg_dropped_transaction] = 1);
+        warn!("Transaction {} dropped by the reorg", transaction_hash.to_fixed_hex_string());
+
+        chain.forget(transaction_hash);
+        if !self.resubmit {
+            return;
+        }
+
+        match chain.resubmit(&calls).await {
+            Ok(hash) => info!(
+                "Transaction {} dropped by the reorg submitted again as {}",
+                transaction_hash.to_fixed_hex_string(),
+                hash.to_fixed_hex_string()
+            ),
+            Err(e) => error!(
+                "Could not submit again the transaction {} dropped by the reorg: {}",
+                transaction_hash.to_fixed_hex_string(),
+                e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use paymaster_starknet::transaction::Calls;
+    use starknet::core::types::{ExecutionResult, Felt, TransactionStatus};
+
+    use crate::reorg::service::{ReconciledChain, Reconciliation};
+    use crate::reorg::ReorgConfiguration;
+    use crate::testing::transaction::an_eth_transfer;
+    use crate::Error;
+
+    /// Chain on which the transactions whose status is not set are missing
+    #[derive(Default)]
+    struct FakeChain {
+        statuses: Mutex<HashMap<Felt, TransactionStatus>>,
+
+        recorded: Mutex<Vec<Felt>>,
+        forgotten: Mutex<Vec<Felt>>,
+        resubmitted: Mutex<Vec<Calls>>,
+    }
+
+    impl FakeChain {
+        fn include(&self, transaction_hash: Felt) {
+            let status = TransactionStatus::AcceptedOnL2(ExecutionResult::Succeeded);
+            self.statuses.lock().unwrap().insert(transaction_hash, status);
+        }
+    }
+
+    #[async_trait]
+    impl ReconciledChain for FakeChain {
+        async fn fetch_status(&self, transaction_hash: Felt) -> Result<TransactionStatus, paymaster_starknet::Error> {
+            let status = self.statuses.lock().unwrap().get(&transaction_hash).cloned();
+            status.ok_or(paymaster_starknet::Error::TransactionNotFound)
+        }
+
+        fn record_status(&self, transaction_hash: Felt, _status: &TransactionStatus) {
+            self.recorded.lock().unwrap().push(transaction_hash);
+        }
+
+        fn forget(&self, transaction_hash: Felt) {
+            self.forgotten.lock().unwrap().push(transaction_hash);
+        }
+
+        async fn resubmit(&self, calls: &Calls) -> Result<Felt, Error> {
+            self.resubmitted.lock().unwrap().push(calls.clone());
+            Ok(Felt::from(0xbeef))
+        }
+    }
+
+    fn a_reconciliation(resubmit: bool) -> Reconciliation {
+        Reconciliation::new(&ReorgConfiguration {
+            check_interval: 5,
+            depth: 64,
+            resubmit,
+            drop_confirmations: 3,
+        })
+    }
+
+    fn an_execution(hash: u64) -> (Felt, Calls) {
+        (Felt::from(hash), Calls::new(vec![an_eth_transfer(Felt::ONE, Felt::from(hash))]))
+    }
+
+    #[tokio::test]
+    async fn transactions_still_included_are_kept() {
+        let chain = FakeChain::default();
+        chain.include(Felt::from(1));
+
+        let mut reconciliation = a_reconciliation(true);
+        reconciliation.reconcile(&chain, vec![an_execution(1)]).await;
+
+        assert_eq!(*chain.recorded.lock().unwrap(), vec![Felt::from(1)]);
+        assert!(chain.forgotten.lock().unwrap().is_empty());
+        assert!(reconciliation.missing.is_empty());
+    }
+
+    #[tokio::test]
+    async fn missing_transactions_are_resubmitted_after_consecutive_misses() {
+        let chain = FakeChain::default();
+
+        let mut reconciliation = a_reconciliation(true);
+        reconciliation.reconcile(&chain, vec![an_execution(1)]).await;
+        reconciliation.confirm_missing(&chain).await;
+        assert!(chain.resubmitted.lock().unwrap().is_empty());
+        assert!(chain.forgotten.lock().unwrap().is_empty());
+
+        reconciliation.confirm_missing(&chain).await;
+        assert_eq!(chain.resubmitted.lock().unwrap().len(), 1);
+        assert_eq!(*chain.forgotten.lock().unwrap(), vec![Felt::from(1)]);
+
+        // Dropped transactions are not checked again
+        reconciliation.confirm_missing(&chain).await;
+        assert_eq!(chain.resubmitted.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn transactions_found_again_are_not_resubmitted() {
+        let chain = FakeChain::default();
+
+        let mut reconciliation = a_reconciliation(true);
+        reconciliation.reconcile(&chain, vec![an_execution(1)]).await;
+        reconciliation.confirm_missing(&chain).await;
+
+        // The node lagging behind the reorg caught up
+        chain.include(Felt::from(1));
+        reconciliation.confirm_missing(&chain).await;
+        reconciliation.confirm_missing(&chain).await;
+
+        assert!(chain.resubmitted.lock().unwrap().is_empty());
+        assert!(chain.forgotten.lock().unwrap().is_empty());
+        assert!(reconciliation.missing.is_empty());
+    }
+
+    #[tokio::test]
+    async fn dropped_transactions_are_only_forgotten_without_resubmission() {
+        let chain = FakeChain::default();
+
+        let mut reconciliation = a_reconciliation(false);
+        for _ in 0..3 {
+            reconciliation.reconcile(&chain, vec![an_execution(1)]).await;
+        }
+
+        assert_eq!(*chain.forgotten.lock().unwrap(), vec![Felt::from(1)]);
+        assert!(chain.resubmitted.lock().unwrap().is_empty());
+    }
+}
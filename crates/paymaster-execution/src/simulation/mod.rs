@@ -0,0 +1,392 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+
+use deadpool_redis::redis::{cmd, pipe, AsyncCommands};
+use deadpool_redis::{Connection, Pool};
+use paymaster_relayer::lock::LockLayerConfiguration;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{Felt, NonZeroFelt};
+use tracing::error;
+
+use crate::clock::now;
+use crate::execution::FeeQuote;
+use crate::Error;
+
+/// Number of transactions kept in the [`ExecutionLedger`]
+const LEDGER_CAPACITY: usize = 10_000;
+
+/// Duration during which the fee paid for a transaction of the [`ExecutionLedger`] is kept in Redis (in seconds)
+const SETTLEMENT_RETENTION: u64 = 7 * 24 * 3600;
+
+const LEDGER_KEY: &str = "execution-ledger";
+
+/// Pricing parameters of the paymaster, see the execution [`crate::Configuration`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PricingParameters {
+    pub max_fee_multiplier: f32,
+    pub provider_fee_overhead: f32,
+}
+
+/// Transaction executed by the paymaster along with the fee quoted to execute it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionRecord {
+    pub transaction_hash: Felt,
+    pub quote: FeeQuote,
+
+    /// Whether the fee was paid by a sponsor rather than by the user
+    pub sponsored: bool,
+
+    /// Fee actually paid by the relayer in STRK, None until the transaction is confirmed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actual_fee_in_strk: Option<Felt>,
+
+    /// Unix timestamp (in seconds) at which the transaction was executed
+    pub timestamp: u64,
+}
+
+/// Ledger of the most recent transactions executed by the paymaster. Shared by the instances through the Redis of
+/// the shared lock layer, kept in memory otherwise.
+#[derive(Clone)]
+pub struct ExecutionLedger {
+    redis: Option<Pool>,
+
+    memory: Arc<RwLock<VecDeque<ExecutionRecord>>>,
+    capacity: usize,
+}
+
+impl Default for ExecutionLedger {
+    fn default() -> Self {
+        Self::with_capacity(LEDGER_CAPACITY)
+    }
+}
+
+impl ExecutionLedger {
+    pub fn new(lock: &LockLayerConfiguration) -> Result<Self, Error> {
+        let redis = match lock {
+            LockLayerConfiguration::Shared { redis, .. } => Some(redis.create_pool().map_err(|e| Error::Internal(e.to_string()))?),
+            _ => None,
+        };
+
+        Ok(Self { redis, ..Self::default() })
+    }
+
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            redis: None,
+            memory: Arc::new(RwLock::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    /// Record a transaction executed with the given `quote`, evicting the oldest one when the ledger is full. A
+    /// transaction which cannot be recorded is left out of the simulations.
+    pub async fn record(&self, transaction_hash: Felt, quote: FeeQuote, sponsored: bool) {
+        let record = ExecutionRecord {
+            transaction_hash,
+            quote,
+            sponsored,
+            actual_fee_in_strk: None,
+            timestamp: now(),
+        };
+
+        let Some(redis) = &self.redis else {
+            let mut ledger = self.memory.write().unwrap_or_else(|e| e.into_inner());
+            if ledger.len() >= self.capacity {
+                ledger.pop_front();
+            }
+            ledger.push_back(record);
+            return;
+        };
+
+        if let Err(e) = self.push(redis, &record).await {
+            error!(
+                "Failed to record transaction {} in the execution ledger: {}",
+                transaction_hash.to_fixed_hex_string(),
+                e
+            );
+        }
+    }
+
+    async fn push(&self, redis: &Pool, record: &ExecutionRecord) -> Result<(), Error> {
+        let value = serde_json::to_vec(record).map_err(|e| Error::Internal(e.to_string()))?;
+
+        let mut connection = Self::connection(redis).await?;
+        pipe()
+            .atomic()
+            .lpush(LEDGER_KEY, value)
+            .ignore()
+            .ltrim(LEDGER_KEY, 0, self.capacity as isize - 1)
+            .ignore()
+            .query_async::<()>(&mut connection)
+            .await
+            .map_err(|e| Error::Internal(e.to_string()))
+    }
+
+    /// Set the fee actually paid for the given transaction once it is confirmed
+    pub async fn settle(&self, transaction_hash: Felt, actual_fee_in_strk: Felt) -> Result<(), Error> {
+        let Some(redis) = &self.redis else {
+            let mut ledger = self.memory.write().unwrap_or_else(|e| e.into_inner());
+            if let Some(record) = ledger.iter_mut().find(|x| x.transaction_hash == transaction_hash) {
+                record.actual_fee_in_strk = Some(actual_fee_in_strk);
+            }
+            return Ok(());
+        };
+
+        let mut connection = Self::connection(redis).await?;
+        let _: () = connection
+            .set_ex(Self::settlement_key(transaction_hash), actual_fee_in_strk.to_fixed_hex_string(), SETTLEMENT_RETENTION)
+            .await
+            .map_err(|e| Error::Internal(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Returns the `limit` most recent transactions, most recent first
+    pub async fn recent(&self, limit: usize) -> Result<Vec<ExecutionRecord>, Error> {
+        let Some(redis) = &self.redis else {
+            let ledger = self.memory.read().unwrap_or_else(|e| e.into_inner());
+            return Ok(ledger.iter().rev().take(limit).cloned().collect());
+        };
+        if limit == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut connection = Self::connection(redis).await?;
+        let values: Vec<Vec<u8>> = connection
+            .lrange(LEDGER_KEY, 0, limit as isize - 1)
+            .await
+            .map_err(|e| Error::Internal(e.to_string()))?;
+        let mut records: Vec<ExecutionRecord> = values.iter().filter_map(|x| serde_json::from_slice(x).ok()).collect();
+        if records.is_empty() {
+            return Ok(records);
+        }
+
+        let keys: Vec<String> = records.iter().map(|x| Self::settlement_key(x.transaction_hash)).collect();
+        let fees: Vec<Option<String>> = cmd("MGET")
+            .arg(keys)
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| Error::Internal(e.to_string()))?;
+        for (record, fee) in records.iter_mut().zip(fees) {
+            record.actual_fee_in_strk = fee.and_then(|x| Felt::from_hex(&x).ok());
+        }
+
+        Ok(records)
+    }
+
+    fn settlement_key(transaction_hash: Felt) -> String {
+        format!("execution-ledger-fee:{}", transaction_hash.to_fixed_hex_string())
+    }
+
+    async fn connection(redis: &Pool) -> Result<Connection, Error> {
+        redis.get().await.map_err(|e| Error::Internal(e.to_string()))
+    }
+}
+
+/// Outcome of a set of transactions under given pricing parameters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PricingOutcome {
+    /// Fee charged to the users and sponsors in STRK
+    pub charged_in_strk: Felt,
+
+    /// Fee charged to the users only (i.e. excluding sponsored transactions) in STRK
+    pub user_cost_in_strk: Felt,
+
+    /// Maximum fee the users had to approve in STRK
+    pub max_fee_in_strk: Felt,
+
+    /// Number of transactions whose actual fee exceeds the maximum fee the user approved. These transactions
+    /// could not have been executed with the parameters.
+    pub uncovered_transactions: usize,
+}
+
+/// Result of the replay of recent transactions against proposed pricing parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PricingSimulation {
+    /// Number of confirmed transactions replayed
+    pub transactions: usize,
+
+    /// Fee actually paid by the relayers in STRK
+    pub paid_in_strk: Felt,
+
+    pub current: PricingOutcome,
+    pub proposed: PricingOutcome,
+}
+
+impl PricingSimulation {
+    /// Replay the confirmed `records` with the `proposed` parameters. The estimate on which the fee of each
+    /// transaction was computed is recovered from its quote using the `current` parameters.
+    pub fn run(records: &[ExecutionRecord], current: PricingParameters, proposed: PricingParameters) -> Self {
+        let mut simulation = Self {
+            transactions: 0,
+            paid_in_strk: Felt::ZERO,
+            current: PricingOutcome::default(),
+            proposed: PricingOutcome::default(),
+        };
+
+        for record in records {
+            let Some(actual_fee_in_strk) = record.actual_fee_in_strk else {
+                continue;
+            };
+
+            let base_estimate = unscale(record.quote.fee_in_strk, 1.0 + current.provider_fee_overhead);
+            simulation.transactions += 1;
+            simulation.paid_in_strk += actual_fee_in_strk;
+
+            Self::apply(&mut simulation.current, record, base_estimate, actual_fee_in_strk, current);
+            Self::apply(&mut simulation.proposed, record, base_estimate, actual_fee_in_strk, proposed);
+        }
+
+        simulation
+    }
+
+    fn apply(outcome: &mut PricingOutcome, record: &ExecutionRecord, base_estimate: Felt, actual_fee_in_strk: Felt, parameters: PricingParameters) {
+        let charged = scale(base_estimate, 1.0 + parameters.provider_fee_overhead);
+        outcome.charged_in_strk += charged;
+
+        if record.sponsored {
+            return;
+        }
+
+        let max_fee = scale(base_estimate, parameters.max_fee_multiplier);
+        outcome.user_cost_in_strk += charged;
+        outcome.max_fee_in_strk += max_fee;
+        if actual_fee_in_strk > max_fee {
+            outcome.uncovered_transactions += 1;
+        }
+    }
+
+    /// Difference in revenue (fee charged minus fee paid) between the proposed and the current parameters, in STRK
+    pub fn revenue_delta_in_strk(&self) -> i128 {
+        delta(self.current.charged_in_strk, self.proposed.charged_in_strk)
+    }
+
+    /// Difference in cost for the users between the proposed and the current parameters, in STRK
+    pub fn user_cost_delta_in_strk(&self) -> i128 {
+        delta(self.current.user_cost_in_strk, self.proposed.user_cost_in_strk)
+    }
+}
+
+// Multipliers are applied with a precision of 1e-3 like the execution client does
+fn scale(value: Felt, multiplier: f32) -> Felt {
+    let multiplier = Felt::from((multiplier * 1000.0) as u32);
+    (multiplier * value).floor_div(&NonZeroFelt::from_felt_unchecked(Felt::from(1000)))
+}
+
+fn unscale(value: Felt, multiplier: f32) -> Felt {
+    let multiplier = Felt::from(((multiplier * 1000.0) as u32).max(1));
+    (value * Felt::from(1000)).floor_div(&NonZeroFelt::from_felt_unchecked(multiplier))
+}
+
+fn delta(from: Felt, to: Felt) -> i128 {
+    let from: u128 = from.try_into().unwrap_or(u128::MAX);
+    let to: u128 = to.try_into().unwrap_or(u128::MAX);
+
+    i128::try_from(to)
+        .unwrap_or(i128::MAX)
+        .saturating_sub(i128::try_from(from).unwrap_or(i128::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use paymaster_relayer::lock::shared::RedisParameters;
+    use paymaster_relayer::lock::LockLayerConfiguration;
+    use paymaster_starknet::constants::ChainTokens;
+    use paymaster_starknet::ChainID;
+    use starknet::core::types::Felt;
+    use testcontainers::core::{IntoContainerPort, WaitFor};
+    use testcontainers::runners::AsyncRunner;
+    use testcontainers::GenericImage;
+
+    use crate::execution::FeeQuote;
+    use crate::simulation::{ExecutionLedger, PricingParameters, PricingSimulation};
+
+    const CURRENT: PricingParameters = PricingParameters {
+        max_fee_multiplier: 3.0,
+        provider_fee_overhead: 0.1,
+    };
+
+    fn a_quote(fee_in_strk: u64) -> FeeQuote {
+        FeeQuote {
+            gas_token: Felt::ONE,
+            fee_in_token: Felt::from(fee_in_strk),
+            fee_in_strk: Felt::from(fee_in_strk),
+        }
+    }
+
+    #[tokio::test]
+    async fn ledger_keeps_most_recent_transactions() {
+        let ledger = ExecutionLedger::with_capacity(2);
+        ledger.record(Felt::ONE, a_quote(1), false).await;
+        ledger.record(Felt::TWO, a_quote(2), false).await;
+        ledger.record(Felt::THREE, a_quote(3), false).await;
+
+        let recent = ledger.recent(10).await.unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].transaction_hash, Felt::THREE);
+        assert_eq!(recent[1].transaction_hash, Felt::TWO);
+    }
+
+    #[tokio::test]
+    async fn ledger_is_shared_through_redis() {
+        let container = GenericImage::new("redis", "7")
+            .with_exposed_port(6379.tcp())
+            .with_wait_for(WaitFor::message_on_stdout("Ready to accept connections"))
+            .start()
+            .await
+            .unwrap();
+        let port = container.get_host_port_ipv4(6379).await.unwrap();
+        let lock = LockLayerConfiguration::Shared {
+            retry_timeout: Duration::from_secs(5),
+            redis: RedisParameters::new(&format!("redis://127.0.0.1:{}", port)),
+            fallback: None,
+        };
+
+        let ledger = ExecutionLedger::new(&lock).unwrap();
+        ledger.record(Felt::ONE, a_quote(1), false).await;
+        ledger
+            .record(Felt::TWO, FeeQuote::sponsored(&ChainTokens::starknet(&ChainID::Sepolia), Felt::TWO), true)
+            .await;
+        ledger.settle(Felt::ONE, Felt::from(10)).await.unwrap();
+
+        // Another instance sees the transactions and their settlement
+        let other = ExecutionLedger::new(&lock).unwrap();
+        let recent = other.recent(10).await.unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].transaction_hash, Felt::TWO);
+        assert!(recent[0].sponsored);
+        assert_eq!(recent[0].actual_fee_in_strk, None);
+        assert_eq!(recent[1].actual_fee_in_strk, Some(Felt::from(10)));
+        assert_eq!(other.recent(1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn simulation_reports_revenue_and_user_cost_deltas() {
+        let ledger = ExecutionLedger::default();
+        ledger.record(Felt::ONE, a_quote(1100), false).await;
+        ledger
+            .record(Felt::TWO, FeeQuote::sponsored(&ChainTokens::starknet(&ChainID::Sepolia), Felt::from(2200)), true)
+            .await;
+        ledger.record(Felt::THREE, a_quote(5500), false).await;
+        ledger.settle(Felt::ONE, Felt::from(1000)).await.unwrap();
+        ledger.settle(Felt::TWO, Felt::from(2000)).await.unwrap();
+
+        let proposed = PricingParameters {
+            max_fee_multiplier: 1.5,
+            provider_fee_overhead: 0.2,
+        };
+        let simulation = PricingSimulation::run(&ledger.recent(10).await.unwrap(), CURRENT, proposed);
+
+        // The unconfirmed transaction is not replayed
+        assert_eq!(simulation.transactions, 2);
+        assert_eq!(simulation.paid_in_strk, Felt::from(3000));
+        assert_eq!(simulation.current.charged_in_strk, Felt::from(3300));
+        assert_eq!(simulation.proposed.charged_in_strk, Felt::from(3600));
+        assert_eq!(simulation.revenue_delta_in_strk(), 300);
+        assert_eq!(simulation.user_cost_delta_in_strk(), 100);
+        assert_eq!(simulation.proposed.max_fee_in_strk, Felt::from(1500));
+        assert_eq!(simulation.proposed.uncovered_transactions, 0);
+    }
+}
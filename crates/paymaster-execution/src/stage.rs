@@ -0,0 +1,64 @@
+use std::future::Future;
+
+use paymaster_common::{measure_duration, metric};
+use tracing::{info_span, Instrument};
+
+/// Stage of the build and execute flows whose latency is measured on its own, so that a latency regression
+/// can be attributed to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Checks performed on the request before anything is fetched or estimated
+    Validation,
+
+    /// Fetch of the price of the gas token
+    PriceFetch,
+
+    /// Estimation of the transaction on Starknet
+    Estimation,
+
+    /// Wait for an available relayer
+    LockAcquisition,
+
+    /// Submission of the transaction with the relayer, nonce retries included
+    Submission,
+}
+
+impl Stage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Validation => "validation",
+            Self::PriceFetch => "price_fetch",
+            Self::Estimation => "estimation",
+            Self::LockAcquisition => "lock_acquisition",
+            Self::Submission => "submission",
+        }
+    }
+}
+
+/// Run the given stage of `method` in a span nested in the span of the request, recording its latency in the
+/// `execution_stage_duration_milliseconds` histogram
+pub async fn measure_stage<F: Future>(method: &'static str, stage: Stage, future: F) -> F::Output {
+    let span = info_span!("stage", method, stage = stage.name());
+
+    let (result, duration) = measure_duration!(future.instrument(span).await);
+    metric!(
+        histogram[execution_stage_duration_milliseconds] = duration.as_millis(),
+        method = method,
+        stage = stage.name()
+    );
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stage::{measure_stage, Stage};
+
+    #[tokio::test]
+    async fn stage_returns_the_output_of_its_future() {
+        let result = measure_stage("build", Stage::Validation, async { 42 }).await;
+
+        assert_eq!(result, 42);
+        assert_eq!(Stage::LockAcquisition.name(), "lock_acquisition");
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use paymaster_starknet::BlockGasPrice;
+
+/// Window over which the average gas price is measured
+pub const GAS_PRICE_WINDOW: Duration = Duration::from_secs(300);
+
+/// Keeps the gas prices fetched recently to detect when they surge
+#[derive(Clone)]
+pub struct GasPriceTracker {
+    window: Duration,
+    samples: Arc<RwLock<VecDeque<(Instant, [f64; 3])>>>,
+}
+
+impl Default for GasPriceTracker {
+    fn default() -> Self {
+        Self::new(GAS_PRICE_WINDOW)
+    }
+}
+
+impl GasPriceTracker {
+    pub fn new(window: Duration) -> Self {
+        Self { window, samples: Arc::default() }
+    }
+
+    /// Record the gas price of the latest block
+    pub fn record(&self, price: BlockGasPrice) {
+        let components = [price.l1_gas_price, price.l1_data_gas_price, price.l2_gas_price].map(|x| u128::try_from(x).unwrap_or(u128::MAX) as f64);
+
+        let now = Instant::now();
+        let mut samples = self.samples.write().unwrap_or_else(|e| e.into_inner());
+
+        samples.push_back((now, components));
+        while samples.front().is_some_and(|(at, _)| now.duration_since(*at) > self.window) {
+            samples.pop_front();
+        }
+    }
+
+    /// Returns true when one of the components of the latest gas price exceeds its average over the window by
+    /// the given factor. Returns false when not enough prices were recorded to measure it.
+    pub fn is_surging(&self, factor: f64) -> bool {
+        let samples = self.samples.read().unwrap_or_else(|e| e.into_inner());
+        let Some((_, latest)) = samples.back() else { return false };
+        if samples.len() < 2 {
+            return false;
+        }
+
+        (0..latest.len()).any(|i| {
+            let average = samples.iter().map(|(_, x)| x[i]).sum::<f64>() / samples.len() as f64;
+            average > 0.0 && latest[i] > average * factor
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use paymaster_starknet::BlockGasPrice;
+    use starknet::core::types::Felt;
+
+    use crate::starknet::gas::GasPriceTracker;
+
+    fn price(l2_gas_price: u64) -> BlockGasPrice {
+        BlockGasPrice {
+            l1_gas_price: Felt::from(100),
+            l1_data_gas_price: Felt::from(10),
+            l2_gas_price: Felt::from(l2_gas_price),
+        }
+    }
+
+    #[test]
+    fn surge_is_detected_against_the_average_price() {
+        let tracker = GasPriceTracker::default();
+
+        tracker.record(price(1000));
+        assert!(!tracker.is_surging(1.5));
+
+        tracker.record(price(1100));
+        tracker.record(price(900));
+        assert!(!tracker.is_surging(1.5));
+
+        // The average is now 1750, the latest price exceeds it by more than 50%
+        tracker.record(price(4000));
+        assert!(tracker.is_surging(1.5));
+        assert!(!tracker.is_surging(3.0));
+    }
+}
@@ -2,16 +2,19 @@ use std::ops::Deref;
 use std::time::Duration;
 
 use moka::sync::Cache;
-use paymaster_common::cache::ExpirableCache;
-use paymaster_common::concurrency::SyncValue;
-use paymaster_starknet::transaction::PaymasterVersion;
+use paymaster_common::cache::{ExpirableCache, RefreshAheadValue};
+use paymaster_starknet::transaction::{controller, PaymasterVersion};
 use paymaster_starknet::{BlockGasPrice, Configuration, ContractAddress};
 use starknet::core::types::Felt;
 use tracing::warn;
 
 use crate::execution::ValidationGasOverhead;
+use crate::low_rpc::{LowRpcConfiguration, LowRpcMode};
 use crate::Error;
 
+mod gas;
+pub use gas::GasPriceTracker;
+
 /// Starknet client with convenience methods used when executing paymaster transaction. This
 /// can be shared between threads safely, taking advantages of the internal caching to reduce
 /// the number of external calls made.
@@ -19,11 +22,18 @@ use crate::Error;
 pub struct Client {
     inner: paymaster_starknet::Client,
 
-    // Cache block price for 10 seconds
-    cache_block_price: SyncValue<BlockGasPrice>,
+    // Cache block price for 10 seconds, refreshed ahead of expiration
+    cache_block_price: RefreshAheadValue<BlockGasPrice>,
+
+    // Block prices fetched over the last minutes, used to detect surges
+    gas_prices: GasPriceTracker,
+
+    // Cache block price while the low-RPC mode is enabled, refreshed less frequently
+    low_rpc: LowRpcMode,
+    cache_block_price_low_rpc: RefreshAheadValue<BlockGasPrice>,
 
-    // Cache median tip for 10 seconds
-    cache_median_tip: SyncValue<u64>,
+    // Cache median tip for 10 seconds, refreshed ahead of expiration
+    cache_median_tip: RefreshAheadValue<u64>,
 
     // Cache account version for 5 minutes
     cache_account_version: ExpirableCache<Felt, PaymasterVersion>,
@@ -31,6 +41,9 @@ pub struct Client {
     // Cache class version
     cache_class_version: Cache<Felt, PaymasterVersion>,
 
+    // Cache whether a class is a Cartridge Controller account
+    cache_controller_class: Cache<Felt, bool>,
+
     // Cache account overhead
     cache_overhead: Cache<Felt, ValidationGasOverhead>,
 }
@@ -45,16 +58,27 @@ impl Deref for Client {
 
 impl Client {
     /// Creates a new client given a [`configuration`]
-    pub fn new(configuration: &Configuration) -> Self {
-        Self {
-            inner: paymaster_starknet::Client::new(configuration),
-
-            cache_block_price: SyncValue::new(Duration::from_secs(10)),
-            cache_median_tip: SyncValue::new(Duration::from_secs(10)),
+    pub fn new(configuration: &Configuration) -> Result<Self, Error> {
+        Ok(Self {
+            inner: paymaster_starknet::Client::new(configuration)?,
+
+            cache_block_price: RefreshAheadValue::new(Duration::from_secs(10)),
+            gas_prices: GasPriceTracker::default(),
+            low_rpc: LowRpcMode::new(&LowRpcConfiguration::default()),
+            cache_block_price_low_rpc: RefreshAheadValue::new(Duration::from_secs(LowRpcConfiguration::default().gas_price_refresh)),
+            cache_median_tip: RefreshAheadValue::new(Duration::from_secs(10)),
             cache_account_version: ExpirableCache::new(1024),
             cache_class_version: Cache::new(128),
+            cache_controller_class: Cache::new(128),
             cache_overhead: Cache::new(1024),
-        }
+        })
+    }
+
+    /// Refresh the gas price at the interval of the given low-RPC mode while it is enabled
+    pub fn with_low_rpc(mut self, low_rpc: LowRpcMode) -> Self {
+        self.cache_block_price_low_rpc = RefreshAheadValue::new(low_rpc.gas_price_refresh());
+        self.low_rpc = low_rpc;
+        self
     }
 
     /// Resolve the paymaster version associated to the [`user`] account. This function relies on a
@@ -97,6 +121,21 @@ impl Client {
         Ok(version)
     }
 
+    /// Returns true if the [`class_hash`] is the class of a Cartridge Controller account. This function relies on
+    /// a cache so subsequent calls for the same class_hash are resolved without any external calls.
+    pub async fn is_controller_class(&self, class_hash: Felt) -> Result<bool, Error> {
+        if let Some(value) = self.cache_controller_class.get(&class_hash) {
+            return Ok(value);
+        }
+
+        let class = self.inner.fetch_class(class_hash).await?;
+        let is_controller = controller::is_controller_class(&class);
+
+        self.cache_controller_class.insert(class_hash, is_controller);
+
+        Ok(is_controller)
+    }
+
     /// Resolve the gas overhead associated to the [`user`] account. This function relies on a cache so subsequent
     /// call for the same user are resolved without any external calls
     pub async fn resolve_gas_overhead(&self, user: Felt) -> Result<ValidationGasOverhead, Error> {
@@ -110,25 +149,49 @@ impl Client {
         Ok(overhead)
     }
 
-    /// Fetch the current block gas price. This function relies on a cache that expires every 10s so
-    /// during that time frame calling it won't induce external calls
+    /// Fetch the current block gas price. This function relies on a cache that becomes stale every 10s, or at the
+    /// interval of the low-RPC mode while it is enabled, so during that time frame calling it won't induce external
+    /// calls. Stale values are refreshed in the background
     pub async fn fetch_block_gas_price(&self) -> Result<BlockGasPrice, Error> {
+        let cache = if self.low_rpc.is_enabled() {
+            &self.cache_block_price_low_rpc
+        } else {
+            &self.cache_block_price
+        };
+
         let client = self.inner.clone();
-        let price = self
-            .cache_block_price
-            .read_or_refresh(|| Box::pin(async move { client.fetch_block_gas_price().await }))
+        let gas_prices = self.gas_prices.clone();
+        let price = cache
+            .read_or_refresh(move || {
+                let client = client.clone();
+                let gas_prices = gas_prices.clone();
+                Box::pin(async move {
+                    let price = client.fetch_block_gas_price().await?;
+                    gas_prices.record(price);
+
+                    Ok::<_, paymaster_starknet::Error>(price)
+                })
+            })
             .await?;
 
         Ok(price)
     }
 
-    /// Fetch the median tip of the latest block. This function relies on a cache that expires every 10s so
-    /// during that time frame calling it won't induce external calls
+    /// Returns true when the gas price of the latest blocks fetched exceeds its recent average by the given factor
+    pub fn is_gas_price_surging(&self, factor: f64) -> bool {
+        self.gas_prices.is_surging(factor)
+    }
+
+    /// Fetch the median tip of the latest block. This function relies on a cache that becomes stale every 10s so
+    /// during that time frame calling it won't induce external calls. Stale values are refreshed in the background
     pub async fn fetch_median_tip(&self) -> Result<u64, Error> {
         let client = self.inner.clone();
         let tip = self
             .cache_median_tip
-            .read_or_refresh(|| Box::pin(async move { client.fetch_block_median_tip().await }))
+            .read_or_refresh(move || {
+                let client = client.clone();
+                Box::pin(async move { client.fetch_block_median_tip().await })
+            })
             .await?;
 
         Ok(tip)
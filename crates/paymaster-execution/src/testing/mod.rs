@@ -81,11 +81,19 @@ impl TestEnvironment {
                     fallbacks: vec![],
                 },
                 supported_tokens: HashSet::from([Token::usdc(starknet.chain_id()).address]),
+                token_metadata: Default::default(),
+                permit_tokens: HashSet::new(),
+                direct_fee_payment: false,
+                profitability: None,
+                quote_ttl: None,
+                low_rpc: Default::default(),
                 max_fee_multiplier: 3.0,
                 provider_fee_overhead: 0.1,
+                fee_rounding: Default::default(),
 
                 estimate_account: StarknetTestEnvironment::ACCOUNT_1,
                 gas_tank: StarknetTestEnvironment::ACCOUNT_1,
+                gas_tank_multisig: None,
 
                 relayers: RelayersConfiguration {
                     private_key: StarknetTestEnvironment::ACCOUNT_2.private_key,
@@ -93,6 +101,18 @@ impl TestEnvironment {
 
                     min_relayer_balance: Felt::ZERO,
                     lock: LockLayerConfiguration::mock_with_timeout::<CoordinationLayer>(Duration::from_secs(5)),
+                    max_in_flight_transactions: RelayersConfiguration::default_max_in_flight_transactions(),
+                    execution_concurrency_factor: RelayersConfiguration::default_execution_concurrency_factor(),
+                    spend_caps: Default::default(),
+                    secondary: None,
+                    gas_tank_top_up: None,
+                    staking: None,
+                    journal: None,
+                    watchdog: None,
+                    dedicated: vec![],
+                    deployment_relayers: vec![],
+                    treasury_history: None,
+                    messaging: None,
                     rebalancing: paymaster_relayer::rebalancing::OptionalRebalancingConfiguration::initialize(None),
                 },
             },
@@ -102,6 +122,6 @@ impl TestEnvironment {
     }
 
     pub fn default_client(&self) -> Client {
-        Client::new(&self.configuration)
+        Client::new(&self.configuration).unwrap()
     }
 }
@@ -0,0 +1,188 @@
+//! Token metadata set by the operator.
+//!
+//! AVNU only lists the tokens of mainnet and sepolia, the tokens of appchains must be described in the
+//! configuration to be rendered properly. The metadata configured takes precedence over the one listed by AVNU.
+
+use std::collections::HashMap;
+
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use crate::tokens::TokenInfo;
+
+/// Metadata of a token. Every field is optional so that an override only replaces the fields it sets.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+
+    /// Display name of the token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+
+    /// Minimum fee charged in the token (in token units)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_fee_amount: Option<Felt>,
+}
+
+impl From<TokenInfo> for TokenMetadata {
+    fn from(value: TokenInfo) -> Self {
+        Self {
+            symbol: Some(value.symbol),
+            name: Some(value.name),
+            decimals: Some(value.decimals),
+            min_fee_amount: None,
+        }
+    }
+}
+
+impl TokenMetadata {
+    /// Returns this metadata with the fields set by `other` replaced
+    pub fn merge(self, other: &TokenMetadata) -> Self {
+        Self {
+            symbol: other.symbol.clone().or(self.symbol),
+            name: other.name.clone().or(self.name),
+            decimals: other.decimals.or(self.decimals),
+            min_fee_amount: other.min_fee_amount.or(self.min_fee_amount),
+        }
+    }
+}
+
+/// Metadata configured by the operator indexed by token address
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TokenMetadataOverrides(HashMap<Felt, TokenMetadata>);
+
+impl TokenMetadataOverrides {
+    pub fn new(tokens: HashMap<Felt, TokenMetadata>) -> Self {
+        Self(tokens)
+    }
+
+    pub fn get(&self, token: &Felt) -> Option<&TokenMetadata> {
+        self.0.get(token)
+    }
+
+    /// Set the fields of the token which are not configured yet, the ones already configured take precedence
+    pub fn fill(&mut self, token: Felt, metadata: TokenMetadata) {
+        let configured = self.0.remove(&token).unwrap_or_default();
+        self.0.insert(token, metadata.merge(&configured));
+    }
+
+    /// Returns the fee raised to the minimum fee configured for the token, if any. Keeps extremely cheap
+    /// transactions from costing more to collect than they pay.
+    pub fn apply_min_fee(&self, token: &Felt, fee_in_token: Felt) -> Felt {
+        match self.0.get(token).and_then(|x| x.min_fee_amount) {
+            Some(min_fee) => fee_in_token.max(min_fee),
+            None => fee_in_token,
+        }
+    }
+
+    /// Returns the metadata of the token listed by AVNU, if any, merged with the one configured
+    pub fn resolve(&self, token: &Felt, listed: Option<TokenInfo>) -> TokenMetadata {
+        let metadata = listed.map(TokenMetadata::from).unwrap_or_default();
+        match self.0.get(token) {
+            Some(overrides) => metadata.merge(overrides),
+            None => metadata,
+        }
+    }
+}
+
+impl Validate for TokenMetadataOverrides {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        for (token, metadata) in &self.0 {
+            report.nested(&token.to_hex_string(), |report| {
+                if let Some(symbol) = &metadata.symbol {
+                    report.ensure(!symbol.is_empty(), "symbol", "must not be empty");
+                }
+                if let Some(name) = &metadata.name {
+                    report.ensure(!name.is_empty(), "name", "must not be empty");
+                }
+                if let Some(decimals) = metadata.decimals {
+                    report.ensure(decimals <= 36, "decimals", "must not exceed 36");
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use paymaster_common::validation::Validate;
+    use starknet::core::types::Felt;
+
+    use crate::tokens::metadata::{TokenMetadata, TokenMetadataOverrides};
+    use crate::tokens::TokenInfo;
+
+    fn listed() -> TokenInfo {
+        TokenInfo {
+            name: "Ether".to_string(),
+            address: "0x1".to_string(),
+            symbol: "ETH".to_string(),
+            decimals: 18,
+            logo_uri: None,
+        }
+    }
+
+    #[test]
+    fn configured_fields_take_precedence_over_listed_ones() {
+        let overrides = TokenMetadataOverrides::new(HashMap::from([(
+            Felt::ONE,
+            TokenMetadata {
+                name: Some("Bridged Ether".to_string()),
+                min_fee_amount: Some(Felt::from(1000)),
+                ..Default::default()
+            },
+        )]));
+
+        let metadata = overrides.resolve(&Felt::ONE, Some(listed()));
+        assert_eq!(metadata.symbol.as_deref(), Some("ETH"));
+        assert_eq!(metadata.name.as_deref(), Some("Bridged Ether"));
+        assert_eq!(metadata.decimals, Some(18));
+        assert_eq!(metadata.min_fee_amount, Some(Felt::from(1000)));
+    }
+
+    #[test]
+    fn unlisted_tokens_only_have_the_configured_fields() {
+        let overrides: TokenMetadataOverrides = serde_json::from_str(r#"{ "0x2": { "symbol": "GAME", "decimals": 6 } }"#).unwrap();
+        assert!(overrides.validate_all().is_ok());
+
+        let metadata = overrides.resolve(&Felt::TWO, None);
+        assert_eq!(metadata.symbol.as_deref(), Some("GAME"));
+        assert_eq!(metadata.decimals, Some(6));
+        assert_eq!(metadata.name, None);
+
+        assert_eq!(overrides.resolve(&Felt::THREE, None), TokenMetadata::default());
+    }
+
+    #[test]
+    fn filled_fields_do_not_replace_configured_ones() {
+        let mut overrides: TokenMetadataOverrides = serde_json::from_str(r#"{ "0x2": { "symbol": "GAME" } }"#).unwrap();
+        overrides.fill(
+            Felt::TWO,
+            TokenMetadata {
+                symbol: Some("OTHER".to_string()),
+                decimals: Some(6),
+                ..Default::default()
+            },
+        );
+
+        let metadata = overrides.resolve(&Felt::TWO, None);
+        assert_eq!(metadata.symbol.as_deref(), Some("GAME"));
+        assert_eq!(metadata.decimals, Some(6));
+    }
+
+    #[test]
+    fn fee_is_raised_to_the_minimum_fee_of_the_token() {
+        let overrides: TokenMetadataOverrides = serde_json::from_str(r#"{ "0x2": { "min_fee_amount": "0x64" } }"#).unwrap();
+
+        assert_eq!(overrides.apply_min_fee(&Felt::TWO, Felt::from(10)), Felt::from(100));
+        assert_eq!(overrides.apply_min_fee(&Felt::TWO, Felt::from(250)), Felt::from(250));
+        assert_eq!(overrides.apply_min_fee(&Felt::THREE, Felt::from(10)), Felt::from(10));
+    }
+}
@@ -1,12 +1,13 @@
 //! Token service for fetching and caching token metadata.
 //!
 //! This module provides a service that fetches token information from the AVNU API
-//! and caches it locally with a 1-hour TTL using SyncValue.
+//! and caches it locally with a 1-hour TTL, refreshing it in the background once stale.
 
 use std::collections::HashMap;
 use std::time::Duration;
 
-use paymaster_common::concurrency::SyncValue;
+use paymaster_common::cache::RefreshAheadValue;
+use paymaster_common::egress;
 use paymaster_starknet::math::denormalize_felt;
 use paymaster_starknet::ChainID;
 use serde::Deserialize;
@@ -14,6 +15,8 @@ use starknet::core::types::Felt;
 use thiserror::Error;
 use tracing::warn;
 
+pub mod metadata;
+
 /// Base URL for the AVNU API on mainnet.
 const AVNU_API_MAINNET_URL: &str = "https://starknet.api.avnu.fi";
 
@@ -56,11 +59,12 @@ type Tokens = HashMap<Felt, TokenInfo>;
 
 /// Token service that caches token metadata.
 ///
-/// Uses SyncValue with a 1-hour TTL for automatic cache refresh.
+/// Uses a RefreshAheadValue with a 1-hour TTL so that lookups never wait for the refresh
+/// once the cache has been populated.
 #[derive(Clone)]
 pub struct TokenClient {
-    /// Cached tokens indexed by address, with automatic TTL-based refresh.
-    cache: SyncValue<Tokens>,
+    /// Cached tokens indexed by address, refreshed ahead of expiration.
+    cache: RefreshAheadValue<Tokens>,
     /// HTTP client
     client: reqwest::Client,
     /// Base URL for the API
@@ -90,22 +94,27 @@ impl TokenClient {
 
     fn with_base_url(base_url: &str) -> Self {
         Self {
-            cache: SyncValue::new(CACHE_TTL),
-            client: reqwest::Client::new(),
+            cache: RefreshAheadValue::new(CACHE_TTL),
+            client: egress::apply(reqwest::Client::builder())
+                .build()
+                .expect("Failed to build HTTP client"),
             base_url: base_url.to_string(),
         }
     }
 
     /// Gets token info by address.
     ///
-    /// Automatically refreshes the cache if it has expired (1-hour TTL).
+    /// Automatically refreshes the cache in the background once it is stale (1-hour TTL).
     /// Returns `None` if the token is not found.
     pub async fn get_token(&self, address: Felt) -> Option<TokenInfo> {
         let cache = self
             .cache
             .read_or_refresh({
                 let this = self.clone();
-                move || Box::pin(async move { this.fetch_all_tokens().await })
+                move || {
+                    let this = this.clone();
+                    Box::pin(async move { this.fetch_all_tokens().await })
+                }
             })
             .await
             .ok()?;
@@ -114,6 +123,7 @@ impl TokenClient {
 
     async fn fetch_token_page(&self, page: u32, page_size: u32) -> Result<PageToken, TokenServiceError> {
         let url = format!("{}/v1/starknet/tokens?page={}&size={}", self.base_url, page, page_size);
+        egress::ensure_allowed(&url).map_err(|e| TokenServiceError::HttpError(e.to_string()))?;
 
         let response = self
             .client
@@ -0,0 +1,178 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use paymaster_common::metric;
+use paymaster_common::service::{Error as ServiceError, Service, TokioServiceManager};
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_relayer::lock::leader::LeaderLock;
+use paymaster_relayer::lock::LockLayerConfiguration;
+use paymaster_starknet::contract::forwarder::Forwarder;
+use paymaster_starknet::StarknetAccountConfiguration;
+use serde::{Deserialize, Serialize};
+use starknet::accounts::ConnectedAccount;
+use starknet::core::types::Felt;
+use tokio::time::interval;
+use tracing::{error, info};
+
+use crate::{Client, Error};
+
+/// Owner of the forwarder keeping the relayers and the estimate account whitelisted, so that the relayers added or
+/// rotated in the configuration can execute transactions as soon as the instance starts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwarderWhitelistConfiguration {
+    /// Owner of the forwarder (i.e. the master account)
+    pub admin: StarknetAccountConfiguration,
+
+    /// Interval between two checks of the whitelist (in seconds)
+    #[serde(default = "ForwarderWhitelistConfiguration::default_check_interval")]
+    pub check_interval: u64,
+}
+
+impl ForwarderWhitelistConfiguration {
+    fn default_check_interval() -> u64 {
+        300
+    }
+}
+
+impl Validate for ForwarderWhitelistConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.field("admin", &self.admin);
+        report.ensure(self.check_interval > 0, "check_interval", "must be greater than 0");
+    }
+}
+
+/// Whitelists on the forwarder the accounts which are not whitelisted yet, using its owner
+#[derive(Clone)]
+pub struct ForwarderWhitelist {
+    client: Client,
+    forwarder: Forwarder,
+    admin: StarknetAccountConfiguration,
+}
+
+impl ForwarderWhitelist {
+    pub fn new(client: &Client, forwarder: Felt, admin: &StarknetAccountConfiguration) -> Self {
+        Self {
+            client: client.clone(),
+            forwarder: Forwarder::new(forwarder),
+            admin: *admin,
+        }
+    }
+
+    /// Whitelist the given accounts which are not whitelisted yet, returns the accounts whitelisted
+    pub async fn sync(&self, accounts: &[Felt]) -> Result<Vec<Felt>, ServiceError> {
+        let missing = self
+            .forwarder
+            .not_whitelisted(&self.client.starknet, accounts)
+            .await
+            .map_err(ServiceError::from)?;
+        if missing.is_empty() {
+            return Ok(missing);
+        }
+
+        let admin = self.client.starknet.initialize_account(&self.admin);
+        let nonce = admin.get_nonce().await.map_err(ServiceError::from)?;
+        let result = self
+            .forwarder
+            .update_whitelist(&missing, &[])
+            .execute(&admin, nonce)
+            .await
+            .map_err(ServiceError::from)?;
+        let succeeded = self
+            .client
+            .starknet
+            .wait_for_acceptance(result.transaction_hash, Duration::from_secs(2), Duration::from_secs(120))
+            .await
+            .map_err(ServiceError::from)?;
+        if !succeeded {
+            return Err(ServiceError::new("whitelist update reverted"));
+        }
+
+        metric!(counter[forwarder_whitelisted_accounts] = missing.len() as u64);
+        info!(
+            "Whitelisted {} accounts on the forwarder in transaction {}",
+            missing.len(),
+            result.transaction_hash.to_fixed_hex_string()
+        );
+
+        Ok(missing)
+    }
+}
+
+#[derive(Clone)]
+pub struct ForwarderWhitelistContext {
+    pub whitelist: ForwarderWhitelist,
+    pub accounts: Vec<Felt>,
+    pub leader: LeaderLock,
+
+    pub configuration: ForwarderWhitelistConfiguration,
+}
+
+/// Keeps the accounts of the configuration whitelisted on the forwarder, checked at startup and then periodically.
+/// Runs on a single instance at a time.
+pub struct ForwarderWhitelistService {
+    context: ForwarderWhitelistContext,
+}
+
+#[async_trait]
+impl Service for ForwarderWhitelistService {
+    type Context = ForwarderWhitelistContext;
+
+    const NAME: &'static str = "ForwarderWhitelistService";
+
+    async fn new(context: ForwarderWhitelistContext) -> Self {
+        Self { context }
+    }
+
+    async fn run(self) -> Result<(), ServiceError> {
+        let mut ticker = interval(Duration::from_secs(self.context.configuration.check_interval));
+        loop {
+            ticker.tick().await;
+
+            if !self.context.leader.acquire().await {
+                continue;
+            }
+
+            if let Err(e) = self.context.whitelist.sync(&self.context.accounts).await {
+                error!("Failed to whitelist the accounts on the forwarder, retrying next round: {}", e);
+            }
+        }
+    }
+}
+
+/// Keeps the accounts whitelisted on the forwarder when a configuration is given
+#[derive(Clone)]
+pub struct ForwarderWhitelistManager {
+    #[allow(dead_code)]
+    services: Option<Arc<TokioServiceManager<ForwarderWhitelistContext>>>,
+}
+
+impl ForwarderWhitelistManager {
+    pub fn new(
+        client: &Client,
+        forwarder: Felt,
+        accounts: Vec<Felt>,
+        lock: &LockLayerConfiguration,
+        configuration: Option<&ForwarderWhitelistConfiguration>,
+    ) -> Result<Self, Error> {
+        let Some(configuration) = configuration else {
+            return Ok(Self { services: None });
+        };
+
+        // The lease outlives the interval so that the leader keeps it between two checks
+        let lease = Duration::from_secs(configuration.check_interval * 2);
+        let leader = LeaderLock::new(lock, &format!("forwarder-whitelist:{}", forwarder.to_fixed_hex_string()), lease).map_err(paymaster_relayer::Error::from)?;
+
+        let mut services = TokioServiceManager::new(ForwarderWhitelistContext {
+            whitelist: ForwarderWhitelist::new(client, forwarder, &configuration.admin),
+            accounts,
+            leader,
+            configuration: configuration.clone(),
+        });
+        services.spawn::<ForwarderWhitelistService>();
+
+        Ok(Self {
+            services: Some(Arc::new(services)),
+        })
+    }
+}
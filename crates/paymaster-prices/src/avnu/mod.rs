@@ -1,7 +1,8 @@
 use std::time::Duration;
 
-use paymaster_common::cache::ExpirableCache;
-use paymaster_starknet::constants::Token;
+use paymaster_common::cache::RefreshAheadCache;
+use paymaster_common::egress;
+use paymaster_common::validation::{Validate, ValidationReport};
 use paymaster_starknet::math::normalize_felt;
 use paymaster_starknet::Configuration as StarknetConfiguration;
 use reqwest::header::{HeaderMap, HeaderValue};
@@ -31,6 +32,13 @@ pub struct AVNUPriceClientConfiguration {
     pub starknet: StarknetConfiguration,
 }
 
+impl Validate for AVNUPriceClientConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure_url("endpoint", &self.endpoint);
+        report.field("starknet", &self.starknet);
+    }
+}
+
 impl From<AVNUPriceClientConfiguration> for PriceOracleConfiguration {
     fn from(value: AVNUPriceClientConfiguration) -> Self {
         Self::AVNU(value)
@@ -41,7 +49,10 @@ impl From<AVNUPriceClientConfiguration> for PriceOracleConfiguration {
 pub struct AVNUPriceOracle {
     endpoint: String,
     client: HTTPClient,
-    cache: ExpirableCache<Felt, Price>,
+    cache: RefreshAheadCache<Felt, Price>,
+
+    /// Address of the STRK token of the chain, in which the prices are expressed
+    strk: Felt,
 
     resolver: DecimalsResolver,
 }
@@ -59,19 +70,18 @@ impl AVNUPriceOracle {
 
         Self {
             endpoint: configuration.endpoint.clone(),
-            client: HTTPClient::builder()
-                .default_headers(headers)
-                .timeout(Duration::from_secs(3))
+            client: egress::apply(HTTPClient::builder().default_headers(headers).timeout(Duration::from_secs(3)))
                 .build()
                 .expect("invalid client"),
 
+            strk: configuration.starknet.tokens().strk,
             resolver: DecimalsResolver::new(&configuration.starknet),
-            cache: ExpirableCache::new(128),
+            cache: RefreshAheadCache::new(128, Duration::from_secs(3)),
         }
     }
 
     pub async fn fetch_token(&self, address: &Felt) -> Result<TokenPrice, Error> {
-        let strk_price = self.fetch_token_by_address(&Token::STRK_ADDRESS).await?;
+        let strk_price = self.fetch_token_by_address(&self.strk).await?;
         if !strk_price.price_in_usd.is_normal() {
             return Err(Error::InvalidPrice(*address));
         }
@@ -87,21 +97,22 @@ impl AVNUPriceOracle {
     }
 
     async fn fetch_token_by_address(&self, address: &Felt) -> Result<Price, Error> {
-        if let Some(price) = self.fetch_token_from_cache(address) {
-            return Ok(price);
-        }
-
-        self.fetch_token_from_avnu(address).await
-    }
-
-    fn fetch_token_from_cache(&self, address: &Felt) -> Option<Price> {
-        self.cache.get_if_not_expired(address)
+        let this = self.clone();
+        let address = *address;
+
+        self.cache
+            .get_or_refresh(address, move || {
+                let this = this.clone();
+                Box::pin(async move { this.fetch_token_from_avnu(&address).await })
+            })
+            .await
     }
 
     async fn fetch_token_from_avnu(&self, address: &Felt) -> Result<Price, Error> {
         let url = Url::parse(&self.endpoint)
             .and_then(|x| x.join("/v1/tokens/prices"))
             .map_err(|e| Error::URL(e.to_string()))?;
+        egress::ensure_allowed(url.as_str())?;
 
         // Fetch
         let response = self
@@ -124,7 +135,6 @@ impl AVNUPriceOracle {
             .cloned()
             .ok_or(Error::InvalidPrice(*address))?;
 
-        self.cache.insert(*address, price, Duration::from_secs(3));
         Ok(price)
     }
 }
@@ -149,6 +159,9 @@ mod tests {
                 chain_id: ChainID::Sepolia,
                 timeout: 10,
                 fallbacks: vec![],
+                local_estimation: None,
+                endpoint_options: Default::default(),
+                tokens: None,
             },
         });
 
@@ -4,8 +4,9 @@ use std::time::Duration;
 
 use crate::decimals::DecimalsResolver;
 use crate::{Error, PriceClient, PriceOracleConfiguration, TokenPrice};
-use paymaster_common::cache::ExpirableCache;
-use paymaster_starknet::constants::Token;
+use paymaster_common::cache::RefreshAheadCache;
+use paymaster_common::egress;
+use paymaster_common::validation::{Validate, ValidationReport};
 use paymaster_starknet::math::normalize_felt;
 use paymaster_starknet::Configuration as StarknetConfiguration;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
@@ -39,6 +40,14 @@ pub struct CoingeckoPriceClientConfiguration {
     pub starknet: StarknetConfiguration,
 }
 
+impl Validate for CoingeckoPriceClientConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure_url("endpoint", &self.endpoint);
+        report.ensure(!self.address_to_id.is_empty(), "address_to_id", "at least one token must be mapped to a coingecko id");
+        report.field("starknet", &self.starknet);
+    }
+}
+
 impl From<CoingeckoPriceClientConfiguration> for PriceOracleConfiguration {
     fn from(value: CoingeckoPriceClientConfiguration) -> Self {
         Self::Coingecko(value)
@@ -52,8 +61,11 @@ pub struct CoingeckoPriceClient {
 
     address_to_id: HashMap<Felt, String>,
 
+    /// Address of the STRK token of the chain, in which the prices are expressed
+    strk: Felt,
+
     resolver: DecimalsResolver,
-    cache: ExpirableCache<Felt, Price>,
+    cache: RefreshAheadCache<Felt, Price>,
 }
 
 impl From<CoingeckoPriceClient> for PriceClient {
@@ -92,22 +104,26 @@ impl CoingeckoPriceClient {
             headers.insert(HeaderName::from_str("x-cg-pro-api-key").unwrap(), HeaderValue::from_str(api_key).unwrap());
         }
 
+        let strk = configuration.starknet.tokens().strk;
         let mut address_to_id = configuration.address_to_id.clone();
-        address_to_id.insert(Token::STRK_ADDRESS, "starknet".to_string());
+        address_to_id.insert(strk, "starknet".to_string());
 
         Self {
             endpoint: configuration.endpoint.to_string(),
-            client: ClientBuilder::new().default_headers(headers).build().expect("invalid client"),
+            client: egress::apply(ClientBuilder::new().default_headers(headers))
+                .build()
+                .expect("invalid client"),
 
             address_to_id,
+            strk,
 
             resolver: DecimalsResolver::new(&configuration.starknet),
-            cache: ExpirableCache::new(128),
+            cache: RefreshAheadCache::new(128, Duration::from_secs(3)),
         }
     }
 
     pub async fn fetch_token(&self, token: &Felt) -> Result<TokenPrice, Error> {
-        let strk_price = self.fetch_token_by_address(&Token::STRK_ADDRESS).await?;
+        let strk_price = self.fetch_token_by_address(&self.strk).await?;
         if !strk_price.usd.is_normal() {
             return Err(Error::InvalidPrice(*token));
         }
@@ -123,15 +139,15 @@ impl CoingeckoPriceClient {
     }
 
     async fn fetch_token_by_address(&self, token: &Felt) -> Result<Price, Error> {
-        if let Some(price) = self.fetch_token_from_cache(token) {
-            return Ok(price);
-        }
-
-        self.fetch_token_from_coingecko(token).await
-    }
-
-    fn fetch_token_from_cache(&self, token: &Felt) -> Option<Price> {
-        self.cache.get_if_not_expired(token)
+        let this = self.clone();
+        let token = *token;
+
+        self.cache
+            .get_or_refresh(token, move || {
+                let this = this.clone();
+                Box::pin(async move { this.fetch_token_from_coingecko(&token).await })
+            })
+            .await
     }
 
     async fn fetch_token_from_coingecko(&self, token: &Felt) -> Result<Price, Error> {
@@ -148,6 +164,7 @@ impl CoingeckoPriceClient {
             .append_pair("ids", token_id)
             .append_pair("vs_currencies", "usd");
 
+        egress::ensure_allowed(url.as_str())?;
         let response: CoingeckoResponse<PriceResponse> = self.client.get(url).send().await?.json().await?;
 
         let prices = match response {
@@ -157,7 +174,6 @@ impl CoingeckoPriceClient {
 
         let price = prices.0.get(token_id).cloned().ok_or(Error::InvalidPrice(*token))?;
 
-        self.cache.insert(*token, price, Duration::from_secs(3));
         Ok(price)
     }
 }
@@ -186,6 +202,9 @@ mod tests {
                 chain_id: ChainID::Mainnet,
                 timeout: 10,
                 fallbacks: vec![],
+                local_estimation: None,
+                endpoint_options: Default::default(),
+                tokens: None,
             },
         });
 
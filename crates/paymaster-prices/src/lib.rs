@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use std::time::Duration;
 
 use crate::avnu::{AVNUPriceClientConfiguration, AVNUPriceOracle};
 use paymaster_common::concurrency::ConcurrentExecutor;
@@ -10,6 +11,7 @@ pub mod avnu;
 pub mod coingecko;
 
 pub mod math;
+pub mod volatility;
 
 mod decimals;
 #[cfg(feature = "testing")]
@@ -17,10 +19,13 @@ pub mod mock;
 
 use paymaster_common::service::fallback::{FailurePredicate, WithFallback};
 use paymaster_common::service::tracing::instrument;
+use paymaster_common::validation::{Validate, ValidationReport};
 use paymaster_common::{log_if_error, measure_duration, metric, task};
+use paymaster_starknet::ChainID;
 
 use crate::coingecko::{CoingeckoPriceClient, CoingeckoPriceClientConfiguration};
 use crate::math::{convert_strk_to_token, convert_token_to_strk};
+use crate::volatility::VolatilityTracker;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -30,6 +35,9 @@ pub enum Error {
     #[error(transparent)]
     HTTP(#[from] reqwest::Error),
 
+    #[error(transparent)]
+    Egress(#[from] paymaster_common::egress::Error),
+
     #[error("wrong format error {0}")]
     Format(String),
 
@@ -66,6 +74,36 @@ impl PriceConfiguration {
     }
 }
 
+impl PriceConfiguration {
+    /// Returns the chain id of the principal oracle, if it is bound to one
+    pub fn chain_id(&self) -> Option<ChainID> {
+        self.principal.chain_id()
+    }
+}
+
+impl Validate for PriceConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.field("principal", &self.principal);
+
+        for (i, fallback) in self.fallbacks.iter().enumerate() {
+            let field = format!("fallbacks[{}]", i);
+            report.field(&field, fallback);
+
+            if let (Some(principal), Some(fallback)) = (self.chain_id(), fallback.chain_id()) {
+                report.ensure(
+                    principal.as_felt() == fallback.as_felt(),
+                    &format!("{}.starknet.chain_id", field),
+                    format!(
+                        "chain id {} does not match principal chain id {}",
+                        fallback.as_identifier(),
+                        principal.as_identifier()
+                    ),
+                );
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum PriceOracleConfiguration {
     #[cfg(feature = "testing")]
@@ -75,6 +113,31 @@ pub enum PriceOracleConfiguration {
     Coingecko(CoingeckoPriceClientConfiguration),
 }
 
+impl PriceOracleConfiguration {
+    /// Returns the chain id used by the oracle to resolve token decimals, if any
+    pub fn chain_id(&self) -> Option<ChainID> {
+        match self {
+            #[cfg(feature = "testing")]
+            Self::Mock(_) => None,
+
+            Self::AVNU(x) => Some(x.starknet.chain_id),
+            Self::Coingecko(x) => Some(x.starknet.chain_id),
+        }
+    }
+}
+
+impl Validate for PriceOracleConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        match self {
+            #[cfg(feature = "testing")]
+            Self::Mock(_) => {},
+
+            Self::AVNU(x) => x.validate_into(report),
+            Self::Coingecko(x) => x.validate_into(report),
+        }
+    }
+}
+
 #[cfg(feature = "testing")]
 impl PriceOracleConfiguration {
     pub fn mock<T: mock::MockPriceOracle>() -> Self {
@@ -85,6 +148,7 @@ impl PriceOracleConfiguration {
 #[derive(Clone)]
 pub struct Client {
     client: WithFallback<PriceClient>,
+    volatility: VolatilityTracker,
 }
 
 impl Client {
@@ -95,13 +159,17 @@ impl Client {
             client = client.with(PriceClient::new(fallback));
         }
 
-        Self { client }
+        Self {
+            client,
+            volatility: VolatilityTracker::default(),
+        }
     }
 
     #[cfg(feature = "testing")]
     pub fn mock<I: 'static + mock::MockPriceOracle>() -> Self {
         Self {
             client: WithFallback::new().with(PriceClient::mock::<I>()),
+            volatility: VolatilityTracker::default(),
         }
     }
 
@@ -118,7 +186,8 @@ impl Client {
     }
 
     pub async fn fetch_tokens(&self, tokens: &HashSet<Felt>) -> Vec<Result<TokenPrice, Error>> {
-        let mut executor = ConcurrentExecutor::new(self.clone(), 8);
+        // Bound each fetch so that a single stuck oracle request does not hang the whole batch
+        let mut executor = ConcurrentExecutor::new(self.clone(), 8).with_timeout(Duration::from_secs(10));
         for token in tokens.iter().cloned() {
             executor.register(task!(|context| { context.fetch_token(token).await }));
         }
@@ -132,10 +201,19 @@ impl Client {
     }
 
     pub async fn fetch_token(&self, token: Felt) -> Result<TokenPrice, Error> {
-        self.client
+        let price = self
+            .client
             .call_all(|x| async move { x.fetch_token(token).await })
             .await
-            .map_err(|_| Error::Internal("could not fetch price".to_string()))
+            .map_err(|_| Error::Internal("could not fetch price".to_string()))?;
+
+        self.volatility.record(token, price.price_in_strk);
+        Ok(price)
+    }
+
+    /// Returns the volatility of the given token measured on the prices fetched recently, if enough were fetched
+    pub fn volatility(&self, token: Felt) -> Option<f64> {
+        self.volatility.volatility(token)
     }
 }
 
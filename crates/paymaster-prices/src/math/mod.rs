@@ -1,9 +1,12 @@
-use bigdecimal::num_bigint::{BigInt, ToBigInt};
+use bigdecimal::num_bigint::ToBigInt;
 use bigdecimal::{BigDecimal, Zero};
 use starknet::core::types::Felt;
 
 use crate::{Error, TokenPrice};
 
+mod rounding;
+pub use rounding::{RoundingMode, RoundingPolicy};
+
 pub fn convert_token_to_strk(token: &TokenPrice, amount: Felt) -> Result<Felt, Error> {
     let amount_scaled = BigDecimal::new(amount.to_bigint(), token.decimals);
     let price_scaled = BigDecimal::from_bigint(token.price_in_strk.to_bigint(), 0);
@@ -15,6 +18,13 @@ pub fn convert_token_to_strk(token: &TokenPrice, amount: Felt) -> Result<Felt, E
 }
 
 pub fn convert_strk_to_token(token: &TokenPrice, amount: Felt, round_up: bool) -> Result<Felt, Error> {
+    let rounding = if round_up { RoundingPolicy::up() } else { RoundingPolicy::down() };
+
+    convert_strk_to_token_rounded(token, amount, &rounding)
+}
+
+/// Convert an amount of STRK to the token, rounded to the base unit of the token following the given policy
+pub fn convert_strk_to_token_rounded(token: &TokenPrice, amount: Felt, rounding: &RoundingPolicy) -> Result<Felt, Error> {
     if token.price_in_strk.is_zero() {
         return Err(Error::InvalidPrice(token.price_in_strk));
     }
@@ -25,18 +35,7 @@ pub fn convert_strk_to_token(token: &TokenPrice, amount: Felt, round_up: bool) -
 
     let amount_in_token = amount_in_token_scaled * BigDecimal::from(10_u128.pow(token.decimals as u32));
 
-    if round_up {
-        let (rounded_int, _remainder) = amount_in_token.clone().with_scale(0).into_bigint_and_exponent();
-        let remainder_check = &amount_in_token - BigDecimal::from(rounded_int.clone());
-        let rounded = if remainder_check > BigDecimal::zero() {
-            rounded_int + BigInt::from(1)
-        } else {
-            rounded_int
-        };
-        Ok(Felt::from(rounded))
-    } else {
-        Ok(Felt::from(amount_in_token.to_bigint().unwrap()))
-    }
+    Ok(Felt::from(rounding.round(&amount_in_token)))
 }
 
 #[cfg(test)]
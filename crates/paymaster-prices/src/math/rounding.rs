@@ -0,0 +1,127 @@
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::{BigDecimal, RoundingMode as DecimalRoundingMode};
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+
+/// How an amount converted from STRK is rounded to the base unit of the token
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// Never charge less than the exact amount
+    #[default]
+    Up,
+
+    /// Never charge more than the exact amount
+    Down,
+
+    /// Round to the nearest unit, halfway amounts are rounded up
+    Nearest,
+
+    /// Round to the nearest unit, halfway amounts are rounded to the even unit
+    Bankers,
+}
+
+impl From<RoundingMode> for DecimalRoundingMode {
+    fn from(value: RoundingMode) -> Self {
+        match value {
+            RoundingMode::Up => DecimalRoundingMode::Ceiling,
+            RoundingMode::Down => DecimalRoundingMode::Floor,
+            RoundingMode::Nearest => DecimalRoundingMode::HalfUp,
+            RoundingMode::Bankers => DecimalRoundingMode::HalfEven,
+        }
+    }
+}
+
+/// Rounding applied when converting a fee from STRK to a token
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundingPolicy {
+    #[serde(default)]
+    pub mode: RoundingMode,
+
+    /// Maximum surplus the rounding may add to the exact amount (in basis points of the exact amount). The amount
+    /// is rounded down when rounding it would exceed the surplus, which matters for the tokens with few decimals.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_surplus_bps: Option<u64>,
+}
+
+impl RoundingPolicy {
+    pub fn up() -> Self {
+        Self {
+            mode: RoundingMode::Up,
+            max_surplus_bps: None,
+        }
+    }
+
+    pub fn down() -> Self {
+        Self {
+            mode: RoundingMode::Down,
+            max_surplus_bps: None,
+        }
+    }
+
+    /// Round the given amount to an integer following the policy
+    pub fn round(&self, amount: &BigDecimal) -> BigInt {
+        let rounded = amount.with_scale_round(0, self.mode.into());
+
+        let exceeds_surplus = match self.max_surplus_bps {
+            Some(bps) => (&rounded - amount) * BigDecimal::from(10_000u64) > amount * BigDecimal::from(bps),
+            None => false,
+        };
+
+        let rounded = if exceeds_surplus {
+            amount.with_scale_round(0, DecimalRoundingMode::Floor)
+        } else {
+            rounded
+        };
+
+        let (value, _) = rounded.into_bigint_and_exponent();
+        value
+    }
+}
+
+impl Validate for RoundingPolicy {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        if let Some(bps) = self.max_surplus_bps {
+            report.ensure(bps <= 10_000, "max_surplus_bps", "must not exceed 10000");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::num_bigint::BigInt;
+    use bigdecimal::BigDecimal;
+
+    use crate::math::{RoundingMode, RoundingPolicy};
+
+    fn round(mode: RoundingMode, amount: &str) -> BigInt {
+        RoundingPolicy { mode, max_surplus_bps: None }.round(&BigDecimal::from_str(amount).unwrap())
+    }
+
+    #[test]
+    fn amounts_are_rounded_according_to_the_mode() {
+        assert_eq!(round(RoundingMode::Up, "2.1"), BigInt::from(3));
+        assert_eq!(round(RoundingMode::Down, "2.9"), BigInt::from(2));
+        assert_eq!(round(RoundingMode::Nearest, "2.5"), BigInt::from(3));
+        assert_eq!(round(RoundingMode::Nearest, "2.4"), BigInt::from(2));
+        assert_eq!(round(RoundingMode::Bankers, "2.5"), BigInt::from(2));
+        assert_eq!(round(RoundingMode::Bankers, "3.5"), BigInt::from(4));
+        assert_eq!(round(RoundingMode::Up, "4"), BigInt::from(4));
+    }
+
+    #[test]
+    fn amount_is_rounded_down_when_the_surplus_exceeds_the_maximum() {
+        let policy = RoundingPolicy {
+            mode: RoundingMode::Up,
+            max_surplus_bps: Some(1_000),
+        };
+
+        // Rounding 2.1 up adds 0.9, more than 10% of the amount
+        assert_eq!(policy.round(&BigDecimal::from_str("2.1").unwrap()), BigInt::from(2));
+
+        // Rounding 100.5 up adds 0.5, less than 10% of the amount
+        assert_eq!(policy.round(&BigDecimal::from_str("100.5").unwrap()), BigInt::from(101));
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use starknet::core::types::Felt;
+
+/// Window over which the volatility of a token is measured
+pub const VOLATILITY_WINDOW: Duration = Duration::from_secs(600);
+
+/// Keeps the prices fetched recently for each token to measure how much they move
+#[derive(Clone)]
+pub struct VolatilityTracker {
+    window: Duration,
+    samples: Arc<RwLock<HashMap<Felt, VecDeque<(Instant, f64)>>>>,
+}
+
+impl Default for VolatilityTracker {
+    fn default() -> Self {
+        Self::new(VOLATILITY_WINDOW)
+    }
+}
+
+impl VolatilityTracker {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            samples: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Record the price of the given token
+    pub fn record(&self, token: Felt, price_in_strk: Felt) {
+        let Ok(price) = u128::try_from(price_in_strk) else { return };
+
+        let now = Instant::now();
+        let mut samples = self.samples.write().unwrap_or_else(|e| e.into_inner());
+        let samples = samples.entry(token).or_default();
+
+        samples.push_back((now, price as f64));
+        while samples.front().is_some_and(|(at, _)| now.duration_since(*at) > self.window) {
+            samples.pop_front();
+        }
+    }
+
+    /// Returns the relative range of the prices of the given token over the window, (max - min) / max.
+    /// Returns `None` when not enough prices were recorded to measure it.
+    pub fn volatility(&self, token: Felt) -> Option<f64> {
+        let samples = self.samples.read().unwrap_or_else(|e| e.into_inner());
+        let samples = samples.get(&token).filter(|x| x.len() >= 2)?;
+
+        let max = samples.iter().map(|(_, x)| *x).fold(f64::MIN, f64::max);
+        let min = samples.iter().map(|(_, x)| *x).fold(f64::MAX, f64::min);
+        if max <= 0.0 {
+            return None;
+        }
+
+        Some((max - min) / max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use starknet::core::types::Felt;
+
+    use crate::volatility::VolatilityTracker;
+
+    #[test]
+    fn volatility_is_the_relative_price_range() {
+        let tracker = VolatilityTracker::default();
+
+        tracker.record(Felt::ONE, Felt::from(100));
+        assert_eq!(tracker.volatility(Felt::ONE), None);
+
+        tracker.record(Felt::ONE, Felt::from(90));
+        tracker.record(Felt::ONE, Felt::from(95));
+        assert_eq!(tracker.volatility(Felt::ONE), Some(0.1));
+
+        assert_eq!(tracker.volatility(Felt::TWO), None);
+    }
+
+    #[test]
+    fn prices_outside_of_the_window_are_dropped() {
+        let tracker = VolatilityTracker::new(Duration::ZERO);
+
+        tracker.record(Felt::ONE, Felt::from(100));
+        std::thread::sleep(Duration::from_millis(1));
+        tracker.record(Felt::ONE, Felt::from(50));
+
+        assert_eq!(tracker.volatility(Felt::ONE), None);
+    }
+}
@@ -0,0 +1,152 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use starknet::core::types::Felt;
+
+use crate::{Context, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountRole {
+    GasTank,
+    Relayer,
+}
+
+/// Balance of a token held by an account of the paymaster
+#[derive(Debug, Clone, Copy)]
+pub struct AccountBalance {
+    pub address: Felt,
+    pub role: AccountRole,
+    pub token: Felt,
+    pub balance: Felt,
+}
+
+/// Balances of the accounts of the paymaster, all read at the same block so that the funds moving between the
+/// gas tank and the relayers are neither missed nor counted twice
+#[derive(Debug, Clone)]
+pub struct AccountingSnapshot {
+    pub block_number: u64,
+
+    /// Unix timestamp in seconds
+    pub taken_at: u64,
+
+    pub balances: Vec<AccountBalance>,
+
+    /// STRK token of the chain, in which the relayers pay the fees
+    pub fee_token: Felt,
+
+    /// Estimated fee (in FRI) of the relayer transactions submitted by this instance but not included at the block
+    pub in_flight_cost: Felt,
+}
+
+impl AccountingSnapshot {
+    pub fn new(block_number: u64, fee_token: Felt) -> Self {
+        Self {
+            block_number,
+            taken_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            balances: vec![],
+            fee_token,
+            in_flight_cost: Felt::ZERO,
+        }
+    }
+
+    /// Record the balance of the gas tank in STRK and in each supported token
+    pub async fn record_gas_tank(&mut self, context: &Context) -> Result<(), Error> {
+        let address = context.configuration.gas_tank.address;
+
+        let mut tokens: Vec<Felt> = context
+            .configuration
+            .supported_tokens
+            .iter()
+            .copied()
+            .filter(|x| *x != self.fee_token)
+            .collect();
+        tokens.sort();
+        tokens.insert(0, self.fee_token);
+
+        for token in tokens {
+            let balance = context
+                .starknet
+                .fetch_balance_at(token, address, self.block_number)
+                .await
+                .map_err(|e| Error::Execution(e.to_string()))?;
+
+            self.balances.push(AccountBalance {
+                address,
+                role: AccountRole::GasTank,
+                token,
+                balance,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Record the STRK balance of the relayers of the fleet along with the cost of their transactions in flight
+    pub async fn record_relayers(&mut self, context: &Context) -> Result<(), Error> {
+        for address in &context.configuration.relayers.addresses {
+            let balance = context
+                .starknet
+                .fetch_balance_at(self.fee_token, *address, self.block_number)
+                .await
+                .map_err(|e| Error::Execution(e.to_string()))?;
+            let nonce = context
+                .starknet
+                .fetch_nonce_at(*address, self.block_number)
+                .await
+                .map_err(|e| Error::Execution(e.to_string()))?;
+
+            self.balances.push(AccountBalance {
+                address: *address,
+                role: AccountRole::Relayer,
+                token: self.fee_token,
+                balance,
+            });
+            self.in_flight_cost += context.relayers.in_flight_cost(*address, nonce);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the total balance of the given token over all the accounts
+    pub fn total(&self, token: Felt) -> Felt {
+        self.balances
+            .iter()
+            .filter(|x| x.token == token)
+            .fold(Felt::ZERO, |total, x| total + x.balance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use paymaster_starknet::constants::Token;
+    use starknet::core::types::Felt;
+
+    use crate::accounting::{AccountBalance, AccountRole, AccountingSnapshot};
+
+    #[test]
+    fn total_only_includes_the_given_token() {
+        let mut snapshot = AccountingSnapshot::new(10, Token::STRK_ADDRESS);
+        snapshot.balances = vec![
+            AccountBalance {
+                address: Felt::ONE,
+                role: AccountRole::GasTank,
+                token: Token::STRK_ADDRESS,
+                balance: Felt::from(100),
+            },
+            AccountBalance {
+                address: Felt::ONE,
+                role: AccountRole::GasTank,
+                token: Token::ETH_ADDRESS,
+                balance: Felt::from(7),
+            },
+            AccountBalance {
+                address: Felt::TWO,
+                role: AccountRole::Relayer,
+                token: strk,
+                balance: Felt::from(50),
+            },
+        ];
+
+        assert_eq!(snapshot.total(Token::STRK_ADDRESS), Felt::from(150));
+        assert_eq!(snapshot.total(Token::ETH_ADDRESS), Felt::from(7));
+    }
+}
@@ -1,11 +1,21 @@
-use paymaster_common::service::Error as ServiceError;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use paymaster_common::service::messaging::redis::RedisMessagingConfiguration;
+use paymaster_common::validation::{Validate, ValidationReport};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use starknet::core::serde::unsigned_field_element::UfeHex;
 use starknet::core::types::Felt;
 
+use crate::journal::JournalConfiguration;
 use crate::lock::LockLayerConfiguration;
 use crate::rebalancing::OptionalRebalancingConfiguration;
+use crate::selection::RelayerSelection;
+use crate::spend::SpendCapsConfiguration;
+use crate::staking::StakingConfiguration;
+use crate::topup::GasTankTopUpConfiguration;
+use crate::watchdog::WatchdogConfiguration;
 
 #[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,17 +33,306 @@ pub struct RelayersConfiguration {
 
     #[serde(default)]
     pub rebalancing: OptionalRebalancingConfiguration,
+
+    /// Maximum number of transactions a relayer can have submitted but not yet included on chain. Only applies to
+    /// the relayers locked per instance, the transactions of relayers shared through Redis are not pipelined.
+    #[serde(default = "RelayersConfiguration::default_max_in_flight_transactions")]
+    pub max_in_flight_transactions: usize,
+
+    /// Number of simultaneous executions allowed per relayer, executions beyond the limit are rejected
+    #[serde(default = "RelayersConfiguration::default_execution_concurrency_factor")]
+    pub execution_concurrency_factor: usize,
+
+    /// Caps on the STRK spent by the relayers over time
+    #[serde(default)]
+    pub spend_caps: SpendCapsConfiguration,
+
+    /// Fleet used when this one has no enabled relayer or keeps failing to provide one. It has its own
+    /// keys and can rely on a different lock backend.
+    #[serde(default)]
+    pub secondary: Option<Box<RelayersConfiguration>>,
+
+    /// Automatic top-up of the gas tank from a treasury account, disabled when not set
+    #[serde(default)]
+    pub gas_tank_top_up: Option<GasTankTopUpConfiguration>,
+
+    /// Delegation of the idle STRK of the gas tank to a staking pool, disabled when not set
+    #[serde(default)]
+    pub staking: Option<StakingConfiguration>,
+
+    /// Journal of the executions in progress, reconciled on startup after a crash. Disabled when not set
+    #[serde(default)]
+    pub journal: Option<JournalConfiguration>,
+
+    /// Cancellation of the transactions which stay unconfirmed for too long. Disabled when not set
+    #[serde(default)]
+    pub watchdog: Option<WatchdogConfiguration>,
+
+    /// Relayers reserved to some sponsors. The other sponsors and the unsponsored transactions are executed by the
+    /// remaining relayers, so a sponsor cannot exhaust the relayers of the other tenants.
+    #[serde(default)]
+    pub dedicated: Vec<DedicatedRelayersConfiguration>,
+
+    /// Relayers reserved to the transactions deploying an account, which may also use the other relayers. Keeps
+    /// the onboarding of users fast when the fleet is saturated with ordinary invokes.
+    #[serde_as(as = "Vec<UfeHex>")]
+    #[serde(default)]
+    pub deployment_relayers: Vec<Felt>,
+
+    /// File in which the value of the gas tank is recorded every hour, used to compute the evolution of the
+    /// treasury and its burn rate. Disabled when not set
+    #[serde(default)]
+    pub treasury_history: Option<PathBuf>,
+
+    /// Redis pub/sub through which the fleet and availability events are propagated to the other instances sharing
+    /// the fleet. The events are only published in the instance when not set
+    #[serde(default)]
+    pub messaging: Option<RedisMessagingConfiguration>,
+}
+
+/// Relayers dedicated to the given sponsors, which only execute their transactions
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedicatedRelayersConfiguration {
+    /// Fingerprints of the API keys of the sponsors, as reported in the logs and metrics (keccak of the key)
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub sponsors: Vec<Felt>,
+
+    /// Relayers reserved to the sponsors, they must be part of the relayers of the fleet
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub addresses: Vec<Felt>,
 }
 
 impl RelayersConfiguration {
-    pub fn validate(&self) -> Result<(), ServiceError> {
-        if self.addresses.is_empty() {
-            return Err(ServiceError::new("At least one relayer address must be configured"));
+    pub fn default_max_in_flight_transactions() -> usize {
+        4
+    }
+
+    pub fn default_execution_concurrency_factor() -> usize {
+        2
+    }
+
+    /// Maximum number of simultaneous executions, given the relayers of both fleets
+    pub fn max_concurrent_executions(&self) -> usize {
+        let relayers = self.addresses.len() + self.secondary.as_ref().map(|x| x.addresses.len()).unwrap_or_default();
+
+        relayers * self.execution_concurrency_factor
+    }
+
+    /// Returns the relayers of both fleets
+    pub fn fleet_addresses(&self) -> Vec<Felt> {
+        let mut addresses = self.addresses.clone();
+        if let Some(secondary) = &self.secondary {
+            addresses.extend(secondary.fleet_addresses());
         }
 
+        addresses
+    }
+
+    /// Configuration of the secondary fleet, if any. Its events are propagated on the messaging of the primary fleet
+    /// under their own namespace when it has no messaging of its own.
+    pub fn secondary_fleet(&self) -> Option<RelayersConfiguration> {
+        let mut secondary = self.secondary.as_deref()?.clone();
+        if secondary.messaging.is_none() {
+            secondary.messaging = self.messaging.as_ref().map(|x| RedisMessagingConfiguration {
+                namespace: format!("{}:secondary", x.namespace),
+                ..x.clone()
+            });
+        }
+
+        Some(secondary)
+    }
+
+    /// Number of simultaneous executions reserved to the deployments of accounts
+    pub fn reserved_deployment_executions(&self) -> usize {
+        self.deployment_relayers.len() * self.execution_concurrency_factor
+    }
+
+    /// Returns true if some relayers are dedicated to sponsors or reserved to deployments
+    pub fn is_isolated(&self) -> bool {
+        !self.dedicated.is_empty() || !self.deployment_relayers.is_empty()
+    }
+
+    /// Relayers which may execute a transaction. The transactions of a sponsor with dedicated relayers only use
+    /// them, the other transactions share the relayers which are not dedicated, the deployments also using the
+    /// relayers reserved to them.
+    pub fn relayers_of(&self, selection: RelayerSelection) -> Vec<Felt> {
+        let dedicated = selection
+            .sponsor
+            .and_then(|sponsor| self.dedicated.iter().find(|x| x.sponsors.contains(&sponsor)));
+        match dedicated {
+            Some(dedicated) => dedicated.addresses.clone(),
+            None => self
+                .addresses
+                .iter()
+                .filter(|address| !self.dedicated.iter().any(|x| x.addresses.contains(address)))
+                .filter(|address| selection.deployment || !self.deployment_relayers.contains(address))
+                .copied()
+                .collect(),
+        }
+    }
+}
+
+impl Validate for RelayersConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.private_key != Felt::ZERO, "private_key", "must not be zero");
+        report.ensure(!self.addresses.is_empty(), "addresses", "At least one relayer address must be configured");
+
+        let mut addresses = HashSet::new();
+        for (i, address) in self.addresses.iter().enumerate() {
+            let field = format!("addresses[{}]", i);
+            report.ensure(*address != Felt::ZERO, &field, "must not be zero");
+            report.ensure(addresses.insert(*address), &field, format!("duplicated relayer address {:#x}", address));
+        }
+
+        report.field("lock", &self.lock);
+        if let LockLayerConfiguration::Shared { fallback: Some(fallback), .. } = &self.lock {
+            for (i, address) in fallback.addresses.iter().enumerate() {
+                report.ensure(
+                    self.addresses.contains(address),
+                    &format!("lock.fallback.addresses[{}]", i),
+                    format!("{:#x} is not a relayer of the fleet", address),
+                );
+            }
+        }
+        report.field("spend_caps", &self.spend_caps);
+        report.ensure(self.max_in_flight_transactions > 0, "max_in_flight_transactions", "must be greater than 0");
+        report.ensure(self.execution_concurrency_factor > 0, "execution_concurrency_factor", "must be greater than 0");
+
         // Validate rebalancing configuration (including trigger_balance > min_relayer_balance)
-        self.rebalancing.validate(self.min_relayer_balance)?;
+        report.nested("rebalancing", |report| self.rebalancing.validate_with_min_balance(self.min_relayer_balance, report));
+
+        if let Some(top_up) = &self.gas_tank_top_up {
+            report.field("gas_tank_top_up", top_up);
+        }
+
+        if let Some(staking) = &self.staking {
+            report.field("staking", staking);
+        }
+
+        if let Some(journal) = &self.journal {
+            report.field("journal", journal);
+        }
+
+        if let Some(watchdog) = &self.watchdog {
+            report.field("watchdog", watchdog);
+        }
+
+        if let Some(messaging) = &self.messaging {
+            report.field("messaging", messaging);
+        }
+
+        let mut sponsors = HashSet::new();
+        let mut dedicated = HashSet::new();
+        for (i, group) in self.dedicated.iter().enumerate() {
+            report.nested(&format!("dedicated[{}]", i), |report| {
+                report.ensure(!group.sponsors.is_empty(), "sponsors", "At least one sponsor must be configured");
+                report.ensure(!group.addresses.is_empty(), "addresses", "At least one relayer address must be configured");
+
+                for (j, sponsor) in group.sponsors.iter().enumerate() {
+                    report.ensure(sponsors.insert(*sponsor), &format!("sponsors[{}]", j), "sponsor already has dedicated relayers");
+                }
+                for (j, address) in group.addresses.iter().enumerate() {
+                    let field = format!("addresses[{}]", j);
+                    report.ensure(self.addresses.contains(address), &field, format!("{:#x} is not a relayer of the fleet", address));
+                    report.ensure(dedicated.insert(*address), &field, format!("relayer {:#x} is already dedicated", address));
+                }
+            });
+        }
+        for (i, address) in self.deployment_relayers.iter().enumerate() {
+            let field = format!("deployment_relayers[{}]", i);
+            report.ensure(self.addresses.contains(address), &field, format!("{:#x} is not a relayer of the fleet", address));
+            report.ensure(!dedicated.contains(address), &field, format!("relayer {:#x} is dedicated to sponsors", address));
+        }
+        report.ensure(
+            !self.is_isolated()
+                || self
+                    .addresses
+                    .iter()
+                    .any(|x| !dedicated.contains(x) && !self.deployment_relayers.contains(x)),
+            "dedicated",
+            "At least one relayer must be shared by the other sponsors",
+        );
+
+        if let Some(secondary) = &self.secondary {
+            report.field("secondary", secondary.as_ref());
+            report.ensure(secondary.secondary.is_none(), "secondary.secondary", "fleets cannot be chained");
+            report.ensure(
+                secondary.gas_tank_top_up.is_none(),
+                "secondary.gas_tank_top_up",
+                "the gas tank is topped up by the primary fleet",
+            );
+            report.ensure(secondary.staking.is_none(), "secondary.staking", "the gas tank funds are staked by the primary fleet");
+            report.ensure(
+                !secondary.rebalancing.has_configuration(),
+                "secondary.rebalancing",
+                "the relayers of both fleets are rebalanced by the primary fleet",
+            );
+            report.ensure(
+                secondary.treasury_history.is_none(),
+                "secondary.treasury_history",
+                "the gas tank value is recorded by the primary fleet",
+            );
+            if let (Some(messaging), Some(secondary_messaging)) = (&self.messaging, &secondary.messaging) {
+                report.ensure(
+                    messaging.endpoint != secondary_messaging.endpoint || messaging.namespace != secondary_messaging.namespace,
+                    "secondary.messaging.namespace",
+                    "must differ from the namespace of the primary fleet",
+                );
+            }
+            report.ensure(
+                secondary.addresses.iter().all(|x| !self.addresses.contains(x)),
+                "secondary.addresses",
+                "must not contain relayers of the primary fleet",
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use paymaster_common::validation::Validate;
+    use starknet::core::types::Felt;
+
+    use crate::context::configuration::RelayersConfiguration;
+    use crate::lock::shared::RedisParameters;
+    use crate::lock::{LockFallbackConfiguration, LockLayerConfiguration};
+    use crate::rebalancing::OptionalRebalancingConfiguration;
+
+    fn relayers(fallback: Vec<Felt>) -> RelayersConfiguration {
+        RelayersConfiguration {
+            private_key: Felt::ONE,
+            addresses: vec![Felt::ONE, Felt::TWO],
+            min_relayer_balance: Felt::ZERO,
+            lock: LockLayerConfiguration::Shared {
+                retry_timeout: Duration::from_secs(5),
+                redis: RedisParameters::new("redis://localhost:6379"),
+                fallback: Some(LockFallbackConfiguration { addresses: fallback }),
+            },
+            rebalancing: OptionalRebalancingConfiguration::initialize(None),
+            max_in_flight_transactions: RelayersConfiguration::default_max_in_flight_transactions(),
+            execution_concurrency_factor: RelayersConfiguration::default_execution_concurrency_factor(),
+            spend_caps: Default::default(),
+            secondary: None,
+            gas_tank_top_up: None,
+            staking: None,
+            journal: None,
+            watchdog: None,
+            dedicated: vec![],
+            deployment_relayers: vec![],
+            treasury_history: None,
+            messaging: None,
+        }
+    }
+
+    #[test]
+    fn fallback_relayers_must_belong_to_the_fleet() {
+        assert!(relayers(vec![Felt::TWO]).validate_all().is_ok());
 
-        Ok(())
+        let errors = relayers(vec![Felt::TWO, Felt::THREE]).validate_all().unwrap_err();
+        assert_eq!(errors.errors().len(), 1);
+        assert!(errors.contains("lock.fallback.addresses[1]"));
     }
 }
@@ -1,8 +1,14 @@
 use paymaster_prices::Client as PriceClient;
 use paymaster_starknet::Client;
 
+use crate::events::RelayerEvents;
+use crate::failover::{Fleet, FleetEvent};
+use crate::gas_tank::GasTankSender;
+use crate::journal::ExecutionJournal;
 use crate::lock::LockLayer;
+use crate::monitoring::availability::RelayerAvailability;
 use crate::rebalancing::RelayerManagerConfiguration;
+use crate::Error;
 
 pub mod configuration;
 
@@ -12,28 +18,51 @@ pub use relayers::Relayers;
 #[derive(Clone)]
 pub struct Context {
     pub configuration: RelayerManagerConfiguration,
+    pub fleet: Fleet,
     pub starknet: Client,
     pub relayers: Relayers,
     pub relayers_locks: LockLayer,
+    pub availability: RelayerAvailability,
+    pub journal: ExecutionJournal,
     pub price: PriceClient,
+
+    /// Sends the transactions of the gas tank, shared by the fleets
+    pub gas_tank: GasTankSender,
+
+    /// Events of the fleet, propagated to the other instances when `relayers.messaging` is configured
+    pub events: RelayerEvents<FleetEvent>,
 }
 
 impl Context {
-    pub fn new(configuration: RelayerManagerConfiguration) -> Self {
+    /// Creates the context of the relayers. Fails when the configuration is invalid
+    pub fn new(configuration: RelayerManagerConfiguration) -> Result<Self, Error> {
         // Validate configuration before creating context
-        if let Err(e) = configuration.validate() {
-            panic!("Configuration validation failed: {}", e);
-        }
+        configuration
+            .validate()
+            .map_err(|e| Error::Configuration(format!("validation failed: {}", e)))?;
 
-        let starknet = Client::new(&configuration.starknet);
-        let relayers = Relayers::new(&starknet, &configuration.relayers);
+        let starknet = Client::new(&configuration.starknet).map_err(|e| Error::Configuration(e.to_string()))?;
+        let journal = ExecutionJournal::new(configuration.relayers.journal.as_ref())?;
+        let relayers = Relayers::new(&starknet, &configuration.relayers, &journal);
         let price = PriceClient::new(&configuration.price);
-        Self {
+        let gas_tank = GasTankSender::new(
+            &starknet,
+            &configuration.gas_tank,
+            configuration.gas_tank_multisig.as_ref(),
+            &configuration.relayers.lock,
+        )?;
+        let messaging = configuration.relayers.messaging.as_ref();
+        Ok(Self {
+            fleet: Fleet::Primary,
             starknet,
             relayers,
-            relayers_locks: LockLayer::new(&configuration),
+            relayers_locks: LockLayer::new(&configuration)?,
+            availability: RelayerAvailability::new(RelayerEvents::new(messaging, "availability")?),
+            journal,
             price,
+            gas_tank,
+            events: RelayerEvents::new(messaging, "fleet")?,
             configuration,
-        }
+        })
     }
 }
@@ -5,7 +5,12 @@ use paymaster_common::cache::ExpirableCache;
 use paymaster_starknet::{Client, StarknetAccountConfiguration};
 use starknet::core::types::Felt;
 
-use crate::relayer::Relayer;
+use crate::journal::ExecutionJournal;
+use crate::lock::LockLayerConfiguration;
+use crate::pipeline::NoncePipeline;
+use crate::relayer::{Relayer, RelayerContext};
+use crate::spend::UnsettledSpends;
+use crate::watchdog::PendingTransactions;
 use crate::{Error, RelayerConfiguration, RelayersConfiguration};
 
 #[derive(Clone)]
@@ -13,19 +18,34 @@ pub struct Relayers {
     relayers: HashMap<Felt, Relayer>,
     // Map of relayer address to its balance
     balances: ExpirableCache<Felt, Felt>,
+    pipeline: NoncePipeline,
+    pending: PendingTransactions,
+    unsettled: UnsettledSpends,
 }
 
 impl Relayers {
-    pub fn new(starknet: &Client, configuration: &RelayersConfiguration) -> Self {
+    pub fn new(starknet: &Client, configuration: &RelayersConfiguration, journal: &ExecutionJournal) -> Self {
         let mut relayers = HashMap::new();
         let num_relayers = configuration.addresses.len().try_into().unwrap();
         let balances = ExpirableCache::new(num_relayers);
+        let pipeline = match configuration.lock {
+            LockLayerConfiguration::Shared { .. } => NoncePipeline::disabled(),
+            _ => NoncePipeline::new(configuration.max_in_flight_transactions),
+        };
+        let pending = PendingTransactions::new(configuration.watchdog.is_some());
+        let unsettled = UnsettledSpends::default();
         for address in &configuration.addresses {
             relayers.insert(
                 *address,
                 Relayer::new(
                     starknet,
-                    balances.clone(),
+                    RelayerContext {
+                        balances: balances.clone(),
+                        pipeline: pipeline.clone(),
+                        journal: journal.clone(),
+                        pending: pending.clone(),
+                        unsettled: unsettled.clone(),
+                    },
                     &RelayerConfiguration {
                         account: StarknetAccountConfiguration {
                             address: *address,
@@ -36,7 +56,34 @@ impl Relayers {
             );
         }
 
-        Self { relayers, balances }
+        Self {
+            relayers,
+            balances,
+            pipeline,
+            pending,
+            unsettled,
+        }
+    }
+
+    /// Forget the nonces tracked for the relayer so that the next one is fetched from the chain
+    pub fn reset_pipeline(&self, relayer: Felt) {
+        self.pipeline.reset(relayer);
+    }
+
+    /// Returns the estimated fee of the transactions of the relayer which are not included given its `chain_nonce`
+    pub fn in_flight_cost(&self, relayer: Felt, chain_nonce: Felt) -> Felt {
+        self.pipeline.confirm(relayer, chain_nonce);
+        self.pipeline.in_flight_cost(relayer)
+    }
+
+    /// Transactions submitted by the relayers of this instance which are not known to be included yet
+    pub fn pending_transactions(&self) -> &PendingTransactions {
+        &self.pending
+    }
+
+    /// Transactions submitted by the relayers of this instance whose spend is still counted at their estimated fee
+    pub fn unsettled_spends(&self) -> &UnsettledSpends {
+        &self.unsettled
     }
 
     pub fn acquire_relayer(&self, relayer: &Felt) -> Result<Relayer, Error> {
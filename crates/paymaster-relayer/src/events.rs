@@ -0,0 +1,164 @@
+use async_trait::async_trait;
+use paymaster_common::service::messaging::redis::{RedisMessages, RedisMessagingConfiguration};
+use paymaster_common::service::messaging::{MessageIdentity, Messages};
+use paymaster_common::service::{Error as ServiceError, Service};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::error;
+
+use crate::{Context, Error};
+
+/// Messaging layer on which the events of the relayers are published. When `relayers.messaging` is configured, the
+/// events are also propagated through Redis pub/sub to the other instances sharing the fleet, which receive them
+/// on their own layer while the [`RelayerEventsListener`] runs.
+#[derive(Clone)]
+pub enum RelayerEvents<T>
+where
+    T: Clone + Send + Sync,
+{
+    Local(Messages<T>),
+    Redis(RedisMessages<T>),
+}
+
+impl<T> Default for RelayerEvents<T>
+where
+    T: Clone + Send + Sync,
+{
+    fn default() -> Self {
+        Self::Local(Messages::new())
+    }
+}
+
+impl<T> RelayerEvents<T>
+where
+    T: Clone + Serialize + DeserializeOwned,
+    T: Send + Sync + 'static,
+{
+    /// Creates the layer of the events of the given `topic`. Each topic has its own channels so that the
+    /// instances only receive the events of the type they listen to.
+    pub fn new(configuration: Option<&RedisMessagingConfiguration>, topic: &str) -> Result<Self, Error> {
+        let Some(configuration) = configuration else { return Ok(Self::default()) };
+
+        let configuration = RedisMessagingConfiguration {
+            namespace: format!("{}:{}", configuration.namespace, topic),
+            ..configuration.clone()
+        };
+        RedisMessages::new(&configuration, Messages::new())
+            .map(Self::Redis)
+            .map_err(|e| Error::Configuration(e.to_string()))
+    }
+
+    /// Local messaging layer, on which the events of this instance and of the other instances are received
+    pub fn messages(&self) -> Messages<T> {
+        match self {
+            Self::Local(messages) => messages.clone(),
+            Self::Redis(messages) => messages.messages().clone(),
+        }
+    }
+
+    /// Publish the event to the local receivers and to the other instances. An event which cannot be sent to Redis
+    /// is only delivered locally.
+    pub async fn publish<S: MessageIdentity>(&self, event: T) {
+        match self {
+            Self::Local(messages) => messages.publish::<S>(event).await,
+            Self::Redis(messages) => {
+                if let Err(e) = messages.publish::<S>(event).await {
+                    error!("Failed to propagate the {} event to the other instances: {}", S::NAME, e);
+                }
+            },
+        }
+    }
+
+    /// Forward the events published by the other instances to the local receivers. Never returns when the events
+    /// are not propagated.
+    pub async fn listen(&self) -> Result<(), ServiceError> {
+        match self {
+            Self::Local(_) => futures::future::pending().await,
+            Self::Redis(messages) => messages.listen().await.map_err(|e| ServiceError::new(&e.to_string())),
+        }
+    }
+}
+
+/// Receives the fleet and availability events published by the other instances. Only spawned when the messaging
+/// is configured.
+pub struct RelayerEventsListener {
+    context: Context,
+}
+
+#[async_trait]
+impl Service for RelayerEventsListener {
+    type Context = Context;
+
+    const NAME: &'static str = "RelayerEventsListener";
+
+    async fn new(context: Self::Context) -> Self {
+        Self { context }
+    }
+
+    async fn run(self) -> Result<(), ServiceError> {
+        tokio::try_join!(self.context.events.listen(), self.context.availability.listen())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use paymaster_common::declare_message_identity;
+    use paymaster_common::service::messaging::redis::RedisMessagingConfiguration;
+    use testcontainers::core::{IntoContainerPort, WaitFor};
+    use testcontainers::runners::AsyncRunner;
+    use testcontainers::GenericImage;
+
+    use crate::events::RelayerEvents;
+    use crate::failover::FleetEvent;
+
+    struct Receiver;
+    declare_message_identity!(Receiver);
+
+    struct Sender;
+    declare_message_identity!(Sender);
+
+    #[tokio::test]
+    async fn local_events_are_only_received_by_the_instance() {
+        let events = RelayerEvents::<FleetEvent>::new(None, "fleet").unwrap();
+        let other = RelayerEvents::<FleetEvent>::new(None, "fleet").unwrap();
+
+        let mut receiver = events.messages().receiver::<Receiver>().subscribe_to::<Sender>().build().await;
+        let mut other_receiver = other.messages().receiver::<Receiver>().subscribe_to::<Sender>().build().await;
+
+        events.publish::<Sender>(FleetEvent::FailedOver).await;
+
+        assert_eq!(receiver.receive_all().await, vec![FleetEvent::FailedOver]);
+        assert!(other_receiver.receive_all().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn events_are_propagated_to_the_other_instances() {
+        let container = GenericImage::new("redis", "7")
+            .with_exposed_port(6379.tcp())
+            .with_wait_for(WaitFor::message_on_stdout("Ready to accept connections"))
+            .start()
+            .await
+            .unwrap();
+        let configuration = RedisMessagingConfiguration {
+            endpoint: format!("redis://127.0.0.1:{}", container.get_host_port_ipv4(6379).await.unwrap()),
+            namespace: "relayers".to_string(),
+        };
+
+        let events = RelayerEvents::<FleetEvent>::new(Some(&configuration), "fleet").unwrap();
+        let other = RelayerEvents::<FleetEvent>::new(Some(&configuration), "fleet").unwrap();
+        let mut receiver = other.messages().receiver::<Receiver>().subscribe_to::<Sender>().build().await;
+
+        let listener = other.clone();
+        tokio::spawn(async move { listener.listen().await });
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        events.publish::<Sender>(FleetEvent::FailedOver).await;
+
+        let event = tokio::time::timeout(Duration::from_secs(5), receiver.receive()).await.unwrap();
+        assert_eq!(event, Some(FleetEvent::FailedOver));
+    }
+}
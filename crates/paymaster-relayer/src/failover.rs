@@ -0,0 +1,117 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use paymaster_common::declare_message_identity;
+use serde::{Deserialize, Serialize};
+
+/// Number of consecutive failures of the primary fleet after which execution fails over to the secondary fleet
+pub const FAILOVER_THRESHOLD: usize = 3;
+
+/// Duration during which the primary fleet is skipped once execution failed over
+pub const FAILOVER_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Identity under which the fleet events are published on the messaging layer
+pub struct RelayerFleets;
+
+declare_message_identity!(RelayerFleets);
+
+/// Fleet of relayers to which a context belongs, reported in the logs and metrics of its services
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fleet {
+    #[default]
+    Primary,
+    Secondary,
+}
+
+impl Fleet {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Primary => "primary",
+            Self::Secondary => "secondary",
+        }
+    }
+}
+
+/// Events published when execution moves from one relayer fleet to the other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FleetEvent {
+    /// The primary fleet failed persistently, relayers are taken from the secondary fleet
+    FailedOver,
+
+    /// The primary fleet serves relayers again
+    Recovered,
+}
+
+/// Tracks the failures of the primary fleet to decide whether the relayers should be taken from the secondary fleet
+#[derive(Debug, Default)]
+pub struct FleetHealth {
+    consecutive_failures: AtomicUsize,
+    failed_over: AtomicBool,
+    skip_until: Mutex<Option<Instant>>,
+}
+
+impl FleetHealth {
+    /// Returns true while the primary fleet must be skipped
+    pub fn is_skipped(&self) -> bool {
+        let skip_until = self.skip_until.lock().unwrap_or_else(|e| e.into_inner());
+        skip_until.is_some_and(|x| Instant::now() < x)
+    }
+
+    /// Record a failure of the primary fleet. Returns the event to publish if the fleet just failed over
+    pub fn record_failure(&self) -> Option<FleetEvent> {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::SeqCst) + 1;
+        if failures < FAILOVER_THRESHOLD {
+            return None;
+        }
+
+        *self.skip_until.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now() + FAILOVER_COOLDOWN);
+        (!self.failed_over.swap(true, Ordering::SeqCst)).then_some(FleetEvent::FailedOver)
+    }
+
+    /// Record a success of the primary fleet. Returns the event to publish if the fleet just recovered
+    pub fn record_success(&self) -> Option<FleetEvent> {
+        self.consecutive_failures.store(0, Ordering::SeqCst);
+        *self.skip_until.lock().unwrap_or_else(|e| e.into_inner()) = None;
+
+        self.failed_over.swap(false, Ordering::SeqCst).then_some(FleetEvent::Recovered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::failover::{FleetEvent, FleetHealth, FAILOVER_THRESHOLD};
+
+    #[test]
+    fn fails_over_after_consecutive_failures_and_recovers() {
+        let health = FleetHealth::default();
+
+        for _ in 1..FAILOVER_THRESHOLD {
+            assert_eq!(health.record_failure(), None);
+            assert!(!health.is_skipped());
+        }
+
+        assert_eq!(health.record_failure(), Some(FleetEvent::FailedOver));
+        assert!(health.is_skipped());
+
+        // Further failures do not publish the event again
+        assert_eq!(health.record_failure(), None);
+
+        assert_eq!(health.record_success(), Some(FleetEvent::Recovered));
+        assert!(!health.is_skipped());
+        assert_eq!(health.record_success(), None);
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let health = FleetHealth::default();
+
+        for _ in 1..FAILOVER_THRESHOLD {
+            health.record_failure();
+        }
+        health.record_success();
+
+        assert_eq!(health.record_failure(), None);
+        assert!(!health.is_skipped());
+    }
+}
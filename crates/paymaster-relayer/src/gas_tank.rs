@@ -0,0 +1,207 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use paymaster_common::metric;
+use paymaster_starknet::transaction::multisig::MultisigProposal;
+use paymaster_starknet::transaction::{Calls, EstimatedCalls};
+use paymaster_starknet::{Client, StarknetAccount, StarknetAccountConfiguration};
+use starknet::accounts::Account;
+use starknet::core::types::{ExecutionResult, Felt, TransactionStatus};
+use starknet::signers::SigningKey;
+use tokio::sync::Mutex;
+use tracing::info;
+
+use crate::lock::leader::LeaderLock;
+use crate::lock::LockLayerConfiguration;
+use crate::multisig::{MultisigConfiguration, ProposalStore};
+use crate::Error;
+
+/// Maximum duration to wait for the transactions of the gas tank sent by other tasks or instances
+const SENDER_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// Maximum duration to wait for a transaction of the gas tank to be accepted
+const ACCEPTANCE_TIMEOUT: Duration = Duration::from_secs(120);
+const ACCEPTANCE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Outcome of the calls sent from the gas tank
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasTankSubmission {
+    /// Executed by the gas tank and accepted on chain
+    Executed { transaction_hash: Felt },
+
+    /// Sent by the gas tank at `nonce` but still not accepted after the timeout, see [`GasTankSender::outcome`]
+    Submitted { transaction_hash: Felt, nonce: Felt },
+
+    /// Proposed to the signers of the multisig gas tank at `nonce`, executed once they approved it
+    Proposed { transaction_hash: Felt, nonce: Felt },
+}
+
+impl GasTankSubmission {
+    pub fn transaction_hash(&self) -> Felt {
+        match self {
+            Self::Executed { transaction_hash } | Self::Submitted { transaction_hash, .. } | Self::Proposed { transaction_hash, .. } => *transaction_hash,
+        }
+    }
+
+    pub fn status(&self) -> &'static str {
+        match self {
+            Self::Executed { .. } => "executed",
+            Self::Submitted { .. } => "submitted",
+            Self::Proposed { .. } => "proposed",
+        }
+    }
+}
+
+/// Single place from which the transactions of the gas tank are sent (rebalancing, refunds, messages, staking).
+/// The transactions are sent one at a time across the tasks of the instance and, through the [`LeaderLock`] of the
+/// shared lock layer, across the instances, each one waiting for the previous one to be accepted so that they never
+/// compete for the nonce of the gas tank. A multisig gas tank proposes the transactions to its signers instead.
+#[derive(Clone)]
+pub struct GasTankSender {
+    starknet: Client,
+    gas_tank: StarknetAccount,
+    private_key: Felt,
+    multisig: Option<(MultisigConfiguration, ProposalStore)>,
+
+    leader: LeaderLock,
+    local: Arc<Mutex<()>>,
+}
+
+impl GasTankSender {
+    pub fn new(
+        starknet: &Client,
+        gas_tank: &StarknetAccountConfiguration,
+        multisig: Option<&MultisigConfiguration>,
+        lock: &LockLayerConfiguration,
+    ) -> Result<Self, Error> {
+        // The lease outlives the submission and the acceptance of a transaction so that it is only released by the sender
+        let leader = LeaderLock::new(lock, &format!("gas-tank:{}", gas_tank.address.to_fixed_hex_string()), SENDER_TIMEOUT)?;
+
+        Ok(Self {
+            starknet: starknet.clone(),
+            gas_tank: starknet.initialize_account(gas_tank),
+            private_key: gas_tank.private_key,
+            multisig: multisig.map(|x| (x.clone(), ProposalStore::new(x))),
+            leader,
+            local: Arc::new(Mutex::new(())),
+        })
+    }
+
+    pub fn address(&self) -> Felt {
+        self.gas_tank.address()
+    }
+
+    pub fn is_multisig(&self) -> bool {
+        self.multisig.is_some()
+    }
+
+    /// Estimate the given `calls` executed by the gas tank. The validation of the signature is skipped for a multisig.
+    pub async fn estimate(&self, calls: &Calls) -> Result<EstimatedCalls, Error> {
+        let result = match &self.multisig {
+            Some(_) => {
+                let nonce = self.fetch_nonce().await?;
+                MultisigProposal::estimate(&self.starknet, self.address(), calls, nonce).await
+            },
+            None => calls.estimate(&self.gas_tank, None).await,
+        };
+
+        result.map_err(|e| Error::Execution(e.to_string()))
+    }
+
+    /// Returns the proposals of the multisig gas tank waiting for the approval of the signers
+    pub async fn pending_proposals(&self) -> Result<Vec<MultisigProposal>, Error> {
+        let Some((_, proposals)) = &self.multisig else { return Ok(vec![]) };
+
+        let nonce = self.fetch_nonce().await?;
+        proposals.prune(nonce)
+    }
+
+    /// Send the `calls` from the gas tank once the transactions sent before are accepted. Returns once the
+    /// transaction is accepted, once it is still not accepted after the timeout in which case it may be accepted
+    /// later, or once it is proposed for a multisig gas tank. Fails with [`Error::Reverted`] when the transaction
+    /// reverted.
+    pub async fn send(&self, calls: &Calls) -> Result<GasTankSubmission, Error> {
+        let _local = self.local.lock().await;
+        self.leader.acquire_within(SENDER_TIMEOUT).await?;
+
+        let result = match &self.multisig {
+            Some((multisig, proposals)) => self.propose(multisig, proposals, calls).await,
+            None => self.execute(calls).await,
+        };
+        self.leader.release().await;
+
+        let status = result.as_ref().map(|x| x.status()).unwrap_or("failure");
+        metric!(counter[gas_tank_transaction] = 1, status = status);
+
+        result
+    }
+
+    /// Returns whether the transaction sent or proposed by the gas tank at `nonce` succeeded, or None while it may
+    /// still be included. A transaction unknown once the nonce of the gas tank moved past `nonce` never will be.
+    pub async fn outcome(&self, transaction_hash: Felt, nonce: Felt) -> Result<Option<bool>, Error> {
+        // The nonce is fetched first so that a transaction included in between is seen with its status
+        let current = self.fetch_nonce().await?;
+        match self.starknet.get_transaction_status(transaction_hash).await {
+            Ok(TransactionStatus::AcceptedOnL2(result) | TransactionStatus::AcceptedOnL1(result)) => Ok(Some(matches!(result, ExecutionResult::Succeeded))),
+            Ok(_) => Ok(None),
+            Err(paymaster_starknet::Error::TransactionNotFound) => Ok((current > nonce).then_some(false)),
+            Err(e) => Err(Error::Execution(e.to_string())),
+        }
+    }
+
+    async fn execute(&self, calls: &Calls) -> Result<GasTankSubmission, Error> {
+        let nonce = self.fetch_nonce().await?;
+        let estimated_calls = calls
+            .estimate(&self.gas_tank, None)
+            .await
+            .map_err(|e| Error::Execution(e.to_string()))?;
+        let result = estimated_calls
+            .execute(&self.gas_tank, nonce)
+            .await
+            .map_err(|e| Error::Execution(e.to_string()))?;
+
+        let transaction_hash = result.transaction_hash;
+        match self
+            .starknet
+            .wait_for_acceptance(transaction_hash, ACCEPTANCE_POLL_INTERVAL, ACCEPTANCE_TIMEOUT)
+            .await
+        {
+            Ok(true) => Ok(GasTankSubmission::Executed { transaction_hash }),
+            Ok(false) => Err(Error::Reverted(transaction_hash)),
+            Err(_) => Ok(GasTankSubmission::Submitted { transaction_hash, nonce }),
+        }
+    }
+
+    async fn propose(&self, multisig: &MultisigConfiguration, proposals: &ProposalStore, calls: &Calls) -> Result<GasTankSubmission, Error> {
+        let nonce = self.fetch_nonce().await?;
+        let estimated_calls = MultisigProposal::estimate(&self.starknet, self.address(), calls, nonce)
+            .await
+            .map_err(|e| Error::Execution(e.to_string()))?;
+
+        let nonce = proposals.next_nonce(nonce)?;
+        let mut proposal = MultisigProposal::new(&self.gas_tank, &estimated_calls, nonce, multisig.threshold).map_err(|e| Error::Multisig(e.to_string()))?;
+        proposal
+            .sign(&SigningKey::from_secret_scalar(self.private_key))
+            .map_err(|e| Error::Multisig(e.to_string()))?;
+        proposals.save(&proposal)?;
+
+        info!(
+            "Gas tank transaction proposed to the signers ({}/{} signatures), tx hash: {}",
+            proposal.signatures.len(),
+            multisig.threshold,
+            proposal.transaction_hash.to_fixed_hex_string()
+        );
+
+        Ok(GasTankSubmission::Proposed {
+            transaction_hash: proposal.transaction_hash,
+            nonce,
+        })
+    }
+
+    async fn fetch_nonce(&self) -> Result<Felt, Error> {
+        self.starknet
+            .fetch_nonce(self.address())
+            .await
+            .map_err(|e| Error::Execution(e.to_string()))
+    }
+}
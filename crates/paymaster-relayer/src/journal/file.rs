@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use starknet::core::types::Felt;
+use tokio::sync::Mutex;
+
+use crate::journal::{Error, JournalEntry};
+
+/// Journal kept in a local file holding all the entries, rewritten on each change
+#[derive(Clone)]
+pub struct FileJournal {
+    path: Arc<Mutex<PathBuf>>,
+}
+
+impl FileJournal {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path: Arc::new(Mutex::new(path)),
+        }
+    }
+
+    pub async fn write(&self, entry: &JournalEntry) -> Result<(), Error> {
+        let path = self.path.lock().await;
+
+        let mut entries = Self::read(&path).await?;
+        entries.retain(|x| (x.relayer, x.nonce) != (entry.relayer, entry.nonce));
+        entries.push(entry.clone());
+
+        Self::save(&path, &entries).await
+    }
+
+    pub async fn remove(&self, relayer: Felt, nonce: Option<Felt>) -> Result<(), Error> {
+        let path = self.path.lock().await;
+
+        let mut entries = Self::read(&path).await?;
+        entries.retain(|x| x.relayer != relayer || nonce.is_some_and(|nonce| x.nonce != nonce));
+
+        Self::save(&path, &entries).await
+    }
+
+    pub async fn entries(&self) -> Result<Vec<JournalEntry>, Error> {
+        let path = self.path.lock().await;
+
+        Self::read(&path).await
+    }
+
+    async fn read(path: &PathBuf) -> Result<Vec<JournalEntry>, Error> {
+        match tokio::fs::read(path).await {
+            Ok(content) => serde_json::from_slice(&content).map_err(|e| Error::Storage(e.to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+            Err(e) => Err(Error::Storage(e.to_string())),
+        }
+    }
+
+    // Write to a temporary file first so that a crash while writing cannot corrupt the journal
+    async fn save(path: &PathBuf, entries: &[JournalEntry]) -> Result<(), Error> {
+        let content = serde_json::to_vec(entries).map_err(|e| Error::Storage(e.to_string()))?;
+        let temporary = path.with_extension("tmp");
+
+        tokio::fs::write(&temporary, content)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        tokio::fs::rename(&temporary, path)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+}
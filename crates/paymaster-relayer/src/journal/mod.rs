@@ -0,0 +1,234 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use paymaster_common::metric;
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+use thiserror::Error;
+
+use crate::journal::file::FileJournal;
+use crate::journal::redis::RedisJournal;
+use crate::lock::instance_id;
+use crate::lock::shared::RedisParameters;
+
+mod file;
+mod recovery;
+mod redis;
+
+pub use recovery::ExecutionJournalRecovery;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("journal {0}")]
+    Storage(String),
+}
+
+/// Storage of the execution journal, in which an entry is written before each relayer transaction is submitted
+/// so that the executions interrupted by a crash can be reconciled on startup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum JournalConfiguration {
+    /// Entries are shared by the instances, to be used along with the shared lock layer
+    Redis { redis: RedisParameters },
+
+    /// Entries are kept in a local file, to be used along with the seggregated lock layer
+    File { path: PathBuf },
+}
+
+impl Validate for JournalConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        match self {
+            Self::Redis { redis } => report.field("redis", redis),
+            Self::File { path } => report.ensure(!path.as_os_str().is_empty(), "path", "must not be empty"),
+        }
+    }
+}
+
+/// Transaction of a relayer being executed by an instance
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    #[serde_as(as = "UfeHex")]
+    pub relayer: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub nonce: Felt,
+
+    /// Estimated fee of the transaction in STRK (in FRI)
+    #[serde_as(as = "UfeHex")]
+    pub fee: Felt,
+
+    /// Hash of the transaction computed before submitting it, so that a crash during the submission can still be
+    /// reconciled with the chain
+    #[serde_as(as = "Option<UfeHex>")]
+    #[serde(default)]
+    pub expected_transaction_hash: Option<Felt>,
+
+    /// Set once the transaction is submitted
+    #[serde_as(as = "Option<UfeHex>")]
+    pub transaction_hash: Option<Felt>,
+
+    /// Instance executing the transaction
+    pub instance: String,
+
+    /// Unix timestamp in seconds
+    pub started_at: u64,
+}
+
+impl JournalEntry {
+    pub fn new(relayer: Felt, nonce: Felt, fee: Felt) -> Self {
+        Self {
+            relayer,
+            nonce,
+            fee,
+            expected_transaction_hash: None,
+            transaction_hash: None,
+            instance: instance_id().to_string(),
+            started_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        }
+    }
+
+    pub fn expecting(mut self, transaction_hash: Felt) -> Self {
+        self.expected_transaction_hash = Some(transaction_hash);
+        self
+    }
+
+    /// Returns the hash of the transaction submitted, or expected to be, for the entry
+    pub fn transaction_hash(&self) -> Option<Felt> {
+        self.transaction_hash.or(self.expected_transaction_hash)
+    }
+
+    /// Returns true if the entry was written by another instance long enough ago for its execution to be over
+    pub fn is_orphaned(&self, age: Duration) -> bool {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+        self.instance != instance_id() && now.saturating_sub(self.started_at) >= age.as_secs()
+    }
+}
+
+#[derive(Clone)]
+pub enum ExecutionJournal {
+    Disabled,
+    Redis(RedisJournal),
+    File(FileJournal),
+}
+
+impl ExecutionJournal {
+    pub fn new(configuration: Option<&JournalConfiguration>) -> Result<Self, Error> {
+        match configuration {
+            None => Ok(Self::Disabled),
+            Some(JournalConfiguration::Redis { redis }) => Ok(Self::Redis(RedisJournal::new(redis)?)),
+            Some(JournalConfiguration::File { path }) => Ok(Self::File(FileJournal::new(path.clone()))),
+        }
+    }
+
+    /// Write the entry of a transaction about to be submitted. The transaction must not be submitted if it fails.
+    pub async fn begin(&self, entry: &JournalEntry) -> Result<(), Error> {
+        let result = match self {
+            Self::Disabled => Ok(()),
+            Self::Redis(x) => x.write(entry).await,
+            Self::File(x) => x.write(entry).await,
+        };
+
+        metric!(on error result => counter [ relayer_journal_error ] = 1, method = "begin");
+        result
+    }
+
+    /// Record the hash of the transaction submitted for the given entry
+    pub async fn submitted(&self, entry: &JournalEntry, transaction_hash: Felt) -> Result<(), Error> {
+        let entry = JournalEntry {
+            transaction_hash: Some(transaction_hash),
+            ..entry.clone()
+        };
+
+        let result = match self {
+            Self::Disabled => Ok(()),
+            Self::Redis(x) => x.write(&entry).await,
+            Self::File(x) => x.write(&entry).await,
+        };
+
+        metric!(on error result => counter [ relayer_journal_error ] = 1, method = "submitted");
+        result
+    }
+
+    /// Remove the given entry, once its transaction is known not to be submitted or has been reconciled
+    pub async fn forget(&self, entry: &JournalEntry) -> Result<(), Error> {
+        match self {
+            Self::Disabled => Ok(()),
+            Self::Redis(x) => x.remove(entry.relayer, Some(entry.nonce)).await,
+            Self::File(x) => x.remove(entry.relayer, Some(entry.nonce)).await,
+        }
+    }
+
+    /// Remove the entries of the relayer once it is released, which is when its executions are recorded
+    pub async fn close(&self, relayer: Felt) -> Result<(), Error> {
+        let result = match self {
+            Self::Disabled => Ok(()),
+            Self::Redis(x) => x.remove(relayer, None).await,
+            Self::File(x) => x.remove(relayer, None).await,
+        };
+
+        metric!(on error result => counter [ relayer_journal_error ] = 1, method = "close");
+        result
+    }
+
+    /// Returns all the entries of the journal
+    pub async fn entries(&self) -> Result<Vec<JournalEntry>, Error> {
+        match self {
+            Self::Disabled => Ok(vec![]),
+            Self::Redis(x) => x.entries().await,
+            Self::File(x) => x.entries().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use starknet::core::types::Felt;
+
+    use crate::journal::{ExecutionJournal, JournalConfiguration, JournalEntry};
+
+    #[tokio::test]
+    async fn file_journal_keeps_entries_until_the_relayer_is_released() {
+        let path = std::env::temp_dir().join(format!("paymaster-journal-{:08x}.json", rand::random::<u32>()));
+        let journal = ExecutionJournal::new(Some(&JournalConfiguration::File { path: path.clone() })).unwrap();
+
+        let first = JournalEntry::new(Felt::ONE, Felt::from(5), Felt::from(100));
+        let second = JournalEntry::new(Felt::TWO, Felt::from(7), Felt::from(100)).expecting(Felt::from(8));
+        journal.begin(&first).await.unwrap();
+        journal.begin(&second).await.unwrap();
+        journal.submitted(&first, Felt::THREE).await.unwrap();
+
+        // A new journal on the same file sees the entries, as would the instance restarted after a crash
+        let restarted = ExecutionJournal::new(Some(&JournalConfiguration::File { path: path.clone() })).unwrap();
+        let mut entries = restarted.entries().await.unwrap();
+        entries.sort_by_key(|x| x.relayer);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].transaction_hash, Some(Felt::THREE));
+        assert_eq!(entries[1].transaction_hash, None);
+
+        // The hash computed before submitting is known even when the submission was interrupted
+        assert_eq!(entries[1].transaction_hash(), Some(Felt::from(8)));
+
+        restarted.close(Felt::ONE).await.unwrap();
+        restarted.forget(&second).await.unwrap();
+        assert!(restarted.entries().await.unwrap().is_empty());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn entries_of_this_instance_are_never_orphaned() {
+        let mut entry = JournalEntry::new(Felt::ONE, Felt::ZERO, Felt::ZERO);
+        entry.started_at = 0;
+        assert!(!entry.is_orphaned(Duration::from_secs(60)));
+
+        entry.instance = "crashed".to_string();
+        assert!(entry.is_orphaned(Duration::from_secs(60)));
+    }
+}
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use paymaster_common::service::{Error, Service};
+use paymaster_common::{metric, service_check, service_warn};
+use tokio::time;
+
+use crate::journal::JournalEntry;
+use crate::Context;
+
+/// Age from which the entry of another instance is considered interrupted, well above the validity of a lock
+const ORPHANED_ENTRY_AGE: Duration = Duration::from_secs(120);
+
+/// Reconcile the executions which were interrupted before their relayer was released, typically by a crash. It
+/// runs on startup and keeps checking the journal for the instances which stop without restarting.
+pub struct ExecutionJournalRecovery {
+    context: Context,
+}
+
+#[async_trait]
+impl Service for ExecutionJournalRecovery {
+    type Context = Context;
+
+    const NAME: &'static str = "ExecutionJournalRecovery";
+
+    async fn new(context: Self::Context) -> Self {
+        Self { context }
+    }
+
+    async fn run(self) -> Result<(), Error> {
+        let mut ticker = time::interval(Duration::from_secs(60));
+        loop {
+            ticker.tick().await;
+
+            let entries = service_check!(self.context.journal.entries().await => continue);
+            for entry in entries.into_iter().filter(|x| x.is_orphaned(ORPHANED_ENTRY_AGE)) {
+                self.recover(&entry).await;
+            }
+        }
+    }
+}
+
+impl ExecutionJournalRecovery {
+    // Release the lock still held by the interrupted instance and drop the nonces which do not account for the
+    // transaction, then record the spend of the transaction if it reached the chain
+    async fn recover(&self, entry: &JournalEntry) {
+        let relayer = entry.relayer.to_fixed_hex_string();
+
+        service_check!(self.context.relayers_locks.recover_relayer(entry.relayer, &entry.instance).await => return);
+        self.context.relayers.reset_pipeline(entry.relayer);
+
+        let outcome = match entry.transaction_hash() {
+            None => "not_submitted",
+            Some(transaction_hash) => match self.context.starknet.get_transaction_status(transaction_hash).await {
+                Ok(_) => {
+                    service_check!(self.context.relayers_locks.record_spend(entry.relayer, entry.fee).await => return);
+                    "submitted"
+                },
+                Err(paymaster_starknet::Error::TransactionNotFound) if entry.transaction_hash.is_none() => "not_submitted",
+                Err(paymaster_starknet::Error::TransactionNotFound) => "dropped",
+                Err(e) => {
+                    service_warn!("could not fetch the status of the transaction of relayer {}: {}", relayer, e);
+                    return;
+                },
+            },
+        };
+
+        service_check!(self.context.journal.forget(entry).await => return);
+
+        service_warn!(
+            "recovered the execution of relayer {} with nonce {} interrupted on {} ({})",
+            relayer,
+            entry.nonce.to_hex_string(),
+            entry.instance,
+            outcome
+        );
+        metric!(counter[relayer_journal_recovered] = 1, outcome = outcome);
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use deadpool_redis::redis::{pipe, AsyncCommands, RedisWrite, ToRedisArgs};
+use deadpool_redis::{Connection, Pool};
+use futures::StreamExt;
+use starknet::core::types::Felt;
+
+use crate::journal::{Error, JournalEntry};
+use crate::lock::shared::RedisParameters;
+
+/// Entries are dropped after a day even if they were never reconciled
+const JOURNAL_EXPIRY: i64 = 86400;
+
+enum JournalKey {
+    All,
+    Relayer(Felt),
+}
+
+impl ToRedisArgs for JournalKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + RedisWrite,
+    {
+        match self {
+            Self::All => out.write_arg_fmt("relayer-journal:*"),
+            Self::Relayer(x) => out.write_arg_fmt(format!("relayer-journal:{}", x.to_fixed_hex_string())),
+        }
+    }
+}
+
+/// Journal shared by the instances. The entries of a relayer are kept in a hash indexed by their nonce.
+#[derive(Clone)]
+pub struct RedisJournal {
+    redis: Pool,
+}
+
+impl RedisJournal {
+    pub fn new(params: &RedisParameters) -> Result<Self, Error> {
+        let redis = params.create_pool().map_err(|e| Error::Storage(e.to_string()))?;
+
+        Ok(Self { redis })
+    }
+
+    pub async fn write(&self, entry: &JournalEntry) -> Result<(), Error> {
+        let mut connection = self.get_redis_connection().await?;
+        let value = serde_json::to_vec(entry).map_err(|e| Error::Storage(e.to_string()))?;
+
+        let mut pipeline = pipe();
+        pipeline.atomic();
+        pipeline
+            .hset(JournalKey::Relayer(entry.relayer), entry.nonce.to_fixed_hex_string(), value)
+            .ignore();
+        pipeline.expire(JournalKey::Relayer(entry.relayer), JOURNAL_EXPIRY).ignore();
+
+        pipeline
+            .query_async::<()>(&mut connection)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    pub async fn remove(&self, relayer: Felt, nonce: Option<Felt>) -> Result<(), Error> {
+        let mut connection = self.get_redis_connection().await?;
+
+        let result: Result<(), _> = match nonce {
+            Some(nonce) => connection.hdel(JournalKey::Relayer(relayer), nonce.to_fixed_hex_string()).await,
+            None => connection.del(JournalKey::Relayer(relayer)).await,
+        };
+
+        result.map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    pub async fn entries(&self) -> Result<Vec<JournalEntry>, Error> {
+        let mut connection = self.get_redis_connection().await?;
+
+        let keys: Vec<String> = connection
+            .scan_match(JournalKey::All)
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+            .collect()
+            .await;
+
+        let mut entries = vec![];
+        for key in keys {
+            let values: HashMap<String, Vec<u8>> = connection.hgetall(key).await.map_err(|e| Error::Storage(e.to_string()))?;
+            entries.extend(values.values().filter_map(|x| serde_json::from_slice(x).ok()));
+        }
+
+        Ok(entries)
+    }
+
+    async fn get_redis_connection(&self) -> Result<Connection, Error> {
+        self.redis.get().await.map_err(|e| Error::Storage(e.to_string()))
+    }
+}
@@ -1,14 +1,30 @@
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
+use paymaster_common::metric;
+use paymaster_common::service::messaging::Messages;
 use paymaster_common::service::TokioServiceManager;
+use rand::prelude::IndexedRandom;
+use rand::rng;
+use rand::seq::SliceRandom;
 use starknet::accounts::Account;
+use starknet::core::types::Felt;
 use thiserror::Error;
-use tracing::debug;
+use tracing::{debug, warn};
 
+use crate::accounting::AccountingSnapshot;
 pub use crate::context::Context;
-use crate::lock::RelayerLock;
-
+use crate::events::RelayerEventsListener;
+use crate::failover::FleetHealth;
+pub use crate::failover::{Fleet, FleetEvent, RelayerFleets};
+use crate::gas_tank::GasTankSender;
+use crate::journal::ExecutionJournalRecovery;
+use crate::lock::{RelayerLock, RelayerLockStatus};
+
+pub mod accounting;
+pub mod events;
+mod failover;
+pub mod journal;
 pub mod lock;
 
 mod relayer;
@@ -16,17 +32,35 @@ pub mod swap;
 pub use relayer::{LockedRelayer, Relayer, RelayerConfiguration};
 
 mod context;
-pub use context::configuration::RelayersConfiguration;
+pub use context::configuration::{DedicatedRelayersConfiguration, RelayersConfiguration};
 use paymaster_common::service::tracing::instrument;
 pub use rebalancing::RelayerManagerConfiguration;
 
+use crate::lock::LockLayerConfiguration;
 use crate::monitoring::availability::EnabledRelayersService;
+pub use crate::monitoring::availability::{AvailabilityTransition, RelayerAvailability, RelayersAvailability};
 use crate::monitoring::balance::RelayerBalanceMonitoring;
 use crate::monitoring::gas_tank::GasTankBalanceMonitoring;
-
+use crate::monitoring::lock::{LockLayerHealthProbe, RelayerLockMonitoring};
+use crate::monitoring::spend::SpendCapMonitoring;
+use crate::staking::GasTankStakingService;
+use crate::topup::GasTankTopUpService;
+use crate::treasury::report::{TreasuryReport, TreasurySnapshotService};
+use crate::watchdog::RelayerTransactionWatchdog;
+
+pub mod gas_tank;
 mod monitoring;
+pub mod multisig;
+pub mod pipeline;
 pub mod rebalancing;
+mod selection;
+pub mod spend;
+pub mod staking;
+pub mod topup;
+pub mod treasury;
+pub mod watchdog;
 pub use rebalancing::RelayerRebalancingService;
+pub use selection::RelayerSelection;
 
 macro_rules! log_if_error {
     ($e: expr) => {
@@ -40,11 +74,17 @@ macro_rules! log_if_error {
     };
 }
 
+/// Interval between two attempts to lock a given relayer while it is used
+const LOCK_RELAYER_AT_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error(transparent)]
     Lock(#[from] lock::Error),
 
+    #[error(transparent)]
+    Journal(#[from] journal::Error),
+
     #[error("invalid nonce")]
     InvalidNonce,
 
@@ -59,41 +99,171 @@ pub enum Error {
 
     #[error("execution {0}")]
     Execution(String),
+
+    #[error("multisig {0}")]
+    Multisig(String),
+
+    #[error("gas tank transaction {0:#x} reverted")]
+    Reverted(Felt),
+
+    #[error("invalid configuration {0}")]
+    Configuration(String),
+}
+
+impl Error {
+    /// Returns true when the relayers could not be locked because they were all in use
+    pub fn is_contention(&self) -> bool {
+        matches!(self, Self::Lock(lock::Error::LockUnavailable | lock::Error::AlreadyLocked))
+    }
 }
 
 #[derive(Clone)]
 pub struct RelayerManager {
     context: Context,
 
+    /// Fleet the relayers are taken from when the primary fleet fails
+    secondary: Option<Box<RelayerManager>>,
+    health: Arc<FleetHealth>,
+
     #[allow(dead_code)]
     services: Arc<TokioServiceManager<Context>>,
 }
 
 impl RelayerManager {
-    pub fn new(configuration: &RelayerManagerConfiguration) -> Self {
-        let context = Context::new(configuration.clone());
+    pub fn new(configuration: &RelayerManagerConfiguration) -> Result<Self, Error> {
+        Self::with_context(Context::new(configuration.clone())?)
+    }
 
+    // The fleets share the sender of the gas tank so that their transactions are never sent concurrently
+    fn with_context(context: Context) -> Result<Self, Error> {
+        let configuration = context.configuration.clone();
+        let secondary = match configuration.relayers.secondary_fleet() {
+            Some(relayers) => {
+                let secondary = Context::new(RelayerManagerConfiguration {
+                    relayers,
+                    ..configuration.clone()
+                })?;
+
+                Some(Box::new(Self::with_context(Context {
+                    fleet: Fleet::Secondary,
+                    gas_tank: context.gas_tank.clone(),
+                    ..secondary
+                })?))
+            },
+            None => None,
+        };
+
+        // Both fleets monitor the balances and the spend caps of their relayers, re-enabling the relayers whose
+        // caps are no longer exceeded, while the gas tank is only monitored and rebalanced by the primary fleet
         let mut services = TokioServiceManager::new(context.clone());
         services.spawn::<RelayerBalanceMonitoring>();
         services.spawn::<EnabledRelayersService>();
-        services.spawn::<GasTankBalanceMonitoring>();
+        services.spawn::<RelayerLockMonitoring>();
+        services.spawn::<SpendCapMonitoring>();
+        if context.fleet == Fleet::Primary {
+            services.spawn::<GasTankBalanceMonitoring>();
+        }
+
+        if matches!(configuration.relayers.lock, LockLayerConfiguration::Shared { .. }) {
+            services.spawn::<LockLayerHealthProbe>();
+        }
 
         // Start the rebalancing service if configured
         if configuration.relayers.rebalancing.has_configuration() {
             services.spawn::<RelayerRebalancingService>();
         }
 
-        Self {
+        if configuration.relayers.gas_tank_top_up.is_some() {
+            services.spawn::<GasTankTopUpService>();
+        }
+
+        if configuration.relayers.staking.is_some() {
+            services.spawn::<GasTankStakingService>();
+        }
+
+        if configuration.relayers.journal.is_some() {
+            services.spawn::<ExecutionJournalRecovery>();
+        }
+
+        if configuration.relayers.watchdog.is_some() {
+            services.spawn::<RelayerTransactionWatchdog>();
+        }
+
+        if configuration.relayers.treasury_history.is_some() {
+            services.spawn::<TreasurySnapshotService>();
+        }
+
+        if configuration.relayers.messaging.is_some() {
+            services.spawn::<RelayerEventsListener>();
+        }
+
+        Ok(Self {
             context,
+            secondary,
+            health: Arc::new(FleetHealth::default()),
             services: Arc::new(services),
-        }
+        })
     }
 
-    #[instrument(name = "lock_relayer", skip(self))]
+    /// Messaging layer on which the [`FleetEvent`] are published by [`RelayerFleets`]
+    pub fn events(&self) -> Messages<FleetEvent> {
+        self.context.events.messages()
+    }
+
+    /// Lock a relayer of the primary fleet, or of the secondary fleet when the primary one has no enabled
+    /// relayer or keeps failing
     pub async fn lock_relayer(&self) -> Result<LockedRelayer, Error> {
+        self.lock_relayer_for(RelayerSelection::default()).await
+    }
+
+    /// Same as [`lock_relayer`], only locking the relayers matching the given selection when some relayers are
+    /// dedicated to sponsors or reserved to deployments
+    #[instrument(name = "lock_relayer", skip(self))]
+    pub async fn lock_relayer_for(&self, selection: RelayerSelection) -> Result<LockedRelayer, Error> {
+        let Some(secondary) = &self.secondary else {
+            return self.lock_fleet_relayer(selection).await;
+        };
+
+        if !self.health.is_skipped() {
+            match self.lock_fleet_relayer(selection).await {
+                Ok(relayer) => {
+                    self.publish(self.health.record_success()).await;
+                    return Ok(relayer);
+                },
+                // All the relayers being busy is not a failure of the fleet, the secondary one only absorbs the load
+                Err(e) if e.is_contention() => debug!("Primary relayer fleet busy, using secondary fleet: {}", e),
+                Err(e) => {
+                    warn!("Primary relayer fleet failed, using secondary fleet: {}", e);
+                    self.publish(self.health.record_failure()).await;
+                },
+            }
+        }
+
+        metric!(counter[relayer_fleet_failover] = 1);
+        Box::pin(secondary.lock_relayer_for(selection)).await
+    }
+
+    async fn publish(&self, event: Option<FleetEvent>) {
+        if let Some(event) = event {
+            metric!(counter[relayer_fleet_event] = 1, event = format!("{:?}", event));
+            self.context.events.publish::<RelayerFleets>(event).await;
+        }
+    }
+
+    async fn lock_fleet_relayer(&self, selection: RelayerSelection) -> Result<LockedRelayer, Error> {
+        if !self.context.relayers_locks.is_available() {
+            return Err(lock::Error::Unreachable.into());
+        }
         self.check_enabled_relayers().await?;
 
-        let lock = log_if_error!(self.try_lock_relayer().await)?;
+        let lock = if self.context.configuration.relayers.is_isolated() {
+            log_if_error!(
+                self.try_lock_relayer_among(self.context.configuration.relayers.relayers_of(selection))
+                    .await
+            )?
+        } else {
+            log_if_error!(self.try_lock_relayer().await)?
+        };
         let relayer = log_if_error!(self.context.relayers.acquire_relayer(&lock.address))?;
         debug!(target: "Relayers", "lock relayer {}", relayer.address().to_fixed_hex_string());
 
@@ -113,24 +283,135 @@ impl RelayerManager {
         }
     }
 
+    // Lock one of the given relayers, trying them in a random order until one is available
+    async fn try_lock_relayer_among(&self, mut candidates: Vec<Felt>) -> Result<RelayerLock, Error> {
+        let now = Instant::now();
+        let timeout = self.context.configuration.relayers.lock.retry_timeout();
+
+        loop {
+            candidates.shuffle(&mut rng());
+
+            let mut error = Error::NoEnabledRelayer;
+            for address in &candidates {
+                match self.context.relayers_locks.lock_relayer_at(*address).await {
+                    Ok(lock) => return Ok(lock),
+                    Err(e) => error = e.into(),
+                }
+            }
+
+            if now.elapsed() > timeout {
+                metric!(counter[relayer_pool_exhausted] = 1);
+                return Err(error);
+            }
+            tokio::time::sleep(LOCK_RELAYER_AT_RETRY_INTERVAL).await;
+        }
+    }
+
+    /// Returns true if the given address is a relayer of either fleet
+    pub fn is_relayer(&self, address: Felt) -> bool {
+        self.context.configuration.relayers.addresses.contains(&address) || self.secondary.as_ref().is_some_and(|x| x.is_relayer(address))
+    }
+
+    /// Pick a relayer to which a transaction is assigned before it is executed, among the enabled relayers whose
+    /// balance is above the minimum. Relayers dedicated to sponsors or reserved to deployments are never assigned.
+    /// The relayers of the secondary fleet are assigned while the primary fleet is skipped or has no such relayer.
+    pub async fn assign_relayer(&self) -> Result<Felt, Error> {
+        if let Some(secondary) = self.secondary.as_deref().filter(|_| self.health.is_skipped()) {
+            return Box::pin(secondary.assign_relayer()).await;
+        }
+
+        let min_balance = self.context.configuration.relayers.min_relayer_balance;
+        let mut candidates = vec![];
+        for relayer in self.context.configuration.relayers.relayers_of(RelayerSelection::default()) {
+            let balance = self.context.relayers.get_relayer_balance(&relayer).await;
+            if self.context.relayers_locks.is_enabled(relayer).await && balance.is_none_or(|x| x > min_balance) {
+                candidates.push(relayer);
+            }
+        }
+
+        match (candidates.choose(&mut rng()), &self.secondary) {
+            (Some(relayer), _) => Ok(*relayer),
+            (None, Some(secondary)) => Box::pin(secondary.assign_relayer()).await,
+            (None, None) => Err(Error::NoEnabledRelayer),
+        }
+    }
+
+    /// Lock the relayer with the given address, waiting for it to be released while it is used
+    #[instrument(name = "lock_relayer_at", skip(self), fields(relayer = %address.to_hex_string()))]
+    pub async fn lock_relayer_at(&self, address: Felt) -> Result<LockedRelayer, Error> {
+        if !self.context.configuration.relayers.addresses.contains(&address) {
+            return match &self.secondary {
+                Some(secondary) if secondary.is_relayer(address) => Box::pin(secondary.lock_relayer_at(address)).await,
+                _ => Err(Error::InvalidRelayer),
+            };
+        }
+
+        let now = Instant::now();
+        let timeout = self.context.configuration.relayers.lock.retry_timeout();
+
+        let lock = loop {
+            match self.context.relayers_locks.lock_relayer_at(address).await {
+                Ok(lock) => break lock,
+                Err(e) if now.elapsed() > timeout => return Err(e.into()),
+                _ => tokio::time::sleep(LOCK_RELAYER_AT_RETRY_INTERVAL).await,
+            }
+        };
+
+        let relayer = log_if_error!(self.context.relayers.acquire_relayer(&lock.address))?;
+        debug!(target: "Relayers", "lock relayer {}", relayer.address().to_fixed_hex_string());
+
+        Ok(relayer.lock(lock))
+    }
+
+    // Returns the secondary fleet if the relayer belongs to it
+    fn secondary_fleet_of(&self, relayer: &LockedRelayer) -> Option<&RelayerManager> {
+        self.secondary
+            .as_deref()
+            .filter(|_| !self.context.configuration.relayers.addresses.contains(&relayer.address()))
+    }
+
     #[instrument(name = "lock_relayer", skip(self, relayer), fields(relayer = %relayer.address().to_hex_string()))]
     pub async fn release_relayer(&self, relayer: LockedRelayer) -> Result<(), Error> {
+        if let Some(secondary) = self.secondary_fleet_of(&relayer) {
+            return Box::pin(secondary.release_relayer(relayer)).await;
+        }
+
+        let spent = relayer.spent();
         let (relayer, lock) = relayer.unlock();
         debug!(target: "Relayers", "release relayer {}", relayer.address().to_fixed_hex_string());
 
         log_if_error!(self.context.relayers_locks.release_relayer(lock).await)?;
+        self.record_spend(lock.address, spent).await;
+        let _ = log_if_error!(self.context.journal.close(lock.address).await);
 
         Ok(())
     }
 
     #[instrument(name = "release_relayer_delayed", skip(self, relayer), fields(relayer = %relayer.address().to_hex_string()))]
     pub async fn release_relayer_delayed(&self, relayer: LockedRelayer, delay: u64) -> Result<(), Error> {
+        if let Some(secondary) = self.secondary_fleet_of(&relayer) {
+            return Box::pin(secondary.release_relayer_delayed(relayer, delay)).await;
+        }
+
+        let spent = relayer.spent();
         let (_, lock) = relayer.unlock();
         log_if_error!(self.context.relayers_locks.release_relayer_delayed(lock, delay).await)?;
+        self.record_spend(lock.address, spent).await;
+        let _ = log_if_error!(self.context.journal.close(lock.address).await);
 
         Ok(())
     }
 
+    // Record the amount spent by the relayer while it was locked, at the estimated fee of its transactions until the
+    // spend caps monitoring settles them with their actual fee
+    async fn record_spend(&self, relayer: Felt, amount: Felt) {
+        if amount == Felt::ZERO {
+            return;
+        }
+
+        let _ = log_if_error!(self.context.relayers_locks.record_spend(relayer, amount).await);
+    }
+
     async fn check_enabled_relayers(&self) -> Result<(), Error> {
         if self.context.relayers_locks.count_enabled_relayers().await > 0 {
             return Ok(());
@@ -139,8 +420,68 @@ impl RelayerManager {
         Err(Error::NoEnabledRelayer)
     }
 
+    /// Count the enabled relayers of both fleets
     pub async fn count_enabled_relayers(&self) -> usize {
-        self.context.relayers_locks.count_enabled_relayers().await
+        let secondary = match &self.secondary {
+            Some(secondary) => secondary.context.relayers_locks.count_enabled_relayers().await,
+            None => 0,
+        };
+
+        self.context.relayers_locks.count_enabled_relayers().await + secondary
+    }
+
+    /// Returns false while the relayers of the primary fleet are recovering from an outage, until enough consecutive
+    /// checks found them healthy, or while their lock layer is unreachable without fallback
+    pub fn is_available(&self) -> bool {
+        self.context.availability.is_available() && self.context.relayers_locks.is_available()
+    }
+
+    /// Latest availability transitions of the relayers of the primary fleet, the most recent last
+    pub fn availability_history(&self) -> Vec<AvailabilityTransition> {
+        self.context.availability.history()
+    }
+
+    /// Returns the lock state of the relayers of both fleets
+    pub async fn lock_statuses(&self) -> Result<Vec<RelayerLockStatus>, Error> {
+        let mut statuses = self.context.relayers_locks.lock_statuses().await?;
+        if let Some(secondary) = &self.secondary {
+            statuses.extend(secondary.context.relayers_locks.lock_statuses().await?);
+        }
+
+        Ok(statuses)
+    }
+
+    /// Take a snapshot of the balances of the gas tank and of the relayers of both fleets, all read at the
+    /// latest block
+    pub async fn accounting_snapshot(&self) -> Result<AccountingSnapshot, Error> {
+        let block_number = self
+            .context
+            .starknet
+            .fetch_block_number()
+            .await
+            .map_err(|e| Error::Execution(e.to_string()))?;
+
+        let mut snapshot = AccountingSnapshot::new(block_number, self.context.starknet.tokens().strk);
+        snapshot.record_gas_tank(&self.context).await?;
+        snapshot.record_relayers(&self.context).await?;
+        if let Some(secondary) = &self.secondary {
+            snapshot.record_relayers(&secondary.context).await?;
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Report the holdings of the gas tank valued in STRK and USD, with their evolution over the last days when
+    /// the treasury history is configured
+    pub async fn treasury_report(&self) -> Result<TreasuryReport, Error> {
+        treasury::report::treasury_report(&self.context)
+            .await
+            .map_err(|e| Error::Execution(e.to_string()))
+    }
+
+    /// Sender of the gas tank transactions, through which every transaction of the gas tank must go
+    pub fn gas_tank(&self) -> &GasTankSender {
+        &self.context.gas_tank
     }
 
     pub fn get_context(&self) -> &Context {
@@ -209,17 +550,33 @@ mod tests {
                     chain_id: ChainID::Sepolia,
                     timeout: 10,
                     fallbacks: vec![],
+                    local_estimation: None,
+                    endpoint_options: Default::default(),
+                    tokens: None,
                 },
                 supported_tokens: HashSet::from([Token::usdc(&ChainID::Sepolia).address]),
                 gas_tank: StarknetAccountConfiguration {
                     address: felt!("0x0"),
                     private_key: felt!("0x0"),
                 },
+                gas_tank_multisig: None,
                 relayers: RelayersConfiguration {
                     min_relayer_balance: Felt::ZERO,
                     private_key: felt!("0x0"),
                     addresses: vec![felt!("0x0")],
                     lock: LockLayerConfiguration::mock_with_timeout::<Lock>(Duration::from_secs(5)),
+                    max_in_flight_transactions: RelayersConfiguration::default_max_in_flight_transactions(),
+                    execution_concurrency_factor: RelayersConfiguration::default_execution_concurrency_factor(),
+                    spend_caps: Default::default(),
+                    secondary: None,
+                    gas_tank_top_up: None,
+                    staking: None,
+                    journal: None,
+                    watchdog: None,
+                    dedicated: vec![],
+                    deployment_relayers: vec![],
+                    treasury_history: None,
+                    messaging: None,
                     rebalancing: OptionalRebalancingConfiguration::initialize(None),
                 },
                 price: PriceConfiguration::mock::<MockPrice>(),
@@ -228,7 +585,7 @@ mod tests {
 
         #[tokio::test]
         async fn acquire_release_works_properly() {
-            let relayers = RelayerManager::new(&configuration());
+            let relayers = RelayerManager::new(&configuration()).unwrap();
 
             // Acquire relayer
             let relayer = relayers.lock_relayer().await.unwrap();
@@ -241,6 +598,281 @@ mod tests {
         }
     }
 
+    mod chaos_behaviors {
+        use std::collections::{BTreeSet, HashSet};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        use paymaster_prices::mock::MockPriceOracle;
+        use paymaster_prices::PriceConfiguration;
+        use paymaster_starknet::constants::Token;
+        use paymaster_starknet::{ChainID, Configuration as StarknetConfiguration, StarknetAccountConfiguration};
+        use starknet::core::types::Felt;
+        use starknet::macros::felt;
+
+        use crate::failover::FAILOVER_THRESHOLD;
+        use crate::lock::chaos::{ChaosConfiguration, ChaosLockLayer};
+        use crate::lock::mock::MockLockLayer;
+        use crate::lock::LockLayerConfiguration;
+        use crate::rebalancing::{OptionalRebalancingConfiguration, RelayerManagerConfiguration};
+        use crate::{DedicatedRelayersConfiguration, Error, RelayerManager, RelayerSelection, RelayersConfiguration};
+
+        #[derive(Debug)]
+        pub struct MockPrice;
+
+        impl MockPriceOracle for MockPrice {
+            fn new() -> Self {
+                Self
+            }
+        }
+
+        fn relayers(addresses: &[Felt], layer: Arc<ChaosLockLayer>) -> RelayersConfiguration {
+            RelayersConfiguration {
+                min_relayer_balance: Felt::ZERO,
+                private_key: felt!("0x1"),
+                addresses: addresses.to_vec(),
+                lock: LockLayerConfiguration::mock_with_layer(layer, Duration::from_millis(200)),
+                max_in_flight_transactions: RelayersConfiguration::default_max_in_flight_transactions(),
+                execution_concurrency_factor: RelayersConfiguration::default_execution_concurrency_factor(),
+                spend_caps: Default::default(),
+                secondary: None,
+                gas_tank_top_up: None,
+                staking: None,
+                journal: None,
+                watchdog: None,
+                dedicated: vec![],
+                deployment_relayers: vec![],
+                treasury_history: None,
+                messaging: None,
+                rebalancing: OptionalRebalancingConfiguration::initialize(None),
+            }
+        }
+
+        fn configuration(relayers: RelayersConfiguration) -> RelayerManagerConfiguration {
+            RelayerManagerConfiguration {
+                starknet: StarknetConfiguration {
+                    endpoint: "https://dummy".to_string(),
+                    chain_id: ChainID::Sepolia,
+                    timeout: 10,
+                    fallbacks: vec![],
+                    local_estimation: None,
+                    endpoint_options: Default::default(),
+                    tokens: None,
+                },
+                supported_tokens: HashSet::from([Token::usdc(&ChainID::Sepolia).address]),
+                gas_tank: StarknetAccountConfiguration {
+                    address: felt!("0x100"),
+                    private_key: felt!("0x1"),
+                },
+                gas_tank_multisig: None,
+                relayers,
+                price: PriceConfiguration::mock::<MockPrice>(),
+            }
+        }
+
+        #[tokio::test]
+        async fn injected_lock_failures_are_retried() {
+            let addresses = [felt!("0x1")];
+            let layer = Arc::new(ChaosLockLayer::with_faults(
+                &addresses,
+                ChaosConfiguration {
+                    latency: Duration::from_millis(1),
+                    lock_failure_rate: 0.5,
+                    seed: 42,
+                    ..Default::default()
+                },
+            ));
+            let manager = RelayerManager::new(&configuration(relayers(&addresses, layer.clone()))).unwrap();
+
+            let relayer = manager.lock_relayer().await.unwrap();
+            assert_eq!(layer.report().await.held, BTreeSet::from([felt!("0x1")]));
+
+            manager.release_relayer(relayer).await.unwrap();
+            assert!(layer.report().await.held.is_empty());
+        }
+
+        #[tokio::test]
+        async fn dedicated_relayers_only_execute_their_sponsors() {
+            let addresses = [felt!("0x1"), felt!("0x2")];
+            let layer = Arc::new(ChaosLockLayer::with_faults(&addresses, ChaosConfiguration::default()));
+            let mut relayers = relayers(&addresses, layer.clone());
+            relayers.dedicated = vec![DedicatedRelayersConfiguration {
+                sponsors: vec![felt!("0xa")],
+                addresses: vec![felt!("0x2")],
+            }];
+            let manager = RelayerManager::new(&configuration(relayers)).unwrap();
+
+            let sponsored = manager
+                .lock_relayer_for(RelayerSelection::sponsored(felt!("0xa")))
+                .await
+                .unwrap();
+            assert_eq!(sponsored.address(), felt!("0x2"));
+
+            // The dedicated relayer is busy, the sponsor cannot take the shared one
+            assert!(manager
+                .lock_relayer_for(RelayerSelection::sponsored(felt!("0xa")))
+                .await
+                .is_err());
+
+            let shared = manager
+                .lock_relayer_for(RelayerSelection::sponsored(felt!("0xb")))
+                .await
+                .unwrap();
+            assert_eq!(shared.address(), felt!("0x1"));
+            assert_eq!(manager.assign_relayer().await.unwrap(), felt!("0x1"));
+
+            manager.release_relayer(sponsored).await.unwrap();
+            manager.release_relayer(shared).await.unwrap();
+            assert!(layer.report().await.held.is_empty());
+        }
+
+        #[tokio::test]
+        async fn only_enabled_relayers_are_assigned() {
+            let addresses = [felt!("0x1"), felt!("0x2")];
+            let layer = Arc::new(ChaosLockLayer::with_faults(&addresses, ChaosConfiguration::default()));
+            let manager = RelayerManager::new(&configuration(relayers(&addresses, layer.clone()))).unwrap();
+
+            layer.set_enabled_relayers(&HashSet::from([felt!("0x2")])).await;
+            for _ in 0..10 {
+                assert_eq!(manager.assign_relayer().await.unwrap(), felt!("0x2"));
+            }
+
+            layer.set_enabled_relayers(&HashSet::new()).await;
+            assert!(matches!(manager.assign_relayer().await, Err(Error::NoEnabledRelayer)));
+        }
+
+        #[tokio::test]
+        async fn deployment_relayers_are_reserved_to_deployments() {
+            let addresses = [felt!("0x1"), felt!("0x2")];
+            let layer = Arc::new(ChaosLockLayer::with_faults(&addresses, ChaosConfiguration::default()));
+            let mut relayers = relayers(&addresses, layer.clone());
+            relayers.deployment_relayers = vec![felt!("0x2")];
+            let manager = RelayerManager::new(&configuration(relayers)).unwrap();
+
+            let invoke = manager.lock_relayer().await.unwrap();
+            assert_eq!(invoke.address(), felt!("0x1"));
+            assert!(manager.lock_relayer().await.is_err());
+
+            let deployment = manager
+                .lock_relayer_for(RelayerSelection::default().with_deployment(true))
+                .await
+                .unwrap();
+            assert_eq!(deployment.address(), felt!("0x2"));
+
+            manager.release_relayer(invoke).await.unwrap();
+            manager.release_relayer(deployment).await.unwrap();
+            assert!(layer.report().await.held.is_empty());
+        }
+
+        #[tokio::test]
+        async fn concurrent_executions_do_not_leak_locks() {
+            let addresses = [felt!("0x1"), felt!("0x2"), felt!("0x3")];
+            let layer = Arc::new(ChaosLockLayer::with_faults(
+                &addresses,
+                ChaosConfiguration {
+                    latency: Duration::from_millis(2),
+                    lock_failure_rate: 0.2,
+                    seed: 7,
+                    ..Default::default()
+                },
+            ));
+            let manager = RelayerManager::new(&configuration(relayers(&addresses, layer.clone()))).unwrap();
+
+            let executions = (0..12).map(|_| {
+                let manager = manager.clone();
+                async move {
+                    if let Ok(relayer) = manager.lock_relayer().await {
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                        manager.release_relayer(relayer).await.unwrap();
+                    }
+                }
+            });
+            futures::future::join_all(executions).await;
+
+            let report = layer.report().await;
+            assert!(report.granted > 0);
+            assert!(report.held.is_empty());
+            assert_eq!(report.unexpected_releases, 0);
+        }
+
+        #[tokio::test]
+        async fn lost_release_leaves_relayer_locked() {
+            let addresses = [felt!("0x1")];
+            let layer = Arc::new(ChaosLockLayer::with_faults(
+                &addresses,
+                ChaosConfiguration {
+                    lost_release_rate: 1.0,
+                    ..Default::default()
+                },
+            ));
+            let manager = RelayerManager::new(&configuration(relayers(&addresses, layer.clone()))).unwrap();
+
+            let relayer = manager.lock_relayer().await.unwrap();
+            manager.release_relayer(relayer).await.unwrap();
+
+            assert!(manager.lock_relayer().await.is_err());
+
+            let report = layer.report().await;
+            assert_eq!(report.lost_releases, 1);
+            assert_eq!(report.held, BTreeSet::from([felt!("0x1")]));
+        }
+
+        #[tokio::test]
+        async fn failing_primary_fleet_fails_over_to_secondary() {
+            let primary_layer = Arc::new(ChaosLockLayer::with_faults(
+                &[felt!("0x1")],
+                ChaosConfiguration {
+                    lock_failure_rate: 1.0,
+                    ..Default::default()
+                },
+            ));
+            let secondary_layer = Arc::new(ChaosLockLayer::with_faults(&[felt!("0x2")], ChaosConfiguration::default()));
+
+            let mut relayers = relayers(&[felt!("0x1")], primary_layer.clone());
+            relayers.secondary = Some(Box::new(self::relayers(&[felt!("0x2")], secondary_layer.clone())));
+            let manager = RelayerManager::new(&configuration(relayers)).unwrap();
+
+            let relayer = manager.lock_relayer().await.unwrap();
+            assert_eq!(relayer.address(), felt!("0x2"));
+
+            manager.release_relayer(relayer).await.unwrap();
+            assert!(secondary_layer.report().await.held.is_empty());
+            assert_eq!(primary_layer.report().await.granted, 0);
+        }
+
+        #[tokio::test]
+        async fn busy_primary_fleet_does_not_fail_over() {
+            let primary_layer = Arc::new(ChaosLockLayer::with_faults(&[felt!("0x1")], ChaosConfiguration::default()));
+            let secondary_addresses = [felt!("0x2"), felt!("0x3"), felt!("0x4")];
+            let secondary_layer = Arc::new(ChaosLockLayer::with_faults(&secondary_addresses, ChaosConfiguration::default()));
+
+            let mut relayers = relayers(&[felt!("0x1")], primary_layer.clone());
+            relayers.secondary = Some(Box::new(self::relayers(&secondary_addresses, secondary_layer.clone())));
+            let manager = RelayerManager::new(&configuration(relayers)).unwrap();
+
+            // The only relayer of the primary fleet is busy, the executions overflow to the secondary fleet
+            let busy = manager.lock_relayer().await.unwrap();
+            assert_eq!(busy.address(), felt!("0x1"));
+
+            let mut overflow = vec![];
+            for _ in 0..FAILOVER_THRESHOLD {
+                let relayer = manager.lock_relayer().await.unwrap();
+                assert!(secondary_addresses.contains(&relayer.address()));
+                overflow.push(relayer);
+            }
+            assert!(!manager.health.is_skipped());
+
+            manager.release_relayer(busy).await.unwrap();
+            for relayer in overflow {
+                manager.release_relayer(relayer).await.unwrap();
+            }
+
+            let relayer = manager.lock_relayer().await.unwrap();
+            assert_eq!(relayer.address(), felt!("0x1"));
+            manager.release_relayer(relayer).await.unwrap();
+        }
+    }
+
     /*mod services {
         use std::collections::HashSet;
         use std::sync::Arc;
@@ -288,7 +920,7 @@ mod tests {
 
         #[tokio::test]
         async fn service_should_disable_relayer() {
-            let relayers = RelayerManager::new(configuration());
+            let relayers = RelayerManager::new(configuration()).unwrap();
 
             while let Ok(_) = relayers.check_available_relayers().await {
                 time::sleep(Duration::from_secs(1)).await
@@ -0,0 +1,203 @@
+use std::collections::{BTreeSet, HashSet};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use starknet::core::types::Felt;
+use tokio::sync::Mutex;
+
+use crate::lock::mock::MockLockLayer;
+use crate::lock::{instance_id, Error, RelayerLock, RelayerLockStatus};
+
+/// Faults injected by the [`ChaosLockLayer`]. Faults are drawn from a seeded generator so that a
+/// given configuration always produces the same sequence of faults.
+#[derive(Debug, Clone)]
+pub struct ChaosConfiguration {
+    /// Latency added to every lock and release
+    pub latency: Duration,
+
+    /// Probability for a lock attempt to fail even though a relayer is available
+    pub lock_failure_rate: f64,
+
+    /// Probability for a release to be acknowledged without actually freeing the relayer
+    pub lost_release_rate: f64,
+
+    pub seed: u64,
+}
+
+impl Default for ChaosConfiguration {
+    fn default() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            lock_failure_rate: 0.0,
+            lost_release_rate: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+/// What happened on the lock layer, used to assert on lock handling
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChaosReport {
+    /// Number of locks granted
+    pub granted: usize,
+
+    /// Number of lock attempts failed on purpose
+    pub injected_failures: usize,
+
+    /// Number of releases dropped on purpose
+    pub lost_releases: usize,
+
+    /// Number of releases of a relayer which was not locked, which denotes a double release
+    pub unexpected_releases: usize,
+
+    /// Relayers still locked
+    pub held: BTreeSet<Felt>,
+}
+
+#[derive(Debug)]
+struct ChaosState {
+    rng: StdRng,
+    enabled: BTreeSet<Felt>,
+    disabled: BTreeSet<Felt>,
+    report: ChaosReport,
+}
+
+/// In-memory lock layer injecting latency, lock failures and lost releases. Relayers are never locked
+/// twice, so every inconsistency showing up in the [`ChaosReport`] comes from the caller.
+#[derive(Debug)]
+pub struct ChaosLockLayer {
+    configuration: ChaosConfiguration,
+    state: Mutex<ChaosState>,
+}
+
+impl ChaosLockLayer {
+    pub fn with_faults(relayers: &[Felt], configuration: ChaosConfiguration) -> Self {
+        Self {
+            state: Mutex::new(ChaosState {
+                rng: StdRng::seed_from_u64(configuration.seed),
+                enabled: relayers.iter().cloned().collect(),
+                disabled: BTreeSet::new(),
+                report: ChaosReport::default(),
+            }),
+            configuration,
+        }
+    }
+
+    /// Returns what happened on the lock layer so far
+    pub async fn report(&self) -> ChaosReport {
+        self.state.lock().await.report.clone()
+    }
+
+    async fn release(&self, lock: RelayerLock) {
+        tokio::time::sleep(self.configuration.latency).await;
+
+        let mut state = self.state.lock().await;
+        if state.rng.random_bool(self.configuration.lost_release_rate) {
+            state.report.lost_releases += 1;
+            return;
+        }
+
+        if !state.report.held.remove(&lock.address) {
+            state.report.unexpected_releases += 1;
+        }
+    }
+}
+
+#[async_trait]
+impl MockLockLayer for ChaosLockLayer {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self::with_faults(&[Felt::ZERO], ChaosConfiguration::default())
+    }
+
+    async fn count_enabled_relayers(&self) -> usize {
+        let state = self.state.lock().await;
+        state.enabled.difference(&state.disabled).count()
+    }
+
+    async fn is_enabled(&self, relayer: Felt) -> bool {
+        let state = self.state.lock().await;
+        state.enabled.contains(&relayer) && !state.disabled.contains(&relayer)
+    }
+
+    async fn set_enabled_relayers(&self, relayers: &HashSet<Felt>) {
+        self.state.lock().await.enabled = relayers.iter().cloned().collect();
+    }
+
+    async fn set_disabled_relayers(&self, relayers: &HashSet<Felt>) -> Result<(), Error> {
+        self.state.lock().await.disabled = relayers.iter().cloned().collect();
+        Ok(())
+    }
+
+    async fn lock_relayer(&self) -> Result<RelayerLock, Error> {
+        tokio::time::sleep(self.configuration.latency).await;
+
+        let mut state = self.state.lock().await;
+        if state.rng.random_bool(self.configuration.lock_failure_rate) {
+            state.report.injected_failures += 1;
+            return Err(Error::LockUnavailable);
+        }
+
+        let relayer = state
+            .enabled
+            .iter()
+            .find(|x| !state.report.held.contains(x) && !state.disabled.contains(x))
+            .cloned()
+            .ok_or(Error::LockUnavailable)?;
+
+        state.report.held.insert(relayer);
+        state.report.granted += 1;
+
+        Ok(RelayerLock::new(relayer, None, Duration::from_secs(180)))
+    }
+
+    async fn lock_relayer_at(&self, address: Felt) -> Result<RelayerLock, Error> {
+        tokio::time::sleep(self.configuration.latency).await;
+
+        let mut state = self.state.lock().await;
+        if state.rng.random_bool(self.configuration.lock_failure_rate) {
+            state.report.injected_failures += 1;
+            return Err(Error::LockUnavailable);
+        }
+
+        if !state.enabled.contains(&address) || state.disabled.contains(&address) || !state.report.held.insert(address) {
+            return Err(Error::LockUnavailable);
+        }
+        state.report.granted += 1;
+
+        Ok(RelayerLock::new(address, None, Duration::from_secs(180)))
+    }
+
+    async fn release_relayer(&self, lock: RelayerLock) -> Result<(), Error> {
+        self.release(lock).await;
+        Ok(())
+    }
+
+    async fn release_relayer_delayed(&self, lock: RelayerLock, _delay: u64) -> Result<(), Error> {
+        self.release(lock).await;
+        Ok(())
+    }
+
+    async fn lock_statuses(&self) -> Result<Vec<RelayerLockStatus>, Error> {
+        let state = self.state.lock().await;
+        let relayers: BTreeSet<Felt> = state.enabled.union(&state.report.held).cloned().collect();
+
+        Ok(relayers
+            .into_iter()
+            .map(|address| {
+                let locked = state.report.held.contains(&address);
+                RelayerLockStatus {
+                    address,
+                    locked,
+                    holder: locked.then(|| instance_id().to_string()),
+                    age: None,
+                    cached_nonce: None,
+                }
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use deadpool_redis::redis::cmd;
+use deadpool_redis::Pool;
+
+use crate::lock::{instance_id, Error, LockLayerConfiguration};
+
+// Takes the lease when it is free and extends it when the instance already holds it
+const ACQUIRE: &str = r#"
+local holder = redis.call('GET', KEYS[1])
+if holder == ARGV[1] then
+    redis.call('PEXPIRE', KEYS[1], ARGV[2])
+    return 1
+end
+if not holder then
+    redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2])
+    return 1
+end
+return 0
+"#;
+
+// Frees the lease only when the instance holds it
+const RELEASE: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+/// Lease elected among the instances sharing the Redis of the lock layer, so that the work which must not be
+/// duplicated (background services sending transactions, transactions of the gas tank) runs on a single instance
+/// at a time. With a lock layer local to the instance, the instance always holds the lease.
+#[derive(Clone)]
+pub struct LeaderLock {
+    redis: Option<Pool>,
+
+    key: String,
+    lease: Duration,
+}
+
+impl LeaderLock {
+    /// Returns the lock of the given name, held for `lease` once acquired unless it is extended
+    pub fn new(configuration: &LockLayerConfiguration, name: &str, lease: Duration) -> Result<Self, Error> {
+        let redis = match configuration {
+            LockLayerConfiguration::Shared { redis, .. } => Some(redis.create_pool()?),
+            _ => None,
+        };
+
+        Ok(Self::with_pool(redis, name, lease))
+    }
+
+    fn with_pool(redis: Option<Pool>, name: &str, lease: Duration) -> Self {
+        Self {
+            redis,
+            key: format!("leader-lock:{}", name),
+            lease,
+        }
+    }
+
+    /// Returns true if the instance holds the lease, taking it when it is free or extending it when the instance
+    /// already holds it. Returns false when Redis is unreachable since another instance may hold it.
+    pub async fn acquire(&self) -> bool {
+        let Some(redis) = &self.redis else { return true };
+
+        let Ok(mut connection) = redis.get().await else { return false };
+        let acquired: Result<u8, _> = cmd("EVAL")
+            .arg(ACQUIRE)
+            .arg(1)
+            .arg(&self.key)
+            .arg(instance_id())
+            .arg(self.lease.as_millis() as u64)
+            .query_async(&mut connection)
+            .await;
+
+        acquired.is_ok_and(|x| x == 1)
+    }
+
+    /// Wait until the instance holds the lease, fails once `timeout` elapsed
+    pub async fn acquire_within(&self, timeout: Duration) -> Result<(), Error> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        while !self.acquire().await {
+            if tokio::time::Instant::now() >= deadline {
+                return Err(Error::LockUnavailable);
+            }
+
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+
+        Ok(())
+    }
+
+    /// Free the lease held by the instance, if any
+    pub async fn release(&self) {
+        let Some(redis) = &self.redis else { return };
+
+        if let Ok(mut connection) = redis.get().await {
+            let _: Result<u8, _> = cmd("EVAL")
+                .arg(RELEASE)
+                .arg(1)
+                .arg(&self.key)
+                .arg(instance_id())
+                .query_async(&mut connection)
+                .await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use deadpool_redis::redis::AsyncCommands;
+    use deadpool_redis::{Config, Pool, Runtime};
+    use testcontainers::core::{IntoContainerPort, WaitFor};
+    use testcontainers::runners::AsyncRunner;
+    use testcontainers::{ContainerAsync, GenericImage};
+
+    use crate::lock::leader::LeaderLock;
+    use crate::lock::LockLayerConfiguration;
+
+    type RedisContainer = ContainerAsync<GenericImage>;
+
+    async fn redis_container() -> RedisContainer {
+        GenericImage::new("redis", "7")
+            .with_exposed_port(6379.tcp())
+            .with_wait_for(WaitFor::message_on_stdout("Ready to accept connections"))
+            .start()
+            .await
+            .unwrap()
+    }
+
+    async fn redis_pool(container: &RedisContainer) -> Pool {
+        let port = container.get_host_port_ipv4(6379).await.unwrap();
+        let redis_url = format!("redis://127.0.0.1:{}", port);
+
+        Config::from_url(redis_url).create_pool(Some(Runtime::Tokio1)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn local_lock_layer_always_holds_the_lease() {
+        let lock = LeaderLock::new(
+            &LockLayerConfiguration::Seggregated {
+                retry_timeout: Duration::from_secs(5),
+            },
+            "service",
+            Duration::from_secs(10),
+        )
+        .unwrap();
+
+        assert!(lock.acquire().await);
+        assert!(lock.acquire().await);
+    }
+
+    #[tokio::test]
+    async fn lease_is_held_by_a_single_instance() {
+        let container = redis_container().await;
+        let pool = redis_pool(&container).await;
+
+        let lock = LeaderLock::with_pool(Some(pool.clone()), "service", Duration::from_secs(10));
+        assert!(lock.acquire().await);
+        assert!(lock.acquire().await);
+
+        // Another instance holds the lease
+        let mut connection = pool.get().await.unwrap();
+        let _: () = connection.set("leader-lock:other", "another-instance").await.unwrap();
+        let other = LeaderLock::with_pool(Some(pool.clone()), "other", Duration::from_secs(10));
+        assert!(!other.acquire().await);
+        assert!(other.acquire_within(Duration::from_millis(500)).await.is_err());
+
+        // Releasing a lease held by another instance does nothing
+        other.release().await;
+        assert!(!other.acquire().await);
+
+        lock.release().await;
+        let holder: Option<String> = connection.get("leader-lock:service").await.unwrap();
+        assert!(holder.is_none());
+    }
+}
@@ -4,7 +4,8 @@ use std::fmt::Debug;
 use async_trait::async_trait;
 use starknet::core::types::Felt;
 
-use crate::lock::{Error, RelayerLock};
+use crate::lock::{Error, RelayerLock, RelayerLockStatus};
+use crate::spend::SpendTotals;
 
 #[async_trait]
 pub trait MockLockLayer: 'static + Debug + Send + Sync {
@@ -15,16 +16,40 @@ pub trait MockLockLayer: 'static + Debug + Send + Sync {
     async fn count_enabled_relayers(&self) -> usize {
         unimplemented!()
     }
+    async fn is_enabled(&self, _relayer: Felt) -> bool {
+        true
+    }
     async fn set_enabled_relayers(&self, _relayers: &HashSet<Felt>) {
         unimplemented!()
     }
+    async fn set_disabled_relayers(&self, _relayers: &HashSet<Felt>) -> Result<(), Error> {
+        Ok(())
+    }
     async fn lock_relayer(&self) -> Result<RelayerLock, Error> {
         unimplemented!()
     }
+    async fn lock_relayer_at(&self, _address: Felt) -> Result<RelayerLock, Error> {
+        unimplemented!()
+    }
     async fn release_relayer(&self, _lock: RelayerLock) -> Result<(), Error> {
         unimplemented!()
     }
     async fn release_relayer_delayed(&self, _lock: RelayerLock, _delay: u64) -> Result<(), Error> {
         unimplemented!()
     }
+    async fn record_spend(&self, _relayer: Felt, _amount: Felt) -> Result<(), Error> {
+        Ok(())
+    }
+    async fn correct_spend(&self, _relayer: Felt, _estimated: Felt, _actual: Felt) -> Result<(), Error> {
+        Ok(())
+    }
+    async fn spent(&self, _relayer: Option<Felt>) -> Result<SpendTotals, Error> {
+        Ok(SpendTotals::default())
+    }
+    async fn lock_statuses(&self) -> Result<Vec<RelayerLockStatus>, Error> {
+        Ok(vec![])
+    }
+    async fn recover_relayer(&self, _address: Felt, _holder: &str) -> Result<(), Error> {
+        Ok(())
+    }
 }
@@ -1,21 +1,28 @@
 use std::collections::HashSet;
+use std::sync::OnceLock;
 use std::time::{Duration, Instant};
 
 use deadpool_redis::redis::RedisError;
-use deadpool_redis::PoolError;
+use deadpool_redis::{CreatePoolError, PoolError};
+use paymaster_common::validation::{Validate, ValidationReport};
 use paymaster_common::{measure_duration, metric};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
 use starknet::core::types::Felt;
 use thiserror::Error;
 
 use crate::lock::seggregated::SeggregatedLockLayer;
 use crate::lock::shared::{RedisParameters, SharedLockLayer};
 use crate::rebalancing::RelayerManagerConfiguration;
+use crate::spend::{FleetSpend, SpendTotals};
 
+#[cfg(feature = "testing")]
+pub mod chaos;
 #[cfg(feature = "testing")]
 pub mod mock;
 
+pub mod leader;
 pub mod seggregated;
 pub mod shared;
 
@@ -27,11 +34,42 @@ pub enum Error {
     #[error(transparent)]
     Connection(#[from] PoolError),
 
+    #[error(transparent)]
+    Pool(#[from] CreatePoolError),
+
     #[error("already locked")]
     AlreadyLocked,
 
     #[error("lock is unavailable")]
     LockUnavailable,
+
+    #[error("lock layer is unreachable")]
+    Unreachable,
+}
+
+/// Identifier of this instance, recorded as the holder of the locks it takes
+pub fn instance_id() -> &'static str {
+    static INSTANCE_ID: OnceLock<String> = OnceLock::new();
+    INSTANCE_ID.get_or_init(|| {
+        let host = std::env::var("HOSTNAME").unwrap_or("paymaster".to_string());
+        format!("{}-{:08x}", host, rand::random::<u32>())
+    })
+}
+
+/// Lock state of a relayer as seen by the lock layer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayerLockStatus {
+    pub address: Felt,
+    pub locked: bool,
+
+    /// Instance holding the lock. Not set for a relayer cooling down after a delayed release
+    pub holder: Option<String>,
+
+    /// Time elapsed since the lock was taken
+    pub age: Option<Duration>,
+
+    /// Nonce cached when the relayer was last released
+    pub cached_nonce: Option<Felt>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -76,9 +114,23 @@ pub enum LockLayerConfiguration {
         #[serde_as(as = "serde_with::DurationSeconds")]
         retry_timeout: Duration,
         redis: RedisParameters,
+
+        /// Local locks used while Redis is unreachable, the locks are refused when not set
+        #[serde(default)]
+        fallback: Option<LockFallbackConfiguration>,
     },
 }
 
+/// Relayers locked in process by the instance while the Redis of the shared lock layer is unreachable, so that a
+/// single instance deployment keeps sponsoring. They must be reserved to the instance: no other instance may list
+/// them, as their locks are no longer coordinated.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockFallbackConfiguration {
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub addresses: Vec<Felt>,
+}
+
 #[cfg(feature = "testing")]
 impl LockLayerConfiguration {
     pub fn mock<T: mock::MockLockLayer>() -> Self {
@@ -86,10 +138,12 @@ impl LockLayerConfiguration {
     }
 
     pub fn mock_with_timeout<T: mock::MockLockLayer>(retry_timeout: Duration) -> Self {
-        Self::Mock {
-            retry_timeout,
-            lock_layer: std::sync::Arc::new(T::new()),
-        }
+        Self::mock_with_layer(std::sync::Arc::new(T::new()), retry_timeout)
+    }
+
+    /// Use the given lock layer, which lets the caller keep a handle on it to inspect its state
+    pub fn mock_with_layer(lock_layer: std::sync::Arc<dyn mock::MockLockLayer>, retry_timeout: Duration) -> Self {
+        Self::Mock { retry_timeout, lock_layer }
     }
 }
 
@@ -104,6 +158,21 @@ impl LockLayerConfiguration {
     }
 }
 
+impl Validate for LockLayerConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        if let Self::Shared { redis, fallback, .. } = self {
+            report.field("redis", redis);
+            if let Some(fallback) = fallback {
+                report.ensure(
+                    !fallback.addresses.is_empty(),
+                    "fallback.addresses",
+                    "At least one relayer address must be configured",
+                );
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum LockLayer {
     #[cfg(feature = "testing")]
@@ -114,13 +183,13 @@ pub enum LockLayer {
 }
 
 impl LockLayer {
-    pub fn new(configuration: &RelayerManagerConfiguration) -> Self {
+    pub fn new(configuration: &RelayerManagerConfiguration) -> Result<Self, Error> {
         match &configuration.relayers.lock {
-            LockLayerConfiguration::Shared { redis, .. } => LockLayer::Shared(SharedLockLayer::new(configuration, &redis)),
-            LockLayerConfiguration::Seggregated { .. } => LockLayer::Seggregated(SeggregatedLockLayer::new(configuration)),
+            LockLayerConfiguration::Shared { redis, fallback, .. } => Ok(LockLayer::Shared(SharedLockLayer::new(configuration, redis, fallback.as_ref())?)),
+            LockLayerConfiguration::Seggregated { .. } => Ok(LockLayer::Seggregated(SeggregatedLockLayer::new(configuration))),
 
             #[cfg(feature = "testing")]
-            LockLayerConfiguration::Mock { lock_layer, .. } => LockLayer::Mock(lock_layer.clone()),
+            LockLayerConfiguration::Mock { lock_layer, .. } => Ok(LockLayer::Mock(lock_layer.clone())),
         }
     }
 
@@ -129,6 +198,14 @@ impl LockLayer {
         Self::Mock(std::sync::Arc::new(I::new()))
     }
 
+    /// Returns false while the lock layer cannot lock relayers, its backend being unreachable without fallback
+    pub fn is_available(&self) -> bool {
+        match self {
+            Self::Shared(x) => x.is_healthy() || x.has_fallback(),
+            _ => true,
+        }
+    }
+
     pub async fn count_enabled_relayers(&self) -> usize {
         match self {
             #[cfg(feature = "testing")]
@@ -138,6 +215,16 @@ impl LockLayer {
         }
     }
 
+    /// Returns true if the relayer is enabled given its balance and is not disabled by its spend caps
+    pub async fn is_enabled(&self, relayer: Felt) -> bool {
+        match self {
+            #[cfg(feature = "testing")]
+            Self::Mock(x) => x.is_enabled(relayer).await,
+            Self::Shared(x) => x.is_enabled(relayer).await,
+            Self::Seggregated(x) => x.is_enabled(relayer).await,
+        }
+    }
+
     pub async fn set_enabled_relayers(&self, relayers: &HashSet<Felt>) {
         match self {
             #[cfg(feature = "testing")]
@@ -147,6 +234,86 @@ impl LockLayer {
         }
     }
 
+    /// Disable the given relayers because they exceeded their spend caps, re-enabling the other ones. This is
+    /// independent from the relayers enabled given their balance, see [`LockLayer::set_enabled_relayers`].
+    pub async fn set_disabled_relayers(&self, relayers: &HashSet<Felt>) -> Result<(), Error> {
+        match self {
+            #[cfg(feature = "testing")]
+            Self::Mock(x) => x.set_disabled_relayers(relayers).await,
+            Self::Shared(x) => x.set_disabled_relayers(relayers).await,
+            Self::Seggregated(x) => x.set_disabled_relayers(relayers).await,
+        }
+    }
+
+    /// Record that the given relayer spent `amount` of STRK (in FRI)
+    pub async fn record_spend(&self, relayer: Felt, amount: Felt) -> Result<(), Error> {
+        match self {
+            #[cfg(feature = "testing")]
+            Self::Mock(x) => x.record_spend(relayer, amount).await,
+            Self::Shared(x) => x.record_spend(relayer, amount).await,
+            Self::Seggregated(x) => x.record_spend(relayer, amount).await,
+        }
+    }
+
+    /// Replace the `estimated` fee recorded for a transaction of the given relayer by the `actual` fee it paid
+    pub async fn correct_spend(&self, relayer: Felt, estimated: Felt, actual: Felt) -> Result<(), Error> {
+        match self {
+            #[cfg(feature = "testing")]
+            Self::Mock(x) => x.correct_spend(relayer, estimated, actual).await,
+            Self::Shared(x) => x.correct_spend(relayer, estimated, actual).await,
+            Self::Seggregated(x) => x.correct_spend(relayer, estimated, actual).await,
+        }
+    }
+
+    /// Returns the amount of STRK spent during the current windows by the whole fleet and by each of the given
+    /// relayers
+    pub async fn fleet_spent(&self, relayers: &[Felt]) -> Result<FleetSpend, Error> {
+        if let Self::Shared(x) = self {
+            return x.fleet_spent(relayers).await;
+        }
+
+        let mut spend = FleetSpend {
+            fleet: self.spent(None).await?,
+            ..Default::default()
+        };
+        for relayer in relayers {
+            spend.relayers.insert(*relayer, self.spent(Some(*relayer)).await?);
+        }
+
+        Ok(spend)
+    }
+
+    /// Returns the amount of STRK spent during the current windows by the given relayer, or by the whole fleet
+    /// when no relayer is given
+    pub async fn spent(&self, relayer: Option<Felt>) -> Result<SpendTotals, Error> {
+        match self {
+            #[cfg(feature = "testing")]
+            Self::Mock(x) => x.spent(relayer).await,
+            Self::Shared(x) => x.spent(relayer).await,
+            Self::Seggregated(x) => x.spent(relayer).await,
+        }
+    }
+
+    /// Returns the lock state of every relayer of the fleet
+    pub async fn lock_statuses(&self) -> Result<Vec<RelayerLockStatus>, Error> {
+        match self {
+            #[cfg(feature = "testing")]
+            Self::Mock(x) => x.lock_statuses().await,
+            Self::Shared(x) => x.lock_statuses().await,
+            Self::Seggregated(x) => x.lock_statuses().await,
+        }
+    }
+
+    /// Recover the relayer after the instance `holder` stopped while executing one of its transactions
+    pub async fn recover_relayer(&self, address: Felt, holder: &str) -> Result<(), Error> {
+        match self {
+            #[cfg(feature = "testing")]
+            Self::Mock(x) => x.recover_relayer(address, holder).await,
+            Self::Shared(x) => x.recover_relayer(address, holder).await,
+            Self::Seggregated(x) => x.recover_relayer(address, holder).await,
+        }
+    }
+
     pub async fn lock_relayer(&self) -> Result<RelayerLock, Error> {
         let (result, duration) = measure_duration!(match self {
             #[cfg(feature = "testing")]
@@ -162,6 +329,22 @@ impl LockLayer {
         result
     }
 
+    /// Lock the relayer with the given address, failing if it is not available
+    pub async fn lock_relayer_at(&self, address: Felt) -> Result<RelayerLock, Error> {
+        let (result, duration) = measure_duration!(match self {
+            #[cfg(feature = "testing")]
+            Self::Mock(x) => x.lock_relayer_at(address).await,
+            Self::Shared(x) => x.lock_relayer_at(address).await,
+            Self::Seggregated(x) => x.lock_relayer_at(address).await,
+        });
+
+        metric!(counter[relayer_request_duration_milliseconds] = 1, method = "lock_relayer_at");
+        metric!(histogram[relayer_request_duration_milliseconds] = duration.as_millis(), method = "lock_relayer_at");
+        metric!(on error result => counter [ relayer_request_error ] = 1, method = "lock_relayer_at");
+
+        result
+    }
+
     pub async fn release_relayer(&self, lock: RelayerLock) -> Result<(), Error> {
         let (result, duration) = measure_duration!(match self {
             #[cfg(feature = "testing")]
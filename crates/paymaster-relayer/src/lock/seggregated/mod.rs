@@ -9,7 +9,8 @@ use rand::rng;
 use starknet::core::types::Felt;
 use tokio::sync::Mutex;
 
-use crate::lock::{Error, RelayerLock};
+use crate::lock::{instance_id, Error, RelayerLock, RelayerLockStatus};
+use crate::spend::{SpendTotals, SpendWindow};
 use crate::RelayerManagerConfiguration;
 
 #[derive(Clone, Copy)]
@@ -18,6 +19,12 @@ struct SeggregatedRelayerLock {
     nonce: Option<Felt>,
     enabled: bool,
     cooldown: Instant,
+
+    // Set while the relayer exceeds its spend caps
+    disabled: bool,
+
+    // Set while the relayer is locked
+    locked_at: Option<Instant>,
 }
 
 impl SeggregatedRelayerLock {
@@ -27,11 +34,23 @@ impl SeggregatedRelayerLock {
             nonce: None,
             enabled: true,
             cooldown: Instant::now(),
+            disabled: false,
+            locked_at: None,
         }
     }
 
     pub fn is_available(&self) -> bool {
-        self.enabled && self.cooldown <= Instant::now()
+        self.enabled && !self.disabled && self.cooldown <= Instant::now()
+    }
+
+    pub fn status(&self) -> RelayerLockStatus {
+        RelayerLockStatus {
+            address: self.address,
+            locked: self.locked_at.is_some() || self.cooldown > Instant::now(),
+            holder: self.locked_at.map(|_| instance_id().to_string()),
+            age: self.locked_at.map(|x| x.elapsed()),
+            cached_nonce: self.nonce,
+        }
     }
 }
 
@@ -41,16 +60,25 @@ impl From<SeggregatedRelayerLock> for RelayerLock {
     }
 }
 
+// Amount spent during the current window of each relayer, or of the whole fleet when no relayer is given
+type SpendCounters = HashMap<(Option<Felt>, SpendWindow), (u64, Felt)>;
+
 #[derive(Clone)]
 pub struct SeggregatedLockLayer {
     relayer_by_address: Arc<HashMap<ContractAddress, usize>>,
     relayers: Arc<Mutex<Vec<SeggregatedRelayerLock>>>,
+    spending: Arc<Mutex<SpendCounters>>,
 }
 
 impl SeggregatedLockLayer {
     pub fn new(configuration: &RelayerManagerConfiguration) -> Self {
+        Self::with_addresses(&configuration.relayers.addresses)
+    }
+
+    /// Lock layer over the given relayers only
+    pub fn with_addresses(addresses: &[Felt]) -> Self {
         let mut relayers = vec![];
-        for address in &configuration.relayers.addresses {
+        for address in addresses {
             relayers.push(SeggregatedRelayerLock::new(*address))
         }
 
@@ -63,12 +91,34 @@ impl SeggregatedLockLayer {
                 .into(),
 
             relayers: Arc::new(Mutex::new(relayers)),
+            spending: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    /// Returns true if the relayer is currently locked by this layer
+    pub async fn is_locked(&self, address: Felt) -> bool {
+        let Some(lock_index) = self.relayer_by_address.get(&address) else {
+            return false;
+        };
+
+        self.relayers.lock().await[*lock_index].locked_at.is_some()
+    }
+
+    /// Returns the relayers locked by this layer, or still cooling down after their delayed release
+    pub async fn locked_relayers(&self) -> HashSet<Felt> {
+        let relayers = self.relayers.lock().await;
+
+        relayers.iter().filter(|x| x.status().locked).map(|x| x.address).collect()
+    }
+
     pub async fn count_enabled_relayers(&self) -> usize {
         let enabled_relayers = self.relayers.lock().await;
-        enabled_relayers.iter().filter(|x| x.enabled).count()
+        enabled_relayers.iter().filter(|x| x.enabled && !x.disabled).count()
+    }
+
+    pub async fn is_enabled(&self, relayer: Felt) -> bool {
+        let relayers = self.relayers.lock().await;
+        relayers.iter().any(|x| x.address == relayer && x.enabled && !x.disabled)
     }
 
     pub async fn set_enabled_relayers(&self, relayers: &HashSet<Felt>) {
@@ -78,6 +128,13 @@ impl SeggregatedLockLayer {
             .for_each(|x| x.enabled = relayers.contains(&x.address))
     }
 
+    pub async fn set_disabled_relayers(&self, relayers: &HashSet<Felt>) -> Result<(), Error> {
+        let mut locks = self.relayers.lock().await;
+        locks.iter_mut().for_each(|x| x.disabled = relayers.contains(&x.address));
+
+        Ok(())
+    }
+
     pub async fn lock_relayer(&self) -> Result<RelayerLock, Error> {
         let mut relayers = self.relayers.lock().await;
 
@@ -91,6 +148,20 @@ impl SeggregatedLockLayer {
         let lock_index = available_relayers.choose(&mut rng()).cloned().ok_or(Error::LockUnavailable)?;
 
         relayers[lock_index].cooldown = Instant::now().add(Duration::from_secs(5));
+        relayers[lock_index].locked_at = Some(Instant::now());
+        Ok(relayers[lock_index].into())
+    }
+
+    pub async fn lock_relayer_at(&self, address: Felt) -> Result<RelayerLock, Error> {
+        let lock_index = *self.relayer_by_address.get(&address).ok_or(Error::LockUnavailable)?;
+
+        let mut relayers = self.relayers.lock().await;
+        if !relayers[lock_index].is_available() {
+            return Err(Error::LockUnavailable);
+        }
+
+        relayers[lock_index].cooldown = Instant::now().add(Duration::from_secs(5));
+        relayers[lock_index].locked_at = Some(Instant::now());
         Ok(relayers[lock_index].into())
     }
 
@@ -100,6 +171,7 @@ impl SeggregatedLockLayer {
         let mut relayers = self.relayers.lock().await;
         relayers[*lock_index].cooldown = Instant::now();
         relayers[*lock_index].nonce = lock.nonce;
+        relayers[*lock_index].locked_at = None;
 
         Ok(())
     }
@@ -110,9 +182,78 @@ impl SeggregatedLockLayer {
         let mut relayers = self.relayers.lock().await;
         relayers[*lock_index].cooldown = Instant::now().add(Duration::from_secs(delay));
         relayers[*lock_index].nonce = lock.nonce;
+        relayers[*lock_index].locked_at = None;
+
+        Ok(())
+    }
+
+    /// Forget the nonce cached for the relayer when it is not locked. Locks do not survive a restart of the
+    /// instance, only the cached nonce can be stale.
+    pub async fn recover_relayer(&self, address: Felt, _holder: &str) -> Result<(), Error> {
+        let lock_index = self.relayer_by_address.get(&address).ok_or(Error::LockUnavailable)?;
+
+        let mut relayers = self.relayers.lock().await;
+        if relayers[*lock_index].locked_at.is_none() {
+            relayers[*lock_index].nonce = None;
+        }
+
+        Ok(())
+    }
+
+    pub async fn lock_statuses(&self) -> Result<Vec<RelayerLockStatus>, Error> {
+        let relayers = self.relayers.lock().await;
+
+        Ok(relayers.iter().map(|x| x.status()).collect())
+    }
+
+    pub async fn record_spend(&self, relayer: Felt, amount: Felt) -> Result<(), Error> {
+        let mut spending = self.spending.lock().await;
+        for window in SpendWindow::ALL {
+            let bucket = window.current_bucket();
+            for owner in [Some(relayer), None] {
+                let (counter_bucket, spent) = spending.entry((owner, window)).or_insert((bucket, Felt::ZERO));
+                if *counter_bucket != bucket {
+                    *counter_bucket = bucket;
+                    *spent = Felt::ZERO;
+                }
+
+                *spent += amount;
+            }
+        }
 
         Ok(())
     }
+
+    pub async fn correct_spend(&self, relayer: Felt, estimated: Felt, actual: Felt) -> Result<(), Error> {
+        let mut spending = self.spending.lock().await;
+        for window in SpendWindow::ALL {
+            for owner in [Some(relayer), None] {
+                if let Some((bucket, spent)) = spending.get_mut(&(owner, window)) {
+                    if *bucket == window.current_bucket() {
+                        let provisional = if *spent > estimated { *spent - estimated } else { Felt::ZERO };
+                        *spent = provisional + actual;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn spent(&self, relayer: Option<Felt>) -> Result<SpendTotals, Error> {
+        let spending = self.spending.lock().await;
+
+        let mut totals = SpendTotals::default();
+        for window in SpendWindow::ALL {
+            if let Some((bucket, spent)) = spending.get(&(relayer, window)) {
+                if *bucket == window.current_bucket() {
+                    totals.set(window, *spent);
+                }
+            }
+        }
+
+        Ok(totals)
+    }
 }
 
 #[cfg(test)]
@@ -130,6 +271,7 @@ mod tests {
     use tokio::sync::Mutex;
     use tokio::time;
 
+    use crate::lock::instance_id;
     use crate::lock::seggregated::SeggregatedLockLayer;
     use crate::lock::LockLayerConfiguration;
     use crate::rebalancing::OptionalRebalancingConfiguration;
@@ -152,6 +294,9 @@ mod tests {
                 endpoint: "dummy".to_string(),
                 chain_id: ChainID::Sepolia,
                 fallbacks: vec![],
+                local_estimation: None,
+                endpoint_options: Default::default(),
+                tokens: None,
                 timeout: 10,
             },
             supported_tokens: HashSet::from([Token::usdc(&ChainID::Sepolia).address]),
@@ -159,6 +304,7 @@ mod tests {
                 address: felt!("0x0"),
                 private_key: felt!("0x0"),
             },
+            gas_tank_multisig: None,
             relayers: RelayersConfiguration {
                 min_relayer_balance: felt!("0x0"),
                 private_key: Felt::ZERO,
@@ -167,6 +313,18 @@ mod tests {
                     retry_timeout: Duration::from_secs(5),
                 },
                 rebalancing: OptionalRebalancingConfiguration::initialize(None),
+                max_in_flight_transactions: RelayersConfiguration::default_max_in_flight_transactions(),
+                execution_concurrency_factor: RelayersConfiguration::default_execution_concurrency_factor(),
+                spend_caps: Default::default(),
+                secondary: None,
+                gas_tank_top_up: None,
+                staking: None,
+                journal: None,
+                watchdog: None,
+                dedicated: vec![],
+                deployment_relayers: vec![],
+                treasury_history: None,
+                messaging: None,
             },
             price: PriceConfiguration::mock::<MockPrice>(),
         })
@@ -185,6 +343,34 @@ mod tests {
         assert_eq!(layer.count_enabled_relayers().await, 1)
     }
 
+    #[tokio::test]
+    async fn spend_is_tracked_per_relayer_and_fleet() {
+        let layer = locking_layer(vec![felt!("0x0"), felt!("0x1")]);
+
+        layer.record_spend(felt!("0x0"), Felt::from(10)).await.unwrap();
+        layer.record_spend(felt!("0x1"), Felt::from(5)).await.unwrap();
+        layer.record_spend(felt!("0x0"), Felt::from(1)).await.unwrap();
+
+        assert_eq!(layer.spent(Some(felt!("0x0"))).await.unwrap().hourly, Felt::from(11));
+        assert_eq!(layer.spent(Some(felt!("0x1"))).await.unwrap().daily, Felt::from(5));
+        assert_eq!(layer.spent(None).await.unwrap().daily, Felt::from(16));
+
+        layer.correct_spend(felt!("0x0"), Felt::from(10), Felt::from(4)).await.unwrap();
+        assert_eq!(layer.spent(Some(felt!("0x0"))).await.unwrap().hourly, Felt::from(5));
+        assert_eq!(layer.spent(None).await.unwrap().daily, Felt::from(10));
+
+        layer.set_disabled_relayers(&HashSet::from([felt!("0x0")])).await.unwrap();
+        assert_eq!(layer.count_enabled_relayers().await, 1);
+        assert_eq!(layer.lock_relayer().await.unwrap().address, felt!("0x1"));
+
+        // Relayers enabled given their balance stay disabled until their spend caps are no longer exceeded
+        layer.set_enabled_relayers(&HashSet::from([felt!("0x0"), felt!("0x1")])).await;
+        assert_eq!(layer.count_enabled_relayers().await, 1);
+
+        layer.set_disabled_relayers(&HashSet::new()).await.unwrap();
+        assert_eq!(layer.count_enabled_relayers().await, 2);
+    }
+
     #[tokio::test]
     async fn lock_unlock_relayers_works_properly() {
         let layer = locking_layer(vec![felt!("0x0"), felt!("0x1")]);
@@ -215,6 +401,25 @@ mod tests {
         let _ = layer.lock_relayer().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn lock_statuses_report_holder_and_nonce() {
+        let layer = locking_layer(vec![felt!("0x0")]);
+
+        let mut lock = layer.lock_relayer().await.unwrap();
+        let statuses = layer.lock_statuses().await.unwrap();
+        assert!(statuses[0].locked);
+        assert_eq!(statuses[0].holder.as_deref(), Some(instance_id()));
+        assert!(statuses[0].age.is_some());
+
+        lock.nonce = Some(felt!("0x42"));
+        layer.release_relayer(lock).await.unwrap();
+
+        let statuses = layer.lock_statuses().await.unwrap();
+        assert!(!statuses[0].locked);
+        assert_eq!(statuses[0].holder, None);
+        assert_eq!(statuses[0].cached_nonce, Some(felt!("0x42")));
+    }
+
     #[tokio::test]
     async fn multiple_concurrent_lock_unlock_works_properly() {
         let layer = locking_layer((0..8).map(Felt::from).collect());
@@ -1,12 +1,13 @@
 use std::collections::HashSet;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use deadpool_redis::redis::{AsyncCommands, ExistenceCheck, RedisWrite, SetExpiry, SetOptions, ToRedisArgs};
 use deadpool_redis::Connection;
 use futures::StreamExt;
+use serde::{Deserialize, Serialize};
 use starknet::core::types::Felt;
 
-use crate::lock::{Error, RelayerLock};
+use crate::lock::{instance_id, Error, RelayerLock, RelayerLockStatus};
 
 enum LockKey {
     All,
@@ -36,6 +37,28 @@ impl ToRedisArgs for CacheKey {
     }
 }
 
+/// Value stored under the lock key, identifying who took the lock and when
+#[derive(Serialize, Deserialize)]
+struct LockHolder {
+    holder: String,
+
+    /// Unix timestamp in seconds
+    locked_at: u64,
+}
+
+impl LockHolder {
+    fn current() -> Self {
+        Self {
+            holder: instance_id().to_string(),
+            locked_at: unix_timestamp(),
+        }
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
 pub struct RedisRelayerLock {
     expiry: Instant,
 
@@ -87,7 +110,8 @@ impl RedisRelayerLock {
             .conditional_set(ExistenceCheck::NX)
             .with_expiration(SetExpiry::EX(expiry));
 
-        if !redis.set_options(lock_key, Vec::<u8>::new(), options).await? {
+        let holder = serde_json::to_vec(&LockHolder::current()).unwrap_or_default();
+        if !redis.set_options(lock_key, holder, options).await? {
             return Err(Error::AlreadyLocked);
         }
 
@@ -105,6 +129,29 @@ impl RedisRelayerLock {
         })
     }
 
+    /// Returns the lock state of the given relayer. A relayer locked with an empty value is cooling down
+    /// after a delayed release.
+    pub async fn status(redis: &mut Connection, relayer: Felt) -> Result<RelayerLockStatus, Error> {
+        let lock: Option<Vec<u8>> = redis.get(LockKey::Address(relayer)).await?;
+        let holder = lock.as_ref().and_then(|x| serde_json::from_slice::<LockHolder>(x).ok());
+
+        let cached_nonce: Option<Felt> = redis
+            .get(CacheKey(relayer))
+            .await
+            .map(|x: Vec<u8>| serde_json::from_slice(&x).ok())?
+            .unwrap_or(None);
+
+        Ok(RelayerLockStatus {
+            address: relayer,
+            locked: lock.is_some(),
+            age: holder
+                .as_ref()
+                .map(|x| Duration::from_secs(unix_timestamp().saturating_sub(x.locked_at))),
+            holder: holder.map(|x| x.holder),
+            cached_nonce,
+        })
+    }
+
     // TODO: update redis dep
     #[allow(dependency_on_unit_never_type_fallback)]
     pub async fn unlock(self, redis: &mut Connection) -> Result<(), Error> {
@@ -119,6 +166,21 @@ impl RedisRelayerLock {
         Ok(())
     }
 
+    /// Release the lock of the relayer if it is still held by `holder`, an instance that stopped while it was
+    /// executing a transaction, and drop its cached nonce which does not account for that transaction
+    #[allow(dependency_on_unit_never_type_fallback)]
+    pub async fn recover(redis: &mut Connection, relayer: Felt, holder: &str) -> Result<(), Error> {
+        let lock: Option<Vec<u8>> = redis.get(LockKey::Address(relayer)).await?;
+        let current = lock.as_ref().and_then(|x| serde_json::from_slice::<LockHolder>(x).ok());
+        if current.is_some_and(|x| x.holder == holder) {
+            redis.del(LockKey::Address(relayer)).await?;
+        }
+
+        redis.del(CacheKey(relayer)).await?;
+
+        Ok(())
+    }
+
     // TODO: update redis dep
     #[allow(dependency_on_unit_never_type_fallback)]
     pub async fn unlock_with_expiry(self, redis: &mut Connection, expiry: u64) -> Result<(), Error> {
@@ -143,6 +205,7 @@ mod tests {
     use testcontainers::{ContainerAsync, GenericImage};
     use tokio::time;
 
+    use crate::lock::instance_id;
     use crate::lock::shared::lock::RedisRelayerLock;
 
     type RedisContainer = ContainerAsync<GenericImage>;
@@ -237,6 +300,27 @@ mod tests {
         assert_eq!(lock.nonce, Some(felt!("0x42")))
     }
 
+    #[tokio::test]
+    async fn status_reports_holder_and_cached_nonce() {
+        let container = redis_container().await;
+        let pool = redis_pool(&container).await;
+
+        let mut connection = pool.get().await.unwrap();
+
+        let mut lock = RedisRelayerLock::lock(&mut connection, felt!("0x0")).await.unwrap();
+        let status = RedisRelayerLock::status(&mut connection, felt!("0x0")).await.unwrap();
+        assert!(status.locked);
+        assert_eq!(status.holder.as_deref(), Some(instance_id()));
+
+        lock.nonce = Some(felt!("0x42"));
+        lock.unlock(&mut connection).await.unwrap();
+
+        let status = RedisRelayerLock::status(&mut connection, felt!("0x0")).await.unwrap();
+        assert!(!status.locked);
+        assert_eq!(status.holder, None);
+        assert_eq!(status.cached_nonce, Some(felt!("0x42")));
+    }
+
     #[tokio::test]
     async fn lock_with_expiry_works_properly() {
         let container = redis_container().await;
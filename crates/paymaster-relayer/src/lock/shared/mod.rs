@@ -1,59 +1,186 @@
 use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use deadpool_redis::{Config, Connection, Pool, Runtime};
+use deadpool_redis::redis::cmd;
+use deadpool_redis::{Config, Connection, CreatePoolError, Pool, PoolConfig, Runtime, Timeouts};
+use paymaster_common::validation::{Validate, ValidationReport};
 use rand::prelude::SliceRandom;
 use rand::rng;
 use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 use starknet::core::types::Felt;
 use tokio::sync::RwLock;
 
+use crate::lock::seggregated::SeggregatedLockLayer;
 use crate::lock::shared::lock::RedisRelayerLock;
-use crate::lock::{Error, RelayerLock};
+use crate::lock::shared::spend::RedisRelayerSpend;
+use crate::lock::{Error, LockFallbackConfiguration, RelayerLock, RelayerLockStatus};
 use crate::rebalancing::RelayerManagerConfiguration;
+use crate::spend::{FleetSpend, SpendTotals};
 
 pub mod lock;
+pub mod spend;
 
+#[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RedisParameters {
     endpoint: String,
+
+    /// Maximum number of connections of the pool
+    #[serde(default = "RedisParameters::default_pool_size")]
+    pub pool_size: usize,
+
+    /// Maximum time waited for a connection of the pool to be available, unbounded when not set
+    #[serde_as(as = "Option<serde_with::DurationSeconds>")]
+    #[serde(default)]
+    pub wait_timeout: Option<Duration>,
+
+    /// Maximum time waited for a new connection to be established, unbounded when not set
+    #[serde_as(as = "Option<serde_with::DurationSeconds>")]
+    #[serde(default)]
+    pub connect_timeout: Option<Duration>,
+
+    /// Maximum time waited for a connection to be checked before it is reused, unbounded when not set
+    #[serde_as(as = "Option<serde_with::DurationSeconds>")]
+    #[serde(default)]
+    pub recycle_timeout: Option<Duration>,
+
+    /// Interval between two pings of Redis. They keep the connections of the pool alive and mark the lock layer
+    /// unhealthy while Redis is unreachable
+    #[serde_as(as = "serde_with::DurationSeconds")]
+    #[serde(default = "RedisParameters::default_keepalive_interval")]
+    pub keepalive_interval: Duration,
+}
+
+impl RedisParameters {
+    pub fn new(endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            pool_size: Self::default_pool_size(),
+            wait_timeout: None,
+            connect_timeout: None,
+            recycle_timeout: None,
+            keepalive_interval: Self::default_keepalive_interval(),
+        }
+    }
+
+    fn default_pool_size() -> usize {
+        16
+    }
+
+    fn default_keepalive_interval() -> Duration {
+        Duration::from_secs(5)
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Create a pool of connections to Redis sized and bounded by the parameters
+    pub fn create_pool(&self) -> Result<Pool, CreatePoolError> {
+        let mut timeouts = Timeouts::default();
+        timeouts.wait = self.wait_timeout;
+        timeouts.create = self.connect_timeout;
+        timeouts.recycle = self.recycle_timeout;
+
+        let mut pool = PoolConfig::new(self.pool_size);
+        pool.timeouts = timeouts;
+
+        let mut config = Config::from_url(&self.endpoint);
+        config.pool = Some(pool);
+        config.create_pool(Some(Runtime::Tokio1))
+    }
+}
+
+impl Validate for RedisParameters {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure_url("endpoint", &self.endpoint);
+        report.ensure(self.pool_size > 0, "pool_size", "must be greater than 0");
+        report.ensure(!self.keepalive_interval.is_zero(), "keepalive_interval", "must be greater than 0");
+    }
 }
 
 #[derive(Clone)]
 pub struct SharedLockLayer {
     redis: Pool,
 
+    // False while the pings of Redis fail, the locks are then refused without reaching Redis
+    healthy: Arc<AtomicBool>,
+    keepalive_interval: Duration,
+
+    // Local locks over the relayers reserved to the instance, used while Redis is unreachable
+    fallback: Option<SeggregatedLockLayer>,
+
+    // All the relayers of the fleet, enabled or not
+    addresses: Arc<Vec<Felt>>,
     relayers: Arc<RwLock<HashSet<Felt>>>,
+
+    // Relayers exceeding their spend caps as last set by this instance, the locks read the set shared in Redis
+    disabled: Arc<RwLock<HashSet<Felt>>>,
 }
 
 impl SharedLockLayer {
-    pub fn new(configuration: &RelayerManagerConfiguration, params: &RedisParameters) -> Self {
-        Self {
-            redis: Config::from_url(&params.endpoint)
-                .create_pool(Some(Runtime::Tokio1))
-                .expect("invalid client"),
-
+    pub fn new(configuration: &RelayerManagerConfiguration, params: &RedisParameters, fallback: Option<&LockFallbackConfiguration>) -> Result<Self, Error> {
+        Ok(Self {
+            redis: params.create_pool()?,
+            healthy: Arc::new(AtomicBool::new(true)),
+            keepalive_interval: params.keepalive_interval,
+            fallback: fallback.map(|x| SeggregatedLockLayer::with_addresses(&x.addresses)),
+
+            addresses: Arc::new(configuration.relayers.addresses.clone()),
             relayers: Arc::new(RwLock::new(configuration.relayers.addresses.iter().cloned().collect())),
-        }
+            disabled: Arc::default(),
+        })
     }
 
     pub async fn count_enabled_relayers(&self) -> usize {
         let enabled_relayers = self.relayers.read().await;
-        enabled_relayers.len()
+        let disabled_relayers = self.disabled.read().await;
+        enabled_relayers.difference(&disabled_relayers).count()
+    }
+
+    pub async fn is_enabled(&self, relayer: Felt) -> bool {
+        self.relayers.read().await.contains(&relayer) && !self.disabled.read().await.contains(&relayer)
     }
 
     pub async fn set_enabled_relayers(&self, relayers: &HashSet<Felt>) {
+        if let Some(fallback) = &self.fallback {
+            fallback.set_enabled_relayers(relayers).await;
+        }
+
         let mut enabled_relayers = self.relayers.write().await;
         *enabled_relayers = relayers.clone()
     }
 
+    pub async fn set_disabled_relayers(&self, relayers: &HashSet<Felt>) -> Result<(), Error> {
+        if let Some(fallback) = &self.fallback {
+            fallback.set_disabled_relayers(relayers).await?;
+        }
+        *self.disabled.write().await = relayers.clone();
+
+        let mut connection = self.get_redis_connection().await?;
+        RedisRelayerSpend::set_disabled(&mut connection, relayers).await
+    }
+
     pub async fn lock_relayer(&self) -> Result<RelayerLock, Error> {
+        if let Some(fallback) = self.active_fallback() {
+            return fallback.lock_relayer().await;
+        }
+
         let mut connection = self.get_redis_connection().await?;
         let relayers = self.relayers.read().await;
 
         let locked_relayers = RedisRelayerLock::list_locked(&mut connection).await?;
+        let disabled_relayers = RedisRelayerSpend::disabled(&mut connection).await?;
+        let fallback_relayers = self.held_by_fallback().await;
 
-        let mut available_relayers: Vec<Felt> = relayers.difference(&locked_relayers).cloned().collect();
+        let mut available_relayers: Vec<Felt> = relayers
+            .difference(&locked_relayers)
+            .filter(|x| !disabled_relayers.contains(x) && !fallback_relayers.contains(x))
+            .cloned()
+            .collect();
 
         // Shuffle the list to reduce collision when picking relayers concurrently. Also prevent picking
         // always the same relayer
@@ -69,7 +196,33 @@ impl SharedLockLayer {
         Err(Error::LockUnavailable)
     }
 
+    pub async fn lock_relayer_at(&self, address: Felt) -> Result<RelayerLock, Error> {
+        if !self.relayers.read().await.contains(&address) {
+            return Err(Error::LockUnavailable);
+        }
+
+        if let Some(fallback) = self.active_fallback() {
+            return fallback.lock_relayer_at(address).await;
+        }
+
+        // A relayer locked while Redis was unreachable may still be executing a transaction
+        if self.held_by_fallback().await.contains(&address) {
+            return Err(Error::LockUnavailable);
+        }
+
+        let mut connection = self.get_redis_connection().await?;
+        if RedisRelayerSpend::is_disabled(&mut connection, address).await? {
+            return Err(Error::LockUnavailable);
+        }
+
+        Ok(RedisRelayerLock::lock(&mut connection, address).await?.into())
+    }
+
     pub async fn release_relayer(&self, lock: RelayerLock) -> Result<(), Error> {
+        if let Some(fallback) = self.fallback_holding(lock.address).await {
+            return fallback.release_relayer(lock).await;
+        }
+
         let mut connection = self.get_redis_connection().await?;
         let redis_lock: RedisRelayerLock = lock.into();
 
@@ -77,15 +230,146 @@ impl SharedLockLayer {
     }
 
     pub async fn release_relayer_delayed(&self, lock: RelayerLock, delay: u64) -> Result<(), Error> {
+        if let Some(fallback) = self.fallback_holding(lock.address).await {
+            return fallback.release_relayer_delayed(lock, delay).await;
+        }
+
         let mut connection = self.get_redis_connection().await?;
         let redis_lock: RedisRelayerLock = lock.into();
 
         redis_lock.unlock_with_expiry(&mut connection, delay).await
     }
+
+    pub async fn recover_relayer(&self, address: Felt, holder: &str) -> Result<(), Error> {
+        let mut connection = self.get_redis_connection().await?;
+
+        RedisRelayerLock::recover(&mut connection, address, holder).await
+    }
+
+    pub async fn lock_statuses(&self) -> Result<Vec<RelayerLockStatus>, Error> {
+        if let Some(fallback) = self.active_fallback() {
+            return fallback.lock_statuses().await;
+        }
+
+        let mut connection = self.get_redis_connection().await?;
+
+        let mut statuses = vec![];
+        for address in self.addresses.iter() {
+            statuses.push(RedisRelayerLock::status(&mut connection, *address).await?);
+        }
+
+        Ok(statuses)
+    }
+
+    pub async fn record_spend(&self, relayer: Felt, amount: Felt) -> Result<(), Error> {
+        if let Some(fallback) = self.active_fallback() {
+            return fallback.record_spend(relayer, amount).await;
+        }
+
+        let mut connection = self.get_redis_connection().await?;
+
+        RedisRelayerSpend::record(&mut connection, relayer, amount).await
+    }
+
+    pub async fn correct_spend(&self, relayer: Felt, estimated: Felt, actual: Felt) -> Result<(), Error> {
+        if let Some(fallback) = self.active_fallback() {
+            return fallback.correct_spend(relayer, estimated, actual).await;
+        }
+
+        let mut connection = self.get_redis_connection().await?;
+
+        RedisRelayerSpend::correct(&mut connection, relayer, estimated, actual).await
+    }
+
+    pub async fn fleet_spent(&self, relayers: &[Felt]) -> Result<FleetSpend, Error> {
+        if let Some(fallback) = self.active_fallback() {
+            let mut spend = FleetSpend {
+                fleet: fallback.spent(None).await?,
+                ..Default::default()
+            };
+            for relayer in relayers {
+                spend.relayers.insert(*relayer, fallback.spent(Some(*relayer)).await?);
+            }
+
+            return Ok(spend);
+        }
+
+        let mut connection = self.get_redis_connection().await?;
+
+        RedisRelayerSpend::fleet_spent(&mut connection, relayers).await
+    }
+
+    pub async fn spent(&self, relayer: Option<Felt>) -> Result<SpendTotals, Error> {
+        if let Some(fallback) = self.active_fallback() {
+            return fallback.spent(relayer).await;
+        }
+
+        let mut connection = self.get_redis_connection().await?;
+
+        RedisRelayerSpend::spent(&mut connection, relayer).await
+    }
 }
 
 impl SharedLockLayer {
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    pub fn has_fallback(&self) -> bool {
+        self.fallback.is_some()
+    }
+
+    // Local locks to use instead of Redis, set while Redis is unreachable
+    fn active_fallback(&self) -> Option<&SeggregatedLockLayer> {
+        self.fallback.as_ref().filter(|_| !self.is_healthy())
+    }
+
+    // Local locks holding the relayer, which must be released locally even once Redis is reachable again
+    async fn fallback_holding(&self, address: Felt) -> Option<&SeggregatedLockLayer> {
+        match &self.fallback {
+            Some(fallback) if fallback.is_locked(address).await => Some(fallback),
+            _ => None,
+        }
+    }
+
+    // Relayers locked through the local locks, unknown to Redis, which cannot be locked through Redis until they are
+    // released locally
+    async fn held_by_fallback(&self) -> HashSet<Felt> {
+        match &self.fallback {
+            Some(fallback) => fallback.locked_relayers().await,
+            None => HashSet::new(),
+        }
+    }
+
+    /// Interval between two probes of Redis
+    pub fn keepalive_interval(&self) -> Duration {
+        self.keepalive_interval
+    }
+
+    /// Ping Redis and record whether it is reachable. A ping which does not answer within the keepalive interval
+    /// is a failure
+    pub async fn probe(&self) -> Result<(), Error> {
+        let result = match tokio::time::timeout(self.keepalive_interval, self.ping()).await {
+            Ok(result) => result,
+            Err(_) => Err(Error::Unreachable),
+        };
+        self.healthy.store(result.is_ok(), Ordering::Relaxed);
+
+        result
+    }
+
+    async fn ping(&self) -> Result<(), Error> {
+        let mut connection = self.redis.get().await?;
+        cmd("PING").query_async::<()>(&mut connection).await?;
+
+        Ok(())
+    }
+
     async fn get_redis_connection(&self) -> Result<Connection, Error> {
+        if !self.is_healthy() {
+            return Err(Error::Unreachable);
+        }
+
         let result = self.redis.get().await?;
 
         Ok(result)
@@ -95,6 +379,7 @@ impl SharedLockLayer {
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
+    use std::sync::atomic::AtomicBool;
     use std::sync::Arc;
 
     use deadpool_redis::{Config, Pool, Runtime};
@@ -108,8 +393,10 @@ mod tests {
     use tokio::sync::{Mutex, RwLock};
     use tokio::time;
 
-    use crate::lock::shared::SharedLockLayer;
+    use crate::lock::seggregated::SeggregatedLockLayer;
+    use crate::lock::shared::{RedisParameters, SharedLockLayer};
     use crate::lock::Duration;
+    use crate::lock::{Error, LockFallbackConfiguration};
 
     type RedisContainer = ContainerAsync<GenericImage>;
 
@@ -137,7 +424,12 @@ mod tests {
 
         let layer = SharedLockLayer {
             redis: pool,
+            healthy: Arc::new(AtomicBool::new(true)),
+            keepalive_interval: Duration::from_secs(5),
+            fallback: None,
+            addresses: Arc::new((0..10).map(Felt::from).collect()),
             relayers: Arc::new(RwLock::new((0..10).map(Felt::from).collect())),
+            disabled: Arc::default(),
         };
 
         let mut executor = ConcurrentExecutor::new(layer.clone(), 8);
@@ -165,7 +457,12 @@ mod tests {
 
         let layer = SharedLockLayer {
             redis: pool,
+            healthy: Arc::new(AtomicBool::new(true)),
+            keepalive_interval: Duration::from_secs(5),
+            fallback: None,
+            addresses: Arc::new((0..8).map(Felt::from).collect()),
             relayers: Arc::new(RwLock::new((0..8).map(Felt::from).collect())),
+            disabled: Arc::default(),
         };
 
         let ctx = Context {
@@ -197,4 +494,123 @@ mod tests {
 
         assert!(results.is_ok())
     }
+    #[tokio::test]
+    async fn unreachable_redis_marks_the_layer_unhealthy() {
+        let mut params = RedisParameters::new("redis://127.0.0.1:1");
+        params.keepalive_interval = Duration::from_secs(1);
+
+        let layer = SharedLockLayer {
+            redis: params.create_pool().unwrap(),
+            healthy: Arc::new(AtomicBool::new(true)),
+            keepalive_interval: params.keepalive_interval,
+            fallback: None,
+            addresses: Arc::new(vec![Felt::ONE]),
+            relayers: Arc::new(RwLock::new(HashSet::from([Felt::ONE]))),
+            disabled: Arc::default(),
+        };
+
+        assert!(layer.probe().await.is_err());
+        assert!(!layer.is_healthy());
+        assert!(matches!(layer.lock_relayer().await, Err(Error::Unreachable)));
+    }
+    #[tokio::test]
+    async fn unreachable_redis_falls_back_to_the_reserved_relayers() {
+        let mut params = RedisParameters::new("redis://127.0.0.1:1");
+        params.keepalive_interval = Duration::from_secs(1);
+
+        let fallback = LockFallbackConfiguration { addresses: vec![Felt::TWO] };
+        let layer = SharedLockLayer {
+            redis: params.create_pool().unwrap(),
+            healthy: Arc::new(AtomicBool::new(true)),
+            keepalive_interval: params.keepalive_interval,
+            fallback: Some(SeggregatedLockLayer::with_addresses(&fallback.addresses)),
+            addresses: Arc::new(vec![Felt::ONE, Felt::TWO]),
+            relayers: Arc::new(RwLock::new(HashSet::from([Felt::ONE, Felt::TWO]))),
+            disabled: Arc::default(),
+        };
+        assert!(layer.probe().await.is_err());
+
+        let lock = layer.lock_relayer().await.unwrap();
+        assert_eq!(lock.address, Felt::TWO);
+        assert!(matches!(layer.lock_relayer_at(Felt::ONE).await, Err(Error::LockUnavailable)));
+
+        layer.release_relayer(lock).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn relayers_locked_by_the_fallback_are_held_until_released() {
+        let container = redis_container().await;
+
+        let fallback = LockFallbackConfiguration { addresses: vec![Felt::TWO] };
+        let layer = SharedLockLayer {
+            redis: redis_pool(&container).await,
+            healthy: Arc::new(AtomicBool::new(false)),
+            keepalive_interval: Duration::from_secs(5),
+            fallback: Some(SeggregatedLockLayer::with_addresses(&fallback.addresses)),
+            addresses: Arc::new(vec![Felt::ONE, Felt::TWO]),
+            relayers: Arc::new(RwLock::new(HashSet::from([Felt::ONE, Felt::TWO]))),
+            disabled: Arc::default(),
+        };
+
+        let fallback_lock = layer.lock_relayer().await.unwrap();
+        assert_eq!(fallback_lock.address, Felt::TWO);
+
+        // Redis is reachable again while the relayer is still executing the transaction locked locally
+        assert!(layer.probe().await.is_ok());
+        assert!(matches!(layer.lock_relayer_at(Felt::TWO).await, Err(Error::LockUnavailable)));
+
+        let lock = layer.lock_relayer().await.unwrap();
+        assert_eq!(lock.address, Felt::ONE);
+        assert!(matches!(layer.lock_relayer().await, Err(Error::LockUnavailable)));
+
+        layer.release_relayer(fallback_lock).await.unwrap();
+        layer
+            .release_relayer(layer.lock_relayer_at(Felt::TWO).await.unwrap())
+            .await
+            .unwrap();
+        layer.release_relayer(lock).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn disabled_relayers_and_spend_are_shared_across_the_instances() {
+        let container = redis_container().await;
+        let layer = SharedLockLayer {
+            redis: redis_pool(&container).await,
+            healthy: Arc::new(AtomicBool::new(true)),
+            keepalive_interval: Duration::from_secs(5),
+            fallback: None,
+            addresses: Arc::new(vec![Felt::ONE, Felt::TWO]),
+            relayers: Arc::new(RwLock::new(HashSet::from([Felt::ONE, Felt::TWO]))),
+            disabled: Arc::default(),
+        };
+        let other = SharedLockLayer {
+            redis: redis_pool(&container).await,
+            disabled: Arc::default(),
+            ..layer.clone()
+        };
+
+        layer.record_spend(Felt::ONE, Felt::from(10_000_000_000u64)).await.unwrap();
+        other
+            .correct_spend(Felt::ONE, Felt::from(10_000_000_000u64), Felt::from(4_000_000_000u64))
+            .await
+            .unwrap();
+
+        let spend = other.fleet_spent(&[Felt::ONE, Felt::TWO]).await.unwrap();
+        assert_eq!(spend.fleet.hourly, Felt::from(4_000_000_000u64));
+        assert_eq!(spend.relayers[&Felt::ONE].daily, Felt::from(4_000_000_000u64));
+        assert_eq!(spend.relayers[&Felt::TWO].daily, Felt::ZERO);
+
+        // The relayers disabled by one instance cannot be locked by the other ones
+        layer.set_disabled_relayers(&HashSet::from([Felt::ONE])).await.unwrap();
+        assert!(matches!(other.lock_relayer_at(Felt::ONE).await, Err(Error::LockUnavailable)));
+        for _ in 0..5 {
+            let lock = other.lock_relayer().await.unwrap();
+            assert_eq!(lock.address, Felt::TWO);
+            other.release_relayer(lock).await.unwrap();
+        }
+
+        layer.set_disabled_relayers(&HashSet::new()).await.unwrap();
+        let lock = other.lock_relayer_at(Felt::ONE).await.unwrap();
+        other.release_relayer(lock).await.unwrap();
+    }
 }
@@ -0,0 +1,135 @@
+use std::collections::HashSet;
+
+use deadpool_redis::redis::{cmd, pipe, AsyncCommands, RedisWrite, ToRedisArgs};
+use deadpool_redis::Connection;
+use starknet::core::types::Felt;
+
+use crate::lock::Error;
+use crate::spend::{from_spend_units, to_spend_units, FleetSpend, SpendTotals, SpendWindow};
+
+/// Set of the relayers disabled because they exceeded their spend caps, read by every instance when locking
+const DISABLED_RELAYERS_KEY: &str = "relayer-spend-disabled";
+
+/// The disabled set is rewritten by every check of the caps, it expires if the checks stop
+const DISABLED_RELAYERS_EXPIRY: i64 = 3600;
+
+// Counter of the amount spent by a relayer, or by the whole fleet when no relayer is given, during a window
+struct SpendKey(Option<Felt>, SpendWindow);
+
+impl ToRedisArgs for SpendKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + RedisWrite,
+    {
+        let owner = self.0.map(|x| x.to_fixed_hex_string()).unwrap_or("fleet".to_string());
+        out.write_arg_fmt(format!("relayer-spend:{}:{}:{}", owner, self.1.name(), self.1.current_bucket()))
+    }
+}
+
+pub struct RedisRelayerSpend;
+
+impl RedisRelayerSpend {
+    /// Add `amount` to the counters of the relayer and of the fleet. Counters expire once their window is over.
+    pub async fn record(redis: &mut Connection, relayer: Felt, amount: Felt) -> Result<(), Error> {
+        let units = to_spend_units(amount);
+
+        let mut pipeline = pipe();
+        pipeline.atomic();
+        for window in SpendWindow::ALL {
+            let expiry = window.duration().as_secs() as i64;
+            for owner in [Some(relayer), None] {
+                pipeline.incr(SpendKey(owner, window), units).ignore();
+                pipeline.expire(SpendKey(owner, window), expiry).ignore();
+            }
+        }
+
+        pipeline.query_async::<()>(redis).await?;
+        Ok(())
+    }
+
+    /// Replace the `estimated` fee recorded for a transaction of the relayer by its `actual` fee. The correction
+    /// applies to the current windows.
+    pub async fn correct(redis: &mut Connection, relayer: Felt, estimated: Felt, actual: Felt) -> Result<(), Error> {
+        let delta = to_spend_units(actual) as i64 - to_spend_units(estimated) as i64;
+        if delta == 0 {
+            return Ok(());
+        }
+
+        let mut pipeline = pipe();
+        pipeline.atomic();
+        for window in SpendWindow::ALL {
+            let expiry = window.duration().as_secs() as i64;
+            for owner in [Some(relayer), None] {
+                pipeline.incr(SpendKey(owner, window), delta).ignore();
+                pipeline.expire(SpendKey(owner, window), expiry).ignore();
+            }
+        }
+
+        pipeline.query_async::<()>(redis).await?;
+        Ok(())
+    }
+
+    pub async fn spent(redis: &mut Connection, relayer: Option<Felt>) -> Result<SpendTotals, Error> {
+        let keys: Vec<SpendKey> = SpendWindow::ALL.into_iter().map(|window| SpendKey(relayer, window)).collect();
+        let units: Vec<Option<i64>> = cmd("MGET").arg(&keys).query_async(redis).await?;
+
+        Ok(Self::totals(&units))
+    }
+
+    /// Returns the amounts spent by the fleet and by each of the given relayers, read at once
+    pub async fn fleet_spent(redis: &mut Connection, relayers: &[Felt]) -> Result<FleetSpend, Error> {
+        let owners: Vec<Option<Felt>> = [None].into_iter().chain(relayers.iter().copied().map(Some)).collect();
+        let keys: Vec<SpendKey> = owners
+            .iter()
+            .flat_map(|owner| SpendWindow::ALL.into_iter().map(|window| SpendKey(*owner, window)))
+            .collect();
+        let units: Vec<Option<i64>> = cmd("MGET").arg(&keys).query_async(redis).await?;
+
+        let mut spend = FleetSpend::default();
+        for (owner, units) in owners.iter().zip(units.chunks(SpendWindow::ALL.len())) {
+            match owner {
+                Some(relayer) => {
+                    spend.relayers.insert(*relayer, Self::totals(units));
+                },
+                None => spend.fleet = Self::totals(units),
+            }
+        }
+
+        Ok(spend)
+    }
+
+    /// Replace the relayers disabled because they exceeded their spend caps
+    pub async fn set_disabled(redis: &mut Connection, relayers: &HashSet<Felt>) -> Result<(), Error> {
+        let mut pipeline = pipe();
+        pipeline.atomic();
+        pipeline.del(DISABLED_RELAYERS_KEY).ignore();
+        if !relayers.is_empty() {
+            let members: Vec<String> = relayers.iter().map(|x| x.to_fixed_hex_string()).collect();
+            pipeline.sadd(DISABLED_RELAYERS_KEY, members).ignore();
+            pipeline.expire(DISABLED_RELAYERS_KEY, DISABLED_RELAYERS_EXPIRY).ignore();
+        }
+
+        pipeline.query_async::<()>(redis).await?;
+        Ok(())
+    }
+
+    pub async fn disabled(redis: &mut Connection) -> Result<HashSet<Felt>, Error> {
+        let members: Vec<String> = redis.smembers(DISABLED_RELAYERS_KEY).await?;
+
+        Ok(members.iter().filter_map(|x| Felt::from_hex(x).ok()).collect())
+    }
+
+    pub async fn is_disabled(redis: &mut Connection, relayer: Felt) -> Result<bool, Error> {
+        Ok(redis.sismember(DISABLED_RELAYERS_KEY, relayer.to_fixed_hex_string()).await?)
+    }
+
+    // Counters are in the order of the windows, the corrections may bring them below zero
+    fn totals(units: &[Option<i64>]) -> SpendTotals {
+        let mut totals = SpendTotals::default();
+        for (window, units) in SpendWindow::ALL.into_iter().zip(units) {
+            totals.set(window, from_spend_units(units.unwrap_or_default().max(0) as u64));
+        }
+
+        totals
+    }
+}
@@ -1,12 +1,154 @@
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use async_trait::async_trait;
+use paymaster_common::declare_message_identity;
 use paymaster_common::metric;
+use paymaster_common::service::messaging::Messages;
 use paymaster_common::service::{Error, Service};
+use serde::{Deserialize, Serialize};
 use tokio::time;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::context::Context;
+use crate::events::RelayerEvents;
+
+/// Number of consecutive healthy checks required before the relayers are considered available again
+pub const AVAILABILITY_RECOVERY_CHECKS: usize = 3;
+
+/// Number of transitions kept in the availability history
+pub const AVAILABILITY_HISTORY_LENGTH: usize = 100;
+
+/// Identity under which the availability transitions are published on the messaging layer
+pub struct RelayersAvailability;
+
+declare_message_identity!(RelayersAvailability);
+
+/// Change of the availability of the relayers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AvailabilityTransition {
+    /// Unix timestamp of the transition, in seconds
+    pub at: u64,
+    pub available: bool,
+    pub enabled_relayers: usize,
+
+    /// Duration of the state left by the transition, in seconds. Short durations are blips rather than outages.
+    pub previous_state_seconds: u64,
+}
+
+/// Availability of the relayers with hysteresis: they become unavailable as soon as no relayer is enabled, but only
+/// available again after [`AVAILABILITY_RECOVERY_CHECKS`] consecutive healthy checks, so that a flapping fleet is
+/// not reported available between two failures.
+#[derive(Clone)]
+pub struct RelayerAvailability {
+    inner: Arc<Mutex<AvailabilityState>>,
+    events: RelayerEvents<AvailabilityTransition>,
+}
+
+struct AvailabilityState {
+    available: bool,
+    healthy_checks: usize,
+    since: u64,
+    history: VecDeque<AvailabilityTransition>,
+}
+
+impl Default for RelayerAvailability {
+    fn default() -> Self {
+        Self::new(RelayerEvents::default())
+    }
+}
+
+impl RelayerAvailability {
+    /// Creates the availability of the relayers, whose transitions are published on `events`
+    pub fn new(events: RelayerEvents<AvailabilityTransition>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(AvailabilityState {
+                available: true,
+                healthy_checks: 0,
+                since: now(),
+                history: VecDeque::new(),
+            })),
+            events,
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).available
+    }
+
+    /// Latest transitions, the most recent last
+    pub fn history(&self) -> Vec<AvailabilityTransition> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .history
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    /// Messaging layer on which the [`AvailabilityTransition`] are published by [`RelayersAvailability`]
+    pub fn events(&self) -> Messages<AvailabilityTransition> {
+        self.events.messages()
+    }
+
+    /// Record the result of a check. Returns the transition if the availability changed
+    pub fn record_check(&self, enabled_relayers: usize) -> Option<AvailabilityTransition> {
+        let mut state = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+
+        let available = match (state.available, enabled_relayers > 0) {
+            (true, true) | (false, false) => {
+                state.healthy_checks = 0;
+                return None;
+            },
+            (true, false) => false,
+            (false, true) => {
+                state.healthy_checks += 1;
+                if state.healthy_checks < AVAILABILITY_RECOVERY_CHECKS {
+                    return None;
+                }
+
+                true
+            },
+        };
+
+        let at = now();
+        let transition = AvailabilityTransition {
+            at,
+            available,
+            enabled_relayers,
+            previous_state_seconds: at.saturating_sub(state.since),
+        };
+
+        state.available = available;
+        state.healthy_checks = 0;
+        state.since = at;
+        state.history.push_back(transition);
+        if state.history.len() > AVAILABILITY_HISTORY_LENGTH {
+            state.history.pop_front();
+        }
+
+        Some(transition)
+    }
+
+    async fn publish(&self, transition: AvailabilityTransition) {
+        let state = if transition.available { "available" } else { "unavailable" };
+        metric!(counter[relayers_availability_transition] = 1, state = state);
+        metric!(histogram[relayers_availability_state_duration_seconds] = transition.previous_state_seconds);
+
+        self.events.publish::<RelayersAvailability>(transition).await;
+    }
+
+    /// Forward the transitions of the other instances to the local receivers, see [`RelayerEvents::listen`]
+    pub(crate) async fn listen(&self) -> Result<(), Error> {
+        self.events.listen().await
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
 
 pub struct EnabledRelayersService {
     context: Context,
@@ -32,16 +174,64 @@ impl Service for EnabledRelayersService {
             ticker.tick().await;
 
             let enabled_relayers = self.context.relayers_locks.count_enabled_relayers().await;
+            let fleet = self.context.fleet.as_str();
             if previous_available_relayers_count != enabled_relayers {
                 if enabled_relayers > 0 {
-                    info!("{} enabled relayers", enabled_relayers);
+                    info!("{} enabled relayers in the {} fleet", enabled_relayers, fleet);
                 }
                 previous_available_relayers_count = enabled_relayers;
             }
             if enabled_relayers == 0 {
-                error!("No enabled relayer. Please check the STRK balance of the relayers.");
+                error!("No enabled relayer in the {} fleet. Please check the STRK balance of the relayers.", fleet);
             }
-            metric!(gauge[available_relayers] = enabled_relayers)
+            metric!(gauge[available_relayers] = enabled_relayers, fleet = fleet);
+
+            if let Some(transition) = self.context.availability.record_check(enabled_relayers) {
+                match transition.available {
+                    true => info!("Relayers of the {} fleet available again after {}s", fleet, transition.previous_state_seconds),
+                    false => warn!("Relayers of the {} fleet unavailable after {}s", fleet, transition.previous_state_seconds),
+                }
+                self.context.availability.publish(transition).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::monitoring::availability::{RelayerAvailability, AVAILABILITY_RECOVERY_CHECKS};
+
+    #[test]
+    fn availability_recovers_after_consecutive_healthy_checks() {
+        let availability = RelayerAvailability::default();
+        assert!(availability.is_available());
+        assert_eq!(availability.record_check(2), None);
+
+        let transition = availability.record_check(0).unwrap();
+        assert!(!transition.available);
+        assert!(!availability.is_available());
+
+        for _ in 1..AVAILABILITY_RECOVERY_CHECKS {
+            assert_eq!(availability.record_check(1), None);
+            assert!(!availability.is_available());
+        }
+
+        let transition = availability.record_check(1).unwrap();
+        assert!(transition.available);
+        assert!(availability.is_available());
+        assert_eq!(availability.history().len(), 2);
+    }
+
+    #[test]
+    fn unhealthy_check_resets_the_recovery() {
+        let availability = RelayerAvailability::default();
+        availability.record_check(0);
+
+        for _ in 1..AVAILABILITY_RECOVERY_CHECKS {
+            availability.record_check(1);
         }
+        assert_eq!(availability.record_check(0), None);
+        assert_eq!(availability.record_check(1), None);
+        assert!(!availability.is_available());
     }
 }
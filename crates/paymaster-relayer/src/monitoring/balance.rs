@@ -6,7 +6,6 @@ use num_traits::ToPrimitive;
 use paymaster_common::concurrency::ConcurrentExecutor;
 use paymaster_common::service::{Error, Service};
 use paymaster_common::{metric, service_check, task};
-use paymaster_starknet::constants::Token;
 use starknet::core::types::Felt;
 use tokio::time;
 
@@ -62,7 +61,7 @@ impl RelayerBalanceMonitoring {
 
         for relayer in relayers {
             executor.register(task!(|ctx| {
-                ctx.starknet.fetch_balance(Token::STRK_ADDRESS, relayer).await.map(|x| (relayer, x))
+                ctx.starknet.fetch_balance(ctx.starknet.tokens().strk, relayer).await.map(|x| (relayer, x))
             }));
         }
 
@@ -77,7 +76,7 @@ impl RelayerBalanceMonitoring {
             let balance_in_strk = balance.to_biguint().to_f64().unwrap_or_default();
             let balance_in_strk_normalized = balance_in_strk / 1e18;
 
-            metric!(gauge [ relayer_balance_in_strk ] = balance_in_strk_normalized, relayer = relayer.to_fixed_hex_string());
+            metric!(gauge [ relayer_balance_in_strk ] = balance_in_strk_normalized, relayer = relayer.to_fixed_hex_string(), fleet = self.context.fleet.as_str());
             balances.insert(relayer, balance);
         }
 
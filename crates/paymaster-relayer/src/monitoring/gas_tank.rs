@@ -5,7 +5,6 @@ use async_trait::async_trait;
 use paymaster_common::concurrency::ConcurrentExecutor;
 use paymaster_common::service::{Error, Service};
 use paymaster_common::{metric, service_check, task};
-use paymaster_starknet::constants::Token;
 use paymaster_starknet::math::denormalize_felt;
 use starknet::core::types::Felt;
 use tokio::time;
@@ -70,7 +69,7 @@ impl GasTankBalanceMonitoring {
             }
 
             // Convert to STRK (no conversion needed if already STRK)
-            let balance_in_strk = if token == Token::STRK_ADDRESS {
+            let balance_in_strk = if token == self.context.starknet.tokens().strk {
                 balance
             } else {
                 match self.context.price.convert_token_to_strk(token, balance).await {
@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use paymaster_common::service::{Error, Service};
+use paymaster_common::{metric, service_check, service_info, service_warn};
+use tokio::time;
+
+use crate::lock::shared::SharedLockLayer;
+use crate::lock::LockLayer;
+use crate::Context;
+
+/// Age above which a lock is reported as long-held
+const LONG_HELD_LOCK: Duration = Duration::from_secs(60);
+
+/// Publishes the lock state of the relayers so that leaked or long-held locks can be spotted
+pub struct RelayerLockMonitoring {
+    context: Context,
+}
+
+#[async_trait]
+impl Service for RelayerLockMonitoring {
+    type Context = Context;
+
+    const NAME: &'static str = "RelayerLockMonitoring";
+
+    async fn new(context: Self::Context) -> Self {
+        Self { context }
+    }
+
+    async fn run(self) -> Result<(), Error> {
+        let mut ticker = time::interval(Duration::from_secs(15));
+        loop {
+            ticker.tick().await;
+
+            let statuses = service_check!(self.context.relayers_locks.lock_statuses().await => continue);
+            for status in statuses {
+                let relayer = status.address.to_fixed_hex_string();
+                let age = status.age.unwrap_or_default();
+
+                metric!(gauge[relayer_locked] = status.locked as u64, relayer = relayer.as_str());
+                metric!(gauge[relayer_lock_age_seconds] = age.as_secs(), relayer = relayer.as_str());
+
+                if age > LONG_HELD_LOCK {
+                    service_warn!("relayer {} locked by {} for {}s", relayer, status.holder.unwrap_or_default(), age.as_secs());
+                }
+            }
+        }
+    }
+}
+
+/// Pings the Redis of the shared lock layer, marking the layer unhealthy while Redis is unreachable so that the
+/// locks are refused at once rather than each of them waiting for Redis to time out, or taken locally over the
+/// reserved relayers when a fallback is configured
+pub struct LockLayerHealthProbe {
+    layer: SharedLockLayer,
+}
+
+#[async_trait]
+impl Service for LockLayerHealthProbe {
+    type Context = Context;
+
+    const NAME: &'static str = "LockLayerHealthProbe";
+
+    async fn new(context: Self::Context) -> Self {
+        let LockLayer::Shared(layer) = context.relayers_locks.clone() else {
+            panic!("lock layer is not shared")
+        };
+
+        Self { layer }
+    }
+
+    async fn run(self) -> Result<(), Error> {
+        let mut ticker = time::interval(self.layer.keepalive_interval());
+        loop {
+            ticker.tick().await;
+
+            let was_healthy = self.layer.is_healthy();
+            let result = self.layer.probe().await;
+            metric!(gauge[lock_layer_healthy] = result.is_ok() as u64);
+
+            match result {
+                Err(e) if was_healthy && self.layer.has_fallback() => {
+                    service_warn!("lock layer is unreachable, falling back to the local locks of the reserved relayers: {}", e)
+                },
+                Err(e) if was_healthy => service_warn!("lock layer is unreachable, locks are refused until it recovers: {}", e),
+                Ok(()) if !was_healthy => service_info!("lock layer is reachable again"),
+                _ => {},
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
 pub mod availability;
 pub mod balance;
 pub mod gas_tank;
+pub mod lock;
+pub mod spend;
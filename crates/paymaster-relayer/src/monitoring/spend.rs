@@ -0,0 +1,110 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use paymaster_common::service::{Error, Service};
+use paymaster_common::{metric, service_check, service_error, service_warn};
+use starknet::core::types::Felt;
+use tokio::time;
+
+use crate::spend::{SpendWindow, UnsettledSpend};
+use crate::Context;
+
+/// Age above which a transaction whose receipt is still unknown stays counted at its estimated fee
+const SETTLEMENT_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// Settles the spend of the transactions submitted by this instance with their actual fee, then disables the
+/// relayers of the fleet exceeding their spend caps. The disabled relayers are shared with the other instances
+/// through the lock layer, so that each check replaces the set computed by the previous one.
+pub struct SpendCapMonitoring {
+    context: Context,
+    addresses: Vec<Felt>,
+    exceeded: HashMap<Felt, SpendWindow>,
+}
+
+#[async_trait]
+impl Service for SpendCapMonitoring {
+    type Context = Context;
+
+    const NAME: &'static str = "SpendCapMonitoring";
+
+    async fn new(context: Context) -> Self {
+        Self {
+            addresses: context.configuration.relayers.addresses.clone(),
+            exceeded: HashMap::new(),
+            context,
+        }
+    }
+
+    async fn run(mut self) -> Result<(), Error> {
+        let mut ticker = time::interval(Duration::from_secs(10));
+        loop {
+            ticker.tick().await;
+
+            self.settle_spends().await;
+
+            let spend = service_check!(self.context.relayers_locks.fleet_spent(&self.addresses).await => continue);
+            let exceeded = self.context.configuration.relayers.spend_caps.exceeded_by(&spend);
+            for (relayer, window) in &exceeded {
+                if self.exceeded.contains_key(relayer) {
+                    continue;
+                }
+
+                let relayer = relayer.to_fixed_hex_string();
+                service_error!("relayer {} disabled, {} spend cap exceeded", relayer, window.name());
+                metric!(counter[relayer_spend_cap_exceeded] = 1, relayer = relayer.as_str(), window = window.name());
+            }
+
+            let disabled: HashSet<Felt> = exceeded.keys().cloned().collect();
+            service_check!(self.context.relayers_locks.set_disabled_relayers(&disabled).await => continue);
+            self.exceeded = exceeded;
+        }
+    }
+}
+
+impl SpendCapMonitoring {
+    // Replace the estimated fee of the transactions included since the last check by their actual fee. The
+    // transactions which are not included yet are checked again on the next round.
+    async fn settle_spends(&self) {
+        let unsettled = self.context.relayers.unsettled_spends();
+
+        let mut remaining = vec![];
+        for spend in unsettled.take() {
+            match self.settle(&spend).await {
+                Ok(true) => {},
+                Ok(false) if spend.submitted_at.elapsed() > SETTLEMENT_TIMEOUT => {
+                    service_warn!(
+                        "transaction {} of relayer {} not found, its spend is kept at its estimated fee",
+                        spend.transaction_hash.to_fixed_hex_string(),
+                        spend.relayer.to_fixed_hex_string()
+                    );
+                },
+                Ok(false) => remaining.push(spend),
+                Err(e) => {
+                    service_warn!("could not settle the spend of transaction {}: {}", spend.transaction_hash.to_fixed_hex_string(), e);
+                    remaining.push(spend);
+                },
+            }
+        }
+
+        unsettled.restore(remaining);
+    }
+
+    // Returns whether the spend of the transaction was settled
+    async fn settle(&self, spend: &UnsettledSpend) -> Result<bool, Error> {
+        let receipt = match self.context.starknet.get_transaction_receipt(spend.transaction_hash).await {
+            Ok(receipt) => receipt,
+            Err(paymaster_starknet::Error::TransactionNotFound) => return Ok(false),
+            Err(e) => return Err(Error::from(e)),
+        };
+
+        let actual_fee = receipt.receipt.actual_fee().amount;
+        self.context
+            .relayers_locks
+            .correct_spend(spend.relayer, spend.estimated_fee, actual_fee)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(true)
+    }
+}
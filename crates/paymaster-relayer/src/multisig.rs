@@ -0,0 +1,152 @@
+use std::fs;
+use std::path::PathBuf;
+
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_starknet::transaction::multisig::MultisigProposal;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use crate::Error;
+
+/// Configuration of a gas tank which is a multisig account. Rather than executing its transactions directly,
+/// the paymaster signs them with the key of the gas tank (which must be one of the signers) and stores them as
+/// proposals until enough signers approved them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultisigConfiguration {
+    /// Number of signatures required by the multisig to execute a transaction
+    pub threshold: usize,
+
+    /// Directory where the pending proposals are stored. It must be shared with the signers approving them.
+    pub proposals_directory: PathBuf,
+}
+
+impl Validate for MultisigConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.threshold > 0, "threshold", "must be greater than 0");
+        report.ensure(!self.proposals_directory.as_os_str().is_empty(), "proposals_directory", "must not be empty");
+    }
+}
+
+/// Proposals of the gas tank waiting for the approval of the signers, one JSON file per proposal
+#[derive(Debug, Clone)]
+pub struct ProposalStore {
+    directory: PathBuf,
+}
+
+impl ProposalStore {
+    pub fn new(configuration: &MultisigConfiguration) -> Self {
+        Self {
+            directory: configuration.proposals_directory.clone(),
+        }
+    }
+
+    fn path(&self, transaction_hash: Felt) -> PathBuf {
+        self.directory.join(format!("{}.json", transaction_hash.to_fixed_hex_string()))
+    }
+
+    pub fn save(&self, proposal: &MultisigProposal) -> Result<(), Error> {
+        fs::create_dir_all(&self.directory).map_err(|e| Error::Multisig(e.to_string()))?;
+
+        let data = serde_json::to_vec_pretty(proposal).map_err(|e| Error::Multisig(e.to_string()))?;
+        fs::write(self.path(proposal.transaction_hash), data).map_err(|e| Error::Multisig(e.to_string()))
+    }
+
+    pub fn remove(&self, transaction_hash: Felt) -> Result<(), Error> {
+        fs::remove_file(self.path(transaction_hash)).map_err(|e| Error::Multisig(e.to_string()))
+    }
+
+    /// Returns the stored proposals ordered by nonce
+    pub fn proposals(&self) -> Result<Vec<MultisigProposal>, Error> {
+        if !self.directory.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut proposals = vec![];
+        for entry in fs::read_dir(&self.directory).map_err(|e| Error::Multisig(e.to_string()))? {
+            let path = entry.map_err(|e| Error::Multisig(e.to_string()))?.path();
+            if path.extension().is_some_and(|x| x == "json") {
+                let data = fs::read(&path).map_err(|e| Error::Multisig(e.to_string()))?;
+                proposals.push(serde_json::from_slice::<MultisigProposal>(&data).map_err(|e| Error::Multisig(e.to_string()))?);
+            }
+        }
+
+        proposals.sort_by_key(|x| x.nonce);
+        Ok(proposals)
+    }
+
+    /// Remove the proposals whose nonce has been consumed since they can no longer be executed and returns the
+    /// remaining ones
+    pub fn prune(&self, nonce: Felt) -> Result<Vec<MultisigProposal>, Error> {
+        let mut pending = vec![];
+        for proposal in self.proposals()? {
+            if proposal.nonce < nonce {
+                self.remove(proposal.transaction_hash)?;
+            } else {
+                pending.push(proposal);
+            }
+        }
+
+        Ok(pending)
+    }
+
+    /// Returns the nonce of the next proposal given the current `nonce` of the gas tank. Proposals are queued after
+    /// the ones waiting for approval, which take the nonces in between.
+    pub fn next_nonce(&self, nonce: Felt) -> Result<Felt, Error> {
+        let pending = self.prune(nonce)?;
+
+        Ok(pending.last().map(|x| x.nonce + Felt::ONE).unwrap_or(nonce))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use paymaster_starknet::transaction::multisig::MultisigProposal;
+    use paymaster_starknet::transaction::Calls;
+    use starknet::core::types::{Felt, ResourceBounds, ResourceBoundsMapping};
+
+    use crate::multisig::{MultisigConfiguration, ProposalStore};
+
+    fn a_proposal(nonce: u64) -> MultisigProposal {
+        let bounds = ResourceBounds {
+            max_amount: 0,
+            max_price_per_unit: 0,
+        };
+
+        MultisigProposal {
+            account: Felt::ONE,
+            transaction_hash: Felt::from(100 + nonce),
+            calls: Calls::empty(),
+            nonce: Felt::from(nonce),
+            resource_bounds: ResourceBoundsMapping {
+                l1_gas: bounds.clone(),
+                l1_data_gas: bounds.clone(),
+                l2_gas: bounds,
+            },
+            tip: 0,
+            signatures: vec![],
+        }
+    }
+
+    #[test]
+    fn consumed_proposals_are_pruned() {
+        let store = ProposalStore::new(&MultisigConfiguration {
+            threshold: 2,
+            proposals_directory: env::temp_dir().join(format!("paymaster-proposals-{}", std::process::id())),
+        });
+
+        store.save(&a_proposal(1)).unwrap();
+        store.save(&a_proposal(2)).unwrap();
+
+        let pending = store.prune(Felt::TWO).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].nonce, Felt::TWO);
+        assert_eq!(store.proposals().unwrap().len(), 1);
+
+        // The next proposal is queued after the pending one
+        assert_eq!(store.next_nonce(Felt::TWO).unwrap(), Felt::THREE);
+
+        store.remove(pending[0].transaction_hash).unwrap();
+    }
+}
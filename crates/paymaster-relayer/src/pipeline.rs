@@ -0,0 +1,196 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use starknet::core::types::Felt;
+
+/// Duration after which a transaction in flight which is the next one to be included on chain is considered
+/// dropped, leaving the nonces of the following transactions unusable
+const STUCK_NONCE_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy)]
+struct InFlightTransaction {
+    fee: Felt,
+    submitted_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct RelayerPipeline {
+    next_nonce: Option<Felt>,
+
+    /// Transactions in flight indexed by nonce
+    in_flight: BTreeMap<Felt, InFlightTransaction>,
+}
+
+/// Keep track, for each relayer, of the next usable nonce and of the transactions submitted but not yet
+/// included on chain. Consecutive transactions of a relayer can then be submitted without waiting for the
+/// receipt of the previous one, as long as the number of transactions in flight stays within the window.
+///
+/// The state is local to the instance, so the transactions are only pipelined when the relayers are locked by
+/// this instance alone. With relayers shared through Redis the pipeline is disabled: the nonce comes from the
+/// lock or from the chain, and only the cost of the transactions in flight is tracked.
+#[derive(Clone)]
+pub struct NoncePipeline {
+    window: Option<usize>,
+    stuck_timeout: Duration,
+    inner: Arc<Mutex<HashMap<Felt, RelayerPipeline>>>,
+}
+
+impl NoncePipeline {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: Some(window),
+            stuck_timeout: STUCK_NONCE_TIMEOUT,
+            inner: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Pipeline of relayers shared with other instances, which never provides the next nonce nor limits the
+    /// transactions in flight
+    pub fn disabled() -> Self {
+        Self { window: None, ..Self::new(1) }
+    }
+
+    pub fn with_stuck_timeout(mut self, timeout: Duration) -> Self {
+        self.stuck_timeout = timeout;
+        self
+    }
+
+    /// Returns the next nonce to use for the given relayer if it is known
+    pub fn next_nonce(&self, relayer: Felt) -> Option<Felt> {
+        self.window?;
+
+        let pipelines = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        pipelines.get(&relayer).and_then(|x| x.next_nonce)
+    }
+
+    /// Returns the number of transactions of the given relayer which are not included on chain yet
+    pub fn in_flight(&self, relayer: Felt) -> usize {
+        let pipelines = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        pipelines.get(&relayer).map(|x| x.in_flight.len()).unwrap_or_default()
+    }
+
+    /// Returns true if a new transaction can be submitted by the given relayer
+    pub fn has_capacity(&self, relayer: Felt) -> bool {
+        self.window.is_none_or(|window| self.in_flight(relayer) < window)
+    }
+
+    /// Returns true if the transaction of the given relayer expected to be included next, given its `chain_nonce`,
+    /// is in flight for longer than the timeout. It was most likely dropped and the transactions following it
+    /// will never be included until its nonce is used again.
+    pub fn is_stuck(&self, relayer: Felt, chain_nonce: Felt) -> bool {
+        let pipelines = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        pipelines
+            .get(&relayer)
+            .and_then(|x| x.in_flight.get(&chain_nonce))
+            .is_some_and(|x| x.submitted_at.elapsed() > self.stuck_timeout)
+    }
+
+    /// Returns the estimated fee (in FRI) of the transactions of the given relayer which are not included on chain yet
+    pub fn in_flight_cost(&self, relayer: Felt) -> Felt {
+        let pipelines = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        pipelines
+            .get(&relayer)
+            .map(|x| {
+                x.in_flight
+                    .values()
+                    .fold(Felt::ZERO, |total, transaction| total + transaction.fee)
+            })
+            .unwrap_or_default()
+    }
+
+    /// Register a transaction submitted by the relayer with the given nonce and estimated fee
+    pub fn submitted(&self, relayer: Felt, nonce: Felt, fee: Felt) {
+        let mut pipelines = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let pipeline = pipelines.entry(relayer).or_default();
+
+        pipeline.in_flight.insert(
+            nonce,
+            InFlightTransaction {
+                fee,
+                submitted_at: Instant::now(),
+            },
+        );
+        pipeline.next_nonce = Some(nonce + Felt::ONE);
+    }
+
+    /// Update the pipeline of the relayer given its nonce on chain. Transactions with a lower nonce
+    /// are included and no longer in flight.
+    pub fn confirm(&self, relayer: Felt, chain_nonce: Felt) {
+        let mut pipelines = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let pipeline = pipelines.entry(relayer).or_default();
+
+        pipeline.in_flight = pipeline.in_flight.split_off(&chain_nonce);
+        if pipeline.next_nonce.is_none_or(|x| x < chain_nonce) {
+            pipeline.next_nonce = Some(chain_nonce);
+        }
+    }
+
+    /// Forget everything about the relayer, typically when its nonce turned out to be invalid or stuck
+    pub fn reset(&self, relayer: Felt) {
+        let mut pipelines = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        pipelines.remove(&relayer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use starknet::core::types::Felt;
+
+    use crate::pipeline::NoncePipeline;
+
+    #[test]
+    fn pipeline_is_bounded_by_window() {
+        let pipeline = NoncePipeline::new(2);
+        let relayer = Felt::ONE;
+
+        assert_eq!(pipeline.next_nonce(relayer), None);
+
+        pipeline.submitted(relayer, Felt::from(5), Felt::from(100));
+        pipeline.submitted(relayer, Felt::from(6), Felt::from(200));
+        assert_eq!(pipeline.next_nonce(relayer), Some(Felt::from(7)));
+        assert!(!pipeline.has_capacity(relayer));
+
+        pipeline.confirm(relayer, Felt::from(6));
+        assert_eq!(pipeline.in_flight(relayer), 1);
+        assert_eq!(pipeline.in_flight_cost(relayer), Felt::from(200));
+        assert_eq!(pipeline.next_nonce(relayer), Some(Felt::from(7)));
+        assert!(pipeline.has_capacity(relayer));
+
+        pipeline.reset(relayer);
+        assert_eq!(pipeline.next_nonce(relayer), None);
+        assert_eq!(pipeline.in_flight(relayer), 0);
+    }
+
+    #[test]
+    fn dropped_transaction_is_detected() {
+        let pipeline = NoncePipeline::new(2).with_stuck_timeout(Duration::ZERO);
+        let relayer = Felt::ONE;
+
+        pipeline.submitted(relayer, Felt::from(5), Felt::from(100));
+        pipeline.submitted(relayer, Felt::from(6), Felt::from(200));
+        std::thread::sleep(Duration::from_millis(1));
+
+        // Only the transaction which should be included next blocks the following ones
+        assert!(pipeline.is_stuck(relayer, Felt::from(5)));
+        assert!(!pipeline.is_stuck(relayer, Felt::from(4)));
+
+        pipeline.confirm(relayer, Felt::from(7));
+        assert!(!pipeline.is_stuck(relayer, Felt::from(7)));
+    }
+
+    #[test]
+    fn disabled_pipeline_only_tracks_the_cost() {
+        let pipeline = NoncePipeline::disabled();
+        let relayer = Felt::ONE;
+
+        pipeline.submitted(relayer, Felt::from(5), Felt::from(100));
+        pipeline.submitted(relayer, Felt::from(6), Felt::from(200));
+
+        assert_eq!(pipeline.next_nonce(relayer), None);
+        assert!(pipeline.has_capacity(relayer));
+        assert_eq!(pipeline.in_flight_cost(relayer), Felt::from(300));
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+
+/// Period during which the rebalancing and the swaps must not run, e.g during a planned chain upgrade or a
+/// sponsor campaign. Bounds are unix timestamps in seconds, the start being included and the end excluded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Validate for MaintenanceWindow {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.start < self.end, "end", "must be after start");
+    }
+}
+
+impl MaintenanceWindow {
+    pub fn contains(&self, timestamp: u64) -> bool {
+        self.start <= timestamp && timestamp < self.end
+    }
+}
+
+/// Returns the window the current time falls in, if any
+pub fn active_window(windows: &[MaintenanceWindow]) -> Option<&MaintenanceWindow> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default();
+
+    windows.iter().find(|window| window.contains(now))
+}
+
+#[cfg(test)]
+mod tests {
+    use paymaster_common::validation::Validate;
+
+    use crate::rebalancing::maintenance::{active_window, MaintenanceWindow};
+
+    #[test]
+    fn window_bounds_are_half_open() {
+        let window = MaintenanceWindow { start: 100, end: 200 };
+
+        assert!(!window.contains(99));
+        assert!(window.contains(100));
+        assert!(window.contains(199));
+        assert!(!window.contains(200));
+    }
+
+    #[test]
+    fn active_window_is_found() {
+        let past = MaintenanceWindow { start: 0, end: 1 };
+        let current = MaintenanceWindow { start: 1, end: u64::MAX };
+
+        assert_eq!(active_window(&[past]), None);
+        assert_eq!(active_window(&[past, current]), Some(&current));
+    }
+
+    #[test]
+    fn empty_window_is_reported() {
+        let errors = MaintenanceWindow { start: 200, end: 100 }.validate_all().unwrap_err();
+        assert!(errors.contains("end"));
+    }
+}
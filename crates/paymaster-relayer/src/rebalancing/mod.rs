@@ -1,28 +1,42 @@
 use std::collections::HashSet;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
-use paymaster_common::concurrency::ConcurrentExecutor;
+use paymaster_common::concurrency::{ConcurrentExecutor, RetryPolicy};
+use paymaster_common::metric;
 use paymaster_common::service::{Error as ServiceError, Service};
-use paymaster_common::task;
+use paymaster_common::validation::{Validate, ValidationReport};
 use paymaster_prices::PriceConfiguration;
-use paymaster_starknet::constants::Token;
 use paymaster_starknet::math::denormalize_felt;
 use paymaster_starknet::transaction::{Calls, TokenTransfer};
-use paymaster_starknet::{Configuration as StarknetConfiguration, StarknetAccount, StarknetAccountConfiguration};
+use paymaster_starknet::{Configuration as StarknetConfiguration, StarknetAccountConfiguration};
 use serde::{Deserialize, Serialize};
-use starknet::accounts::{Account, ConnectedAccount};
 use starknet::core::types::Felt;
 use tokio::time::interval;
 use tracing::{error, info};
 
 use crate::context::Context;
-use crate::swap::{SwapClient, SwapConfiguration};
+use crate::gas_tank::GasTankSubmission;
+use crate::multisig::MultisigConfiguration;
+use crate::swap::{SwapClient, SwapClientConfigurator, SwapConfiguration};
+use crate::treasury::{self, TreasuryPolicyConfiguration};
 use crate::RelayersConfiguration;
 
+pub mod maintenance;
+pub mod strategy;
+use maintenance::MaintenanceWindow;
+use strategy::{RebalancingStrategy, RebalancingStrategyConfiguration};
+
+/// Maximum duration of a relayer balance fetch, retries included
+const BALANCE_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct RelayerBalance {
-    relayer: Felt,
-    balance: Felt,
+    pub relayer: Felt,
+    pub balance: Felt,
+
+    /// STRK spent by the relayer during the current day
+    pub usage: Felt,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -54,22 +68,26 @@ impl OptionalRebalancingConfiguration {
         }
     }
 
+    pub fn treasury_policy(&self) -> Option<&TreasuryPolicyConfiguration> {
+        self.0.as_ref().and_then(|config| config.treasury_policy.as_ref())
+    }
+
     pub fn initialize(config: Option<RebalancingConfiguration>) -> Self {
         OptionalRebalancingConfiguration(config)
     }
 
-    pub fn validate(&self, min_relayer_balance: Felt) -> Result<(), ServiceError> {
+    /// Validate the rebalancing configuration, if any, against the minimum balance of the relayers
+    pub fn validate_with_min_balance(&self, min_relayer_balance: Felt, report: &mut ValidationReport) {
         if let Some(rebalancing_config) = &self.0 {
-            rebalancing_config.validate()?;
+            rebalancing_config.validate_into(report);
 
             // Ensure trigger_balance > min_relayer_balance
-            if rebalancing_config.trigger_balance <= min_relayer_balance {
-                return Err(ServiceError::new(
-                    "trigger_balance must be greater than min_relayer_balance to ensure relayers are rebalanced before being disabled",
-                ));
-            }
+            report.ensure(
+                rebalancing_config.trigger_balance > min_relayer_balance,
+                "trigger_balance",
+                "trigger_balance must be greater than min_relayer_balance to ensure relayers are rebalanced before being disabled",
+            );
         }
-        Ok(())
     }
 }
 
@@ -84,16 +102,34 @@ pub struct RebalancingConfiguration {
 
     // Configuration for the swap service
     pub swap_config: SwapConfiguration,
+
+    // How the available funds are distributed among the relayers
+    #[serde(default)]
+    pub strategy: RebalancingStrategyConfiguration,
+
+    // Periods during which neither the rebalancing nor the swaps run
+    #[serde(default)]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+
+    // Mix of tokens kept in the gas tank, every token is swapped to STRK when not set
+    #[serde(default)]
+    pub treasury_policy: Option<TreasuryPolicyConfiguration>,
 }
 
-impl RebalancingConfiguration {
-    pub fn validate(&self) -> Result<(), ServiceError> {
-        if self.check_interval <= self.swap_config.swap_interval {
-            return Err(ServiceError::new(
-                "check_interval must be greater than swap_interval to reduce price impact over time",
-            ));
+impl Validate for RebalancingConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(
+            self.check_interval > self.swap_config.swap_interval,
+            "check_interval",
+            "check_interval must be greater than swap_interval to reduce price impact over time",
+        );
+        report.field("swap_config", &self.swap_config);
+        for (i, window) in self.maintenance_windows.iter().enumerate() {
+            report.field(&format!("maintenance_windows[{}]", i), window);
+        }
+        if let Some(treasury_policy) = &self.treasury_policy {
+            report.field("treasury_policy", treasury_policy);
         }
-        self.swap_config.validate()
     }
 }
 
@@ -101,16 +137,70 @@ impl RebalancingConfiguration {
 pub struct RelayerManagerConfiguration {
     pub starknet: StarknetConfiguration,
     pub gas_tank: StarknetAccountConfiguration,
+    pub gas_tank_multisig: Option<MultisigConfiguration>,
     pub relayers: RelayersConfiguration,
     pub supported_tokens: HashSet<Felt>,
     pub price: PriceConfiguration,
 }
 
 impl RelayerManagerConfiguration {
+    /// Validate the whole configuration, reporting every problem found at once
     pub fn validate(&self) -> Result<(), ServiceError> {
+        self.validate_all().map_err(ServiceError::from)
+    }
+}
+
+impl Validate for RelayerManagerConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.field("starknet", &self.starknet);
+        report.field("gas_tank", &self.gas_tank);
+        if let Some(multisig) = &self.gas_tank_multisig {
+            report.field("gas_tank_multisig", multisig);
+        }
+        report.field("price", &self.price);
+
+        if let Some(chain_id) = self.price.chain_id() {
+            report.ensure(
+                chain_id.as_felt() == self.starknet.chain_id.as_felt(),
+                "price.principal.starknet.chain_id",
+                format!(
+                    "chain id {} does not match starknet chain id {}",
+                    chain_id.as_identifier(),
+                    self.starknet.chain_id.as_identifier()
+                ),
+            );
+        }
+
         // Validate relayers configuration (which includes rebalancing validation)
-        self.relayers.validate()?;
-        Ok(())
+        report.field("relayers", &self.relayers);
+
+        if let Some(policy) = self.relayers.rebalancing.treasury_policy() {
+            let strk = self.starknet.tokens().strk;
+            report.ensure(
+                policy.weights.keys().all(|x| *x == strk || self.supported_tokens.contains(x)),
+                "relayers.rebalancing.treasury_policy.weights",
+                "must only contain supported tokens",
+            );
+            report.ensure(
+                policy.weights.contains_key(&strk),
+                "relayers.rebalancing.treasury_policy.weights",
+                "must contain the weight of STRK",
+            );
+        }
+
+        if self.relayers.rebalancing.has_configuration() {
+            if let SwapClientConfigurator::AVNU(swap_client) = &self.relayers.rebalancing.swap_config().swap_client_config {
+                report.ensure(
+                    swap_client.chain_id.as_felt() == self.starknet.chain_id.as_felt(),
+                    "relayers.rebalancing.swap_config.swap_client_config.chain_id",
+                    format!(
+                        "chain id {} does not match starknet chain id {}",
+                        swap_client.chain_id.as_identifier(),
+                        self.starknet.chain_id.as_identifier()
+                    ),
+                );
+            }
+        }
     }
 }
 
@@ -118,7 +208,7 @@ pub struct RelayerRebalancingService {
     context: Context,
     rebalancing_configuration: RebalancingConfiguration,
     swap_configuration: SwapConfiguration,
-    gas_tank: StarknetAccount,
+    strategy: Arc<dyn RebalancingStrategy>,
     supported_tokens: HashSet<Felt>,
     swap_client: SwapClient,
 }
@@ -134,14 +224,14 @@ impl Service for RelayerRebalancingService {
             panic!("no rebalancing configuration")
         };
         let swap_configuration = rebalancing_configuration.swap_config.clone();
+        let strategy = rebalancing_configuration.strategy.build();
         let supported_tokens = context.configuration.supported_tokens.clone();
         let swap_client = SwapClient::new(&swap_configuration.swap_client_config);
-        let gas_tank = context.starknet.initialize_account(&context.configuration.gas_tank);
         Self {
             context,
             rebalancing_configuration,
             swap_configuration,
-            gas_tank,
+            strategy,
             supported_tokens,
             swap_client,
         }
@@ -156,6 +246,13 @@ impl Service for RelayerRebalancingService {
 
         loop {
             swap_check_ticker.tick().await;
+
+            if let Some(window) = maintenance::active_window(&self.rebalancing_configuration.maintenance_windows) {
+                info!("Maintenance window until {} in progress, skipping rebalancing and swaps", window.end);
+                metric!(counter[rebalancing_round_suppressed] = 1);
+                continue;
+            }
+
             info!("Swap interval reached, try to swap tokens to STRK");
             // Swap tokens to STRK with error handling
             let (swap_calls, swap_resulted_strk_balance) = match self.swap_to_strk_calls().await {
@@ -195,35 +292,28 @@ impl Service for RelayerRebalancingService {
             // If there are no calls to execute, skip
             if calls.is_empty() {
                 info!("Nothing to execute, skipping");
-            } else {
-                // Handle estimation errors gracefully
-                let calls_estimate = match calls.estimate(&self.gas_tank, None).await {
-                    Ok(estimate) => estimate,
-                    Err(e) => {
-                        error!("Failed to estimate calls for rebalancing, skip this round: {}", e);
-                        continue; // Skip this iteration and try again next time
-                    },
-                };
+                continue;
+            }
 
-                let nonce = match self.gas_tank.get_nonce().await {
-                    Ok(nonce) => nonce,
-                    Err(e) => {
-                        error!("Failed to get nonce for rebalancing, skip this round: {}", e);
-                        continue; // Skip this iteration and try again next time
-                    },
-                };
+            // A multisig gas tank proposes the rebalancing to its signers, no other one is proposed while it waits
+            // for their approval since the balances it is computed from are outdated once it is executed
+            match self.context.gas_tank.pending_proposals().await {
+                Ok(pending) if !pending.is_empty() => {
+                    info!("{} gas tank proposals are waiting for approval, skip this round", pending.len());
+                    continue;
+                },
+                Ok(_) => {},
+                Err(e) => {
+                    error!("Failed to fetch the pending gas tank proposals, skip this round: {}", e);
+                    continue;
+                },
+            }
 
-                // Execute the rebalancing with error handling
-                match calls_estimate.execute(&self.gas_tank, nonce).await {
-                    Ok(calls_execute) => {
-                        let tx_hash = calls_execute.transaction_hash;
-                        info!("Rebalancing executed, tx hash: {:?}", tx_hash);
-                    },
-                    Err(e) => {
-                        error!("Failed to execute rebalancing: {}", e);
-                        // Continue running
-                    },
-                }
+            match self.context.gas_tank.send(&calls).await {
+                Ok(GasTankSubmission::Executed { transaction_hash }) => info!("Rebalancing executed, tx hash: {:?}", transaction_hash),
+                Ok(GasTankSubmission::Submitted { transaction_hash, .. }) => info!("Rebalancing submitted but not confirmed yet, tx hash: {:?}", transaction_hash),
+                Ok(GasTankSubmission::Proposed { transaction_hash, .. }) => info!("Rebalancing proposed to the gas tank signers, tx hash: {:?}", transaction_hash),
+                Err(e) => error!("Failed to execute rebalancing: {}", e),
             }
         }
     }
@@ -235,40 +325,36 @@ impl RelayerRebalancingService {
         let relayers = self
             .context
             .relayers
-            .get_relayers_with_stale_balances(&self.context.configuration.relayers.addresses)
+            .get_relayers_with_stale_balances(&self.context.configuration.relayers.fleet_addresses())
             .await;
         if relayers.is_empty() {
             info!("No relayers out of cache, skipping fetch and sync");
             return Ok(());
         }
-        // If there are relayers out of cache, fetch their balances
-        let mut executor = ConcurrentExecutor::new(self.context.clone(), 8);
+        // If there are relayers out of cache, fetch their balances. Each fetch is bounded so that a single
+        // stuck request cannot block the whole rebalancing round
+        let mut executor = ConcurrentExecutor::<_, Result<(Felt, Felt), ServiceError>>::new(self.context.clone(), 8).with_timeout(BALANCE_FETCH_TIMEOUT);
         for relayer in relayers.iter().copied() {
-            executor.register(task!(|env| {
-                let balance = env
-                    .starknet
-                    .fetch_balance(Token::STRK_ADDRESS, relayer)
-                    .await
-                    .map_err(ServiceError::from)?;
-
-                Ok::<(Felt, Felt), ServiceError>((relayer, balance))
-            }));
+            executor.register_with_retry(RetryPolicy::new(3, Duration::from_millis(500)), move |env| {
+                Box::pin(async move {
+                    let balance = env
+                        .starknet
+                        .fetch_balance(env.starknet.tokens().strk, relayer)
+                        .await
+                        .map_err(ServiceError::from)?;
+
+                    Ok((relayer, balance))
+                })
+            });
         }
 
-        let results = match executor.execute().await {
-            Ok(results) => results,
-            Err(e) => {
-                error!("Failed to fetch relayers balances: {}", e);
-                // Return OK to continue service operation, but log the error
-                return Ok(());
-            },
-        };
+        let results = executor.execute_all().await;
 
         let mut successful_updates = 0;
         let total_relayers = results.len();
 
         for result in results {
-            match result {
+            match result.map_err(ServiceError::from).flatten() {
                 Ok((relayer, balance)) => {
                     // Update the cache with the fetched balance
                     self.context.relayers.set_relayer_balance(relayer, balance).await;
@@ -287,9 +373,17 @@ impl RelayerRebalancingService {
 
     async fn relayers_with_synced_balances(&self) -> Vec<RelayerBalance> {
         let mut relayers: Vec<RelayerBalance> = vec![];
-        for relayer in &self.context.configuration.relayers.addresses {
+        for relayer in &self.context.configuration.relayers.fleet_addresses() {
             let balance = self.context.relayers.get_relayer_balance(relayer).await.unwrap_or(Felt::ZERO);
-            relayers.push(RelayerBalance { relayer: *relayer, balance });
+            let usage = match self.context.relayers_locks.spent(Some(*relayer)).await {
+                Ok(totals) => totals.daily,
+                Err(_) => Felt::ZERO,
+            };
+            relayers.push(RelayerBalance {
+                relayer: *relayer,
+                balance,
+                usage,
+            });
         }
         relayers
     }
@@ -322,7 +416,7 @@ impl RelayerRebalancingService {
         let gas_tank_strk_balance = match self
             .context
             .starknet
-            .fetch_balance(Token::STRK_ADDRESS, self.gas_tank.address())
+            .fetch_balance(self.context.starknet.tokens().strk, self.context.gas_tank.address())
             .await
         {
             Ok(balance) => balance,
@@ -360,12 +454,27 @@ impl RelayerRebalancingService {
         let total_tokens = self.supported_tokens.len();
 
         // Remove the STRK token from the supported tokens before swapping
+        let strk = self.context.starknet.tokens().strk;
         let mut supported_tokens_without_strk = self.supported_tokens.clone();
-        supported_tokens_without_strk.remove(&Token::STRK_ADDRESS);
+        supported_tokens_without_strk.remove(&strk);
+
+        // Only the tokens exceeding their weight in the treasury are swapped when a policy is set
+        let excess_amounts = match &self.rebalancing_configuration.treasury_policy {
+            Some(policy) => {
+                let holdings = treasury::fetch_holdings(&self.context, self.context.gas_tank.address(), &supported_tokens_without_strk).await?;
+                Some(policy.excess_amounts(strk, &holdings))
+            },
+            None => None,
+        };
 
         for token in &supported_tokens_without_strk {
             // Get token balance with error handling
-            let token_balance = match self.context.starknet.fetch_balance(*token, self.gas_tank.address()).await {
+            let token_balance = match self
+                .context
+                .starknet
+                .fetch_balance(*token, self.context.gas_tank.address())
+                .await
+            {
                 Ok(balance) => balance,
                 Err(e) => {
                     error!("Failed to fetch balance for token {:?}: {}", token, e);
@@ -373,19 +482,41 @@ impl RelayerRebalancingService {
                 },
             };
 
+            let token_balance = match &excess_amounts {
+                Some(excess_amounts) => excess_amounts.get(token).copied().unwrap_or_default().min(token_balance),
+                None => token_balance,
+            };
+
             if token_balance == Felt::ZERO {
                 info!("Nothing to swap for token {:?}, omit it", token);
                 continue;
             }
 
+            // Thin pairs are sold in tranches over the next intervals to limit the price impact
+            let sell_amount = match self
+                .swap_configuration
+                .tranche_amount(&self.swap_client, *token, strk, token_balance, self.context.gas_tank.address())
+                .await
+            {
+                Ok(amount) => amount,
+                Err(e) => {
+                    error!("Failed to quote token {:?}, omit it: {}", token, e);
+                    continue;
+                },
+            };
+            if sell_amount < token_balance {
+                info!("Swapping a tranche of {} out of {} for token {:?}", sell_amount, token_balance, token);
+                metric!(counter[swap_tranche] = 1);
+            }
+
             // Swap token to STRK
             let (swap_calls, min_received) = match self
                 .swap_client
                 .swap(
                     *token,
-                    Token::STRK_ADDRESS,
-                    token_balance,
-                    self.gas_tank.address(),
+                    strk,
+                    sell_amount,
+                    self.context.gas_tank.address(),
                     self.swap_configuration.slippage,
                     self.swap_configuration.max_price_impact,
                     self.swap_configuration.min_usd_sell_amount,
@@ -404,7 +535,7 @@ impl RelayerRebalancingService {
             // If the swap succeeds, we add the calls to the multicall
             // If the swap succeeds, we add the min received to the accumulated gas swap result
             let calls_to_validate = Calls::new(swap_calls);
-            match calls_to_validate.estimate(&self.gas_tank, None).await {
+            match self.context.gas_tank.estimate(&calls_to_validate).await {
                 Ok(_calls_estimate) => {
                     calls.merge(&calls_to_validate);
                     accumulated_gas_swap_result += min_received;
@@ -421,94 +552,25 @@ impl RelayerRebalancingService {
         Ok((calls, accumulated_gas_swap_result))
     }
 
-    /// Calculate the calls to refill the relayers to the target balance
+    /// Calculate the calls to refill the relayers according to the rebalancing strategy
     /// Consists of a multicall of transfers to the relayers
     async fn refill_relayers_calls(&self, strk_to_refill: Felt, relayers: &Vec<RelayerBalance>) -> (Calls, Felt) {
-        // Calculate the target balance
-        let final_target_balance = self.calculate_optimal_target_balance(strk_to_refill, relayers);
+        let amounts = self
+            .strategy
+            .refill_amounts(strk_to_refill, self.rebalancing_configuration.trigger_balance, relayers);
 
+        let strk = self.context.starknet.tokens().strk;
         let mut calls = Calls::new(vec![]);
         let mut min_amount_needed = Felt::ZERO;
-        // Distribute the funds equally among all relayers
-        for relayer in relayers {
-            // Calculate how much this relayer needs to reach the final target balance
-            let current_balance = relayer.balance;
-            let amount_needed = if current_balance < final_target_balance {
-                final_target_balance - current_balance
-            } else {
-                Felt::ZERO
-            };
-
+        for (relayer, amount_needed) in relayers.iter().zip(amounts) {
             // Only create a transfer call if the relayer needs funds
             if amount_needed > Felt::ZERO {
-                calls.push(TokenTransfer::new(Token::STRK_ADDRESS, relayer.relayer, amount_needed).to_call());
+                calls.push(TokenTransfer::new(strk, relayer.relayer, amount_needed).to_call());
                 min_amount_needed += amount_needed;
             }
         }
         (calls, min_amount_needed)
     }
-
-    /// Calculate the target balance for each relayer to achieve optimal homogeneous distribution after a rebalance.
-    /// Strategy:
-    /// 1) Ensure all relayers reach at least trigger_balance
-    /// 2) Distribute remaining funds to achieve homogeneous final balances
-    /// 3) Relayers with lower current balances get more funds to level the playing field
-    /// 4) Use ALL available funds (gas tank will be emptied except for 1 STRK reserve)
-    fn calculate_optimal_target_balance(&self, available_funds: Felt, relayers: &Vec<RelayerBalance>) -> Felt {
-        let trigger_balance = self.rebalancing_configuration.trigger_balance;
-
-        // If there are no relayers, return 0, there is no refill needed
-        if relayers.is_empty() {
-            return Felt::ZERO;
-        }
-
-        // Simple binary search approach to find the target balance that uses exactly all available funds
-        // We need to find the target T such that sum(max(0, T - balance_i)) = available_funds
-
-        // Start with minimum possible target (trigger_balance)
-        let mut low = trigger_balance;
-        // Maximum possible target: if we gave all funds to the relayer with lowest balance
-        let min_balance = relayers.iter().map(|r| r.balance).min().unwrap_or(Felt::ZERO);
-        let mut high = min_balance + available_funds;
-
-        // Binary search to find exact target
-        while low < high {
-            let mid_u64 = (low.try_into().unwrap_or(0u128) + high.try_into().unwrap_or(0u128)) / 2;
-            let mid = Felt::from(mid_u64);
-
-            // Calculate total funds needed to bring all relayers to this target
-            let mut funds_needed = Felt::ZERO;
-            for relayer in relayers {
-                if relayer.balance < mid {
-                    funds_needed += mid - relayer.balance;
-                }
-            }
-
-            if funds_needed == available_funds {
-                return mid;
-            } else if funds_needed < available_funds {
-                low = mid + Felt::ONE;
-            } else {
-                high = mid;
-            }
-        }
-
-        // If binary search doesn't find exact match, return the closest target that doesn't exceed available funds
-        let mut best_target = low;
-        let mut funds_needed = Felt::ZERO;
-        for relayer in relayers {
-            if relayer.balance < best_target {
-                funds_needed += best_target - relayer.balance;
-            }
-        }
-
-        // If we still need more funds than available, reduce target
-        if funds_needed > available_funds {
-            best_target = if best_target > Felt::ONE { best_target - Felt::ONE } else { best_target };
-        }
-
-        best_target.max(trigger_balance)
-    }
 }
 
 #[cfg(test)]
@@ -518,12 +580,14 @@ mod rebalancing_tests {
 
     use crate::lock::mock::MockLockLayer;
     use crate::lock::{LockLayerConfiguration, RelayerLock};
+    use crate::rebalancing::strategy::Equalize;
     use crate::rebalancing::{OptionalRebalancingConfiguration, RebalancingConfiguration, RelayerBalance};
     use crate::swap::client::mock::MockSimpleSwap;
     use crate::swap::{SwapClientConfigurator, SwapConfiguration};
     use crate::{Context, RelayerManagerConfiguration, RelayerRebalancingService, RelayersConfiguration};
     use async_trait::async_trait;
     use paymaster_common::service::Service;
+    use paymaster_common::validation::Validate;
     use paymaster_prices::mock::MockPriceOracle;
     use paymaster_prices::PriceConfiguration;
     use paymaster_starknet::constants::Token;
@@ -533,6 +597,12 @@ mod rebalancing_tests {
     use starknet::core::types::Felt;
     use starknet::macros::felt_hex;
 
+    impl RelayerRebalancingService {
+        pub(super) fn calculate_optimal_target_balance(&self, available_funds: Felt, relayers: &Vec<RelayerBalance>) -> Felt {
+            Equalize::target_balance(available_funds, self.rebalancing_configuration.trigger_balance, relayers)
+        }
+    }
+
     #[derive(Debug)]
     pub struct MockPrice;
 
@@ -584,6 +654,9 @@ mod rebalancing_tests {
                 chain_id: ChainID::Sepolia,
                 endpoint: "http://localhost:5050".to_string(),
                 fallbacks: vec![],
+                local_estimation: None,
+                endpoint_options: Default::default(),
+                tokens: None,
                 timeout: 10,
             },
             supported_tokens: HashSet::from([Token::usdc(&ChainID::Sepolia).address]),
@@ -592,19 +665,36 @@ mod rebalancing_tests {
                 addresses: relayers,
                 min_relayer_balance,
                 lock: LockLayerConfiguration::mock_with_timeout::<MockLock>(Duration::from_secs(5)),
+                max_in_flight_transactions: RelayersConfiguration::default_max_in_flight_transactions(),
+                execution_concurrency_factor: RelayersConfiguration::default_execution_concurrency_factor(),
+                spend_caps: Default::default(),
+                secondary: None,
+                gas_tank_top_up: None,
+                staking: None,
+                journal: None,
+                watchdog: None,
+                dedicated: vec![],
+                deployment_relayers: vec![],
+                treasury_history: None,
+                messaging: None,
                 rebalancing: OptionalRebalancingConfiguration::initialize(Some(RebalancingConfiguration {
                     trigger_balance,
                     check_interval,
+                    strategy: Default::default(),
+                    maintenance_windows: vec![],
+                    treasury_policy: None,
                     swap_config: SwapConfiguration {
                         swap_interval,
                         max_price_impact,
                         slippage,
                         swap_client_config: SwapClientConfigurator::mock::<MockSimpleSwap>(),
                         min_usd_sell_amount,
+                        tranche_price_impact: None,
                     },
                 })),
             },
             gas_tank: StarknetTestEnvironment::GAS_TANK,
+            gas_tank_multisig: None,
             price: PriceConfiguration::mock::<MockPrice>(),
         }
     }
@@ -636,7 +726,7 @@ mod rebalancing_tests {
             min_usd_sell_amount,
         );
 
-        let context = Context::new(configuration);
+        let context = Context::new(configuration).unwrap();
         let service = RelayerRebalancingService::new(context).await;
 
         // Mock high balances for all relayers (above trigger)
@@ -677,7 +767,7 @@ mod rebalancing_tests {
             min_usd_sell_amount,
         );
 
-        let context = Context::new(configuration);
+        let context = Context::new(configuration).unwrap();
         let service = RelayerRebalancingService::new(context).await;
 
         // Direct test of the logic with mocked RelayerBalance
@@ -685,10 +775,12 @@ mod rebalancing_tests {
             RelayerBalance {
                 relayer: relayers[0],
                 balance: Felt::from(500u64), // Below trigger
+                usage: Felt::ZERO,
             },
             RelayerBalance {
                 relayer: relayers[1],
                 balance: Felt::from(800u64), // Below trigger
+                usage: Felt::ZERO,
             },
         ];
 
@@ -720,17 +812,19 @@ mod rebalancing_tests {
             0.01,
         );
 
-        let context = Context::new(configuration);
+        let context = Context::new(configuration).unwrap();
         let service = RelayerRebalancingService::new(context).await;
 
         let relayers = vec![
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_1,
                 balance: Felt::from(100u64), // Very low balance
+                usage: Felt::ZERO,
             },
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_2,
                 balance: Felt::from(200u64), // Very low balance
+                usage: Felt::ZERO,
             },
         ];
 
@@ -759,7 +853,7 @@ mod rebalancing_tests {
             0.01,
         );
 
-        let context = Context::new(configuration);
+        let context = Context::new(configuration).unwrap();
         let service = RelayerRebalancingService::new(context).await;
 
         // Test with relayers having different balances
@@ -767,10 +861,12 @@ mod rebalancing_tests {
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_1,
                 balance: Felt::from(500u64), // Below trigger
+                usage: Felt::ZERO,
             },
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_2,
                 balance: Felt::from(800u64), // Below trigger
+                usage: Felt::ZERO,
             },
         ];
 
@@ -806,7 +902,7 @@ mod rebalancing_tests {
             0.01,
         );
 
-        let context = Context::new(configuration);
+        let context = Context::new(configuration).unwrap();
         let service = RelayerRebalancingService::new(context).await;
 
         // Test with one relayer already having sufficient funds
@@ -814,14 +910,17 @@ mod rebalancing_tests {
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_1,
                 balance: Felt::from(500u64), // Below trigger
+                usage: Felt::ZERO,
             },
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_2,
                 balance: Felt::from(1500u64), // Above trigger
+                usage: Felt::ZERO,
             },
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_3,
                 balance: Felt::from(800u64), // Below trigger
+                usage: Felt::ZERO,
             },
         ];
 
@@ -870,7 +969,7 @@ mod rebalancing_tests {
             0.01,
         );
 
-        let context = Context::new(configuration);
+        let context = Context::new(configuration).unwrap();
         let service = RelayerRebalancingService::new(context).await;
 
         // Test with one relayer already having sufficient funds
@@ -878,14 +977,17 @@ mod rebalancing_tests {
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_1,
                 balance: normalize_felt(8.0, 18), // Above trigger
+                usage: Felt::ZERO,
             },
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_2,
                 balance: normalize_felt(8.0, 18), // Above trigger
+                usage: Felt::ZERO,
             },
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_3,
                 balance: normalize_felt(1.0, 18), // Below trigger
+                usage: Felt::ZERO,
             },
         ];
 
@@ -929,7 +1031,7 @@ mod rebalancing_tests {
             0.01,
         );
 
-        let context = Context::new(configuration);
+        let context = Context::new(configuration).unwrap();
         let service = RelayerRebalancingService::new(context).await;
 
         // Test with one relayer below trigger
@@ -937,10 +1039,12 @@ mod rebalancing_tests {
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_1,
                 balance: Felt::from(500u64), // Below trigger
+                usage: Felt::ZERO,
             },
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_2,
                 balance: Felt::from(1500u64), // Above trigger
+                usage: Felt::ZERO,
             },
         ];
 
@@ -952,10 +1056,12 @@ mod rebalancing_tests {
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_1,
                 balance: Felt::from(1500u64), // Above trigger
+                usage: Felt::ZERO,
             },
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_2,
                 balance: Felt::from(2000u64), // Above trigger
+                usage: Felt::ZERO,
             },
         ];
 
@@ -979,17 +1085,19 @@ mod rebalancing_tests {
             0.01,
         );
 
-        let context = Context::new(configuration);
+        let context = Context::new(configuration).unwrap();
         let service = RelayerRebalancingService::new(context).await;
 
         let relayers = vec![
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_1,
                 balance: Felt::from(500u64), // Needs 500 to reach trigger
+                usage: Felt::ZERO,
             },
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_2,
                 balance: Felt::from(800u64), // Needs 200 to reach trigger
+                usage: Felt::ZERO,
             },
         ];
 
@@ -1019,7 +1127,7 @@ mod rebalancing_tests {
             0.01,
         );
 
-        let context = Context::new(configuration);
+        let context = Context::new(configuration).unwrap();
         let service = RelayerRebalancingService::new(context).await;
 
         // Test with empty relayers list (for business logic)
@@ -1078,6 +1186,56 @@ mod rebalancing_tests {
         assert!(validation_result_valid.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_validation_reports_all_errors() {
+        let configuration = setup_mock_configuration(
+            Felt::from(500u64),  // trigger_balance
+            10,                  // check_interval
+            100,                 // swap_interval
+            0.08,                // max_price_impact
+            1.5,                 // slippage
+            vec![Felt::ZERO],    // relayers
+            Felt::from(1000u64), // min_relayer_balance
+            0.01,
+        );
+
+        let errors = configuration.validate_all().unwrap_err();
+
+        assert_eq!(errors.errors().len(), 4);
+        assert!(errors.contains("relayers.addresses[0]"));
+        assert!(errors.contains("relayers.rebalancing.check_interval"));
+        assert!(errors.contains("relayers.rebalancing.trigger_balance"));
+        assert!(errors.contains("relayers.rebalancing.swap_config.slippage"));
+    }
+
+    #[tokio::test]
+    async fn test_secondary_fleet_is_rebalanced_by_primary() {
+        let mut configuration = setup_mock_configuration(
+            Felt::from(2000u64),
+            100,
+            10,
+            0.08,
+            0.05,
+            vec![StarknetTestEnvironment::RELAYER_1],
+            Felt::from(1000u64),
+            0.01,
+        );
+        let mut secondary = configuration.relayers.clone();
+        secondary.addresses = vec![StarknetTestEnvironment::RELAYER_2];
+        configuration.relayers.secondary = Some(Box::new(secondary));
+
+        let errors = configuration.validate_all().unwrap_err();
+        assert!(errors.contains("relayers.secondary.rebalancing"));
+
+        let secondary = configuration.relayers.secondary.as_mut().unwrap();
+        secondary.rebalancing = OptionalRebalancingConfiguration::initialize(None);
+        assert!(configuration.validate().is_ok());
+        assert_eq!(
+            configuration.relayers.fleet_addresses(),
+            vec![StarknetTestEnvironment::RELAYER_1, StarknetTestEnvironment::RELAYER_2]
+        );
+    }
+
     #[tokio::test]
     async fn test_all_relayers_above_trigger() {
         let trigger_balance = Felt::from(1000u64);
@@ -1092,7 +1250,7 @@ mod rebalancing_tests {
             0.01,
         );
 
-        let context = Context::new(configuration);
+        let context = Context::new(configuration).unwrap();
         let service = RelayerRebalancingService::new(context).await;
 
         // Test with all relayers already above trigger
@@ -1100,10 +1258,12 @@ mod rebalancing_tests {
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_1,
                 balance: Felt::from(2000u64), // Already above trigger
+                usage: Felt::ZERO,
             },
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_2,
                 balance: Felt::from(1500u64), // Already above trigger
+                usage: Felt::ZERO,
             },
         ];
 
@@ -1150,7 +1310,7 @@ mod rebalancing_tests {
             0.01,
         );
 
-        let context = Context::new(configuration);
+        let context = Context::new(configuration).unwrap();
         let service = RelayerRebalancingService::new(context).await;
 
         // Test with relayers exactly at trigger balance
@@ -1158,10 +1318,12 @@ mod rebalancing_tests {
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_1,
                 balance: trigger_balance, // Exactly at trigger
+                usage: Felt::ZERO,
             },
             RelayerBalance {
                 relayer: StarknetTestEnvironment::RELAYER_2,
                 balance: trigger_balance, // Exactly at trigger
+                usage: Felt::ZERO,
             },
         ];
 
@@ -1286,24 +1448,41 @@ mod integration_tests {
                 addresses: relayer_addresses.clone(),
                 min_relayer_balance,
                 lock: LockLayerConfiguration::mock_with_timeout::<IntegrationMockLock>(Duration::from_secs(10)),
+                max_in_flight_transactions: RelayersConfiguration::default_max_in_flight_transactions(),
+                execution_concurrency_factor: RelayersConfiguration::default_execution_concurrency_factor(),
+                spend_caps: Default::default(),
+                secondary: None,
+                gas_tank_top_up: None,
+                staking: None,
+                journal: None,
+                watchdog: None,
+                dedicated: vec![],
+                deployment_relayers: vec![],
+                treasury_history: None,
+                messaging: None,
                 rebalancing: OptionalRebalancingConfiguration::initialize(Some(RebalancingConfiguration {
                     trigger_balance,
                     check_interval,
+                    strategy: Default::default(),
+                    maintenance_windows: vec![],
+                    treasury_policy: None,
                     swap_config: SwapConfiguration {
                         swap_interval,
                         max_price_impact: 0.08,
                         slippage: 0.05,
                         swap_client_config: SwapClientConfigurator::mock::<MockSimpleSwap>(),
                         min_usd_sell_amount: 0.01,
+                        tranche_price_impact: None,
                     },
                 })),
             },
             gas_tank: StarknetTestEnvironment::GAS_TANK,
+            gas_tank_multisig: None,
             price: PriceConfiguration::mock::<IntegrationMockPrice>(),
         };
 
         // Create rebalancing service
-        let context = Context::new(configuration);
+        let context = Context::new(configuration).unwrap();
         let service = RelayerRebalancingService::new(context).await;
 
         println!("🏗️  Setting up test environment...");
@@ -1453,23 +1632,40 @@ mod integration_tests {
                 addresses: relayer_addresses.clone(),
                 min_relayer_balance: Felt::from(500000000000000000u128),
                 lock: LockLayerConfiguration::mock_with_timeout::<IntegrationMockLock>(Duration::from_secs(10)),
+                max_in_flight_transactions: RelayersConfiguration::default_max_in_flight_transactions(),
+                execution_concurrency_factor: RelayersConfiguration::default_execution_concurrency_factor(),
+                spend_caps: Default::default(),
+                secondary: None,
+                gas_tank_top_up: None,
+                staking: None,
+                journal: None,
+                watchdog: None,
+                dedicated: vec![],
+                deployment_relayers: vec![],
+                treasury_history: None,
+                messaging: None,
                 rebalancing: OptionalRebalancingConfiguration::initialize(Some(RebalancingConfiguration {
                     trigger_balance,
                     check_interval: 60,
+                    strategy: Default::default(),
+                    maintenance_windows: vec![],
+                    treasury_policy: None,
                     swap_config: SwapConfiguration {
                         swap_interval: 30,
                         max_price_impact: 0.08,
                         slippage: 0.05,
                         swap_client_config: SwapClientConfigurator::mock::<MockSimpleSwap>(),
                         min_usd_sell_amount: 0.01,
+                        tranche_price_impact: None,
                     },
                 })),
             },
             gas_tank: StarknetTestEnvironment::GAS_TANK,
+            gas_tank_multisig: None,
             price: PriceConfiguration::mock::<IntegrationMockPrice>(),
         };
 
-        let context = Context::new(configuration);
+        let context = Context::new(configuration).unwrap();
         let service = RelayerRebalancingService::new(context).await;
 
         println!("🔧 Setting high balances for relayers (above trigger)...");
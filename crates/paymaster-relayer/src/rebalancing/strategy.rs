@@ -0,0 +1,212 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use crate::rebalancing::RelayerBalance;
+
+/// Decides how the STRK available in the gas tank is distributed among the relayers during a rebalance.
+/// Implement this trait and use [`RebalancingStrategyConfiguration::Custom`] to plug a strategy of your own.
+pub trait RebalancingStrategy: Debug + Send + Sync {
+    /// Returns the amount of STRK to transfer to each relayer, in the order of `relayers`. The amounts may sum
+    /// to more than `available_funds` when the funds are not sufficient to bring every relayer to `trigger_balance`,
+    /// in which case the rebalance is skipped.
+    fn refill_amounts(&self, available_funds: Felt, trigger_balance: Felt, relayers: &[RelayerBalance]) -> Vec<Felt>;
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RebalancingStrategyConfiguration {
+    /// Use all the available funds so that relayers end with the same balance
+    #[default]
+    Equalize,
+
+    /// Only bring the relayers below the trigger balance back to it, keeping the remaining funds in the gas tank
+    Waterline,
+
+    /// Bring every relayer to the trigger balance then distribute the remaining funds according to the STRK
+    /// spent by each relayer over the last day
+    ProportionalToUsage,
+
+    #[serde(skip)]
+    Custom(Arc<dyn RebalancingStrategy>),
+}
+
+impl RebalancingStrategyConfiguration {
+    pub fn build(&self) -> Arc<dyn RebalancingStrategy> {
+        match self {
+            Self::Equalize => Arc::new(Equalize),
+            Self::Waterline => Arc::new(Waterline),
+            Self::ProportionalToUsage => Arc::new(ProportionalToUsage),
+            Self::Custom(strategy) => strategy.clone(),
+        }
+    }
+}
+
+fn to_u128(value: Felt) -> u128 {
+    u128::try_from(value).unwrap_or(u128::MAX)
+}
+
+/// Amounts needed to bring each relayer to `target`
+fn amounts_to_target(target: Felt, relayers: &[RelayerBalance]) -> Vec<Felt> {
+    relayers
+        .iter()
+        .map(|relayer| if relayer.balance < target { target - relayer.balance } else { Felt::ZERO })
+        .collect()
+}
+
+#[derive(Debug)]
+pub struct Equalize;
+
+impl Equalize {
+    /// Calculate the target balance for each relayer to achieve optimal homogeneous distribution after a rebalance.
+    /// Strategy:
+    /// 1) Ensure all relayers reach at least trigger_balance
+    /// 2) Distribute remaining funds to achieve homogeneous final balances
+    /// 3) Relayers with lower current balances get more funds to level the playing field
+    /// 4) Use ALL available funds (gas tank will be emptied except for 1 STRK reserve)
+    pub fn target_balance(available_funds: Felt, trigger_balance: Felt, relayers: &[RelayerBalance]) -> Felt {
+        // If there are no relayers, return 0, there is no refill needed
+        if relayers.is_empty() {
+            return Felt::ZERO;
+        }
+
+        // Simple binary search approach to find the target balance that uses exactly all available funds
+        // We need to find the target T such that sum(max(0, T - balance_i)) = available_funds
+
+        // Start with minimum possible target (trigger_balance)
+        let mut low = trigger_balance;
+        // Maximum possible target: if we gave all funds to the relayer with lowest balance
+        let min_balance = relayers.iter().map(|r| r.balance).min().unwrap_or(Felt::ZERO);
+        let mut high = min_balance + available_funds;
+
+        // Binary search to find exact target
+        while low < high {
+            let mid_u64 = (low.try_into().unwrap_or(0u128) + high.try_into().unwrap_or(0u128)) / 2;
+            let mid = Felt::from(mid_u64);
+
+            // Calculate total funds needed to bring all relayers to this target
+            let mut funds_needed = Felt::ZERO;
+            for relayer in relayers {
+                if relayer.balance < mid {
+                    funds_needed += mid - relayer.balance;
+                }
+            }
+
+            if funds_needed == available_funds {
+                return mid;
+            } else if funds_needed < available_funds {
+                low = mid + Felt::ONE;
+            } else {
+                high = mid;
+            }
+        }
+
+        // If binary search doesn't find exact match, return the closest target that doesn't exceed available funds
+        let mut best_target = low;
+        let mut funds_needed = Felt::ZERO;
+        for relayer in relayers {
+            if relayer.balance < best_target {
+                funds_needed += best_target - relayer.balance;
+            }
+        }
+
+        // If we still need more funds than available, reduce target
+        if funds_needed > available_funds {
+            best_target = if best_target > Felt::ONE { best_target - Felt::ONE } else { best_target };
+        }
+
+        best_target.max(trigger_balance)
+    }
+}
+
+impl RebalancingStrategy for Equalize {
+    fn refill_amounts(&self, available_funds: Felt, trigger_balance: Felt, relayers: &[RelayerBalance]) -> Vec<Felt> {
+        amounts_to_target(Self::target_balance(available_funds, trigger_balance, relayers), relayers)
+    }
+}
+
+#[derive(Debug)]
+pub struct Waterline;
+
+impl RebalancingStrategy for Waterline {
+    fn refill_amounts(&self, _available_funds: Felt, trigger_balance: Felt, relayers: &[RelayerBalance]) -> Vec<Felt> {
+        amounts_to_target(trigger_balance, relayers)
+    }
+}
+
+#[derive(Debug)]
+pub struct ProportionalToUsage;
+
+impl RebalancingStrategy for ProportionalToUsage {
+    fn refill_amounts(&self, available_funds: Felt, trigger_balance: Felt, relayers: &[RelayerBalance]) -> Vec<Felt> {
+        let total_usage: u128 = relayers.iter().map(|x| to_u128(x.usage)).fold(0, u128::saturating_add);
+        if total_usage == 0 {
+            return Equalize.refill_amounts(available_funds, trigger_balance, relayers);
+        }
+
+        let mut amounts = amounts_to_target(trigger_balance, relayers);
+        let needed = amounts.iter().fold(Felt::ZERO, |acc, x| acc + *x);
+        if needed >= available_funds {
+            return amounts;
+        }
+
+        // Share what remains according to the usage, never distributing more than what is available
+        let remaining = to_u128(available_funds - needed);
+        let mut distributed = 0u128;
+        for (amount, relayer) in amounts.iter_mut().zip(relayers) {
+            let share = to_u128(relayer.usage) as f64 / total_usage as f64;
+            let extra = ((remaining as f64 * share) as u128).min(remaining - distributed);
+
+            distributed += extra;
+            *amount += Felt::from(extra);
+        }
+
+        amounts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use crate::rebalancing::strategy::{ProportionalToUsage, RebalancingStrategy, Waterline};
+    use crate::rebalancing::RelayerBalance;
+
+    fn relayer(balance: u64, usage: u64) -> RelayerBalance {
+        RelayerBalance {
+            relayer: Felt::from(balance),
+            balance: Felt::from(balance),
+            usage: Felt::from(usage),
+        }
+    }
+
+    #[test]
+    fn waterline_only_refills_relayers_below_trigger() {
+        let relayers = [relayer(100, 0), relayer(1500, 0), relayer(900, 0)];
+
+        let amounts = Waterline.refill_amounts(Felt::from(10_000), Felt::from(1000), &relayers);
+
+        assert_eq!(amounts, vec![Felt::from(900), Felt::ZERO, Felt::from(100)]);
+    }
+
+    #[test]
+    fn proportional_to_usage_shares_remaining_funds() {
+        let relayers = [relayer(1000, 300), relayer(500, 100), relayer(2000, 0)];
+
+        // 500 are needed to reach the trigger, the remaining 4000 are shared 3:1
+        let amounts = ProportionalToUsage.refill_amounts(Felt::from(4500), Felt::from(1000), &relayers);
+
+        assert_eq!(amounts, vec![Felt::from(3000), Felt::from(1500), Felt::ZERO]);
+    }
+
+    #[test]
+    fn proportional_to_usage_without_usage_equalizes() {
+        let relayers = [relayer(1000, 0), relayer(2000, 0)];
+
+        let amounts = ProportionalToUsage.refill_amounts(Felt::from(3000), Felt::from(500), &relayers);
+
+        assert_eq!(amounts, vec![Felt::from(2000), Felt::from(1000)]);
+    }
+}
@@ -7,9 +7,14 @@ use paymaster_starknet::transaction::EstimatedCalls;
 use paymaster_starknet::{Client, StarknetAccount, StarknetAccountConfiguration};
 use starknet::accounts::{Account, ConnectedAccount};
 use starknet::core::types::{BlockId, BlockTag, Felt, InvokeTransactionResult};
+use starknet::providers::Provider;
 use tracing::warn;
 
+use crate::journal::{ExecutionJournal, JournalEntry};
 use crate::lock::RelayerLock;
+use crate::pipeline::NoncePipeline;
+use crate::spend::UnsettledSpends;
+use crate::watchdog::{PendingTransaction, PendingTransactions};
 use crate::Error;
 
 #[derive(Debug, Clone, Copy)]
@@ -20,6 +25,10 @@ pub struct RelayerConfiguration {
 #[derive(Clone)]
 pub struct RelayerContext {
     pub balances: ExpirableCache<Felt, Felt>,
+    pub pipeline: NoncePipeline,
+    pub journal: ExecutionJournal,
+    pub pending: PendingTransactions,
+    pub unsettled: UnsettledSpends,
 }
 
 #[derive(Clone)]
@@ -39,19 +48,20 @@ impl Deref for Relayer {
 }
 
 impl Relayer {
-    pub fn new(starknet: &Client, balances: ExpirableCache<Felt, Felt>, configuration: &RelayerConfiguration) -> Self {
+    pub fn new(starknet: &Client, context: RelayerContext, configuration: &RelayerConfiguration) -> Self {
         let mut account = starknet.initialize_account(&configuration.account);
         account.set_block_id(BlockId::Tag(BlockTag::PreConfirmed));
 
-        Self {
-            account,
-            context: RelayerContext { balances },
-        }
+        Self { account, context }
     }
 
     // TODO: the semantic is not clear
     pub fn lock(self, lock: RelayerLock) -> LockedRelayer {
-        LockedRelayer { lock, relayer: self }
+        LockedRelayer {
+            lock,
+            relayer: self,
+            spent: Felt::ZERO,
+        }
     }
 
     pub async fn update_relayer_balance(&self, gas_used: Felt) {
@@ -79,6 +89,9 @@ impl Relayer {
 pub struct LockedRelayer {
     lock: RelayerLock,
     relayer: Relayer,
+
+    // Amount of STRK spent by the transactions executed while the relayer is locked
+    spent: Felt,
 }
 
 impl LockedRelayer {
@@ -86,6 +99,10 @@ impl LockedRelayer {
         self.relayer.address()
     }
 
+    pub fn spent(&self) -> Felt {
+        self.spent
+    }
+
     pub fn unlock(self) -> (Relayer, RelayerLock) {
         (self.relayer, self.lock)
     }
@@ -99,12 +116,57 @@ impl LockedRelayer {
             return Err(Error::RelayerLockExpired);
         }
 
+        self.wait_for_pipeline_capacity().await?;
+
         let nonce = self.get_nonce().await?;
-        let result = calls.execute(&self.relayer.account, nonce).await;
+
+        // The entry is written before submitting so that a crash during the execution can be reconciled
+        let expected_hash = calls
+            .transaction_hash(&self.relayer.account, nonce)
+            .map_err(|e| Error::Execution(e.to_string()))?;
+        let entry = JournalEntry::new(self.address(), nonce, Felt::from(calls.estimate().overall_fee)).expecting(expected_hash);
+        self.relayer.context.journal.begin(&entry).await?;
+
+        let result = match calls.execute(&self.relayer.account, nonce).await {
+            // The submission may have failed after the transaction was accepted, in which case it is tracked as if
+            // it had succeeded rather than submitted again
+            Err(e @ (paymaster_starknet::Error::Internal(_) | paymaster_starknet::Error::Starknet(_))) => {
+                match self.relayer.account.provider().get_transaction_status(expected_hash).await {
+                    Ok(_) => {
+                        metric!(counter[relayer_submission_recovered] = 1);
+                        warn!("transaction {} was accepted despite its submission failing: {}", expected_hash.to_hex_string(), e);
+                        Ok(InvokeTransactionResult { transaction_hash: expected_hash })
+                    },
+                    Err(_) => Err(e),
+                }
+            },
+            result => result,
+        };
+        match &result {
+            Ok(value) => {
+                let _ = self.relayer.context.journal.submitted(&entry, value.transaction_hash).await;
+            },
+            Err(_) => {
+                let _ = self.relayer.context.journal.forget(&entry).await;
+            },
+        }
 
         match result {
             Ok(value) => {
                 self.lock.nonce = Some(nonce + Felt::ONE);
+                self.relayer
+                    .context
+                    .pipeline
+                    .submitted(self.address(), nonce, Felt::from(calls.estimate().overall_fee));
+                self.relayer
+                    .context
+                    .pending
+                    .track(PendingTransaction::new(self.address(), nonce, value.transaction_hash, calls.estimate().tip()));
+                self.relayer
+                    .context
+                    .unsettled
+                    .track(self.address(), value.transaction_hash, Felt::from(calls.estimate().overall_fee));
+                self.spent += Felt::from(calls.estimate().overall_fee);
                 self.relayer
                     .update_relayer_balance(Felt::from(calls.estimate().overall_fee))
                     .await;
@@ -131,23 +193,175 @@ impl LockedRelayer {
         }
     }
 
+    // Wait until the number of transactions of the relayer which are not yet included on chain is within
+    // the in-flight window. Gives up once the lock expires. The pipeline is reset when the next transaction to be
+    // included was dropped, its nonce being used again by the execution.
+    async fn wait_for_pipeline_capacity(&mut self) -> Result<(), Error> {
+        let pipeline = self.relayer.context.pipeline.clone();
+        while !pipeline.has_capacity(self.address()) {
+            let chain_nonce = self.fetch_nonce().await?;
+            pipeline.confirm(self.address(), chain_nonce);
+            if pipeline.is_stuck(self.address(), chain_nonce) {
+                metric!(counter[relayer_nonce_stuck] = 1);
+                warn!(
+                    "transaction of relayer {} with nonce {} was not included in time, resetting its pipeline",
+                    self.address().to_fixed_hex_string(),
+                    chain_nonce.to_hex_string()
+                );
+
+                self.invalidate_nonce();
+                break;
+            }
+            if pipeline.has_capacity(self.address()) {
+                break;
+            }
+
+            if self.lock.is_expired() {
+                metric!(counter[relayer_request_error] = 1, method = "execute", error = "in_flight_window_full");
+
+                return Err(Error::RelayerLockExpired);
+            }
+
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+
+        Ok(())
+    }
+
     fn invalidate_nonce(&mut self) {
         self.lock.nonce = None;
+        self.relayer.context.pipeline.reset(self.address());
     }
 
     async fn get_nonce(&mut self) -> Result<Felt, Error> {
-        if let Some(nonce) = self.lock.nonce {
+        // Use the nonce cached in the lock first, then the one maintained by the pipeline which allows to
+        // submit consecutive transactions without waiting for the previous ones to be included
+        if let Some(nonce) = self
+            .lock
+            .nonce
+            .or_else(|| self.relayer.context.pipeline.next_nonce(self.address()))
+        {
             return Ok(nonce);
         }
 
-        let nonce = self
-            .relayer
+        let nonce = self.fetch_nonce().await?;
+        self.relayer.context.pipeline.confirm(self.address(), nonce);
+
+        self.lock.nonce = Some(nonce);
+        Ok(nonce)
+    }
+
+    async fn fetch_nonce(&self) -> Result<Felt, Error> {
+        self.relayer
             .account
             .get_nonce()
             .await
-            .map_err(|e| Error::Execution(e.to_string()))?;
+            .map_err(|e| Error::Execution(e.to_string()))
+    }
+}
 
-        self.lock.nonce = Some(nonce);
-        Ok(nonce)
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use paymaster_common::cache::ExpirableCache;
+    use paymaster_starknet::testing::provider::MockProvider;
+    use paymaster_starknet::transaction::{Calls, EstimatedCalls, TokenTransfer, TransactionGasEstimate};
+    use paymaster_starknet::{ChainID, Client, StarknetAccountConfiguration};
+    use starknet::core::types::{FeeEstimate, Felt};
+
+    use crate::journal::ExecutionJournal;
+    use crate::lock::RelayerLock;
+    use crate::pipeline::NoncePipeline;
+    use crate::relayer::{LockedRelayer, Relayer, RelayerConfiguration, RelayerContext};
+    use crate::spend::UnsettledSpends;
+    use crate::watchdog::PendingTransactions;
+
+    const RELAYER: Felt = Felt::from_hex_unchecked("0x1234");
+
+    fn relayer(provider: &MockProvider, pipeline: NoncePipeline) -> Relayer {
+        provider.set_nonce(RELAYER, Felt::ZERO);
+
+        Relayer::new(
+            &Client::mock(ChainID::Sepolia, provider.clone()),
+            RelayerContext {
+                balances: ExpirableCache::new(1),
+                pipeline,
+                journal: ExecutionJournal::Disabled,
+                pending: PendingTransactions::new(false),
+                unsettled: UnsettledSpends::default(),
+            },
+            &RelayerConfiguration {
+                account: StarknetAccountConfiguration {
+                    address: RELAYER,
+                    private_key: Felt::ONE,
+                },
+            },
+        )
+    }
+
+    fn lock(relayer: &Relayer) -> LockedRelayer {
+        relayer.clone().lock(RelayerLock::new(RELAYER, None, Duration::from_secs(30)))
+    }
+
+    fn submitted_nonces(provider: &MockProvider) -> Vec<Felt> {
+        provider.submitted_transactions().iter().map(|x| x.nonce).collect()
+    }
+
+    fn calls() -> EstimatedCalls {
+        let estimate = FeeEstimate {
+            l1_gas_consumed: 1,
+            l1_gas_price: 1,
+            l2_gas_consumed: 1,
+            l2_gas_price: 1,
+            l1_data_gas_consumed: 1,
+            l1_data_gas_price: 1,
+            overall_fee: 3,
+        };
+
+        Calls::new(vec![TokenTransfer::new(Felt::TWO, Felt::THREE, Felt::ONE).to_call()]).with_estimate(TransactionGasEstimate::new(estimate, 0))
+    }
+
+    #[tokio::test]
+    async fn dropped_transaction_is_submitted_again_with_its_nonce() {
+        let provider = MockProvider::new();
+        let relayer = relayer(&provider, NoncePipeline::new(1).with_stuck_timeout(Duration::from_millis(100)));
+
+        lock(&relayer).execute(&calls()).await.unwrap();
+
+        // The transaction is dropped, the nonce of the relayer on chain does not move past it
+        provider.set_nonce(RELAYER, Felt::ZERO);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        lock(&relayer).execute(&calls()).await.unwrap();
+        assert_eq!(submitted_nonces(&provider), vec![Felt::ZERO, Felt::ZERO]);
+    }
+
+    #[tokio::test]
+    async fn shared_relayer_uses_the_nonce_of_the_chain() {
+        let provider = MockProvider::new();
+        let relayer = relayer(&provider, NoncePipeline::disabled());
+
+        lock(&relayer).execute(&calls()).await.unwrap();
+
+        // Another instance used the relayer without the lock carrying its next nonce
+        provider.set_nonce(RELAYER, Felt::from(5));
+
+        lock(&relayer).execute(&calls()).await.unwrap();
+        assert_eq!(submitted_nonces(&provider), vec![Felt::ZERO, Felt::from(5)]);
+    }
+
+    #[tokio::test]
+    async fn pipelined_relayer_does_not_wait_for_its_transactions() {
+        let provider = MockProvider::new();
+        let relayer = relayer(&provider, NoncePipeline::new(2));
+
+        lock(&relayer).execute(&calls()).await.unwrap();
+
+        // The transaction is not included yet, the next one is submitted with the following nonce
+        provider.set_nonce(RELAYER, Felt::ZERO);
+
+        lock(&relayer).execute(&calls()).await.unwrap();
+        assert_eq!(submitted_nonces(&provider), vec![Felt::ZERO, Felt::ONE]);
     }
 }
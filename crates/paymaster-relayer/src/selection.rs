@@ -0,0 +1,25 @@
+use starknet::core::types::Felt;
+
+/// Criteria restricting the relayers which may execute a transaction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RelayerSelection {
+    /// Fingerprint of the API key of the sponsor of the transaction (keccak of the key), restricting the
+    /// transaction to the relayers dedicated to it if any
+    pub sponsor: Option<Felt>,
+
+    /// Whether the transaction deploys an account, in which case it may also use the relayers reserved to deployments
+    pub deployment: bool,
+}
+
+impl RelayerSelection {
+    pub fn sponsored(sponsor: Felt) -> Self {
+        Self {
+            sponsor: Some(sponsor),
+            deployment: false,
+        }
+    }
+
+    pub fn with_deployment(self, deployment: bool) -> Self {
+        Self { deployment, ..self }
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+
+/// Amounts spent are tracked in units of 1e9 FRI so that they fit in the 64-bit counters of the lock layer
+const SPEND_UNIT: u128 = 1_000_000_000;
+
+/// Time window over which the STRK spent by the relayers is capped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpendWindow {
+    Hourly,
+    Daily,
+}
+
+impl SpendWindow {
+    pub const ALL: [SpendWindow; 2] = [SpendWindow::Hourly, SpendWindow::Daily];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Hourly => "hourly",
+            Self::Daily => "daily",
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            Self::Hourly => Duration::from_secs(3600),
+            Self::Daily => Duration::from_secs(86400),
+        }
+    }
+
+    /// Returns the index of the window containing the current time
+    pub fn current_bucket(&self) -> u64 {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        now.as_secs() / self.duration().as_secs()
+    }
+}
+
+/// Convert an amount of FRI to spend units, rounding up so that spending is never underestimated
+pub fn to_spend_units(amount: Felt) -> u64 {
+    let amount = u128::try_from(amount).unwrap_or(u128::MAX);
+    amount.div_ceil(SPEND_UNIT).try_into().unwrap_or(u64::MAX)
+}
+
+/// Convert spend units back to an amount of FRI
+pub fn from_spend_units(units: u64) -> Felt {
+    Felt::from(units as u128 * SPEND_UNIT)
+}
+
+/// Amount of STRK (in FRI) spent during the current hourly and daily windows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpendTotals {
+    pub hourly: Felt,
+    pub daily: Felt,
+}
+
+impl SpendTotals {
+    pub fn get(&self, window: SpendWindow) -> Felt {
+        match window {
+            SpendWindow::Hourly => self.hourly,
+            SpendWindow::Daily => self.daily,
+        }
+    }
+
+    pub fn set(&mut self, window: SpendWindow, amount: Felt) {
+        match window {
+            SpendWindow::Hourly => self.hourly = amount,
+            SpendWindow::Daily => self.daily = amount,
+        }
+    }
+}
+
+/// Maximum amount of STRK (in FRI) that can be spent within each window. No cap applies when a value is not set.
+#[serde_as]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SpendCaps {
+    #[serde(default)]
+    #[serde_as(as = "Option<UfeHex>")]
+    pub hourly: Option<Felt>,
+
+    #[serde(default)]
+    #[serde_as(as = "Option<UfeHex>")]
+    pub daily: Option<Felt>,
+}
+
+impl SpendCaps {
+    pub fn get(&self, window: SpendWindow) -> Option<Felt> {
+        match window {
+            SpendWindow::Hourly => self.hourly,
+            SpendWindow::Daily => self.daily,
+        }
+    }
+
+    /// Returns the first window whose cap is exceeded by the given totals
+    pub fn exceeded_by(&self, totals: &SpendTotals) -> Option<SpendWindow> {
+        SpendWindow::ALL
+            .into_iter()
+            .find(|window| self.get(*window).is_some_and(|cap| totals.get(*window) > cap))
+    }
+}
+
+impl Validate for SpendCaps {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        for window in SpendWindow::ALL {
+            report.ensure(self.get(window) != Some(Felt::ZERO), window.name(), "must not be zero");
+        }
+    }
+}
+
+/// Caps on the STRK spent by each relayer and by the whole fleet. Once a cap is exceeded the relayers
+/// concerned are disabled until the window ends, limiting the losses if a sponsor key leaks or users are undercharged.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SpendCapsConfiguration {
+    #[serde(default)]
+    pub per_relayer: SpendCaps,
+
+    #[serde(default)]
+    pub fleet: SpendCaps,
+}
+
+impl Validate for SpendCapsConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.field("per_relayer", &self.per_relayer);
+        report.field("fleet", &self.fleet);
+    }
+}
+
+/// Amounts spent by the whole fleet and by each of its relayers during the current windows
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FleetSpend {
+    pub fleet: SpendTotals,
+    pub relayers: HashMap<Felt, SpendTotals>,
+}
+
+impl SpendCapsConfiguration {
+    /// Returns the relayers which exceeded one of their caps, with the window exceeded. All the relayers are
+    /// returned when the whole fleet exceeded its caps.
+    pub fn exceeded_by(&self, spend: &FleetSpend) -> HashMap<Felt, SpendWindow> {
+        if let Some(window) = self.fleet.exceeded_by(&spend.fleet) {
+            return spend.relayers.keys().map(|x| (*x, window)).collect();
+        }
+
+        spend
+            .relayers
+            .iter()
+            .filter_map(|(relayer, totals)| self.per_relayer.exceeded_by(totals).map(|window| (*relayer, window)))
+            .collect()
+    }
+}
+
+/// Transaction whose spend was counted at its estimated fee when it was submitted, until its actual fee is known
+#[derive(Debug, Clone, Copy)]
+pub struct UnsettledSpend {
+    pub relayer: Felt,
+    pub transaction_hash: Felt,
+    pub estimated_fee: Felt,
+    pub submitted_at: Instant,
+}
+
+/// Transactions submitted by the relayers of this instance whose spend must be corrected with their actual fee
+/// once they are included
+#[derive(Clone, Default)]
+pub struct UnsettledSpends(Arc<Mutex<Vec<UnsettledSpend>>>);
+
+impl UnsettledSpends {
+    pub fn track(&self, relayer: Felt, transaction_hash: Felt, estimated_fee: Felt) {
+        let mut spends = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        spends.push(UnsettledSpend {
+            relayer,
+            transaction_hash,
+            estimated_fee,
+            submitted_at: Instant::now(),
+        });
+    }
+
+    /// Take the transactions to settle, the ones which cannot be settled yet must be tracked again with [`restore`]
+    pub fn take(&self) -> Vec<UnsettledSpend> {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn restore(&self, spends: Vec<UnsettledSpend>) {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).extend(spends);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use starknet::core::types::Felt;
+    use starknet::macros::felt;
+
+    use crate::spend::{from_spend_units, to_spend_units, FleetSpend, SpendCaps, SpendCapsConfiguration, SpendTotals, SpendWindow};
+
+    #[test]
+    fn spend_units_never_underestimate() {
+        assert_eq!(to_spend_units(Felt::from(1)), 1);
+        assert_eq!(to_spend_units(Felt::from(2_000_000_000u64)), 2);
+        assert_eq!(from_spend_units(to_spend_units(Felt::from(2_000_000_001u64))), Felt::from(3_000_000_000u64));
+    }
+
+    #[test]
+    fn exceeded_window_is_detected() {
+        let caps = SpendCaps {
+            hourly: None,
+            daily: Some(Felt::from(100)),
+        };
+
+        let mut totals = SpendTotals::default();
+        assert_eq!(caps.exceeded_by(&totals), None);
+
+        totals.set(SpendWindow::Hourly, Felt::from(1000));
+        assert_eq!(caps.exceeded_by(&totals), None);
+
+        totals.set(SpendWindow::Daily, Felt::from(101));
+        assert_eq!(caps.exceeded_by(&totals), Some(SpendWindow::Daily));
+    }
+
+    #[test]
+    fn fleet_cap_disables_all_the_relayers() {
+        let caps = SpendCapsConfiguration {
+            per_relayer: SpendCaps {
+                hourly: Some(Felt::from(100)),
+                daily: None,
+            },
+            fleet: SpendCaps {
+                hourly: None,
+                daily: Some(Felt::from(150)),
+            },
+        };
+
+        let totals = |hourly: u64, daily: u64| SpendTotals {
+            hourly: Felt::from(hourly),
+            daily: Felt::from(daily),
+        };
+        let mut spend = FleetSpend {
+            fleet: totals(120, 120),
+            relayers: HashMap::from([(felt!("0x1"), totals(101, 101)), (felt!("0x2"), totals(19, 19))]),
+        };
+        assert_eq!(caps.exceeded_by(&spend), HashMap::from([(felt!("0x1"), SpendWindow::Hourly)]));
+
+        spend.fleet = totals(120, 151);
+        assert_eq!(
+            caps.exceeded_by(&spend),
+            HashMap::from([(felt!("0x1"), SpendWindow::Daily), (felt!("0x2"), SpendWindow::Daily)])
+        );
+    }
+}
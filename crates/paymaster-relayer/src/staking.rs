@@ -0,0 +1,441 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use paymaster_common::service::{Error as ServiceError, Service};
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_common::{metric, service_check};
+use paymaster_starknet::math::denormalize_felt;
+use paymaster_starknet::transaction::Calls;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::{Call, Felt, FunctionCall};
+use starknet::macros::selector;
+use tokio::time;
+use tracing::{error, info};
+
+use crate::lock::leader::LeaderLock;
+use crate::spend::SpendWindow;
+use crate::Context;
+
+/// Target of the audit logs emitted for every staking operation
+const AUDIT_TARGET: &str = "paymaster::staking::audit";
+
+/// Delegation of the idle STRK of the gas tank to a Starknet staking pool. The gas tank keeps `float` STRK liquid,
+/// funds above `float + buffer` are delegated and funds are undelegated when the balance drops below `float - buffer`.
+/// Since undelegated funds are only withdrawn once the exit window is over, the STRK the relayers are expected to
+/// spend in the meantime is kept liquid on top of the float.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StakingConfiguration {
+    /// Delegation pool of the validator the funds are delegated to
+    #[serde_as(as = "UfeHex")]
+    pub pool: Felt,
+
+    /// STRK balance (in FRI) the gas tank keeps liquid
+    #[serde_as(as = "UfeHex")]
+    pub float: Felt,
+
+    /// Margin (in FRI) around the float within which nothing is delegated or undelegated
+    #[serde_as(as = "UfeHex")]
+    pub buffer: Felt,
+
+    /// Minimum amount (in FRI) of a delegation, smaller amounts are kept in the gas tank
+    #[serde_as(as = "UfeHex")]
+    pub min_delegation: Felt,
+
+    /// How often to check the gas tank balance (in seconds)
+    #[serde(default = "StakingConfiguration::default_check_interval")]
+    pub check_interval: u64,
+
+    /// Duration (in seconds) between the intent to undelegate and the withdrawal of the funds, set by the staking contract
+    #[serde(default = "StakingConfiguration::default_exit_window")]
+    pub exit_window: u64,
+}
+
+impl StakingConfiguration {
+    pub fn default_check_interval() -> u64 {
+        3600
+    }
+
+    pub fn default_exit_window() -> u64 {
+        7 * 86400
+    }
+}
+
+impl Validate for StakingConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.pool != Felt::ZERO, "pool", "must not be zero");
+        report.ensure(self.float != Felt::ZERO, "float", "must not be zero");
+        report.ensure(self.buffer < self.float, "buffer", "must be lower than float");
+        report.ensure(self.min_delegation != Felt::ZERO, "min_delegation", "must not be zero");
+        report.ensure(self.check_interval > 0, "check_interval", "must be greater than 0");
+        report.ensure(self.exit_window > 0, "exit_window", "must be greater than 0");
+    }
+}
+
+/// Position of the gas tank in the delegation pool
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolMember {
+    pub amount: Felt,
+    pub unpool_amount: Felt,
+
+    /// Time (in seconds) from which the funds being undelegated can be withdrawn
+    pub unpool_time: Option<u64>,
+}
+
+impl PoolMember {
+    /// Parse the result of `pool_member_info_v1`, which returns an `Option<PoolMemberInfoV1>`
+    pub fn parse(result: &[Felt]) -> Option<Self> {
+        // Cairo serializes Some as 0 followed by the value
+        if result.first() != Some(&Felt::ZERO) {
+            return None;
+        }
+
+        // reward_address, amount, unclaimed_rewards, commission, unpool_amount, unpool_time
+        let amount = *result.get(2)?;
+        let unpool_amount = *result.get(5)?;
+        let unpool_time = match result.get(6) {
+            Some(x) if *x == Felt::ZERO => Some(u64::try_from(*result.get(7)?).ok()?),
+            _ => None,
+        };
+
+        Some(Self {
+            amount,
+            unpool_amount,
+            unpool_time,
+        })
+    }
+}
+
+/// Operation performed on the delegation pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StakingAction {
+    /// Delegate the amount, the gas tank entering the pool when it is not a member yet
+    Delegate { amount: Felt, enter: bool },
+
+    /// Declare the intent to undelegate the amount
+    Undelegate { amount: Felt },
+
+    /// Withdraw the funds whose undelegation window is over
+    Withdraw,
+}
+
+impl StakingAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Delegate { .. } => "delegate",
+            Self::Undelegate { .. } => "undelegate",
+            Self::Withdraw => "withdraw",
+        }
+    }
+
+    /// Decide which operation to perform given the STRK `balance` of the gas tank and its position in the pool.
+    /// The `reserve` is the STRK expected to be spent during the exit window, kept liquid on top of the float so that
+    /// the funds are undelegated ahead of need. Nothing is delegated nor undelegated while an undelegation is pending.
+    pub fn plan(configuration: &StakingConfiguration, balance: Felt, member: Option<PoolMember>, reserve: Felt, now: u64) -> Option<Self> {
+        let enter = member.is_none();
+        let member = member.unwrap_or_default();
+        if member.unpool_amount != Felt::ZERO {
+            return member.unpool_time.is_some_and(|x| x <= now).then_some(Self::Withdraw);
+        }
+
+        let float = configuration.float + reserve;
+        if balance > float + configuration.buffer {
+            let amount = balance - float;
+            return (amount >= configuration.min_delegation).then_some(Self::Delegate { amount, enter });
+        }
+
+        if balance + configuration.buffer < float && member.amount != Felt::ZERO {
+            let amount = (float - balance).min(member.amount);
+            return Some(Self::Undelegate { amount });
+        }
+
+        None
+    }
+
+    pub fn to_calls(&self, strk: Felt, pool: Felt, gas_tank: Felt) -> Calls {
+        let calls = match *self {
+            Self::Delegate { amount, enter } => vec![
+                Call {
+                    to: strk,
+                    selector: selector!("approve"),
+                    calldata: vec![pool, amount, Felt::ZERO],
+                },
+                Call {
+                    to: pool,
+                    selector: if enter {
+                        selector!("enter_delegation_pool")
+                    } else {
+                        selector!("add_to_delegation_pool")
+                    },
+                    calldata: vec![gas_tank, amount],
+                },
+            ],
+            Self::Undelegate { amount } => vec![Call {
+                to: pool,
+                selector: selector!("exit_delegation_pool_intent"),
+                calldata: vec![amount],
+            }],
+            Self::Withdraw => vec![Call {
+                to: pool,
+                selector: selector!("exit_delegation_pool_action"),
+                calldata: vec![gas_tank],
+            }],
+        };
+
+        Calls::new(calls)
+    }
+}
+
+/// Delegates the idle STRK of the gas tank to a staking pool and undelegates it before the gas tank runs low.
+/// Runs on a single instance at a time and every operation is recorded in the audit logs.
+pub struct GasTankStakingService {
+    context: Context,
+    configuration: StakingConfiguration,
+}
+
+#[async_trait]
+impl Service for GasTankStakingService {
+    type Context = Context;
+
+    const NAME: &'static str = "GasTankStaking";
+
+    async fn new(context: Context) -> Self {
+        let Some(configuration) = context.configuration.relayers.staking.clone() else {
+            panic!("no staking configuration")
+        };
+
+        Self { configuration, context }
+    }
+
+    async fn run(self) -> Result<(), ServiceError> {
+        // The lease outlives the interval so that the leader keeps it between two checks
+        let leader = LeaderLock::new(
+            &self.context.configuration.relayers.lock,
+            &format!("gas-tank-staking:{}", self.context.gas_tank.address().to_fixed_hex_string()),
+            Duration::from_secs(self.configuration.check_interval * 2),
+        )
+        .map_err(ServiceError::from)?;
+
+        let mut ticker = time::interval(Duration::from_secs(self.configuration.check_interval));
+        loop {
+            ticker.tick().await;
+            if !leader.acquire().await {
+                continue;
+            }
+
+            service_check!(self.try_stake().await => continue);
+        }
+    }
+}
+
+impl GasTankStakingService {
+    async fn fetch_pool_member(&self) -> Result<Option<PoolMember>, ServiceError> {
+        let result = self
+            .context
+            .starknet
+            .call(&FunctionCall {
+                contract_address: self.configuration.pool,
+                entry_point_selector: selector!("pool_member_info_v1"),
+                calldata: vec![self.context.gas_tank.address()],
+            })
+            .await
+            .map_err(ServiceError::from)?;
+
+        Ok(PoolMember::parse(&result))
+    }
+
+    /// STRK (in FRI) the relayers are expected to spend during the exit window, extrapolated from their spending
+    /// during the current day
+    async fn exit_window_spending(&self) -> Felt {
+        let spent = match self.context.relayers_locks.spent(None).await {
+            Ok(spent) => spent.daily,
+            Err(e) => {
+                error!("Failed to fetch the spending of the relayers, no reserve kept for the exit window: {}", e);
+                return Felt::ZERO;
+            },
+        };
+
+        // The first hour of the day is extrapolated as a whole hour to smooth the first transactions
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let elapsed = (now % SpendWindow::Daily.duration().as_secs()).max(3600);
+        let spent = u128::try_from(spent).unwrap_or(u128::MAX);
+
+        Felt::from(spent.saturating_mul(self.configuration.exit_window as u128) / elapsed as u128)
+    }
+
+    async fn try_stake(&self) -> Result<(), ServiceError> {
+        let strk = self.context.starknet.tokens().strk;
+        let gas_tank = self.context.gas_tank.address();
+        let balance = self
+            .context
+            .starknet
+            .fetch_balance(strk, gas_tank)
+            .await
+            .map_err(ServiceError::from)?;
+        let member = self.fetch_pool_member().await?;
+        let reserve = self.exit_window_spending().await;
+
+        metric!(gauge[gas_tank_staked_strk] = member.map(|x| denormalize_felt(x.amount, 18)).unwrap_or_default());
+        metric!(gauge[gas_tank_staking_reserve_strk] = denormalize_felt(reserve, 18));
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let Some(action) = StakingAction::plan(&self.configuration, balance, member, reserve, now) else {
+            return Ok(());
+        };
+
+        let calls = action.to_calls(strk, self.configuration.pool, gas_tank);
+        match self.context.gas_tank.send(&calls).await {
+            Ok(submission) => {
+                info!(
+                    target: AUDIT_TARGET,
+                    action = action.name(),
+                    pool = %self.configuration.pool.to_fixed_hex_string(),
+                    gas_tank_balance = denormalize_felt(balance, 18),
+                    reserve = denormalize_felt(reserve, 18),
+                    transaction_hash = %submission.transaction_hash().to_fixed_hex_string(),
+                    status = submission.status(),
+                    "{:?}",
+                    action
+                );
+                metric!(counter[gas_tank_staking] = 1, action = action.name(), status = "success");
+                Ok(())
+            },
+            Err(e) => {
+                error!(
+                    target: AUDIT_TARGET,
+                    action = action.name(),
+                    pool = %self.configuration.pool.to_fixed_hex_string(),
+                    gas_tank_balance = denormalize_felt(balance, 18),
+                    "{:?} failed: {}",
+                    action,
+                    e
+                );
+                metric!(counter[gas_tank_staking] = 1, action = action.name(), status = "failure");
+                Err(ServiceError::new(&format!("failed to {} gas tank funds: {}", action.name(), e)))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use crate::staking::{PoolMember, StakingAction, StakingConfiguration};
+
+    fn configuration() -> StakingConfiguration {
+        StakingConfiguration {
+            pool: Felt::ONE,
+            float: Felt::from(1000),
+            buffer: Felt::from(100),
+            min_delegation: Felt::from(50),
+            check_interval: 60,
+            exit_window: 86400,
+        }
+    }
+
+    fn member(amount: u64) -> Option<PoolMember> {
+        Some(PoolMember {
+            amount: Felt::from(amount),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn funds_above_float_are_delegated() {
+        let configuration = configuration();
+
+        assert_eq!(StakingAction::plan(&configuration, Felt::from(1050), None, Felt::ZERO, 0), None);
+        assert_eq!(
+            StakingAction::plan(&configuration, Felt::from(1500), None, Felt::ZERO, 0),
+            Some(StakingAction::Delegate {
+                amount: Felt::from(500),
+                enter: true
+            })
+        );
+        assert_eq!(
+            StakingAction::plan(&configuration, Felt::from(1500), member(200), Felt::ZERO, 0),
+            Some(StakingAction::Delegate {
+                amount: Felt::from(500),
+                enter: false
+            })
+        );
+    }
+
+    #[test]
+    fn funds_are_undelegated_when_balance_runs_low() {
+        let configuration = configuration();
+
+        assert_eq!(StakingAction::plan(&configuration, Felt::from(950), member(200), Felt::ZERO, 0), None);
+        assert_eq!(StakingAction::plan(&configuration, Felt::from(500), None, Felt::ZERO, 0), None);
+        assert_eq!(
+            StakingAction::plan(&configuration, Felt::from(500), member(200), Felt::ZERO, 0),
+            Some(StakingAction::Undelegate { amount: Felt::from(200) })
+        );
+    }
+
+    #[test]
+    fn funds_are_undelegated_ahead_of_the_exit_window() {
+        let configuration = configuration();
+        let reserve = Felt::from(300);
+
+        // The balance covers the float but not the spending expected until the funds can be withdrawn
+        assert_eq!(StakingAction::plan(&configuration, Felt::from(1050), member(200), Felt::ZERO, 0), None);
+        assert_eq!(
+            StakingAction::plan(&configuration, Felt::from(1050), member(200), reserve, 0),
+            Some(StakingAction::Undelegate { amount: Felt::from(200) })
+        );
+
+        // The reserve is never delegated
+        assert_eq!(StakingAction::plan(&configuration, Felt::from(1350), None, reserve, 0), None);
+        assert_eq!(
+            StakingAction::plan(&configuration, Felt::from(1500), None, reserve, 0),
+            Some(StakingAction::Delegate {
+                amount: Felt::from(200),
+                enter: true
+            })
+        );
+    }
+
+    #[test]
+    fn pending_undelegation_is_withdrawn_once_available() {
+        let configuration = configuration();
+        let pending = Some(PoolMember {
+            amount: Felt::ZERO,
+            unpool_amount: Felt::from(200),
+            unpool_time: Some(100),
+        });
+
+        assert_eq!(StakingAction::plan(&configuration, Felt::from(5000), pending, Felt::ZERO, 99), None);
+        assert_eq!(
+            StakingAction::plan(&configuration, Felt::from(5000), pending, Felt::ZERO, 100),
+            Some(StakingAction::Withdraw)
+        );
+    }
+
+    #[test]
+    fn pool_member_is_parsed() {
+        assert_eq!(PoolMember::parse(&[Felt::ONE]), None);
+
+        let result = [
+            Felt::ZERO,
+            Felt::TWO,
+            Felt::from(300),
+            Felt::ZERO,
+            Felt::ZERO,
+            Felt::from(100),
+            Felt::ZERO,
+            Felt::from(42),
+        ];
+        assert_eq!(
+            PoolMember::parse(&result),
+            Some(PoolMember {
+                amount: Felt::from(300),
+                unpool_amount: Felt::from(100),
+                unpool_time: Some(42),
+            })
+        );
+    }
+}
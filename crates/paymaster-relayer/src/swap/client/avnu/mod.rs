@@ -3,9 +3,10 @@ pub mod models;
 use std::time::Duration;
 
 use crate::swap::client::avnu::models::{AVNUBuildedQuote, AVNUQuote};
-use crate::swap::client::{Swap, SwapClientConfiguration};
+use crate::swap::client::{Swap, SwapClientConfiguration, SwapQuote};
 use crate::swap::SwapClient;
 use async_trait::async_trait;
+use paymaster_common::egress;
 use paymaster_common::service::Error as ServiceError;
 use reqwest::Client as HTTPClient;
 use serde_json::json;
@@ -30,8 +31,7 @@ impl AVNUSwapClient {
     pub fn new(configuration: &SwapClientConfiguration) -> Self {
         Self {
             endpoint: configuration.endpoint.clone(),
-            client: HTTPClient::builder()
-                .timeout(Duration::from_secs(3))
+            client: egress::apply(HTTPClient::builder().timeout(Duration::from_secs(3)))
                 .build()
                 .expect("invalid client"),
         }
@@ -39,6 +39,17 @@ impl AVNUSwapClient {
 
     // Get quotes fora swap
     async fn get_quote(&self, sell_token: Felt, buy_token: Felt, sell_amount: Felt, taker_address: Felt, max_price_impact: f64) -> Result<AVNUQuote, ServiceError> {
+        let quote = self.fetch_quote(sell_token, buy_token, sell_amount, taker_address).await?;
+
+        // Verify security of the quote
+        quote.assert_security(max_price_impact)?;
+
+        Ok(quote)
+    }
+
+    // Get the best quote for a swap, without verifying it
+    async fn fetch_quote(&self, sell_token: Felt, buy_token: Felt, sell_amount: Felt, taker_address: Felt) -> Result<AVNUQuote, ServiceError> {
+        egress::ensure_allowed(&self.endpoint).map_err(|e| ServiceError::new(&e.to_string()))?;
         let response = self
             .client
             .get(&format!("{}/quotes", self.endpoint))
@@ -66,14 +77,12 @@ impl AVNUSwapClient {
         }
         let quote = quotes.into_iter().next().unwrap();
 
-        // Verify security of the quote
-        quote.assert_security(max_price_impact)?;
-
         Ok(quote)
     }
 
     // Build transaction calls based on quote_id received
     async fn build_transaction(&self, quote_id: &str, taker_address: Felt, slippage: f64) -> Result<AVNUBuildedQuote, ServiceError> {
+        egress::ensure_allowed(&self.endpoint).map_err(|e| ServiceError::new(&e.to_string()))?;
         let request_body = json!({
             "quoteId": quote_id,
             "takerAddress": format!("0x{:x}", taker_address),
@@ -129,6 +138,15 @@ impl Swap for AVNUSwapClient {
         let calls = build_response.calls.into_iter().map(|call| call.as_call()).collect();
         Ok((calls, min_received))
     }
+
+    async fn quote(&self, sell_token: Felt, buy_token: Felt, sell_amount: Felt, taker_address: Felt) -> Result<SwapQuote, ServiceError> {
+        let quote = self.fetch_quote(sell_token, buy_token, sell_amount, taker_address).await?;
+
+        Ok(SwapQuote {
+            price_impact: quote.price_impact()?,
+            sell_amount_in_usd: quote.sell_amount_in_usd.unwrap_or_default(),
+        })
+    }
 }
 
 #[cfg(test)]
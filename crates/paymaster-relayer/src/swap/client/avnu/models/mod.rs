@@ -21,6 +21,20 @@ pub struct AVNUQuote {
 impl AVNUQuote {
     // Validates the quote price by comparing USD values
     pub fn assert_security(&self, max_price_impact: f64) -> Result<(), ServiceError> {
+        let price_impact = self.price_impact()?;
+
+        if price_impact > max_price_impact.abs() {
+            return Err(ServiceError::new(&format!(
+                "Quote price impact is too high: {:.2}% (max allowed: {:.2}%)",
+                price_impact * 100.0,
+                max_price_impact.abs() * 100.0
+            )));
+        }
+        Ok(())
+    }
+
+    // Price impact of the quote, computed from the USD values of the sold and bought amounts
+    pub fn price_impact(&self) -> Result<f64, ServiceError> {
         let sell_amount_in_usd = self
             .sell_amount_in_usd
             .ok_or_else(|| ServiceError::new("Missing USD value for sell amount in quote"))?;
@@ -33,16 +47,7 @@ impl AVNUQuote {
             return Err(ServiceError::new("Invalid USD values in quote"));
         }
 
-        let price_impact = (sell_amount_in_usd - buy_amount_in_usd) / sell_amount_in_usd;
-
-        if price_impact > max_price_impact.abs() {
-            return Err(ServiceError::new(&format!(
-                "Quote price impact is too high: {:.2}% (max allowed: {:.2}%)",
-                price_impact * 100.0,
-                max_price_impact.abs() * 100.0
-            )));
-        }
-        Ok(())
+        Ok((sell_amount_in_usd - buy_amount_in_usd) / sell_amount_in_usd)
     }
 
     // Verify that the quote is above the minimum sell value
@@ -4,6 +4,8 @@ use async_trait::async_trait;
 use paymaster_common::service::Error as ServiceError;
 use starknet::core::types::{Call, Felt};
 
+use crate::swap::client::SwapQuote;
+
 #[async_trait]
 pub trait MockSwapClient: 'static + Send + Sync + Debug {
     fn new() -> Self
@@ -22,6 +24,13 @@ pub trait MockSwapClient: 'static + Send + Sync + Debug {
     ) -> Result<(Vec<Call>, Felt), ServiceError> {
         unimplemented!()
     }
+
+    async fn quote(&self, _sell_token: Felt, _buy_token: Felt, _sell_amount: Felt, _taker_address: Felt) -> Result<SwapQuote, ServiceError> {
+        Ok(SwapQuote {
+            price_impact: 0.0,
+            sell_amount_in_usd: f64::MAX,
+        })
+    }
 }
 
 /// Simple mock implementation for testing
@@ -7,6 +7,7 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use paymaster_common::service::Error as ServiceError;
+use paymaster_common::validation::{Validate, ValidationReport};
 use paymaster_starknet::ChainID;
 use serde::{Deserialize, Serialize};
 use starknet::core::types::{Call, Felt};
@@ -29,6 +30,16 @@ pub trait Swap: 'static + Send + Sync + Clone {
         max_price_impact: f64,
         min_usd_sell_amount: f64,
     ) -> Result<(Vec<Call>, Felt), ServiceError>;
+
+    // Quote the swap of the given amount without building it
+    async fn quote(&self, sell_token: Felt, buy_token: Felt, sell_amount: Felt, taker_address: Felt) -> Result<SwapQuote, ServiceError>;
+}
+
+/// Quote of a swap, used to decide how much of a balance to sell at once
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SwapQuote {
+    pub price_impact: f64,
+    pub sell_amount_in_usd: f64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -49,7 +60,7 @@ impl SwapClientConfiguration {
     pub fn default_mainnet() -> Self {
         Self {
             endpoint: DEFAULT_MAINNET_AVNU_SWAP_ENDPOINT.to_string(),
-            chain_id: ChainID::Sepolia,
+            chain_id: ChainID::Mainnet,
         }
     }
 
@@ -59,16 +70,13 @@ impl SwapClientConfiguration {
             chain_id: ChainID::Sepolia,
         }
     }
+}
 
-    /// Validate configuration
-    pub fn validate(&self) -> Result<(), ServiceError> {
-        // Validate endpoint
-        if self.endpoint.is_empty() {
-            return Err(ServiceError::new("AVNU endpoint cannot be empty"));
-        }
+impl Validate for SwapClientConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
         // Any chain id is accepted: Mainnet/Sepolia use their respective AVNU
         // deployments, and Unknown chains reuse the Sepolia configuration.
-        Ok(())
+        report.ensure_url("endpoint", &self.endpoint);
     }
 }
 
@@ -98,12 +106,12 @@ impl SwapClientConfigurator {
     }
 }
 
-impl SwapClientConfigurator {
-    pub fn validate(&self) -> Result<(), ServiceError> {
+impl Validate for SwapClientConfigurator {
+    fn validate_into(&self, report: &mut ValidationReport) {
         match self {
             #[cfg(feature = "testing")]
-            SwapClientConfigurator::Mock(_) => Ok(()), // Mock doesn't need validation
-            SwapClientConfigurator::AVNU(config) => config.validate(),
+            SwapClientConfigurator::Mock(_) => {}, // Mock doesn't need validation
+            SwapClientConfigurator::AVNU(config) => config.validate_into(report),
         }
     }
 }
@@ -144,6 +152,14 @@ impl SwapClient {
             },
         }
     }
+
+    pub async fn quote(&self, sell_token: Felt, buy_token: Felt, sell_amount: Felt, taker_address: Felt) -> Result<SwapQuote, ServiceError> {
+        match self {
+            #[cfg(feature = "testing")]
+            SwapClient::Mock(x) => x.quote(sell_token, buy_token, sell_amount, taker_address).await,
+            SwapClient::AVNU(x) => x.quote(sell_token, buy_token, sell_amount, taker_address).await,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -156,7 +172,7 @@ mod tests {
             endpoint: DEFAULT_SEPOLIA_AVNU_SWAP_ENDPOINT.to_string(),
             chain_id: ChainID::Unknown(Felt::from_hex("0x534e5f4b41545241").unwrap()),
         };
-        assert!(config.validate().is_ok());
+        assert!(config.validate_all().is_ok());
     }
 
     #[test]
@@ -1,8 +1,13 @@
 pub mod client;
 
-pub use client::{SwapClient, SwapClientConfigurator};
+pub use client::{SwapClient, SwapClientConfigurator, SwapQuote};
 use paymaster_common::service::Error as ServiceError;
+use paymaster_common::validation::{Validate, ValidationReport};
 use serde::{Deserialize, Serialize};
+use starknet::core::types::{Felt, NonZeroFelt};
+
+/// Maximum number of times the amount sold at once is halved to reduce its price impact
+const MAX_TRANCHE_SPLITS: usize = 5;
 
 // Configuration for swap service
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -17,25 +22,122 @@ pub struct SwapConfiguration {
     pub swap_interval: u64,
     // Minimum sell value for a swap (in USD)
     pub min_usd_sell_amount: f64,
+    // Price impact above which a balance is sold in tranches over the next swap intervals rather than at once,
+    // the amount sold being halved until its quote falls below it. Balances are sold at once when not set.
+    #[serde(default)]
+    pub tranche_price_impact: Option<f64>,
 }
 
-impl SwapConfiguration {
-    // Validates the configuration parameters
-    pub fn validate(&self) -> Result<(), ServiceError> {
-        if self.slippage < 0.0 || self.slippage > 1.0 {
-            return Err(ServiceError::new("slippage must be between 0.0 and 1.0"));
-        }
-        if self.max_price_impact < 0.0 || self.max_price_impact > 1.0 {
-            return Err(ServiceError::new("Max price impact must be between 0.0 and 1.0"));
-        }
-        if self.min_usd_sell_amount <= 0.0 {
-            return Err(ServiceError::new("min_usd_sell_amount must be greater than 0.0"));
+impl Validate for SwapConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure((0.0..=1.0).contains(&self.slippage), "slippage", "slippage must be between 0.0 and 1.0");
+        report.ensure(
+            (0.0..=1.0).contains(&self.max_price_impact),
+            "max_price_impact",
+            "Max price impact must be between 0.0 and 1.0",
+        );
+        report.ensure(self.min_usd_sell_amount > 0.0, "min_usd_sell_amount", "min_usd_sell_amount must be greater than 0.0");
+        if let Some(tranche_price_impact) = self.tranche_price_impact {
+            report.ensure(
+                tranche_price_impact > 0.0 && tranche_price_impact <= self.max_price_impact,
+                "tranche_price_impact",
+                "Tranche price impact must be between 0.0 and max_price_impact",
+            );
         }
-        self.swap_client_config.validate()
+        report.field("swap_client_config", &self.swap_client_config);
     }
+}
 
+impl SwapConfiguration {
     /// Create a swap client from this configuration
     pub fn create_client(&self) -> SwapClient {
         SwapClient::new(&self.swap_client_config)
     }
+
+    /// Amount of `balance` to sell during the current swap interval. When the quote of the whole balance has a price
+    /// impact above [`tranche_price_impact`], the amount is halved until it falls below, as long as the tranche
+    /// stays above the minimum sell value. The remaining balance is sold during the next intervals.
+    pub async fn tranche_amount(&self, client: &SwapClient, sell_token: Felt, buy_token: Felt, balance: Felt, taker_address: Felt) -> Result<Felt, ServiceError> {
+        let Some(tranche_price_impact) = self.tranche_price_impact else {
+            return Ok(balance);
+        };
+
+        let two = NonZeroFelt::from_felt_unchecked(Felt::TWO);
+        let mut amount = balance;
+        for _ in 0..MAX_TRANCHE_SPLITS {
+            let quote = client.quote(sell_token, buy_token, amount, taker_address).await?;
+
+            // Halving the amount roughly halves its value
+            if quote.price_impact <= tranche_price_impact || quote.sell_amount_in_usd / 2.0 < self.min_usd_sell_amount {
+                break;
+            }
+            amount = amount.floor_div(&two);
+        }
+
+        Ok(amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use paymaster_common::service::Error as ServiceError;
+    use starknet::core::types::Felt;
+
+    use crate::swap::client::mock::MockSwapClient;
+    use crate::swap::{SwapClient, SwapClientConfigurator, SwapConfiguration, SwapQuote};
+
+    // Price impact of 1% per 100 tokens sold, each token being worth 1 USD
+    #[derive(Debug)]
+    struct ThinPair;
+
+    #[async_trait]
+    impl MockSwapClient for ThinPair {
+        fn new() -> Self {
+            Self
+        }
+
+        async fn quote(&self, _: Felt, _: Felt, sell_amount: Felt, _: Felt) -> Result<SwapQuote, ServiceError> {
+            let amount = u64::try_from(sell_amount).unwrap() as f64;
+            Ok(SwapQuote {
+                price_impact: amount / 10_000.0,
+                sell_amount_in_usd: amount,
+            })
+        }
+    }
+
+    fn configuration(tranche_price_impact: Option<f64>, min_usd_sell_amount: f64) -> SwapConfiguration {
+        SwapConfiguration {
+            slippage: 0.01,
+            swap_client_config: SwapClientConfigurator::mock::<ThinPair>(),
+            max_price_impact: 0.1,
+            swap_interval: 60,
+            min_usd_sell_amount,
+            tranche_price_impact,
+        }
+    }
+
+    async fn tranche_amount(configuration: SwapConfiguration, balance: u64) -> Felt {
+        let client = SwapClient::mock::<ThinPair>();
+        configuration
+            .tranche_amount(&client, Felt::ONE, Felt::TWO, Felt::from(balance), Felt::THREE)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn balance_is_sold_at_once_without_tranches() {
+        assert_eq!(tranche_amount(configuration(None, 1.0), 1000).await, Felt::from(1000));
+    }
+
+    #[tokio::test]
+    async fn balance_is_split_until_price_impact_is_acceptable() {
+        assert_eq!(tranche_amount(configuration(Some(0.05), 1.0), 400).await, Felt::from(400));
+        assert_eq!(tranche_amount(configuration(Some(0.02), 1.0), 1000).await, Felt::from(125));
+    }
+
+    #[tokio::test]
+    async fn tranches_stay_above_min_sell_value() {
+        assert_eq!(tranche_amount(configuration(Some(0.02), 400.0), 1000).await, Felt::from(500));
+    }
 }
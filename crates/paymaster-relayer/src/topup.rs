@@ -0,0 +1,365 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use deadpool_redis::redis::{pipe, AsyncCommands};
+use deadpool_redis::Pool;
+use paymaster_common::service::{Error as ServiceError, Service};
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_common::{metric, service_check};
+use paymaster_starknet::math::denormalize_felt;
+use paymaster_starknet::transaction::{Calls, TokenTransfer};
+use paymaster_starknet::{StarknetAccount, StarknetAccountConfiguration};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::accounts::{Account, ConnectedAccount};
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+use tokio::time;
+use tracing::{error, info};
+
+use crate::lock::leader::LeaderLock;
+use crate::lock::{Error, LockLayerConfiguration};
+use crate::spend::{from_spend_units, to_spend_units, SpendWindow};
+use crate::Context;
+
+/// Maximum duration to wait for a top-up to be accepted before checking the balance of the gas tank again
+const ACCEPTANCE_TIMEOUT: Duration = Duration::from_secs(120);
+const ACCEPTANCE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Configuration of the automatic top-up of the gas tank from a treasury account
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GasTankTopUpConfiguration {
+    /// Account holding the funds used to top up the gas tank
+    pub treasury: StarknetAccountConfiguration,
+
+    /// STRK balance (in FRI) of the gas tank below which a top-up is triggered
+    #[serde_as(as = "UfeHex")]
+    pub floor: Felt,
+
+    /// Amount of STRK (in FRI) transferred by each top-up
+    #[serde_as(as = "UfeHex")]
+    pub amount: Felt,
+
+    /// Maximum amount of STRK (in FRI) transferred to the gas tank per day
+    #[serde_as(as = "UfeHex")]
+    pub daily_cap: Felt,
+
+    /// How often to check the gas tank balance (in seconds)
+    #[serde(default = "GasTankTopUpConfiguration::default_check_interval")]
+    pub check_interval: u64,
+}
+
+impl GasTankTopUpConfiguration {
+    pub fn default_check_interval() -> u64 {
+        300
+    }
+}
+
+impl Validate for GasTankTopUpConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.field("treasury", &self.treasury);
+        report.ensure(self.floor != Felt::ZERO, "floor", "must not be zero");
+        report.ensure(self.amount != Felt::ZERO, "amount", "must not be zero");
+        report.ensure(self.daily_cap >= self.amount, "daily_cap", "must be greater than or equal to amount");
+        report.ensure(self.check_interval > 0, "check_interval", "must be greater than 0");
+    }
+}
+
+/// Amount transferred to the gas tank during the current day
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TopUpBudget {
+    bucket: u64,
+    spent: Felt,
+}
+
+impl TopUpBudget {
+    /// Returns the amount which can still be transferred during the day of `bucket` given the daily `cap`
+    pub fn remaining(&self, bucket: u64, cap: Felt) -> Felt {
+        let spent = if self.bucket == bucket { self.spent } else { Felt::ZERO };
+        if spent >= cap {
+            Felt::ZERO
+        } else {
+            cap - spent
+        }
+    }
+
+    /// Record an `amount` transferred during the day of `bucket`
+    pub fn record(&mut self, bucket: u64, amount: Felt) {
+        if self.bucket != bucket {
+            *self = Self { bucket, spent: Felt::ZERO };
+        }
+        self.spent += amount;
+    }
+}
+
+/// Amount transferred to the gas tank per day, shared by the instances through the Redis of the shared lock layer
+/// so that the cap holds across instances and restarts. Kept in memory with a lock layer local to the instance.
+#[derive(Clone)]
+pub struct TopUpLedger {
+    redis: Option<Pool>,
+    key: String,
+    local: Arc<Mutex<TopUpBudget>>,
+}
+
+impl TopUpLedger {
+    pub fn new(configuration: &LockLayerConfiguration, gas_tank: Felt) -> Result<Self, Error> {
+        let redis = match configuration {
+            LockLayerConfiguration::Shared { redis, .. } => Some(redis.create_pool()?),
+            _ => None,
+        };
+
+        Ok(Self {
+            redis,
+            key: format!("gas-tank-top-up:{}", gas_tank.to_fixed_hex_string()),
+            local: Arc::new(Mutex::new(TopUpBudget::default())),
+        })
+    }
+
+    /// Returns the amount which can still be transferred during the current day given the daily `cap`
+    pub async fn remaining(&self, cap: Felt) -> Result<Felt, Error> {
+        let bucket = SpendWindow::Daily.current_bucket();
+        let Some(redis) = &self.redis else {
+            return Ok(self.local.lock().unwrap_or_else(|e| e.into_inner()).remaining(bucket, cap));
+        };
+
+        let mut connection = redis.get().await?;
+        let units: Option<u64> = connection.get(self.bucket_key(bucket)).await?;
+        let budget = TopUpBudget {
+            bucket,
+            spent: from_spend_units(units.unwrap_or_default()),
+        };
+
+        Ok(budget.remaining(bucket, cap))
+    }
+
+    /// Record an `amount` transferred during the current day
+    pub async fn record(&self, amount: Felt) -> Result<(), Error> {
+        let bucket = SpendWindow::Daily.current_bucket();
+        let Some(redis) = &self.redis else {
+            self.local.lock().unwrap_or_else(|e| e.into_inner()).record(bucket, amount);
+            return Ok(());
+        };
+
+        let mut connection = redis.get().await?;
+        let mut pipeline = pipe();
+        pipeline.atomic();
+        pipeline.incr(self.bucket_key(bucket), to_spend_units(amount)).ignore();
+        pipeline
+            .expire(self.bucket_key(bucket), SpendWindow::Daily.duration().as_secs() as i64)
+            .ignore();
+        pipeline.query_async::<()>(&mut connection).await?;
+
+        Ok(())
+    }
+
+    fn bucket_key(&self, bucket: u64) -> String {
+        format!("{}:{}", self.key, bucket)
+    }
+}
+
+/// Tops up the gas tank from the treasury account whenever its STRK balance drops below the configured floor,
+/// so that rebalancing never stalls for lack of STRK. Transfers are capped per day and an alert is raised when
+/// the gas tank cannot be topped up. Runs on a single instance at a time and waits for each top-up to be
+/// accepted before checking the balance again.
+pub struct GasTankTopUpService {
+    context: Context,
+    configuration: GasTankTopUpConfiguration,
+    treasury: StarknetAccount,
+
+    // Top-up sent but not accepted yet
+    pending: Option<Felt>,
+}
+
+#[async_trait]
+impl Service for GasTankTopUpService {
+    type Context = Context;
+
+    const NAME: &'static str = "GasTankTopUp";
+
+    async fn new(context: Context) -> Self {
+        let Some(configuration) = context.configuration.relayers.gas_tank_top_up.clone() else {
+            panic!("no gas tank top-up configuration")
+        };
+
+        Self {
+            treasury: context.starknet.initialize_account(&configuration.treasury),
+            pending: None,
+            configuration,
+            context,
+        }
+    }
+
+    async fn run(mut self) -> Result<(), ServiceError> {
+        let gas_tank = self.context.configuration.gas_tank.address;
+        let budget = TopUpLedger::new(&self.context.configuration.relayers.lock, gas_tank).map_err(ServiceError::from)?;
+
+        // The lease outlives the interval so that the leader keeps it between two checks
+        let leader = LeaderLock::new(
+            &self.context.configuration.relayers.lock,
+            &format!("gas-tank-top-up:{}", gas_tank.to_fixed_hex_string()),
+            Duration::from_secs(self.configuration.check_interval * 2),
+        )
+        .map_err(ServiceError::from)?;
+
+        let mut ticker = time::interval(Duration::from_secs(self.configuration.check_interval));
+        loop {
+            ticker.tick().await;
+            if !leader.acquire().await {
+                continue;
+            }
+
+            service_check!(self.try_top_up(&budget).await => continue);
+        }
+    }
+}
+
+impl GasTankTopUpService {
+    /// Returns true once the top-up sent before, if any, is no longer pending
+    async fn settle_pending_top_up(&mut self) -> bool {
+        let Some(transaction_hash) = self.pending else { return true };
+
+        match self
+            .context
+            .starknet
+            .wait_for_acceptance(transaction_hash, ACCEPTANCE_POLL_INTERVAL, ACCEPTANCE_TIMEOUT)
+            .await
+        {
+            Ok(succeeded) => {
+                if !succeeded {
+                    error!("Gas tank top-up reverted, tx hash: {:?}", transaction_hash);
+                    metric!(counter[gas_tank_top_up] = 1, status = "reverted");
+                }
+                self.pending = None;
+                true
+            },
+            Err(e) => {
+                error!("Gas tank top-up not accepted yet, checking again next round: {}", e);
+                false
+            },
+        }
+    }
+
+    async fn try_top_up(&mut self, budget: &TopUpLedger) -> Result<(), ServiceError> {
+        if !self.settle_pending_top_up().await {
+            return Ok(());
+        }
+
+        let strk = self.context.starknet.tokens().strk;
+        let gas_tank = self.context.configuration.gas_tank.address;
+        let gas_tank_balance = self
+            .context
+            .starknet
+            .fetch_balance(strk, gas_tank)
+            .await
+            .map_err(ServiceError::from)?;
+
+        if gas_tank_balance >= self.configuration.floor {
+            return Ok(());
+        }
+
+        let remaining = budget
+            .remaining(self.configuration.daily_cap)
+            .await
+            .map_err(ServiceError::from)?;
+        if remaining == Felt::ZERO {
+            error!(
+                "Gas tank balance is below the floor ({} STRK) but the daily top-up cap is reached",
+                denormalize_felt(gas_tank_balance, 18)
+            );
+            metric!(counter[gas_tank_top_up] = 1, status = "cap_reached");
+            return Ok(());
+        }
+
+        let amount = self.configuration.amount.min(remaining);
+        let treasury_balance = self
+            .context
+            .starknet
+            .fetch_balance(strk, self.treasury.address())
+            .await
+            .map_err(ServiceError::from)?;
+
+        if treasury_balance <= amount {
+            error!(
+                "Gas tank balance is below the floor ({} STRK) but the treasury does not have enough funds ({} STRK)",
+                denormalize_felt(gas_tank_balance, 18),
+                denormalize_felt(treasury_balance, 18)
+            );
+            metric!(counter[gas_tank_top_up] = 1, status = "insufficient_funds");
+            return Ok(());
+        }
+
+        let calls = Calls::new(vec![TokenTransfer::new(strk, gas_tank, amount).to_call()]);
+        let nonce = self.treasury.get_nonce().await.map_err(|e| ServiceError::new(&e.to_string()))?;
+        let result = match calls.estimate(&self.treasury, None).await {
+            Ok(estimated_calls) => estimated_calls.execute(&self.treasury, nonce).await,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(execution) => {
+                self.pending = Some(execution.transaction_hash);
+                info!(
+                    "Gas tank topped up with {} STRK, tx hash: {:?}",
+                    denormalize_felt(amount, 18),
+                    execution.transaction_hash
+                );
+                metric!(counter[gas_tank_top_up] = 1, status = "success");
+
+                budget.record(amount).await.map_err(ServiceError::from)?;
+                self.settle_pending_top_up().await;
+                Ok(())
+            },
+            Err(e) => {
+                metric!(counter[gas_tank_top_up] = 1, status = "failure");
+                Err(ServiceError::new(&format!("failed to top up the gas tank: {}", e)))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use starknet::core::types::Felt;
+
+    use crate::lock::LockLayerConfiguration;
+    use crate::topup::{TopUpBudget, TopUpLedger};
+
+    #[test]
+    fn budget_is_capped_per_day() {
+        let mut budget = TopUpBudget::default();
+        let cap = Felt::from(100);
+
+        assert_eq!(budget.remaining(1, cap), cap);
+
+        budget.record(1, Felt::from(60));
+        assert_eq!(budget.remaining(1, cap), Felt::from(40));
+
+        budget.record(1, Felt::from(60));
+        assert_eq!(budget.remaining(1, cap), Felt::ZERO);
+
+        // A new day resets the budget
+        assert_eq!(budget.remaining(2, cap), cap);
+        budget.record(2, Felt::from(10));
+        assert_eq!(budget.remaining(2, cap), Felt::from(90));
+    }
+
+    #[tokio::test]
+    async fn local_ledger_caps_the_top_ups_per_day() {
+        let configuration = LockLayerConfiguration::Seggregated {
+            retry_timeout: Duration::from_secs(5),
+        };
+        let ledger = TopUpLedger::new(&configuration, Felt::ONE).unwrap();
+        let cap = Felt::from(100);
+
+        ledger.record(Felt::from(60)).await.unwrap();
+        assert_eq!(ledger.remaining(cap).await.unwrap(), Felt::from(40));
+
+        // The ledger is shared by the clones used across the tasks
+        ledger.clone().record(Felt::from(60)).await.unwrap();
+        assert_eq!(ledger.remaining(cap).await.unwrap(), Felt::ZERO);
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::{HashMap, HashSet};
+
+use paymaster_common::service::Error as ServiceError;
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use crate::context::Context;
+
+pub mod report;
+
+/// Tolerance on the sum of the weights of a [`TreasuryPolicyConfiguration`]
+const WEIGHTS_TOLERANCE: f64 = 1e-6;
+
+/// Mix of tokens the gas tank keeps instead of swapping everything to STRK, e.g 80% of STRK and 20% of USDC to
+/// hold a stable-denominated buffer. Weights are expressed in STRK value, must sum to 1 and include the STRK token
+/// of the chain, which is checked along with the configuration of the relayers.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TreasuryPolicyConfiguration {
+    /// Target weight of each token, the tokens without weight are swapped entirely
+    pub weights: HashMap<Felt, f64>,
+}
+
+impl Validate for TreasuryPolicyConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        for (token, weight) in &self.weights {
+            report.ensure(
+                (0.0..=1.0).contains(weight),
+                &format!("weights.{}", token.to_hex_string()),
+                "must be between 0.0 and 1.0",
+            );
+        }
+
+        let total: f64 = self.weights.values().sum();
+        report.ensure((total - 1.0).abs() < WEIGHTS_TOLERANCE, "weights", "weights must sum to 1.0");
+    }
+}
+
+/// Balance of a token held by the gas tank
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreasuryHolding {
+    pub token: Felt,
+    pub balance: Felt,
+    pub value_in_strk: Felt,
+}
+
+impl TreasuryPolicyConfiguration {
+    pub fn weight(&self, token: Felt) -> f64 {
+        self.weights.get(&token).copied().unwrap_or_default()
+    }
+
+    /// Amount of each token to swap to STRK so that the tokens above their target weight come back to it. The
+    /// tokens below their target are kept and build up from the fees collected, `strk` is never sold.
+    pub fn excess_amounts(&self, strk: Felt, holdings: &[TreasuryHolding]) -> HashMap<Felt, Felt> {
+        let total: f64 = holdings.iter().map(|x| to_f64(x.value_in_strk)).sum();
+
+        holdings
+            .iter()
+            .filter(|holding| holding.token != strk)
+            .filter_map(|holding| {
+                let value = to_f64(holding.value_in_strk);
+                let excess = value - total * self.weight(holding.token);
+                if value <= 0.0 || excess <= 0.0 {
+                    return None;
+                }
+
+                let amount = (to_f64(holding.balance) * (excess / value)) as u128;
+                Some((holding.token, Felt::from(amount).min(holding.balance)))
+            })
+            .collect()
+    }
+}
+
+fn to_f64(value: Felt) -> f64 {
+    u128::try_from(value).unwrap_or(u128::MAX) as f64
+}
+
+/// Fetch the balance of the given tokens and of STRK held by `owner`, valued in STRK
+pub async fn fetch_holdings(context: &Context, owner: Felt, tokens: &HashSet<Felt>) -> Result<Vec<TreasuryHolding>, ServiceError> {
+    let strk = context.starknet.tokens().strk;
+    let mut tokens = tokens.clone();
+    tokens.insert(strk);
+
+    let mut holdings = vec![];
+    for token in tokens {
+        let balance = context
+            .starknet
+            .fetch_balance(token, owner)
+            .await
+            .map_err(|e| ServiceError::new(&format!("Failed to fetch balance of token {}: {}", token.to_hex_string(), e)))?;
+
+        let value_in_strk = if token == strk {
+            balance
+        } else {
+            context
+                .price
+                .convert_token_to_strk(token, balance)
+                .await
+                .map_err(|e| ServiceError::new(&format!("Failed to value token {}: {}", token.to_hex_string(), e)))?
+        };
+
+        holdings.push(TreasuryHolding { token, balance, value_in_strk });
+    }
+
+    Ok(holdings)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use paymaster_common::validation::Validate;
+    use paymaster_starknet::constants::Token;
+    use starknet::core::types::Felt;
+    use starknet::macros::felt;
+
+    use crate::treasury::{TreasuryHolding, TreasuryPolicyConfiguration};
+
+    const USDC: Felt = felt!("0x1");
+    const ETH: Felt = felt!("0x2");
+
+    fn policy(weights: &[(Felt, f64)]) -> TreasuryPolicyConfiguration {
+        TreasuryPolicyConfiguration {
+            weights: weights.iter().cloned().collect(),
+        }
+    }
+
+    fn holding(token: Felt, balance: u64, value_in_strk: u64) -> TreasuryHolding {
+        TreasuryHolding {
+            token,
+            balance: Felt::from(balance),
+            value_in_strk: Felt::from(value_in_strk),
+        }
+    }
+
+    #[test]
+    fn tokens_above_their_weight_are_reduced_to_it() {
+        let policy = policy(&[(Token::STRK_ADDRESS, 0.8), (USDC, 0.2)]);
+
+        // USDC worth 2 STRK per unit holds 400 out of 1000 STRK, 200 STRK worth of it must be swapped
+        let holdings = [holding(Token::STRK_ADDRESS, 600, 600), holding(USDC, 200, 400), holding(ETH, 10, 0)];
+
+        assert_eq!(policy.excess_amounts(Token::STRK_ADDRESS, &holdings), HashMap::from([(USDC, Felt::from(100))]));
+    }
+
+    #[test]
+    fn tokens_below_their_weight_are_kept() {
+        let policy = policy(&[(Token::STRK_ADDRESS, 0.8), (USDC, 0.2)]);
+        let holdings = [holding(Token::STRK_ADDRESS, 900, 900), holding(USDC, 50, 100)];
+
+        assert!(policy.excess_amounts(Token::STRK_ADDRESS, &holdings).is_empty());
+    }
+
+    #[test]
+    fn tokens_without_weight_are_swapped_entirely() {
+        let policy = policy(&[(Token::STRK_ADDRESS, 1.0)]);
+        let holdings = [holding(Token::STRK_ADDRESS, 900, 900), holding(ETH, 50, 100)];
+
+        assert_eq!(policy.excess_amounts(Token::STRK_ADDRESS, &holdings), HashMap::from([(ETH, Felt::from(50))]));
+    }
+
+    #[test]
+    fn invalid_weights_are_reported() {
+        let errors = policy(&[(USDC, 0.5)]).validate_all().unwrap_err();
+        assert!(errors.contains("weights"));
+    }
+}
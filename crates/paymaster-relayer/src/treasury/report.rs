@@ -0,0 +1,292 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use paymaster_common::service::{Error as ServiceError, Service};
+use paymaster_common::service_check;
+use paymaster_starknet::constants::Token;
+use paymaster_starknet::math::{denormalize_felt, normalize_felt};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+use tokio::sync::Mutex;
+use tokio::time;
+
+use crate::treasury::{fetch_holdings, TreasuryHolding};
+use crate::Context;
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Interval between two snapshots recorded by the [`TreasurySnapshotService`]
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Snapshots older than this are dropped from the history
+const SNAPSHOT_RETENTION: u64 = 31 * DAY;
+
+/// Value of the gas tank at a given time
+#[serde_as]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreasurySnapshot {
+    /// Unix timestamp in seconds
+    pub taken_at: u64,
+
+    #[serde_as(as = "UfeHex")]
+    pub value_in_strk: Felt,
+}
+
+/// Snapshots of the value of the gas tank kept in a local file, rewritten on each snapshot
+#[derive(Clone)]
+pub struct TreasuryHistory {
+    path: Arc<Mutex<PathBuf>>,
+}
+
+impl TreasuryHistory {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path: Arc::new(Mutex::new(path)),
+        }
+    }
+
+    /// Add a snapshot to the history, dropping the snapshots past the retention
+    pub async fn record(&self, snapshot: TreasurySnapshot) -> Result<(), ServiceError> {
+        let path = self.path.lock().await;
+
+        let mut snapshots = Self::read(&path).await?;
+        snapshots.retain(|x| x.taken_at + SNAPSHOT_RETENTION >= snapshot.taken_at);
+        snapshots.push(snapshot);
+
+        let content = serde_json::to_vec(&snapshots).map_err(|e| ServiceError::new(&e.to_string()))?;
+        let temporary = path.with_extension("tmp");
+        tokio::fs::write(&temporary, content)
+            .await
+            .map_err(|e| ServiceError::new(&e.to_string()))?;
+        tokio::fs::rename(&temporary, &*path)
+            .await
+            .map_err(|e| ServiceError::new(&e.to_string()))
+    }
+
+    pub async fn snapshots(&self) -> Result<Vec<TreasurySnapshot>, ServiceError> {
+        let path = self.path.lock().await;
+
+        Self::read(&path).await
+    }
+
+    async fn read(path: &PathBuf) -> Result<Vec<TreasurySnapshot>, ServiceError> {
+        match tokio::fs::read(path).await {
+            Ok(content) => serde_json::from_slice(&content).map_err(|e| ServiceError::new(&e.to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+            Err(e) => Err(ServiceError::new(&e.to_string())),
+        }
+    }
+}
+
+/// Holdings of the gas tank valued in STRK and in USD, along with the evolution of its value over the last days
+#[derive(Debug, Clone)]
+pub struct TreasuryReport {
+    /// Unix timestamp in seconds
+    pub taken_at: u64,
+
+    pub holdings: Vec<TreasuryHolding>,
+
+    /// Value of 1 STRK in USD, derived from the price of USDC. Not set when the price could not be fetched
+    pub strk_price_in_usd: Option<f64>,
+
+    /// Change of the value of the gas tank (in STRK) over the last 7 and 30 days, not set without a snapshot old enough
+    pub delta_7d: Option<f64>,
+    pub delta_30d: Option<f64>,
+}
+
+impl TreasuryReport {
+    pub fn new(taken_at: u64, holdings: Vec<TreasuryHolding>, strk_price_in_usd: Option<f64>, history: &[TreasurySnapshot]) -> Self {
+        let value = denormalize_felt(holdings.iter().fold(Felt::ZERO, |total, x| total + x.value_in_strk), 18);
+        let delta = |days: u64| {
+            history
+                .iter()
+                .filter(|x| x.taken_at + days * DAY <= taken_at)
+                .max_by_key(|x| x.taken_at)
+                .map(|x| value - denormalize_felt(x.value_in_strk, 18))
+        };
+
+        Self {
+            taken_at,
+            delta_7d: delta(7),
+            delta_30d: delta(30),
+            holdings,
+            strk_price_in_usd,
+        }
+    }
+
+    /// Value of the gas tank in STRK
+    pub fn value_in_strk(&self) -> Felt {
+        self.holdings.iter().fold(Felt::ZERO, |total, x| total + x.value_in_strk)
+    }
+
+    /// Converts a value in STRK to USD
+    pub fn to_usd(&self, value_in_strk: Felt) -> Option<f64> {
+        self.strk_price_in_usd.map(|price| denormalize_felt(value_in_strk, 18) * price)
+    }
+
+    /// STRK drained from the gas tank per day, averaged over the last 7 days or over the last 30 days otherwise.
+    /// Zero when the value of the gas tank grows.
+    pub fn daily_burn(&self) -> Option<f64> {
+        let burn = match (self.delta_7d, self.delta_30d) {
+            (Some(delta), _) => -delta / 7.0,
+            (None, Some(delta)) => -delta / 30.0,
+            (None, None) => return None,
+        };
+
+        Some(burn.max(0.0))
+    }
+
+    /// Number of days before the gas tank is empty at the current burn rate, not set when it does not decrease
+    pub fn runway_days(&self) -> Option<f64> {
+        let burn = self.daily_burn().filter(|x| *x > 0.0)?;
+
+        Some(denormalize_felt(self.value_in_strk(), 18) / burn)
+    }
+}
+
+/// Build the report of the gas tank, the deltas being computed from the history when it is configured
+pub async fn treasury_report(context: &Context) -> Result<TreasuryReport, ServiceError> {
+    let holdings = fetch_holdings(context, context.configuration.gas_tank.address, &context.configuration.supported_tokens).await?;
+
+    let usdc = Token {
+        address: context.starknet.tokens().usdc,
+        ..Token::usdc(context.starknet.chain_id())
+    };
+    let strk_price_in_usd = context
+        .price
+        .convert_strk_to_token(usdc.address, normalize_felt(1.0, 18), false)
+        .await
+        .ok()
+        .map(|x| denormalize_felt(x, usdc.decimals));
+
+    let history = match &context.configuration.relayers.treasury_history {
+        Some(path) => TreasuryHistory::new(path.clone()).snapshots().await?,
+        None => vec![],
+    };
+
+    Ok(TreasuryReport::new(now(), holdings, strk_price_in_usd, &history))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Record the value of the gas tank in the treasury history every hour
+pub struct TreasurySnapshotService {
+    context: Context,
+    history: TreasuryHistory,
+}
+
+#[async_trait]
+impl Service for TreasurySnapshotService {
+    type Context = Context;
+
+    const NAME: &'static str = "TreasurySnapshot";
+
+    async fn new(context: Context) -> Self {
+        let Some(path) = context.configuration.relayers.treasury_history.clone() else {
+            panic!("no treasury history configuration")
+        };
+
+        Self {
+            history: TreasuryHistory::new(path),
+            context,
+        }
+    }
+
+    async fn run(self) -> Result<(), ServiceError> {
+        let mut ticker = time::interval(SNAPSHOT_INTERVAL);
+        loop {
+            ticker.tick().await;
+            service_check!(self.record_snapshot().await => continue);
+        }
+    }
+}
+
+impl TreasurySnapshotService {
+    async fn record_snapshot(&self) -> Result<(), ServiceError> {
+        let holdings = fetch_holdings(
+            &self.context,
+            self.context.configuration.gas_tank.address,
+            &self.context.configuration.supported_tokens,
+        )
+        .await?;
+
+        self.history
+            .record(TreasurySnapshot {
+                taken_at: now(),
+                value_in_strk: holdings.iter().fold(Felt::ZERO, |total, x| total + x.value_in_strk),
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use paymaster_starknet::constants::Token;
+    use paymaster_starknet::math::normalize_felt;
+
+    use crate::treasury::report::{TreasuryHistory, TreasuryReport, TreasurySnapshot, DAY};
+    use crate::treasury::TreasuryHolding;
+
+    fn snapshot(days_ago: u64, value: f64) -> TreasurySnapshot {
+        TreasurySnapshot {
+            taken_at: 100 * DAY - days_ago * DAY,
+            value_in_strk: normalize_felt(value, 18),
+        }
+    }
+
+    fn report(value: f64, history: &[TreasurySnapshot]) -> TreasuryReport {
+        let holdings = vec![TreasuryHolding {
+            token: Token::STRK_ADDRESS,
+            balance: normalize_felt(value, 18),
+            value_in_strk: normalize_felt(value, 18),
+        }];
+
+        TreasuryReport::new(100 * DAY, holdings, Some(0.5), history)
+    }
+
+    #[test]
+    fn deltas_are_computed_from_the_closest_older_snapshot() {
+        let report = report(1000.0, &[snapshot(40, 5000.0), snapshot(31, 4000.0), snapshot(8, 1800.0), snapshot(2, 1100.0)]);
+
+        assert_eq!(report.delta_7d, Some(-800.0));
+        assert_eq!(report.delta_30d, Some(-3000.0));
+        assert_eq!(report.daily_burn(), Some(800.0 / 7.0));
+        assert_eq!(report.runway_days(), Some(1000.0 / (800.0 / 7.0)));
+        assert_eq!(report.to_usd(report.value_in_strk()), Some(500.0));
+    }
+
+    #[test]
+    fn burn_rate_requires_snapshots() {
+        let report = report(1000.0, &[snapshot(2, 1100.0)]);
+
+        assert_eq!(report.delta_7d, None);
+        assert_eq!(report.daily_burn(), None);
+        assert_eq!(report.runway_days(), None);
+    }
+
+    #[test]
+    fn growing_treasury_has_no_runway() {
+        let report = report(1000.0, &[snapshot(10, 500.0)]);
+
+        assert_eq!(report.daily_burn(), Some(0.0));
+        assert_eq!(report.runway_days(), None);
+    }
+
+    #[tokio::test]
+    async fn history_drops_expired_snapshots() {
+        let path = std::env::temp_dir().join(format!("treasury-history-{}.json", std::process::id()));
+        let history = TreasuryHistory::new(path.clone());
+
+        history.record(snapshot(40, 1.0)).await.unwrap();
+        history.record(snapshot(0, 2.0)).await.unwrap();
+        assert_eq!(history.snapshots().await.unwrap(), vec![snapshot(0, 2.0)]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use paymaster_common::service::{Error as ServiceError, Service};
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_common::{metric, service_check, service_warn};
+use paymaster_starknet::transaction::{Calls, TokenTransfer};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{Felt, TransactionStatus};
+use tokio::time;
+use tracing::info;
+
+use crate::Context;
+
+/// Cancellation of the relayer transactions which stay unconfirmed for too long
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchdogConfiguration {
+    /// Time after which a transaction which is still not included is cancelled (in seconds)
+    #[serde(default = "WatchdogConfiguration::default_timeout")]
+    pub timeout: u64,
+
+    /// How often to check the transactions of the relayers (in seconds)
+    #[serde(default = "WatchdogConfiguration::default_check_interval")]
+    pub check_interval: u64,
+
+    /// Increase of the tip of the replacement transaction over the tip of the transaction it cancels (in percent)
+    #[serde(default = "WatchdogConfiguration::default_tip_bump_percent")]
+    pub tip_bump_percent: u64,
+}
+
+impl WatchdogConfiguration {
+    pub fn default_timeout() -> u64 {
+        120
+    }
+
+    pub fn default_check_interval() -> u64 {
+        30
+    }
+
+    pub fn default_tip_bump_percent() -> u64 {
+        50
+    }
+
+    /// Returns the tip of the transaction replacing the one submitted with the given `tip`
+    pub fn replacement_tip(&self, tip: u64) -> u64 {
+        let bump = tip.saturating_mul(self.tip_bump_percent) / 100;
+        tip.saturating_add(bump.max(1))
+    }
+}
+
+impl Validate for WatchdogConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.timeout > 0, "timeout", "must be greater than 0");
+        report.ensure(self.check_interval > 0, "check_interval", "must be greater than 0");
+        report.ensure(self.tip_bump_percent > 0, "tip_bump_percent", "must be greater than 0");
+    }
+}
+
+/// Transaction submitted by a relayer which is not known to be included on chain yet
+#[derive(Debug, Clone, Copy)]
+pub struct PendingTransaction {
+    pub relayer: Felt,
+    pub nonce: Felt,
+    pub transaction_hash: Felt,
+    pub tip: u64,
+    pub submitted_at: Instant,
+}
+
+impl PendingTransaction {
+    pub fn new(relayer: Felt, nonce: Felt, transaction_hash: Felt, tip: u64) -> Self {
+        Self {
+            relayer,
+            nonce,
+            transaction_hash,
+            tip,
+            submitted_at: Instant::now(),
+        }
+    }
+}
+
+/// Transactions submitted by the relayers of this instance, indexed by relayer and nonce. Nothing is tracked
+/// when the watchdog is disabled.
+#[derive(Clone, Default)]
+pub struct PendingTransactions {
+    enabled: bool,
+    inner: Arc<Mutex<HashMap<(Felt, Felt), PendingTransaction>>>,
+}
+
+impl PendingTransactions {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, inner: Arc::default() }
+    }
+
+    /// Track a transaction which was just submitted, replacing the one previously submitted with the same nonce
+    pub fn track(&self, transaction: PendingTransaction) {
+        if !self.enabled {
+            return;
+        }
+
+        let mut transactions = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        transactions.insert((transaction.relayer, transaction.nonce), transaction);
+    }
+
+    pub fn forget(&self, relayer: Felt, nonce: Felt) {
+        let mut transactions = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        transactions.remove(&(relayer, nonce));
+    }
+
+    /// Returns the transactions submitted for longer than `timeout`, the oldest nonces first
+    pub fn lingering(&self, timeout: Duration) -> Vec<PendingTransaction> {
+        let transactions = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut result: Vec<_> = transactions
+            .values()
+            .filter(|x| x.submitted_at.elapsed() >= timeout)
+            .copied()
+            .collect();
+        result.sort_by_key(|x| (x.relayer, x.nonce));
+        result
+    }
+}
+
+/// Cancel the relayer transactions which linger unconfirmed past the timeout by submitting, at the same nonce,
+/// a transfer of zero STRK from the relayer to itself with a higher tip. This frees the nonce of the relayer
+/// instead of piling up transactions behind one which is never included.
+pub struct RelayerTransactionWatchdog {
+    context: Context,
+    configuration: WatchdogConfiguration,
+}
+
+#[async_trait]
+impl Service for RelayerTransactionWatchdog {
+    type Context = Context;
+
+    const NAME: &'static str = "RelayerTransactionWatchdog";
+
+    async fn new(context: Context) -> Self {
+        let Some(configuration) = context.configuration.relayers.watchdog.clone() else {
+            panic!("no watchdog configuration")
+        };
+
+        Self { context, configuration }
+    }
+
+    async fn run(self) -> Result<(), ServiceError> {
+        let mut ticker = time::interval(Duration::from_secs(self.configuration.check_interval));
+        loop {
+            ticker.tick().await;
+
+            let timeout = Duration::from_secs(self.configuration.timeout);
+            for transaction in self.context.relayers.pending_transactions().lingering(timeout) {
+                service_check!(self.inspect(&transaction).await => continue);
+            }
+        }
+    }
+}
+
+impl RelayerTransactionWatchdog {
+    async fn inspect(&self, transaction: &PendingTransaction) -> Result<(), ServiceError> {
+        let pending = self.context.relayers.pending_transactions();
+
+        // A nonce consumed on chain means the transaction, or one replacing it, was included
+        let chain_nonce = self
+            .context
+            .starknet
+            .fetch_nonce(transaction.relayer)
+            .await
+            .map_err(ServiceError::from)?;
+        if chain_nonce > transaction.nonce {
+            pending.forget(transaction.relayer, transaction.nonce);
+            return Ok(());
+        }
+
+        match self.context.starknet.get_transaction_status(transaction.transaction_hash).await {
+            Ok(TransactionStatus::Received | TransactionStatus::Candidate) => self.cancel(transaction).await,
+            Ok(_) => {
+                pending.forget(transaction.relayer, transaction.nonce);
+                Ok(())
+            },
+            Err(paymaster_starknet::Error::TransactionNotFound) => {
+                // The transaction was dropped so the nonce is free already, it must be fetched again from the chain
+                self.context.relayers.reset_pipeline(transaction.relayer);
+                pending.forget(transaction.relayer, transaction.nonce);
+                metric!(counter[relayer_watchdog_cancel] = 1, outcome = "dropped");
+                Ok(())
+            },
+            Err(e) => Err(ServiceError::from(e)),
+        }
+    }
+
+    async fn cancel(&self, transaction: &PendingTransaction) -> Result<(), ServiceError> {
+        let relayer_address = transaction.relayer.to_fixed_hex_string();
+
+        // The relayer is only used while it is locked, it is busy if it cannot be locked and will be checked again
+        let Ok(mut lock) = self.context.relayers_locks.lock_relayer_at(transaction.relayer).await else {
+            metric!(counter[relayer_watchdog_cancel] = 1, outcome = "busy");
+            return Ok(());
+        };
+
+        let result = async {
+            let relayer = self
+                .context
+                .relayers
+                .acquire_relayer(&transaction.relayer)
+                .map_err(|e| ServiceError::new(&e.to_string()))?;
+
+            let calls = Calls::new(vec![
+                TokenTransfer::new(self.context.starknet.tokens().strk, transaction.relayer, Felt::ZERO).to_call()
+            ]);
+            let tip = self.configuration.replacement_tip(transaction.tip);
+            let estimated_calls = calls.estimate(&relayer, Some(tip)).await.map_err(ServiceError::from)?;
+            let result = estimated_calls
+                .execute(&relayer, transaction.nonce)
+                .await
+                .map_err(ServiceError::from)?;
+
+            Ok::<_, ServiceError>((result.transaction_hash, Felt::from(estimated_calls.estimate().overall_fee)))
+        }
+        .await;
+
+        // The nonces following the cancelled transaction must be fetched again from the chain
+        lock.nonce = None;
+        self.context.relayers.reset_pipeline(transaction.relayer);
+        service_check!(self.context.relayers_locks.release_relayer(lock).await);
+
+        match result {
+            Ok((transaction_hash, fee)) => {
+                info!(
+                    "Cancelled transaction {} of relayer {} with nonce {} lingering for {}s, replaced by {}",
+                    transaction.transaction_hash.to_hex_string(),
+                    relayer_address,
+                    transaction.nonce.to_hex_string(),
+                    transaction.submitted_at.elapsed().as_secs(),
+                    transaction_hash.to_hex_string()
+                );
+                metric!(counter[relayer_watchdog_cancel] = 1, outcome = "cancelled");
+
+                self.context
+                    .relayers
+                    .pending_transactions()
+                    .forget(transaction.relayer, transaction.nonce);
+                service_check!(self.context.relayers_locks.record_spend(transaction.relayer, fee).await);
+                self.context
+                    .relayers
+                    .unsettled_spends()
+                    .track(transaction.relayer, transaction_hash, fee);
+                Ok(())
+            },
+            Err(e) => {
+                service_warn!(
+                    "could not cancel transaction of relayer {} with nonce {}: {}",
+                    relayer_address,
+                    transaction.nonce.to_hex_string(),
+                    e
+                );
+                metric!(counter[relayer_watchdog_cancel] = 1, outcome = "failed");
+                Ok(())
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use starknet::core::types::Felt;
+
+    use crate::watchdog::{PendingTransaction, PendingTransactions, WatchdogConfiguration};
+
+    #[test]
+    fn only_lingering_transactions_are_reported() {
+        let pending = PendingTransactions::new(true);
+
+        pending.track(PendingTransaction::new(Felt::ONE, Felt::from(4), Felt::from(101), 10));
+        pending.track(PendingTransaction::new(Felt::ONE, Felt::from(3), Felt::from(100), 10));
+        assert!(pending.lingering(Duration::from_secs(120)).is_empty());
+
+        let result = pending.lingering(Duration::ZERO);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].nonce, Felt::from(3));
+
+        pending.forget(Felt::ONE, Felt::from(3));
+        assert_eq!(pending.lingering(Duration::ZERO).len(), 1);
+    }
+
+    #[test]
+    fn nothing_is_tracked_when_disabled() {
+        let pending = PendingTransactions::new(false);
+        pending.track(PendingTransaction::new(Felt::ONE, Felt::ZERO, Felt::ONE, 0));
+
+        assert!(pending.lingering(Duration::ZERO).is_empty());
+    }
+
+    #[test]
+    fn replacement_tip_is_always_higher() {
+        let configuration = WatchdogConfiguration {
+            timeout: 120,
+            check_interval: 30,
+            tip_bump_percent: 50,
+        };
+
+        assert_eq!(configuration.replacement_tip(100), 150);
+        assert_eq!(configuration.replacement_tip(0), 1);
+        assert_eq!(configuration.replacement_tip(u64::MAX), u64::MAX);
+    }
+}
@@ -1,38 +1,418 @@
-use jsonrpsee::http_client::HttpClient;
+use std::future::Future;
+use std::time::Duration;
+
+use jsonrpsee::core::ClientError;
+use jsonrpsee::http_client::{HeaderMap, HeaderValue, HttpClient};
+use paymaster_starknet::ChainID;
+use starknet::core::types::Felt;
+use tracing::{info_span, warn, Instrument};
 
 use crate::endpoint::execute_raw::{ExecuteDirectRequest, ExecuteDirectResponse};
-use crate::{BuildTransactionRequest, BuildTransactionResponse, ExecuteRequest, ExecuteResponse, PaymasterAPIClient, TokenPrice};
+use crate::{
+    AccountStatusRequest, AccountStatusResponse, AccountingSnapshotRequest, AccountingSnapshotResponse, ApprovalRequest, BuildTransactionRequest,
+    BuildTransactionResponse, CanSponsorRequest, CanSponsorResponse, DeadLetterRequest, DeadLettersRequest, DeadLettersResponse, EstimateMessageFeeRequest,
+    ExecuteRequest, ExecuteResponse, ExecutionReceiptRequest, ExecutionReceiptResponse, FleetStatusRequest, FleetStatusResponse, MessageFeeEstimate, PaymasterAPIClient,
+    PendingApprovalsRequest, PendingApprovalsResponse, RefundsRequest, RefundsResponse, SetLogFilterRequest, SetLowRpcModeRequest, SetMaintenanceRequest,
+    SimulatePricingRequest, SimulatePricingResponse, SponsorMessageRequest, SponsorMessageResponse, SponsorUsageRequest, SponsorUsageResponse, TokenPrice,
+    TreasuryReportRequest, TreasuryReportResponse,
+};
 
 pub type Error = jsonrpsee::core::ClientError;
 
+/// Header carrying the API key of the integrator
+pub const API_KEY_HEADER: &str = "x-paymaster-api-key";
+
+/// Policy used to retry the requests that failed with a transient error. Requests which
+/// are safe to replay are retried on transport errors and timeouts while requests which
+/// submit a transaction are only retried when the paymaster reports that it is busy, in
+/// which case the request was not processed.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy which never retries
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the delay before the given retry, doubling at each attempt up to the maximum backoff
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Idempotency {
+    /// The request can be replayed safely
+    Safe,
+    /// The request must only be replayed when it is known not to have been processed
+    Unsafe,
+}
+
+/// Returns the delay requested by the paymaster if the error reports that it is busy
+fn busy_retry_after(error: &ClientError) -> Option<Duration> {
+    let ClientError::Call(error) = error else { return None };
+    if error.code() != 163 {
+        return None;
+    }
+
+    let data: serde_json::Value = serde_json::from_str(error.data()?.get()).ok()?;
+    data.get("retry_after")?.as_u64().map(Duration::from_secs)
+}
+
+fn is_transient(error: &ClientError) -> bool {
+    matches!(error, ClientError::Transport(_) | ClientError::RequestTimeout)
+}
+
+pub struct ClientBuilder {
+    endpoint: String,
+
+    chain_id: Option<ChainID>,
+    api_key: Option<String>,
+    timeout: Option<Duration>,
+    retry_policy: RetryPolicy,
+    response_public_key: Option<Felt>,
+}
+
+impl ClientBuilder {
+    /// Target the given chain on a paymaster serving multiple chains. Requests that
+    /// already specify a chain id are left untouched.
+    pub fn with_chain_id(mut self, chain_id: ChainID) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Authenticate the requests with the given API key
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Fail the requests which do not complete within the given duration
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Refuse the build responses which are not signed by the paymaster with the given public key
+    pub fn with_response_verification(mut self, public_key: Felt) -> Self {
+        self.response_public_key = Some(public_key);
+        self
+    }
+
+    pub fn build(self) -> Result<Client, Error> {
+        let mut builder = HttpClient::builder();
+        if let Some(api_key) = &self.api_key {
+            let value = HeaderValue::from_str(api_key).map_err(|e| ClientError::Custom(format!("invalid api key: {e}")))?;
+
+            let mut headers = HeaderMap::new();
+            headers.insert(API_KEY_HEADER, value);
+            builder = builder.set_headers(headers);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.request_timeout(timeout);
+        }
+
+        Ok(Client {
+            inner: builder.build(&self.endpoint)?,
+            chain_id: self.chain_id,
+            retry_policy: self.retry_policy,
+            response_public_key: self.response_public_key,
+        })
+    }
+}
+
 pub struct Client {
     inner: HttpClient,
+
+    chain_id: Option<ChainID>,
+    retry_policy: RetryPolicy,
+    response_public_key: Option<Felt>,
 }
 
 impl Client {
     pub fn new(endpoint: &str) -> Self {
-        Self {
-            inner: HttpClient::builder().build(endpoint).expect("invalid endpoint"),
+        Self::builder(endpoint).build().expect("invalid endpoint")
+    }
+
+    pub fn builder(endpoint: &str) -> ClientBuilder {
+        ClientBuilder {
+            endpoint: endpoint.to_string(),
+            chain_id: None,
+            api_key: None,
+            timeout: None,
+            retry_policy: RetryPolicy::default(),
+            response_public_key: None,
+        }
+    }
+
+    /// Target the given chain on a paymaster serving multiple chains. Requests that
+    /// already specify a chain id are left untouched.
+    pub fn with_chain_id(mut self, chain_id: ChainID) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Send the request, retrying it according to the retry policy. Each attempt is traced in its own span.
+    async fn call<T, F, Fut>(&self, method: &'static str, idempotency: Idempotency, request: F) -> Result<T, Error>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut retry = 0;
+        loop {
+            let span = info_span!("paymaster_request", method, attempt = retry + 1);
+            let error = match request().instrument(span).await {
+                Ok(result) => return Ok(result),
+                Err(e) => e,
+            };
+
+            let delay = match busy_retry_after(&error) {
+                Some(retry_after) => retry_after.max(self.retry_policy.backoff(retry)),
+                None if idempotency == Idempotency::Safe && is_transient(&error) => self.retry_policy.backoff(retry),
+                None => return Err(error),
+            };
+            if retry >= self.retry_policy.max_retries {
+                return Err(error);
+            }
+
+            warn!(method, attempt = retry + 1, delay = ?delay, "paymaster request failed, retrying: {error}");
+            tokio::time::sleep(delay).await;
+            retry += 1;
         }
     }
 
     pub async fn is_available(&self) -> Result<bool, Error> {
-        self.inner.is_available().await
+        self.call("paymaster_isAvailable", Idempotency::Safe, || self.inner.is_available(self.chain_id))
+            .await
+    }
+
+    pub async fn build_transaction(&self, mut params: BuildTransactionRequest) -> Result<BuildTransactionResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        let response = self
+            .call("paymaster_buildTransaction", Idempotency::Safe, || self.inner.build_transaction(params.clone()))
+            .await?;
+
+        match self.response_public_key {
+            Some(public_key) if !response.verify_signature(public_key, params.transaction.user_address()) => {
+                Err(ClientError::Custom("build response is not signed by the paymaster".to_string()))
+            },
+            _ => Ok(response),
+        }
+    }
+
+    pub async fn execute_transaction(&self, mut params: ExecuteRequest) -> Result<ExecuteResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_executeTransaction", Idempotency::Unsafe, || self.inner.execute_transaction(params.clone()))
+            .await
+    }
+
+    pub async fn execute_direct_transaction(&self, mut params: ExecuteDirectRequest) -> Result<ExecuteDirectResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_executeDirectTransaction", Idempotency::Unsafe, || {
+            self.inner.execute_direct_transaction(params.clone())
+        })
+        .await
+    }
+
+    pub async fn get_execution_receipt(&self, mut params: ExecutionReceiptRequest) -> Result<ExecutionReceiptResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_getExecutionReceipt", Idempotency::Safe, || {
+            self.inner.get_execution_receipt(params.clone())
+        })
+        .await
+    }
+
+    pub async fn estimate_message_fee(&self, mut params: EstimateMessageFeeRequest) -> Result<MessageFeeEstimate, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_estimateMessageFee", Idempotency::Safe, || self.inner.estimate_message_fee(params.clone()))
+            .await
+    }
+
+    pub async fn sponsor_message(&self, mut params: SponsorMessageRequest) -> Result<SponsorMessageResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_sponsorMessage", Idempotency::Unsafe, || self.inner.sponsor_message(params.clone()))
+            .await
+    }
+
+    pub async fn get_refunds(&self, mut params: RefundsRequest) -> Result<RefundsResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_getRefunds", Idempotency::Safe, || self.inner.get_refunds(params.clone()))
+            .await
+    }
+
+    pub async fn simulate_pricing(&self, mut params: SimulatePricingRequest) -> Result<SimulatePricingResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_simulatePricing", Idempotency::Safe, || self.inner.simulate_pricing(params.clone()))
+            .await
     }
 
-    pub async fn build_transaction(&self, params: BuildTransactionRequest) -> Result<BuildTransactionResponse, Error> {
-        self.inner.build_transaction(params).await
+    pub async fn set_maintenance(&self, mut params: SetMaintenanceRequest) -> Result<bool, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_setMaintenance", Idempotency::Unsafe, || self.inner.set_maintenance(params.clone()))
+            .await
     }
 
-    pub async fn execute_transaction(&self, params: ExecuteRequest) -> Result<ExecuteResponse, Error> {
-        self.inner.execute_transaction(params).await
+    pub async fn set_log_filter(&self, mut params: SetLogFilterRequest) -> Result<String, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_setLogFilter", Idempotency::Unsafe, || self.inner.set_log_filter(params.clone()))
+            .await
     }
 
-    pub async fn execute_direct_transaction(&self, params: ExecuteDirectRequest) -> Result<ExecuteDirectResponse, Error> {
-        self.inner.execute_direct_transaction(params).await
+    pub async fn set_low_rpc_mode(&self, mut params: SetLowRpcModeRequest) -> Result<bool, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_setLowRpcMode", Idempotency::Unsafe, || self.inner.set_low_rpc_mode(params.clone()))
+            .await
     }
 
     pub async fn get_supported_tokens(&self) -> Result<Vec<TokenPrice>, Error> {
-        self.inner.get_supported_tokens().await
+        self.call("paymaster_getSupportedTokens", Idempotency::Safe, || self.inner.get_supported_tokens(self.chain_id))
+            .await
+    }
+
+    pub async fn get_sponsor_usage(&self, mut params: SponsorUsageRequest) -> Result<SponsorUsageResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_getSponsorUsage", Idempotency::Safe, || self.inner.get_sponsor_usage(params.clone()))
+            .await
+    }
+
+    pub async fn can_sponsor(&self, mut params: CanSponsorRequest) -> Result<CanSponsorResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_canSponsor", Idempotency::Safe, || self.inner.can_sponsor(params.clone()))
+            .await
+    }
+
+    pub async fn get_account_status(&self, mut params: AccountStatusRequest) -> Result<AccountStatusResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_getAccountStatus", Idempotency::Safe, || self.inner.get_account_status(params.clone()))
+            .await
+    }
+
+    pub async fn get_fleet_status(&self, mut params: FleetStatusRequest) -> Result<FleetStatusResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_getFleetStatus", Idempotency::Safe, || self.inner.get_fleet_status(params.clone()))
+            .await
+    }
+
+    pub async fn get_accounting_snapshot(&self, mut params: AccountingSnapshotRequest) -> Result<AccountingSnapshotResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_getAccountingSnapshot", Idempotency::Safe, || {
+            self.inner.get_accounting_snapshot(params.clone())
+        })
+        .await
+    }
+
+    pub async fn get_treasury_report(&self, mut params: TreasuryReportRequest) -> Result<TreasuryReportResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_getTreasuryReport", Idempotency::Safe, || self.inner.get_treasury_report(params.clone()))
+            .await
+    }
+
+    pub async fn get_dead_letters(&self, mut params: DeadLettersRequest) -> Result<DeadLettersResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_getDeadLetters", Idempotency::Safe, || self.inner.get_dead_letters(params.clone()))
+            .await
+    }
+
+    pub async fn retry_dead_letter(&self, mut params: DeadLetterRequest) -> Result<ExecuteResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_retryDeadLetter", Idempotency::Unsafe, || self.inner.retry_dead_letter(params.clone()))
+            .await
+    }
+
+    pub async fn discard_dead_letter(&self, mut params: DeadLetterRequest) -> Result<bool, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_discardDeadLetter", Idempotency::Unsafe, || self.inner.discard_dead_letter(params.clone()))
+            .await
+    }
+
+    pub async fn get_pending_approvals(&self, mut params: PendingApprovalsRequest) -> Result<PendingApprovalsResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_getPendingApprovals", Idempotency::Safe, || {
+            self.inner.get_pending_approvals(params.clone())
+        })
+        .await
+    }
+
+    pub async fn approve_execution(&self, mut params: ApprovalRequest) -> Result<ExecuteResponse, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_approveExecution", Idempotency::Unsafe, || self.inner.approve_execution(params.clone()))
+            .await
+    }
+
+    pub async fn reject_execution(&self, mut params: ApprovalRequest) -> Result<bool, Error> {
+        params.chain_id = params.chain_id.or(self.chain_id);
+        self.call("paymaster_rejectExecution", Idempotency::Unsafe, || self.inner.reject_execution(params.clone()))
+            .await
+    }
+
+    /// Returns the OpenRPC specification of the API served by the paymaster
+    pub async fn discover(&self) -> Result<serde_json::Value, Error> {
+        self.call("paymaster_discover", Idempotency::Safe, || self.inner.discover())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use jsonrpsee::core::ClientError;
+    use jsonrpsee::types::ErrorObject;
+
+    use crate::client::{busy_retry_after, Client, RetryPolicy};
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff(3), Duration::from_millis(500));
+        assert_eq!(policy.backoff(40), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn busy_errors_carry_their_retry_delay() {
+        let busy = ClientError::Call(ErrorObject::owned(163, "busy", Some(serde_json::json!({ "message": "busy", "retry_after": 3 }))));
+        assert_eq!(busy_retry_after(&busy), Some(Duration::from_secs(3)));
+
+        let other = ClientError::Call(ErrorObject::owned(163, "error", Some("service not available")));
+        assert_eq!(busy_retry_after(&other), None);
+    }
+
+    #[test]
+    fn invalid_api_key_is_rejected() {
+        let result = Client::builder("http://localhost:12777").with_api_key("invalid\nkey").build();
+
+        assert!(result.is_err());
     }
 }
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use deadpool_redis::redis::cmd;
+use deadpool_redis::{Config, Pool, Runtime};
+use paymaster_common::metric;
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_execution::Error as ExecutionError;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::{Call, Felt};
+use starknet::core::utils::starknet_keccak;
+
+use crate::context::call_policy::find_transfer_over;
+use crate::{ExecuteDirectRequest, ExecuteRequest};
+
+/// Redis stream in which the sponsored executions above a threshold are parked until an operator approves them
+/// through the admin api. Puts a human in the loop of the sponsorships funded by the treasury.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApprovalQueueConfiguration {
+    pub endpoint: String,
+    pub stream: String,
+
+    /// Approximate number of entries kept in the stream, the oldest ones are dropped beyond it
+    #[serde(default = "ApprovalQueueConfiguration::default_max_length")]
+    pub max_length: usize,
+
+    /// Fee in STRK (FRI) from which a sponsored execution requires an approval
+    #[serde_as(as = "Option<UfeHex>")]
+    #[serde(default)]
+    pub fee_threshold: Option<Felt>,
+
+    /// Amount of a token, in its smallest unit, from which a sponsored execution transferring or approving it requires
+    /// an approval
+    #[serde_as(as = "HashMap<UfeHex, UfeHex>")]
+    #[serde(default)]
+    pub transfer_thresholds: HashMap<Felt, Felt>,
+}
+
+impl ApprovalQueueConfiguration {
+    fn default_max_length() -> usize {
+        1_000
+    }
+
+    /// Returns why the sponsored execution requires an approval, if it does. The calls are the ones executed, the
+    /// batched calls included, `None` when they cannot be decoded.
+    pub fn requires_approval(&self, fee_in_strk: Felt, calls: Option<&[Call]>) -> Option<String> {
+        if self.fee_threshold.is_some_and(|x| fee_in_strk >= x) {
+            return Some(format!("fee of {} FRI", fee_in_strk));
+        }
+
+        if self.transfer_thresholds.is_empty() {
+            return None;
+        }
+
+        match calls {
+            Some(calls) => find_transfer_over(&self.transfer_thresholds, calls).map(|x| format!("transfer of token {}", x.to_hex_string())),
+            None => Some("undecoded calls".to_string()),
+        }
+    }
+}
+
+impl Validate for ApprovalQueueConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure_url("endpoint", &self.endpoint);
+        report.ensure(!self.stream.is_empty(), "stream", "must not be empty");
+        report.ensure(self.max_length > 0, "max_length", "must be greater than 0");
+        report.ensure(
+            self.fee_threshold.is_some() || !self.transfer_thresholds.is_empty(),
+            "fee_threshold",
+            "at least one threshold must be configured",
+        );
+    }
+}
+
+/// Request parked in the approval queue, executed again through the endpoint which received it once approved
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PendingRequest {
+    /// Request of `executeTransaction`, holding the typed data even when the user only sent its hash
+    Execute(ExecuteRequest),
+
+    /// Request of `executeDirectTransaction`
+    ExecuteDirect(ExecuteDirectRequest),
+}
+
+impl PendingRequest {
+    pub fn user_address(&self) -> Felt {
+        match self {
+            Self::Execute(request) => request.transaction.user_address(),
+            Self::ExecuteDirect(request) => request.transaction.user_address(),
+        }
+    }
+}
+
+/// Sponsored execution waiting for the approval of an operator, along with what is needed to execute it
+#[serde_as]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PendingExecution {
+    pub request: PendingRequest,
+
+    /// Fingerprint of the api key of the sponsor (keccak of the key). The key itself is never stored, the operator
+    /// approving the execution provides it again to execute the request on behalf of the sponsor.
+    #[serde(default)]
+    pub sponsor: Option<String>,
+
+    #[serde_as(as = "UfeHex")]
+    pub fee_in_strk: Felt,
+
+    /// Threshold exceeded by the execution
+    pub reason: String,
+
+    /// Unix timestamp in seconds
+    pub parked_at: u64,
+}
+
+impl PendingExecution {
+    pub fn new(request: PendingRequest, api_key: Option<&str>, fee_in_strk: Felt, reason: String) -> Self {
+        Self {
+            request,
+            sponsor: api_key.map(Self::fingerprint),
+            fee_in_strk,
+            reason,
+            parked_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        }
+    }
+
+    /// Returns the fingerprint identifying the given api key
+    pub fn fingerprint(api_key: &str) -> String {
+        starknet_keccak(api_key.as_bytes()).to_fixed_hex_string()
+    }
+
+    /// Check the api key provided by the operator is the one of the sponsor who made the request, returns the key
+    /// to execute the request with
+    pub fn authenticate_sponsor(&self, api_key: Option<String>) -> Result<Option<String>, String> {
+        match (&self.sponsor, api_key) {
+            (None, _) => Ok(None),
+            (Some(sponsor), Some(api_key)) if *sponsor == Self::fingerprint(&api_key) => Ok(Some(api_key)),
+            (Some(_), Some(_)) => Err("api key does not belong to the sponsor of the execution".to_string()),
+            (Some(_), None) => Err("api key of the sponsor is required".to_string()),
+        }
+    }
+}
+
+/// Queue of the sponsored executions waiting for an approval. No execution requires an approval when the queue is
+/// not configured.
+#[derive(Clone)]
+pub struct ApprovalQueue {
+    redis: Option<(Pool, ApprovalQueueConfiguration)>,
+}
+
+impl ApprovalQueue {
+    pub fn new(configuration: Option<&ApprovalQueueConfiguration>) -> Result<Self, ExecutionError> {
+        let redis = configuration
+            .map(|x| {
+                Config::from_url(&x.endpoint)
+                    .create_pool(Some(Runtime::Tokio1))
+                    .map(|pool| (pool, x.clone()))
+                    .map_err(|e| ExecutionError::Internal(e.to_string()))
+            })
+            .transpose()?;
+
+        Ok(Self { redis })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.redis.is_some()
+    }
+
+    /// Returns why the sponsored execution requires an approval, if it does
+    pub fn requires_approval(&self, fee_in_strk: Felt, calls: Option<&[Call]>) -> Option<String> {
+        let (_, configuration) = self.redis.as_ref()?;
+
+        configuration.requires_approval(fee_in_strk, calls)
+    }
+
+    /// Park the execution until it is approved, returns the id of its entry
+    pub async fn push(&self, execution: &PendingExecution) -> Result<String, String> {
+        let Some((pool, configuration)) = &self.redis else {
+            return Err("approval queue is not configured".to_string());
+        };
+
+        let payload = serde_json::to_string(execution).map_err(|e| e.to_string())?;
+        let mut connection = pool.get().await.map_err(|e| e.to_string())?;
+
+        let id: String = cmd("XADD")
+            .arg(&configuration.stream)
+            .arg("MAXLEN")
+            .arg("~")
+            .arg(configuration.max_length)
+            .arg("*")
+            .arg("entry")
+            .arg(payload)
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        metric!(counter[execution_parked] = 1);
+        Ok(id)
+    }
+
+    /// Returns the `count` most recent entries of the queue along with their id
+    pub async fn list(&self, count: usize) -> Result<Vec<(String, PendingExecution)>, String> {
+        self.range("+", "-", count).await
+    }
+
+    /// Returns the entry with the given id if it is still in the queue
+    pub async fn get(&self, id: &str) -> Result<Option<PendingExecution>, String> {
+        Ok(self.range(id, id, 1).await?.into_iter().next().map(|(_, x)| x))
+    }
+
+    /// Remove the entry with the given id, returns false if it was not in the queue
+    pub async fn remove(&self, id: &str) -> Result<bool, String> {
+        let Some((pool, configuration)) = &self.redis else { return Ok(false) };
+
+        let mut connection = pool.get().await.map_err(|e| e.to_string())?;
+        let removed: usize = cmd("XDEL")
+            .arg(&configuration.stream)
+            .arg(id)
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(removed > 0)
+    }
+
+    // Read the entries from `end` down to `start`, entries which cannot be decoded are skipped
+    async fn range(&self, end: &str, start: &str, count: usize) -> Result<Vec<(String, PendingExecution)>, String> {
+        let Some((pool, configuration)) = &self.redis else { return Ok(vec![]) };
+
+        let mut connection = pool.get().await.map_err(|e| e.to_string())?;
+        let entries: Vec<(String, Vec<String>)> = cmd("XREVRANGE")
+            .arg(&configuration.stream)
+            .arg(end)
+            .arg(start)
+            .arg("COUNT")
+            .arg(count)
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(entries
+            .into_iter()
+            .filter_map(|(id, fields)| {
+                let payload = fields.chunks(2).find(|x| x[0] == "entry")?.get(1)?;
+                serde_json::from_str(payload).ok().map(|x| (id, x))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use paymaster_common::validation::Validate;
+    use starknet::core::types::{Call, Felt};
+    use starknet::macros::selector;
+
+    use crate::context::approval::{ApprovalQueue, ApprovalQueueConfiguration, PendingExecution, PendingRequest};
+    use crate::endpoint::common::{ExecutionParameters, FeeMode, TipPriority};
+    use crate::{DirectInvokeParameters, ExecuteDirectRequest, ExecuteDirectTransactionParameters};
+
+    fn configuration() -> ApprovalQueueConfiguration {
+        ApprovalQueueConfiguration {
+            endpoint: "redis://localhost:6379".to_string(),
+            stream: "approvals".to_string(),
+            max_length: 1_000,
+            fee_threshold: Some(Felt::from(1_000)),
+            transfer_thresholds: HashMap::from([(Felt::TWO, Felt::from(100))]),
+        }
+    }
+
+    #[test]
+    fn executions_above_a_threshold_require_an_approval() {
+        let configuration = configuration();
+        let transfer = |amount: u64| Call {
+            to: Felt::TWO,
+            selector: selector!("transfer"),
+            calldata: vec![Felt::ONE, Felt::from(amount), Felt::ZERO],
+        };
+
+        assert!(configuration
+            .requires_approval(Felt::from(999), Some([transfer(100)].as_slice()))
+            .is_none());
+        assert!(configuration
+            .requires_approval(Felt::from(1_000), Some([].as_slice()))
+            .is_some());
+        assert!(configuration
+            .requires_approval(Felt::ONE, Some([transfer(101)].as_slice()))
+            .is_some());
+        assert!(configuration.requires_approval(Felt::ONE, None).is_some());
+    }
+
+    #[tokio::test]
+    async fn disabled_queue_requires_no_approval() {
+        let queue = ApprovalQueue::new(None).unwrap();
+        assert!(!queue.is_enabled());
+
+        assert!(queue.requires_approval(Felt::MAX, None).is_none());
+        assert!(queue.list(10).await.unwrap().is_empty());
+        assert!(!queue.remove("0-1").await.unwrap());
+    }
+
+    #[test]
+    fn only_the_fingerprint_of_the_api_key_is_stored() {
+        let request = PendingRequest::ExecuteDirect(ExecuteDirectRequest {
+            transaction: ExecuteDirectTransactionParameters::Invoke {
+                invoke: DirectInvokeParameters {
+                    user_address: Felt::ONE,
+                    execute_from_outside_call: Call {
+                        to: Felt::ONE,
+                        selector: selector!("execute_from_outside_v2"),
+                        calldata: vec![],
+                    },
+                },
+            },
+            parameters: ExecutionParameters::V1 {
+                fee_mode: FeeMode::Sponsored { tip: TipPriority::Normal },
+                time_bounds: None,
+            },
+            chain_id: None,
+            client_nonce: None,
+        });
+        let execution = PendingExecution::new(request, Some("paymaster_sponsor"), Felt::ONE, "fee".to_string());
+
+        let payload = serde_json::to_string(&execution).unwrap();
+        assert!(!payload.contains("paymaster_sponsor"));
+
+        assert!(execution.authenticate_sponsor(None).is_err());
+        assert!(execution.authenticate_sponsor(Some("paymaster_other".to_string())).is_err());
+        assert_eq!(
+            execution
+                .authenticate_sponsor(Some("paymaster_sponsor".to_string()))
+                .unwrap()
+                .as_deref(),
+            Some("paymaster_sponsor")
+        );
+    }
+
+    #[test]
+    fn configuration_requires_a_threshold() {
+        let configuration = ApprovalQueueConfiguration {
+            fee_threshold: None,
+            transfer_thresholds: HashMap::new(),
+            ..configuration()
+        };
+
+        assert!(configuration.validate_all().is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::Duration;
+
+use paymaster_common::cache::ExpirableCache;
+use paymaster_common::metric;
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_execution::Client as ExecutionClient;
+use serde::{Deserialize, Serialize};
+
+use crate::{BuildTransactionRequest, BuildTransactionResponse};
+
+/// Cache of the responses of `paymaster_buildTransaction`, so that the same unsigned request made again within a
+/// few seconds (UI re-renders, double fetches) does not trigger another estimation
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BuildCacheConfiguration {
+    /// Duration during which a response is served again (in seconds)
+    #[serde(default = "BuildCacheConfiguration::default_ttl")]
+    pub ttl: u64,
+
+    /// Factor by which the gas price must exceed its recent average for the cache to be bypassed
+    #[serde(default = "BuildCacheConfiguration::default_surge_factor")]
+    pub surge_factor: f64,
+}
+
+impl BuildCacheConfiguration {
+    fn default_ttl() -> u64 {
+        5
+    }
+
+    fn default_surge_factor() -> f64 {
+        1.5
+    }
+}
+
+impl Validate for BuildCacheConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.ttl > 0 && self.ttl <= 60, "ttl", "must be between 1 and 60 seconds");
+        report.ensure(self.surge_factor >= 1.0, "surge_factor", "must be greater than or equal to 1");
+    }
+}
+
+/// Responses built recently indexed by request. Nothing is cached when the cache is not configured.
+#[derive(Clone)]
+pub struct BuildCache {
+    configuration: Option<BuildCacheConfiguration>,
+    responses: ExpirableCache<u64, BuildTransactionResponse>,
+}
+
+impl BuildCache {
+    pub fn new(configuration: Option<&BuildCacheConfiguration>) -> Self {
+        Self {
+            configuration: configuration.cloned(),
+            responses: ExpirableCache::new(10_000),
+        }
+    }
+
+    /// Returns the key of the request. Requests made with the same api key, for the same user, calls and fee mode share it.
+    pub fn key(api_key: Option<&str>, request: &BuildTransactionRequest) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        api_key.hash(&mut hasher);
+        serde_json::to_string(&request.transaction)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        serde_json::to_string(&request.parameters).unwrap_or_default().hash(&mut hasher);
+
+        hasher.finish()
+    }
+
+    /// Returns the response built recently for the request, if any. The cache is bypassed while the gas price surges
+    /// since the responses built before would underestimate the fee.
+    pub fn get(&self, execution: &ExecutionClient, key: u64) -> Option<BuildTransactionResponse> {
+        let configuration = self.configuration.as_ref()?;
+        if execution.starknet.is_gas_price_surging(configuration.surge_factor) {
+            metric!(counter[build_cache] = 1, result = "bypass");
+            return None;
+        }
+
+        let response = self.responses.get_if_not_stale(&key);
+        let result = if response.is_some() { "hit" } else { "miss" };
+        metric!(counter[build_cache] = 1, result = result);
+
+        response
+    }
+
+    pub fn insert(&self, key: u64, response: &BuildTransactionResponse) {
+        if let Some(configuration) = &self.configuration {
+            self.responses
+                .insert(key, response.clone(), Duration::from_secs(configuration.ttl));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use paymaster_common::validation::Validate;
+
+    use crate::context::build_cache::BuildCacheConfiguration;
+
+    #[test]
+    fn configuration_is_defaulted_and_validated() {
+        let configuration: BuildCacheConfiguration = serde_json::from_str("{}").unwrap();
+        assert_eq!(configuration.ttl, 5);
+        assert_eq!(configuration.surge_factor, 1.5);
+        assert!(configuration.validate_all().is_ok());
+
+        let configuration: BuildCacheConfiguration = serde_json::from_str(r#"{ "ttl": 120 }"#).unwrap();
+        assert!(configuration.validate_all().is_err());
+    }
+}
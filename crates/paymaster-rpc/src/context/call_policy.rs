@@ -0,0 +1,389 @@
+use std::collections::{HashMap, HashSet};
+
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::{Call, Felt};
+use starknet::macros::selector;
+
+/// Entrypoints of the ERC-20 moving or approving tokens, with the position of the u256 amount in their calldata
+const TRANSFER_ENTRYPOINTS: [(Felt, usize); 6] = [
+    (selector!("transfer"), 1),
+    (selector!("transfer_from"), 2),
+    (selector!("transferFrom"), 2),
+    (selector!("approve"), 1),
+    (selector!("increase_allowance"), 1),
+    (selector!("increaseAllowance"), 1),
+];
+
+/// Policies applied to the calls of the users. The calls to the batching entrypoints are decoded so that the
+/// policies also apply to the calls they batch, which would otherwise hide any call behind a multicall.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CallPolicyConfiguration {
+    /// Contracts which cannot be called, whether directly or through a batching entrypoint
+    #[serde(default)]
+    pub blacklist: HashSet<Felt>,
+
+    /// Entrypoints whose calldata is a list of calls, e.g. the `__execute__` of the accounts or the `multicall` of
+    /// the batching contracts. Their calldata must be the serialization of an `Array<Call>`.
+    #[serde(default)]
+    pub batching_entrypoints: Vec<BatchingEntrypoint>,
+
+    /// Maximum number of batching entrypoints nested in one another, the deeper calls are rejected
+    #[serde(default = "CallPolicyConfiguration::default_max_depth")]
+    pub max_depth: usize,
+
+    /// Maximum amount of a token, in its smallest unit, that the calls of a sponsored transaction can transfer or
+    /// approve in total. The tokens not listed are not limited.
+    #[serde_as(as = "HashMap<UfeHex, UfeHex>")]
+    #[serde(default)]
+    pub transfer_limits: HashMap<Felt, Felt>,
+
+    /// Detection of the unlimited approvals to untrusted spenders in the sponsored transactions, disabled when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approvals: Option<ApprovalPolicyConfiguration>,
+}
+
+/// Approvals granting an untrusted spender an unlimited allowance, or every token of a collection through
+/// `set_approval_for_all`, are the usual way of draining the wallets of the users. Sponsoring them is refused unless
+/// the api key is trusted with them.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApprovalPolicyConfiguration {
+    #[serde(default)]
+    pub action: ApprovalAction,
+
+    /// Spenders which can be granted any approval, e.g. the routers of the known exchanges
+    #[serde_as(as = "HashSet<UfeHex>")]
+    #[serde(default)]
+    pub trusted_spenders: HashSet<Felt>,
+
+    /// Amount from which an approval is considered unlimited. An u256 amount whose high part is not zero is always
+    /// considered unlimited.
+    #[serde_as(as = "UfeHex")]
+    #[serde(default = "ApprovalPolicyConfiguration::default_unlimited_amount")]
+    pub unlimited_amount: Felt,
+
+    /// Whether the `set_approval_for_all` calls granting an untrusted operator are detected
+    #[serde(default = "ApprovalPolicyConfiguration::default_approvals_for_all")]
+    pub approvals_for_all: bool,
+}
+
+/// Outcome of the transactions making a suspicious approval
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalAction {
+    /// The transaction is never sponsored
+    #[default]
+    Block,
+
+    /// The transaction is only sponsored when the scope of the api key lists the approval entrypoint of the contract
+    /// explicitly
+    RequireScope,
+}
+
+impl ApprovalPolicyConfiguration {
+    pub fn default_unlimited_amount() -> Felt {
+        Felt::from(u128::MAX)
+    }
+
+    pub fn default_approvals_for_all() -> bool {
+        true
+    }
+
+    /// Returns true if the call grants an untrusted spender an unlimited allowance, or an untrusted operator every
+    /// token of a collection
+    pub fn is_suspicious(&self, call: &Call) -> bool {
+        let spender = call.calldata.first();
+        if spender.is_some_and(|x| self.trusted_spenders.contains(x)) {
+            return false;
+        }
+
+        match call.calldata.as_slice() {
+            [_, low, high] if [selector!("approve"), selector!("increase_allowance"), selector!("increaseAllowance")].contains(&call.selector) => {
+                *high != Felt::ZERO || *low >= self.unlimited_amount
+            },
+            [_, approved] if [selector!("set_approval_for_all"), selector!("setApprovalForAll")].contains(&call.selector) => {
+                self.approvals_for_all && *approved != Felt::ZERO
+            },
+            _ => false,
+        }
+    }
+}
+
+impl Default for CallPolicyConfiguration {
+    fn default() -> Self {
+        Self {
+            blacklist: HashSet::new(),
+            batching_entrypoints: vec![],
+            max_depth: Self::default_max_depth(),
+            transfer_limits: HashMap::new(),
+            approvals: None,
+        }
+    }
+}
+
+/// Entrypoint batching calls
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BatchingEntrypoint {
+    /// Contract exposing the entrypoint, any contract when not set
+    #[serde(default)]
+    pub contract_address: Option<Felt>,
+
+    pub selector: Felt,
+}
+
+impl BatchingEntrypoint {
+    pub fn matches(&self, call: &Call) -> bool {
+        call.selector == self.selector && self.contract_address.is_none_or(|x| x == call.to)
+    }
+}
+
+impl Validate for CallPolicyConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.max_depth > 0 && self.max_depth <= 8, "max_depth", "must be between 1 and 8");
+        if let Some(approvals) = &self.approvals {
+            report.ensure(approvals.unlimited_amount != Felt::ZERO, "approvals.unlimited_amount", "must not be zero");
+        }
+        for (i, entrypoint) in self.batching_entrypoints.iter().enumerate() {
+            report.ensure(
+                entrypoint.selector != Felt::ZERO,
+                &format!("batching_entrypoints[{}].selector", i),
+                "must not be zero",
+            );
+            report.ensure(
+                entrypoint.contract_address != Some(Felt::ZERO),
+                &format!("batching_entrypoints[{}].contract_address", i),
+                "must not be zero",
+            );
+        }
+    }
+}
+
+impl CallPolicyConfiguration {
+    pub fn default_max_depth() -> usize {
+        2
+    }
+
+    /// Returns the calls executed by the transaction, the calls to a batching entrypoint being replaced by the calls
+    /// they batch. Returns `None` when the calldata of a batching call cannot be decoded or the calls are nested
+    /// deeper than `max_depth`, in which case the policies cannot be enforced.
+    pub fn expand_calls(&self, calls: &[Call]) -> Option<Vec<Call>> {
+        let mut expanded = vec![];
+        self.expand_into(calls, 0, &mut expanded)?;
+
+        Some(expanded)
+    }
+
+    fn expand_into(&self, calls: &[Call], depth: usize, expanded: &mut Vec<Call>) -> Option<()> {
+        for call in calls {
+            if !self.batching_entrypoints.iter().any(|x| x.matches(call)) {
+                expanded.push(call.clone());
+                continue;
+            }
+
+            if depth >= self.max_depth {
+                return None;
+            }
+
+            self.expand_into(&decode_calls(&call.calldata)?, depth + 1, expanded)?;
+        }
+
+        Some(())
+    }
+
+    /// Returns true if one of the calls, or one of the calls they batch, targets a blacklisted contract. Calls which
+    /// cannot be decoded are considered blacklisted as soon as the blacklist is not empty.
+    pub fn is_blacklisted(&self, calls: &[Call]) -> bool {
+        if self.blacklist.is_empty() {
+            return false;
+        }
+
+        let targets_blacklist = |calls: &[Call]| calls.iter().any(|x| self.blacklist.contains(&x.to));
+        match self.expand_calls(calls) {
+            Some(expanded) => targets_blacklist(calls) || targets_blacklist(&expanded),
+            None => true,
+        }
+    }
+
+    /// Returns the first token whose amount transferred or approved by the calls exceeds its limit
+    pub fn find_transfer_over_limit(&self, calls: &[Call]) -> Option<Felt> {
+        find_transfer_over(&self.transfer_limits, calls)
+    }
+}
+
+/// Returns the first token whose amount transferred or approved by the calls exceeds the given amount. The transfers
+/// of a listed token whose amount cannot be decoded exceed it.
+pub fn find_transfer_over(amounts: &HashMap<Felt, Felt>, calls: &[Call]) -> Option<Felt> {
+    let mut totals: HashMap<Felt, Felt> = HashMap::new();
+    for call in calls {
+        let Some(limit) = amounts.get(&call.to) else {
+            continue;
+        };
+        let Some((_, position)) = TRANSFER_ENTRYPOINTS.iter().find(|(selector, _)| *selector == call.selector) else {
+            continue;
+        };
+
+        // Amounts are encoded as an u256 (low, high) and end the calldata
+        let amount = match call.calldata.as_slice() {
+            calldata if calldata.len() == position + 2 && calldata[position + 1] == Felt::ZERO => calldata[*position],
+            _ => return Some(call.to),
+        };
+
+        let total = totals.entry(call.to).or_default();
+        *total += amount;
+        if *total > *limit {
+            return Some(call.to);
+        }
+    }
+
+    None
+}
+
+/// Decode the serialization of an `Array<Call>`: the number of calls followed by the address, the selector, the
+/// length of the calldata and the calldata of each call
+pub fn decode_calls(calldata: &[Felt]) -> Option<Vec<Call>> {
+    let (count, mut remaining) = calldata.split_first()?;
+
+    let count = u64::try_from(*count).ok()? as usize;
+    let mut calls = Vec::with_capacity(count.min(remaining.len() / 3));
+    for _ in 0..count {
+        let [to, selector, length, tail @ ..] = remaining else {
+            return None;
+        };
+
+        let length = u64::try_from(*length).ok()? as usize;
+        if tail.len() < length {
+            return None;
+        }
+
+        calls.push(Call {
+            to: *to,
+            selector: *selector,
+            calldata: tail[..length].to_vec(),
+        });
+        remaining = &tail[length..];
+    }
+
+    remaining.is_empty().then_some(calls)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use starknet::core::types::{Call, Felt};
+    use starknet::macros::selector;
+
+    use crate::context::call_policy::{decode_calls, ApprovalPolicyConfiguration, BatchingEntrypoint, CallPolicyConfiguration};
+
+    const MULTICALL: Felt = Felt::from_hex_unchecked("0x1234");
+
+    fn call(to: Felt, selector: Felt, calldata: Vec<Felt>) -> Call {
+        Call { to, selector, calldata }
+    }
+
+    fn encode(calls: &[Call]) -> Vec<Felt> {
+        let mut calldata = vec![Felt::from(calls.len())];
+        for call in calls {
+            calldata.extend([call.to, call.selector, Felt::from(call.calldata.len())]);
+            calldata.extend(call.calldata.iter().copied());
+        }
+
+        calldata
+    }
+
+    fn policy() -> CallPolicyConfiguration {
+        CallPolicyConfiguration {
+            blacklist: HashSet::from([Felt::THREE]),
+            batching_entrypoints: vec![BatchingEntrypoint {
+                contract_address: Some(MULTICALL),
+                selector: selector!("multicall"),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn calls_are_decoded() {
+        let calls = vec![call(Felt::ONE, Felt::TWO, vec![Felt::THREE]), call(Felt::TWO, Felt::ONE, vec![])];
+        assert_eq!(decode_calls(&encode(&calls)), Some(calls));
+
+        assert_eq!(decode_calls(&[]), None);
+        assert_eq!(decode_calls(&[Felt::ONE, Felt::ONE, Felt::TWO, Felt::TWO]), None);
+        assert_eq!(decode_calls(&[Felt::ZERO, Felt::ONE]), None);
+    }
+
+    #[test]
+    fn batched_calls_are_expanded() {
+        let policy = policy();
+        let inner = call(Felt::ONE, Felt::TWO, vec![]);
+        let batch = call(MULTICALL, selector!("multicall"), encode(&[inner.clone(), inner.clone()]));
+
+        assert_eq!(policy.expand_calls(&[batch.clone()]), Some(vec![inner.clone(), inner.clone()]));
+
+        let nested = call(MULTICALL, selector!("multicall"), encode(&[batch.clone()]));
+        assert_eq!(policy.expand_calls(&[nested.clone()]), Some(vec![inner.clone(), inner.clone()]));
+
+        let too_deep = call(MULTICALL, selector!("multicall"), encode(&[nested]));
+        assert_eq!(policy.expand_calls(&[too_deep]), None);
+
+        let malformed = call(MULTICALL, selector!("multicall"), vec![Felt::TWO]);
+        assert_eq!(policy.expand_calls(&[malformed]), None);
+    }
+
+    #[test]
+    fn blacklist_applies_to_batched_calls() {
+        let policy = policy();
+        let allowed = call(Felt::ONE, Felt::TWO, vec![]);
+        let blacklisted = call(Felt::THREE, Felt::TWO, vec![]);
+
+        assert!(!policy.is_blacklisted(&[allowed.clone()]));
+        assert!(policy.is_blacklisted(&[blacklisted.clone()]));
+        assert!(policy.is_blacklisted(&[call(MULTICALL, selector!("multicall"), encode(&[allowed.clone(), blacklisted]))]));
+        assert!(!policy.is_blacklisted(&[call(MULTICALL, selector!("multicall"), encode(&[allowed]))]));
+        assert!(policy.is_blacklisted(&[call(MULTICALL, selector!("multicall"), vec![Felt::TWO])]));
+    }
+
+    #[test]
+    fn transfers_are_limited_per_token() {
+        let token = Felt::from(0x10);
+        let policy = CallPolicyConfiguration {
+            transfer_limits: HashMap::from([(token, Felt::from(100))]),
+            ..Default::default()
+        };
+        let transfer = |to: Felt, amount: u64| call(to, selector!("transfer"), vec![Felt::ONE, Felt::from(amount), Felt::ZERO]);
+
+        assert_eq!(policy.find_transfer_over_limit(&[transfer(token, 60), transfer(Felt::TWO, 1000)]), None);
+        assert_eq!(policy.find_transfer_over_limit(&[transfer(token, 60), transfer(token, 60)]), Some(token));
+        assert_eq!(
+            policy.find_transfer_over_limit(&[call(token, selector!("approve"), vec![Felt::ONE, Felt::ZERO, Felt::ONE])]),
+            Some(token)
+        );
+        assert_eq!(policy.find_transfer_over_limit(&[call(token, selector!("transfer"), vec![Felt::ONE])]), Some(token));
+        assert_eq!(policy.find_transfer_over_limit(&[call(token, selector!("balance_of"), vec![Felt::ONE])]), None);
+    }
+
+    #[test]
+    fn unlimited_approvals_to_untrusted_spenders_are_suspicious() {
+        let policy = ApprovalPolicyConfiguration {
+            action: Default::default(),
+            trusted_spenders: HashSet::from([Felt::TWO]),
+            unlimited_amount: ApprovalPolicyConfiguration::default_unlimited_amount(),
+            approvals_for_all: true,
+        };
+        let approve = |spender: Felt, low: Felt, high: Felt| call(Felt::ONE, selector!("approve"), vec![spender, low, high]);
+        let max = Felt::from(u128::MAX);
+
+        assert!(policy.is_suspicious(&approve(Felt::THREE, max, max)));
+        assert!(policy.is_suspicious(&approve(Felt::THREE, Felt::ZERO, Felt::ONE)));
+        assert!(!policy.is_suspicious(&approve(Felt::THREE, Felt::from(1000), Felt::ZERO)));
+        assert!(!policy.is_suspicious(&approve(Felt::TWO, max, max)));
+
+        assert!(policy.is_suspicious(&call(Felt::ONE, selector!("set_approval_for_all"), vec![Felt::THREE, Felt::ONE])));
+        assert!(!policy.is_suspicious(&call(Felt::ONE, selector!("set_approval_for_all"), vec![Felt::THREE, Felt::ZERO])));
+        assert!(!policy.is_suspicious(&call(Felt::ONE, selector!("set_approval_for_all"), vec![Felt::TWO, Felt::ONE])));
+    }
+}
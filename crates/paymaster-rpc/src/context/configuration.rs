@@ -1,6 +1,21 @@
 use std::collections::HashSet;
 
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_execution::analytics::AnalyticsConfiguration;
+use paymaster_execution::callback::CallbacksConfiguration;
+use paymaster_execution::cost::CostAttributionConfiguration;
+use paymaster_execution::hook::HooksConfiguration;
+use paymaster_execution::low_rpc::LowRpcConfiguration;
+use paymaster_execution::profitability::ProfitabilityConfiguration;
+use paymaster_execution::quote::QuoteTtlConfiguration;
+use paymaster_execution::recipient::FeeRecipientsConfiguration;
+use paymaster_execution::refund::RefundConfiguration;
+use paymaster_execution::reorg::ReorgConfiguration;
+use paymaster_execution::tokens::metadata::TokenMetadataOverrides;
+use paymaster_execution::whitelist::ForwarderWhitelistConfiguration;
+use paymaster_prices::math::RoundingPolicy;
 use paymaster_prices::PriceConfiguration;
+use paymaster_relayer::multisig::MultisigConfiguration;
 use paymaster_relayer::RelayersConfiguration;
 use paymaster_sponsoring::Configuration as SponsoringConfiguration;
 use paymaster_starknet::{Configuration as StarknetConfiguration, StarknetAccountConfiguration};
@@ -8,24 +23,136 @@ use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use starknet::core::types::Felt;
 
+use crate::context::{
+    ApprovalQueueConfiguration, BuildCacheConfiguration, CallPolicyConfiguration, DeadLetterConfiguration, MaintenanceConfiguration, ReplayProtectionConfiguration,
+    TraceSamplingConfiguration,
+};
+use crate::middleware::MethodDeprecation;
+
 #[derive(Clone, Debug)]
 pub struct Configuration {
     pub rpc: RPCConfiguration,
 
     pub forwarder: Felt,
+
+    /// Owner of the forwarder keeping the relayers and the estimate account whitelisted, disabled when not set
+    pub forwarder_whitelist: Option<ForwarderWhitelistConfiguration>,
+
     pub supported_tokens: HashSet<Felt>,
 
+    /// Metadata of the supported tokens taking precedence over the one listed by AVNU
+    pub token_metadata: TokenMetadataOverrides,
+
+    /// Gas tokens whose fee is collected through an allowance granted to the forwarder
+    pub permit_tokens: HashSet<Felt>,
+
+    /// Whether the users pay the fee directly to the relayer executing their transaction, without the forwarder
+    pub direct_fee_payment: bool,
+
+    /// Minimum margin the fee must leave over the cost of a transaction for it to be executed
+    pub profitability: Option<ProfitabilityConfiguration>,
+
+    /// Validity of the quotes derived from the volatility of the gas token
+    pub quote_ttl: Option<QuoteTtlConfiguration>,
+
+    /// Degraded mode used when the quota of the Starknet RPC is constrained
+    pub low_rpc: LowRpcConfiguration,
+
     pub max_fee_multiplier: f32,
     pub provider_fee_overhead: f32,
 
+    /// Rounding of the fees converted from STRK to the gas token
+    pub fee_rounding: RoundingPolicy,
+
     pub estimate_account: StarknetAccountConfiguration,
     pub gas_tank: StarknetAccountConfiguration,
+    pub gas_tank_multisig: Option<MultisigConfiguration>,
+
+    /// Addresses receiving the fee of some sponsors or gas tokens in place of the gas tank
+    pub fee_recipients: FeeRecipientsConfiguration,
 
     pub relayers: RelayersConfiguration,
 
     pub starknet: StarknetConfiguration,
     pub price: PriceConfiguration,
     pub sponsoring: SponsoringConfiguration,
+
+    /// Refund of the fee overcharged to the users, disabled when not set
+    pub refund: Option<RefundConfiguration>,
+
+    /// Detection of the reorgs of the chain, reconciling the transactions executed recently, disabled when not set
+    pub reorg: Option<ReorgConfiguration>,
+
+    /// Hooks rewriting the calls of the users before their transaction is built
+    pub hooks: HooksConfiguration,
+
+    /// Stream of the events emitted for every transaction built, executed and confirmed, disabled when not set
+    pub analytics: Option<AnalyticsConfiguration>,
+
+    /// Callbacks notifying the sponsors of the lifecycle of their sponsored transactions
+    pub callbacks: CallbacksConfiguration,
+
+    /// Metrics attributing the fees collected, the STRK spent and the margin realized to the sponsors and gas tokens, disabled when not set
+    pub cost_attribution: Option<CostAttributionConfiguration>,
+}
+
+impl Validate for Configuration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.field("rpc", &self.rpc);
+        report.ensure(self.forwarder != Felt::ZERO, "forwarder", "must not be zero");
+        report.ensure(!self.supported_tokens.is_empty(), "supported_tokens", "at least one token must be supported");
+        report.field("sponsoring", &self.sponsoring);
+        report.field("fee_recipients", &self.fee_recipients);
+        if let Some(forwarder_whitelist) = &self.forwarder_whitelist {
+            report.field("forwarder_whitelist", forwarder_whitelist);
+        }
+        if let Some(refund) = &self.refund {
+            report.field("refund", refund);
+        }
+        if let Some(reorg) = &self.reorg {
+            report.field("reorg", reorg);
+        }
+        report.field("hooks", &self.hooks);
+        if let Some(analytics) = &self.analytics {
+            report.field("analytics", analytics);
+        }
+        report.field("callbacks", &self.callbacks);
+        if let Some(cost_attribution) = &self.cost_attribution {
+            report.field("cost_attribution", cost_attribution);
+        }
+
+        // Remaining fields are shared with the execution configuration and validated there
+        paymaster_execution::Configuration::from(self.clone()).validate_into(report);
+    }
+}
+
+impl Configuration {
+    /// Returns the accounts which must be whitelisted on the forwarder: the relayers of both fleets and the estimate
+    /// account
+    pub fn whitelisted_accounts(&self) -> Vec<Felt> {
+        let mut accounts = self.relayers.fleet_addresses();
+        accounts.push(self.estimate_account.address);
+
+        accounts
+    }
+
+    /// Returns the accounts operated by the paymaster: the relayers of both fleets, the gas tank, the estimate account
+    /// and the treasury topping up the gas tank
+    pub fn operator_accounts(&self) -> HashSet<Felt> {
+        let mut accounts: HashSet<Felt> = [self.gas_tank.address, self.estimate_account.address].into();
+
+        let mut relayers = Some(&self.relayers);
+        while let Some(fleet) = relayers {
+            accounts.extend(&fleet.addresses);
+            if let Some(top_up) = &fleet.gas_tank_top_up {
+                accounts.insert(top_up.treasury.address);
+            }
+
+            relayers = fleet.secondary.as_deref();
+        }
+
+        accounts
+    }
 }
 
 impl From<Configuration> for paymaster_execution::Configuration {
@@ -34,11 +161,19 @@ impl From<Configuration> for paymaster_execution::Configuration {
             starknet: value.starknet,
             price: value.price,
             supported_tokens: value.supported_tokens,
+            token_metadata: value.token_metadata,
+            permit_tokens: value.permit_tokens,
+            direct_fee_payment: value.direct_fee_payment,
+            profitability: value.profitability,
+            quote_ttl: value.quote_ttl,
+            low_rpc: value.low_rpc,
             max_fee_multiplier: value.max_fee_multiplier,
             provider_fee_overhead: value.provider_fee_overhead,
+            fee_rounding: value.fee_rounding,
 
             estimate_account: value.estimate_account,
             gas_tank: value.gas_tank,
+            gas_tank_multisig: value.gas_tank_multisig,
 
             relayers: value.relayers,
         }
@@ -49,4 +184,77 @@ impl From<Configuration> for paymaster_execution::Configuration {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RPCConfiguration {
     pub port: u64,
+
+    #[serde(default)]
+    pub maintenance: MaintenanceConfiguration,
+
+    /// Attach a diagnosis of the state of the user (balance, allowance, nonce...) to the execution errors, and the
+    /// time spent in each stage to the execution responses. Meant for debugging as it discloses on-chain state in
+    /// the error payloads.
+    #[serde(default)]
+    pub debug_diagnostics: bool,
+
+    /// Queue receiving the sponsored executions which failed, so that they are not silently lost
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dead_letter: Option<DeadLetterConfiguration>,
+
+    /// Queue parking the sponsored executions above a fee or transfer threshold until an operator approves them,
+    /// disabled when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_queue: Option<ApprovalQueueConfiguration>,
+
+    /// Cache of the responses of the identical build requests, disabled when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_cache: Option<BuildCacheConfiguration>,
+
+    /// Capture of the payload and outcome of a fraction of the execution requests per sponsor, disabled when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_sampling: Option<TraceSamplingConfiguration>,
+
+    /// Refuse the execute requests whose client nonce was already used, disabled when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_protection: Option<ReplayProtectionConfiguration>,
+
+    /// Blacklist of the contracts and decoding of the batching entrypoints, so that the policies also apply to the
+    /// calls they batch
+    #[serde(default)]
+    pub call_policy: CallPolicyConfiguration,
+
+    /// Methods announced as deprecated in the headers of their responses, still served until their sunset
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deprecated_methods: Vec<MethodDeprecation>,
+
+    /// Private key signing the build responses, so that the services relaying them can prove they were not
+    /// tampered with. The responses are not signed when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_signing_key: Option<Felt>,
+}
+
+impl Validate for RPCConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.port > 0 && self.port <= u16::MAX as u64, "port", "must be between 1 and 65535");
+        report.field("maintenance", &self.maintenance);
+        if let Some(dead_letter) = &self.dead_letter {
+            report.field("dead_letter", dead_letter);
+        }
+        if let Some(approval_queue) = &self.approval_queue {
+            report.field("approval_queue", approval_queue);
+        }
+        if let Some(build_cache) = &self.build_cache {
+            report.field("build_cache", build_cache);
+        }
+        if let Some(trace_sampling) = &self.trace_sampling {
+            report.field("trace_sampling", trace_sampling);
+        }
+        if let Some(replay_protection) = &self.replay_protection {
+            report.field("replay_protection", replay_protection);
+        }
+        report.field("call_policy", &self.call_policy);
+        for (i, deprecation) in self.deprecated_methods.iter().enumerate() {
+            report.field(&format!("deprecated_methods[{}]", i), deprecation);
+        }
+        if let Some(key) = self.response_signing_key {
+            report.ensure(key != Felt::ZERO, "response_signing_key", "must not be zero");
+        }
+    }
 }
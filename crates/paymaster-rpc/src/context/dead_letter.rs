@@ -0,0 +1,188 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use deadpool_redis::redis::cmd;
+use deadpool_redis::{Config, Pool, Runtime};
+use paymaster_common::metric;
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_execution::Error as ExecutionError;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::ExecuteRequest;
+
+/// Redis stream receiving the sponsored executions which failed, so that they can be inspected and retried
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeadLetterConfiguration {
+    pub endpoint: String,
+    pub stream: String,
+
+    /// Approximate number of entries kept in the stream, the oldest ones are dropped beyond it
+    #[serde(default = "DeadLetterConfiguration::default_max_length")]
+    pub max_length: usize,
+}
+
+impl DeadLetterConfiguration {
+    fn default_max_length() -> usize {
+        10_000
+    }
+}
+
+impl Validate for DeadLetterConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure_url("endpoint", &self.endpoint);
+        report.ensure(!self.stream.is_empty(), "stream", "must not be empty");
+        report.ensure(self.max_length > 0, "max_length", "must be greater than 0");
+    }
+}
+
+/// Sponsored execution which failed, along with what is needed to execute it again
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// Request of the execution, holding the typed data even when the user only sent its hash
+    pub request: ExecuteRequest,
+
+    /// Api key of the sponsor, needed to execute the request again on its behalf. Never exposed by the admin api.
+    pub api_key: Option<String>,
+
+    pub error: String,
+
+    /// Unix timestamp in seconds
+    pub failed_at: u64,
+}
+
+impl DeadLetter {
+    pub fn new(request: ExecuteRequest, api_key: Option<String>, error: String) -> Self {
+        Self {
+            request,
+            api_key,
+            error,
+            failed_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        }
+    }
+}
+
+/// Dead-letter queue of the sponsored executions. Entries are only pushed when the queue is configured.
+#[derive(Clone)]
+pub struct DeadLetterQueue {
+    redis: Option<(Pool, DeadLetterConfiguration)>,
+}
+
+impl DeadLetterQueue {
+    pub fn new(configuration: Option<&DeadLetterConfiguration>) -> Result<Self, ExecutionError> {
+        let redis = configuration
+            .map(|x| {
+                Config::from_url(&x.endpoint)
+                    .create_pool(Some(Runtime::Tokio1))
+                    .map(|pool| (pool, x.clone()))
+                    .map_err(|e| ExecutionError::Internal(e.to_string()))
+            })
+            .transpose()?;
+
+        Ok(Self { redis })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.redis.is_some()
+    }
+
+    /// Push the failed execution to the queue. The failure is only logged if the queue cannot be reached.
+    pub async fn push(&self, letter: &DeadLetter) {
+        let Some((pool, configuration)) = &self.redis else { return };
+
+        let result: Result<String, String> = async {
+            let payload = serde_json::to_string(letter).map_err(|e| e.to_string())?;
+            let mut connection = pool.get().await.map_err(|e| e.to_string())?;
+
+            cmd("XADD")
+                .arg(&configuration.stream)
+                .arg("MAXLEN")
+                .arg("~")
+                .arg(configuration.max_length)
+                .arg("*")
+                .arg("entry")
+                .arg(payload)
+                .query_async(&mut connection)
+                .await
+                .map_err(|e| e.to_string())
+        }
+        .await;
+
+        match result {
+            Ok(_) => metric!(counter[dead_letter_pushed] = 1),
+            Err(e) => error!("could not push failed execution to the dead-letter queue: {}", e),
+        }
+    }
+
+    /// Returns the `count` most recent entries of the queue along with their id
+    pub async fn list(&self, count: usize) -> Result<Vec<(String, DeadLetter)>, String> {
+        self.range("+", "-", count).await
+    }
+
+    /// Returns the entry with the given id if it is still in the queue
+    pub async fn get(&self, id: &str) -> Result<Option<DeadLetter>, String> {
+        Ok(self.range(id, id, 1).await?.into_iter().next().map(|(_, x)| x))
+    }
+
+    /// Remove the entry with the given id, returns false if it was not in the queue
+    pub async fn remove(&self, id: &str) -> Result<bool, String> {
+        let Some((pool, configuration)) = &self.redis else { return Ok(false) };
+
+        let mut connection = pool.get().await.map_err(|e| e.to_string())?;
+        let removed: usize = cmd("XDEL")
+            .arg(&configuration.stream)
+            .arg(id)
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(removed > 0)
+    }
+
+    // Read the entries from `end` down to `start`, entries which cannot be decoded are skipped
+    async fn range(&self, end: &str, start: &str, count: usize) -> Result<Vec<(String, DeadLetter)>, String> {
+        let Some((pool, configuration)) = &self.redis else { return Ok(vec![]) };
+
+        let mut connection = pool.get().await.map_err(|e| e.to_string())?;
+        let entries: Vec<(String, Vec<String>)> = cmd("XREVRANGE")
+            .arg(&configuration.stream)
+            .arg(end)
+            .arg(start)
+            .arg("COUNT")
+            .arg(count)
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(entries
+            .into_iter()
+            .filter_map(|(id, fields)| {
+                let payload = fields.chunks(2).find(|x| x[0] == "entry")?.get(1)?;
+                serde_json::from_str(payload).ok().map(|x| (id, x))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use paymaster_common::validation::Validate;
+
+    use crate::context::dead_letter::{DeadLetterConfiguration, DeadLetterQueue};
+
+    #[tokio::test]
+    async fn disabled_queue_holds_no_entries() {
+        let queue = DeadLetterQueue::new(None).unwrap();
+        assert!(!queue.is_enabled());
+
+        assert!(queue.list(10).await.unwrap().is_empty());
+        assert!(queue.get("0-1").await.unwrap().is_none());
+        assert!(!queue.remove("0-1").await.unwrap());
+    }
+
+    #[test]
+    fn configuration_requires_a_stream() {
+        let configuration: DeadLetterConfiguration = serde_json::from_str(r#"{ "endpoint": "redis://localhost:6379", "stream": "" }"#).unwrap();
+        assert_eq!(configuration.max_length, 10_000);
+        assert!(configuration.validate_all().is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::{Arc, RwLock};
+
+use deadpool_redis::redis::AsyncCommands;
+use deadpool_redis::{Config, Pool, Runtime};
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_execution::Error as ExecutionError;
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+pub const DEFAULT_MAINTENANCE_MESSAGE: &str = "the paymaster is under maintenance";
+
+/// Redis key shared by the instances of the paymaster. The maintenance mode is enabled while the key holds a message.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MaintenanceFlagConfiguration {
+    pub endpoint: String,
+    pub key: String,
+}
+
+impl Validate for MaintenanceFlagConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure_url("endpoint", &self.endpoint);
+        report.ensure(!self.key.is_empty(), "key", "must not be empty");
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MaintenanceConfiguration {
+    /// Start the instance with the maintenance mode enabled
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Message returned to the users while the maintenance mode is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    /// Api key required to toggle the maintenance mode through `paymaster_setMaintenance`. The admin api is disabled when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin_api_key: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redis: Option<MaintenanceFlagConfiguration>,
+}
+
+impl Validate for MaintenanceConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        if let Some(admin_api_key) = &self.admin_api_key {
+            report.ensure(!admin_api_key.is_empty(), "admin_api_key", "must not be empty");
+        }
+        if let Some(redis) = &self.redis {
+            report.field("redis", redis);
+        }
+    }
+}
+
+/// Switch rejecting the executions while enabled, either from the configuration, the admin api or the Redis flag.
+/// Builds and status queries are still served during a maintenance.
+#[derive(Clone)]
+pub struct MaintenanceSwitch {
+    message: Arc<RwLock<Option<String>>>,
+    redis: Option<(Pool, String)>,
+}
+
+impl MaintenanceSwitch {
+    pub fn new(configuration: &MaintenanceConfiguration) -> Result<Self, ExecutionError> {
+        let message = configuration
+            .enabled
+            .then(|| configuration.message.clone().unwrap_or(DEFAULT_MAINTENANCE_MESSAGE.to_string()));
+
+        let redis = configuration
+            .redis
+            .as_ref()
+            .map(|x| {
+                Config::from_url(&x.endpoint)
+                    .create_pool(Some(Runtime::Tokio1))
+                    .map(|pool| (pool, x.key.clone()))
+                    .map_err(|e| ExecutionError::Internal(e.to_string()))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            message: Arc::new(RwLock::new(message)),
+            redis,
+        })
+    }
+
+    /// Returns the maintenance message if the maintenance mode is enabled
+    pub async fn current(&self) -> Option<String> {
+        let local = self.message.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if local.is_some() {
+            return local;
+        }
+
+        let (pool, key) = self.redis.as_ref()?;
+        let result: Result<Option<String>, String> = async {
+            let mut connection = pool.get().await.map_err(|e| e.to_string())?;
+            connection.get(key).await.map_err(|e| e.to_string())
+        }
+        .await;
+
+        // The executions are not blocked if the flag cannot be read
+        result.unwrap_or_else(|e| {
+            warn!("could not read maintenance flag: {}", e);
+            None
+        })
+    }
+
+    /// Enable or disable the maintenance mode. The Redis flag, if any, is updated so that every instance is affected.
+    pub async fn set(&self, message: Option<String>) {
+        if let Some((pool, key)) = &self.redis {
+            let result: Result<(), String> = async {
+                let mut connection = pool.get().await.map_err(|e| e.to_string())?;
+                match &message {
+                    Some(message) => connection.set::<_, _, ()>(key, message).await.map_err(|e| e.to_string()),
+                    None => connection.del::<_, ()>(key).await.map_err(|e| e.to_string()),
+                }
+            }
+            .await;
+
+            if let Err(e) = result {
+                error!("could not update maintenance flag: {}", e);
+            }
+        }
+
+        *self.message.write().unwrap_or_else(|e| e.into_inner()) = message;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::maintenance::{MaintenanceConfiguration, MaintenanceSwitch, DEFAULT_MAINTENANCE_MESSAGE};
+
+    #[tokio::test]
+    async fn maintenance_can_be_toggled() {
+        let switch = MaintenanceSwitch::new(&MaintenanceConfiguration {
+            enabled: true,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(switch.current().await, Some(DEFAULT_MAINTENANCE_MESSAGE.to_string()));
+
+        switch.set(None).await;
+        assert_eq!(switch.current().await, None);
+
+        switch.set(Some("upgrading relayers".to_string())).await;
+        assert_eq!(switch.current().await, Some("upgrading relayers".to_string()));
+    }
+}
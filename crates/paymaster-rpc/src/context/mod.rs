@@ -1,30 +1,251 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+mod approval;
+pub use approval::{ApprovalQueue, ApprovalQueueConfiguration, PendingExecution, PendingRequest};
+
+mod build_cache;
+pub use build_cache::{BuildCache, BuildCacheConfiguration};
+
+mod call_policy;
+pub use call_policy::{ApprovalAction, ApprovalPolicyConfiguration, BatchingEntrypoint, CallPolicyConfiguration};
+
 mod configuration;
 pub use configuration::{Configuration, RPCConfiguration};
-use paymaster_execution::{Client as ExecutionClient, TransactionDuplicateFilter};
+
+mod dead_letter;
+pub use dead_letter::{DeadLetter, DeadLetterConfiguration, DeadLetterQueue};
+
+mod maintenance;
+pub use maintenance::{MaintenanceConfiguration, MaintenanceFlagConfiguration, MaintenanceSwitch, DEFAULT_MAINTENANCE_MESSAGE};
+
+mod replay;
+pub use replay::{ReplayGuard, ReplayProtectionConfiguration};
+
+mod shared_cache;
+pub use shared_cache::SharedCache;
+
+mod trace_sampling;
+use paymaster_execution::analytics::AnalyticsPublisher;
+use paymaster_execution::callback::SponsorCallbacks;
+use paymaster_execution::cost::CostAttribution;
+use paymaster_execution::hook::CallHooks;
+use paymaster_execution::refund::RefundManager;
+use paymaster_execution::reorg::ReorgMonitor;
+use paymaster_execution::simulation::ExecutionLedger;
+use paymaster_execution::tokens::TokenClient;
+use paymaster_execution::whitelist::ForwarderWhitelistManager;
+use paymaster_execution::{Client as ExecutionClient, Error as ExecutionError, FeeQuote, SponsoredMessages, TransactionDuplicateFilter};
 use paymaster_prices::Client as PriceClient;
+use paymaster_relayer::lock::LockLayerConfiguration;
+use paymaster_sponsoring::usage::UsageLedger;
 use paymaster_sponsoring::Client as SponsoringClient;
+use paymaster_starknet::ChainID;
+use starknet::core::types::{Felt, TypedData};
+pub use trace_sampling::{TraceSampling, TraceSamplingConfiguration};
+
+use crate::Error;
+
+/// Duration during which the quote of an executed transaction is kept to build its receipt
+pub const QUOTE_RETENTION: Duration = Duration::from_secs(24 * 3600);
+
+/// Duration during which the typed data built by an instance can be executed
+pub const TYPED_DATA_RETENTION: Duration = Duration::from_secs(600);
 
 #[derive(Clone)]
 pub struct Context {
     pub configuration: Configuration,
 
     pub price: PriceClient,
+
+    /// Metadata of the tokens listed by AVNU
+    pub tokens: TokenClient,
+
     pub sponsoring: SponsoringClient,
 
     pub execution: ExecutionClient,
     pub transaction_filter: TransactionDuplicateFilter,
+
+    /// Sponsored transactions of the instances, counted against the budget of their sponsor
+    pub usage: UsageLedger,
+
+    /// Quotes of the transactions executed by the instances indexed by transaction hash
+    pub quotes: SharedCache<FeeQuote>,
+
+    /// Typed data built by the instances indexed by message hash
+    pub typed_data: SharedCache<TypedData>,
+
+    /// Responses built recently, served again to the identical requests
+    pub builds: BuildCache,
+
+    pub refunds: RefundManager,
+
+    /// Keeps the relayers and the estimate account whitelisted on the forwarder
+    pub whitelist: ForwarderWhitelistManager,
+
+    /// Reconciles the transactions executed recently when the chain is reorganized
+    pub reorgs: ReorgMonitor,
+
+    /// Most recent transactions executed by the instances, replayed to simulate pricing changes
+    pub executions: ExecutionLedger,
+
+    pub hooks: CallHooks,
+
+    /// Publishes the activity of the instance to the analytics sink
+    pub analytics: AnalyticsPublisher,
+
+    /// Notifies the sponsors of the lifecycle of the transactions they sponsored
+    pub callbacks: SponsorCallbacks,
+
+    /// Reports the cost of the executed transactions per sponsor and gas token
+    pub costs: CostAttribution,
+
+    /// L1 transactions whose messages fee has been sponsored by this instance
+    pub sponsored_messages: SponsoredMessages,
+
+    /// Rejects the executions while the maintenance mode is enabled
+    pub maintenance: MaintenanceSwitch,
+
+    /// Keeps the sponsored executions which failed so that they can be inspected and retried
+    pub dead_letters: DeadLetterQueue,
+
+    /// Parks the sponsored executions above a threshold until an operator approves them
+    pub approvals: ApprovalQueue,
+
+    /// Decides which requests have their payload and outcome captured in their trace
+    pub trace_sampling: TraceSampling,
+
+    /// Refuses the execute requests whose client nonce was already used
+    pub replays: ReplayGuard,
 }
 
 impl Context {
-    pub fn new(configuration: Configuration) -> Self {
-        Self {
+    /// Creates the context of a chain. Fails when the configuration cannot be used to reach Starknet or to
+    /// coordinate the relayers
+    pub fn new(configuration: Configuration) -> Result<Self, ExecutionError> {
+        let execution = ExecutionClient::new(&configuration.clone().into())?;
+
+        Ok(Self {
             price: PriceClient::new(&configuration.price),
+            tokens: TokenClient::new(configuration.starknet.chain_id),
             sponsoring: SponsoringClient::new(&configuration.sponsoring),
 
-            execution: ExecutionClient::new(&configuration.clone().into()),
+            refunds: RefundManager::new(&execution, configuration.refund.as_ref(), &configuration.relayers.lock)?,
+            whitelist: ForwarderWhitelistManager::new(
+                &execution,
+                configuration.forwarder,
+                configuration.whitelisted_accounts(),
+                &configuration.relayers.lock,
+                configuration.forwarder_whitelist.as_ref(),
+            )?,
+            reorgs: ReorgMonitor::new(&execution, configuration.reorg.as_ref()),
+            hooks: CallHooks::new(&configuration.hooks),
+            analytics: AnalyticsPublisher::new(&execution, configuration.analytics.as_ref()),
+            callbacks: SponsorCallbacks::new(&execution, configuration.starknet.chain_id, &configuration.callbacks),
+            costs: CostAttribution::new(&execution, configuration.cost_attribution.as_ref()),
+            maintenance: MaintenanceSwitch::new(&configuration.rpc.maintenance)?,
+            dead_letters: DeadLetterQueue::new(configuration.rpc.dead_letter.as_ref())?,
+            approvals: ApprovalQueue::new(configuration.rpc.approval_queue.as_ref())?,
+            builds: BuildCache::new(configuration.rpc.build_cache.as_ref()),
+            trace_sampling: TraceSampling::new(configuration.rpc.trace_sampling.as_ref()),
+            replays: ReplayGuard::new(configuration.rpc.replay_protection.as_ref())?,
+
+            execution,
             transaction_filter: TransactionDuplicateFilter::default(),
 
+            usage: match &configuration.relayers.lock {
+                LockLayerConfiguration::Shared { redis, .. } => {
+                    UsageLedger::default().with_redis(redis.create_pool().map_err(|e| ExecutionError::Internal(e.to_string()))?)
+                },
+                _ => UsageLedger::default(),
+            },
+            quotes: SharedCache::new(&configuration.relayers.lock, "quote", 100_000)?,
+            typed_data: SharedCache::new(&configuration.relayers.lock, "typed-data", 100_000)?,
+            sponsored_messages: SponsoredMessages::new(&configuration.relayers.lock)?,
+            executions: ExecutionLedger::new(&configuration.relayers.lock)?,
+
             configuration,
+        })
+    }
+}
+
+/// Contexts of every chain served by the instance. Requests are routed to the context matching their
+/// chain id, or to the principal context when they do not specify one.
+#[derive(Clone)]
+pub struct Contexts {
+    principal: Context,
+    chains: HashMap<Felt, Context>,
+}
+
+impl Contexts {
+    pub fn new(principal: Context) -> Self {
+        let chains = HashMap::from([(principal.configuration.starknet.chain_id.as_felt(), principal.clone())]);
+
+        Self { principal, chains }
+    }
+
+    /// Register an additional chain. A context already registered for the same chain id is replaced.
+    pub fn with_chain(mut self, context: Context) -> Self {
+        self.chains.insert(context.configuration.starknet.chain_id.as_felt(), context);
+        self
+    }
+
+    pub fn principal(&self) -> &Context {
+        &self.principal
+    }
+
+    /// Returns the chain ids served by the instance
+    pub fn chain_ids(&self) -> Vec<ChainID> {
+        self.chains.values().map(|x| x.configuration.starknet.chain_id).collect()
+    }
+
+    /// Returns the context of the given chain or the principal context if no chain is given
+    pub fn resolve(&self, chain_id: Option<&ChainID>) -> Result<&Context, Error> {
+        match chain_id {
+            None => Ok(&self.principal),
+            Some(chain_id) => self.chains.get(&chain_id.as_felt()).ok_or(Error::ChainNotSupported),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use paymaster_starknet::ChainID;
+    use starknet::core::types::Felt;
+
+    use crate::context::Contexts;
+    use crate::testing::TestEnvironment;
+    use crate::Error;
+
+    // TODO: enable when we can fix starknet image
+    #[ignore]
+    #[tokio::test]
+    async fn requests_are_routed_to_the_context_of_their_chain() {
+        let test = TestEnvironment::new().await;
+        let principal = test.context().clone();
+        let principal_chain = principal.configuration.starknet.chain_id;
+
+        let mut other = principal.clone();
+        other.configuration.starknet.chain_id = ChainID::Unknown(Felt::from(42));
+        other.configuration.forwarder = Felt::from(43);
+
+        let contexts = Contexts::new(principal).with_chain(other);
+        assert_eq!(contexts.chain_ids().len(), 2);
+
+        let default = contexts.resolve(None).unwrap();
+        assert_eq!(default.configuration.starknet.chain_id.as_felt(), principal_chain.as_felt());
+        assert_eq!(
+            contexts.resolve(Some(&principal_chain)).unwrap().configuration.forwarder,
+            default.configuration.forwarder
+        );
+        assert_eq!(
+            contexts
+                .resolve(Some(&ChainID::Unknown(Felt::from(42))))
+                .unwrap()
+                .configuration
+                .forwarder,
+            Felt::from(43)
+        );
+        assert!(matches!(contexts.resolve(Some(&ChainID::Unknown(Felt::from(44)))), Err(Error::ChainNotSupported)));
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashSet;
+
+use deadpool_redis::redis::cmd;
+use deadpool_redis::{Config, Pool, Runtime};
+use paymaster_common::metric;
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_execution::Error as ExecutionError;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+
+use crate::endpoint::execute::{ClientNonce, ClientNonceBinding};
+use crate::Error;
+
+/// Nonces signed by the clients relaying the execute requests, each accepted once so that a captured request cannot
+/// be replayed over HTTP while its typed data is still valid
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayProtectionConfiguration {
+    pub endpoint: String,
+
+    /// Public keys of the clients allowed to sign nonces
+    #[serde_as(as = "HashSet<UfeHex>")]
+    pub client_keys: HashSet<Felt>,
+
+    /// Duration during which a nonce cannot be used again (in seconds), at least the validity of the typed data
+    #[serde(default = "ReplayProtectionConfiguration::default_window")]
+    pub window: u64,
+
+    /// Refuse the execute requests without a nonce
+    #[serde(default)]
+    pub required: bool,
+}
+
+impl ReplayProtectionConfiguration {
+    fn default_window() -> u64 {
+        600
+    }
+}
+
+impl Validate for ReplayProtectionConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure_url("endpoint", &self.endpoint);
+        report.ensure(!self.client_keys.is_empty(), "client_keys", "at least one client key must be configured");
+        report.ensure(self.window >= 60, "window", "must be at least 60 seconds");
+    }
+}
+
+/// Accepts each client nonce once. Every request is accepted when the replay protection is not configured.
+#[derive(Clone)]
+pub struct ReplayGuard {
+    redis: Option<(Pool, ReplayProtectionConfiguration)>,
+}
+
+impl ReplayGuard {
+    pub fn new(configuration: Option<&ReplayProtectionConfiguration>) -> Result<Self, ExecutionError> {
+        let redis = configuration
+            .map(|x| {
+                Config::from_url(&x.endpoint)
+                    .create_pool(Some(Runtime::Tokio1))
+                    .map(|pool| (pool, x.clone()))
+                    .map_err(|e| ExecutionError::Internal(e.to_string()))
+            })
+            .transpose()?;
+
+        Ok(Self { redis })
+    }
+
+    /// Check the nonce of the request was signed by a known client for this transaction, returns the nonce to
+    /// consume once the request is validated if any
+    pub fn verify(&self, nonce: Option<&ClientNonce>, transaction: &impl ClientNonceBinding) -> Result<Option<ClientNonce>, Error> {
+        let Some((_, configuration)) = &self.redis else { return Ok(None) };
+
+        let Some(nonce) = nonce else {
+            return match configuration.required {
+                true => Err(Error::ClientNonce("client nonce is required".to_string())),
+                false => Ok(None),
+            };
+        };
+
+        if !configuration.client_keys.contains(&nonce.public_key) {
+            return Err(Error::ClientNonce("unknown client key".to_string()));
+        }
+        if !nonce.verify(transaction) {
+            return Err(Error::InvalidSignature);
+        }
+
+        Ok(Some(nonce.clone()))
+    }
+
+    /// Consume the nonce, fails if it was already consumed by another request
+    pub async fn consume(&self, nonce: Option<&ClientNonce>) -> Result<(), Error> {
+        let (Some((pool, configuration)), Some(nonce)) = (&self.redis, nonce) else {
+            return Ok(());
+        };
+
+        let mut connection = pool.get().await.map_err(|e| Error::ClientNonce(e.to_string()))?;
+        let result: Option<String> = cmd("SET")
+            .arg(Self::key(nonce))
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(configuration.window)
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| Error::ClientNonce(e.to_string()))?;
+
+        match result {
+            Some(_) => Ok(()),
+            None => {
+                metric!(counter[execute_request_replayed] = 1);
+                Err(Error::ReplayedRequest)
+            },
+        }
+    }
+
+    /// Make the nonce usable again, once the request is known not to have been processed
+    pub async fn release(&self, nonce: &ClientNonce) {
+        let Some((pool, _)) = &self.redis else { return };
+
+        if let Ok(mut connection) = pool.get().await {
+            let _: Result<usize, _> = cmd("DEL").arg(Self::key(nonce)).query_async(&mut connection).await;
+        }
+    }
+
+    fn key(nonce: &ClientNonce) -> String {
+        format!(
+            "paymaster-client-nonce:{}:{}",
+            nonce.public_key.to_fixed_hex_string(),
+            nonce.nonce.to_fixed_hex_string()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use paymaster_common::validation::Validate;
+    use starknet::core::types::Felt;
+    use starknet::signers::SigningKey;
+
+    use crate::context::replay::{ReplayGuard, ReplayProtectionConfiguration};
+    use crate::endpoint::common::DeploymentParameters;
+    use crate::endpoint::execute::{ClientNonce, ExecutableTransactionParameters};
+    use crate::testing::redis_container;
+    use crate::Error;
+
+    fn deployment() -> ExecutableTransactionParameters {
+        ExecutableTransactionParameters::Deploy {
+            deployment: DeploymentParameters {
+                address: Felt::ONE,
+                class_hash: Felt::TWO,
+                salt: Felt::THREE,
+                calldata: vec![],
+                sigdata: None,
+                version: 1,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn disabled_guard_accepts_every_request() {
+        let guard = ReplayGuard::new(None).unwrap();
+        let transaction = deployment();
+
+        assert!(guard.verify(None, &transaction).unwrap().is_none());
+        guard.consume(None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn nonce_is_consumed_once() {
+        let (_container, endpoint) = redis_container().await;
+        let key = SigningKey::from_secret_scalar(Felt::from(42));
+        let guard = ReplayGuard::new(Some(&ReplayProtectionConfiguration {
+            endpoint,
+            client_keys: HashSet::from([key.verifying_key().scalar()]),
+            window: 600,
+            required: true,
+        }))
+        .unwrap();
+        let transaction = deployment();
+
+        assert!(matches!(guard.verify(None, &transaction), Err(Error::ClientNonce(_))));
+
+        let nonce = ClientNonce::sign(&key, Felt::ONE, &transaction).unwrap();
+        let verified = guard.verify(Some(&nonce), &transaction).unwrap();
+
+        // Verifying does not consume the nonce, a request failing its validation can be submitted again
+        guard.verify(Some(&nonce), &transaction).unwrap();
+
+        guard.consume(verified.as_ref()).await.unwrap();
+        assert!(matches!(guard.consume(verified.as_ref()).await, Err(Error::ReplayedRequest)));
+
+        guard.release(&nonce).await;
+        guard.consume(verified.as_ref()).await.unwrap();
+    }
+
+    #[test]
+    fn configuration_requires_a_client_key() {
+        let configuration = ReplayProtectionConfiguration {
+            endpoint: "redis://localhost:6379".to_string(),
+            client_keys: HashSet::new(),
+            window: 600,
+            required: false,
+        };
+
+        assert!(configuration.validate_all().is_err());
+    }
+}
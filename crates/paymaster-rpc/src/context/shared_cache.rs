@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use deadpool_redis::redis::AsyncCommands;
+use deadpool_redis::Pool;
+use paymaster_common::cache::ExpirableCache;
+use paymaster_execution::Error as ExecutionError;
+use paymaster_relayer::lock::LockLayerConfiguration;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use starknet::core::types::Felt;
+use tracing::error;
+
+use crate::Error;
+
+/// Values indexed by hash which must be visible to every instance, such as the typed data built by one instance and
+/// executed through another one behind the load balancer. They are stored in the Redis of the shared lock layer and
+/// kept in memory when the lock layer is local to the instance.
+#[derive(Clone)]
+pub struct SharedCache<V> {
+    redis: Option<Pool>,
+    prefix: &'static str,
+
+    // Values inserted by the instance, read before reaching Redis
+    memory: ExpirableCache<Felt, V>,
+}
+
+impl<V> SharedCache<V>
+where
+    V: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    pub fn new(lock: &LockLayerConfiguration, prefix: &'static str, capacity: usize) -> Result<Self, ExecutionError> {
+        let redis = match lock {
+            LockLayerConfiguration::Shared { redis, .. } => Some(redis.create_pool().map_err(|e| ExecutionError::Internal(e.to_string()))?),
+            _ => None,
+        };
+
+        Ok(Self::with_pool(redis, prefix, capacity))
+    }
+
+    fn with_pool(redis: Option<Pool>, prefix: &'static str, capacity: usize) -> Self {
+        Self {
+            redis,
+            prefix,
+            memory: ExpirableCache::new(capacity),
+        }
+    }
+
+    /// Store the value for `retention`. A value which cannot be written to Redis is only visible to the instance.
+    pub async fn insert(&self, key: Felt, value: V, retention: Duration) {
+        if let Some(redis) = &self.redis {
+            if let Err(e) = self.store(redis, key, &value, retention).await {
+                error!("Failed to share {} {}: {}", self.prefix, key.to_fixed_hex_string(), e);
+            }
+        }
+
+        self.memory.insert(key, value, retention);
+    }
+
+    /// Returns the value stored by any instance, if it did not expire
+    pub async fn get(&self, key: &Felt) -> Result<Option<V>, Error> {
+        if let Some(value) = self.memory.get_if_not_stale(key) {
+            return Ok(Some(value));
+        }
+        let Some(redis) = &self.redis else { return Ok(None) };
+
+        let mut connection = redis.get().await.map_err(|e| Self::unavailable(e.to_string()))?;
+        let value: Option<String> = connection
+            .get(self.key(key))
+            .await
+            .map_err(|e| Self::unavailable(e.to_string()))?;
+
+        Ok(value.and_then(|x| serde_json::from_str(&x).ok()))
+    }
+
+    async fn store(&self, redis: &Pool, key: Felt, value: &V, retention: Duration) -> Result<(), String> {
+        let value = serde_json::to_string(value).map_err(|e| e.to_string())?;
+        let mut connection = redis.get().await.map_err(|e| e.to_string())?;
+
+        let _: () = connection
+            .set_ex(self.key(&key), value, retention.as_secs().max(1))
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    fn key(&self, key: &Felt) -> String {
+        format!("{}:{}", self.prefix, key.to_fixed_hex_string())
+    }
+
+    fn unavailable(message: String) -> Error {
+        error!("Failed to read the shared cache: {}", message);
+        Error::ServiceNotAvailable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use deadpool_redis::{Config, Runtime};
+    use starknet::core::types::Felt;
+
+    use crate::context::shared_cache::SharedCache;
+    use crate::testing::redis_container;
+
+    #[tokio::test]
+    async fn local_cache_is_only_visible_to_the_instance() {
+        let cache = SharedCache::<u64>::with_pool(None, "value", 10);
+        let other = SharedCache::<u64>::with_pool(None, "value", 10);
+
+        cache.insert(Felt::ONE, 42, Duration::from_secs(60)).await;
+
+        assert_eq!(cache.get(&Felt::ONE).await.unwrap(), Some(42));
+        assert_eq!(other.get(&Felt::ONE).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn values_are_shared_across_the_instances() {
+        let (_container, endpoint) = redis_container().await;
+        let pool = || Some(Config::from_url(&endpoint).create_pool(Some(Runtime::Tokio1)).unwrap());
+
+        let cache = SharedCache::<u64>::with_pool(pool(), "value", 10);
+        let other = SharedCache::<u64>::with_pool(pool(), "value", 10);
+
+        cache.insert(Felt::ONE, 42, Duration::from_secs(1)).await;
+        assert_eq!(other.get(&Felt::ONE).await.unwrap(), Some(42));
+        assert_eq!(other.get(&Felt::TWO).await.unwrap(), None);
+
+        // The value is no longer visible once its retention elapsed
+        tokio::time::sleep(Duration::from_millis(2100)).await;
+        assert_eq!(other.get(&Felt::ONE).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use starknet::core::types::Felt;
+use starknet::core::utils::starknet_keccak;
+use tracing::{info, warn};
+
+/// Target of the events carrying the traces of the sampled requests
+pub const TRACE_TARGET: &str = "paymaster_rpc::trace";
+
+/// Capture of the full trace of a fraction of the requests: their payload and their response or error are logged
+/// within the span of the request. Bounds the overhead of the capture while keeping enough detail to debug the
+/// issues of a given sponsor.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TraceSamplingConfiguration {
+    /// Fraction of the requests traced when their sponsor has no rate of its own
+    #[serde(default)]
+    pub default_rate: f64,
+
+    /// Fraction of the requests traced per sponsor, by fingerprint of their api key (keccak of the key)
+    #[serde(default)]
+    pub sponsors: HashMap<Felt, f64>,
+
+    /// Trace every failed request, whether it was sampled or not
+    #[serde(default)]
+    pub failed_requests: bool,
+}
+
+impl Validate for TraceSamplingConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure((0.0..=1.0).contains(&self.default_rate), "default_rate", "must be between 0 and 1");
+        for (sponsor, rate) in &self.sponsors {
+            report.ensure(
+                (0.0..=1.0).contains(rate),
+                &format!("sponsors.{}", sponsor.to_hex_string()),
+                "must be between 0 and 1",
+            );
+        }
+    }
+}
+
+/// Decides which requests are traced. Nothing is traced when the sampling is not configured.
+#[derive(Clone, Default)]
+pub struct TraceSampling {
+    configuration: Option<TraceSamplingConfiguration>,
+}
+
+impl TraceSampling {
+    pub fn new(configuration: Option<&TraceSamplingConfiguration>) -> Self {
+        Self {
+            configuration: configuration.cloned(),
+        }
+    }
+
+    /// Returns the fraction of the requests made with the given api key which are traced
+    pub fn rate(&self, api_key: Option<&str>) -> f64 {
+        let Some(configuration) = &self.configuration else {
+            return 0.0;
+        };
+
+        api_key
+            .and_then(|x| configuration.sponsors.get(&starknet_keccak(x.as_bytes())))
+            .copied()
+            .unwrap_or(configuration.default_rate)
+    }
+
+    /// Start the trace of a request, its payload is only captured when the request is sampled or may be traced
+    /// if it fails
+    pub fn start<T: Serialize>(&self, api_key: Option<&str>, request: &T) -> RequestTrace {
+        let failed_requests = self.configuration.as_ref().is_some_and(|x| x.failed_requests);
+
+        let rate = self.rate(api_key);
+        let sampled = rate >= 1.0 || (rate > 0.0 && rand::random::<f64>() < rate);
+
+        RequestTrace {
+            sampled,
+            failed_requests,
+            payload: (sampled || failed_requests).then(|| serde_json::to_value(request).unwrap_or_default()),
+        }
+    }
+}
+
+/// Trace of a request in progress
+pub struct RequestTrace {
+    sampled: bool,
+    failed_requests: bool,
+    payload: Option<Value>,
+}
+
+impl RequestTrace {
+    /// Returns true if the request is traced given its outcome
+    pub fn is_traced(&self, failed: bool) -> bool {
+        self.sampled || (failed && self.failed_requests)
+    }
+
+    /// Log the payload of the request with its response or error when it is traced
+    pub fn finish<T: Serialize, E: std::fmt::Display>(self, result: &Result<T, E>) {
+        if !self.is_traced(result.is_err()) {
+            return;
+        }
+
+        let payload = self.payload.unwrap_or_default();
+        match result {
+            Ok(response) => {
+                let response = serde_json::to_value(response).unwrap_or_default();
+                info!(target: TRACE_TARGET, sampled = self.sampled, payload = %payload, response = %response, "request traced");
+            },
+            Err(error) => {
+                warn!(target: TRACE_TARGET, sampled = self.sampled, payload = %payload, error = %error, "request traced");
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use paymaster_common::validation::Validate;
+    use starknet::core::utils::starknet_keccak;
+
+    use crate::context::trace_sampling::{TraceSampling, TraceSamplingConfiguration};
+
+    fn sampling(default_rate: f64, failed_requests: bool) -> TraceSampling {
+        TraceSampling::new(Some(&TraceSamplingConfiguration {
+            default_rate,
+            sponsors: HashMap::from([(starknet_keccak(b"sponsor"), 1.0)]),
+            failed_requests,
+        }))
+    }
+
+    #[test]
+    fn sponsors_use_their_own_rate() {
+        let sampling = sampling(0.1, false);
+
+        assert_eq!(sampling.rate(Some("sponsor")), 1.0);
+        assert_eq!(sampling.rate(Some("other")), 0.1);
+        assert_eq!(sampling.rate(None), 0.1);
+        assert_eq!(TraceSampling::default().rate(Some("sponsor")), 0.0);
+    }
+
+    #[test]
+    fn failed_requests_are_traced_when_configured() {
+        let trace = sampling(0.0, true).start(None, &"payload");
+        assert!(trace.is_traced(true));
+        assert!(!trace.is_traced(false));
+
+        let trace = sampling(0.0, false).start(None, &"payload");
+        assert!(!trace.is_traced(true));
+
+        let trace = sampling(0.0, false).start(Some("sponsor"), &"payload");
+        assert!(trace.is_traced(false));
+    }
+
+    #[test]
+    fn rates_are_validated() {
+        let configuration = TraceSamplingConfiguration {
+            default_rate: 1.5,
+            ..Default::default()
+        };
+
+        assert!(configuration.validate_all().is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use paymaster_starknet::transaction::PaymasterVersion;
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+
+#[cfg(feature = "server")]
+use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
+use crate::Error;
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountStatusRequest {
+    #[serde_as(as = "UfeHex")]
+    pub address: Felt,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+/// Version of the outside execution (SNIP-9) supported by an account
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutsideExecutionVersion {
+    V1,
+    V2,
+}
+
+impl From<PaymasterVersion> for OutsideExecutionVersion {
+    fn from(value: PaymasterVersion) -> Self {
+        match value {
+            PaymasterVersion::V1 => Self::V1,
+            PaymasterVersion::V2 => Self::V2,
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountStatusResponse {
+    #[serde_as(as = "UfeHex")]
+    pub address: Felt,
+
+    /// Whether the account is deployed. When it is not, the account must be deployed along with its first invoke
+    pub deployed: bool,
+
+    #[serde_as(as = "Option<UfeHex>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class_hash: Option<Felt>,
+
+    /// Whether the class of the account supports the outside execution
+    pub supported: bool,
+
+    /// Highest SNIP-9 version supported by the account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outside_execution_version: Option<OutsideExecutionVersion>,
+
+    /// Whether the account is a Cartridge Controller
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub controller: bool,
+}
+
+#[cfg(feature = "server")]
+impl From<paymaster_execution::AccountStatus> for AccountStatusResponse {
+    fn from(value: paymaster_execution::AccountStatus) -> Self {
+        Self {
+            deployed: value.is_deployed(),
+            supported: value.is_supported(),
+
+            address: value.address,
+            class_hash: value.class_hash,
+            outside_execution_version: value.outside_execution_version.map(Into::into),
+            controller: value.controller,
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+pub async fn get_account_status_endpoint(ctx: &RequestContext<'_>, request: AccountStatusRequest) -> Result<AccountStatusResponse, Error> {
+    let status = ctx.execution.fetch_account_status(request.address).await?;
+
+    Ok(status.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use paymaster_execution::AccountStatus;
+    use paymaster_starknet::transaction::PaymasterVersion;
+    use serde_json::json;
+    use starknet::core::types::Felt;
+
+    use crate::endpoint::account::AccountStatusResponse;
+
+    #[test]
+    fn status_of_undeployed_account_omits_class() {
+        let response: AccountStatusResponse = AccountStatus {
+            address: Felt::ONE,
+            class_hash: None,
+            outside_execution_version: None,
+            controller: false,
+        }
+        .into();
+
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(value, json!({ "address": "0x1", "deployed": false, "supported": false }));
+    }
+
+    #[test]
+    fn status_of_deployed_account_reports_version() {
+        let response: AccountStatusResponse = AccountStatus {
+            address: Felt::ONE,
+            class_hash: Some(Felt::TWO),
+            outside_execution_version: Some(PaymasterVersion::V2),
+            controller: false,
+        }
+        .into();
+
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            value,
+            json!({ "address": "0x1", "deployed": true, "class_hash": "0x2", "supported": true, "outside_execution_version": "v2" })
+        );
+    }
+
+    #[test]
+    fn status_of_controller_account_reports_it() {
+        let response: AccountStatusResponse = AccountStatus {
+            address: Felt::ONE,
+            class_hash: Some(Felt::TWO),
+            outside_execution_version: Some(PaymasterVersion::V2),
+            controller: true,
+        }
+        .into();
+
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(value["controller"], json!(true));
+    }
+}
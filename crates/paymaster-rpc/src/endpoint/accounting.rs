@@ -0,0 +1,98 @@
+#[cfg(feature = "server")]
+use paymaster_relayer::accounting::{AccountBalance as Balance, AccountRole as Role, AccountingSnapshot};
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+
+#[cfg(feature = "server")]
+use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
+use crate::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AccountingSnapshotRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountRole {
+    GasTank,
+    Relayer,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountBalance {
+    #[serde_as(as = "UfeHex")]
+    pub address: Felt,
+
+    pub role: AccountRole,
+
+    #[serde_as(as = "UfeHex")]
+    pub token: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub balance: Felt,
+}
+
+#[cfg(feature = "server")]
+impl From<Balance> for AccountBalance {
+    fn from(value: Balance) -> Self {
+        Self {
+            address: value.address,
+            role: match value.role {
+                Role::GasTank => AccountRole::GasTank,
+                Role::Relayer => AccountRole::Relayer,
+            },
+            token: value.token,
+            balance: value.balance,
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountingSnapshotResponse {
+    /// Block at which every balance was read
+    pub block_number: u64,
+
+    /// Unix timestamp in seconds
+    pub taken_at: u64,
+
+    pub balances: Vec<AccountBalance>,
+
+    /// STRK token of the chain, in which the relayers pay the fees
+    #[serde_as(as = "UfeHex")]
+    pub fee_token: Felt,
+
+    /// Estimated fee of the relayer transactions submitted by the instance but not included at the block
+    #[serde_as(as = "UfeHex")]
+    pub in_flight_cost_in_strk: Felt,
+}
+
+#[cfg(feature = "server")]
+impl From<AccountingSnapshot> for AccountingSnapshotResponse {
+    fn from(value: AccountingSnapshot) -> Self {
+        Self {
+            block_number: value.block_number,
+            taken_at: value.taken_at,
+            balances: value.balances.into_iter().map(AccountBalance::from).collect(),
+            fee_token: value.fee_token,
+            in_flight_cost_in_strk: value.in_flight_cost,
+        }
+    }
+}
+
+/// Returns the balances of the gas tank and of the relayers at the latest block. Requires the admin api key of the instance.
+#[cfg(feature = "server")]
+pub async fn get_accounting_snapshot_endpoint(ctx: &RequestContext<'_>, _request: AccountingSnapshotRequest) -> Result<AccountingSnapshotResponse, Error> {
+    ctx.validate_admin_api_key()?;
+
+    let snapshot = ctx.execution.get_relayer_manager().accounting_snapshot().await?;
+
+    Ok(snapshot.into())
+}
@@ -0,0 +1,156 @@
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+
+#[cfg(feature = "server")]
+use crate::context::{PendingExecution, PendingRequest};
+#[cfg(feature = "server")]
+use crate::endpoint::dead_letter::retain_typed_data;
+#[cfg(feature = "server")]
+use crate::endpoint::execute::execute_approved_endpoint;
+use crate::endpoint::execute::ExecuteResponse;
+#[cfg(feature = "server")]
+use crate::endpoint::execute_raw::execute_direct_approved_endpoint;
+#[cfg(feature = "server")]
+use crate::endpoint::{APIKey, RequestContext};
+#[cfg(feature = "server")]
+use crate::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PendingApprovalsRequest {
+    /// Maximum number of entries returned, the most recent first. Defaults to 100
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingApprovalEntry {
+    /// Identifier of the entry in the queue
+    pub id: String,
+
+    #[serde_as(as = "UfeHex")]
+    pub user_address: Felt,
+
+    /// Fingerprint of the api key of the sponsor, the key itself is never returned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sponsor: Option<String>,
+
+    /// Fee estimated when the execution was parked
+    #[serde_as(as = "UfeHex")]
+    pub fee_in_strk: Felt,
+
+    /// Threshold exceeded by the execution
+    pub reason: String,
+
+    /// Unix timestamp in seconds
+    pub parked_at: u64,
+}
+
+#[cfg(feature = "server")]
+impl PendingApprovalEntry {
+    fn new(id: String, execution: &PendingExecution) -> Self {
+        Self {
+            id,
+            user_address: execution.request.user_address(),
+            sponsor: execution.sponsor.clone(),
+            fee_in_strk: execution.fee_in_strk,
+            reason: execution.reason.clone(),
+            parked_at: execution.parked_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingApprovalsResponse {
+    pub entries: Vec<PendingApprovalEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApprovalRequest {
+    /// Identifier of the entry in the queue
+    pub id: String,
+
+    /// Api key of the sponsor of the entry, required to approve a sponsored execution on its behalf. Ignored when
+    /// rejecting the entry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sponsor_api_key: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+/// Returns the most recent sponsored executions waiting for an approval. Requires the admin api key of the instance.
+#[cfg(feature = "server")]
+pub async fn get_pending_approvals_endpoint(ctx: &RequestContext<'_>, request: PendingApprovalsRequest) -> Result<PendingApprovalsResponse, Error> {
+    ctx.validate_admin_api_key()?;
+
+    let entries = ctx
+        .approvals
+        .list(request.limit.unwrap_or(100))
+        .await
+        .map_err(Error::ApprovalQueue)?;
+
+    Ok(PendingApprovalsResponse {
+        entries: entries
+            .iter()
+            .map(|(id, execution)| PendingApprovalEntry::new(id.clone(), execution))
+            .collect(),
+    })
+}
+
+/// Execute the given entry on behalf of the sponsor who made the original request, whose api key must be provided
+/// again. The entry is removed from the queue, the execution is not parked again. Requires the admin api key of the
+/// instance.
+#[cfg(feature = "server")]
+pub async fn approve_execution_endpoint(ctx: &RequestContext<'_>, request: ApprovalRequest) -> Result<ExecuteResponse, Error> {
+    ctx.validate_admin_api_key()?;
+
+    let execution = ctx.approvals.get(&request.id).await.map_err(Error::ApprovalQueue)?;
+    let execution = execution.ok_or(Error::ApprovalNotFound)?;
+    let api_key = execution
+        .authenticate_sponsor(request.sponsor_api_key)
+        .map_err(|_| Error::InvalidAPIKey)?;
+
+    // Removing the entry first guarantees that concurrent approvals of the same entry cannot both execute it
+    if !ctx.approvals.remove(&request.id).await.map_err(Error::ApprovalQueue)? {
+        return Err(Error::ApprovalNotFound);
+    }
+
+    let sponsor_context = RequestContext::with_api_key(ctx, api_key.as_deref().map(APIKey::new));
+    match execution.request {
+        PendingRequest::Execute(request) => {
+            // The typed data was built by the paymaster, it must be known again for the request to be executed
+            retain_typed_data(ctx, &request.transaction).await;
+
+            execute_approved_endpoint(&sponsor_context, request).await
+        },
+        PendingRequest::ExecuteDirect(request) => {
+            let response = execute_direct_approved_endpoint(&sponsor_context, request).await?;
+
+            Ok(ExecuteResponse {
+                transaction_hash: response.transaction_hash,
+                tracking_id: response.tracking_id,
+                tip: response.tip,
+                timings: None,
+                finality: None,
+            })
+        },
+    }
+}
+
+/// Remove the given entry from the queue without executing it. Requires the admin api key of the instance.
+#[cfg(feature = "server")]
+pub async fn reject_execution_endpoint(ctx: &RequestContext<'_>, request: ApprovalRequest) -> Result<bool, Error> {
+    ctx.validate_admin_api_key()?;
+
+    match ctx.approvals.remove(&request.id).await.map_err(Error::ApprovalQueue)? {
+        true => Ok(true),
+        false => Err(Error::ApprovalNotFound),
+    }
+}
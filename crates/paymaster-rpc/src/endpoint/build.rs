@@ -1,21 +1,48 @@
-use std::collections::HashSet;
+#[cfg(feature = "server")]
+use std::time::Instant;
 
 use jsonrpsee::core::Serialize;
-use paymaster_execution::Transaction;
+#[cfg(feature = "server")]
+use paymaster_execution::analytics::AnalyticsEventKind;
+#[cfg(feature = "server")]
+use paymaster_execution::stage::{measure_stage, Stage};
+#[cfg(feature = "server")]
+use paymaster_execution::{FeeQuote, Transaction};
+#[cfg(feature = "server")]
 use paymaster_starknet::transaction::Calls;
+use paymaster_starknet::ChainID;
 use serde::Deserialize;
+use starknet::core::crypto::{compute_hash_on_elements, Signature};
+#[cfg(feature = "server")]
+use starknet::core::types::ContractExecutionError;
 use starknet::core::types::{Call, Felt, TypedData};
-
-use crate::context::Context;
-use crate::endpoint::common::{DeploymentParameters, ExecutionParameters};
-use crate::endpoint::validation::{check_is_allowed_fee_mode, check_is_supported_token, check_no_blacklisted_call, check_service_is_available};
+use starknet::core::utils::starknet_keccak;
+use starknet::macros::short_string;
+#[cfg(feature = "server")]
+use starknet::signers::SigningKey;
+use starknet::signers::VerifyingKey;
+
+#[cfg(feature = "server")]
+use crate::context::{BuildCache, Context, TYPED_DATA_RETENTION};
+use crate::endpoint::common::{AppliedTip, DeploymentParameters, ExecutionParameters, FeeMode, TipPriority};
+#[cfg(feature = "server")]
+use crate::endpoint::validation::{
+    check_calls_in_scope, check_is_allowed_fee_mode, check_is_supported_token, check_no_blacklisted_call, check_no_call_to_operator, check_no_suspicious_approval,
+    check_service_is_available, check_transaction_is_well_formed, check_transfers_within_limits,
+};
+#[cfg(feature = "server")]
 use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
 use crate::Error;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BuildTransactionRequest {
     pub transaction: TransactionParameters,
     pub parameters: ExecutionParameters,
+
+    /// Chain on which the request must be executed. When not provided, the request is routed to the principal chain of the instance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -26,6 +53,7 @@ pub enum TransactionParameters {
     DeployAndInvoke { deployment: DeploymentParameters, invoke: InvokeParameters },
 }
 
+#[cfg(feature = "server")]
 impl From<TransactionParameters> for paymaster_execution::TransactionParameters {
     fn from(value: TransactionParameters) -> Self {
         match value {
@@ -40,6 +68,14 @@ impl From<TransactionParameters> for paymaster_execution::TransactionParameters
 }
 
 impl TransactionParameters {
+    pub fn user_address(&self) -> Felt {
+        match self {
+            Self::Deploy { deployment } => deployment.address,
+            Self::Invoke { invoke } => invoke.user_address,
+            Self::DeployAndInvoke { invoke, .. } => invoke.user_address,
+        }
+    }
+
     pub fn calls(&self) -> &[Call] {
         match self {
             Self::Deploy { .. } => &[],
@@ -53,13 +89,19 @@ impl TransactionParameters {
 pub struct InvokeParameters {
     pub user_address: Felt,
     pub calls: Vec<Call>,
+
+    /// Set when the transaction will be signed with a session key
+    #[serde(default)]
+    pub session: bool,
 }
 
+#[cfg(feature = "server")]
 impl From<InvokeParameters> for paymaster_execution::InvokeParameters {
     fn from(value: InvokeParameters) -> Self {
         Self {
             user_address: value.user_address,
             calls: Calls::new(value.calls),
+            session: value.session,
         }
     }
 }
@@ -72,11 +114,181 @@ pub enum BuildTransactionResponse {
     DeployAndInvoke(DeployAndInvokeTransaction),
 }
 
+/// Domain of the digest signed by the paymaster, keeping the signature from being valid for another payload
+const RESPONSE_SIGNATURE_DOMAIN: Felt = short_string!("paymaster_build_response");
+
+impl BuildTransactionResponse {
+    pub fn fee(&self) -> &FeeEstimate {
+        match self {
+            Self::Deploy(x) => &x.fee,
+            Self::Invoke(x) => &x.fee,
+            Self::DeployAndInvoke(x) => &x.fee,
+        }
+    }
+
+    pub fn parameters(&self) -> &ExecutionParameters {
+        match self {
+            Self::Deploy(x) => &x.parameters,
+            Self::Invoke(x) => &x.parameters,
+            Self::DeployAndInvoke(x) => &x.parameters,
+        }
+    }
+
+    pub fn signature(&self) -> Option<&ResponseSignature> {
+        match self {
+            Self::Deploy(x) => x.signature.as_ref(),
+            Self::Invoke(x) => x.signature.as_ref(),
+            Self::DeployAndInvoke(x) => x.signature.as_ref(),
+        }
+    }
+
+    /// Returns the digest signed by the paymaster. It covers the execution parameters, the fee and its tip, the
+    /// deployment and the message hash, which is itself bound to the typed data by [`verify_signature`].
+    pub fn digest(&self, user_address: Felt) -> Felt {
+        let (deployment, message_hash) = match self {
+            Self::Deploy(x) => (Some(&x.deployment), Felt::ZERO),
+            Self::Invoke(x) => (None, x.message_hash),
+            Self::DeployAndInvoke(x) => (Some(&x.deployment), x.message_hash),
+        };
+
+        let mut elements = vec![RESPONSE_SIGNATURE_DOMAIN, user_address, message_hash];
+        match deployment {
+            Some(deployment) => elements.extend([
+                deployment.address,
+                deployment.class_hash,
+                deployment.salt,
+                compute_hash_on_elements(&deployment.calldata),
+            ]),
+            None => elements.extend([Felt::ZERO; 4]),
+        }
+
+        let fee = self.fee();
+        elements.extend([
+            fee.gas_token_price_in_strk,
+            fee.estimated_fee_in_strk,
+            fee.estimated_fee_in_gas_token,
+            fee.suggested_max_fee_in_strk,
+            fee.suggested_max_fee_in_gas_token,
+        ]);
+        elements.extend(tip_elements(fee.tip.as_ref()));
+        elements.extend(parameters_elements(self.parameters()));
+
+        compute_hash_on_elements(&elements)
+    }
+
+    /// Sign the response for the given user with the key of the paymaster
+    #[cfg(feature = "server")]
+    pub fn sign(mut self, signing_key: &SigningKey, user_address: Felt) -> Result<Self, Error> {
+        let signature = signing_key
+            .sign(&self.digest(user_address))
+            .map_err(|e| Error::Execution(ContractExecutionError::Message(format!("could not sign the response: {}", e))))?;
+        let signature = Some(ResponseSignature {
+            public_key: signing_key.verifying_key().scalar(),
+            r: signature.r,
+            s: signature.s,
+        });
+
+        match &mut self {
+            Self::Deploy(x) => x.signature = signature,
+            Self::Invoke(x) => x.signature = signature,
+            Self::DeployAndInvoke(x) => x.signature = signature,
+        }
+
+        Ok(self)
+    }
+
+    /// Returns true if the response built for the given user is signed with the key whose public key is given, and
+    /// its typed data matches the message hash signed. Fails for the unsigned responses.
+    pub fn verify_signature(&self, public_key: Felt, user_address: Felt) -> bool {
+        let Some(signature) = self.signature() else { return false };
+        if signature.public_key != public_key {
+            return false;
+        }
+
+        let typed_data = match self {
+            Self::Deploy(_) => None,
+            Self::Invoke(x) => Some((&x.typed_data, x.message_hash)),
+            Self::DeployAndInvoke(x) => Some((&x.typed_data, x.message_hash)),
+        };
+        if let Some((typed_data, message_hash)) = typed_data {
+            if !typed_data.message_hash(user_address).is_ok_and(|x| x == message_hash) {
+                return false;
+            }
+        }
+
+        let signature = Signature { r: signature.r, s: signature.s };
+        VerifyingKey::from_scalar(public_key)
+            .verify(&self.digest(user_address), &signature)
+            .unwrap_or(false)
+    }
+}
+
+// Encodes the execution parameters covered by the digest of a response
+fn parameters_elements(parameters: &ExecutionParameters) -> Vec<Felt> {
+    let ExecutionParameters::V1 { fee_mode, time_bounds } = parameters;
+
+    let (mode, gas_token, tip) = match fee_mode {
+        FeeMode::Default { gas_token, tip } => (Felt::ZERO, *gas_token, tip),
+        FeeMode::Sponsored { tip } => (Felt::ONE, Felt::ZERO, tip),
+    };
+
+    let mut elements = vec![Felt::ONE, mode, gas_token];
+    elements.extend(priority_elements(tip));
+    match time_bounds {
+        Some(bounds) => elements.extend([Felt::ONE, Felt::from(bounds.execute_after), Felt::from(bounds.execute_before)]),
+        None => elements.extend([Felt::ZERO; 3]),
+    }
+
+    elements
+}
+
+// Encodes the tip of a fee estimate covered by the digest of a response
+fn tip_elements(tip: Option<&AppliedTip>) -> [Felt; 7] {
+    match tip {
+        Some(tip) => {
+            let [priority, custom] = priority_elements(&tip.priority);
+            [
+                Felt::ONE,
+                priority,
+                custom,
+                starknet_keccak(tip.strategy.as_bytes()),
+                if tip.median_tip.is_some() { Felt::ONE } else { Felt::ZERO },
+                Felt::from(tip.median_tip.unwrap_or_default()),
+                Felt::from(tip.tip),
+            ]
+        },
+        None => [Felt::ZERO; 7],
+    }
+}
+
+fn priority_elements(priority: &TipPriority) -> [Felt; 2] {
+    match priority {
+        TipPriority::Slow => [Felt::ZERO, Felt::ZERO],
+        TipPriority::Normal => [Felt::ONE, Felt::ZERO],
+        TipPriority::Fast => [Felt::TWO, Felt::ZERO],
+        TipPriority::Custom(tip) => [Felt::THREE, Felt::from(*tip)],
+    }
+}
+
+/// Signature of a build response by the paymaster, so that the services relaying the quotes to the users can prove
+/// that they were not tampered with in transit
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ResponseSignature {
+    /// Public key of the paymaster
+    pub public_key: Felt,
+    pub r: Felt,
+    pub s: Felt,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct DeployTransaction {
     pub deployment: DeploymentParameters,
     pub parameters: ExecutionParameters,
     pub fee: FeeEstimate,
+
+    /// Set when the paymaster is configured to sign its responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ResponseSignature>,
 }
 
 impl From<DeployTransaction> for BuildTransactionResponse {
@@ -88,8 +300,16 @@ impl From<DeployTransaction> for BuildTransactionResponse {
 #[derive(Serialize, Deserialize, Clone)]
 pub struct InvokeTransaction {
     pub typed_data: TypedData,
+
+    /// Hash of the typed data which can be given instead of the typed data when executing the transaction
+    pub message_hash: Felt,
+
     pub parameters: ExecutionParameters,
     pub fee: FeeEstimate,
+
+    /// Set when the paymaster is configured to sign its responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ResponseSignature>,
 }
 
 impl From<InvokeTransaction> for BuildTransactionResponse {
@@ -102,8 +322,16 @@ impl From<InvokeTransaction> for BuildTransactionResponse {
 pub struct DeployAndInvokeTransaction {
     pub deployment: DeploymentParameters,
     pub typed_data: TypedData,
+
+    /// Hash of the typed data which can be given instead of the typed data when executing the transaction
+    pub message_hash: Felt,
+
     pub parameters: ExecutionParameters,
     pub fee: FeeEstimate,
+
+    /// Set when the paymaster is configured to sign its responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ResponseSignature>,
 }
 
 impl From<DeployAndInvokeTransaction> for BuildTransactionResponse {
@@ -119,8 +347,17 @@ pub struct FeeEstimate {
     pub estimated_fee_in_gas_token: Felt,
     pub suggested_max_fee_in_strk: Felt,
     pub suggested_max_fee_in_gas_token: Felt,
+
+    /// Tip applied to the transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tip: Option<AppliedTip>,
+
+    /// Set when the fee was estimated in low-RPC mode, the estimate may have been reused and the max fee is wider
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub low_rpc: bool,
 }
 
+#[cfg(feature = "server")]
 impl From<paymaster_execution::FeeEstimate> for FeeEstimate {
     fn from(value: paymaster_execution::FeeEstimate) -> Self {
         Self {
@@ -131,24 +368,90 @@ impl From<paymaster_execution::FeeEstimate> for FeeEstimate {
 
             suggested_max_fee_in_strk: value.suggested_max_fee_in_strk,
             suggested_max_fee_in_gas_token: value.suggested_max_fee_in_gas_token,
+
+            tip: Some(value.tip.into()),
+            low_rpc: value.low_rpc,
         }
     }
 }
 
+#[cfg(feature = "server")]
 pub async fn build_transaction_endpoint(ctx: &RequestContext<'_>, request: BuildTransactionRequest) -> Result<BuildTransactionResponse, Error> {
-    check_service_is_available(ctx).await?;
-    check_is_allowed_fee_mode(ctx, &request.parameters).await?;
+    let started_at = Instant::now();
+    let cache_key = BuildCache::key(ctx.api_key.as_deref(), &request);
+
+    let request = measure_stage("build", Stage::Validation, async {
+        check_service_is_available(ctx).await?;
+        let api_key = check_is_allowed_fee_mode(ctx, &request.parameters).await?;
+
+        // The calls added or rewritten by the hooks go through the same checks as the calls of the user
+        let request = apply_call_hooks(ctx, request).await?;
+
+        // Do preliminary checks
+        check_transaction_is_well_formed(&request.transaction)?;
+        check_no_blacklisted_call(&request.transaction, &ctx.configuration.rpc.call_policy)?;
+        if let Some(api_key) = &api_key {
+            check_calls_in_scope(api_key, &ctx.configuration.rpc.call_policy, Some(request.transaction.calls()))?;
+            check_transfers_within_limits(&ctx.configuration.rpc.call_policy, Some(request.transaction.calls()))?;
+            check_no_suspicious_approval(api_key, &ctx.configuration.rpc.call_policy, Some(request.transaction.calls()))?;
+            check_no_call_to_operator(&ctx.configuration, Some(request.transaction.calls()))?;
+        }
+        check_is_supported_token(&request.parameters, ctx.execution.starknet.tokens(), &ctx.configuration.supported_tokens)?;
 
-    // Do preliminary checks
-    check_no_blacklisted_call(&request.transaction, &HashSet::new())?;
-    check_is_supported_token(&request.parameters, &ctx.configuration.supported_tokens)?;
+        Ok(request)
+    })
+    .await?;
+
+    if let Some(response) = ctx.builds.get(&ctx.execution, cache_key) {
+        return Ok(response);
+    }
+
+    let user = request.transaction.user_address();
+    let gas_token = request.parameters.gas_token(ctx.execution.starknet.tokens());
+    let is_sponsored = request.parameters.fee_mode().is_sponsored();
+
+    let response = match &request.transaction {
+        TransactionParameters::Deploy { .. } if is_sponsored => build_deploy_sponsored(ctx, request).await?,
+        _ => build_transaction(ctx, request).await?,
+    };
+    let response = match ctx.configuration.rpc.response_signing_key {
+        Some(key) => response.sign(&SigningKey::from_secret_scalar(key), user)?,
+        None => response,
+    };
+    ctx.builds.insert(cache_key, &response);
+
+    let quote = FeeQuote {
+        gas_token,
+        fee_in_token: response.fee().estimated_fee_in_gas_token,
+        fee_in_strk: response.fee().estimated_fee_in_strk,
+    };
+    ctx.analytics
+        .publish(ctx.analytics_event(AnalyticsEventKind::Build, user, quote, started_at, is_sponsored));
+
+    Ok(response)
+}
+
+// Let the hooks rewrite the calls of the user. The hooks specific to a sponsor only apply once its api key is validated
+#[cfg(feature = "server")]
+async fn apply_call_hooks(ctx: &RequestContext<'_>, mut request: BuildTransactionRequest) -> Result<BuildTransactionRequest, Error> {
+    let sponsor = match ctx.api_key.as_deref().map(|x| starknet_keccak(x.as_bytes())) {
+        Some(sponsor) if ctx.hooks.has_sponsor(sponsor) => {
+            ctx.validate_api_key().await?;
+            Some(sponsor)
+        },
+        _ => None,
+    };
 
-    match &request.transaction {
-        TransactionParameters::Deploy { .. } if request.parameters.fee_mode().is_sponsored() => build_deploy_sponsored(ctx, request).await,
-        _ => build_transaction(ctx, request).await,
+    if let TransactionParameters::Invoke { invoke } | TransactionParameters::DeployAndInvoke { invoke, .. } = &mut request.transaction {
+        invoke.calls = ctx
+            .hooks
+            .apply(sponsor, invoke.user_address, std::mem::take(&mut invoke.calls))?;
     }
+
+    Ok(request)
 }
 
+#[cfg(feature = "server")]
 async fn build_deploy_sponsored(ctx: &Context, request: BuildTransactionRequest) -> Result<BuildTransactionResponse, Error> {
     let deployment = match &request.transaction {
         TransactionParameters::Deploy { deployment } => deployment.clone(),
@@ -168,9 +471,11 @@ async fn build_deploy_sponsored(ctx: &Context, request: BuildTransactionRequest)
         deployment,
         parameters,
         fee: estimated_transaction.fee_estimate.into(),
+        signature: None,
     }))
 }
 
+#[cfg(feature = "server")]
 async fn build_transaction(ctx: &Context, request: BuildTransactionRequest) -> Result<BuildTransactionResponse, Error> {
     let transaction = Transaction {
         forwarder: ctx.configuration.forwarder,
@@ -184,25 +489,39 @@ async fn build_transaction(ctx: &Context, request: BuildTransactionRequest) -> R
     let typed_data = versioned_transaction.to_execute_from_outside().to_typed_data()?;
     let parameters = versioned_transaction.parameters.into();
 
+    // Keep the typed data so the payload signed by the user can be checked against it on execution
+    let message_hash = typed_data
+        .message_hash(versioned_transaction.transaction.user_address())
+        .map_err(paymaster_starknet::Error::from)?;
+    ctx.typed_data
+        .insert(message_hash, typed_data.clone(), TYPED_DATA_RETENTION)
+        .await;
+
     Ok(match versioned_transaction.transaction {
         paymaster_execution::TransactionParameters::Deploy { deployment } => DeployAndInvokeTransaction {
             deployment: deployment.into(),
             typed_data,
+            message_hash,
             parameters,
             fee: versioned_transaction.fee_estimate.into(),
+            signature: None,
         }
         .into(),
         paymaster_execution::TransactionParameters::Invoke { .. } => InvokeTransaction {
             typed_data,
+            message_hash,
             parameters,
             fee: versioned_transaction.fee_estimate.into(),
+            signature: None,
         }
         .into(),
         paymaster_execution::TransactionParameters::DeployAndInvoke { deployment, .. } => DeployAndInvokeTransaction {
             deployment: deployment.into(),
             typed_data,
+            message_hash,
             parameters,
             fee: versioned_transaction.fee_estimate.into(),
+            signature: None,
         }
         .into(),
     })
@@ -216,9 +535,12 @@ mod tests {
     use paymaster_starknet::testing::transaction::an_eth_transfer;
     use paymaster_starknet::testing::TestEnvironment as StarknetTestEnvironment;
     use starknet::core::types::Felt;
+    use starknet::signers::SigningKey;
 
-    use crate::endpoint::build::{build_transaction_endpoint, BuildTransactionRequest, InvokeParameters, TransactionParameters};
-    use crate::endpoint::common::{ExecutionParameters, FeeMode, TipPriority};
+    use crate::endpoint::build::{
+        build_transaction_endpoint, BuildTransactionRequest, BuildTransactionResponse, DeployTransaction, FeeEstimate, InvokeParameters, TransactionParameters,
+    };
+    use crate::endpoint::common::{AppliedTip, DeploymentParameters, ExecutionParameters, FeeMode, TimeBounds, TipPriority};
     use crate::endpoint::RequestContext;
     use crate::testing::TestEnvironment;
     use crate::Error;
@@ -259,6 +581,7 @@ mod tests {
                 invoke: InvokeParameters {
                     user_address: Felt::ZERO,
                     calls: vec![],
+                    session: false,
                 },
             },
             parameters: ExecutionParameters::V1 {
@@ -268,6 +591,7 @@ mod tests {
                 },
                 time_bounds: None,
             },
+            chain_id: None,
         };
 
         let result = build_transaction_endpoint(&request_context, request).await;
@@ -286,6 +610,7 @@ mod tests {
                 invoke: InvokeParameters {
                     user_address: Felt::ZERO,
                     calls: vec![],
+                    session: false,
                 },
             },
             parameters: ExecutionParameters::V1 {
@@ -295,6 +620,7 @@ mod tests {
                 },
                 time_bounds: None,
             },
+            chain_id: None,
         };
 
         let result = build_transaction_endpoint(&request_context, request).await;
@@ -313,6 +639,7 @@ mod tests {
                 invoke: InvokeParameters {
                     user_address: StarknetTestEnvironment::ACCOUNT_ARGENT_1.address,
                     calls: vec![an_eth_transfer(StarknetTestEnvironment::ACCOUNT_2.address, Felt::ONE)],
+                    session: false,
                 },
             },
             parameters: ExecutionParameters::V1 {
@@ -322,9 +649,169 @@ mod tests {
                 },
                 time_bounds: None,
             },
+            chain_id: None,
         };
 
         let result = build_transaction_endpoint(&request_context, request).await;
         assert!(result.is_ok())
     }
+
+    #[test]
+    fn signed_response_cannot_be_tampered_with() {
+        let signing_key = SigningKey::from_secret_scalar(Felt::from(0x1234));
+        let public_key = signing_key.verifying_key().scalar();
+        let user = Felt::from(0x42);
+
+        let response = BuildTransactionResponse::Deploy(DeployTransaction {
+            deployment: DeploymentParameters {
+                address: user,
+                class_hash: Felt::ONE,
+                salt: Felt::TWO,
+                calldata: vec![Felt::THREE],
+                sigdata: None,
+                version: 1,
+            },
+            parameters: ExecutionParameters::V1 {
+                fee_mode: FeeMode::Sponsored { tip: TipPriority::Normal },
+                time_bounds: None,
+            },
+            fee: FeeEstimate {
+                gas_token_price_in_strk: Felt::ONE,
+                estimated_fee_in_strk: Felt::from(100),
+                estimated_fee_in_gas_token: Felt::from(100),
+                suggested_max_fee_in_strk: Felt::from(150),
+                suggested_max_fee_in_gas_token: Felt::from(150),
+                tip: None,
+                low_rpc: false,
+            },
+            signature: None,
+        });
+        assert!(!response.verify_signature(public_key, user));
+
+        let signed = response.sign(&signing_key, user).unwrap();
+        assert!(signed.verify_signature(public_key, user));
+        assert!(!signed.verify_signature(Felt::from(0x5678), user));
+        assert!(!signed.verify_signature(public_key, Felt::from(0x43)));
+
+        let BuildTransactionResponse::Deploy(mut tampered) = signed else { unreachable!() };
+        tampered.fee.suggested_max_fee_in_gas_token = Felt::from(1_000);
+        assert!(!BuildTransactionResponse::Deploy(tampered).verify_signature(public_key, user));
+    }
+
+    #[test]
+    fn signed_parameters_and_tip_cannot_be_tampered_with() {
+        let signing_key = SigningKey::from_secret_scalar(Felt::from(0x1234));
+        let public_key = signing_key.verifying_key().scalar();
+        let user = Felt::from(0x42);
+
+        let response = DeployTransaction {
+            deployment: DeploymentParameters {
+                address: user,
+                class_hash: Felt::ONE,
+                salt: Felt::TWO,
+                calldata: vec![Felt::THREE],
+                sigdata: None,
+                version: 1,
+            },
+            parameters: ExecutionParameters::V1 {
+                fee_mode: FeeMode::Default {
+                    gas_token: Felt::from(0x99),
+                    tip: TipPriority::Normal,
+                },
+                time_bounds: Some(TimeBounds {
+                    execute_after: 100,
+                    execute_before: 200,
+                }),
+            },
+            fee: FeeEstimate {
+                gas_token_price_in_strk: Felt::ONE,
+                estimated_fee_in_strk: Felt::from(100),
+                estimated_fee_in_gas_token: Felt::from(100),
+                suggested_max_fee_in_strk: Felt::from(150),
+                suggested_max_fee_in_gas_token: Felt::from(150),
+                tip: Some(AppliedTip {
+                    priority: TipPriority::Normal,
+                    strategy: "median".to_string(),
+                    median_tip: Some(10),
+                    tip: 10,
+                }),
+                low_rpc: false,
+            },
+            signature: None,
+        };
+        let signed = BuildTransactionResponse::Deploy(response).sign(&signing_key, user).unwrap();
+        assert!(signed.verify_signature(public_key, user));
+        let BuildTransactionResponse::Deploy(signed) = signed else { unreachable!() };
+
+        let tamperings: Vec<fn(&mut DeployTransaction)> = vec![
+            |x| {
+                x.parameters = ExecutionParameters::V1 {
+                    fee_mode: FeeMode::Sponsored { tip: TipPriority::Normal },
+                    time_bounds: Some(TimeBounds {
+                        execute_after: 100,
+                        execute_before: 200,
+                    }),
+                }
+            },
+            |x| {
+                x.parameters = ExecutionParameters::V1 {
+                    fee_mode: FeeMode::Default {
+                        gas_token: Felt::from(0x98),
+                        tip: TipPriority::Normal,
+                    },
+                    time_bounds: Some(TimeBounds {
+                        execute_after: 100,
+                        execute_before: 200,
+                    }),
+                }
+            },
+            |x| {
+                x.parameters = ExecutionParameters::V1 {
+                    fee_mode: FeeMode::Default {
+                        gas_token: Felt::from(0x99),
+                        tip: TipPriority::Fast,
+                    },
+                    time_bounds: Some(TimeBounds {
+                        execute_after: 100,
+                        execute_before: 200,
+                    }),
+                }
+            },
+            |x| {
+                x.parameters = ExecutionParameters::V1 {
+                    fee_mode: FeeMode::Default {
+                        gas_token: Felt::from(0x99),
+                        tip: TipPriority::Normal,
+                    },
+                    time_bounds: Some(TimeBounds {
+                        execute_after: 100,
+                        execute_before: 300,
+                    }),
+                }
+            },
+            |x| {
+                x.parameters = ExecutionParameters::V1 {
+                    fee_mode: FeeMode::Default {
+                        gas_token: Felt::from(0x99),
+                        tip: TipPriority::Normal,
+                    },
+                    time_bounds: None,
+                }
+            },
+            |x| x.fee.tip.as_mut().unwrap().tip = 1_000,
+            |x| x.fee.tip.as_mut().unwrap().priority = TipPriority::Custom(10),
+            |x| x.fee.tip.as_mut().unwrap().median_tip = None,
+            |x| x.fee.tip = None,
+        ];
+        for (i, tamper) in tamperings.into_iter().enumerate() {
+            let mut tampered = signed.clone();
+            tamper(&mut tampered);
+
+            assert!(
+                !BuildTransactionResponse::Deploy(tampered).verify_signature(public_key, user),
+                "tampering {} is not detected",
+                i
+            );
+        }
+    }
 }
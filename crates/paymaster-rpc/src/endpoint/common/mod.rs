@@ -1,8 +1,11 @@
 use jsonrpsee::core::Serialize;
-use paymaster_starknet::constants::Token;
+use paymaster_starknet::constants::ChainTokens;
 use serde::Deserialize;
 use starknet::core::types::Felt;
 
+#[cfg(feature = "server")]
+use crate::Error;
+
 /// Deployment parameters required to deploy a contract
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DeploymentParameters {
@@ -14,6 +17,7 @@ pub struct DeploymentParameters {
     pub version: u8,
 }
 
+#[cfg(feature = "server")]
 impl From<paymaster_execution::DeploymentParameters> for DeploymentParameters {
     fn from(value: paymaster_execution::DeploymentParameters) -> Self {
         Self {
@@ -27,6 +31,7 @@ impl From<paymaster_execution::DeploymentParameters> for DeploymentParameters {
     }
 }
 
+#[cfg(feature = "server")]
 impl From<DeploymentParameters> for paymaster_execution::DeploymentParameters {
     fn from(value: DeploymentParameters) -> Self {
         Self {
@@ -41,6 +46,95 @@ impl From<DeploymentParameters> for paymaster_execution::DeploymentParameters {
     }
 }
 
+/// Session registered on a Cartridge Controller or on an Argent account
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Session {
+    pub expires_at: u64,
+    pub policies_root: Felt,
+    pub metadata_hash: Felt,
+    pub session_key_guid: Felt,
+
+    /// Guid of the guardian of the session, only part of the Cartridge Controller sessions
+    #[serde(default)]
+    pub guardian_key_guid: Option<Felt>,
+}
+
+#[cfg(feature = "server")]
+impl From<Session> for paymaster_starknet::transaction::Session {
+    fn from(value: Session) -> Self {
+        Self {
+            expires_at: value.expires_at,
+            policies_root: value.policies_root,
+            metadata_hash: value.metadata_hash,
+            session_key_guid: value.session_key_guid,
+            guardian_key_guid: value.guardian_key_guid,
+        }
+    }
+}
+
+/// Signature of a Starknet signer along with its public key
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct SignerSignature {
+    pub signer: Felt,
+    pub r: Felt,
+    pub s: Felt,
+}
+
+#[cfg(feature = "server")]
+impl From<SignerSignature> for paymaster_starknet::transaction::SignerSignature {
+    fn from(value: SignerSignature) -> Self {
+        Self::Starknet {
+            signer: value.signer,
+            r: value.r,
+            s: value.s,
+        }
+    }
+}
+
+/// Session authorizing the session key which signed a transaction on behalf of the account owner. The signature of
+/// the transaction is then the `[r, s]` signature of the session message hash by the session key.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionAuthorization {
+    pub session: Session,
+
+    /// Whether the account caches the authorization once checked
+    #[serde(default)]
+    pub cache_authorization: bool,
+
+    /// Signature of the session by the owner of the account
+    pub authorization: Vec<Felt>,
+
+    /// Public key of the session key
+    pub session_key: Felt,
+
+    /// Signature of the session message hash by the guardian of the session
+    pub guardian_signature: SignerSignature,
+
+    /// Merkle proofs that each call of the typed data is allowed by the session, in the order of the calls
+    pub proofs: Vec<Vec<Felt>>,
+}
+
+#[cfg(feature = "server")]
+impl SessionAuthorization {
+    /// Convert into the session token sent to the account, given the signature of the session key
+    pub fn into_session_token(self, signature: &[Felt]) -> Result<paymaster_starknet::transaction::SessionAuthorization, Error> {
+        let [r, s] = signature else { return Err(Error::InvalidSignature) };
+
+        Ok(paymaster_starknet::transaction::SessionAuthorization {
+            session: self.session.into(),
+            cache_authorization: self.cache_authorization,
+            authorization: self.authorization,
+            session_signature: paymaster_starknet::transaction::SignerSignature::Starknet {
+                signer: self.session_key,
+                r: *r,
+                s: *s,
+            },
+            guardian_signature: self.guardian_signature.into(),
+            proofs: self.proofs,
+        })
+    }
+}
+
 /// Execution parameters to use when executing the paymaster transaction.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "version")]
@@ -49,6 +143,7 @@ pub enum ExecutionParameters {
     V1 { fee_mode: FeeMode, time_bounds: Option<TimeBounds> },
 }
 
+#[cfg(feature = "server")]
 impl From<paymaster_execution::ExecutionParameters> for ExecutionParameters {
     fn from(value: paymaster_execution::ExecutionParameters) -> Self {
         match value {
@@ -60,6 +155,7 @@ impl From<paymaster_execution::ExecutionParameters> for ExecutionParameters {
     }
 }
 
+#[cfg(feature = "server")]
 impl From<ExecutionParameters> for paymaster_execution::ExecutionParameters {
     fn from(value: ExecutionParameters) -> Self {
         match value {
@@ -78,9 +174,9 @@ impl ExecutionParameters {
         }
     }
 
-    pub fn gas_token(&self) -> Felt {
+    pub fn gas_token(&self, tokens: &ChainTokens) -> Felt {
         match self {
-            Self::V1 { fee_mode, .. } => fee_mode.gas_token(),
+            Self::V1 { fee_mode, .. } => fee_mode.gas_token(tokens),
         }
     }
 
@@ -100,6 +196,7 @@ pub struct TimeBounds {
     pub execute_before: u64,
 }
 
+#[cfg(feature = "server")]
 impl From<paymaster_execution::TimeBounds> for TimeBounds {
     fn from(value: paymaster_execution::TimeBounds) -> Self {
         Self {
@@ -109,6 +206,7 @@ impl From<paymaster_execution::TimeBounds> for TimeBounds {
     }
 }
 
+#[cfg(feature = "server")]
 impl From<TimeBounds> for paymaster_execution::TimeBounds {
     fn from(value: TimeBounds) -> Self {
         Self {
@@ -144,6 +242,7 @@ pub enum FeeMode {
     },
 }
 
+#[cfg(feature = "server")]
 impl From<paymaster_execution::FeeMode> for FeeMode {
     fn from(value: paymaster_execution::FeeMode) -> Self {
         match value {
@@ -153,6 +252,7 @@ impl From<paymaster_execution::FeeMode> for FeeMode {
     }
 }
 
+#[cfg(feature = "server")]
 impl From<FeeMode> for paymaster_execution::FeeMode {
     fn from(value: FeeMode) -> Self {
         match value {
@@ -162,6 +262,7 @@ impl From<FeeMode> for paymaster_execution::FeeMode {
     }
 }
 
+#[cfg(feature = "server")]
 impl From<paymaster_execution::TipPriority> for TipPriority {
     fn from(value: paymaster_execution::TipPriority) -> Self {
         match value {
@@ -173,6 +274,7 @@ impl From<paymaster_execution::TipPriority> for TipPriority {
     }
 }
 
+#[cfg(feature = "server")]
 impl From<TipPriority> for paymaster_execution::TipPriority {
     fn from(value: TipPriority) -> Self {
         match value {
@@ -184,17 +286,42 @@ impl From<TipPriority> for paymaster_execution::TipPriority {
     }
 }
 
+/// Tip applied to a transaction along with the strategy used to derive it from the priority requested
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AppliedTip {
+    pub priority: TipPriority,
+    pub strategy: String,
+
+    /// Median tip of the recent blocks from which the tip was derived. Not set for custom tips.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub median_tip: Option<u64>,
+
+    pub tip: u64,
+}
+
+#[cfg(feature = "server")]
+impl From<paymaster_execution::AppliedTip> for AppliedTip {
+    fn from(value: paymaster_execution::AppliedTip) -> Self {
+        Self {
+            priority: value.priority.into(),
+            strategy: value.strategy().to_string(),
+            median_tip: value.median_tip,
+            tip: value.tip,
+        }
+    }
+}
+
 impl FeeMode {
     pub fn is_sponsored(&self) -> bool {
         matches!(self, Self::Sponsored { tip: _ })
     }
 
     /// Returns the gas token corresponding to the  [`FeeMode`]. In the case where the transaction is sponsored
-    /// the gas token is set as the STRK token
-    pub fn gas_token(&self) -> Felt {
+    /// the gas token is set as the STRK token of the chain
+    pub fn gas_token(&self, tokens: &ChainTokens) -> Felt {
         match self {
             Self::Default { gas_token, tip: _ } => *gas_token,
-            Self::Sponsored { tip: _ } => Token::STRK_ADDRESS,
+            Self::Sponsored { tip: _ } => tokens.strk,
         }
     }
 
@@ -0,0 +1,151 @@
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+#[cfg(feature = "server")]
+use starknet::core::utils::starknet_keccak;
+
+#[cfg(feature = "server")]
+use crate::context::{DeadLetter, TYPED_DATA_RETENTION};
+use crate::endpoint::execute::ExecuteResponse;
+#[cfg(feature = "server")]
+use crate::endpoint::execute::{execute_retried_endpoint, ExecutableTransactionParameters};
+#[cfg(feature = "server")]
+use crate::endpoint::{APIKey, RequestContext};
+#[cfg(feature = "server")]
+use crate::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DeadLettersRequest {
+    /// Maximum number of entries returned, the most recent first. Defaults to 100
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeadLetterEntry {
+    /// Identifier of the entry in the queue
+    pub id: String,
+
+    #[serde_as(as = "UfeHex")]
+    pub user_address: Felt,
+
+    /// Fingerprint of the api key of the sponsor, the key itself is never returned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sponsor: Option<String>,
+
+    pub error: String,
+
+    /// Unix timestamp in seconds
+    pub failed_at: u64,
+}
+
+#[cfg(feature = "server")]
+impl DeadLetterEntry {
+    fn new(id: String, letter: &DeadLetter) -> Self {
+        Self {
+            id,
+            user_address: letter.request.transaction.user_address(),
+            sponsor: letter
+                .api_key
+                .as_ref()
+                .map(|x| starknet_keccak(x.as_bytes()).to_fixed_hex_string()),
+            error: letter.error.clone(),
+            failed_at: letter.failed_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeadLettersResponse {
+    pub entries: Vec<DeadLetterEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeadLetterRequest {
+    /// Identifier of the entry in the queue
+    pub id: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+/// Returns the most recent sponsored executions which failed. Requires the admin api key of the instance.
+#[cfg(feature = "server")]
+pub async fn get_dead_letters_endpoint(ctx: &RequestContext<'_>, request: DeadLettersRequest) -> Result<DeadLettersResponse, Error> {
+    ctx.validate_admin_api_key()?;
+
+    let entries = ctx
+        .dead_letters
+        .list(request.limit.unwrap_or(100))
+        .await
+        .map_err(Error::DeadLetterQueue)?;
+
+    Ok(DeadLettersResponse {
+        entries: entries
+            .iter()
+            .map(|(id, letter)| DeadLetterEntry::new(id.clone(), letter))
+            .collect(),
+    })
+}
+
+/// Execute again the given entry on behalf of the sponsor who made the original request. The entry is removed from
+/// the queue, a new one is pushed if the execution fails again. Requires the admin api key of the instance.
+#[cfg(feature = "server")]
+pub async fn retry_dead_letter_endpoint(ctx: &RequestContext<'_>, request: DeadLetterRequest) -> Result<ExecuteResponse, Error> {
+    ctx.validate_admin_api_key()?;
+
+    let letter = ctx.dead_letters.get(&request.id).await.map_err(Error::DeadLetterQueue)?;
+    let letter = letter.ok_or(Error::DeadLetterNotFound)?;
+
+    // Removing the entry first guarantees that concurrent retries of the same entry cannot both execute it
+    if !ctx.dead_letters.remove(&request.id).await.map_err(Error::DeadLetterQueue)? {
+        return Err(Error::DeadLetterNotFound);
+    }
+
+    // The typed data was built by the paymaster, it must be known again for the request to be executed
+    retain_typed_data(ctx, &letter.request.transaction).await;
+
+    let sponsor_context = RequestContext::with_api_key(ctx, letter.api_key.as_deref().map(APIKey::new));
+    match execute_retried_endpoint(&sponsor_context, letter.request.clone()).await {
+        Ok(response) => Ok(response),
+        // Execution failures are pushed to the queue by the execution itself
+        Err(e @ (Error::Execution(_) | Error::DiagnosedExecution(..))) => Err(e),
+        Err(e) => {
+            ctx.dead_letters.push(&letter).await;
+            Err(e)
+        },
+    }
+}
+
+/// Remove the given entry from the queue without executing it. Requires the admin api key of the instance.
+#[cfg(feature = "server")]
+pub async fn discard_dead_letter_endpoint(ctx: &RequestContext<'_>, request: DeadLetterRequest) -> Result<bool, Error> {
+    ctx.validate_admin_api_key()?;
+
+    match ctx.dead_letters.remove(&request.id).await.map_err(Error::DeadLetterQueue)? {
+        true => Ok(true),
+        false => Err(Error::DeadLetterNotFound),
+    }
+}
+
+#[cfg(feature = "server")]
+pub(crate) async fn retain_typed_data(ctx: &RequestContext<'_>, transaction: &ExecutableTransactionParameters) {
+    let invoke = match transaction {
+        ExecutableTransactionParameters::Invoke { invoke } | ExecutableTransactionParameters::DeployAndInvoke { invoke, .. } => invoke,
+        ExecutableTransactionParameters::Deploy { .. } => return,
+    };
+
+    if let Some(typed_data) = &invoke.typed_data {
+        if let Ok(message_hash) = typed_data.message_hash(invoke.user_address) {
+            ctx.typed_data
+                .insert(message_hash, typed_data.clone(), TYPED_DATA_RETENTION)
+                .await;
+        }
+    }
+}
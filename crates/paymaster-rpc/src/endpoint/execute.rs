@@ -1,22 +1,132 @@
+#[cfg(feature = "server")]
+use std::time::Instant;
+
+#[cfg(feature = "server")]
+use paymaster_execution::analytics::AnalyticsEventKind;
+#[cfg(feature = "server")]
+use paymaster_execution::stage::{measure_stage, Stage};
+#[cfg(feature = "server")]
 use paymaster_execution::ExecutableTransaction;
-use paymaster_starknet::Signature;
+use paymaster_starknet::{ChainID, Signature};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
+use starknet::core::crypto::{compute_hash_on_elements, Signature as ECDSASignature};
 use starknet::core::serde::unsigned_field_element::UfeHex;
 use starknet::core::types::{Felt, TypedData};
-
-use crate::endpoint::common::{DeploymentParameters, ExecutionParameters};
-use crate::endpoint::validation::check_service_is_available;
+use starknet::macros::short_string;
+use starknet::signers::{SigningKey, VerifyingKey};
+
+#[cfg(feature = "server")]
+use crate::context::{Context, DeadLetter, PendingExecution, PendingRequest, QUOTE_RETENTION};
+use crate::endpoint::common::{AppliedTip, DeploymentParameters, ExecutionParameters, SessionAuthorization};
+#[cfg(feature = "server")]
+use crate::endpoint::validation::{
+    check_address_in_range, check_calls_in_scope, check_no_call_to_operator, check_no_suspicious_approval, check_not_in_maintenance, check_service_is_available,
+    check_transfers_within_limits, check_within_budget,
+};
+#[cfg(feature = "server")]
 use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
 use crate::Error;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct ExecuteRequest {
     pub transaction: ExecutableTransactionParameters,
     pub parameters: ExecutionParameters,
+
+    /// Guarantee to wait for before returning. Defaults to returning as soon as the transaction is submitted
+    #[serde(default)]
+    pub finality: FinalityLevel,
+
+    /// Chain on which the request must be executed. When not provided, the request is routed to the principal chain of the instance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+
+    /// Nonce signed by the client relaying the request, accepted once when the replay protection is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_nonce: Option<ClientNonce>,
+}
+
+/// Domain of the digest signed along with a client nonce
+const CLIENT_NONCE_DOMAIN: Felt = short_string!("paymaster_client_nonce");
+
+/// Nonce chosen by the client relaying an execute request, unrelated to the nonce of the account. Signed along with
+/// the transaction, it prevents a captured request from being executed again by replaying it over HTTP.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ClientNonce {
+    #[serde_as(as = "UfeHex")]
+    pub nonce: Felt,
+
+    /// Public key of the client, which must be known by the paymaster
+    #[serde_as(as = "UfeHex")]
+    pub public_key: Felt,
+
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub signature: Vec<Felt>,
+}
+
+/// Transaction of an execute request, to which a client nonce is bound by its signature
+pub trait ClientNonceBinding {
+    /// Returns the address of the account executing the transaction
+    fn user_address(&self) -> Felt;
+
+    /// Returns the hash identifying the transaction
+    fn binding(&self) -> Felt;
+}
+
+impl ClientNonce {
+    /// Sign the nonce along with the given transaction with the key of the client
+    pub fn sign(signing_key: &SigningKey, nonce: Felt, transaction: &impl ClientNonceBinding) -> Result<Self, String> {
+        let signature = signing_key.sign(&Self::digest(nonce, transaction)).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            nonce,
+            public_key: signing_key.verifying_key().scalar(),
+            signature: vec![signature.r, signature.s],
+        })
+    }
+
+    /// Returns true if the nonce was signed along with the given transaction by the key of the client
+    pub fn verify(&self, transaction: &impl ClientNonceBinding) -> bool {
+        let [r, s] = self.signature[..] else { return false };
+
+        VerifyingKey::from_scalar(self.public_key)
+            .verify(&Self::digest(self.nonce, transaction), &ECDSASignature { r, s })
+            .unwrap_or(false)
+    }
+
+    fn digest(nonce: Felt, transaction: &impl ClientNonceBinding) -> Felt {
+        compute_hash_on_elements(&[CLIENT_NONCE_DOMAIN, transaction.user_address(), transaction.binding(), nonce])
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FinalityLevel {
+    /// Return the hash of the transaction as soon as it is submitted
+    #[default]
+    Submitted,
+
+    /// Wait for the transaction to be part of a pre-confirmed block
+    PreConfirmed,
+
+    /// Wait for the transaction to be accepted on L2
+    AcceptedOnL2,
+}
+
+#[cfg(feature = "server")]
+impl From<FinalityLevel> for paymaster_execution::finality::FinalityLevel {
+    fn from(value: FinalityLevel) -> Self {
+        match value {
+            FinalityLevel::Submitted => Self::Submitted,
+            FinalityLevel::PreConfirmed => Self::PreConfirmed,
+            FinalityLevel::AcceptedOnL2 => Self::AcceptedOnL2,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ExecutableTransactionParameters {
     Deploy {
@@ -31,40 +141,134 @@ pub enum ExecutableTransactionParameters {
     },
 }
 
-impl TryFrom<ExecutableTransactionParameters> for paymaster_execution::ExecutableTransactionParameters {
-    type Error = Error;
+impl ExecutableTransactionParameters {
+    /// Returns the address of the account executing the transaction
+    pub fn user_address(&self) -> Felt {
+        match self {
+            Self::Deploy { deployment } | Self::DeployAndInvoke { deployment, .. } => deployment.address,
+            Self::Invoke { invoke } => invoke.user_address,
+        }
+    }
+}
 
-    fn try_from(value: ExecutableTransactionParameters) -> Result<Self, Self::Error> {
-        Ok(match value {
-            ExecutableTransactionParameters::Deploy { deployment } => Self::Deploy { deployment: deployment.into() },
-            ExecutableTransactionParameters::Invoke { invoke } => Self::Invoke { invoke: invoke.try_into()? },
-            ExecutableTransactionParameters::DeployAndInvoke { deployment, invoke } => Self::DeployAndInvoke {
+impl ClientNonceBinding for ExecutableTransactionParameters {
+    fn user_address(&self) -> Felt {
+        ExecutableTransactionParameters::user_address(self)
+    }
+
+    // Covers the deployment and the typed data of the invoke
+    fn binding(&self) -> Felt {
+        let deployment = |x: &DeploymentParameters| compute_hash_on_elements(&[x.address, x.class_hash, x.salt]);
+        let invoke = |x: &ExecutableInvokeParameters| {
+            x.message_hash
+                .or_else(|| x.typed_data.as_ref().and_then(|data| data.message_hash(x.user_address).ok()))
+                .unwrap_or_default()
+        };
+
+        match self {
+            Self::Deploy { deployment: x } => compute_hash_on_elements(&[deployment(x), Felt::ZERO]),
+            Self::Invoke { invoke: x } => compute_hash_on_elements(&[Felt::ZERO, invoke(x)]),
+            Self::DeployAndInvoke { deployment: x, invoke: y } => compute_hash_on_elements(&[deployment(x), invoke(y)]),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+impl ExecutableTransactionParameters {
+    /// Convert into the execution parameters, resolving the typed data of the invoke against the one built by the paymaster
+    async fn resolve(self, ctx: &Context) -> Result<paymaster_execution::ExecutableTransactionParameters, Error> {
+        Ok(match self {
+            Self::Deploy { deployment } => paymaster_execution::ExecutableTransactionParameters::Deploy { deployment: deployment.into() },
+            Self::Invoke { invoke } => paymaster_execution::ExecutableTransactionParameters::Invoke {
+                invoke: invoke.resolve(ctx).await?,
+            },
+            Self::DeployAndInvoke { deployment, invoke } => paymaster_execution::ExecutableTransactionParameters::DeployAndInvoke {
                 deployment: deployment.into(),
-                invoke: invoke.try_into()?,
+                invoke: invoke.resolve(ctx).await?,
             },
         })
     }
+
+    /// Embed the typed data built by the paymaster in the invoke so that the request can be executed again once the
+    /// typed data has expired from the cache
+    async fn with_typed_data(self, ctx: &Context) -> Self {
+        match self {
+            Self::Invoke { invoke } => Self::Invoke {
+                invoke: invoke.with_typed_data(ctx).await,
+            },
+            Self::DeployAndInvoke { deployment, invoke } => Self::DeployAndInvoke {
+                deployment,
+                invoke: invoke.with_typed_data(ctx).await,
+            },
+            deploy => deploy,
+        }
+    }
 }
 
 #[serde_as]
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct ExecutableInvokeParameters {
     #[serde_as(as = "UfeHex")]
     pub user_address: Felt,
 
-    pub typed_data: TypedData,
+    /// Typed data returned by `buildTransaction`. Can be omitted when `message_hash` is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub typed_data: Option<TypedData>,
+
+    /// Hash of the typed data returned by `buildTransaction`
+    #[serde_as(as = "Option<UfeHex>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_hash: Option<Felt>,
 
     #[serde_as(as = "Vec<UfeHex>")]
     pub signature: Signature,
+
+    /// Session which authorizes the key that produced the signature, for session-based accounts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionAuthorization>,
 }
 
-impl TryFrom<ExecutableInvokeParameters> for paymaster_execution::ExecutableInvokeParameters {
-    type Error = Error;
+#[cfg(feature = "server")]
+impl ExecutableInvokeParameters {
+    /// Convert into the execution parameters. The typed data must match exactly the one built by the paymaster,
+    /// which guarantees the calls were not tampered with between build and execution.
+    async fn resolve(self, ctx: &Context) -> Result<paymaster_execution::ExecutableInvokeParameters, Error> {
+        let typed_data = self.resolve_typed_data(ctx).await?;
+        let session = match self.session {
+            Some(session) => Some(session.into_session_token(&self.signature)?),
+            None => None,
+        };
+
+        let result = paymaster_execution::ExecutableInvokeParameters::new(self.user_address, typed_data, self.signature)?;
+        Ok(match session {
+            Some(session) => result.with_session(session),
+            None => result,
+        })
+    }
+
+    async fn resolve_typed_data(&self, ctx: &Context) -> Result<TypedData, Error> {
+        let message_hash = match (&self.typed_data, self.message_hash) {
+            (Some(typed_data), message_hash) => {
+                let hash = typed_data
+                    .message_hash(self.user_address)
+                    .map_err(paymaster_starknet::Error::from)?;
+                if message_hash.is_some_and(|x| x != hash) {
+                    return Err(Error::UnknownTypedData);
+                }
+
+                hash
+            },
+            (None, Some(message_hash)) => message_hash,
+            (None, None) => return Err(Error::UnknownTypedData),
+        };
+
+        ctx.typed_data.get(&message_hash).await?.ok_or(Error::UnknownTypedData)
+    }
 
-    fn try_from(value: ExecutableInvokeParameters) -> Result<Self, Self::Error> {
-        let result = Self::new(value.user_address, value.typed_data, value.signature)?;
+    async fn with_typed_data(self, ctx: &Context) -> Self {
+        let typed_data = self.resolve_typed_data(ctx).await.ok().or(self.typed_data.clone());
 
-        Ok(result)
+        Self { typed_data, ..self }
     }
 }
 
@@ -76,47 +280,267 @@ pub struct ExecuteResponse {
 
     #[serde_as(as = "UfeHex")]
     pub tracking_id: Felt,
+
+    /// Tip applied to the transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tip: Option<AppliedTip>,
+
+    /// Time spent in each stage of the submission. Only set when the debug diagnostics are enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<ExecutionTimings>,
+
+    /// State reached by the transaction when a finality other than `submitted` was requested. It is below the
+    /// requested finality when the transaction did not reach it in time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finality: Option<Finality>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FinalityStatus {
+    Submitted,
+    Received,
+    Candidate,
+    PreConfirmed,
+    AcceptedOnL2,
+    AcceptedOnL1,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Finality {
+    pub status: FinalityStatus,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+}
+
+#[cfg(feature = "server")]
+impl From<paymaster_execution::finality::Finality> for Finality {
+    fn from(value: paymaster_execution::finality::Finality) -> Self {
+        use paymaster_execution::finality::FinalityStatus as Status;
+
+        Self {
+            status: match value.status {
+                Status::Submitted => FinalityStatus::Submitted,
+                Status::Received => FinalityStatus::Received,
+                Status::Candidate => FinalityStatus::Candidate,
+                Status::PreConfirmed => FinalityStatus::PreConfirmed,
+                Status::AcceptedOnL2 => FinalityStatus::AcceptedOnL2,
+                Status::AcceptedOnL1 => FinalityStatus::AcceptedOnL1,
+            },
+            revert_reason: value.revert_reason,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct ExecutionTimings {
+    pub lock_acquisition_ms: u64,
+    pub submission_ms: u64,
+}
+
+#[cfg(feature = "server")]
+impl From<paymaster_execution::ExecutionTimings> for ExecutionTimings {
+    fn from(value: paymaster_execution::ExecutionTimings) -> Self {
+        Self {
+            lock_acquisition_ms: value.lock_acquisition_ms,
+            submission_ms: value.submission_ms,
+        }
+    }
+}
+
+#[cfg(feature = "server")]
 pub async fn execute_endpoint(ctx: &RequestContext<'_>, request: ExecuteRequest) -> Result<ExecuteResponse, Error> {
-    check_service_is_available(ctx).await?;
+    let client_nonce = ctx.replays.verify(request.client_nonce.as_ref(), &request.transaction)?;
+
+    execute(ctx, request, false, client_nonce.as_ref()).await
+}
+
+/// Execute again a request of the dead-letter queue, whose client nonce was consumed by the original request
+#[cfg(feature = "server")]
+pub async fn execute_retried_endpoint(ctx: &RequestContext<'_>, request: ExecuteRequest) -> Result<ExecuteResponse, Error> {
+    execute(ctx, request, false, None).await
+}
+
+/// Execute a request parked in the approval queue, once an operator approved it
+#[cfg(feature = "server")]
+pub async fn execute_approved_endpoint(ctx: &RequestContext<'_>, request: ExecuteRequest) -> Result<ExecuteResponse, Error> {
+    execute(ctx, request, true, None).await
+}
+
+/// The client nonce, if any, is consumed once the request is validated and estimated, right before it is either
+/// parked or sent
+#[cfg(feature = "server")]
+async fn execute(ctx: &RequestContext<'_>, request: ExecuteRequest, approved: bool, client_nonce: Option<&ClientNonce>) -> Result<ExecuteResponse, Error> {
+    let started_at = Instant::now();
+
+    measure_stage("execute", Stage::Validation, async {
+        check_address_in_range(request.transaction.user_address())?;
+        check_not_in_maintenance(ctx).await?;
+        check_service_is_available(ctx).await
+    })
+    .await?;
+
+    // Kept to push the request to the dead-letter queue if its sponsored execution fails
+    let dead_letter = ctx.dead_letters.is_enabled().then(|| request.clone());
+
+    // Kept to park the request until an operator approves it if its sponsored execution exceeds a threshold
+    let pending = (ctx.approvals.is_enabled() && !approved).then(|| request.clone());
 
     let forwarder = ctx.configuration.forwarder;
-    let gas_tank_address = ctx.configuration.gas_tank.address;
+    let finality = request.finality;
+    let parameters: paymaster_execution::ExecutionParameters = request.parameters.into();
+    let gas_tank_address = ctx.fee_recipient(parameters.gas_token(ctx.execution.starknet.tokens())).await?;
 
     let transaction = ExecutableTransaction {
         forwarder,
         gas_tank_address,
-        parameters: request.parameters.into(),
-        transaction: request.transaction.try_into()?,
+        parameters,
+        transaction: request.transaction.resolve(ctx).await?,
     };
 
     ctx.transaction_filter.filter(&transaction.transaction)?;
 
-    let estimated_transaction = if transaction.parameters.fee_mode().is_sponsored() {
-        let authenticated_api_key = ctx.validate_api_key().await?;
-        transaction
-            .estimate_sponsored_transaction(&ctx.execution, authenticated_api_key.sponsor_metadata)
-            .await?
-    } else {
-        transaction.estimate_transaction(&ctx.execution).await?
+    let is_sponsored = transaction.parameters.fee_mode().is_sponsored();
+    let user = transaction.transaction.user();
+    let fee_transfer = transaction.gas_token_transfer();
+
+    // Failures are diagnosed against the state of the user when the debug diagnostics are enabled
+    let execution = async {
+        let estimated_transaction = if is_sponsored {
+            let authenticated_api_key = ctx.validate_api_key().await?;
+            let user_calls = transaction.transaction.user_calls();
+            check_calls_in_scope(&authenticated_api_key, &ctx.configuration.rpc.call_policy, user_calls.as_deref())?;
+            check_transfers_within_limits(&ctx.configuration.rpc.call_policy, user_calls.as_deref())?;
+            check_no_suspicious_approval(&authenticated_api_key, &ctx.configuration.rpc.call_policy, user_calls.as_deref())?;
+            check_no_call_to_operator(&ctx.configuration, user_calls.as_deref())?;
+
+            let estimated_transaction = transaction
+                .estimate_sponsored_transaction(&ctx.execution, authenticated_api_key.sponsor_metadata)
+                .await?
+                .with_sponsor(ctx.api_key.as_deref().unwrap_or_default());
+            check_within_budget(ctx, estimated_transaction.overall_fee()).await?;
+
+            ctx.replays.consume(client_nonce).await?;
+
+            if let Some(request) = &pending {
+                let fee_in_strk = estimated_transaction.overall_fee();
+                let calls = user_calls
+                    .as_deref()
+                    .and_then(|x| ctx.configuration.rpc.call_policy.expand_calls(x));
+                if let Some(reason) = ctx.approvals.requires_approval(fee_in_strk, calls.as_deref()) {
+                    let request = ExecuteRequest {
+                        transaction: request.transaction.clone().with_typed_data(ctx).await,
+                        ..request.clone()
+                    };
+                    return Err(park_execution(ctx, PendingRequest::Execute(request), fee_in_strk, reason).await);
+                }
+            }
+
+            estimated_transaction
+        } else {
+            let estimated_transaction = transaction.estimate_transaction(&ctx.execution).await?;
+            ctx.replays.consume(client_nonce).await?;
+
+            estimated_transaction
+        };
+
+        let fee_in_strk = estimated_transaction.overall_fee();
+        let quote = estimated_transaction.quote();
+        let tip = estimated_transaction.tip();
+
+        let (result, timings) = estimated_transaction.execute_timed(&ctx.execution).await?;
+
+        Ok::<_, Error>((result, timings, fee_in_strk, quote, tip))
+    };
+    let (result, timings, fee_in_strk, quote, tip) = match execution.await {
+        Ok(result) => result,
+        Err(e @ (Error::ServiceBusy(_) | Error::PendingApproval(_))) => {
+            // The transaction was not sent, the user or the operator approving it must be able to submit it again
+            ctx.transaction_filter.release(&transaction.transaction);
+            if let (Error::ServiceBusy(_), Some(nonce)) = (&e, client_nonce) {
+                ctx.replays.release(nonce).await;
+            }
+            return Err(e);
+        },
+        Err(e) => {
+            if let (true, Some(request), Error::Execution(_)) = (is_sponsored, dead_letter, &e) {
+                push_dead_letter(ctx, request, &e).await;
+            }
+
+            return Err(ctx.diagnose_error(e, user, fee_transfer).await);
+        },
     };
 
-    let result = estimated_transaction.execute(&ctx.execution).await?;
+    ctx.quotes.insert(result.transaction_hash, quote, QUOTE_RETENTION).await;
+    ctx.executions.record(result.transaction_hash, quote, is_sponsored).await;
+    ctx.record_cost(result.transaction_hash, quote, is_sponsored);
+    if is_sponsored {
+        ctx.record_sponsored_transaction(user, result.transaction_hash, fee_in_strk)
+            .await;
+    } else {
+        ctx.refunds.track(user, result.transaction_hash, quote).await;
+    }
+
+    let event = ctx.analytics_event(AnalyticsEventKind::Execute, user, quote, started_at, is_sponsored);
+    ctx.analytics.publish(event.with_transaction_hash(result.transaction_hash));
+
+    // The transaction is submitted at this point, waiting for its finality never fails the request
+    let finality = match finality {
+        FinalityLevel::Submitted => None,
+        level => Some(
+            ctx.execution
+                .wait_for_finality(result.transaction_hash, level.into())
+                .await
+                .into(),
+        ),
+    };
 
     Ok(ExecuteResponse {
         transaction_hash: result.transaction_hash,
         tracking_id: Felt::ZERO,
+        tip: Some(tip.into()),
+        timings: ctx.configuration.rpc.debug_diagnostics.then(|| timings.into()),
+        finality,
     })
 }
 
+/// Park the request in the approval queue along with the fingerprint of the api key of the sponsor, so that it can
+/// be executed on its behalf once approved
+#[cfg(feature = "server")]
+pub(crate) async fn park_execution(ctx: &RequestContext<'_>, request: PendingRequest, fee_in_strk: Felt, reason: String) -> Error {
+    match ctx
+        .approvals
+        .push(&PendingExecution::new(request, ctx.api_key.as_deref(), fee_in_strk, reason))
+        .await
+    {
+        Ok(id) => Error::PendingApproval(id),
+        Err(e) => Error::ApprovalQueue(e),
+    }
+}
+
+/// Push the request whose sponsored execution failed to the dead-letter queue, along with the api key of the
+/// sponsor so that it can be executed again on its behalf
+#[cfg(feature = "server")]
+async fn push_dead_letter(ctx: &RequestContext<'_>, request: ExecuteRequest, error: &Error) {
+    let request = ExecuteRequest {
+        transaction: request.transaction.with_typed_data(ctx).await,
+        ..request
+    };
+    let api_key = ctx.api_key.as_deref().map(str::to_string);
+
+    ctx.dead_letters
+        .push(&DeadLetter::new(request, api_key, error.to_string()))
+        .await;
+}
+
 #[cfg(test)]
 mod tests {
     use std::vec;
 
     use crate::endpoint::build::{build_transaction_endpoint, BuildTransactionRequest, BuildTransactionResponse, InvokeParameters, TransactionParameters};
     use crate::endpoint::common::{ExecutionParameters, FeeMode, TipPriority};
-    use crate::endpoint::execute::{execute_endpoint, ExecutableInvokeParameters, ExecutableTransactionParameters, ExecuteRequest};
+    use crate::endpoint::execute::{execute_endpoint, ClientNonce, ExecutableInvokeParameters, ExecutableTransactionParameters, ExecuteRequest, FinalityLevel};
     use crate::endpoint::RequestContext;
     use crate::testing::TestEnvironment;
     use crate::{Error, InvokeTransaction};
@@ -162,6 +586,7 @@ mod tests {
                 invoke: InvokeParameters {
                     user_address: StarknetTestEnvironment::ACCOUNT_ARGENT_1.address,
                     calls: vec![an_eth_transfer(StarknetTestEnvironment::ACCOUNT_2.address, Felt::ONE)],
+                    session: false,
                 },
             },
             parameters: ExecutionParameters::V1 {
@@ -171,6 +596,7 @@ mod tests {
                 },
                 time_bounds: None,
             },
+            chain_id: None,
         };
 
         let build_response = build_transaction_endpoint(&RequestContext::empty(&context), build_request)
@@ -187,8 +613,10 @@ mod tests {
             transaction: ExecutableTransactionParameters::Invoke {
                 invoke: ExecutableInvokeParameters {
                     user_address: Felt::ZERO,
-                    typed_data,
+                    typed_data: Some(typed_data),
+                    message_hash: None,
                     signature: vec![Felt::ZERO, Felt::ZERO],
+                    session: None,
                 },
             },
             parameters: ExecutionParameters::V1 {
@@ -198,6 +626,9 @@ mod tests {
                 },
                 time_bounds: None,
             },
+            finality: FinalityLevel::Submitted,
+            chain_id: None,
+            client_nonce: None,
         };
 
         let result = execute_endpoint(&RequestContext::empty(&context), request).await;
@@ -216,6 +647,7 @@ mod tests {
                 invoke: InvokeParameters {
                     user_address: StarknetTestEnvironment::ACCOUNT_ARGENT_1.address,
                     calls: vec![an_eth_transfer(StarknetTestEnvironment::ACCOUNT_2.address, Felt::ONE)],
+                    session: false,
                 },
             },
             parameters: ExecutionParameters::V1 {
@@ -225,6 +657,7 @@ mod tests {
                 },
                 time_bounds: None,
             },
+            chain_id: None,
         };
 
         let build_response = build_transaction_endpoint(&request_context, build_request).await.unwrap();
@@ -243,8 +676,10 @@ mod tests {
             transaction: ExecutableTransactionParameters::Invoke {
                 invoke: ExecutableInvokeParameters {
                     user_address: StarknetTestEnvironment::ACCOUNT_ARGENT_1.address,
-                    typed_data,
+                    typed_data: Some(typed_data),
+                    message_hash: None,
                     signature: vec![signature.r, signature.s],
+                    session: None,
                 },
             },
 
@@ -255,9 +690,33 @@ mod tests {
                 },
                 time_bounds: None,
             },
+            finality: FinalityLevel::Submitted,
+            chain_id: None,
+            client_nonce: None,
         };
 
         let result = execute_endpoint(&request_context, request).await;
         assert!(result.is_ok())
     }
+
+    #[test]
+    fn client_nonce_is_bound_to_the_transaction() {
+        let signing_key = SigningKey::from_secret_scalar(Felt::from(0x1234));
+        let invoke = |message_hash: Felt| ExecutableTransactionParameters::Invoke {
+            invoke: ExecutableInvokeParameters {
+                user_address: Felt::from(0x42),
+                typed_data: None,
+                message_hash: Some(message_hash),
+                signature: vec![],
+                session: None,
+            },
+        };
+
+        let nonce = ClientNonce::sign(&signing_key, Felt::from(7), &invoke(Felt::ONE)).unwrap();
+        assert!(nonce.verify(&invoke(Felt::ONE)));
+
+        // The nonce cannot be attached to another transaction, nor changed
+        assert!(!nonce.verify(&invoke(Felt::TWO)));
+        assert!(!ClientNonce { nonce: Felt::from(8), ..nonce }.verify(&invoke(Felt::ONE)));
+    }
 }
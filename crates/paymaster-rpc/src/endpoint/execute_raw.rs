@@ -1,26 +1,75 @@
+#[cfg(feature = "server")]
+use std::time::Instant;
+
+#[cfg(feature = "server")]
+use paymaster_execution::analytics::AnalyticsEventKind;
+#[cfg(feature = "server")]
 use paymaster_execution::ExecutableTransaction;
+use paymaster_starknet::ChainID;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
+use starknet::core::crypto::compute_hash_on_elements;
 use starknet::core::serde::unsigned_field_element::UfeHex;
 use starknet::core::types::{Call, Felt};
 
-use crate::endpoint::common::ExecutionParameters;
-use crate::endpoint::validation::check_service_is_available;
+#[cfg(feature = "server")]
+use crate::context::{PendingRequest, QUOTE_RETENTION};
+use crate::endpoint::common::{AppliedTip, ExecutionParameters};
+#[cfg(feature = "server")]
+use crate::endpoint::execute::park_execution;
+use crate::endpoint::execute::{ClientNonce, ClientNonceBinding};
+#[cfg(feature = "server")]
+use crate::endpoint::validation::{
+    check_address_in_range, check_calls_in_scope, check_no_call_to_operator, check_no_suspicious_approval, check_not_in_maintenance, check_service_is_available,
+    check_transfers_within_limits, check_within_budget,
+};
+#[cfg(feature = "server")]
 use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
 use crate::Error;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct ExecuteDirectRequest {
     pub transaction: ExecuteDirectTransactionParameters,
     pub parameters: ExecutionParameters,
+
+    /// Chain on which the request must be executed. When not provided, the request is routed to the principal chain of the instance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+
+    /// Nonce signed by the client relaying the request, accepted once when the replay protection is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_nonce: Option<ClientNonce>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ExecuteDirectTransactionParameters {
     Invoke { invoke: DirectInvokeParameters },
 }
 
+impl ExecuteDirectTransactionParameters {
+    pub fn user_address(&self) -> Felt {
+        let Self::Invoke { invoke } = self;
+        invoke.user_address
+    }
+}
+
+impl ClientNonceBinding for ExecuteDirectTransactionParameters {
+    fn user_address(&self) -> Felt {
+        ExecuteDirectTransactionParameters::user_address(self)
+    }
+
+    // Covers the outside execution call, which carries the signature of the user
+    fn binding(&self) -> Felt {
+        let Self::Invoke { invoke } = self;
+        let call = &invoke.execute_from_outside_call;
+
+        compute_hash_on_elements(&[call.to, call.selector, compute_hash_on_elements(&call.calldata)])
+    }
+}
+
+#[cfg(feature = "server")]
 impl From<ExecuteDirectTransactionParameters> for paymaster_execution::ExecutableTransactionParameters {
     fn from(value: ExecuteDirectTransactionParameters) -> Self {
         match value {
@@ -30,7 +79,7 @@ impl From<ExecuteDirectTransactionParameters> for paymaster_execution::Executabl
 }
 
 #[serde_as]
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct DirectInvokeParameters {
     #[serde_as(as = "UfeHex")]
     pub user_address: Felt,
@@ -38,6 +87,7 @@ pub struct DirectInvokeParameters {
     pub execute_from_outside_call: Call,
 }
 
+#[cfg(feature = "server")]
 impl From<DirectInvokeParameters> for paymaster_execution::ExecutableDirectInvokeParameters {
     fn from(value: DirectInvokeParameters) -> Self {
         Self {
@@ -55,34 +105,124 @@ pub struct ExecuteDirectResponse {
 
     #[serde_as(as = "UfeHex")]
     pub tracking_id: Felt,
+
+    /// Tip applied to the transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tip: Option<AppliedTip>,
 }
 
+#[cfg(feature = "server")]
 pub async fn execute_direct_endpoint(ctx: &RequestContext<'_>, request: ExecuteDirectRequest) -> Result<ExecuteDirectResponse, Error> {
+    execute_direct(ctx, request, false).await
+}
+
+/// Execute a direct request parked in the approval queue, once an operator approved it
+#[cfg(feature = "server")]
+pub async fn execute_direct_approved_endpoint(ctx: &RequestContext<'_>, request: ExecuteDirectRequest) -> Result<ExecuteDirectResponse, Error> {
+    execute_direct(ctx, request, true).await
+}
+
+#[cfg(feature = "server")]
+async fn execute_direct(ctx: &RequestContext<'_>, request: ExecuteDirectRequest, approved: bool) -> Result<ExecuteDirectResponse, Error> {
+    let started_at = Instant::now();
+
+    let ExecuteDirectTransactionParameters::Invoke { invoke } = &request.transaction;
+    check_address_in_range(invoke.user_address)?;
+    check_address_in_range(invoke.execute_from_outside_call.to)?;
+    check_not_in_maintenance(ctx).await?;
     check_service_is_available(ctx).await?;
+    let client_nonce = ctx.replays.verify(request.client_nonce.as_ref(), &request.transaction)?;
+
+    // Kept to park the request until an operator approves it if its sponsored execution exceeds a threshold
+    let pending = (ctx.approvals.is_enabled() && !approved).then(|| request.clone());
 
     let forwarder = ctx.configuration.forwarder;
-    let gas_tank_address = ctx.configuration.gas_tank.address;
+    let parameters: paymaster_execution::ExecutionParameters = request.parameters.into();
+    let gas_tank_address = ctx.fee_recipient(parameters.gas_token(ctx.execution.starknet.tokens())).await?;
 
     let transaction = ExecutableTransaction {
         forwarder,
         gas_tank_address,
-        parameters: request.parameters.into(),
+        parameters,
         transaction: request.transaction.into(),
     };
 
-    let estimated_transaction = if transaction.parameters.fee_mode().is_sponsored() {
-        let authenticated_api_key = ctx.validate_api_key().await?;
-        transaction
-            .estimate_sponsored_transaction(&ctx.execution, authenticated_api_key.sponsor_metadata)
-            .await?
-    } else {
-        transaction.estimate_transaction(&ctx.execution).await?
+    let is_sponsored = transaction.parameters.fee_mode().is_sponsored();
+    let user = transaction.transaction.user();
+    let fee_transfer = transaction.gas_token_transfer();
+
+    // Failures are diagnosed against the state of the user when the debug diagnostics are enabled
+    let execution = async {
+        let estimated_transaction = if is_sponsored {
+            let authenticated_api_key = ctx.validate_api_key().await?;
+            let user_calls = transaction.transaction.user_calls();
+            check_calls_in_scope(&authenticated_api_key, &ctx.configuration.rpc.call_policy, user_calls.as_deref())?;
+            check_transfers_within_limits(&ctx.configuration.rpc.call_policy, user_calls.as_deref())?;
+            check_no_suspicious_approval(&authenticated_api_key, &ctx.configuration.rpc.call_policy, user_calls.as_deref())?;
+            check_no_call_to_operator(&ctx.configuration, user_calls.as_deref())?;
+
+            let estimated_transaction = transaction
+                .estimate_sponsored_transaction(&ctx.execution, authenticated_api_key.sponsor_metadata)
+                .await?
+                .with_sponsor(ctx.api_key.as_deref().unwrap_or_default());
+            check_within_budget(ctx, estimated_transaction.overall_fee()).await?;
+            ctx.replays.consume(client_nonce.as_ref()).await?;
+
+            if let Some(request) = &pending {
+                let fee_in_strk = estimated_transaction.overall_fee();
+                let calls = user_calls
+                    .as_deref()
+                    .and_then(|x| ctx.configuration.rpc.call_policy.expand_calls(x));
+                if let Some(reason) = ctx.approvals.requires_approval(fee_in_strk, calls.as_deref()) {
+                    return Err(park_execution(ctx, PendingRequest::ExecuteDirect(request.clone()), fee_in_strk, reason).await);
+                }
+            }
+
+            estimated_transaction
+        } else {
+            let estimated_transaction = transaction.estimate_transaction(&ctx.execution).await?;
+            ctx.replays.consume(client_nonce.as_ref()).await?;
+
+            estimated_transaction
+        };
+
+        let fee_in_strk = estimated_transaction.overall_fee();
+        let quote = estimated_transaction.quote();
+        let tip = estimated_transaction.tip();
+
+        let result = estimated_transaction.execute(&ctx.execution).await?;
+
+        Ok::<_, Error>((result, fee_in_strk, quote, tip))
     };
+    let (result, fee_in_strk, quote, tip) = match execution.await {
+        Ok(result) => result,
+        Err(e @ (Error::ServiceBusy(_) | Error::PendingApproval(_))) => {
+            // The transaction was not sent, the user or the operator approving it must be able to submit it again
+            if let (Error::ServiceBusy(_), Some(nonce)) = (&e, &client_nonce) {
+                ctx.replays.release(nonce).await;
+            }
+            return Err(e);
+        },
+        Err(e) => return Err(ctx.diagnose_error(e, user, fee_transfer).await),
+    };
+
+    ctx.quotes.insert(result.transaction_hash, quote, QUOTE_RETENTION).await;
+    ctx.executions.record(result.transaction_hash, quote, is_sponsored).await;
+    ctx.record_cost(result.transaction_hash, quote, is_sponsored);
+    if is_sponsored {
+        ctx.record_sponsored_transaction(user, result.transaction_hash, fee_in_strk)
+            .await;
+    } else {
+        ctx.refunds.track(user, result.transaction_hash, quote).await;
+    }
+
+    let event = ctx.analytics_event(AnalyticsEventKind::Execute, user, quote, started_at, is_sponsored);
+    ctx.analytics.publish(event.with_transaction_hash(result.transaction_hash));
 
-    let result = estimated_transaction.execute(&ctx.execution).await?;
     Ok(ExecuteDirectResponse {
         transaction_hash: result.transaction_hash,
         tracking_id: Felt::ZERO,
+        tip: Some(tip.into()),
     })
 }
 
@@ -139,6 +279,7 @@ mod tests {
                 invoke: InvokeParameters {
                     user_address: StarknetTestEnvironment::ACCOUNT_ARGENT_1.address,
                     calls: vec![an_eth_transfer(StarknetTestEnvironment::ACCOUNT_2.address, Felt::ONE)],
+                    session: false,
                 },
             },
             parameters: ExecutionParameters::V1 {
@@ -148,6 +289,7 @@ mod tests {
                 },
                 time_bounds: None,
             },
+            chain_id: None,
         };
 
         let build_response = build_transaction_endpoint(&RequestContext::empty(&context), build_request)
@@ -186,6 +328,8 @@ mod tests {
                 },
                 time_bounds: None,
             },
+            chain_id: None,
+            client_nonce: None,
         };
 
         let result = execute_direct_endpoint(&RequestContext::empty(&context), request).await;
@@ -205,6 +349,7 @@ mod tests {
                 invoke: InvokeParameters {
                     user_address: StarknetTestEnvironment::ACCOUNT_ARGENT_1.address,
                     calls: vec![an_eth_transfer(StarknetTestEnvironment::ACCOUNT_2.address, Felt::ONE)],
+                    session: false,
                 },
             },
             parameters: ExecutionParameters::V1 {
@@ -214,6 +359,7 @@ mod tests {
                 },
                 time_bounds: None,
             },
+            chain_id: None,
         };
 
         let build_response = build_transaction_endpoint(&request_context, build_request).await.unwrap();
@@ -247,6 +393,8 @@ mod tests {
                 },
                 time_bounds: None,
             },
+            chain_id: None,
+            client_nonce: None,
         };
 
         let result = execute_direct_endpoint(&request_context, request).await;
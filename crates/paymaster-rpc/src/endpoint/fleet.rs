@@ -0,0 +1,117 @@
+#[cfg(feature = "server")]
+use paymaster_relayer::lock::RelayerLockStatus as LockStatus;
+#[cfg(feature = "server")]
+use paymaster_relayer::AvailabilityTransition as RelayersAvailabilityTransition;
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+
+#[cfg(feature = "server")]
+use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
+use crate::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct FleetStatusRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RelayerLockStatus {
+    #[serde_as(as = "UfeHex")]
+    pub address: Felt,
+
+    pub locked: bool,
+
+    /// Instance holding the lock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub holder: Option<String>,
+
+    /// Time elapsed since the lock was taken, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lock_age_seconds: Option<u64>,
+
+    #[serde_as(as = "Option<UfeHex>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_nonce: Option<Felt>,
+}
+
+#[cfg(feature = "server")]
+impl From<LockStatus> for RelayerLockStatus {
+    fn from(value: LockStatus) -> Self {
+        Self {
+            address: value.address,
+            locked: value.locked,
+            holder: value.holder,
+            lock_age_seconds: value.age.map(|x| x.as_secs()),
+            cached_nonce: value.cached_nonce,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AvailabilityTransition {
+    /// Unix timestamp of the transition, in seconds
+    pub at: u64,
+    pub available: bool,
+    pub enabled_relayers: usize,
+
+    /// Duration of the state left by the transition, in seconds
+    pub previous_state_seconds: u64,
+}
+
+#[cfg(feature = "server")]
+impl From<RelayersAvailabilityTransition> for AvailabilityTransition {
+    fn from(value: RelayersAvailabilityTransition) -> Self {
+        Self {
+            at: value.at,
+            available: value.available,
+            enabled_relayers: value.enabled_relayers,
+            previous_state_seconds: value.previous_state_seconds,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FleetStatusResponse {
+    pub enabled_relayers: usize,
+    pub relayers: Vec<RelayerLockStatus>,
+
+    /// False while the relayers are recovering from an outage
+    #[serde(default = "FleetStatusResponse::default_available")]
+    pub available: bool,
+
+    /// Latest availability transitions of the relayers, the most recent last
+    #[serde(default)]
+    pub availability_history: Vec<AvailabilityTransition>,
+}
+
+impl FleetStatusResponse {
+    fn default_available() -> bool {
+        true
+    }
+}
+
+/// Returns the lock state of the relayers. Requires the admin api key of the instance.
+#[cfg(feature = "server")]
+pub async fn get_fleet_status_endpoint(ctx: &RequestContext<'_>, _request: FleetStatusRequest) -> Result<FleetStatusResponse, Error> {
+    ctx.validate_admin_api_key()?;
+
+    let relayers = ctx.execution.get_relayer_manager();
+    let statuses = relayers.lock_statuses().await?;
+
+    Ok(FleetStatusResponse {
+        enabled_relayers: relayers.count_enabled_relayers().await,
+        relayers: statuses.into_iter().map(RelayerLockStatus::from).collect(),
+        available: relayers.is_available(),
+        availability_history: relayers
+            .availability_history()
+            .into_iter()
+            .map(AvailabilityTransition::from)
+            .collect(),
+    })
+}
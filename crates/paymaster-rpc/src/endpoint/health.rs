@@ -2,8 +2,13 @@ use crate::endpoint::RequestContext;
 use crate::Error;
 
 pub async fn is_available_endpoint(ctx: &RequestContext<'_>) -> Result<bool, Error> {
-    let at_least_one_relayer = ctx.context.execution.get_relayer_manager().count_enabled_relayers().await > 0;
-    Ok(at_least_one_relayer)
+    if ctx.maintenance.current().await.is_some() {
+        return Ok(false);
+    }
+
+    let relayers = ctx.context.execution.get_relayer_manager();
+    let at_least_one_relayer = relayers.count_enabled_relayers().await > 0;
+    Ok(relayers.is_available() && at_least_one_relayer)
 }
 
 #[cfg(test)]
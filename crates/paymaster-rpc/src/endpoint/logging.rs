@@ -0,0 +1,41 @@
+#[cfg(feature = "server")]
+use std::time::Duration;
+
+#[cfg(feature = "server")]
+use paymaster_common::service::monitoring::set_log_filter;
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "server")]
+use tracing::warn;
+
+#[cfg(feature = "server")]
+use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
+use crate::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SetLogFilterRequest {
+    /// Filter directives to apply, e.g. `info,paymaster_relayer::lock=debug`. The directives used at
+    /// startup are restored when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directives: Option<String>,
+
+    /// Number of seconds after which the directives used at startup are restored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+/// Change the filter of the logs without restarting the instance and return the directives applied.
+/// Requires the admin api key of the instance.
+#[cfg(feature = "server")]
+pub async fn set_log_filter_endpoint(ctx: &RequestContext<'_>, request: SetLogFilterRequest) -> Result<String, Error> {
+    ctx.validate_admin_api_key()?;
+
+    let directives = set_log_filter(request.directives.as_deref(), request.duration.map(Duration::from_secs)).map_err(Error::InvalidLogFilter)?;
+    warn!(directives, duration = request.duration, "log filter changed");
+
+    Ok(directives)
+}
@@ -0,0 +1,25 @@
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
+use crate::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SetLowRpcModeRequest {
+    pub enabled: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+/// Enable or disable the low-RPC mode of the instance. Requires the admin api key of the instance.
+#[cfg(feature = "server")]
+pub async fn set_low_rpc_mode_endpoint(ctx: &RequestContext<'_>, request: SetLowRpcModeRequest) -> Result<bool, Error> {
+    ctx.validate_admin_api_key()?;
+
+    ctx.execution.low_rpc.set_enabled(request.enabled);
+
+    Ok(request.enabled)
+}
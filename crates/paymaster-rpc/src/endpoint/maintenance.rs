@@ -0,0 +1,34 @@
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use crate::context::DEFAULT_MAINTENANCE_MESSAGE;
+#[cfg(feature = "server")]
+use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
+use crate::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SetMaintenanceRequest {
+    pub enabled: bool,
+
+    /// Message returned to the users while the maintenance mode is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+/// Enable or disable the maintenance mode. Requires the admin api key of the instance.
+#[cfg(feature = "server")]
+pub async fn set_maintenance_endpoint(ctx: &RequestContext<'_>, request: SetMaintenanceRequest) -> Result<bool, Error> {
+    ctx.validate_admin_api_key()?;
+
+    let message = request
+        .enabled
+        .then(|| request.message.unwrap_or(DEFAULT_MAINTENANCE_MESSAGE.to_string()));
+    ctx.maintenance.set(message).await;
+
+    Ok(request.enabled)
+}
@@ -0,0 +1,166 @@
+#[cfg(feature = "server")]
+use paymaster_starknet::transaction::{Calls, TokenTransfer};
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::{EthAddress, Felt, Hash256, MsgFromL1};
+
+#[cfg(feature = "server")]
+use crate::endpoint::validation::{check_not_in_maintenance, check_service_is_available, check_within_budget};
+#[cfg(feature = "server")]
+use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
+use crate::Error;
+
+/// Message sent from L1 which triggers the execution of an `l1_handler` on L2
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct L1Message {
+    pub from_address: EthAddress,
+
+    #[serde_as(as = "UfeHex")]
+    pub to_address: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub entry_point_selector: Felt,
+
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub payload: Vec<Felt>,
+}
+
+impl From<L1Message> for MsgFromL1 {
+    fn from(value: L1Message) -> Self {
+        Self {
+            from_address: value.from_address,
+            to_address: value.to_address,
+            entry_point_selector: value.entry_point_selector,
+            payload: value.payload,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EstimateMessageFeeRequest {
+    pub message: L1Message,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MessageFeeEstimate {
+    pub l1_gas_consumed: u64,
+    pub l1_data_gas_consumed: u64,
+    pub l2_gas_consumed: u64,
+
+    /// Fee to pay on L1 when sending the message
+    #[serde_as(as = "UfeHex")]
+    pub overall_fee_in_wei: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub overall_fee_in_strk: Felt,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SponsorMessageRequest {
+    /// Hash of the L1 transaction which sent the messages
+    pub l1_transaction_hash: Hash256,
+
+    /// Address on L2 which receives the reimbursement of the fee paid on L1, must be part of the payload of every message
+    #[serde_as(as = "UfeHex")]
+    pub recipient: Felt,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SponsorMessageResponse {
+    /// Hash of the transaction which reimbursed the fee, or of the proposal to the signers of a multisig gas tank
+    #[serde_as(as = "UfeHex")]
+    pub transaction_hash: Felt,
+
+    /// Hashes of the `l1_handler` transactions whose fee was reimbursed
+    #[serde_as(as = "Vec<UfeHex>")]
+    pub l1_handler_transaction_hashes: Vec<Felt>,
+
+    #[serde_as(as = "UfeHex")]
+    pub fee_in_wei: Felt,
+}
+
+#[cfg(feature = "server")]
+pub async fn estimate_message_fee_endpoint(ctx: &RequestContext<'_>, request: EstimateMessageFeeRequest) -> Result<MessageFeeEstimate, Error> {
+    let estimate = ctx.execution.estimate_message(&request.message.into()).await?;
+
+    let overall_fee_in_wei = Felt::from(estimate.overall_fee);
+    let overall_fee_in_strk = ctx
+        .price
+        .convert_token_to_strk(ctx.execution.starknet.tokens().eth, overall_fee_in_wei)
+        .await?;
+
+    Ok(MessageFeeEstimate {
+        l1_gas_consumed: estimate.l1_gas_consumed,
+        l1_data_gas_consumed: estimate.l1_data_gas_consumed,
+        l2_gas_consumed: estimate.l2_gas_consumed,
+        overall_fee_in_wei,
+        overall_fee_in_strk,
+    })
+}
+
+/// Reimburse, in ETH from the gas tank, the fee paid on L1 for the `l1_handler` transactions triggered by
+/// the given L1 transaction to the account the messages were sent to. The fee is charged to the sponsor and each
+/// L1 transaction can only be sponsored once across the instances.
+#[cfg(feature = "server")]
+pub async fn sponsor_message_endpoint(ctx: &RequestContext<'_>, request: SponsorMessageRequest) -> Result<SponsorMessageResponse, Error> {
+    check_not_in_maintenance(ctx).await?;
+    check_service_is_available(ctx).await?;
+    ctx.validate_api_key().await?;
+
+    let consumed = ctx
+        .execution
+        .fetch_consumed_messages(request.l1_transaction_hash)
+        .await?
+        .ok_or(Error::MessageNotConsumed)?;
+    if !consumed.is_sent_to(request.recipient) {
+        return Err(Error::InvalidMessageRecipient);
+    }
+
+    let fee_in_strk = ctx
+        .price
+        .convert_token_to_strk(ctx.execution.starknet.tokens().eth, consumed.fee_in_wei)
+        .await?;
+    check_within_budget(ctx, fee_in_strk).await?;
+
+    // Reserve the L1 transaction before sending the reimbursement so that no request can sponsor it twice
+    if !ctx.sponsored_messages.reserve(request.l1_transaction_hash).await? {
+        return Err(Error::MessageAlreadySponsored);
+    }
+
+    let transfer = TokenTransfer::new(ctx.execution.starknet.tokens().eth, request.recipient, consumed.fee_in_wei);
+    let submission = match ctx
+        .execution
+        .get_relayer_manager()
+        .gas_tank()
+        .send(&Calls::new(vec![transfer.to_call()]))
+        .await
+    {
+        Ok(submission) => submission,
+        Err(e) => {
+            ctx.sponsored_messages.release(&request.l1_transaction_hash).await;
+            return Err(e.into());
+        },
+    };
+
+    ctx.record_sponsored_transaction(request.recipient, submission.transaction_hash(), fee_in_strk)
+        .await;
+
+    Ok(SponsorMessageResponse {
+        transaction_hash: submission.transaction_hash(),
+        l1_handler_transaction_hashes: consumed.transactions,
+        fee_in_wei: consumed.fee_in_wei,
+    })
+}
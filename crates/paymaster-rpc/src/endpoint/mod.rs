@@ -1,68 +1,32 @@
-use std::ops::Deref;
-
-use bigdecimal::Zero;
-use hyper::http::Extensions;
-use paymaster_prices::TokenPrice;
-use paymaster_sponsoring::AuthenticatedApiKey;
-
-use crate::context::Context;
+#[cfg(feature = "server")]
 pub use crate::middleware::APIKey;
-use crate::Error;
 
+pub mod account;
+pub mod accounting;
+pub mod approval;
 pub mod build;
 pub mod common;
+pub mod dead_letter;
 pub mod execute;
 pub mod execute_raw;
+pub mod fleet;
+#[cfg(feature = "server")]
 pub mod health;
+pub mod logging;
+pub mod low_rpc;
+pub mod maintenance;
+pub mod message;
+pub mod receipt;
+pub mod refund;
+#[cfg(feature = "server")]
+mod request;
+pub mod simulation;
+pub mod sponsorship;
 pub mod token;
+pub mod treasury;
+pub mod usage;
+#[cfg(feature = "server")]
 mod validation;
 
-pub struct RequestContext<'a> {
-    context: &'a Context,
-
-    pub api_key: Option<APIKey>,
-}
-
-impl Deref for RequestContext<'_> {
-    type Target = Context;
-
-    fn deref(&self) -> &Self::Target {
-        self.context
-    }
-}
-
-impl<'a> RequestContext<'a> {
-    pub fn new(ctx: &'a Context, extensions: &Extensions) -> Self {
-        Self {
-            context: ctx,
-            api_key: extensions.get::<APIKey>().cloned(),
-        }
-    }
-
-    #[cfg(test)]
-    pub fn empty(ctx: &'a Context) -> Self {
-        Self { context: ctx, api_key: None }
-    }
-
-    pub async fn validate_api_key(&self) -> Result<AuthenticatedApiKey, Error> {
-        let key = self.api_key.clone().unwrap_or_default();
-        let authenticated_api_key = self.sponsoring.validate(&key).await.map_err(|_| Error::InvalidAPIKey)?;
-
-        if authenticated_api_key.is_valid {
-            return Ok(authenticated_api_key);
-        }
-
-        Err(Error::InvalidAPIKey)
-    }
-
-    pub async fn fetch_available_tokens(&self) -> Vec<TokenPrice> {
-        self.context
-            .price
-            .fetch_tokens(&self.context.configuration.supported_tokens)
-            .await
-            .into_iter()
-            .filter_map(Result::ok)
-            .filter(|tp| !tp.price_in_strk.is_zero())
-            .collect()
-    }
-}
+#[cfg(feature = "server")]
+pub use request::RequestContext;
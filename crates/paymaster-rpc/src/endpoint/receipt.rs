@@ -0,0 +1,147 @@
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+
+#[cfg(feature = "server")]
+use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
+use crate::Error;
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExecutionReceiptRequest {
+    #[serde_as(as = "UfeHex")]
+    pub transaction_hash: Felt,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GasConsumed {
+    pub l1_gas: u64,
+    pub l1_data_gas: u64,
+    pub l2_gas: u64,
+}
+
+#[cfg(feature = "server")]
+impl From<paymaster_execution::GasConsumed> for GasConsumed {
+    fn from(value: paymaster_execution::GasConsumed) -> Self {
+        Self {
+            l1_gas: value.l1_gas,
+            l1_data_gas: value.l1_data_gas,
+            l2_gas: value.l2_gas,
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExecutionReceipt {
+    #[serde_as(as = "UfeHex")]
+    pub transaction_hash: Felt,
+
+    pub block_number: u64,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+
+    pub gas_consumed: GasConsumed,
+
+    /// Fee actually paid by the relayer
+    #[serde_as(as = "UfeHex")]
+    pub actual_fee_in_strk: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub gas_token_address: Felt,
+
+    /// Fee taken from the user in gas token
+    #[serde_as(as = "UfeHex")]
+    pub fee_in_gas_token: Felt,
+
+    /// Fee charged to the user (or the sponsor) in STRK
+    #[serde_as(as = "UfeHex")]
+    pub fee_charged_in_strk: Felt,
+
+    /// Fee charged minus the fee actually paid by the relayer, negative if the transaction cost more than charged
+    #[serde_as(as = "DisplayFromStr")]
+    pub provider_margin_in_strk: i128,
+}
+
+#[cfg(feature = "server")]
+impl From<paymaster_execution::ExecutionReceipt> for ExecutionReceipt {
+    fn from(value: paymaster_execution::ExecutionReceipt) -> Self {
+        Self {
+            provider_margin_in_strk: value.provider_margin_in_strk(),
+
+            transaction_hash: value.transaction_hash,
+            block_number: value.block_number,
+            revert_reason: value.revert_reason,
+            gas_consumed: value.gas_consumed.into(),
+            actual_fee_in_strk: value.actual_fee_in_strk,
+            gas_token_address: value.quote.gas_token,
+            fee_in_gas_token: value.quote.fee_in_token,
+            fee_charged_in_strk: value.quote.fee_in_strk,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ExecutionReceiptResponse {
+    /// The transaction is not confirmed yet
+    Pending,
+    Confirmed(ExecutionReceipt),
+}
+
+#[cfg(feature = "server")]
+pub async fn get_execution_receipt_endpoint(ctx: &RequestContext<'_>, request: ExecutionReceiptRequest) -> Result<ExecutionReceiptResponse, Error> {
+    // Only the transactions executed by the paymaster have a quote we can cross-reference
+    let quote = ctx
+        .quotes
+        .get(&request.transaction_hash)
+        .await?
+        .ok_or(Error::TransactionNotFound)?;
+
+    let receipt = ctx.execution.fetch_execution_receipt(request.transaction_hash, quote).await?;
+
+    Ok(match receipt {
+        Some(receipt) => ExecutionReceiptResponse::Confirmed(receipt.into()),
+        None => ExecutionReceiptResponse::Pending,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use crate::endpoint::receipt::{ExecutionReceipt, ExecutionReceiptResponse, GasConsumed};
+
+    #[test]
+    fn receipt_is_serialized_with_its_status() {
+        let response = ExecutionReceiptResponse::Confirmed(ExecutionReceipt {
+            transaction_hash: Felt::ONE,
+            block_number: 12,
+            revert_reason: None,
+            gas_consumed: GasConsumed {
+                l1_gas: 0,
+                l1_data_gas: 128,
+                l2_gas: 1_000_000,
+            },
+            actual_fee_in_strk: Felt::from(100),
+            gas_token_address: Felt::TWO,
+            fee_in_gas_token: Felt::from(5),
+            fee_charged_in_strk: Felt::from(90),
+            provider_margin_in_strk: -10,
+        });
+
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(value["status"], "confirmed");
+        assert_eq!(value["provider_margin_in_strk"], "-10");
+
+        let value = serde_json::to_value(ExecutionReceiptResponse::Pending).unwrap();
+        assert_eq!(value["status"], "pending");
+    }
+}
@@ -0,0 +1,105 @@
+#[cfg(feature = "server")]
+use paymaster_execution::refund::RefundStatus;
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+
+#[cfg(feature = "server")]
+use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
+use crate::Error;
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RefundsRequest {
+    /// Restrict the report to the refunds of the given user
+    #[serde_as(as = "Option<UfeHex>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_address: Option<Felt>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Refund {
+    #[serde_as(as = "UfeHex")]
+    pub user_address: Felt,
+
+    /// Hash of the transaction for which the user was overcharged
+    #[serde_as(as = "UfeHex")]
+    pub transaction_hash: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub token_address: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub amount: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub amount_in_strk: Felt,
+
+    /// Hash of the transaction which sent the refund, None while the refund is outstanding
+    #[serde_as(as = "Option<UfeHex>")]
+    pub refund_transaction_hash: Option<Felt>,
+
+    /// Whether the transaction which sent the refund was accepted
+    #[serde(default)]
+    pub confirmed: bool,
+
+    pub timestamp: u64,
+}
+
+#[cfg(feature = "server")]
+impl From<paymaster_execution::refund::Refund> for Refund {
+    fn from(value: paymaster_execution::refund::Refund) -> Self {
+        Self {
+            user_address: value.user,
+            transaction_hash: value.transaction_hash,
+            token_address: value.token,
+            amount: value.amount,
+            amount_in_strk: value.amount_in_strk,
+            refund_transaction_hash: match value.status {
+                RefundStatus::Outstanding => None,
+                RefundStatus::Submitted { transaction_hash, .. } | RefundStatus::Refunded { transaction_hash } => Some(transaction_hash),
+            },
+            confirmed: matches!(value.status, RefundStatus::Refunded { .. }),
+            timestamp: value.timestamp,
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RefundsResponse {
+    /// Whether refunds are enabled on the paymaster
+    pub enabled: bool,
+
+    pub outstanding: u64,
+
+    #[serde_as(as = "UfeHex")]
+    pub outstanding_in_strk: Felt,
+
+    pub refunds: Vec<Refund>,
+}
+
+#[cfg(feature = "server")]
+pub async fn get_refunds_endpoint(ctx: &RequestContext<'_>, request: RefundsRequest) -> Result<RefundsResponse, Error> {
+    let refunds = ctx.refunds.ledger().refunds(request.user_address).await?;
+
+    // Refunds sent but not confirmed yet are still owed
+    let outstanding: Vec<_> = refunds
+        .iter()
+        .filter(|x| !matches!(x.status, RefundStatus::Refunded { .. }))
+        .collect();
+
+    Ok(RefundsResponse {
+        enabled: ctx.refunds.is_enabled(),
+        outstanding: outstanding.len() as u64,
+        outstanding_in_strk: outstanding.iter().fold(Felt::ZERO, |acc, x| acc + x.amount_in_strk),
+        refunds: refunds.into_iter().map(Refund::from).collect(),
+    })
+}
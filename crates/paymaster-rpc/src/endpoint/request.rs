@@ -0,0 +1,165 @@
+use std::ops::Deref;
+use std::time::Instant;
+
+use bigdecimal::Zero;
+use hyper::http::Extensions;
+use paymaster_execution::analytics::{AnalyticsEvent, AnalyticsEventKind};
+use paymaster_execution::tokens::metadata::TokenMetadata;
+use paymaster_execution::FeeQuote;
+use paymaster_prices::TokenPrice;
+use paymaster_sponsoring::usage::SponsoredTransaction;
+use paymaster_sponsoring::AuthenticatedApiKey;
+use paymaster_starknet::transaction::TokenTransfer;
+use starknet::core::types::Felt;
+use tracing::error;
+
+use crate::context::Context;
+use crate::middleware::APIKey;
+use crate::Error;
+
+pub struct RequestContext<'a> {
+    pub(super) context: &'a Context,
+
+    pub api_key: Option<APIKey>,
+}
+
+impl Deref for RequestContext<'_> {
+    type Target = Context;
+
+    fn deref(&self) -> &Self::Target {
+        self.context
+    }
+}
+
+impl<'a> RequestContext<'a> {
+    pub fn new(ctx: &'a Context, extensions: &Extensions) -> Self {
+        Self {
+            context: ctx,
+            api_key: extensions.get::<APIKey>().cloned(),
+        }
+    }
+
+    /// Creates the context of a request made on behalf of the given api key, typically to execute again a request
+    pub fn with_api_key(ctx: &'a Context, api_key: Option<APIKey>) -> Self {
+        Self { context: ctx, api_key }
+    }
+
+    #[cfg(test)]
+    pub fn empty(ctx: &'a Context) -> Self {
+        Self { context: ctx, api_key: None }
+    }
+
+    pub async fn validate_api_key(&self) -> Result<AuthenticatedApiKey, Error> {
+        let key = self.api_key.clone().unwrap_or_default();
+        let authenticated_api_key = self.sponsoring.validate(&key).await.map_err(|_| Error::InvalidAPIKey)?;
+
+        if authenticated_api_key.is_valid {
+            return Ok(authenticated_api_key);
+        }
+
+        Err(Error::InvalidAPIKey)
+    }
+
+    /// Check that the request carries the admin api key of the instance
+    pub fn validate_admin_api_key(&self) -> Result<(), Error> {
+        let admin_api_key = self.configuration.rpc.maintenance.admin_api_key.as_deref();
+        match (admin_api_key, self.api_key.as_deref()) {
+            (Some(expected), Some(key)) if expected == key => Ok(()),
+            _ => Err(Error::InvalidAPIKey),
+        }
+    }
+
+    /// Attach a diagnosis of the state of the user to the execution errors when the debug diagnostics are enabled
+    pub async fn diagnose_error(&self, error: Error, user_address: Felt, fee_transfer: Option<TokenTransfer>) -> Error {
+        let Error::Execution(execution_error) = error else {
+            return error;
+        };
+        if !self.configuration.rpc.debug_diagnostics {
+            return Error::Execution(execution_error);
+        }
+
+        let diagnosis = self
+            .execution
+            .diagnose(self.configuration.forwarder, user_address, fee_transfer)
+            .await;
+        match serde_json::to_value(diagnosis) {
+            Ok(diagnosis) => Error::DiagnosedExecution(execution_error, diagnosis),
+            Err(_) => Error::Execution(execution_error),
+        }
+    }
+
+    /// Returns the budget left to the sponsor who made the request, None if the sponsor has no budget
+    pub async fn remaining_budget(&self) -> Result<Option<Felt>, Error> {
+        let sponsor = self.api_key.clone().unwrap_or_default();
+
+        self.usage
+            .remaining_budget(&sponsor, self.sponsoring.budget(&sponsor))
+            .await
+            .map_err(|e| {
+                error!("Failed to read the usage of the sponsor: {}", e);
+                Error::ServiceNotAvailable
+            })
+    }
+
+    /// Record a transaction sponsored on behalf of the sponsor who made the request and notify them of its submission
+    pub async fn record_sponsored_transaction(&self, user: Felt, transaction_hash: Felt, fee_in_strk: Felt) {
+        let sponsor = self.api_key.clone().unwrap_or_default();
+
+        let transaction = SponsoredTransaction::new(&sponsor, user, transaction_hash, fee_in_strk);
+        if let Err(e) = self.usage.record(transaction).await {
+            error!("Failed to record sponsored transaction {}: {}", transaction_hash.to_fixed_hex_string(), e);
+        }
+        self.callbacks.notify_submitted(&sponsor, user, transaction_hash, fee_in_strk);
+    }
+
+    /// Returns the address receiving the fee paid in `gas_token` by the users of the sponsor who made the request.
+    /// The recipient of a sponsor only applies once its api key is validated.
+    pub async fn fee_recipient(&self, gas_token: Felt) -> Result<Felt, Error> {
+        let recipients = &self.configuration.fee_recipients;
+        let api_key = match self.api_key.as_deref() {
+            Some(api_key) if recipients.has_sponsor(api_key) => {
+                self.validate_api_key().await?;
+                Some(api_key)
+            },
+            _ => None,
+        };
+
+        Ok(recipients.resolve(self.configuration.gas_tank.address, api_key, gas_token))
+    }
+
+    /// Report the cost of a transaction executed with the given `quote`. Sponsored transactions are attributed to
+    /// the sponsor who made the request.
+    pub fn record_cost(&self, transaction_hash: Felt, quote: FeeQuote, is_sponsored: bool) {
+        let sponsor = self.api_key.as_deref().filter(|_| is_sponsored);
+        self.costs.record(transaction_hash, sponsor, quote);
+    }
+
+    /// Build the analytics event of the request started at `started_at`. Sponsored transactions are attributed to
+    /// the sponsor who made the request.
+    pub fn analytics_event(&self, kind: AnalyticsEventKind, user: Felt, quote: FeeQuote, started_at: Instant, is_sponsored: bool) -> AnalyticsEvent {
+        let mut event = AnalyticsEvent::new(kind, self.configuration.starknet.chain_id, user, quote, started_at.elapsed());
+        if let (true, Some(api_key)) = (is_sponsored, &self.api_key) {
+            event = event.with_sponsor(api_key.deref());
+        }
+
+        event
+    }
+
+    pub async fn fetch_available_tokens(&self) -> Vec<TokenPrice> {
+        self.context
+            .price
+            .fetch_tokens(&self.context.configuration.supported_tokens)
+            .await
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|tp| !tp.price_in_strk.is_zero())
+            .collect()
+    }
+
+    /// Returns the metadata of the token listed by AVNU merged with the one configured by the operator
+    pub async fn fetch_token_metadata(&self, token: &Felt) -> TokenMetadata {
+        let listed = self.context.tokens.get_token(*token).await;
+
+        self.context.configuration.token_metadata.resolve(token, listed)
+    }
+}
@@ -0,0 +1,112 @@
+#[cfg(feature = "server")]
+use paymaster_execution::simulation::{PricingOutcome as ExecutionPricingOutcome, PricingParameters};
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+
+#[cfg(feature = "server")]
+use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
+use crate::Error;
+
+/// Number of recent transactions replayed when the request does not specify it
+const DEFAULT_SIMULATION_LIMIT: usize = 100;
+
+/// Maximum number of recent transactions replayed, each unconfirmed one requires its receipt to be fetched
+const MAX_SIMULATION_LIMIT: usize = 1_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SimulatePricingRequest {
+    pub max_fee_multiplier: f32,
+    pub provider_fee_overhead: f32,
+
+    /// Number of recent transactions to replay, at most 1000
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PricingOutcome {
+    pub max_fee_multiplier: f32,
+    pub provider_fee_overhead: f32,
+
+    #[serde_as(as = "UfeHex")]
+    pub charged_in_strk: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub user_cost_in_strk: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub max_fee_in_strk: Felt,
+
+    pub uncovered_transactions: usize,
+}
+
+#[cfg(feature = "server")]
+impl PricingOutcome {
+    fn new(parameters: PricingParameters, outcome: ExecutionPricingOutcome) -> Self {
+        Self {
+            max_fee_multiplier: parameters.max_fee_multiplier,
+            provider_fee_overhead: parameters.provider_fee_overhead,
+            charged_in_strk: outcome.charged_in_strk,
+            user_cost_in_strk: outcome.user_cost_in_strk,
+            max_fee_in_strk: outcome.max_fee_in_strk,
+            uncovered_transactions: outcome.uncovered_transactions,
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SimulatePricingResponse {
+    /// Number of confirmed transactions replayed
+    pub transactions: usize,
+
+    #[serde_as(as = "UfeHex")]
+    pub paid_in_strk: Felt,
+
+    pub current: PricingOutcome,
+    pub proposed: PricingOutcome,
+
+    /// Difference of revenue between the proposed and the current parameters, in FRI
+    pub revenue_delta: i128,
+
+    /// Difference of cost for the users between the proposed and the current parameters, in FRI
+    pub user_cost_delta: i128,
+}
+
+#[cfg(feature = "server")]
+pub async fn simulate_pricing_endpoint(ctx: &RequestContext<'_>, request: SimulatePricingRequest) -> Result<SimulatePricingResponse, Error> {
+    ctx.validate_admin_api_key()?;
+
+    let current = PricingParameters {
+        max_fee_multiplier: ctx.configuration.max_fee_multiplier,
+        provider_fee_overhead: ctx.configuration.provider_fee_overhead,
+    };
+    let proposed = PricingParameters {
+        max_fee_multiplier: request.max_fee_multiplier,
+        provider_fee_overhead: request.provider_fee_overhead,
+    };
+
+    if proposed.max_fee_multiplier < 1.0 || proposed.provider_fee_overhead < 0.0 {
+        return Err(Error::InvalidPricingParameters);
+    }
+
+    let limit = request.limit.unwrap_or(DEFAULT_SIMULATION_LIMIT).min(MAX_SIMULATION_LIMIT);
+    let simulation = ctx.execution.simulate_pricing(&ctx.executions, proposed, limit).await?;
+
+    Ok(SimulatePricingResponse {
+        transactions: simulation.transactions,
+        paid_in_strk: simulation.paid_in_strk,
+        current: PricingOutcome::new(current, simulation.current),
+        proposed: PricingOutcome::new(proposed, simulation.proposed),
+        revenue_delta: simulation.revenue_delta_in_strk(),
+        user_cost_delta: simulation.user_cost_delta_in_strk(),
+    })
+}
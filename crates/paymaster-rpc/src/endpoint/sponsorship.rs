@@ -0,0 +1,153 @@
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+
+use crate::endpoint::build::TransactionParameters;
+#[cfg(feature = "server")]
+use crate::endpoint::validation::{
+    check_no_blacklisted_call, check_no_call_to_operator, check_no_suspicious_approval, check_not_in_maintenance, check_service_is_available,
+    check_transaction_is_well_formed, check_transfers_within_limits,
+};
+#[cfg(feature = "server")]
+use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
+use crate::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CanSponsorRequest {
+    /// Transaction the user is about to build, it does not need to be signed
+    pub transaction: TransactionParameters,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+/// Policy of the api key under which the transaction would be sponsored
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SponsorshipPolicy {
+    /// The api key can sponsor any call
+    Unrestricted,
+
+    /// The api key is restricted to a scope, `targets` are the contracts of the scope called by the transaction
+    Scoped {
+        #[serde_as(as = "Vec<UfeHex>")]
+        targets: Vec<Felt>,
+    },
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CanSponsorResponse {
+    pub sponsored: bool,
+
+    /// Reason why the transaction would not be sponsored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<SponsorshipPolicy>,
+
+    #[serde_as(as = "Option<UfeHex>")]
+    pub remaining_budget_in_strk: Option<Felt>,
+}
+
+#[cfg(feature = "server")]
+impl CanSponsorResponse {
+    fn rejected(error: Error, remaining_budget_in_strk: Option<Felt>) -> Self {
+        Self {
+            sponsored: false,
+            reason: Some(error.to_string()),
+            policy: None,
+            remaining_budget_in_strk,
+        }
+    }
+}
+
+/// Returns whether the transaction would be sponsored with the api key of the request, without building it. Apps can
+/// then adapt their interface before asking the user to sign.
+#[cfg(feature = "server")]
+pub async fn can_sponsor_endpoint(ctx: &RequestContext<'_>, request: CanSponsorRequest) -> Result<CanSponsorResponse, Error> {
+    let api_key = match ctx.validate_api_key().await {
+        Ok(api_key) => api_key,
+        Err(e) => return Ok(CanSponsorResponse::rejected(e, None)),
+    };
+
+    let remaining_budget = match ctx.remaining_budget().await {
+        Ok(remaining_budget) => remaining_budget,
+        Err(e) => return Ok(CanSponsorResponse::rejected(e, None)),
+    };
+    if remaining_budget == Some(Felt::ZERO) {
+        return Ok(CanSponsorResponse::rejected(Error::BudgetExhausted, remaining_budget));
+    }
+
+    let availability = async {
+        check_not_in_maintenance(ctx).await?;
+        check_service_is_available(ctx).await
+    };
+    if let Err(e) = availability.await {
+        return Ok(CanSponsorResponse::rejected(e, remaining_budget));
+    }
+
+    if let Err(e) = check_transaction_is_well_formed(&request.transaction) {
+        return Ok(CanSponsorResponse::rejected(e, remaining_budget));
+    }
+
+    let checks = check_no_blacklisted_call(&request.transaction, &ctx.configuration.rpc.call_policy)
+        .and_then(|_| check_transfers_within_limits(&ctx.configuration.rpc.call_policy, Some(request.transaction.calls())))
+        .and_then(|_| check_no_suspicious_approval(&api_key, &ctx.configuration.rpc.call_policy, Some(request.transaction.calls())))
+        .and_then(|_| check_no_call_to_operator(&ctx.configuration, Some(request.transaction.calls())));
+    if let Err(e) = checks {
+        return Ok(CanSponsorResponse::rejected(e, remaining_budget));
+    }
+
+    let policy = match &api_key.scope {
+        None => SponsorshipPolicy::Unrestricted,
+        Some(scope) => {
+            // Scopes apply to the calls batched by the batching entrypoints rather than to the entrypoints themselves
+            let Some(calls) = ctx.configuration.rpc.call_policy.expand_calls(request.transaction.calls()) else {
+                return Ok(CanSponsorResponse::rejected(Error::CallOutOfScope("undecoded calls".to_string()), remaining_budget));
+            };
+            if let Some(call) = scope.find_out_of_scope(&calls) {
+                return Ok(CanSponsorResponse::rejected(Error::CallOutOfScope(call.to.to_hex_string()), remaining_budget));
+            }
+
+            let mut targets: Vec<Felt> = calls
+                .iter()
+                .filter_map(|x| scope.find_target(x))
+                .map(|x| x.contract_address)
+                .collect();
+            targets.sort();
+            targets.dedup();
+
+            SponsorshipPolicy::Scoped { targets }
+        },
+    };
+
+    Ok(CanSponsorResponse {
+        sponsored: true,
+        reason: None,
+        policy: Some(policy),
+        remaining_budget_in_strk: remaining_budget,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use starknet::core::types::Felt;
+
+    use crate::endpoint::sponsorship::SponsorshipPolicy;
+
+    #[test]
+    fn policy_is_tagged_with_its_type() {
+        let policy = SponsorshipPolicy::Scoped { targets: vec![Felt::from(0x10)] };
+        assert_eq!(serde_json::to_value(&policy).unwrap(), json!({ "type": "scoped", "targets": ["0x10"] }));
+
+        let policy: SponsorshipPolicy = serde_json::from_value(json!({ "type": "unrestricted" })).unwrap();
+        assert_eq!(policy, SponsorshipPolicy::Unrestricted);
+    }
+}
@@ -1,8 +1,12 @@
 use jsonrpsee::core::Serialize;
+#[cfg(feature = "server")]
+use paymaster_execution::tokens::metadata::TokenMetadata;
 use serde::Deserialize;
 use starknet::core::types::Felt;
 
+#[cfg(feature = "server")]
 use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
 use crate::Error;
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug)]
@@ -10,20 +14,51 @@ pub struct TokenPrice {
     pub token_address: Felt,
     pub decimals: i64,
     pub price_in_strk: Felt,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Minimum fee charged in the token (in token units)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_fee_amount: Option<Felt>,
 }
 
+#[cfg(feature = "server")]
 impl From<paymaster_prices::TokenPrice> for TokenPrice {
     fn from(value: paymaster_prices::TokenPrice) -> Self {
         Self {
             token_address: value.address,
             decimals: value.decimals,
             price_in_strk: value.price_in_strk,
+            symbol: None,
+            name: None,
+            min_fee_amount: None,
         }
     }
 }
 
+#[cfg(feature = "server")]
+impl TokenPrice {
+    /// Attach the metadata of the token. The decimals configured take precedence over the ones read on chain.
+    pub fn with_metadata(mut self, metadata: TokenMetadata) -> Self {
+        self.decimals = metadata.decimals.map(i64::from).unwrap_or(self.decimals);
+        self.symbol = metadata.symbol;
+        self.name = metadata.name;
+        self.min_fee_amount = metadata.min_fee_amount;
+        self
+    }
+}
+
+#[cfg(feature = "server")]
 pub async fn get_supported_tokens_endpoint(ctx: &RequestContext<'_>) -> Result<Vec<TokenPrice>, Error> {
-    let tokens = ctx.fetch_available_tokens().await.into_iter().map(|x| x.into()).collect();
+    let mut tokens = vec![];
+    for price in ctx.fetch_available_tokens().await {
+        let metadata = ctx.fetch_token_metadata(&price.address).await;
+        tokens.push(TokenPrice::from(price).with_metadata(metadata));
+    }
 
     Ok(tokens)
 }
@@ -33,11 +68,12 @@ mod tests {
     use std::collections::HashSet;
 
     use async_trait::async_trait;
+    use paymaster_execution::tokens::metadata::TokenMetadata;
     use paymaster_prices::mock::MockPriceOracle;
     use paymaster_prices::TokenPrice;
     use starknet::core::types::Felt;
 
-    use crate::endpoint::token::get_supported_tokens_endpoint;
+    use crate::endpoint::token::{get_supported_tokens_endpoint, TokenPrice as SupportedToken};
     use crate::endpoint::RequestContext;
     use crate::testing::{StarknetTestEnvironment, TestEnvironment};
 
@@ -86,4 +122,25 @@ mod tests {
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].token_address, StarknetTestEnvironment::ETH)
     }
+
+    #[test]
+    fn configured_decimals_take_precedence_over_chain_ones() {
+        let price = TokenPrice {
+            address: Felt::ONE,
+            decimals: 18,
+            price_in_strk: Felt::ONE,
+        };
+
+        let token = SupportedToken::from(price).with_metadata(TokenMetadata {
+            symbol: Some("GAME".to_string()),
+            decimals: Some(6),
+            ..Default::default()
+        });
+        assert_eq!(token.decimals, 6);
+        assert_eq!(token.symbol.as_deref(), Some("GAME"));
+        assert_eq!(token.name, None);
+
+        let token = SupportedToken::from(price).with_metadata(TokenMetadata::default());
+        assert_eq!(token.decimals, 18);
+    }
 }
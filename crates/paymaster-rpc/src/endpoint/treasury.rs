@@ -0,0 +1,100 @@
+#[cfg(feature = "server")]
+use paymaster_relayer::treasury::report::TreasuryReport;
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+
+#[cfg(feature = "server")]
+use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
+use crate::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TreasuryReportRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TreasuryHolding {
+    #[serde_as(as = "UfeHex")]
+    pub token: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub balance: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub value_in_strk: Felt,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_in_usd: Option<f64>,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TreasuryReportResponse {
+    /// Unix timestamp in seconds
+    pub taken_at: u64,
+
+    pub holdings: Vec<TreasuryHolding>,
+
+    #[serde_as(as = "UfeHex")]
+    pub value_in_strk: Felt,
+
+    /// Not set when the price of STRK in USD could not be fetched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_in_usd: Option<f64>,
+
+    /// Change of the value of the gas tank in STRK, not set when the history does not go back far enough
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta_7d_in_strk: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta_30d_in_strk: Option<f64>,
+
+    /// STRK drained from the gas tank per day
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_burn_in_strk: Option<f64>,
+
+    /// Days before the gas tank is empty at the current burn rate, not set when it does not decrease
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runway_days: Option<f64>,
+}
+
+#[cfg(feature = "server")]
+impl From<TreasuryReport> for TreasuryReportResponse {
+    fn from(value: TreasuryReport) -> Self {
+        Self {
+            taken_at: value.taken_at,
+            holdings: value
+                .holdings
+                .iter()
+                .map(|x| TreasuryHolding {
+                    token: x.token,
+                    balance: x.balance,
+                    value_in_strk: x.value_in_strk,
+                    value_in_usd: value.to_usd(x.value_in_strk),
+                })
+                .collect(),
+            value_in_strk: value.value_in_strk(),
+            value_in_usd: value.to_usd(value.value_in_strk()),
+            delta_7d_in_strk: value.delta_7d,
+            delta_30d_in_strk: value.delta_30d,
+            daily_burn_in_strk: value.daily_burn(),
+            runway_days: value.runway_days(),
+        }
+    }
+}
+
+/// Returns the holdings of the gas tank valued in STRK and USD along with their evolution. Requires the admin api key of the instance.
+#[cfg(feature = "server")]
+pub async fn get_treasury_report_endpoint(ctx: &RequestContext<'_>, _request: TreasuryReportRequest) -> Result<TreasuryReportResponse, Error> {
+    ctx.validate_admin_api_key()?;
+
+    let report = ctx.execution.get_relayer_manager().treasury_report().await?;
+
+    Ok(report.into())
+}
@@ -0,0 +1,86 @@
+use paymaster_starknet::ChainID;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::Felt;
+#[cfg(feature = "server")]
+use tracing::error;
+
+#[cfg(feature = "server")]
+use crate::endpoint::RequestContext;
+#[cfg(feature = "server")]
+use crate::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SponsorUsageRequest {
+    /// Beginning of the time range as a unix timestamp in seconds
+    pub from: u64,
+
+    /// End of the time range as a unix timestamp in seconds
+    pub to: u64,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<ChainID>,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UserUsage {
+    #[serde_as(as = "UfeHex")]
+    pub user_address: Felt,
+
+    pub transactions: u64,
+
+    #[serde_as(as = "UfeHex")]
+    pub fees_spent_in_strk: Felt,
+}
+
+#[cfg(feature = "server")]
+impl From<paymaster_sponsoring::usage::UserUsage> for UserUsage {
+    fn from(value: paymaster_sponsoring::usage::UserUsage) -> Self {
+        Self {
+            user_address: value.user,
+            transactions: value.transactions,
+            fees_spent_in_strk: value.fees_spent,
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SponsorUsageResponse {
+    pub transactions: u64,
+
+    #[serde_as(as = "UfeHex")]
+    pub fees_spent_in_strk: Felt,
+
+    #[serde_as(as = "Option<UfeHex>")]
+    pub remaining_budget_in_strk: Option<Felt>,
+
+    pub users: Vec<UserUsage>,
+}
+
+#[cfg(feature = "server")]
+pub async fn get_sponsor_usage_endpoint(ctx: &RequestContext<'_>, request: SponsorUsageRequest) -> Result<SponsorUsageResponse, Error> {
+    ctx.validate_api_key().await?;
+    if request.from > request.to {
+        return Err(Error::InvalidTimeBounds);
+    }
+
+    let sponsor = ctx.api_key.clone().unwrap_or_default();
+    let usage = ctx
+        .usage
+        .usage(&sponsor, request.from, request.to, ctx.sponsoring.budget(&sponsor))
+        .await
+        .map_err(|e| {
+            error!("Failed to read the usage of the sponsor: {}", e);
+            Error::ServiceNotAvailable
+        })?;
+
+    Ok(SponsorUsageResponse {
+        transactions: usage.transactions,
+        fees_spent_in_strk: usage.fees_spent,
+        remaining_budget_in_strk: usage.remaining_budget,
+        users: usage.users.into_iter().map(UserUsage::from).collect(),
+    })
+}
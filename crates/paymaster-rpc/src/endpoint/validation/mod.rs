@@ -1,57 +1,250 @@
 use std::collections::HashSet;
 
-use starknet::core::types::Felt;
+use paymaster_common::metric;
+use paymaster_sponsoring::AuthenticatedApiKey;
+use paymaster_starknet::constants::{ChainTokens, Contract};
+use paymaster_starknet::contract::forwarder::Forwarder;
+use starknet::core::types::{Call, Felt};
+use starknet::macros::selector;
 
+use crate::context::{ApprovalAction, CallPolicyConfiguration, Configuration};
 use crate::endpoint::build::TransactionParameters;
-use crate::endpoint::common::ExecutionParameters;
+use crate::endpoint::common::{DeploymentParameters, ExecutionParameters};
 use crate::endpoint::RequestContext;
 use crate::Error;
 
+/// Maximum number of felts in the calldata of a single call
+pub const MAX_CALLDATA_LENGTH: usize = 5000;
+
+/// Upper bound (exclusive) of the contract addresses on Starknet, 2^251 - 256
+const ADDRESS_BOUND: Felt = Felt::from_hex_unchecked("0x7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff00");
+
 pub async fn check_service_is_available(ctx: &RequestContext<'_>) -> Result<(), Error> {
-    if ctx.context.execution.get_relayer_manager().count_enabled_relayers().await == 0 {
+    let relayers = ctx.context.execution.get_relayer_manager();
+    if !relayers.is_available() || relayers.count_enabled_relayers().await == 0 {
         return Err(Error::ServiceNotAvailable);
     }
 
     Ok(())
 }
 
-pub fn check_no_blacklisted_call(transaction: &TransactionParameters, contracts_blacklist: &HashSet<Felt>) -> Result<(), Error> {
-    let has_blacklisted_calls = transaction.calls().iter().any(|x| contracts_blacklist.contains(&x.to));
-    if !has_blacklisted_calls {
+pub async fn check_not_in_maintenance(ctx: &RequestContext<'_>) -> Result<(), Error> {
+    match ctx.maintenance.current().await {
+        Some(message) => Err(Error::Maintenance(message)),
+        None => Ok(()),
+    }
+}
+
+/// Reject the transactions calling a blacklisted contract, directly or through one of the batching entrypoints
+pub fn check_no_blacklisted_call(transaction: &TransactionParameters, policy: &CallPolicyConfiguration) -> Result<(), Error> {
+    if !policy.is_blacklisted(transaction.calls()) {
         return Ok(());
     }
 
+    metric!(counter[execution_request_rejected] = 1, reason = "blacklisted");
     Err(Error::BlacklistedCalls)
 }
 
-pub fn check_is_supported_token(transaction: &ExecutionParameters, supported_tokens: &HashSet<Felt>) -> Result<(), Error> {
-    if supported_tokens.contains(&transaction.gas_token()) {
+/// Reject the malformed transactions before they reach Starknet, whose errors would not tell the user what is wrong
+/// with their request
+pub fn check_transaction_is_well_formed(transaction: &TransactionParameters) -> Result<(), Error> {
+    check_address_in_range(transaction.user_address())?;
+
+    let calls = transaction.calls();
+    match transaction {
+        TransactionParameters::Deploy { deployment } => check_address_in_range(deployment.address)?,
+        TransactionParameters::Invoke { .. } if calls.is_empty() => return Err(Error::EmptyCalls),
+        TransactionParameters::Invoke { .. } => {},
+        TransactionParameters::DeployAndInvoke { .. } if calls.is_empty() => return Err(Error::EmptyCalls),
+        TransactionParameters::DeployAndInvoke { deployment, .. } => {
+            check_address_in_range(deployment.address)?;
+            check_no_duplicate_deployment(deployment, calls)?;
+        },
+    }
+
+    for (index, call) in calls.iter().enumerate() {
+        check_address_in_range(call.to)?;
+        if call.calldata.len() > MAX_CALLDATA_LENGTH {
+            return Err(Error::CalldataTooLong(index));
+        }
+    }
+
+    Ok(())
+}
+
+/// Ensure the address can be the one of a contract
+pub fn check_address_in_range(address: Felt) -> Result<(), Error> {
+    if address < ADDRESS_BOUND {
+        return Ok(());
+    }
+
+    Err(Error::AddressOutOfRange(address.to_hex_string()))
+}
+
+/// Reject the calls deploying through the UDC the account already deployed by the deployment parameters, the
+/// second deployment would revert the whole transaction
+fn check_no_duplicate_deployment(deployment: &DeploymentParameters, calls: &[Call]) -> Result<(), Error> {
+    let deploys_account = calls.iter().any(|call| {
+        call.to == Contract::UDC
+            && call.selector == selector!("deployContract")
+            && call.calldata.first() == Some(&deployment.class_hash)
+            && call.calldata.get(1) == Some(&deployment.salt)
+    });
+    if !deploys_account {
+        return Ok(());
+    }
+
+    Err(Error::DuplicateDeployment)
+}
+
+/// Check the sponsor who made the request has enough budget left to pay `fee_in_strk`
+pub async fn check_within_budget(ctx: &RequestContext<'_>, fee_in_strk: Felt) -> Result<(), Error> {
+    match ctx.remaining_budget().await? {
+        Some(remaining) if remaining < fee_in_strk => {
+            metric!(counter[execution_request_rejected] = 1, reason = "budget_exhausted");
+            Err(Error::BudgetExhausted)
+        },
+        _ => Ok(()),
+    }
+}
+
+pub fn check_is_supported_token(transaction: &ExecutionParameters, tokens: &ChainTokens, supported_tokens: &HashSet<Felt>) -> Result<(), Error> {
+    if supported_tokens.contains(&transaction.gas_token(tokens)) {
         return Ok(());
     }
 
     Err(Error::TokenNotSupported)
 }
 
-pub async fn check_is_allowed_fee_mode(ctx: &RequestContext<'_>, params: &ExecutionParameters) -> Result<(), Error> {
+/// Returns the api key authenticated when the transaction is sponsored
+pub async fn check_is_allowed_fee_mode(ctx: &RequestContext<'_>, params: &ExecutionParameters) -> Result<Option<AuthenticatedApiKey>, Error> {
     if !params.fee_mode().is_sponsored() {
+        return Ok(None);
+    }
+
+    Ok(Some(ctx.validate_api_key().await?))
+}
+
+/// Ensure the api key is allowed to sponsor each of the calls, the calls to the batching entrypoints being checked
+/// through the calls they batch. Calls that cannot be decoded (`None`) are only accepted when the api key is not
+/// restricted to a scope.
+pub fn check_calls_in_scope(api_key: &AuthenticatedApiKey, policy: &CallPolicyConfiguration, calls: Option<&[Call]>) -> Result<(), Error> {
+    let Some(scope) = &api_key.scope else {
         return Ok(());
+    };
+
+    let out_of_scope = match calls.and_then(|x| policy.expand_calls(x)) {
+        Some(calls) => scope.find_out_of_scope(&calls).map(|x| x.to.to_hex_string()),
+        None => Some("undecoded calls".to_string()),
+    };
+
+    match out_of_scope {
+        Some(contract) => {
+            metric!(counter[execution_request_rejected] = 1, reason = "out_of_scope");
+            Err(Error::CallOutOfScope(contract))
+        },
+        None => Ok(()),
     }
+}
 
-    ctx.validate_api_key().await?;
-    Ok(())
+/// Ensure the sponsored calls, including the calls they batch, do not transfer nor approve more than the limit of
+/// each token. Calls that cannot be decoded (`None`) are only accepted when no token is limited.
+pub fn check_transfers_within_limits(policy: &CallPolicyConfiguration, calls: Option<&[Call]>) -> Result<(), Error> {
+    if policy.transfer_limits.is_empty() {
+        return Ok(());
+    }
+
+    let over_limit = match calls.and_then(|x| policy.expand_calls(x)) {
+        Some(calls) => policy.find_transfer_over_limit(&calls).map(|x| x.to_hex_string()),
+        None => Some("undecoded calls".to_string()),
+    };
+
+    match over_limit {
+        Some(token) => {
+            metric!(counter[execution_request_rejected] = 1, reason = "transfer_limit");
+            Err(Error::TransferLimitExceeded(token))
+        },
+        None => Ok(()),
+    }
+}
+
+/// Ensure the sponsored calls, including the calls they batch, make no suspicious approval the api key is not trusted
+/// with. Calls that cannot be decoded (`None`) are only accepted when the detection of the approvals is disabled.
+pub fn check_no_suspicious_approval(api_key: &AuthenticatedApiKey, policy: &CallPolicyConfiguration, calls: Option<&[Call]>) -> Result<(), Error> {
+    let Some(approvals) = &policy.approvals else {
+        return Ok(());
+    };
+
+    let suspicious = match calls.and_then(|x| policy.expand_calls(x)) {
+        Some(calls) => calls
+            .iter()
+            .filter(|call| approvals.is_suspicious(call))
+            .find(|call| match approvals.action {
+                ApprovalAction::Block => true,
+                ApprovalAction::RequireScope => !api_key.scope.as_ref().is_some_and(|x| x.explicitly_allows(call)),
+            })
+            .map(|x| x.to.to_hex_string()),
+        None => Some("undecoded calls".to_string()),
+    };
+
+    match suspicious {
+        Some(contract) => {
+            metric!(counter[execution_request_rejected] = 1, reason = "suspicious_approval");
+            Err(Error::SuspiciousApproval(contract))
+        },
+        None => Ok(()),
+    }
+}
+
+/// Reject the sponsored calls, including the calls they batch, targeting one of the accounts of the paymaster or one
+/// of the admin functions of the forwarder, so that the sponsorship cannot be turned against the infrastructure of
+/// the operator. Calls that cannot be decoded are rejected since their targets cannot be checked.
+pub fn check_no_call_to_operator(configuration: &Configuration, calls: Option<&[Call]>) -> Result<(), Error> {
+    let accounts = configuration.operator_accounts();
+    let forwarder = Forwarder::new(configuration.forwarder);
+
+    let operator_call = match calls.and_then(|x| configuration.rpc.call_policy.expand_calls(x)) {
+        Some(calls) => calls
+            .iter()
+            .find(|call| accounts.contains(&call.to) || forwarder.is_admin_call(call))
+            .map(|x| x.to.to_hex_string()),
+        None => Some("undecoded calls".to_string()),
+    };
+
+    match operator_call {
+        Some(contract) => {
+            metric!(counter[execution_request_rejected] = 1, reason = "operator_call");
+            Err(Error::OperatorCall(contract))
+        },
+        None => Ok(()),
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::{HashMap, HashSet};
+
     use jsonrpsee::Extensions;
-    use paymaster_sponsoring::{Client as AuthenticationClient, Configuration, SelfConfiguration};
+    use paymaster_sponsoring::scope::{CallScope, CallTarget};
+    use paymaster_sponsoring::{AuthenticatedApiKey, Client as AuthenticationClient, Configuration, SelfConfiguration};
     use paymaster_starknet::constants::Token;
+    use paymaster_starknet::{ChainID, Configuration as StarknetConfiguration};
+    use starknet::core::types::{Call, Felt};
+
+    use paymaster_starknet::constants::Contract;
+    use starknet::macros::selector;
 
-    use crate::endpoint::common::{ExecutionParameters, FeeMode, TipPriority};
-    use crate::endpoint::validation::check_is_allowed_fee_mode;
+    use crate::context::{ApprovalAction, ApprovalPolicyConfiguration, BatchingEntrypoint, CallPolicyConfiguration};
+    use crate::endpoint::build::{InvokeParameters, TransactionParameters};
+    use crate::endpoint::common::{DeploymentParameters, ExecutionParameters, FeeMode, TipPriority};
+    use crate::endpoint::validation::{
+        check_calls_in_scope, check_is_allowed_fee_mode, check_no_call_to_operator, check_no_suspicious_approval, check_transaction_is_well_formed,
+        check_transfers_within_limits, MAX_CALLDATA_LENGTH,
+    };
     use crate::endpoint::RequestContext;
     use crate::middleware::APIKey;
-    use crate::testing::TestEnvironment;
+    use crate::testing::{self, TestEnvironment};
+    use crate::Error;
 
     fn params(fee_mode: FeeMode) -> ExecutionParameters {
         ExecutionParameters::V1 { fee_mode, time_bounds: None }
@@ -64,7 +257,7 @@ mod tests {
     async fn self_sponsoring_is_working_properly() {
         let test = TestEnvironment::new().await;
         let mut context = test.context().clone();
-        let config = SelfConfiguration {api_key: "paymaster_123456".to_string(), sponsor_metadata: vec![],};
+        let config = SelfConfiguration {api_key: "paymaster_123456".to_string(), sponsor_metadata: vec![], budget: None, scope: None,};
         context.sponsoring = AuthenticationClient::new(&Configuration::SelfSponsoring(config));
     
         let no_api_key = RequestContext::new(&context, &Extensions::default());
@@ -82,6 +275,53 @@ mod tests {
         check_is_allowed_fee_mode(&dummy_api_key, &params(FeeMode::Sponsored{ tip: TipPriority::Normal})).await.unwrap();
     }
 
+    #[test]
+    fn calls_to_the_operator_are_rejected() {
+        let configuration = &testing::configuration(StarknetConfiguration {
+            chain_id: ChainID::Sepolia,
+            endpoint: "http://localhost:5050".to_string(),
+            timeout: 10,
+            fallbacks: vec![],
+            local_estimation: None,
+            endpoint_options: Default::default(),
+            tokens: None,
+        });
+
+        let call = |to: Felt, selector: Felt| Call { to, selector, calldata: vec![] };
+        let relayer = configuration.relayers.addresses[0];
+
+        check_no_call_to_operator(configuration, Some(&[call(Token::ETH_ADDRESS, selector!("transfer"))])).unwrap();
+        check_no_call_to_operator(configuration, Some(&[])).unwrap();
+
+        // The targets of the calls which cannot be decoded, e.g. the raw outside executions, cannot be checked
+        assert!(matches!(check_no_call_to_operator(configuration, None), Err(Error::OperatorCall(_))));
+
+        assert!(matches!(
+            check_no_call_to_operator(configuration, Some(&[call(relayer, selector!("__execute__"))])),
+            Err(Error::OperatorCall(_))
+        ));
+        assert!(matches!(
+            check_no_call_to_operator(configuration, Some(&[call(configuration.estimate_account.address, selector!("set_public_key"))])),
+            Err(Error::OperatorCall(_))
+        ));
+        assert!(matches!(
+            check_no_call_to_operator(configuration, Some(&[call(configuration.forwarder, selector!("set_whitelisted_address"))])),
+            Err(Error::OperatorCall(_))
+        ));
+
+        let mut configuration = configuration.clone();
+        configuration.rpc.call_policy.batching_entrypoints = vec![BatchingEntrypoint {
+            contract_address: None,
+            selector: selector!("multicall"),
+        }];
+        let malformed = Call {
+            to: Felt::ONE,
+            selector: selector!("multicall"),
+            calldata: vec![Felt::TWO],
+        };
+        assert!(matches!(check_no_call_to_operator(&configuration, Some(&[malformed])), Err(Error::OperatorCall(_))));
+    }
+
     // TODO: enable when we can fix starknet image
     #[ignore]
     #[tokio::test]
@@ -112,4 +352,171 @@ mod tests {
         assert!(check_is_allowed_fee_mode(&no_api_key, &params(FeeMode::Sponsored { tip: TipPriority::Normal})).await.is_err());
         assert!(check_is_allowed_fee_mode(&dummy_api_key, &params(FeeMode::Sponsored{ tip: TipPriority::Normal})).await.is_err());
     }
+
+    #[test]
+    fn scoped_api_key_only_sponsors_calls_in_scope() {
+        let call = |to: Felt| Call {
+            to,
+            selector: Felt::ONE,
+            calldata: vec![],
+        };
+        let scope = CallScope::new(vec![CallTarget {
+            contract_address: Felt::ONE,
+            selectors: vec![],
+        }]);
+        let policy = CallPolicyConfiguration::default();
+
+        let unrestricted = AuthenticatedApiKey::valid(vec![]);
+        check_calls_in_scope(&unrestricted, &policy, Some([call(Felt::TWO)].as_slice())).unwrap();
+        check_calls_in_scope(&unrestricted, &policy, None).unwrap();
+
+        let scoped = AuthenticatedApiKey::valid(vec![]).with_scope(Some(scope));
+        check_calls_in_scope(&scoped, &policy, Some([call(Felt::ONE)].as_slice())).unwrap();
+        assert!(check_calls_in_scope(&scoped, &policy, Some([call(Felt::ONE), call(Felt::TWO)].as_slice())).is_err());
+        assert!(check_calls_in_scope(&scoped, &policy, None).is_err());
+    }
+
+    #[test]
+    fn scope_applies_to_batched_calls() {
+        let multicall = Felt::from(0x1234);
+        let batch = |to: Felt| Call {
+            to: multicall,
+            selector: selector!("multicall"),
+            calldata: vec![Felt::ONE, to, Felt::ONE, Felt::ZERO],
+        };
+        let policy = CallPolicyConfiguration {
+            batching_entrypoints: vec![BatchingEntrypoint {
+                contract_address: Some(multicall),
+                selector: selector!("multicall"),
+            }],
+            ..Default::default()
+        };
+        let scoped = AuthenticatedApiKey::valid(vec![]).with_scope(Some(CallScope::new(vec![CallTarget {
+            contract_address: Felt::ONE,
+            selectors: vec![],
+        }])));
+
+        check_calls_in_scope(&scoped, &policy, Some([batch(Felt::ONE)].as_slice())).unwrap();
+        assert!(check_calls_in_scope(&scoped, &policy, Some([batch(Felt::TWO)].as_slice())).is_err());
+        assert!(check_calls_in_scope(&scoped, &CallPolicyConfiguration::default(), Some([batch(Felt::ONE)].as_slice())).is_err());
+    }
+
+    #[test]
+    fn transfers_over_the_limit_are_rejected() {
+        let token = Felt::from(0x10);
+        let policy = CallPolicyConfiguration {
+            transfer_limits: HashMap::from([(token, Felt::from(100))]),
+            ..Default::default()
+        };
+        let transfer = |amount: u64| Call {
+            to: token,
+            selector: selector!("transfer"),
+            calldata: vec![Felt::ONE, Felt::from(amount), Felt::ZERO],
+        };
+
+        check_transfers_within_limits(&policy, Some([transfer(100)].as_slice())).unwrap();
+        check_transfers_within_limits(&CallPolicyConfiguration::default(), None).unwrap();
+        assert!(matches!(
+            check_transfers_within_limits(&policy, Some([transfer(101)].as_slice())),
+            Err(Error::TransferLimitExceeded(_))
+        ));
+        assert!(check_transfers_within_limits(&policy, None).is_err());
+    }
+
+    #[test]
+    fn suspicious_approvals_require_an_explicit_scope() {
+        let token = Felt::from(0x10);
+        let approve = Call {
+            to: token,
+            selector: selector!("approve"),
+            calldata: vec![Felt::THREE, Felt::from(u128::MAX), Felt::from(u128::MAX)],
+        };
+        let policy = |action: ApprovalAction| CallPolicyConfiguration {
+            approvals: Some(ApprovalPolicyConfiguration {
+                action,
+                trusted_spenders: HashSet::new(),
+                unlimited_amount: ApprovalPolicyConfiguration::default_unlimited_amount(),
+                approvals_for_all: true,
+            }),
+            ..Default::default()
+        };
+        let scoped = |selectors: Vec<Felt>| {
+            AuthenticatedApiKey::valid(vec![]).with_scope(Some(CallScope::new(vec![CallTarget {
+                contract_address: token,
+                selectors,
+            }])))
+        };
+
+        let calls = [approve];
+        let calls = Some(calls.as_slice());
+        check_no_suspicious_approval(&scoped(vec![]), &CallPolicyConfiguration::default(), calls).unwrap();
+        assert!(matches!(
+            check_no_suspicious_approval(&scoped(vec![selector!("approve")]), &policy(ApprovalAction::Block), calls),
+            Err(Error::SuspiciousApproval(_))
+        ));
+        check_no_suspicious_approval(&scoped(vec![selector!("approve")]), &policy(ApprovalAction::RequireScope), calls).unwrap();
+        assert!(check_no_suspicious_approval(&scoped(vec![]), &policy(ApprovalAction::RequireScope), calls).is_err());
+        assert!(check_no_suspicious_approval(&AuthenticatedApiKey::valid(vec![]), &policy(ApprovalAction::RequireScope), calls).is_err());
+    }
+
+    fn invoke(calls: Vec<Call>) -> TransactionParameters {
+        TransactionParameters::Invoke {
+            invoke: InvokeParameters {
+                user_address: Felt::ONE,
+                calls,
+                session: false,
+            },
+        }
+    }
+
+    #[test]
+    fn malformed_transactions_are_rejected() {
+        let call = |to: Felt, calldata: Vec<Felt>| Call {
+            to,
+            selector: Felt::ONE,
+            calldata,
+        };
+
+        check_transaction_is_well_formed(&invoke(vec![call(Felt::TWO, vec![Felt::MAX])])).unwrap();
+
+        assert!(matches!(check_transaction_is_well_formed(&invoke(vec![])), Err(Error::EmptyCalls)));
+        assert!(matches!(
+            check_transaction_is_well_formed(&invoke(vec![call(Felt::MAX, vec![])])),
+            Err(Error::AddressOutOfRange(_))
+        ));
+        assert!(matches!(
+            check_transaction_is_well_formed(&invoke(vec![call(Felt::TWO, vec![]), call(Felt::TWO, vec![Felt::ONE; MAX_CALLDATA_LENGTH + 1])])),
+            Err(Error::CalldataTooLong(1))
+        ));
+    }
+
+    #[test]
+    fn account_deployed_twice_is_rejected() {
+        let deployment = DeploymentParameters {
+            address: Felt::ONE,
+            class_hash: Felt::TWO,
+            salt: Felt::THREE,
+            calldata: vec![],
+            sigdata: None,
+            version: 1,
+        };
+        let transaction = |calldata: Vec<Felt>| TransactionParameters::DeployAndInvoke {
+            deployment: deployment.clone(),
+            invoke: InvokeParameters {
+                user_address: Felt::ONE,
+                calls: vec![Call {
+                    to: Contract::UDC,
+                    selector: selector!("deployContract"),
+                    calldata,
+                }],
+                session: false,
+            },
+        };
+
+        check_transaction_is_well_formed(&transaction(vec![Felt::TWO, Felt::ONE, Felt::ZERO, Felt::ZERO])).unwrap();
+        assert!(matches!(
+            check_transaction_is_well_formed(&transaction(vec![Felt::TWO, Felt::THREE, Felt::ZERO, Felt::ZERO])),
+            Err(Error::DuplicateDeployment)
+        ));
+    }
 }
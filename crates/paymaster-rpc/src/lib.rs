@@ -1,42 +1,90 @@
 use jsonrpsee::core::Serialize;
 use jsonrpsee::proc_macros::rpc;
 use jsonrpsee::types::ErrorObject;
+#[cfg(feature = "server")]
 use paymaster_execution::Error as PaymasterExecutionError;
+#[cfg(feature = "server")]
 use paymaster_prices::Error as PriceError;
+#[cfg(feature = "server")]
 use paymaster_relayer::Error as RelayerError;
-use paymaster_starknet::Error as StarknetError;
+use paymaster_starknet::{ChainID, Error as StarknetError};
 use serde::Deserialize;
 use starknet::core::types::ContractExecutionError;
 use thiserror::Error;
 
+#[cfg(feature = "server")]
 mod context;
-pub use context::{Configuration, RPCConfiguration};
+#[cfg(feature = "server")]
+pub use context::{
+    ApprovalAction, ApprovalPolicyConfiguration, ApprovalQueueConfiguration, BatchingEntrypoint, BuildCacheConfiguration, CallPolicyConfiguration, Configuration,
+    Contexts, DeadLetterConfiguration, MaintenanceConfiguration, MaintenanceFlagConfiguration, RPCConfiguration, ReplayProtectionConfiguration,
+    TraceSamplingConfiguration,
+};
+#[cfg(feature = "server")]
+pub use paymaster_execution::{
+    analytics::AnalyticsConfiguration,
+    callback::{CallbacksConfiguration, SponsorCallbackConfiguration},
+    cost::CostAttributionConfiguration,
+    hook::{HookConfiguration, HooksConfiguration},
+    low_rpc::LowRpcConfiguration,
+    profitability::ProfitabilityConfiguration,
+    quote::QuoteTtlConfiguration,
+    recipient::FeeRecipientsConfiguration,
+    refund::RefundConfiguration,
+    reorg::ReorgConfiguration,
+    tokens::metadata::{TokenMetadata, TokenMetadataOverrides},
+    whitelist::ForwarderWhitelistConfiguration,
+};
 
 mod endpoint;
 pub use crate::endpoint::execute_raw::{DirectInvokeParameters, ExecuteDirectRequest, ExecuteDirectResponse, ExecuteDirectTransactionParameters};
+pub use endpoint::account::{AccountStatusRequest, AccountStatusResponse, OutsideExecutionVersion};
+pub use endpoint::accounting::{AccountBalance, AccountRole, AccountingSnapshotRequest, AccountingSnapshotResponse};
+pub use endpoint::approval::{ApprovalRequest, PendingApprovalEntry, PendingApprovalsRequest, PendingApprovalsResponse};
 pub use endpoint::build::{
     BuildTransactionRequest, BuildTransactionResponse, DeployAndInvokeTransaction, DeployTransaction, FeeEstimate, InvokeParameters, InvokeTransaction,
-    TransactionParameters,
+    ResponseSignature, TransactionParameters,
 };
-pub use endpoint::common::{DeploymentParameters, ExecutionParameters, FeeMode, TimeBounds};
-pub use endpoint::execute::{ExecutableInvokeParameters, ExecutableTransactionParameters, ExecuteRequest, ExecuteResponse};
+pub use endpoint::common::{AppliedTip, DeploymentParameters, ExecutionParameters, FeeMode, Session, SessionAuthorization, SignerSignature, TimeBounds};
+pub use endpoint::dead_letter::{DeadLetterEntry, DeadLetterRequest, DeadLettersRequest, DeadLettersResponse};
+pub use endpoint::execute::{
+    ClientNonce, ExecutableInvokeParameters, ExecutableTransactionParameters, ExecuteRequest, ExecuteResponse, ExecutionTimings, Finality, FinalityLevel, FinalityStatus,
+};
+pub use endpoint::fleet::{AvailabilityTransition, FleetStatusRequest, FleetStatusResponse, RelayerLockStatus};
+pub use endpoint::logging::SetLogFilterRequest;
+pub use endpoint::low_rpc::SetLowRpcModeRequest;
+pub use endpoint::maintenance::SetMaintenanceRequest;
+pub use endpoint::message::{EstimateMessageFeeRequest, L1Message, MessageFeeEstimate, SponsorMessageRequest, SponsorMessageResponse};
+pub use endpoint::receipt::{ExecutionReceipt, ExecutionReceiptRequest, ExecutionReceiptResponse, GasConsumed};
+pub use endpoint::refund::{Refund, RefundsRequest, RefundsResponse};
+pub use endpoint::simulation::{PricingOutcome, SimulatePricingRequest, SimulatePricingResponse};
+pub use endpoint::sponsorship::{CanSponsorRequest, CanSponsorResponse, SponsorshipPolicy};
 pub use endpoint::token::TokenPrice;
+pub use endpoint::treasury::{TreasuryHolding, TreasuryReportRequest, TreasuryReportResponse};
+pub use endpoint::usage::{SponsorUsageRequest, SponsorUsageResponse, UserUsage};
 
+#[cfg(feature = "server")]
 mod middleware;
+#[cfg(feature = "server")]
+pub use middleware::{MethodDeprecation, API_VERSION};
 
 #[cfg(test)]
 mod testing;
 
+#[cfg(feature = "http-client")]
 pub mod client;
+pub mod openrpc;
+#[cfg(feature = "server")]
 pub mod server;
 
-#[rpc(server, client)]
+#[cfg_attr(feature = "server", rpc(server, client))]
+#[cfg_attr(not(feature = "server"), rpc(client))]
 pub trait PaymasterAPI {
     #[method(name = "paymaster_health", with_extensions)]
     async fn health(&self) -> Result<bool, Error>;
 
     #[method(name = "paymaster_isAvailable", with_extensions)]
-    async fn is_available(&self) -> Result<bool, Error>;
+    async fn is_available(&self, chain_id: Option<ChainID>) -> Result<bool, Error>;
 
     #[method(name = "paymaster_buildTransaction", with_extensions)]
     async fn build_transaction(&self, params: BuildTransactionRequest) -> Result<BuildTransactionResponse, Error>;
@@ -47,8 +95,71 @@ pub trait PaymasterAPI {
     #[method(name = "paymaster_executeDirectTransaction", with_extensions)]
     async fn execute_direct_transaction(&self, params: ExecuteDirectRequest) -> Result<ExecuteDirectResponse, Error>;
 
+    #[method(name = "paymaster_getExecutionReceipt", with_extensions)]
+    async fn get_execution_receipt(&self, params: ExecutionReceiptRequest) -> Result<ExecutionReceiptResponse, Error>;
+
+    #[method(name = "paymaster_estimateMessageFee", with_extensions)]
+    async fn estimate_message_fee(&self, params: EstimateMessageFeeRequest) -> Result<MessageFeeEstimate, Error>;
+
+    #[method(name = "paymaster_sponsorMessage", with_extensions)]
+    async fn sponsor_message(&self, params: SponsorMessageRequest) -> Result<SponsorMessageResponse, Error>;
+
+    #[method(name = "paymaster_getRefunds", with_extensions)]
+    async fn get_refunds(&self, params: RefundsRequest) -> Result<RefundsResponse, Error>;
+
+    #[method(name = "paymaster_simulatePricing", with_extensions)]
+    async fn simulate_pricing(&self, params: SimulatePricingRequest) -> Result<SimulatePricingResponse, Error>;
+
+    #[method(name = "paymaster_setMaintenance", with_extensions)]
+    async fn set_maintenance(&self, params: SetMaintenanceRequest) -> Result<bool, Error>;
+
+    #[method(name = "paymaster_setLogFilter", with_extensions)]
+    async fn set_log_filter(&self, params: SetLogFilterRequest) -> Result<String, Error>;
+
+    #[method(name = "paymaster_setLowRpcMode", with_extensions)]
+    async fn set_low_rpc_mode(&self, params: SetLowRpcModeRequest) -> Result<bool, Error>;
+
     #[method(name = "paymaster_getSupportedTokens", with_extensions)]
-    async fn get_supported_tokens(&self) -> Result<Vec<TokenPrice>, Error>;
+    async fn get_supported_tokens(&self, chain_id: Option<ChainID>) -> Result<Vec<TokenPrice>, Error>;
+
+    #[method(name = "paymaster_getSponsorUsage", with_extensions)]
+    async fn get_sponsor_usage(&self, params: SponsorUsageRequest) -> Result<SponsorUsageResponse, Error>;
+
+    #[method(name = "paymaster_canSponsor", with_extensions)]
+    async fn can_sponsor(&self, params: CanSponsorRequest) -> Result<CanSponsorResponse, Error>;
+
+    #[method(name = "paymaster_getAccountStatus", with_extensions)]
+    async fn get_account_status(&self, params: AccountStatusRequest) -> Result<AccountStatusResponse, Error>;
+
+    #[method(name = "paymaster_getFleetStatus", with_extensions)]
+    async fn get_fleet_status(&self, params: FleetStatusRequest) -> Result<FleetStatusResponse, Error>;
+
+    #[method(name = "paymaster_getAccountingSnapshot", with_extensions)]
+    async fn get_accounting_snapshot(&self, params: AccountingSnapshotRequest) -> Result<AccountingSnapshotResponse, Error>;
+
+    #[method(name = "paymaster_getTreasuryReport", with_extensions)]
+    async fn get_treasury_report(&self, params: TreasuryReportRequest) -> Result<TreasuryReportResponse, Error>;
+
+    #[method(name = "paymaster_getDeadLetters", with_extensions)]
+    async fn get_dead_letters(&self, params: DeadLettersRequest) -> Result<DeadLettersResponse, Error>;
+
+    #[method(name = "paymaster_retryDeadLetter", with_extensions)]
+    async fn retry_dead_letter(&self, params: DeadLetterRequest) -> Result<ExecuteResponse, Error>;
+
+    #[method(name = "paymaster_discardDeadLetter", with_extensions)]
+    async fn discard_dead_letter(&self, params: DeadLetterRequest) -> Result<bool, Error>;
+
+    #[method(name = "paymaster_getPendingApprovals", with_extensions)]
+    async fn get_pending_approvals(&self, params: PendingApprovalsRequest) -> Result<PendingApprovalsResponse, Error>;
+
+    #[method(name = "paymaster_approveExecution", with_extensions)]
+    async fn approve_execution(&self, params: ApprovalRequest) -> Result<ExecuteResponse, Error>;
+
+    #[method(name = "paymaster_rejectExecution", with_extensions)]
+    async fn reject_execution(&self, params: ApprovalRequest) -> Result<bool, Error>;
+
+    #[method(name = "paymaster_discover", with_extensions)]
+    async fn discover(&self) -> Result<serde_json::Value, Error>;
 }
 
 #[derive(Deserialize, Error, Debug)]
@@ -56,6 +167,36 @@ pub enum Error {
     #[error("service not available")]
     ServiceNotAvailable,
 
+    #[error("service busy, retry after {0} seconds")]
+    ServiceBusy(u64),
+
+    #[error("chain not supported")]
+    ChainNotSupported,
+
+    #[error("transaction not found")]
+    TransactionNotFound,
+
+    #[error("typed data was not built by the paymaster or has expired")]
+    UnknownTypedData,
+
+    #[error("messages not consumed on L2 yet or reverted")]
+    MessageNotConsumed,
+
+    #[error("messages fee already sponsored")]
+    MessageAlreadySponsored,
+
+    #[error("recipient is not part of the payload of the messages")]
+    InvalidMessageRecipient,
+
+    #[error("max_fee_multiplier must be at least 1 and provider_fee_overhead positive")]
+    InvalidPricingParameters,
+
+    #[error("{0}")]
+    Maintenance(String),
+
+    #[error("invalid log filter: {0}")]
+    InvalidLogFilter(String),
+
     #[error("x-paymaster-api-key is invalid")]
     InvalidAPIKey,
 
@@ -65,9 +206,36 @@ pub enum Error {
     #[error("blacklisted calls")]
     BlacklistedCalls,
 
+    #[error("call to {0} is not allowed for this x-paymaster-api-key")]
+    CallOutOfScope(String),
+
+    #[error("budget of the x-paymaster-api-key is exhausted")]
+    BudgetExhausted,
+
+    #[error("calls transfer more of token {0} than allowed for a sponsored transaction")]
+    TransferLimitExceeded(String),
+
+    #[error("approval made by a call to {0} is not allowed for this x-paymaster-api-key")]
+    SuspiciousApproval(String),
+
+    #[error("call to {0} targets an account or an admin function of the paymaster")]
+    OperatorCall(String),
+
     #[error("invalid address")]
     InvalidAddress,
 
+    #[error("address {0} is out of range")]
+    AddressOutOfRange(String),
+
+    #[error("no calls to execute")]
+    EmptyCalls,
+
+    #[error("calldata of call {0} is too long")]
+    CalldataTooLong(usize),
+
+    #[error("account is deployed both by the deployment data and by one of the calls")]
+    DuplicateDeployment,
+
     #[error("class hash not supported")]
     ClassHashNotSupported,
 
@@ -77,14 +245,42 @@ pub enum Error {
     #[error("invalid time bounds")]
     InvalidTimeBounds,
 
+    #[error("transaction expires before it can be included")]
+    TransactionExpired,
+
     #[error("invalid signature")]
     InvalidSignature,
 
     #[error("max amount too low")]
     MaxAmountTooLow,
 
+    #[error("transaction awaits the approval of an operator ({0})")]
+    PendingApproval(String),
+
+    #[error("pending execution not found")]
+    ApprovalNotFound,
+
+    #[error("approval queue {0}")]
+    ApprovalQueue(String),
+
+    #[error("dead-letter entry not found")]
+    DeadLetterNotFound,
+
+    #[error("dead-letter queue {0}")]
+    DeadLetterQueue(String),
+
+    #[error("request was already received")]
+    ReplayedRequest,
+
+    #[error("invalid client nonce: {0}")]
+    ClientNonce(String),
+
     #[error("{0:?}")]
     Execution(ContractExecutionError),
+
+    /// Execution error along with a diagnosis of the state of the user, only returned when debug diagnostics are enabled
+    #[error("{0:?}")]
+    DiagnosedExecution(ContractExecutionError, serde_json::Value),
 }
 
 impl From<StarknetError> for Error {
@@ -99,27 +295,37 @@ impl From<StarknetError> for Error {
     }
 }
 
+#[cfg(feature = "server")]
 impl From<PriceError> for Error {
     fn from(_: PriceError) -> Self {
         Self::Execution(ContractExecutionError::Message("Internal price oracle error".to_string()))
     }
 }
 
+#[cfg(feature = "server")]
 impl From<RelayerError> for Error {
     fn from(value: RelayerError) -> Self {
         Self::Execution(ContractExecutionError::Message(value.to_string()))
     }
 }
 
+#[cfg(feature = "server")]
 impl From<PaymasterExecutionError> for Error {
     fn from(value: PaymasterExecutionError) -> Self {
-        Self::Execution(ContractExecutionError::Message(value.to_string()))
+        match value {
+            PaymasterExecutionError::Busy(retry_after) => Self::ServiceBusy(retry_after),
+            PaymasterExecutionError::TransactionExpired => Self::TransactionExpired,
+            e => Self::Execution(ContractExecutionError::Message(e.to_string())),
+        }
     }
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq)]
 pub struct ExecutionError {
     execution_error: ContractExecutionError,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    diagnosis: Option<serde_json::Value>,
 }
 
 impl<'a> From<Error> for ErrorObject<'a> {
@@ -127,15 +333,65 @@ impl<'a> From<Error> for ErrorObject<'a> {
         match value {
             Error::TokenNotSupported => ErrorObject::borrowed(151, "An error occurred (TOKEN_NOT_SUPPORTED)", None),
             Error::InvalidAddress => ErrorObject::borrowed(150, "An error occurred (INVALID_ADDRESS)", None),
+            Error::AddressOutOfRange(address) => ErrorObject::owned(150, "An error occurred (INVALID_ADDRESS)", Some(Error::AddressOutOfRange(address).to_string())),
             Error::InvalidSignature => ErrorObject::borrowed(153, "An error occurred (INVALID_SIGNATURE)", None),
             Error::MaxAmountTooLow => ErrorObject::borrowed(154, "An error occurred (MAX_AMOUNT_TOO_LOW)", None),
             Error::ClassHashNotSupported => ErrorObject::borrowed(155, "An error occurred (CLASS_HASH_NOT_SUPPORTED)", None),
             Error::InvalidTimeBounds => ErrorObject::borrowed(157, "An error occurred (INVALID_TIME_BOUNDS)", None),
+            Error::TransactionExpired => ErrorObject::owned(157, "An error occurred (INVALID_TIME_BOUNDS)", Some(Error::TransactionExpired.to_string())),
             Error::InvalidDeploymentData => ErrorObject::borrowed(158, "An error occurred (INVALID_DEPLOYMENT_DATA)", None),
-            Error::Execution(e) => ErrorObject::owned(156, "An error occurred (TRANSACTION_EXECUTION_ERROR)", Some(ExecutionError { execution_error: e })),
+            Error::DuplicateDeployment => ErrorObject::owned(158, "An error occurred (INVALID_DEPLOYMENT_DATA)", Some(Error::DuplicateDeployment.to_string())),
+            Error::Execution(e) => ErrorObject::owned(
+                156,
+                "An error occurred (TRANSACTION_EXECUTION_ERROR)",
+                Some(ExecutionError {
+                    execution_error: e,
+                    diagnosis: None,
+                }),
+            ),
+            Error::DiagnosedExecution(e, diagnosis) => ErrorObject::owned(
+                156,
+                "An error occurred (TRANSACTION_EXECUTION_ERROR)",
+                Some(ExecutionError {
+                    execution_error: e,
+                    diagnosis: Some(diagnosis),
+                }),
+            ),
             Error::BlacklistedCalls => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::BlacklistedCalls.to_string())),
+            Error::EmptyCalls => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::EmptyCalls.to_string())),
+            Error::CalldataTooLong(index) => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::CalldataTooLong(index).to_string())),
+            Error::CallOutOfScope(contract) => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::CallOutOfScope(contract).to_string())),
+            Error::BudgetExhausted => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::BudgetExhausted.to_string())),
+            Error::TransferLimitExceeded(token) => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::TransferLimitExceeded(token).to_string())),
+            Error::SuspiciousApproval(contract) => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::SuspiciousApproval(contract).to_string())),
+            Error::OperatorCall(contract) => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::OperatorCall(contract).to_string())),
             Error::ServiceNotAvailable => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::ServiceNotAvailable.to_string())),
+            Error::ServiceBusy(retry_after) => ErrorObject::owned(
+                163,
+                "An error occurred (UNKNOWN_ERROR)",
+                Some(serde_json::json!({ "message": Error::ServiceBusy(retry_after).to_string(), "retry_after": retry_after })),
+            ),
+            Error::ChainNotSupported => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::ChainNotSupported.to_string())),
+            Error::TransactionNotFound => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::TransactionNotFound.to_string())),
+            Error::UnknownTypedData => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::UnknownTypedData.to_string())),
+            Error::MessageNotConsumed => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::MessageNotConsumed.to_string())),
+            Error::MessageAlreadySponsored => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::MessageAlreadySponsored.to_string())),
+            Error::InvalidMessageRecipient => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::InvalidMessageRecipient.to_string())),
+            Error::InvalidPricingParameters => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::InvalidPricingParameters.to_string())),
+            Error::Maintenance(message) => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(message)),
+            Error::InvalidLogFilter(message) => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::InvalidLogFilter(message).to_string())),
             Error::InvalidAPIKey => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::InvalidAPIKey.to_string())),
+            Error::PendingApproval(id) => ErrorObject::owned(
+                163,
+                "An error occurred (UNKNOWN_ERROR)",
+                Some(serde_json::json!({ "message": Error::PendingApproval(id.clone()).to_string(), "approval_id": id })),
+            ),
+            Error::ApprovalNotFound => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::ApprovalNotFound.to_string())),
+            Error::ApprovalQueue(message) => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::ApprovalQueue(message).to_string())),
+            Error::DeadLetterNotFound => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::DeadLetterNotFound.to_string())),
+            Error::DeadLetterQueue(message) => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::DeadLetterQueue(message).to_string())),
+            Error::ReplayedRequest => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::ReplayedRequest.to_string())),
+            Error::ClientNonce(message) => ErrorObject::owned(163, "An error occurred (UNKNOWN_ERROR)", Some(Error::ClientNonce(message).to_string())),
         }
     }
 }
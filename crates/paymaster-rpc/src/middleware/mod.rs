@@ -3,3 +3,6 @@ pub use authentication::{APIKey, AuthenticationLayer};
 
 mod payload;
 pub use payload::PayloadFormatter;
+
+mod versioning;
+pub use versioning::{ApiVersioning, DeprecationHeadersLayer, MethodDeprecation, API_VERSION};
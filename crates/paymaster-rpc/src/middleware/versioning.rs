@@ -0,0 +1,198 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::future::BoxFuture;
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+use jsonrpsee::server::middleware::rpc::RpcServiceT;
+use jsonrpsee::server::{HttpBody, HttpRequest, HttpResponse};
+use jsonrpsee::types::Request;
+use jsonrpsee::MethodResponse;
+use paymaster_common::metric;
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+use tower::{Layer, Service};
+use tracing::debug;
+
+/// Version of the api served by the unversioned methods. The methods can also be called as `paymaster_v1_<method>`,
+/// so that the wallets can pin the version they were written against before the api evolves.
+pub const API_VERSION: &str = "v1";
+
+/// Method announced as deprecated to the wallets still calling it, which keeps being served until its sunset
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MethodDeprecation {
+    /// Unversioned name of the method, e.g. `paymaster_executeDirectTransaction`
+    pub method: String,
+
+    /// Method to call instead, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+
+    /// Date from which the method may stop being served, as an HTTP date
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sunset: Option<String>,
+}
+
+impl MethodDeprecation {
+    fn warning(&self) -> String {
+        match &self.replacement {
+            Some(replacement) => format!("{} is deprecated, use {} instead", self.method, replacement),
+            None => format!("{} is deprecated", self.method),
+        }
+    }
+
+    // Deprecation (RFC 9745), Sunset (RFC 8594) and Warning headers
+    fn apply(&self, headers: &mut HeaderMap) {
+        headers.insert(HeaderName::from_static("deprecation"), HeaderValue::from_static("true"));
+        if let Some(sunset) = self.sunset.as_deref().and_then(|x| HeaderValue::from_str(x).ok()) {
+            headers.insert(HeaderName::from_static("sunset"), sunset);
+        }
+        if let Ok(warning) = HeaderValue::from_str(&format!("299 - \"{}\"", self.warning())) {
+            headers.insert(HeaderName::from_static("warning"), warning);
+        }
+    }
+}
+
+impl Validate for MethodDeprecation {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.method.starts_with("paymaster_"), "method", "must be the name of a paymaster method");
+        if let Some(replacement) = &self.replacement {
+            report.ensure(replacement.starts_with("paymaster_"), "replacement", "must be the name of a paymaster method");
+        }
+        if let Some(sunset) = &self.sunset {
+            report.ensure(HeaderValue::from_str(sunset).is_ok(), "sunset", "must be a valid header value");
+        }
+    }
+}
+
+/// Returns the unversioned name of the method if it targets the version served. The methods of the other versions
+/// are left untouched so that they are reported as not found.
+fn resolve_method(method: &str) -> Option<String> {
+    let (version, name) = method.strip_prefix("paymaster_")?.split_once('_')?;
+
+    (version == API_VERSION).then(|| format!("paymaster_{}", name))
+}
+
+/// Serve the versioned methods under their unversioned name and attach the deprecation of the method called to the
+/// extensions of the response, turned into headers by [`DeprecationHeadersLayer`]
+#[derive(Clone)]
+pub struct ApiVersioning<S> {
+    service: S,
+    deprecations: Arc<HashMap<String, MethodDeprecation>>,
+}
+
+impl<S> ApiVersioning<S> {
+    pub fn new(service: S, deprecations: &[MethodDeprecation]) -> Self {
+        Self {
+            service,
+            deprecations: Arc::new(deprecations.iter().map(|x| (x.method.clone(), x.clone())).collect()),
+        }
+    }
+}
+
+impl<'a, S> RpcServiceT<'a> for ApiVersioning<S>
+where
+    S: RpcServiceT<'a> + Send + Sync + Clone + 'static,
+{
+    type Future = BoxFuture<'a, MethodResponse>;
+
+    fn call(&self, mut request: Request<'a>) -> Self::Future {
+        let service = self.service.clone();
+
+        if let Some(method) = resolve_method(&request.method) {
+            request.method = Cow::Owned(method);
+        }
+
+        let deprecation = self.deprecations.get(request.method.as_ref()).cloned();
+        Box::pin(async move {
+            let mut response = service.call(request).await;
+            if let Some(deprecation) = deprecation {
+                debug!(method = %deprecation.method, "deprecated method called");
+                metric!(counter[rpc_deprecated_method_call] = 1, method = deprecation.method.clone());
+                response.extensions_mut().insert(deprecation);
+            }
+
+            response
+        })
+    }
+}
+
+/// Set the deprecation headers of the responses to the calls of a deprecated method
+#[derive(Debug, Clone)]
+pub struct DeprecationHeadersLayer;
+
+impl<S> Layer<S> for DeprecationHeadersLayer {
+    type Service = DeprecationHeaders<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DeprecationHeaders { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DeprecationHeaders<S> {
+    inner: S,
+}
+
+impl<S> Service<HttpRequest<HttpBody>> for DeprecationHeaders<S>
+where
+    S: Service<HttpRequest, Response = HttpResponse<HttpBody>>,
+    S::Future: Send + 'static,
+{
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: HttpRequest<HttpBody>) -> Self::Future {
+        let response = self.inner.call(req);
+
+        Box::pin(async move {
+            let mut response = response.await?;
+            if let Some(deprecation) = response.extensions().get::<MethodDeprecation>().cloned() {
+                deprecation.apply(response.headers_mut());
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::HeaderMap;
+
+    use crate::middleware::versioning::{resolve_method, MethodDeprecation};
+
+    #[test]
+    fn versioned_methods_are_served_under_their_unversioned_name() {
+        assert_eq!(resolve_method("paymaster_v1_buildTransaction").as_deref(), Some("paymaster_buildTransaction"));
+        assert_eq!(resolve_method("paymaster_buildTransaction"), None);
+
+        // Versions which are not served are reported as not found
+        assert_eq!(resolve_method("paymaster_v2_buildTransaction"), None);
+    }
+
+    #[test]
+    fn deprecation_is_announced_in_the_headers() {
+        let deprecation = MethodDeprecation {
+            method: "paymaster_executeDirectTransaction".to_string(),
+            replacement: Some("paymaster_executeTransaction".to_string()),
+            sunset: Some("Wed, 31 Dec 2025 23:59:59 GMT".to_string()),
+        };
+
+        let mut headers = HeaderMap::new();
+        deprecation.apply(&mut headers);
+
+        assert_eq!(headers["deprecation"], "true");
+        assert_eq!(headers["sunset"], "Wed, 31 Dec 2025 23:59:59 GMT");
+        assert!(headers["warning"]
+            .to_str()
+            .unwrap()
+            .contains("use paymaster_executeTransaction instead"));
+    }
+}
@@ -0,0 +1,927 @@
+use serde_json::{json, Map, Value};
+
+/// Method of the API along with the schemas of its parameters and result
+struct Method {
+    name: &'static str,
+    summary: &'static str,
+    params: &'static [(&'static str, &'static str)],
+    result: &'static str,
+}
+
+const METHODS: &[Method] = &[
+    Method {
+        name: "paymaster_health",
+        summary: "Returns true if the service is up",
+        params: &[],
+        result: "Boolean",
+    },
+    Method {
+        name: "paymaster_isAvailable",
+        summary: "Returns true if the service can currently execute transactions",
+        params: &[("chain_id", "ChainID")],
+        result: "Boolean",
+    },
+    Method {
+        name: "paymaster_buildTransaction",
+        summary: "Build the typed data to sign in order to execute the given transaction, along with its fee estimate",
+        params: &[("params", "BuildTransactionRequest")],
+        result: "BuildTransactionResponse",
+    },
+    Method {
+        name: "paymaster_executeTransaction",
+        summary: "Execute a transaction built with paymaster_buildTransaction and signed by the user",
+        params: &[("params", "ExecuteRequest")],
+        result: "ExecuteResponse",
+    },
+    Method {
+        name: "paymaster_executeDirectTransaction",
+        summary: "Execute an outside execution built and signed by the user",
+        params: &[("params", "ExecuteDirectRequest")],
+        result: "ExecuteDirectResponse",
+    },
+    Method {
+        name: "paymaster_getExecutionReceipt",
+        summary: "Returns the receipt of a transaction executed by the paymaster",
+        params: &[("params", "ExecutionReceiptRequest")],
+        result: "ExecutionReceiptResponse",
+    },
+    Method {
+        name: "paymaster_estimateMessageFee",
+        summary: "Estimate the fee to pay on L1 to send the given message to L2",
+        params: &[("params", "EstimateMessageFeeRequest")],
+        result: "MessageFeeEstimate",
+    },
+    Method {
+        name: "paymaster_sponsorMessage",
+        summary: "Reimburse on L2 the fee paid on L1 to send the messages of the given transaction",
+        params: &[("params", "SponsorMessageRequest")],
+        result: "SponsorMessageResponse",
+    },
+    Method {
+        name: "paymaster_getRefunds",
+        summary: "Returns the refunds of the users who were overcharged",
+        params: &[("params", "RefundsRequest")],
+        result: "RefundsResponse",
+    },
+    Method {
+        name: "paymaster_simulatePricing",
+        summary: "Replay the recent transactions with other pricing parameters. Requires the admin api key",
+        params: &[("params", "SimulatePricingRequest")],
+        result: "SimulatePricingResponse",
+    },
+    Method {
+        name: "paymaster_setMaintenance",
+        summary: "Enable or disable the maintenance mode. Requires the admin api key",
+        params: &[("params", "SetMaintenanceRequest")],
+        result: "Boolean",
+    },
+    Method {
+        name: "paymaster_setLogFilter",
+        summary: "Change the log filter directives at runtime. Requires the admin api key",
+        params: &[("params", "SetLogFilterRequest")],
+        result: "String",
+    },
+    Method {
+        name: "paymaster_setLowRpcMode",
+        summary: "Enable or disable the low-RPC mode, which reuses the recent estimates and widens the quotes. Requires the admin api key",
+        params: &[("params", "SetLowRpcModeRequest")],
+        result: "Boolean",
+    },
+    Method {
+        name: "paymaster_getSupportedTokens",
+        summary: "Returns the tokens which can be used to pay the fee, along with their price",
+        params: &[("chain_id", "ChainID")],
+        result: "TokenPrices",
+    },
+    Method {
+        name: "paymaster_getSponsorUsage",
+        summary: "Returns the usage of the sponsor identified by the api key over the given time range",
+        params: &[("params", "SponsorUsageRequest")],
+        result: "SponsorUsageResponse",
+    },
+    Method {
+        name: "paymaster_canSponsor",
+        summary: "Returns whether the given transaction would be sponsored with the api key, the policy which matched and the remaining budget",
+        params: &[("params", "CanSponsorRequest")],
+        result: "CanSponsorResponse",
+    },
+    Method {
+        name: "paymaster_getAccountStatus",
+        summary: "Returns whether the given account is deployed and supported by the paymaster",
+        params: &[("params", "AccountStatusRequest")],
+        result: "AccountStatusResponse",
+    },
+    Method {
+        name: "paymaster_getFleetStatus",
+        summary: "Returns the status of the relayers. Requires the admin api key",
+        params: &[("params", "FleetStatusRequest")],
+        result: "FleetStatusResponse",
+    },
+    Method {
+        name: "paymaster_getAccountingSnapshot",
+        summary: "Returns the balances of the gas tank and of the relayers at the latest block. Requires the admin api key",
+        params: &[("params", "AccountingSnapshotRequest")],
+        result: "AccountingSnapshotResponse",
+    },
+    Method {
+        name: "paymaster_getTreasuryReport",
+        summary: "Returns the holdings of the gas tank valued in STRK and USD with their evolution and burn rate. Requires the admin api key",
+        params: &[("params", "TreasuryReportRequest")],
+        result: "TreasuryReportResponse",
+    },
+    Method {
+        name: "paymaster_getDeadLetters",
+        summary: "Returns the most recent sponsored executions which failed. Requires the admin api key",
+        params: &[("params", "DeadLettersRequest")],
+        result: "DeadLettersResponse",
+    },
+    Method {
+        name: "paymaster_retryDeadLetter",
+        summary: "Execute again a sponsored execution which failed on behalf of its sponsor. Requires the admin api key",
+        params: &[("params", "DeadLetterRequest")],
+        result: "ExecuteResponse",
+    },
+    Method {
+        name: "paymaster_discardDeadLetter",
+        summary: "Remove a sponsored execution which failed from the dead-letter queue. Requires the admin api key",
+        params: &[("params", "DeadLetterRequest")],
+        result: "Boolean",
+    },
+    Method {
+        name: "paymaster_getPendingApprovals",
+        summary: "Returns the most recent sponsored executions waiting for the approval of an operator. Requires the admin api key",
+        params: &[("params", "PendingApprovalsRequest")],
+        result: "PendingApprovalsResponse",
+    },
+    Method {
+        name: "paymaster_approveExecution",
+        summary: "Execute a sponsored execution waiting for an approval on behalf of its sponsor. Requires the admin api key",
+        params: &[("params", "ApprovalRequest")],
+        result: "ExecuteResponse",
+    },
+    Method {
+        name: "paymaster_rejectExecution",
+        summary: "Remove a sponsored execution waiting for an approval without executing it. Requires the admin api key",
+        params: &[("params", "ApprovalRequest")],
+        result: "Boolean",
+    },
+    Method {
+        name: "paymaster_discover",
+        summary: "Returns the OpenRPC specification of the API",
+        params: &[],
+        result: "OpenRPC",
+    },
+];
+
+fn reference(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{name}") })
+}
+
+fn array(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+fn object(required: &[(&str, Value)], optional: &[(&str, Value)]) -> Value {
+    let properties: Map<String, Value> = required
+        .iter()
+        .chain(optional)
+        .map(|(name, schema)| (name.to_string(), schema.clone()))
+        .collect();
+    let required: Vec<&str> = required.iter().map(|(name, _)| *name).collect();
+
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+/// Object discriminated by the value of its `tag` field
+fn tagged(tag: &str, variants: &[(&str, Value)]) -> Value {
+    let variants: Vec<Value> = variants
+        .iter()
+        .map(|(name, schema)| {
+            json!({
+                "allOf": [
+                    { "type": "object", "properties": { tag: { "const": name } }, "required": [tag] },
+                    schema
+                ]
+            })
+        })
+        .collect();
+
+    json!({ "oneOf": variants })
+}
+
+fn hex(description: &str) -> Value {
+    json!({ "type": "string", "pattern": "^0x[a-fA-F0-9]+$", "description": description })
+}
+
+fn integer() -> Value {
+    json!({ "type": "integer", "minimum": 0 })
+}
+
+fn schemas() -> Map<String, Value> {
+    let felt = || reference("Felt");
+    let felts = || array(reference("Felt"));
+    let chain_id = || ("chain_id", reference("ChainID"));
+
+    let mut schemas = Map::new();
+    let mut add = |name: &str, schema: Value| {
+        schemas.insert(name.to_string(), schema);
+    };
+
+    add("Felt", hex("Field element encoded in hexadecimal"));
+    add("Boolean", json!({ "type": "boolean" }));
+    add("String", json!({ "type": "string" }));
+    add(
+        "ChainID",
+        json!({
+            "description": "Chain targeted by the request, routed to the principal chain of the instance when not provided",
+            "oneOf": [{ "enum": ["sepolia", "mainnet"] }, reference("Felt")]
+        }),
+    );
+    add("Call", object(&[("to", felt()), ("selector", felt()), ("calldata", felts())], &[]));
+    add("TypedData", json!({ "type": "object", "description": "SNIP-12 typed data to sign by the user" }));
+    add("OpenRPC", json!({ "type": "object", "description": "OpenRPC specification of the API" }));
+
+    add(
+        "DeploymentParameters",
+        object(
+            &[
+                ("address", felt()),
+                ("class_hash", felt()),
+                ("salt", felt()),
+                ("calldata", felts()),
+                ("version", integer()),
+            ],
+            &[("sigdata", felts())],
+        ),
+    );
+    add(
+        "Session",
+        object(
+            &[
+                ("expires_at", integer()),
+                ("policies_root", felt()),
+                ("metadata_hash", felt()),
+                ("session_key_guid", felt()),
+            ],
+            &[("guardian_key_guid", felt())],
+        ),
+    );
+    add("SignerSignature", object(&[("signer", felt()), ("r", felt()), ("s", felt())], &[]));
+    add(
+        "SessionAuthorization",
+        object(
+            &[
+                ("session", reference("Session")),
+                ("authorization", felts()),
+                ("session_key", felt()),
+                ("guardian_signature", reference("SignerSignature")),
+                ("proofs", array(felts())),
+            ],
+            &[("cache_authorization", json!({ "type": "boolean" }))],
+        ),
+    );
+    add("TimeBounds", object(&[("execute_after", integer()), ("execute_before", integer())], &[]));
+    add(
+        "TipPriority",
+        json!({
+            "oneOf": [
+                { "enum": ["slow", "normal", "fast"] },
+                object(&[("custom", integer())], &[])
+            ]
+        }),
+    );
+    add(
+        "FeeMode",
+        tagged(
+            "mode",
+            &[
+                ("default", object(&[("gas_token", felt())], &[("tip", reference("TipPriority"))])),
+                ("sponsored", object(&[], &[("tip", reference("TipPriority"))])),
+            ],
+        ),
+    );
+    add(
+        "ExecutionParameters",
+        tagged(
+            "version",
+            &[("0x1", object(&[("fee_mode", reference("FeeMode"))], &[("time_bounds", reference("TimeBounds"))]))],
+        ),
+    );
+    add(
+        "AppliedTip",
+        object(
+            &[("priority", reference("TipPriority")), ("strategy", json!({ "type": "string" })), ("tip", integer())],
+            &[("median_tip", integer())],
+        ),
+    );
+
+    add(
+        "InvokeParameters",
+        object(
+            &[("user_address", felt()), ("calls", array(reference("Call")))],
+            &[("session", json!({ "type": "boolean" }))],
+        ),
+    );
+    add(
+        "TransactionParameters",
+        tagged(
+            "type",
+            &[
+                ("deploy", object(&[("deployment", reference("DeploymentParameters"))], &[])),
+                ("invoke", object(&[("invoke", reference("InvokeParameters"))], &[])),
+                (
+                    "deploy_and_invoke",
+                    object(&[("deployment", reference("DeploymentParameters")), ("invoke", reference("InvokeParameters"))], &[]),
+                ),
+            ],
+        ),
+    );
+    add(
+        "BuildTransactionRequest",
+        object(
+            &[("transaction", reference("TransactionParameters")), ("parameters", reference("ExecutionParameters"))],
+            &[chain_id()],
+        ),
+    );
+    add(
+        "FeeEstimate",
+        object(
+            &[
+                ("gas_token_price_in_strk", felt()),
+                ("estimated_fee_in_strk", felt()),
+                ("estimated_fee_in_gas_token", felt()),
+                ("suggested_max_fee_in_strk", felt()),
+                ("suggested_max_fee_in_gas_token", felt()),
+            ],
+            &[("tip", reference("AppliedTip")), ("low_rpc", json!({ "type": "boolean" }))],
+        ),
+    );
+    add("ResponseSignature", object(&[("public_key", felt()), ("r", felt()), ("s", felt())], &[]));
+    add(
+        "BuildTransactionResponse",
+        tagged(
+            "type",
+            &[
+                (
+                    "deploy",
+                    object(
+                        &[
+                            ("deployment", reference("DeploymentParameters")),
+                            ("parameters", reference("ExecutionParameters")),
+                            ("fee", reference("FeeEstimate")),
+                        ],
+                        &[("signature", reference("ResponseSignature"))],
+                    ),
+                ),
+                (
+                    "invoke",
+                    object(
+                        &[
+                            ("typed_data", reference("TypedData")),
+                            ("message_hash", felt()),
+                            ("parameters", reference("ExecutionParameters")),
+                            ("fee", reference("FeeEstimate")),
+                        ],
+                        &[("signature", reference("ResponseSignature"))],
+                    ),
+                ),
+                (
+                    "deploy_and_invoke",
+                    object(
+                        &[
+                            ("deployment", reference("DeploymentParameters")),
+                            ("typed_data", reference("TypedData")),
+                            ("message_hash", felt()),
+                            ("parameters", reference("ExecutionParameters")),
+                            ("fee", reference("FeeEstimate")),
+                        ],
+                        &[("signature", reference("ResponseSignature"))],
+                    ),
+                ),
+            ],
+        ),
+    );
+
+    add(
+        "ExecutableInvokeParameters",
+        object(
+            &[("user_address", felt()), ("signature", felts())],
+            &[
+                ("typed_data", reference("TypedData")),
+                ("message_hash", felt()),
+                ("session", reference("SessionAuthorization")),
+            ],
+        ),
+    );
+    add(
+        "ExecutableTransactionParameters",
+        tagged(
+            "type",
+            &[
+                ("deploy", object(&[("deployment", reference("DeploymentParameters"))], &[])),
+                ("invoke", object(&[("invoke", reference("ExecutableInvokeParameters"))], &[])),
+                (
+                    "deploy_and_invoke",
+                    object(
+                        &[("deployment", reference("DeploymentParameters")), ("invoke", reference("ExecutableInvokeParameters"))],
+                        &[],
+                    ),
+                ),
+            ],
+        ),
+    );
+    add("ClientNonce", object(&[("nonce", felt()), ("public_key", felt()), ("signature", felts())], &[]));
+    add(
+        "ExecuteRequest",
+        object(
+            &[
+                ("transaction", reference("ExecutableTransactionParameters")),
+                ("parameters", reference("ExecutionParameters")),
+            ],
+            &[
+                ("finality", json!({ "enum": ["submitted", "pre_confirmed", "accepted_on_l2"] })),
+                chain_id(),
+                ("client_nonce", reference("ClientNonce")),
+            ],
+        ),
+    );
+    add(
+        "Finality",
+        object(
+            &[(
+                "status",
+                json!({ "enum": ["submitted", "received", "candidate", "pre_confirmed", "accepted_on_l2", "accepted_on_l1"] }),
+            )],
+            &[("revert_reason", json!({ "type": "string" }))],
+        ),
+    );
+    add(
+        "ExecuteResponse",
+        object(
+            &[("transaction_hash", felt()), ("tracking_id", felt())],
+            &[
+                ("tip", reference("AppliedTip")),
+                ("timings", object(&[("lock_acquisition_ms", integer()), ("submission_ms", integer())], &[])),
+                ("finality", reference("Finality")),
+            ],
+        ),
+    );
+    add(
+        "ExecuteDirectRequest",
+        object(
+            &[
+                (
+                    "transaction",
+                    tagged(
+                        "type",
+                        &[(
+                            "invoke",
+                            object(
+                                &[("invoke", object(&[("user_address", felt()), ("execute_from_outside_call", reference("Call"))], &[]))],
+                                &[],
+                            ),
+                        )],
+                    ),
+                ),
+                ("parameters", reference("ExecutionParameters")),
+            ],
+            &[chain_id(), ("client_nonce", reference("ClientNonce"))],
+        ),
+    );
+    add(
+        "ExecuteDirectResponse",
+        object(&[("transaction_hash", felt()), ("tracking_id", felt())], &[("tip", reference("AppliedTip"))]),
+    );
+
+    add("ExecutionReceiptRequest", object(&[("transaction_hash", felt())], &[chain_id()]));
+    add(
+        "ExecutionReceiptResponse",
+        tagged(
+            "status",
+            &[
+                ("pending", object(&[], &[])),
+                (
+                    "confirmed",
+                    object(
+                        &[
+                            ("transaction_hash", felt()),
+                            ("block_number", integer()),
+                            (
+                                "gas_consumed",
+                                object(&[("l1_gas", integer()), ("l1_data_gas", integer()), ("l2_gas", integer())], &[]),
+                            ),
+                            ("actual_fee_in_strk", felt()),
+                            ("gas_token_address", felt()),
+                            ("fee_in_gas_token", felt()),
+                            ("fee_charged_in_strk", felt()),
+                            ("provider_margin_in_strk", json!({ "type": "string", "description": "Signed decimal integer" })),
+                        ],
+                        &[("revert_reason", json!({ "type": "string" }))],
+                    ),
+                ),
+            ],
+        ),
+    );
+
+    add(
+        "L1Message",
+        object(
+            &[
+                ("from_address", hex("Ethereum address")),
+                ("to_address", felt()),
+                ("entry_point_selector", felt()),
+                ("payload", felts()),
+            ],
+            &[],
+        ),
+    );
+    add("EstimateMessageFeeRequest", object(&[("message", reference("L1Message"))], &[chain_id()]));
+    add(
+        "MessageFeeEstimate",
+        object(
+            &[
+                ("l1_gas_consumed", integer()),
+                ("l1_data_gas_consumed", integer()),
+                ("l2_gas_consumed", integer()),
+                ("overall_fee_in_wei", felt()),
+                ("overall_fee_in_strk", felt()),
+            ],
+            &[],
+        ),
+    );
+    add(
+        "SponsorMessageRequest",
+        object(&[("l1_transaction_hash", hex("Hash of an L1 transaction")), ("recipient", felt())], &[chain_id()]),
+    );
+    add(
+        "SponsorMessageResponse",
+        object(
+            &[("transaction_hash", felt()), ("l1_handler_transaction_hashes", felts()), ("fee_in_wei", felt())],
+            &[],
+        ),
+    );
+
+    add("RefundsRequest", object(&[], &[("user_address", felt()), chain_id()]));
+    add(
+        "RefundsResponse",
+        object(
+            &[
+                ("enabled", json!({ "type": "boolean" })),
+                ("outstanding", integer()),
+                ("outstanding_in_strk", felt()),
+                (
+                    "refunds",
+                    array(object(
+                        &[
+                            ("user_address", felt()),
+                            ("transaction_hash", felt()),
+                            ("token_address", felt()),
+                            ("amount", felt()),
+                            ("amount_in_strk", felt()),
+                            ("refund_transaction_hash", json!({ "oneOf": [felt(), { "type": "null" }] })),
+                            ("confirmed", json!({ "type": "boolean" })),
+                            ("timestamp", integer()),
+                        ],
+                        &[],
+                    )),
+                ),
+            ],
+            &[],
+        ),
+    );
+
+    add(
+        "SimulatePricingRequest",
+        object(
+            &[
+                ("max_fee_multiplier", json!({ "type": "number" })),
+                ("provider_fee_overhead", json!({ "type": "number" })),
+            ],
+            &[("limit", integer()), chain_id()],
+        ),
+    );
+    add(
+        "PricingOutcome",
+        object(
+            &[
+                ("max_fee_multiplier", json!({ "type": "number" })),
+                ("provider_fee_overhead", json!({ "type": "number" })),
+                ("charged_in_strk", felt()),
+                ("user_cost_in_strk", felt()),
+                ("max_fee_in_strk", felt()),
+                ("uncovered_transactions", integer()),
+            ],
+            &[],
+        ),
+    );
+    add(
+        "SimulatePricingResponse",
+        object(
+            &[
+                ("transactions", integer()),
+                ("paid_in_strk", felt()),
+                ("current", reference("PricingOutcome")),
+                ("proposed", reference("PricingOutcome")),
+                ("revenue_delta", json!({ "type": "integer" })),
+                ("user_cost_delta", json!({ "type": "integer" })),
+            ],
+            &[],
+        ),
+    );
+
+    add(
+        "SetMaintenanceRequest",
+        object(&[("enabled", json!({ "type": "boolean" }))], &[("message", json!({ "type": "string" })), chain_id()]),
+    );
+    add("SetLowRpcModeRequest", object(&[("enabled", json!({ "type": "boolean" }))], &[chain_id()]));
+    add(
+        "SetLogFilterRequest",
+        object(&[], &[("directives", json!({ "type": "string" })), ("duration", integer()), chain_id()]),
+    );
+    add(
+        "TokenPrices",
+        array(object(
+            &[("token_address", felt()), ("decimals", integer()), ("price_in_strk", felt())],
+            &[
+                ("symbol", json!({ "type": "string" })),
+                ("name", json!({ "type": "string" })),
+                ("min_fee_amount", felt()),
+            ],
+        )),
+    );
+
+    add("SponsorUsageRequest", object(&[("from", integer()), ("to", integer())], &[chain_id()]));
+    add(
+        "SponsorUsageResponse",
+        object(
+            &[
+                ("transactions", integer()),
+                ("fees_spent_in_strk", felt()),
+                ("remaining_budget_in_strk", json!({ "oneOf": [felt(), { "type": "null" }] })),
+                (
+                    "users",
+                    array(object(&[("user_address", felt()), ("transactions", integer()), ("fees_spent_in_strk", felt())], &[])),
+                ),
+            ],
+            &[],
+        ),
+    );
+
+    add("CanSponsorRequest", object(&[("transaction", reference("TransactionParameters"))], &[chain_id()]));
+    add(
+        "CanSponsorResponse",
+        object(
+            &[
+                ("sponsored", json!({ "type": "boolean" })),
+                ("remaining_budget_in_strk", json!({ "oneOf": [felt(), { "type": "null" }] })),
+            ],
+            &[
+                ("reason", json!({ "type": "string" })),
+                (
+                    "policy",
+                    tagged("type", &[("unrestricted", object(&[], &[])), ("scoped", object(&[("targets", felts())], &[]))]),
+                ),
+            ],
+        ),
+    );
+
+    add("AccountStatusRequest", object(&[("address", felt())], &[chain_id()]));
+    add(
+        "AccountStatusResponse",
+        object(
+            &[
+                ("address", felt()),
+                ("deployed", json!({ "type": "boolean" })),
+                ("supported", json!({ "type": "boolean" })),
+            ],
+            &[
+                ("class_hash", felt()),
+                ("outside_execution_version", json!({ "enum": ["v1", "v2"] })),
+                ("controller", json!({ "type": "boolean" })),
+            ],
+        ),
+    );
+
+    add("FleetStatusRequest", object(&[], &[chain_id()]));
+    add(
+        "FleetStatusResponse",
+        object(
+            &[
+                ("enabled_relayers", integer()),
+                (
+                    "relayers",
+                    array(object(
+                        &[("address", felt()), ("locked", json!({ "type": "boolean" }))],
+                        &[("holder", json!({ "type": "string" })), ("lock_age_seconds", integer()), ("cached_nonce", felt())],
+                    )),
+                ),
+            ],
+            &[
+                ("available", json!({ "type": "boolean" })),
+                (
+                    "availability_history",
+                    array(object(
+                        &[
+                            ("at", integer()),
+                            ("available", json!({ "type": "boolean" })),
+                            ("enabled_relayers", integer()),
+                            ("previous_state_seconds", integer()),
+                        ],
+                        &[],
+                    )),
+                ),
+            ],
+        ),
+    );
+
+    add("AccountingSnapshotRequest", object(&[], &[chain_id()]));
+    add(
+        "AccountingSnapshotResponse",
+        object(
+            &[
+                ("block_number", integer()),
+                ("taken_at", integer()),
+                (
+                    "balances",
+                    array(object(
+                        &[
+                            ("address", felt()),
+                            ("role", json!({ "enum": ["gas_tank", "relayer"] })),
+                            ("token", felt()),
+                            ("balance", felt()),
+                        ],
+                        &[],
+                    )),
+                ),
+                ("fee_token", felt()),
+                ("in_flight_cost_in_strk", felt()),
+            ],
+            &[],
+        ),
+    );
+
+    add("TreasuryReportRequest", object(&[], &[chain_id()]));
+    add(
+        "TreasuryReportResponse",
+        object(
+            &[
+                ("taken_at", integer()),
+                (
+                    "holdings",
+                    array(object(
+                        &[("token", felt()), ("balance", felt()), ("value_in_strk", felt())],
+                        &[("value_in_usd", json!({ "type": "number" }))],
+                    )),
+                ),
+                ("value_in_strk", felt()),
+            ],
+            &[
+                ("value_in_usd", json!({ "type": "number" })),
+                ("delta_7d_in_strk", json!({ "type": "number" })),
+                ("delta_30d_in_strk", json!({ "type": "number" })),
+                ("daily_burn_in_strk", json!({ "type": "number" })),
+                ("runway_days", json!({ "type": "number" })),
+            ],
+        ),
+    );
+
+    add("DeadLettersRequest", object(&[], &[("limit", integer()), chain_id()]));
+    add(
+        "DeadLettersResponse",
+        object(
+            &[(
+                "entries",
+                array(object(
+                    &[
+                        ("id", json!({ "type": "string" })),
+                        ("user_address", felt()),
+                        ("error", json!({ "type": "string" })),
+                        ("failed_at", integer()),
+                    ],
+                    &[("sponsor", json!({ "type": "string" }))],
+                )),
+            )],
+            &[],
+        ),
+    );
+    add("DeadLetterRequest", object(&[("id", json!({ "type": "string" }))], &[chain_id()]));
+
+    add("PendingApprovalsRequest", object(&[], &[("limit", integer()), chain_id()]));
+    add(
+        "PendingApprovalsResponse",
+        object(
+            &[(
+                "entries",
+                array(object(
+                    &[
+                        ("id", json!({ "type": "string" })),
+                        ("user_address", felt()),
+                        ("fee_in_strk", felt()),
+                        ("reason", json!({ "type": "string" })),
+                        ("parked_at", integer()),
+                    ],
+                    &[("sponsor", json!({ "type": "string" }))],
+                )),
+            )],
+            &[],
+        ),
+    );
+    add(
+        "ApprovalRequest",
+        object(
+            &[("id", json!({ "type": "string" }))],
+            &[("sponsor_api_key", json!({ "type": "string" })), chain_id()],
+        ),
+    );
+
+    schemas
+}
+
+/// Returns the OpenRPC specification of the API, from which the clients in other languages can be generated
+pub fn specification() -> Value {
+    let methods: Vec<Value> = METHODS
+        .iter()
+        .map(|method| {
+            let params: Vec<Value> = method
+                .params
+                .iter()
+                .map(|(name, schema)| json!({ "name": name, "required": *name != "chain_id", "schema": reference(schema) }))
+                .collect();
+
+            json!({
+                "name": method.name,
+                "summary": method.summary,
+                "paramStructure": "by-position",
+                "params": params,
+                "result": { "name": "result", "schema": reference(method.result) },
+            })
+        })
+        .collect();
+
+    json!({
+        "openrpc": "1.2.6",
+        "info": {
+            "title": "Paymaster API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "methods": methods,
+        "components": {
+            "schemas": schemas(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use serde_json::Value;
+
+    use crate::openrpc::specification;
+
+    fn references(value: &Value, into: &mut HashSet<String>) {
+        match value {
+            Value::Object(map) => {
+                if let Some(Value::String(reference)) = map.get("$ref") {
+                    into.insert(reference.trim_start_matches("#/components/schemas/").to_string());
+                }
+                map.values().for_each(|x| references(x, into));
+            },
+            Value::Array(values) => values.iter().for_each(|x| references(x, into)),
+            _ => {},
+        }
+    }
+
+    #[test]
+    fn every_method_of_the_api_is_specified() {
+        let specification = specification();
+        let specified: HashSet<&str> = specification["methods"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|x| x["name"].as_str().unwrap())
+            .collect();
+
+        let declared: HashSet<&str> = include_str!("lib.rs")
+            .lines()
+            .filter_map(|x| x.trim().strip_prefix("#[method(name = \""))
+            .map(|x| x.split('"').next().unwrap())
+            .collect();
+
+        assert!(!declared.is_empty());
+        assert_eq!(specified, declared);
+    }
+
+    #[test]
+    fn every_referenced_schema_is_defined() {
+        let specification = specification();
+
+        let mut referenced = HashSet::new();
+        references(&specification, &mut referenced);
+
+        let schemas = specification["components"]["schemas"].as_object().unwrap();
+        for reference in referenced {
+            assert!(schemas.contains_key(&reference), "{reference} is not defined");
+        }
+    }
+}
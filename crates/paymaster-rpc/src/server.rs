@@ -5,19 +5,43 @@ use jsonrpsee::server::{RpcServiceBuilder, ServerBuilder, ServerHandle};
 use paymaster_common::service::monitoring::trace_layer;
 use paymaster_common::service::Error as ServiceError;
 use paymaster_common::{measure_duration, metric};
+use paymaster_starknet::ChainID;
 use tower::ServiceBuilder;
 use tower_http::cors::CorsLayer;
 use tracing::{error, info, instrument, warn};
 
-use crate::context::Context;
+use crate::context::{Context, Contexts};
+use crate::endpoint::account::get_account_status_endpoint;
+use crate::endpoint::accounting::get_accounting_snapshot_endpoint;
+use crate::endpoint::approval::{approve_execution_endpoint, get_pending_approvals_endpoint, reject_execution_endpoint};
 use crate::endpoint::build::build_transaction_endpoint;
+use crate::endpoint::dead_letter::{discard_dead_letter_endpoint, get_dead_letters_endpoint, retry_dead_letter_endpoint};
 use crate::endpoint::execute::execute_endpoint;
 use crate::endpoint::execute_raw::{execute_direct_endpoint, ExecuteDirectRequest, ExecuteDirectResponse};
+use crate::endpoint::fleet::get_fleet_status_endpoint;
 use crate::endpoint::health::is_available_endpoint;
+use crate::endpoint::logging::set_log_filter_endpoint;
+use crate::endpoint::low_rpc::set_low_rpc_mode_endpoint;
+use crate::endpoint::maintenance::set_maintenance_endpoint;
+use crate::endpoint::message::{estimate_message_fee_endpoint, sponsor_message_endpoint};
+use crate::endpoint::receipt::get_execution_receipt_endpoint;
+use crate::endpoint::refund::get_refunds_endpoint;
+use crate::endpoint::simulation::simulate_pricing_endpoint;
+use crate::endpoint::sponsorship::can_sponsor_endpoint;
 use crate::endpoint::token::get_supported_tokens_endpoint;
+use crate::endpoint::treasury::get_treasury_report_endpoint;
+use crate::endpoint::usage::get_sponsor_usage_endpoint;
 use crate::endpoint::RequestContext;
-use crate::middleware::{AuthenticationLayer, PayloadFormatter};
-use crate::{BuildTransactionRequest, BuildTransactionResponse, Configuration, Error, ExecuteRequest, ExecuteResponse, PaymasterAPIServer, TokenPrice};
+use crate::middleware::{ApiVersioning, AuthenticationLayer, DeprecationHeadersLayer, PayloadFormatter};
+use crate::openrpc;
+use crate::{
+    AccountStatusRequest, AccountStatusResponse, AccountingSnapshotRequest, AccountingSnapshotResponse, ApprovalRequest, BuildTransactionRequest,
+    BuildTransactionResponse, CanSponsorRequest, CanSponsorResponse, Configuration, DeadLetterRequest, DeadLettersRequest, DeadLettersResponse, Error,
+    EstimateMessageFeeRequest, ExecuteRequest, ExecuteResponse, ExecutionReceiptRequest, ExecutionReceiptResponse, FleetStatusRequest, FleetStatusResponse,
+    MessageFeeEstimate, PaymasterAPIServer, PendingApprovalsRequest, PendingApprovalsResponse, RefundsRequest, RefundsResponse, SetLogFilterRequest,
+    SetLowRpcModeRequest, SetMaintenanceRequest, SimulatePricingRequest, SimulatePricingResponse, SponsorMessageRequest, SponsorMessageResponse, SponsorUsageRequest,
+    SponsorUsageResponse, TokenPrice, TreasuryReportRequest, TreasuryReportResponse,
+};
 
 #[macro_export]
 macro_rules! log_if_error {
@@ -34,30 +58,52 @@ macro_rules! log_if_error {
 }
 
 macro_rules! instrument_method {
+    // Capture the payload and the outcome of the requests sampled by the trace sampling
+    (traced $method: ident ($context: expr, $params: expr)) => {{
+        let trace = $context.trace_sampling.start($context.api_key.as_deref(), &$params);
+        let result = instrument_method!($method(&$context, $params));
+        trace.finish(&result);
+
+        result
+    }};
     ($method: ident ($($arg: expr),*)) => {{
         metric!(counter [ rpc_request ] = 1, method = stringify!($method));
 
         let (result, time) = measure_duration!(log_if_error!($method($($arg),*).await));
         metric!(histogram [ rpc_request_duration_milliseconds ] = time.as_millis(), method = stringify!($method));
         metric!(on error result => counter [ rpc_request_error ] = 1);
+        info!(method = stringify!($method), latency_ms = time.as_millis() as u64, success = result.is_ok(), "request served");
 
         result
     }};
 }
 
 pub struct PaymasterServer {
-    context: Context,
+    contexts: Contexts,
 }
 
 impl PaymasterServer {
-    pub fn new(configuration: &Configuration) -> Self {
-        Self {
-            context: Context::new(configuration.clone()),
-        }
+    pub fn new(configuration: &Configuration) -> Result<Self, ServiceError> {
+        Ok(Self {
+            contexts: Contexts::new(Context::new(configuration.clone()).map_err(ServiceError::from)?),
+        })
+    }
+
+    /// Serve an additional chain on the same server. Requests are routed to this chain
+    /// when their `chain_id` matches the one of the given configuration.
+    pub fn with_chain(mut self, configuration: &Configuration) -> Result<Self, ServiceError> {
+        self.contexts = self
+            .contexts
+            .with_chain(Context::new(configuration.clone()).map_err(ServiceError::from)?);
+        Ok(self)
     }
 
     pub async fn start(self) -> Result<ServerHandle, ServiceError> {
-        let url = format!("0.0.0.0:{}", self.context.configuration.rpc.port);
+        let url = format!("0.0.0.0:{}", self.contexts.principal().configuration.rpc.port);
+        for chain_id in self.contexts.chain_ids() {
+            info!("Serving chain {}", chain_id.as_identifier());
+        }
+
         info!("Starting RPC server at {}", url);
 
         // `trace_layer()` goes first so it wraps every other middleware —
@@ -67,9 +113,14 @@ impl PaymasterServer {
             .layer(trace_layer())
             .layer(CorsLayer::permissive())
             .layer(AuthenticationLayer)
-            .layer(ProxyGetRequestLayer::new("/health", "paymaster_health").unwrap());
+            .layer(DeprecationHeadersLayer)
+            .layer(ProxyGetRequestLayer::new("/health", "paymaster_health").unwrap())
+            .layer(ProxyGetRequestLayer::new("/openrpc.json", "paymaster_discover").unwrap());
 
-        let rpc_middleware = RpcServiceBuilder::new().layer_fn(PayloadFormatter::new);
+        let deprecations = self.contexts.principal().configuration.rpc.deprecated_methods.clone();
+        let rpc_middleware = RpcServiceBuilder::new()
+            .layer_fn(PayloadFormatter::new)
+            .layer_fn(move |service| ApiVersioning::new(service, &deprecations));
 
         let server = ServerBuilder::default()
             .max_connections(1024)
@@ -92,32 +143,157 @@ impl PaymasterAPIServer for PaymasterServer {
     }
 
     #[instrument(name = "paymaster_isAvailable", skip(self, ext))]
-    async fn is_available(&self, ext: &Extensions) -> Result<bool, Error> {
-        let context = RequestContext::new(&self.context, ext);
+    async fn is_available(&self, ext: &Extensions, chain_id: Option<ChainID>) -> Result<bool, Error> {
+        let context = RequestContext::new(self.contexts.resolve(chain_id.as_ref())?, ext);
         instrument_method!(is_available_endpoint(&context))
     }
 
     #[instrument(name = "paymaster_buildTransaction", skip(self, ext, params))]
     async fn build_transaction(&self, ext: &Extensions, params: BuildTransactionRequest) -> Result<BuildTransactionResponse, Error> {
-        let context = RequestContext::new(&self.context, ext);
-        instrument_method!(build_transaction_endpoint(&context, params))
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(traced build_transaction_endpoint(context, params))
     }
 
     #[instrument(name = "paymaster_executeTransaction", skip(self, ext, params))]
     async fn execute_transaction(&self, ext: &Extensions, params: ExecuteRequest) -> Result<ExecuteResponse, Error> {
-        let context = RequestContext::new(&self.context, ext);
-        instrument_method!(execute_endpoint(&context, params))
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(traced execute_endpoint(context, params))
     }
 
     #[instrument(name = "paymaster_executeDirectTransaction", skip(self, ext, params))]
     async fn execute_direct_transaction(&self, ext: &Extensions, params: ExecuteDirectRequest) -> Result<ExecuteDirectResponse, Error> {
-        let context = RequestContext::new(&self.context, ext);
-        instrument_method!(execute_direct_endpoint(&context, params))
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(traced execute_direct_endpoint(context, params))
+    }
+
+    #[instrument(name = "paymaster_getExecutionReceipt", skip(self, ext, params))]
+    async fn get_execution_receipt(&self, ext: &Extensions, params: ExecutionReceiptRequest) -> Result<ExecutionReceiptResponse, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(get_execution_receipt_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_estimateMessageFee", skip(self, ext, params))]
+    async fn estimate_message_fee(&self, ext: &Extensions, params: EstimateMessageFeeRequest) -> Result<MessageFeeEstimate, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(estimate_message_fee_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_sponsorMessage", skip(self, ext, params))]
+    async fn sponsor_message(&self, ext: &Extensions, params: SponsorMessageRequest) -> Result<SponsorMessageResponse, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(sponsor_message_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_getRefunds", skip(self, ext, params))]
+    async fn get_refunds(&self, ext: &Extensions, params: RefundsRequest) -> Result<RefundsResponse, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(get_refunds_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_simulatePricing", skip(self, ext, params))]
+    async fn simulate_pricing(&self, ext: &Extensions, params: SimulatePricingRequest) -> Result<SimulatePricingResponse, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(simulate_pricing_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_setMaintenance", skip(self, ext, params))]
+    async fn set_maintenance(&self, ext: &Extensions, params: SetMaintenanceRequest) -> Result<bool, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(set_maintenance_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_setLogFilter", skip(self, ext, params))]
+    async fn set_log_filter(&self, ext: &Extensions, params: SetLogFilterRequest) -> Result<String, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(set_log_filter_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_setLowRpcMode", skip(self, ext, params))]
+    async fn set_low_rpc_mode(&self, ext: &Extensions, params: SetLowRpcModeRequest) -> Result<bool, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(set_low_rpc_mode_endpoint(&context, params))
     }
 
     #[instrument(name = "paymaster_getSupportedTokens", skip(self, ext))]
-    async fn get_supported_tokens(&self, ext: &Extensions) -> Result<Vec<TokenPrice>, Error> {
-        let context = RequestContext::new(&self.context, ext);
+    async fn get_supported_tokens(&self, ext: &Extensions, chain_id: Option<ChainID>) -> Result<Vec<TokenPrice>, Error> {
+        let context = RequestContext::new(self.contexts.resolve(chain_id.as_ref())?, ext);
         instrument_method!(get_supported_tokens_endpoint(&context))
     }
+
+    #[instrument(name = "paymaster_getSponsorUsage", skip(self, ext, params))]
+    async fn get_sponsor_usage(&self, ext: &Extensions, params: SponsorUsageRequest) -> Result<SponsorUsageResponse, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(get_sponsor_usage_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_canSponsor", skip(self, ext, params))]
+    async fn can_sponsor(&self, ext: &Extensions, params: CanSponsorRequest) -> Result<CanSponsorResponse, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(can_sponsor_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_getAccountStatus", skip(self, ext, params))]
+    async fn get_account_status(&self, ext: &Extensions, params: AccountStatusRequest) -> Result<AccountStatusResponse, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(get_account_status_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_getFleetStatus", skip(self, ext, params))]
+    async fn get_fleet_status(&self, ext: &Extensions, params: FleetStatusRequest) -> Result<FleetStatusResponse, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(get_fleet_status_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_getAccountingSnapshot", skip(self, ext, params))]
+    async fn get_accounting_snapshot(&self, ext: &Extensions, params: AccountingSnapshotRequest) -> Result<AccountingSnapshotResponse, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(get_accounting_snapshot_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_getTreasuryReport", skip(self, ext, params))]
+    async fn get_treasury_report(&self, ext: &Extensions, params: TreasuryReportRequest) -> Result<TreasuryReportResponse, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(get_treasury_report_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_getDeadLetters", skip(self, ext, params))]
+    async fn get_dead_letters(&self, ext: &Extensions, params: DeadLettersRequest) -> Result<DeadLettersResponse, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(get_dead_letters_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_retryDeadLetter", skip(self, ext, params))]
+    async fn retry_dead_letter(&self, ext: &Extensions, params: DeadLetterRequest) -> Result<ExecuteResponse, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(retry_dead_letter_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_discardDeadLetter", skip(self, ext, params))]
+    async fn discard_dead_letter(&self, ext: &Extensions, params: DeadLetterRequest) -> Result<bool, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(discard_dead_letter_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_getPendingApprovals", skip(self, ext, params))]
+    async fn get_pending_approvals(&self, ext: &Extensions, params: PendingApprovalsRequest) -> Result<PendingApprovalsResponse, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(get_pending_approvals_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_approveExecution", skip(self, ext, params))]
+    async fn approve_execution(&self, ext: &Extensions, params: ApprovalRequest) -> Result<ExecuteResponse, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(approve_execution_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_rejectExecution", skip(self, ext, params))]
+    async fn reject_execution(&self, ext: &Extensions, params: ApprovalRequest) -> Result<bool, Error> {
+        let context = RequestContext::new(self.contexts.resolve(params.chain_id.as_ref())?, ext);
+        instrument_method!(reject_execution_endpoint(&context, params))
+    }
+
+    #[instrument(name = "paymaster_discover", skip(self))]
+    async fn discover(&self, _: &Extensions) -> Result<serde_json::Value, Error> {
+        Ok(openrpc::specification())
+    }
 }
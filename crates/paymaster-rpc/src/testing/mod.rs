@@ -10,9 +10,12 @@ use paymaster_relayer::lock::{LockLayerConfiguration, RelayerLock};
 use paymaster_relayer::RelayersConfiguration;
 use paymaster_starknet::constants::Token;
 pub use paymaster_starknet::testing::TestEnvironment as StarknetTestEnvironment;
-use paymaster_starknet::StarknetAccountConfiguration;
+use paymaster_starknet::{Configuration as StarknetConfiguration, StarknetAccountConfiguration};
 use starknet::core::types::Felt;
 use starknet::macros::felt;
+use testcontainers::core::{IntoContainerPort, WaitFor};
+use testcontainers::runners::AsyncRunner;
+use testcontainers::{ContainerAsync, GenericImage};
 
 use crate::context::{Context, RPCConfiguration};
 use crate::Configuration;
@@ -65,6 +68,21 @@ impl MockLockLayer for LockingLayer {
     }
 }
 
+pub type RedisContainer = ContainerAsync<GenericImage>;
+
+/// Start a Redis instance, returns its container along with its endpoint
+pub async fn redis_container() -> (RedisContainer, String) {
+    let container = GenericImage::new("redis", "7")
+        .with_exposed_port(6379.tcp())
+        .with_wait_for(WaitFor::message_on_stdout("Ready to accept connections"))
+        .start()
+        .await
+        .unwrap();
+    let port = container.get_host_port_ipv4(6379).await.unwrap();
+
+    (container, format!("redis://127.0.0.1:{}", port))
+}
+
 pub struct TestEnvironment {
     context: Context,
 
@@ -76,47 +94,8 @@ impl TestEnvironment {
     pub async fn new() -> Self {
         let starknet = StarknetTestEnvironment::new().await;
 
-        let configuration = Configuration {
-            rpc: RPCConfiguration { port: 12777 },
-
-            supported_tokens: HashSet::from([Token::ETH_ADDRESS, Token::usdc(starknet.chain_id()).address]),
-            forwarder: StarknetTestEnvironment::FORWARDER,
-            gas_tank: StarknetAccountConfiguration {
-                address: StarknetTestEnvironment::FORWARDER,
-                private_key: felt!("0x0"),
-            },
-
-            max_fee_multiplier: 3.0,
-            provider_fee_overhead: 0.1,
-
-            estimate_account: StarknetAccountConfiguration {
-                address: felt!("0x064b48806902a367c8598f4f95c305e8c1a1acba5f082d294a43793113115691"),
-                private_key: felt!("0x0000000000000000000000000000000071d7bb07b9a64f6f78ac4c816aff4da9"),
-            },
-
-            relayers: RelayersConfiguration {
-                private_key: StarknetTestEnvironment::ACCOUNT_3.private_key,
-                addresses: vec![StarknetTestEnvironment::ACCOUNT_3.address],
-
-                min_relayer_balance: Felt::ZERO,
-
-                lock: LockLayerConfiguration::Mock {
-                    retry_timeout: Duration::from_secs(5),
-                    lock_layer: Arc::new(LockingLayer),
-                },
-                rebalancing: paymaster_relayer::rebalancing::OptionalRebalancingConfiguration::initialize(None),
-            },
-
-            starknet: starknet.configuration(),
-            price: paymaster_prices::PriceConfiguration {
-                principal: paymaster_prices::PriceOracleConfiguration::Mock(Arc::new(PriceOracle)),
-                fallbacks: vec![],
-            },
-            sponsoring: paymaster_sponsoring::Configuration::none(),
-        };
-
         Self {
-            context: Context::new(configuration),
+            context: Context::new(configuration(starknet.configuration())).unwrap(),
 
             starknet,
         }
@@ -126,3 +105,86 @@ impl TestEnvironment {
         &self.context
     }
 }
+
+/// Configuration of the paymaster used by the tests, served by the given Starknet node. The tests which do not
+/// reach the node can use it with any Starknet configuration.
+pub fn configuration(starknet: StarknetConfiguration) -> Configuration {
+    Configuration {
+        rpc: RPCConfiguration {
+            port: 12777,
+            maintenance: Default::default(),
+            debug_diagnostics: false,
+            dead_letter: None,
+            approval_queue: None,
+            build_cache: None,
+            trace_sampling: None,
+            replay_protection: None,
+            call_policy: Default::default(),
+            deprecated_methods: vec![],
+            response_signing_key: None,
+        },
+
+        supported_tokens: HashSet::from([Token::ETH_ADDRESS, Token::usdc(&starknet.chain_id).address]),
+        token_metadata: Default::default(),
+        permit_tokens: HashSet::new(),
+        direct_fee_payment: false,
+        profitability: None,
+        quote_ttl: None,
+        low_rpc: Default::default(),
+        forwarder: StarknetTestEnvironment::FORWARDER,
+        forwarder_whitelist: None,
+        gas_tank: StarknetAccountConfiguration {
+            address: StarknetTestEnvironment::FORWARDER,
+            private_key: felt!("0x0"),
+        },
+        gas_tank_multisig: None,
+        fee_recipients: Default::default(),
+
+        max_fee_multiplier: 3.0,
+        provider_fee_overhead: 0.1,
+        fee_rounding: Default::default(),
+
+        estimate_account: StarknetAccountConfiguration {
+            address: felt!("0x064b48806902a367c8598f4f95c305e8c1a1acba5f082d294a43793113115691"),
+            private_key: felt!("0x0000000000000000000000000000000071d7bb07b9a64f6f78ac4c816aff4da9"),
+        },
+
+        relayers: RelayersConfiguration {
+            private_key: StarknetTestEnvironment::ACCOUNT_3.private_key,
+            addresses: vec![StarknetTestEnvironment::ACCOUNT_3.address],
+
+            min_relayer_balance: Felt::ZERO,
+
+            lock: LockLayerConfiguration::Mock {
+                retry_timeout: Duration::from_secs(5),
+                lock_layer: Arc::new(LockingLayer),
+            },
+            rebalancing: paymaster_relayer::rebalancing::OptionalRebalancingConfiguration::initialize(None),
+            max_in_flight_transactions: RelayersConfiguration::default_max_in_flight_transactions(),
+            execution_concurrency_factor: RelayersConfiguration::default_execution_concurrency_factor(),
+            spend_caps: Default::default(),
+            secondary: None,
+            gas_tank_top_up: None,
+            staking: None,
+            journal: None,
+            watchdog: None,
+            dedicated: vec![],
+            deployment_relayers: vec![],
+            treasury_history: None,
+            messaging: None,
+        },
+
+        starknet,
+        price: paymaster_prices::PriceConfiguration {
+            principal: paymaster_prices::PriceOracleConfiguration::Mock(Arc::new(PriceOracle)),
+            fallbacks: vec![],
+        },
+        sponsoring: paymaster_sponsoring::Configuration::none(),
+        refund: None,
+        reorg: None,
+        hooks: paymaster_execution::hook::HooksConfiguration::default(),
+        analytics: None,
+        cost_attribution: None,
+        callbacks: Default::default(),
+    }
+}
@@ -2,18 +2,28 @@ use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::str::FromStr;
 
+use paymaster_common::egress::EgressConfiguration;
 use paymaster_common::service::monitoring::Configuration as MonitoringConfiguration;
+use paymaster_common::validation::{Validate, ValidationReport};
 use paymaster_prices::avnu::AVNUPriceClientConfiguration;
 use paymaster_prices::coingecko::CoingeckoPriceClientConfiguration;
+use paymaster_prices::math::RoundingPolicy;
+use paymaster_relayer::multisig::MultisigConfiguration;
 use paymaster_relayer::RelayersConfiguration;
+use paymaster_rpc::{
+    AnalyticsConfiguration, CallbacksConfiguration, CostAttributionConfiguration, FeeRecipientsConfiguration, ForwarderWhitelistConfiguration, HooksConfiguration,
+    LowRpcConfiguration, ProfitabilityConfiguration, QuoteTtlConfiguration, RefundConfiguration, ReorgConfiguration, TokenMetadataOverrides,
+};
 use paymaster_sponsoring::Configuration as SponsoringConfiguration;
+use paymaster_starknet::probe::ProbeConfiguration;
 use paymaster_starknet::{Configuration as StarknetConfiguration, StarknetAccountConfiguration};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 use serde_with::serde_as;
 use starknet::core::types::Felt;
 
-use crate::core::context::environment::{JSONPath, Variables};
+use crate::core::context::environment::{JSONPath, Variables, VariablesResolver};
+use crate::core::logging::LoggingConfiguration;
 use crate::core::Error;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -41,25 +51,264 @@ pub struct Configuration {
     pub verbosity: VerbosityConfiguration,
     pub prometheus: Option<MonitoringConfiguration>,
 
+    /// Format and sampling of the logs
+    #[serde(default)]
+    pub logging: LoggingConfiguration,
+
+    /// Proxy and allowlist of the outbound requests, applied to every HTTP client of the paymaster
+    #[serde(default)]
+    pub egress: EgressConfiguration,
+
     pub rpc: paymaster_rpc::RPCConfiguration,
 
     pub forwarder: Felt,
+
+    /// Owner of the forwarder keeping the relayers and the estimate account whitelisted, so that the relayers added
+    /// or rotated are whitelisted when the instance starts
+    #[serde(default)]
+    pub forwarder_whitelist: Option<ForwarderWhitelistConfiguration>,
+
+    /// Tokens in which the fee can be paid, extended with the tokens of the registry when one is configured
+    #[serde(default)]
     pub supported_tokens: HashSet<Felt>,
 
+    /// File or `http(s)` url of a token registry listing the supported tokens along with their metadata
+    #[serde(default)]
+    pub token_registry: Option<String>,
+
+    /// Symbol, name, decimals or minimum fee of the supported tokens, needed for the tokens which AVNU does not list
+    #[serde(default)]
+    pub token_metadata: TokenMetadataOverrides,
+
+    /// Supported tokens whose fee is collected through a permit rather than a transfer
+    #[serde(default)]
+    pub permit_tokens: HashSet<Felt>,
+
+    /// Users transfer the fee straight to the relayer executing their transaction instead of the forwarder
+    #[serde(default)]
+    pub direct_fee_payment: bool,
+
+    /// Reject, or re-quote, the transactions whose fee would not cover their cost with a minimum margin
+    #[serde(default)]
+    pub profitability: Option<ProfitabilityConfiguration>,
+
+    /// Shorten the validity of the quotes in volatile gas tokens instead of a fixed validity of one hour
+    #[serde(default)]
+    pub quote_ttl: Option<QuoteTtlConfiguration>,
+
+    /// Degraded mode reusing the recent estimates and refreshing the gas price less frequently when the quota of
+    /// the Starknet RPC is constrained. Toggled at runtime with `paymaster_setLowRpcMode`.
+    #[serde(default)]
+    pub low_rpc: LowRpcConfiguration,
+
     pub max_fee_multiplier: f32,
     pub provider_fee_overhead: f32,
 
+    /// Rounding of the fees converted from STRK to the gas token, with the maximum surplus it may add. Fees are
+    /// rounded up when not set.
+    #[serde(default)]
+    pub fee_rounding: RoundingPolicy,
+
     pub estimate_account: StarknetAccountConfiguration,
     pub gas_tank: StarknetAccountConfiguration,
 
+    /// Set when the gas tank is a multisig account
+    #[serde(default)]
+    pub gas_tank_multisig: Option<MultisigConfiguration>,
+
+    /// Addresses receiving the fee of some sponsors or gas tokens in place of the gas tank
+    #[serde(default)]
+    pub fee_recipients: FeeRecipientsConfiguration,
+
     pub relayers: RelayersConfiguration,
 
     pub starknet: StarknetConfiguration,
     pub price: PriceConfiguration,
     pub sponsoring: SponsoringConfiguration,
+
+    /// Probing of the Starknet endpoints performed at startup
+    #[serde(default)]
+    pub probe: ProbeConfiguration,
+
+    #[serde(default)]
+    pub refund: Option<RefundConfiguration>,
+
+    /// Detection of the reorgs of the chain, checking again the transactions executed recently and submitting again
+    /// the dropped ones
+    #[serde(default)]
+    pub reorg: Option<ReorgConfiguration>,
+
+    #[serde(default)]
+    pub hooks: HooksConfiguration,
+
+    /// Stream of the paymaster activity consumed by the data teams
+    #[serde(default)]
+    pub analytics: Option<AnalyticsConfiguration>,
+
+    /// Callbacks notifying the sponsors when their sponsored transactions are submitted, confirmed or reverted
+    #[serde(default)]
+    pub callbacks: CallbacksConfiguration,
+
+    /// Metrics attributing the fees collected, the STRK spent and the margin realized to the sponsors and gas tokens
+    #[serde(default)]
+    pub cost_attribution: Option<CostAttributionConfiguration>,
+
+    /// Additional chains served by the instance. Requests specifying one of these chains are
+    /// routed to it, all the others are served by the chain configured above.
+    #[serde(default)]
+    pub chains: Vec<ChainConfiguration>,
+}
+
+/// Configuration of an additional chain. The settings that are not chain specific (rpc, fees,
+/// price oracle and sponsoring) are shared with the principal chain, the settings that depend on the
+/// contracts or the accounts of the chain are never inherited from it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChainConfiguration {
+    pub starknet: StarknetConfiguration,
+
+    pub forwarder: Felt,
+
+    #[serde(default)]
+    pub forwarder_whitelist: Option<ForwarderWhitelistConfiguration>,
+
+    pub supported_tokens: HashSet<Felt>,
+
+    #[serde(default)]
+    pub token_registry: Option<String>,
+
+    #[serde(default)]
+    pub token_metadata: TokenMetadataOverrides,
+
+    #[serde(default)]
+    pub permit_tokens: HashSet<Felt>,
+
+    #[serde(default)]
+    pub direct_fee_payment: bool,
+
+    pub estimate_account: StarknetAccountConfiguration,
+    pub gas_tank: StarknetAccountConfiguration,
+
+    #[serde(default)]
+    pub gas_tank_multisig: Option<MultisigConfiguration>,
+
+    #[serde(default)]
+    pub fee_recipients: FeeRecipientsConfiguration,
+
+    pub relayers: RelayersConfiguration,
+
+    #[serde(default)]
+    pub refund: Option<RefundConfiguration>,
+
+    #[serde(default)]
+    pub reorg: Option<ReorgConfiguration>,
+}
+
+impl Validate for ChainConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.field("starknet", &self.starknet);
+        report.ensure(self.forwarder != Felt::ZERO, "forwarder", "must not be zero");
+        if let Some(forwarder_whitelist) = &self.forwarder_whitelist {
+            report.field("forwarder_whitelist", forwarder_whitelist);
+        }
+        report.ensure(!self.supported_tokens.is_empty(), "supported_tokens", "at least one token must be supported");
+        report.field("estimate_account", &self.estimate_account);
+        report.field("gas_tank", &self.gas_tank);
+        if let Some(multisig) = &self.gas_tank_multisig {
+            report.field("gas_tank_multisig", multisig);
+        }
+        report.field("fee_recipients", &self.fee_recipients);
+        report.field("relayers", &self.relayers);
+        if let Some(refund) = &self.refund {
+            report.field("refund", refund);
+        }
+        if let Some(reorg) = &self.reorg {
+            report.field("reorg", reorg);
+        }
+    }
 }
 
 impl Configuration {
+    /// Returns the configuration of the given additional chain, using the shared settings of this configuration.
+    /// Every field is listed so that a new setting has to be explicitly declared shared or chain specific.
+    pub fn for_chain(&self, chain: &ChainConfiguration) -> Self {
+        let Self {
+            verbosity,
+            prometheus,
+            logging,
+            egress,
+            rpc,
+            profitability,
+            quote_ttl,
+            low_rpc,
+            max_fee_multiplier,
+            provider_fee_overhead,
+            fee_rounding,
+            price,
+            sponsoring,
+            probe,
+            hooks,
+            analytics,
+            callbacks,
+            cost_attribution,
+
+            // Chain specific, taken from the chain
+            starknet: _,
+            forwarder: _,
+            forwarder_whitelist: _,
+            supported_tokens: _,
+            token_registry: _,
+            token_metadata: _,
+            permit_tokens: _,
+            direct_fee_payment: _,
+            estimate_account: _,
+            gas_tank: _,
+            gas_tank_multisig: _,
+            fee_recipients: _,
+            relayers: _,
+            refund: _,
+            reorg: _,
+            chains: _,
+        } = self.clone();
+
+        Self {
+            verbosity,
+            prometheus,
+            logging,
+            egress,
+            rpc,
+            profitability,
+            quote_ttl,
+            low_rpc,
+            max_fee_multiplier,
+            provider_fee_overhead,
+            fee_rounding,
+            price,
+            sponsoring,
+            probe,
+            hooks,
+            analytics,
+            callbacks,
+            cost_attribution,
+
+            starknet: chain.starknet.clone(),
+            forwarder: chain.forwarder,
+            forwarder_whitelist: chain.forwarder_whitelist.clone(),
+            supported_tokens: chain.supported_tokens.clone(),
+            token_registry: chain.token_registry.clone(),
+            token_metadata: chain.token_metadata.clone(),
+            permit_tokens: chain.permit_tokens.clone(),
+            direct_fee_payment: chain.direct_fee_payment,
+            estimate_account: chain.estimate_account,
+            gas_tank: chain.gas_tank,
+            gas_tank_multisig: chain.gas_tank_multisig.clone(),
+            fee_recipients: chain.fee_recipients.clone(),
+            relayers: chain.relayers.clone(),
+            refund: chain.refund.clone(),
+            reorg: chain.reorg.clone(),
+            chains: vec![],
+        }
+    }
+
     #[allow(dead_code)]
     pub fn from_file(path: &str) -> Result<Self, Error> {
         let data = fs::read(path).map_err(|e| Error::Configuration(e.to_string()))?;
@@ -158,6 +407,17 @@ impl Profile {
         Ok(Self(variables))
     }
 
+    /// Merge the layers of the configuration, see [`ConfigurationLayers`] for their precedence
+    pub fn layered(profile: &Profile, overlay: &Profile, environment: Variables, arguments: Variables) -> Result<Self, Error> {
+        let mut layered = Profile::empty();
+        layered.merge(profile);
+        layered.merge(overlay);
+        layered.insert_variables(environment)?;
+        layered.insert_variables(arguments)?;
+
+        Ok(layered)
+    }
+
     pub fn merge(&mut self, profile: &Profile) {
         #[rustfmt::skip]
         fn merge_rec(profile: &mut Map<String, Value>, other: &Map<String, Value>) {
@@ -200,6 +460,60 @@ impl Profile {
     }
 }
 
+/// Layers of the configuration, merged from the lowest to the highest precedence:
+///  1. the base profile (`--profile` or `PAYMASTER_PROFILE`), shared by every environment
+///  2. the overlay of the environment (`--overlay` or `PAYMASTER_OVERLAY`), holding only what differs from the base
+///  3. the environment variables prefixed by `PAYMASTER_`
+///  4. the command line arguments of the form `--xxx=yyy`
+///
+/// Objects are merged field by field, any other value (arrays included) is replaced by the one of the higher layer.
+pub struct ConfigurationLayers {
+    pub profile: Option<String>,
+    pub overlay: Option<String>,
+    pub environment: Variables,
+    pub arguments: Variables,
+}
+
+impl ConfigurationLayers {
+    /// Resolve the layers from the environment variables and the given arguments, which also locate the profile
+    /// and the overlay
+    pub fn resolve(resolver: &VariablesResolver, arguments: Variables) -> Result<Self, Error> {
+        let environment = resolver.resolve_environment()?;
+
+        let path = |name: &str| {
+            arguments
+                .get(name)
+                .or_else(|| environment.get(name))
+                .and_then(|x| x.as_str())
+                .filter(|x| !x.is_empty())
+                .map(|x| x.to_string())
+        };
+
+        Ok(Self {
+            profile: path("profile"),
+            overlay: path("overlay"),
+            environment,
+            arguments,
+        })
+    }
+
+    /// Returns the profile obtained by merging every layer
+    pub fn merge(self) -> Result<Profile, Error> {
+        let profile = self
+            .profile
+            .as_deref()
+            .map(Profile::from_file)
+            .unwrap_or(Ok(Profile::empty()))?;
+        let overlay = self
+            .overlay
+            .as_deref()
+            .map(Profile::from_file)
+            .unwrap_or(Ok(Profile::empty()))?;
+
+        Profile::layered(&profile, &overlay, self.environment, self.arguments)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -250,4 +564,18 @@ mod tests {
 
         assert_eq!(profile.0, expected);
     }
+
+    #[test]
+    fn higher_layers_take_precedence() {
+        let profile: Profile = serde_json::from_str(r#"{ "verbosity": "info", "forwarder": "0x1", "rpc": { "port": 12777, "debug_diagnostics": false } }"#).unwrap();
+        let overlay: Profile = serde_json::from_str(r#"{ "forwarder": "0x2", "rpc": { "port": 8080 } }"#).unwrap();
+        let environment = Variables::from(HashMap::from([(JSONPath::from_str("forwarder"), Value::from("0x3"))]));
+        let arguments = Variables::from(HashMap::from([(JSONPath::from_str("verbosity"), Value::from("debug"))]));
+
+        let layered = Profile::layered(&profile, &overlay, environment, arguments).unwrap();
+
+        let expected: Map<String, Value> =
+            serde_json::from_str(r#"{ "verbosity": "debug", "forwarder": "0x3", "rpc": { "port": 8080, "debug_diagnostics": false } }"#).unwrap();
+        assert_eq!(layered.0, expected);
+    }
 }
@@ -9,6 +9,12 @@ use serde_json::{Number, Value};
 
 use crate::core::Error;
 
+/// Flag running the self-test of the instance instead of starting its services
+pub const SELF_TEST_FLAG: &str = "--self-test";
+
+/// Flag also executing a transaction during the self-test
+pub const SELF_TEST_EXECUTE_FLAG: &str = "--self-test-execute";
+
 static CONFIGURATION_SPECIFICATION: &str = include_str!("../../../../../resources/specification/configuration.json");
 
 lazy_static! {
@@ -64,6 +70,7 @@ impl VariablesResolver {
 
         let mut resolutions = HashMap::new();
         resolutions.insert("profile".to_string(), JSONPath::from_str("profile"));
+        resolutions.insert("overlay".to_string(), JSONPath::from_str("overlay"));
         resolutions.extend(resolve_variables(&[], specification));
 
         Self(resolutions)
@@ -82,6 +89,10 @@ impl VariablesResolver {
 
         let mut arguments = HashMap::new();
         for raw_argument in raw_arguments.skip(1) {
+            if raw_argument == SELF_TEST_FLAG || raw_argument == SELF_TEST_EXECUTE_FLAG {
+                continue;
+            }
+
             if !IS_ARGUMENT.is_match(&raw_argument) {
                 return Err(Error::Configuration(format!("invalid argument {}, must be of the form '--xxx=yyy'", raw_argument)));
             }
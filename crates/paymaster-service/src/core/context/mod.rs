@@ -1,9 +1,16 @@
-use crate::core::context::configuration::{Configuration, Profile};
+use std::collections::HashSet;
+
+use paymaster_common::validation::{Validate, ValidationErrors, ValidationReport};
+use paymaster_starknet::probe::CapabilityMatrix;
+
+use crate::core::context::configuration::{Configuration, ConfigurationLayers};
 use crate::core::context::environment::VariablesResolver;
+use crate::core::context::registry::TokenRegistry;
 use crate::core::Error;
 
 pub mod configuration;
 pub mod environment;
+pub mod registry;
 
 #[derive(Clone)]
 pub struct Context {
@@ -15,20 +22,11 @@ impl Context {
         Context { configuration }
     }
 
-    pub fn load() -> Result<Self, Error> {
-        let mut complete_profile = Profile::empty();
-
+    pub async fn load() -> Result<Self, Error> {
         let resolver = VariablesResolver::initialize();
-        let environment = resolver.resolve_environment()?;
-        let arguments = resolver.resolve_arguments()?;
+        let layers = ConfigurationLayers::resolve(&resolver, resolver.resolve_arguments()?)?;
 
-        let profile_path = arguments
-            .get("profile")
-            .or_else(|| environment.get("profile"))
-            .and_then(|x| x.as_str())
-            .filter(|x| !x.is_empty());
-
-        if profile_path.is_none() {
+        if layers.profile.is_none() {
             println!(
                 "No profile file specified.
 Please provide a configuration profile using the `--profile` argument or the `PAYMASTER_PROFILE` environment variable, \
@@ -36,13 +34,90 @@ unless all variables are set via command line or environment variables."
             );
         }
 
-        let profile = profile_path.map(Profile::from_file).unwrap_or(Ok(Profile::empty()))?;
+        let complete_profile = layers.merge()?;
+
+        let mut context = Configuration::from_profile(&complete_profile).map(Self::new)?;
+
+        // Installed before any client is built, the one fetching the token registry and probing included
+        context
+            .configuration
+            .egress
+            .install()
+            .map_err(|e| Error::Configuration(e.to_string()))?;
+
+        if let Some(source) = context.configuration.token_registry.clone() {
+            TokenRegistry::load(&source).await?.apply(&mut context.configuration);
+        }
+        for i in 0..context.configuration.chains.len() {
+            if let Some(source) = context.configuration.chains[i].token_registry.clone() {
+                let configuration = &mut context.configuration;
+                TokenRegistry::load(&source)
+                    .await?
+                    .apply_to_chain(&mut configuration.chains[i], &mut configuration.price);
+            }
+        }
+
+        // Report every configuration problem at once instead of failing on the first one at startup
+        context.validate().map_err(|e| Error::Configuration(e.to_string()))?;
+
+        Ok(context)
+    }
 
-        complete_profile.merge(&profile);
-        complete_profile.insert_variables(environment)?;
-        complete_profile.insert_variables(arguments)?;
+    /// Probe the endpoints of every chain, logging their capabilities. Fails if a primary endpoint is misconfigured
+    /// for the features the paymaster requires, while the misconfigured fallbacks are only removed.
+    pub async fn probe_endpoints(&mut self) -> Result<(), Error> {
+        if self.configuration.probe.disabled {
+            return Ok(());
+        }
+
+        let probe = self.configuration.probe.clone();
+
+        let matrix = CapabilityMatrix::probe(&self.configuration.starknet).await;
+        matrix.log();
+        self.configuration.starknet = matrix
+            .resolve(&self.configuration.starknet, &probe)
+            .map_err(Error::Configuration)?;
+
+        for chain in self.configuration.chains.iter_mut() {
+            let matrix = CapabilityMatrix::probe(&chain.starknet).await;
+            matrix.log();
+            chain.starknet = matrix.resolve(&chain.starknet, &probe).map_err(Error::Configuration)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the configurations of the additional chains served by the instance
+    pub fn chain_configurations(&self) -> Vec<paymaster_rpc::Configuration> {
+        self.configuration
+            .chains
+            .iter()
+            .map(|chain| Context::new(self.configuration.for_chain(chain)).into())
+            .collect()
+    }
+
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut report = ValidationReport::new();
+
+        let principal: paymaster_rpc::Configuration = self.clone().into();
+        principal.validate_into(&mut report);
+        report.field("logging", &self.configuration.logging);
+        report.field("egress", &self.configuration.egress);
+
+        // Shared settings are validated with the principal chain, only the chain specific ones are left
+        let mut chain_ids = HashSet::from([principal.starknet.chain_id.as_felt()]);
+        for (i, chain) in self.configuration.chains.iter().enumerate() {
+            report.nested(&format!("chains[{}]", i), |report| {
+                chain.validate_into(report);
+                report.ensure(
+                    chain_ids.insert(chain.starknet.chain_id.as_felt()),
+                    "starknet.chain_id",
+                    "chain is configured more than once",
+                );
+            });
+        }
 
-        Configuration::from_profile(&complete_profile).map(Self::new)
+        report.into_result()
     }
 }
 
@@ -52,12 +127,22 @@ impl Into<paymaster_rpc::Configuration> for Context {
             rpc: self.configuration.rpc.clone(),
 
             forwarder: self.configuration.forwarder,
+            forwarder_whitelist: self.configuration.forwarder_whitelist.clone(),
             gas_tank: self.configuration.gas_tank,
+            gas_tank_multisig: self.configuration.gas_tank_multisig.clone(),
+            fee_recipients: self.configuration.fee_recipients.clone(),
 
             supported_tokens: self.configuration.supported_tokens.clone(),
+            token_metadata: self.configuration.token_metadata.clone(),
+            permit_tokens: self.configuration.permit_tokens.clone(),
+            direct_fee_payment: self.configuration.direct_fee_payment,
+            profitability: self.configuration.profitability.clone(),
+            quote_ttl: self.configuration.quote_ttl.clone(),
+            low_rpc: self.configuration.low_rpc.clone(),
 
             max_fee_multiplier: self.configuration.max_fee_multiplier,
             provider_fee_overhead: self.configuration.provider_fee_overhead,
+            fee_rounding: self.configuration.fee_rounding,
 
             estimate_account: self.configuration.estimate_account,
 
@@ -66,6 +151,12 @@ impl Into<paymaster_rpc::Configuration> for Context {
             starknet: self.configuration.starknet.clone(),
             price: self.configuration.clone().into(),
             sponsoring: self.configuration.sponsoring,
+            refund: self.configuration.refund.clone(),
+            reorg: self.configuration.reorg.clone(),
+            hooks: self.configuration.hooks.clone(),
+            analytics: self.configuration.analytics.clone(),
+            callbacks: self.configuration.callbacks.clone(),
+            cost_attribution: self.configuration.cost_attribution.clone(),
         }
     }
 }
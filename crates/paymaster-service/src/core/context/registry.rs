@@ -0,0 +1,181 @@
+use std::collections::HashSet;
+use std::fs;
+use std::time::Duration;
+
+use paymaster_common::egress;
+use paymaster_common::validation::{Validate, ValidationReport};
+use paymaster_rpc::{TokenMetadata, TokenMetadataOverrides};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use crate::core::context::configuration::{ChainConfiguration, Configuration, PriceConfiguration, PriceOracleConfiguration};
+use crate::core::Error;
+
+/// List of the tokens supported by the paymaster, kept in a file or served at a url so that a long list of tokens
+/// can be shared by every environment instead of being repeated in each profile
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenRegistry {
+    pub tokens: Vec<TokenRegistryEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenRegistryEntry {
+    pub symbol: String,
+
+    /// Display name of the token
+    #[serde(default)]
+    pub name: Option<String>,
+
+    pub address: Felt,
+    pub decimals: u8,
+
+    /// Minimum fee charged in the token (in token units)
+    #[serde(default)]
+    pub min_fee_amount: Option<Felt>,
+
+    /// Identifier of the token in the price oracle, used to price it when the oracle is Coingecko
+    #[serde(default)]
+    pub price_source_id: Option<String>,
+}
+
+impl Validate for TokenRegistry {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(!self.tokens.is_empty(), "tokens", "at least one token must be listed");
+
+        let mut addresses = HashSet::new();
+        for (i, token) in self.tokens.iter().enumerate() {
+            report.nested(&format!("tokens[{}]", i), |report| {
+                report.ensure(!token.symbol.is_empty(), "symbol", "must not be empty");
+                if let Some(name) = &token.name {
+                    report.ensure(!name.is_empty(), "name", "must not be empty");
+                }
+                report.ensure(token.address != Felt::ZERO, "address", "must not be zero");
+                report.ensure(addresses.insert(token.address), "address", format!("duplicated token {:#x}", token.address));
+                report.ensure(token.decimals <= 36, "decimals", "must not exceed 36");
+                if let Some(id) = &token.price_source_id {
+                    report.ensure(!id.is_empty(), "price_source_id", "must not be empty");
+                }
+            });
+        }
+    }
+}
+
+impl TokenRegistry {
+    /// Load the registry from the given file or `http(s)` url, failing if it does not follow the schema
+    pub async fn load(source: &str) -> Result<Self, Error> {
+        let data = if source.starts_with("http://") || source.starts_with("https://") {
+            Self::fetch(source).await?
+        } else {
+            fs::read(source).map_err(|e| Error::Configuration(format!("token registry {}: {}", source, e)))?
+        };
+
+        Self::parse(&data).map_err(|e| Error::Configuration(format!("token registry {}: {}", source, e)))
+    }
+
+    fn parse(data: &[u8]) -> Result<Self, String> {
+        let registry: Self = serde_json::from_slice(data).map_err(|e| e.to_string())?;
+        registry.validate_all().map_err(|e| e.to_string())?;
+
+        Ok(registry)
+    }
+
+    async fn fetch(url: &str) -> Result<Vec<u8>, Error> {
+        let error = |e: String| Error::Configuration(format!("token registry {}: {}", url, e));
+
+        egress::ensure_allowed(url).map_err(|e| error(e.to_string()))?;
+        let client = egress::apply(reqwest::Client::builder().timeout(Duration::from_secs(10)))
+            .build()
+            .map_err(|e| error(e.to_string()))?;
+
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .and_then(|x| x.error_for_status())
+            .map_err(|e| error(e.to_string()))?;
+
+        Ok(response.bytes().await.map_err(|e| error(e.to_string()))?.to_vec())
+    }
+
+    /// Add the tokens of the registry to the supported tokens of the configuration. The metadata and the price
+    /// identifiers configured explicitly take precedence over the ones of the registry.
+    pub fn apply(&self, configuration: &mut Configuration) {
+        self.apply_tokens(&mut configuration.supported_tokens, &mut configuration.token_metadata);
+        self.apply_price(&mut configuration.price);
+    }
+
+    /// Add the tokens of the registry to the supported tokens of an additional chain, the price oracle being shared
+    /// with the principal chain
+    pub fn apply_to_chain(&self, chain: &mut ChainConfiguration, price: &mut PriceConfiguration) {
+        self.apply_tokens(&mut chain.supported_tokens, &mut chain.token_metadata);
+        self.apply_price(price);
+    }
+
+    fn apply_tokens(&self, supported_tokens: &mut HashSet<Felt>, token_metadata: &mut TokenMetadataOverrides) {
+        for token in &self.tokens {
+            supported_tokens.insert(token.address);
+            token_metadata.fill(
+                token.address,
+                TokenMetadata {
+                    symbol: Some(token.symbol.clone()),
+                    name: token.name.clone(),
+                    decimals: Some(token.decimals),
+                    min_fee_amount: token.min_fee_amount,
+                },
+            );
+        }
+    }
+
+    fn apply_price(&self, price: &mut PriceConfiguration) {
+        let oracles = match price {
+            PriceConfiguration::Single(x) => vec![x],
+            PriceConfiguration::WithFallback { principal, fallbacks } => std::iter::once(principal).chain(fallbacks.iter_mut()).collect(),
+        };
+
+        for oracle in oracles {
+            if let PriceOracleConfiguration::Coingecko { address_to_id, .. } = oracle {
+                for token in &self.tokens {
+                    if let Some(id) = &token.price_source_id {
+                        address_to_id.entry(token.address).or_insert_with(|| id.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::context::registry::TokenRegistry;
+
+    #[test]
+    fn registry_follows_the_schema() {
+        let registry = TokenRegistry::parse(
+            br#"{ "tokens": [
+                { "symbol": "USDC", "address": "0x1", "decimals": 6, "min_fee_amount": "0x64", "price_source_id": "usd-coin" },
+                { "symbol": "GAME", "name": "Game Token", "address": "0x2", "decimals": 18 }
+            ] }"#,
+        )
+        .unwrap();
+        assert_eq!(registry.tokens.len(), 2);
+
+        // Unknown fields are refused so that a typo is not silently ignored
+        assert!(TokenRegistry::parse(br#"{ "tokens": [{ "symbol": "USDC", "address": "0x1", "decimal": 6 }] }"#).is_err());
+        assert!(TokenRegistry::parse(br#"{ "tokens": [] }"#).is_err());
+    }
+
+    #[test]
+    fn registry_refuses_duplicated_tokens() {
+        let error = TokenRegistry::parse(
+            br#"{ "tokens": [
+                { "symbol": "USDC", "address": "0x1", "decimals": 6 },
+                { "symbol": "USDC.e", "address": "0x1", "decimals": 6 }
+            ] }"#,
+        )
+        .unwrap_err();
+
+        assert!(error.contains("tokens[1].address"));
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Format of the logs written on the standard output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+
+    /// One JSON object per line, with the fields of the enclosing spans (request id, sponsor,
+    /// relayer, ...) flattened into it so that the logs can be indexed
+    Json,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LoggingConfiguration {
+    #[serde(default)]
+    pub format: LogFormat,
+
+    /// Fraction of the events kept for the targets starting with the given prefix, e.g.
+    /// `{ "paymaster_rpc": 0.1 }`. The longest matching prefix applies, warnings and errors are always kept.
+    #[serde(default)]
+    pub sampling: HashMap<String, f64>,
+}
+
+impl Validate for LoggingConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        for (target, rate) in &self.sampling {
+            report.ensure((0.0..=1.0).contains(rate), &format!("sampling.{}", target), "must be between 0 and 1");
+        }
+    }
+}
+
+/// Drops a fraction of the events according to the sampling rate of their target
+#[derive(Clone, Debug)]
+pub struct Sampling {
+    rates: Vec<(String, f64)>,
+}
+
+impl Sampling {
+    pub fn new(rates: &HashMap<String, f64>) -> Self {
+        let mut rates: Vec<(String, f64)> = rates.iter().map(|(target, rate)| (target.clone(), *rate)).collect();
+        rates.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()));
+
+        Self { rates }
+    }
+
+    /// Returns the sampling rate of the given target
+    pub fn rate(&self, target: &str) -> f64 {
+        self.rates
+            .iter()
+            .find(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .map(|(_, rate)| *rate)
+            .unwrap_or(1.0)
+    }
+
+    pub fn keep(&self, metadata: &Metadata<'_>) -> bool {
+        // Spans are never sampled so that the events which are kept still carry their fields
+        if metadata.is_span() || *metadata.level() <= Level::WARN {
+            return true;
+        }
+
+        let rate = self.rate(metadata.target());
+        rate >= 1.0 || rand::random::<f64>() < rate
+    }
+}
+
+/// Fields recorded on a span, attached to its extensions
+#[derive(Default)]
+struct SpanFields(Map<String, Value>);
+
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if let Some(value) = Number::from_f64(value) {
+            self.0.insert(field.name().to_string(), Value::Number(value));
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name().to_string(), Value::from(format!("{:?}", value)));
+    }
+}
+
+/// Writes every event as a single JSON object
+pub struct JsonLayer;
+
+impl JsonLayer {
+    fn format<S>(event: &Event<'_>, ctx: &Context<'_, S>) -> Map<String, Value>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let metadata = event.metadata();
+
+        let mut line = Map::new();
+        line.insert("timestamp".to_string(), Value::from(chrono::Utc::now().to_rfc3339()));
+        line.insert("level".to_string(), Value::from(metadata.level().as_str()));
+        line.insert("target".to_string(), Value::from(metadata.target()));
+
+        // Inner spans override the fields of the outer ones
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                line.insert("span".to_string(), Value::from(span.name()));
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    line.extend(fields.0.clone());
+                }
+            }
+        }
+
+        event.record(&mut JsonVisitor(&mut line));
+        line
+    }
+}
+
+impl<S> Layer<S> for JsonLayer
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+
+        let mut fields = SpanFields::default();
+        attrs.record(&mut JsonVisitor(&mut fields.0));
+        span.extensions_mut().insert(fields);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+
+        if let Some(fields) = span.extensions_mut().get_mut::<SpanFields>() {
+            values.record(&mut JsonVisitor(&mut fields.0));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let line = Self::format(event, &ctx);
+
+        let mut stdout = std::io::stdout().lock();
+        let _ = writeln!(stdout, "{}", Value::Object(line));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use paymaster_common::validation::Validate;
+
+    use crate::core::logging::{LoggingConfiguration, Sampling};
+
+    #[test]
+    fn longest_prefix_gives_the_sampling_rate() {
+        let sampling = Sampling::new(&HashMap::from([("paymaster_rpc".to_string(), 0.5), ("paymaster_rpc::endpoint".to_string(), 0.1)]));
+
+        assert_eq!(sampling.rate("paymaster_rpc::endpoint::build"), 0.1);
+        assert_eq!(sampling.rate("paymaster_rpc::server"), 0.5);
+        assert_eq!(sampling.rate("paymaster_relayer"), 1.0);
+    }
+
+    #[test]
+    fn sampling_rates_are_validated() {
+        let configuration = LoggingConfiguration {
+            sampling: HashMap::from([("paymaster_rpc".to_string(), 1.5)]),
+            ..Default::default()
+        };
+
+        assert!(configuration.validate_all().is_err());
+    }
+}
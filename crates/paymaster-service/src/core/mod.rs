@@ -2,6 +2,8 @@ use thiserror::Error;
 
 pub mod context;
 
+pub mod logging;
+
 mod tracing;
 pub use tracing::Fmt;
 
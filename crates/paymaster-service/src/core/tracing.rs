@@ -1,6 +1,11 @@
+use tracing_subscriber::filter::filter_fn;
 use tracing_subscriber::fmt::format::Writer;
 use tracing_subscriber::fmt::time;
-use tracing_subscriber::{EnvFilter, Layer};
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+
+use paymaster_common::service::monitoring::LogFilter;
+
+use crate::core::logging::{JsonLayer, LogFormat, LoggingConfiguration, Sampling};
 
 const DEFAULT_LOG_FILTER: &str = "info";
 const DEFAULT_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f %Z";
@@ -21,16 +26,30 @@ impl time::FormatTime for LocalTime {
 pub struct Fmt;
 
 impl Fmt {
-    pub fn layer<S>() -> (impl Layer<S>, EnvFilter)
+    /// Layer writing the logs in the configured format, with the configured sampling applied, along with
+    /// the filter of the logs which can be changed at runtime through `paymaster_setLogFilter`
+    pub fn layer<S>(configuration: &LoggingConfiguration) -> (Box<dyn Layer<S> + Send + Sync>, reload::Layer<EnvFilter, Registry>)
     where
         S: for<'span> tracing_subscriber::registry::LookupSpan<'span> + tracing::Subscriber,
     {
         let ansi = std::io::IsTerminal::is_terminal(&std::io::stdout());
 
-        let default_filter = EnvFilter::try_new(DEFAULT_LOG_FILTER);
-        let filter = EnvFilter::try_from_default_env().or(default_filter).expect("valid env filter");
-
-        let layer = tracing_subscriber::fmt::layer().with_timer(LocalTime).with_ansi(ansi);
+        let filter = std::env::var(EnvFilter::DEFAULT_ENV)
+            .ok()
+            .and_then(|directives| LogFilter::layer(&directives).ok())
+            .unwrap_or_else(|| LogFilter::layer(DEFAULT_LOG_FILTER).expect("valid env filter"));
+
+        let sampling = Sampling::new(&configuration.sampling);
+        let sampling = filter_fn(move |metadata| sampling.keep(metadata));
+
+        let layer = match configuration.format {
+            LogFormat::Text => tracing_subscriber::fmt::layer()
+                .with_timer(LocalTime)
+                .with_ansi(ansi)
+                .with_filter(sampling)
+                .boxed(),
+            LogFormat::Json => JsonLayer.with_filter(sampling).boxed(),
+        };
 
         (layer, filter)
     }
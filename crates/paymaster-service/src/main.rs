@@ -5,12 +5,14 @@ use tracing::{info, warn};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::Registry;
 
+use crate::core::context::environment::{SELF_TEST_EXECUTE_FLAG, SELF_TEST_FLAG};
 use crate::core::context::Context;
 use crate::core::Fmt;
 use crate::rpc::RPCService;
 
 mod core;
 mod rpc;
+mod self_test;
 mod version;
 
 #[tokio::main]
@@ -21,11 +23,11 @@ async fn main() -> Result<(), Error> {
         return Ok(());
     }
 
-    let context = Context::load()?;
+    let mut context = Context::load().await?;
 
     let tracer_layer = context.configuration.prometheus.clone().map(|x| Tracer::layer(&x));
     let metric_layer = context.configuration.prometheus.clone().map(|x| Metric::layer(&x));
-    let (fmt_layer, env_filter) = Fmt::layer();
+    let (fmt_layer, env_filter) = Fmt::layer(&context.configuration.logging);
 
     // Layer ordering mirrors katana: filter → telemetry → fmt → metrics.
     let subscriber = Registry::default()
@@ -57,6 +59,21 @@ async fn main() -> Result<(), Error> {
         },
     }
 
+    info!("probing starknet endpoints...");
+    context.probe_endpoints().await?;
+
+    let execute = args.iter().any(|a| a == SELF_TEST_EXECUTE_FLAG);
+    if execute || args.iter().any(|a| a == SELF_TEST_FLAG) {
+        let configurations = std::iter::once(context.clone().into())
+            .chain(context.chain_configurations())
+            .collect();
+        let report = self_test::run(configurations, execute).await;
+        println!("{}", report);
+
+        monitoring::shutdown();
+        return if report.is_success() { Ok(()) } else { Err(Error::new("self-test failed")) };
+    }
+
     let mut services = ServiceManager::new(context);
     info!("starting services...");
     services.spawn::<RPCService>();
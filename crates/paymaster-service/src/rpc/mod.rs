@@ -19,7 +19,11 @@ impl Service for RPCService {
     }
 
     async fn run(mut self) -> Result<(), Error> {
-        let server = PaymasterServer::new(&self.context.into());
+        let mut server = PaymasterServer::new(&self.context.clone().into())?;
+        for configuration in self.context.chain_configurations() {
+            server = server.with_chain(&configuration)?;
+        }
+
         let handle = server.start().await?;
 
         handle.stopped().await;
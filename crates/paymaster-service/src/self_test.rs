@@ -0,0 +1,245 @@
+//! Checks run by `--self-test` instead of starting the services, so that a deployment can be gated on the
+//! instance being able to serve requests: reach Starknet, price the supported tokens, lock a relayer and estimate a
+//! transaction with the estimate account, and check that the forwarder collects the permits when some tokens are
+//! configured to be paid through one. With `--self-test-execute`, a zero STRK transfer is also executed by a
+//! relayer, which is refused on mainnet.
+
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use paymaster_execution::finality::{FinalityLevel, FinalityStatus};
+use paymaster_execution::{Client, TipPriority};
+use paymaster_starknet::transaction::{Calls, TokenTransfer};
+use paymaster_starknet::ChainID;
+use starknet::core::types::Felt;
+use starknet::macros::selector;
+
+pub enum Outcome {
+    Passed(String),
+    Failed(String),
+    Skipped(String),
+}
+
+pub struct CheckResult {
+    pub chain: String,
+    pub name: &'static str,
+    pub outcome: Outcome,
+    pub elapsed: Duration,
+}
+
+impl Display for CheckResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (status, detail) = match &self.outcome {
+            Outcome::Passed(x) => ("PASS", x),
+            Outcome::Failed(x) => ("FAIL", x),
+            Outcome::Skipped(x) => ("SKIP", x),
+        };
+
+        write!(f, "[{}] {} {:<10} {} ({}ms)", status, self.chain, self.name, detail, self.elapsed.as_millis())
+    }
+}
+
+#[derive(Default)]
+pub struct SelfTestReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl SelfTestReport {
+    pub fn is_success(&self) -> bool {
+        !self.checks.iter().any(|x| matches!(x.outcome, Outcome::Failed(_)))
+    }
+
+    async fn check<F>(&mut self, chain: &str, name: &'static str, check: F) -> bool
+    where
+        F: Future<Output = Outcome>,
+    {
+        let start = Instant::now();
+        let outcome = check.await;
+        let passed = !matches!(outcome, Outcome::Failed(_));
+
+        self.checks.push(CheckResult {
+            chain: chain.to_string(),
+            name,
+            outcome,
+            elapsed: start.elapsed(),
+        });
+
+        passed
+    }
+}
+
+impl Display for SelfTestReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "{}", check)?;
+        }
+
+        let failed = self.checks.iter().filter(|x| matches!(x.outcome, Outcome::Failed(_))).count();
+        write!(
+            f,
+            "self-test {}: {} check(s), {} failed",
+            if failed == 0 { "passed" } else { "failed" },
+            self.checks.len(),
+            failed
+        )
+    }
+}
+
+/// Run the checks against every chain served by the instance
+pub async fn run(configurations: Vec<paymaster_rpc::Configuration>, execute: bool) -> SelfTestReport {
+    let mut report = SelfTestReport::default();
+
+    for configuration in configurations {
+        let chain = configuration.starknet.chain_id.as_identifier();
+
+        let client = match Client::new(&configuration.clone().into()) {
+            Ok(client) => client,
+            Err(e) => {
+                report.check(&chain, "client", async { Outcome::Failed(e.to_string()) }).await;
+                continue;
+            },
+        };
+
+        // The other checks cannot succeed when Starknet is not reachable
+        if !report.check(&chain, "rpc", check_rpc(&client)).await {
+            continue;
+        }
+
+        report.check(&chain, "prices", check_prices(&client, &configuration)).await;
+        report.check(&chain, "lock", check_lock(&client)).await;
+        report.check(&chain, "estimate", check_estimate(&client, &configuration)).await;
+        report
+            .check(&chain, "forwarder", check_forwarder(&client, &configuration))
+            .await;
+
+        if execute {
+            report.check(&chain, "execute", check_execution(&client, &configuration)).await;
+        }
+    }
+
+    report
+}
+
+async fn check_rpc(client: &Client) -> Outcome {
+    match client.starknet.fetch_block_number().await {
+        Ok(block_number) => Outcome::Passed(format!("latest block {}", block_number)),
+        Err(e) => Outcome::Failed(e.to_string()),
+    }
+}
+
+async fn check_prices(client: &Client, configuration: &paymaster_rpc::Configuration) -> Outcome {
+    let results = client.price.fetch_tokens(&configuration.supported_tokens).await;
+
+    let failures: Vec<String> = results.into_iter().filter_map(|x| x.err()).map(|e| e.to_string()).collect();
+    if failures.is_empty() {
+        Outcome::Passed(format!("{} token(s) priced", configuration.supported_tokens.len()))
+    } else {
+        Outcome::Failed(failures.join(", "))
+    }
+}
+
+async fn check_lock(client: &Client) -> Outcome {
+    let relayers = client.get_relayer_manager();
+
+    let relayer = match relayers.lock_relayer().await {
+        Ok(relayer) => relayer,
+        Err(e) => return Outcome::Failed(format!("could not lock a relayer: {}", e)),
+    };
+
+    let address = relayer.address();
+    match relayers.release_relayer(relayer).await {
+        Ok(()) => Outcome::Passed(format!("locked and released relayer {}", address.to_hex_string())),
+        Err(e) => Outcome::Failed(format!("could not release relayer {}: {}", address.to_hex_string(), e)),
+    }
+}
+
+// Transfer of zero STRK to the estimate account, harmless whoever executes it
+fn zero_transfer(configuration: &paymaster_rpc::Configuration) -> Calls {
+    Calls::new(vec![TokenTransfer::new(
+        configuration.starknet.tokens().strk,
+        configuration.estimate_account.address,
+        Felt::ZERO,
+    )
+    .to_call()])
+}
+
+async fn check_estimate(client: &Client, configuration: &paymaster_rpc::Configuration) -> Outcome {
+    match client.estimate(&zero_transfer(configuration), TipPriority::Normal).await {
+        Ok(estimated) => Outcome::Passed(format!("zero transfer estimated at {} FRI", estimated.estimate().overall_fee)),
+        Err(e) => Outcome::Failed(e.to_string()),
+    }
+}
+
+// The forwarders declared before the permits were introduced do not expose `execute_with_permit`, every transaction
+// paid with a permit token would then revert
+async fn check_forwarder(client: &Client, configuration: &paymaster_rpc::Configuration) -> Outcome {
+    if configuration.permit_tokens.is_empty() {
+        return Outcome::Skipped("no permit token".to_string());
+    }
+
+    let class = match client.starknet.fetch_class_hash_at(configuration.forwarder).await {
+        Ok(class_hash) => client.starknet.fetch_class(class_hash).await,
+        Err(e) => Err(e),
+    };
+
+    match class {
+        Ok(class) if class.abi.contains_selector(selector!("execute_with_permit")) => Outcome::Passed("collects the permits".to_string()),
+        Ok(_) => Outcome::Failed("forwarder does not expose execute_with_permit, upgrade it or remove the permit tokens".to_string()),
+        Err(e) => Outcome::Failed(e.to_string()),
+    }
+}
+
+async fn check_execution(client: &Client, configuration: &paymaster_rpc::Configuration) -> Outcome {
+    if matches!(configuration.starknet.chain_id, ChainID::Mainnet) {
+        return Outcome::Skipped("refused on mainnet".to_string());
+    }
+
+    let estimated = match client.estimate(&zero_transfer(configuration), TipPriority::Normal).await {
+        Ok(estimated) => estimated,
+        Err(e) => return Outcome::Failed(e.to_string()),
+    };
+
+    let result = match client.execute(&estimated).await {
+        Ok(result) => result,
+        Err(e) => return Outcome::Failed(e.to_string()),
+    };
+
+    let hash = result.transaction_hash.to_hex_string();
+    let finality = client
+        .wait_for_finality(result.transaction_hash, FinalityLevel::AcceptedOnL2)
+        .await;
+    match (finality.status, finality.revert_reason) {
+        (_, Some(reason)) => Outcome::Failed(format!("transaction {} reverted: {}", hash, reason)),
+        (FinalityStatus::AcceptedOnL2 | FinalityStatus::AcceptedOnL1, None) => Outcome::Passed(format!("transaction {} accepted", hash)),
+        (status, None) => Outcome::Failed(format!("transaction {} still {} after the timeout", hash, status.name())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::self_test::{CheckResult, Outcome, SelfTestReport};
+
+    fn check(outcome: Outcome) -> CheckResult {
+        CheckResult {
+            chain: "SN_SEPOLIA".to_string(),
+            name: "rpc",
+            outcome,
+            elapsed: Duration::from_millis(12),
+        }
+    }
+
+    #[test]
+    fn report_fails_when_a_check_fails() {
+        let mut report = SelfTestReport::default();
+        report.checks.push(check(Outcome::Passed("latest block 1".to_string())));
+        report.checks.push(check(Outcome::Skipped("refused on mainnet".to_string())));
+        assert!(report.is_success());
+
+        report.checks.push(check(Outcome::Failed("unreachable".to_string())));
+        assert!(!report.is_success());
+        assert!(report.to_string().ends_with("self-test failed: 3 check(s), 1 failed"));
+    }
+}
@@ -1,16 +1,21 @@
 use std::collections::HashMap;
 
+use paymaster_common::validation::{Validate, ValidationReport};
 use paymaster_common::{measure_duration, metric};
 use serde::{Deserialize, Serialize};
 use starknet::core::types::Felt;
 use thiserror::Error;
 use tracing::{error, warn};
 
+use crate::scope::CallScope;
 use crate::self_sponsoring::SelfSponsoring;
 use crate::webhook_sponsoring::WebhookSponsoring;
 mod self_sponsoring;
 mod webhook_sponsoring;
 
+pub mod scope;
+pub mod usage;
+
 #[macro_export]
 macro_rules! log_if_error {
     ($e: expr) => {{
@@ -20,6 +25,7 @@ macro_rules! log_if_error {
             Err(e@Error::Internal(_)) => error!(message=%e),
             Err(e@Error::Format(_)) => error!(message=%e),
             Err(e@Error::URL(_)) => error!(message=%e),
+            Err(e@Error::Egress(_)) => error!(message=%e),
             Err(e) => warn!(message=%e),
             _ => ()
         };
@@ -35,6 +41,9 @@ pub enum Error {
     #[error(transparent)]
     HTTP(#[from] reqwest::Error),
 
+    #[error(transparent)]
+    Egress(#[from] paymaster_common::egress::Error),
+
     #[error("invalid url {0}")]
     URL(String),
 
@@ -49,12 +58,16 @@ pub enum Error {
 pub struct AuthenticatedApiKey {
     pub is_valid: bool,
     pub sponsor_metadata: Vec<Felt>,
+
+    /// Calls the key is allowed to sponsor, any call can be sponsored when not set
+    pub scope: Option<CallScope>,
 }
 impl AuthenticatedApiKey {
     pub fn valid(sponsor_metadata: Vec<Felt>) -> Self {
         Self {
             is_valid: true,
             sponsor_metadata,
+            scope: None,
         }
     }
 
@@ -62,14 +75,28 @@ impl AuthenticatedApiKey {
         Self {
             is_valid: false,
             sponsor_metadata: vec![],
+            scope: None,
         }
     }
+
+    pub fn with_scope(mut self, scope: Option<CallScope>) -> Self {
+        self.scope = scope;
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SelfConfiguration {
     pub api_key: String,
     pub sponsor_metadata: Vec<Felt>,
+
+    /// Maximum amount of fee (in STRK) the sponsor is willing to spend
+    #[serde(default)]
+    pub budget: Option<Felt>,
+
+    /// Calls the api key is allowed to sponsor, any call can be sponsored when not set
+    #[serde(default)]
+    pub scope: Option<CallScope>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -93,6 +120,23 @@ impl Configuration {
     }
 }
 
+impl Validate for Configuration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        match self {
+            Self::None => {},
+            Self::SelfSponsoring(configuration) => {
+                report.ensure(configuration.api_key.starts_with("paymaster_"), "api_key", "API key must start with 'paymaster_'");
+                if let Some(scope) = &configuration.scope {
+                    report.field("scope", scope);
+                }
+            },
+            Self::Webhook(configuration) => {
+                report.ensure_url("endpoint", &configuration.endpoint);
+            },
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum Authentication {
     None,
@@ -127,4 +171,12 @@ impl Client {
 
         result
     }
+
+    /// Returns the budget of the sponsor associated to the given key, if any
+    pub fn budget(&self, key: &str) -> Option<Felt> {
+        match &self.authentication {
+            Authentication::SelfSponsoring(authentication) => authentication.budget(key),
+            _ => None,
+        }
+    }
 }
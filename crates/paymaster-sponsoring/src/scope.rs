@@ -0,0 +1,98 @@
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{Call, Felt};
+
+/// Contract, and optionally its entrypoints, that an api key is allowed to sponsor calls to
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CallTarget {
+    pub contract_address: Felt,
+
+    /// Selectors of the entrypoints that can be called, every entrypoint of the contract can be called when empty
+    #[serde(default)]
+    pub selectors: Vec<Felt>,
+}
+
+impl CallTarget {
+    pub fn allows(&self, call: &Call) -> bool {
+        call.to == self.contract_address && (self.selectors.is_empty() || self.selectors.contains(&call.selector))
+    }
+}
+
+/// Calls that an api key is allowed to sponsor. A transaction is only sponsored when each of its calls matches one
+/// of the targets, so that a leaked key cannot sponsor arbitrary transactions.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct CallScope(Vec<CallTarget>);
+
+impl CallScope {
+    pub fn new(targets: Vec<CallTarget>) -> Self {
+        Self(targets)
+    }
+
+    /// Returns the first target matching the call, if any
+    pub fn find_target(&self, call: &Call) -> Option<&CallTarget> {
+        self.0.iter().find(|target| target.allows(call))
+    }
+
+    /// Returns true if one of the targets lists the selector of the call explicitly, rather than allowing every
+    /// entrypoint of the contract
+    pub fn explicitly_allows(&self, call: &Call) -> bool {
+        self.0
+            .iter()
+            .any(|target| target.contract_address == call.to && target.selectors.contains(&call.selector))
+    }
+
+    /// Returns the first call which does not match any target, if any
+    pub fn find_out_of_scope<'a>(&self, calls: &'a [Call]) -> Option<&'a Call> {
+        calls.iter().find(|call| self.find_target(call).is_none())
+    }
+}
+
+impl Validate for CallScope {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        for (i, target) in self.0.iter().enumerate() {
+            report.ensure(target.contract_address != Felt::ZERO, &format!("[{}].contract_address", i), "must not be zero");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::{Call, Felt};
+    use starknet::macros::selector;
+
+    use crate::scope::{CallScope, CallTarget};
+
+    fn call(to: Felt, selector: Felt) -> Call {
+        Call { to, selector, calldata: vec![] }
+    }
+
+    #[test]
+    fn calls_must_match_a_target() {
+        let scope = CallScope::new(vec![
+            CallTarget {
+                contract_address: Felt::ONE,
+                selectors: vec![],
+            },
+            CallTarget {
+                contract_address: Felt::TWO,
+                selectors: vec![selector!("play")],
+            },
+        ]);
+
+        let calls = [call(Felt::ONE, selector!("transfer")), call(Felt::TWO, selector!("play"))];
+        assert!(scope.find_out_of_scope(&calls).is_none());
+
+        let calls = [call(Felt::TWO, selector!("play")), call(Felt::TWO, selector!("withdraw"))];
+        assert_eq!(scope.find_out_of_scope(&calls).map(|x| x.selector), Some(selector!("withdraw")));
+
+        let calls = [call(Felt::THREE, selector!("play"))];
+        assert!(scope.find_out_of_scope(&calls).is_some());
+
+        let target = scope.find_target(&call(Felt::TWO, selector!("play")));
+        assert_eq!(target.map(|x| x.contract_address), Some(Felt::TWO));
+
+        assert!(scope.explicitly_allows(&call(Felt::TWO, selector!("play"))));
+        assert!(!scope.explicitly_allows(&call(Felt::ONE, selector!("transfer"))));
+    }
+}
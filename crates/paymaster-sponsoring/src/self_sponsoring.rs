@@ -1,5 +1,6 @@
 use starknet::core::types::Felt;
 
+use crate::scope::CallScope;
 use crate::Error::InvalidApiKey;
 use crate::{AuthenticatedApiKey, Error, SelfConfiguration};
 
@@ -7,6 +8,8 @@ use crate::{AuthenticatedApiKey, Error, SelfConfiguration};
 pub struct SelfSponsoring {
     api_key: String,
     sponsor_metadata: Vec<Felt>,
+    budget: Option<Felt>,
+    scope: Option<CallScope>,
 }
 
 impl SelfSponsoring {
@@ -17,12 +20,22 @@ impl SelfSponsoring {
         Ok(Self {
             api_key: configuration.api_key,
             sponsor_metadata: configuration.sponsor_metadata,
+            budget: configuration.budget,
+            scope: configuration.scope,
         })
     }
 
+    pub fn budget(&self, key: &str) -> Option<Felt> {
+        if key == self.api_key {
+            self.budget
+        } else {
+            None
+        }
+    }
+
     pub fn validate(&self, key: &str) -> AuthenticatedApiKey {
         if key == self.api_key {
-            AuthenticatedApiKey::valid(self.sponsor_metadata.clone())
+            AuthenticatedApiKey::valid(self.sponsor_metadata.clone()).with_scope(self.scope.clone())
         } else {
             AuthenticatedApiKey::invalid()
         }
@@ -46,6 +59,8 @@ mod tests {
             let config = SelfConfiguration {
                 api_key: key.to_string(),
                 sponsor_metadata: vec![Felt::ZERO],
+                budget: None,
+                scope: None,
             };
 
             // When
@@ -70,6 +85,8 @@ mod tests {
             let config = SelfConfiguration {
                 api_key: key.to_string(),
                 sponsor_metadata: vec![],
+                budget: None,
+                scope: None,
             };
             let auth = SelfSponsoring::new(config).unwrap();
 
@@ -88,6 +105,8 @@ mod tests {
             let config = SelfConfiguration {
                 api_key: key.to_string(),
                 sponsor_metadata: vec![],
+                budget: None,
+                scope: None,
             };
             let auth = SelfSponsoring::new(config).unwrap();
 
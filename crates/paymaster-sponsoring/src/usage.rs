@@ -0,0 +1,417 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use deadpool_redis::redis::{cmd, pipe};
+use deadpool_redis::{Connection, Pool};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+use starknet::core::utils::starknet_keccak;
+
+use crate::Error;
+
+/// Duration during which sponsored transactions are kept in the ledger
+const DEFAULT_RETENTION: Duration = Duration::from_secs(30 * 24 * 3600);
+
+/// Amount of FRI counted as one unit in the spending of the sponsors kept in Redis
+const SPEND_UNIT: u128 = 1_000_000_000;
+
+const DAY: u64 = 24 * 3600;
+
+/// Transaction sponsored on behalf of a sponsor, identified by its api key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsoredTransaction {
+    // The api key is never stored, the transactions are indexed by its fingerprint
+    #[serde(skip)]
+    pub sponsor: String,
+    pub user: Felt,
+    pub transaction_hash: Felt,
+
+    /// Fee paid by the sponsor in STRK
+    pub fee_in_strk: Felt,
+
+    /// Unix timestamp (in seconds) at which the transaction was executed
+    pub timestamp: u64,
+}
+
+impl SponsoredTransaction {
+    pub fn new(sponsor: &str, user: Felt, transaction_hash: Felt, fee_in_strk: Felt) -> Self {
+        Self {
+            sponsor: sponsor.to_string(),
+            user,
+            transaction_hash,
+            fee_in_strk,
+            timestamp: now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserUsage {
+    pub user: Felt,
+    pub transactions: u64,
+    pub fees_spent: Felt,
+}
+
+/// Usage of a sponsor over a time range
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SponsorUsage {
+    pub transactions: u64,
+    pub fees_spent: Felt,
+
+    /// Budget left to the sponsor, computed using all the transactions in the ledger
+    /// regardless of the time range. None if the sponsor has no budget.
+    pub remaining_budget: Option<Felt>,
+
+    pub users: Vec<UserUsage>,
+}
+
+#[derive(Default)]
+struct Ledger {
+    // Ordered by timestamp, the oldest first
+    transactions: VecDeque<SponsoredTransaction>,
+
+    // Fee spent by each sponsor over the retention period
+    spent: HashMap<String, Felt>,
+}
+
+/// Ledger of the sponsored transactions executed during the retention period. Shared by the instances through the
+/// Redis of the shared lock layer, where the spending of each sponsor is counted per day, kept in memory otherwise.
+#[derive(Clone)]
+pub struct UsageLedger {
+    retention: Duration,
+
+    redis: Option<Pool>,
+    memory: Arc<RwLock<Ledger>>,
+}
+
+impl Default for UsageLedger {
+    fn default() -> Self {
+        Self::new(DEFAULT_RETENTION)
+    }
+}
+
+impl UsageLedger {
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            retention,
+            redis: None,
+            memory: Arc::default(),
+        }
+    }
+
+    /// Keep the ledger in the given Redis rather than in memory
+    pub fn with_redis(self, redis: Pool) -> Self {
+        Self { redis: Some(redis), ..self }
+    }
+
+    /// Record a sponsored transaction and drop the ones older than the retention period
+    pub async fn record(&self, transaction: SponsoredTransaction) -> Result<(), Error> {
+        let oldest = now().saturating_sub(self.retention.as_secs());
+        let Some(redis) = &self.redis else {
+            let mut ledger = self.memory.write().unwrap_or_else(|e| e.into_inner());
+            *ledger.spent.entry(transaction.sponsor.clone()).or_default() += transaction.fee_in_strk;
+            ledger.transactions.push_back(transaction);
+
+            while ledger.transactions.front().is_some_and(|x| x.timestamp < oldest) {
+                if let Some(expired) = ledger.transactions.pop_front() {
+                    if let Some(spent) = ledger.spent.get_mut(&expired.sponsor) {
+                        *spent = remaining_of(*spent, expired.fee_in_strk);
+                    }
+                }
+            }
+            return Ok(());
+        };
+
+        let sponsor = fingerprint(&transaction.sponsor);
+        let value = serde_json::to_string(&transaction).map_err(|e| Error::Internal(e.to_string()))?;
+        let retention = self.retention.as_secs();
+
+        let mut connection = Self::connection(redis).await?;
+        let mut pipeline = pipe();
+        pipeline.atomic();
+        pipeline
+            .zadd(Self::transactions_key(&sponsor), value, transaction.timestamp)
+            .ignore()
+            .cmd("ZREMRANGEBYSCORE")
+            .arg(Self::transactions_key(&sponsor))
+            .arg("-inf")
+            .arg(format!("({}", oldest))
+            .ignore()
+            .expire(Self::transactions_key(&sponsor), retention as i64)
+            .ignore()
+            .incr(Self::spent_key(&sponsor, transaction.timestamp / DAY), to_spend_units(transaction.fee_in_strk))
+            .ignore()
+            .expire(Self::spent_key(&sponsor, transaction.timestamp / DAY), (retention + DAY) as i64)
+            .ignore();
+
+        pipeline
+            .query_async::<()>(&mut connection)
+            .await
+            .map_err(|e| Error::Internal(e.to_string()))
+    }
+
+    /// Returns the usage of the given sponsor between `from` and `to` (unix timestamps in seconds, inclusive)
+    pub async fn usage(&self, sponsor: &str, from: u64, to: u64, budget: Option<Felt>) -> Result<SponsorUsage, Error> {
+        let transactions: Vec<SponsoredTransaction> = match &self.redis {
+            Some(redis) => {
+                let mut connection = Self::connection(redis).await?;
+                let values: Vec<String> = cmd("ZRANGEBYSCORE")
+                    .arg(Self::transactions_key(&fingerprint(sponsor)))
+                    .arg(from)
+                    .arg(to)
+                    .query_async(&mut connection)
+                    .await
+                    .map_err(|e| Error::Internal(e.to_string()))?;
+
+                values.iter().filter_map(|x| serde_json::from_str(x).ok()).collect()
+            },
+            None => {
+                let ledger = self.memory.read().unwrap_or_else(|e| e.into_inner());
+                ledger
+                    .transactions
+                    .iter()
+                    .filter(|x| x.sponsor == sponsor && x.timestamp >= from && x.timestamp <= to)
+                    .cloned()
+                    .collect()
+            },
+        };
+
+        let mut usage = SponsorUsage {
+            remaining_budget: self.remaining_budget(sponsor, budget).await?,
+            ..SponsorUsage::default()
+        };
+
+        let mut users: HashMap<Felt, UserUsage> = HashMap::new();
+        for transaction in transactions {
+            usage.transactions += 1;
+            usage.fees_spent += transaction.fee_in_strk;
+
+            let user = users.entry(transaction.user).or_insert_with(|| UserUsage {
+                user: transaction.user,
+                ..UserUsage::default()
+            });
+            user.transactions += 1;
+            user.fees_spent += transaction.fee_in_strk;
+        }
+
+        usage.users = users.into_values().collect();
+        usage.users.sort_by(|a, b| b.fees_spent.cmp(&a.fees_spent));
+
+        Ok(usage)
+    }
+
+    /// Returns the budget left to the given sponsor, None if the sponsor has no budget
+    pub async fn remaining_budget(&self, sponsor: &str, budget: Option<Felt>) -> Result<Option<Felt>, Error> {
+        let Some(budget) = budget else { return Ok(None) };
+
+        Ok(Some(remaining_of(budget, self.spent(sponsor).await?)))
+    }
+
+    async fn spent(&self, sponsor: &str) -> Result<Felt, Error> {
+        let Some(redis) = &self.redis else {
+            let ledger = self.memory.read().unwrap_or_else(|e| e.into_inner());
+            return Ok(ledger.spent.get(sponsor).copied().unwrap_or_default());
+        };
+
+        let sponsor = fingerprint(sponsor);
+        let today = now() / DAY;
+        let days = self.retention.as_secs().div_ceil(DAY);
+        let keys: Vec<String> = (today.saturating_sub(days)..=today)
+            .map(|day| Self::spent_key(&sponsor, day))
+            .collect();
+
+        let mut connection = Self::connection(redis).await?;
+        let units: Vec<Option<u64>> = cmd("MGET")
+            .arg(keys)
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| Error::Internal(e.to_string()))?;
+
+        Ok(Felt::from(units.into_iter().flatten().map(u128::from).sum::<u128>() * SPEND_UNIT))
+    }
+
+    fn transactions_key(sponsor: &str) -> String {
+        format!("sponsor-usage:{}", sponsor)
+    }
+
+    fn spent_key(sponsor: &str, day: u64) -> String {
+        format!("sponsor-spent:{}:{}", sponsor, day)
+    }
+
+    async fn connection(redis: &Pool) -> Result<Connection, Error> {
+        redis.get().await.map_err(|e| Error::Internal(e.to_string()))
+    }
+}
+
+// Sponsors are identified in Redis by the keccak of their api key
+fn fingerprint(sponsor: &str) -> String {
+    starknet_keccak(sponsor.as_bytes()).to_fixed_hex_string()
+}
+
+// Rounded up so that the spending of a sponsor is never underestimated
+fn to_spend_units(amount: Felt) -> u64 {
+    let amount = u128::try_from(amount).unwrap_or(u128::MAX);
+    amount.div_ceil(SPEND_UNIT).try_into().unwrap_or(u64::MAX)
+}
+
+fn remaining_of(budget: Felt, spent: Felt) -> Felt {
+    if budget > spent {
+        budget - spent
+    } else {
+        Felt::ZERO
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use deadpool_redis::{Config, Runtime};
+    use starknet::core::types::Felt;
+    use testcontainers::core::{IntoContainerPort, WaitFor};
+    use testcontainers::runners::AsyncRunner;
+    use testcontainers::GenericImage;
+
+    use crate::usage::{now, SponsoredTransaction, UsageLedger};
+
+    fn a_transaction(sponsor: &str, user: u64, fee: u64, timestamp: u64) -> SponsoredTransaction {
+        SponsoredTransaction {
+            sponsor: sponsor.to_string(),
+            user: Felt::from(user),
+            transaction_hash: Felt::from(timestamp * 100 + user + fee),
+            fee_in_strk: Felt::from(fee),
+            timestamp,
+        }
+    }
+
+    #[tokio::test]
+    async fn usage_is_aggregated_per_user() {
+        // Given
+        let now = now();
+        let ledger = UsageLedger::default();
+        ledger.record(a_transaction("paymaster_a", 1, 10, now)).await.unwrap();
+        ledger.record(a_transaction("paymaster_a", 1, 20, now)).await.unwrap();
+        ledger.record(a_transaction("paymaster_a", 2, 5, now)).await.unwrap();
+        ledger.record(a_transaction("paymaster_b", 1, 100, now)).await.unwrap();
+
+        // When
+        let usage = ledger
+            .usage("paymaster_a", now - 10, now + 10, Some(Felt::from(50)))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(usage.transactions, 3);
+        assert_eq!(usage.fees_spent, Felt::from(35));
+        assert_eq!(usage.remaining_budget, Some(Felt::from(15)));
+        assert_eq!(usage.users.len(), 2);
+        assert_eq!(usage.users[0].user, Felt::from(1));
+        assert_eq!(usage.users[0].fees_spent, Felt::from(30));
+    }
+
+    #[tokio::test]
+    async fn usage_is_filtered_by_time_range() {
+        // Given
+        let now = now();
+        let ledger = UsageLedger::default();
+        ledger.record(a_transaction("paymaster_a", 1, 10, now - 100)).await.unwrap();
+        ledger.record(a_transaction("paymaster_a", 1, 20, now)).await.unwrap();
+
+        // When
+        let usage = ledger
+            .usage("paymaster_a", now - 10, now + 10, Some(Felt::from(25)))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(usage.transactions, 1);
+        assert_eq!(usage.fees_spent, Felt::from(20));
+        assert_eq!(usage.remaining_budget, Some(Felt::ZERO));
+    }
+
+    #[tokio::test]
+    async fn remaining_budget_accounts_for_every_transaction_of_the_sponsor() {
+        // Given
+        let now = now();
+        let ledger = UsageLedger::default();
+        ledger.record(a_transaction("paymaster_a", 1, 10, now - 100)).await.unwrap();
+        ledger.record(a_transaction("paymaster_a", 2, 20, now)).await.unwrap();
+        ledger.record(a_transaction("paymaster_b", 1, 100, now)).await.unwrap();
+
+        // When
+        let remaining = ledger.remaining_budget("paymaster_a", Some(Felt::from(50))).await.unwrap();
+
+        // Then
+        assert_eq!(remaining, Some(Felt::from(20)));
+        assert_eq!(ledger.remaining_budget("paymaster_a", None).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn transactions_older_than_the_retention_no_longer_count() {
+        // Given
+        let now = now();
+        let ledger = UsageLedger::new(Duration::from_secs(60));
+        ledger.record(a_transaction("paymaster_a", 1, 10, now - 100)).await.unwrap();
+
+        // When
+        ledger.record(a_transaction("paymaster_a", 1, 20, now)).await.unwrap();
+
+        // Then
+        let usage = ledger.usage("paymaster_a", 0, now, Some(Felt::from(50))).await.unwrap();
+        assert_eq!(usage.transactions, 1);
+        assert_eq!(usage.remaining_budget, Some(Felt::from(30)));
+    }
+
+    #[tokio::test]
+    async fn usage_is_shared_through_redis() {
+        // Given
+        let container = GenericImage::new("redis", "7")
+            .with_exposed_port(6379.tcp())
+            .with_wait_for(WaitFor::message_on_stdout("Ready to accept connections"))
+            .start()
+            .await
+            .unwrap();
+        let port = container.get_host_port_ipv4(6379).await.unwrap();
+        let pool = || {
+            Config::from_url(format!("redis://127.0.0.1:{}", port))
+                .create_pool(Some(Runtime::Tokio1))
+                .unwrap()
+        };
+
+        let now = now();
+        let ledger = UsageLedger::default().with_redis(pool());
+        ledger
+            .record(a_transaction("paymaster_a", 1, 1_000_000_000, now - 100))
+            .await
+            .unwrap();
+        ledger
+            .record(a_transaction("paymaster_a", 2, 2_000_000_000, now))
+            .await
+            .unwrap();
+        ledger
+            .record(a_transaction("paymaster_b", 1, 5_000_000_000, now))
+            .await
+            .unwrap();
+
+        // When
+        let other = UsageLedger::default().with_redis(pool());
+        let usage = other
+            .usage("paymaster_a", now - 10, now + 10, Some(Felt::from(10_000_000_000u64)))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(usage.transactions, 1);
+        assert_eq!(usage.fees_spent, Felt::from(2_000_000_000u64));
+        assert_eq!(usage.remaining_budget, Some(Felt::from(7_000_000_000u64)));
+        assert_eq!(usage.users[0].user, Felt::from(2));
+    }
+}
@@ -3,12 +3,14 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use paymaster_common::concurrency::SyncValue;
+use paymaster_common::egress;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::{Client, Url};
 use serde::{Deserialize, Serialize};
 use starknet::core::types::Felt;
 use tokio::sync::RwLock;
 
+use crate::scope::CallScope;
 use crate::{AuthenticatedApiKey, Error, WebhookConfiguration};
 
 #[derive(Serialize, Deserialize)]
@@ -16,6 +18,10 @@ struct ApiKeyValidationResponse {
     is_valid: bool,
     sponsor_metadata: Vec<Felt>,
     validity_duration: u64,
+
+    /// Calls the api key is allowed to sponsor, any call can be sponsored when not returned
+    #[serde(default)]
+    scope: Option<CallScope>,
 }
 
 #[derive(Clone)]
@@ -28,8 +34,7 @@ pub struct WebhookSponsoring {
 
 impl WebhookSponsoring {
     pub fn new(configuration: WebhookConfiguration) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(3))
+        let client = egress::apply(Client::builder().timeout(Duration::from_secs(3)))
             .build()
             .expect("Failed to build HTTP client");
         let headers = configuration
@@ -62,6 +67,7 @@ impl WebhookSponsoring {
                             AuthenticatedApiKey {
                                 is_valid: response.is_valid,
                                 sponsor_metadata: response.sponsor_metadata,
+                                scope: response.scope,
                             },
                             response.validity_duration,
                         ))
@@ -85,6 +91,7 @@ impl WebhookSponsoring {
 
     async fn fetch_validate(&self, api_key: &str) -> Result<ApiKeyValidationResponse, Error> {
         let url = Url::parse(&self.endpoint).map_err(|e| Error::URL(e.to_string()))?;
+        egress::ensure_allowed(url.as_str())?;
         let mut headers = self.headers.clone();
         headers.insert("x-paymaster-api-key", HeaderValue::from_str(api_key).map_err(|e| Error::Internal(e.to_string()))?);
 
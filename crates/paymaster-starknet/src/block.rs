@@ -0,0 +1,9 @@
+use starknet::core::types::Felt;
+
+/// Identifies an accepted block and the block it extends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub number: u64,
+    pub hash: Felt,
+    pub parent_hash: Felt,
+}
@@ -0,0 +1,496 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use paymaster_common::cache::ExpirableCache;
+use paymaster_common::service::fallback;
+use paymaster_common::{measure_duration, metric};
+use starknet::accounts::{ArgentAccountFactory, ExecutionEncoding, SingleOwnerAccount};
+use starknet::core::types::SimulationFlagForEstimateFee::SkipValidate;
+use starknet::core::types::{
+    BlockId, BlockTag, BroadcastedTransaction, ExecutionResult, FeeEstimate, Felt, FunctionCall, Hash256, MaybePreConfirmedBlockWithTxHashes,
+    MaybePreConfirmedBlockWithTxs, MessageFeeEstimate, MessageStatus, MsgFromL1, Transaction, TransactionReceiptWithBlockInfo, TransactionStatus,
+};
+use starknet::macros::selector;
+use starknet::providers::{Provider, ProviderError};
+use starknet::signers::{LocalWallet, SigningKey};
+use tracing::instrument;
+
+use crate::client::StarknetClient;
+use crate::constants::{ChainTokens, ClassHash};
+use crate::contract::ContractClass;
+#[cfg(feature = "local-estimation")]
+use crate::estimation::BlockifierEstimator;
+use crate::estimation::LocalEstimator;
+use crate::{log_if_error, BlockGasPrice, BlockHeader, ChainID, Configuration, ContractAddress, Error, StarknetAccountConfiguration};
+
+pub type StarknetAccount = SingleOwnerAccount<StarknetClient, LocalWallet>;
+
+/// Duration during which the status of a transaction is served from the cache, bounds the requests of the clients
+/// polling the status of the same transactions
+pub const TRANSACTION_STATUS_TTL: Duration = Duration::from_secs(2);
+
+/// Duration during which the status of a transaction accepted on L1, which can no longer change, is served from the
+/// cache
+pub const FINAL_TRANSACTION_STATUS_TTL: Duration = Duration::from_secs(3600);
+
+impl From<fallback::Error<ProviderError>> for Error {
+    fn from(value: fallback::Error<ProviderError>) -> Self {
+        match value {
+            fallback::Error::Rejected => Self::Internal("could not connect to endpoint".to_string()),
+            fallback::Error::Inner(e) => e.into(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Client {
+    chain_id: ChainID,
+
+    /// Addresses of the well-known tokens of the chain
+    tokens: ChainTokens,
+
+    inner: StarknetClient,
+
+    /// Backend estimating the transactions first, if any
+    local_estimation: Option<Arc<dyn LocalEstimator>>,
+
+    /// Statuses fetched recently indexed by transaction hash
+    statuses: ExpirableCache<Felt, TransactionStatus>,
+}
+
+impl Client {
+    /// Creates a new client given a [`configuration`]. Fails when one of the endpoints is not a valid URL
+    pub fn new(configuration: &Configuration) -> Result<Self, Error> {
+        let mut client = StarknetClient::new(&configuration.endpoint, configuration.timeout, configuration.options_of(&configuration.endpoint))?;
+        for fallback in &configuration.fallbacks {
+            client = client.with_fallback(fallback, configuration.timeout, configuration.options_of(fallback))?;
+        }
+        let client = client.with_chain_id(configuration.chain_id.as_felt());
+
+        Ok(Self {
+            chain_id: configuration.chain_id,
+            tokens: configuration.tokens(),
+            #[cfg(feature = "local-estimation")]
+            local_estimation: configuration
+                .local_estimation
+                .as_ref()
+                .map(|local| Arc::new(BlockifierEstimator::new(client.clone(), configuration.chain_id, configuration.tokens(), local)) as Arc<dyn LocalEstimator>),
+            #[cfg(not(feature = "local-estimation"))]
+            local_estimation: None,
+            inner: client,
+            statuses: ExpirableCache::new(10_000),
+        })
+    }
+
+    /// Client whose requests are served by the given in-memory provider
+    #[cfg(feature = "testing")]
+    pub fn mock(chain_id: ChainID, provider: crate::testing::provider::MockProvider) -> Self {
+        Self {
+            chain_id,
+            tokens: ChainTokens::starknet(&chain_id),
+            inner: StarknetClient::mock(provider).with_chain_id(chain_id.as_felt()),
+            local_estimation: None,
+            statuses: ExpirableCache::new(10_000),
+        }
+    }
+
+    /// Estimates the transactions with the given backend first, falling back on the endpoints when it fails
+    pub fn with_local_estimator(mut self, estimator: Arc<dyn LocalEstimator>) -> Self {
+        self.local_estimation = Some(estimator);
+        self
+    }
+
+    /// Returns the chain_id on which this client is bound
+    pub fn chain_id(&self) -> &ChainID {
+        &self.chain_id
+    }
+
+    /// Returns the addresses of the well-known tokens of the chain on which this client is bound
+    pub fn tokens(&self) -> &ChainTokens {
+        &self.tokens
+    }
+
+    /// Initialize an account using the given account configuration
+    pub fn initialize_account(&self, account: &StarknetAccountConfiguration) -> StarknetAccount {
+        let signing_key = LocalWallet::from_signing_key(SigningKey::from_secret_scalar(account.private_key));
+
+        let mut account = StarknetAccount::new(self.inner.clone(), signing_key, account.address, self.chain_id.as_felt(), ExecutionEncoding::New);
+        account.set_block_id(BlockId::Tag(BlockTag::PreConfirmed));
+        account
+    }
+
+    /// Initialize an argent account using the given account configuration.
+    pub async fn initialize_argent_account(&self, private_key: Felt) -> Result<ArgentAccountFactory<LocalWallet, StarknetClient>, Error> {
+        let class_hash = ClassHash::ARGENT_ACCOUNT;
+        let signer = LocalWallet::from(SigningKey::from_secret_scalar(private_key));
+
+        ArgentAccountFactory::new(class_hash, self.chain_id.as_felt(), None, signer, self.inner.clone())
+            .await
+            .map_err(|e| Error::Internal(e.to_string()))
+    }
+
+    /// Fetch the gas price at the latest block. Price is given in wei
+    #[instrument(name = "fetch_block_gas_price", skip(self))]
+    pub async fn fetch_block_gas_price(&self) -> Result<BlockGasPrice, Error> {
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_block_with_txs(BlockId::Tag(BlockTag::Latest)).await));
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "fetch_block_gas_price");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "fetch_block_gas_price");
+
+        let prices = match result? {
+            MaybePreConfirmedBlockWithTxs::Block(block) => (block.l1_gas_price.price_in_fri, block.l1_data_gas_price.price_in_fri, block.l2_gas_price.price_in_fri),
+            MaybePreConfirmedBlockWithTxs::PreConfirmedBlock(block) => {
+                (block.l1_gas_price.price_in_fri, block.l1_data_gas_price.price_in_fri, block.l2_gas_price.price_in_fri)
+            },
+        };
+
+        Ok(BlockGasPrice {
+            l1_gas_price: prices.0,
+            l1_data_gas_price: prices.1,
+            l2_gas_price: prices.2,
+        })
+    }
+
+    /// Fetch the median tip at the latest block
+    #[instrument(name = "fetch_block_median_tip", skip(self))]
+    pub async fn fetch_block_median_tip(&self) -> Result<u64, Error> {
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_block_with_txs(BlockId::Tag(BlockTag::Latest)).await));
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "fetch_block_median_tip");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "fetch_block_median_tip");
+        Ok(result?.median_tip())
+    }
+
+    /// Call `balance_of(recipient)` on the given `token` address
+    #[instrument(name = "fetch_balance", skip(self))]
+    pub async fn fetch_balance(&self, token: Felt, recipient: Felt) -> Result<Felt, Error> {
+        let call = FunctionCall {
+            contract_address: token,
+            entry_point_selector: selector!("balance_of"),
+            calldata: vec![recipient],
+        };
+
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.call(call, BlockId::Tag(BlockTag::PreConfirmed)).await));
+
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "token_balance_of");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "token_balance_of");
+
+        result?.first().cloned().ok_or(Error::ContractNotFound)
+    }
+
+    /// Call `balance_of(recipient)` on the given `token` address at the given block
+    #[instrument(name = "fetch_balance_at", skip(self))]
+    pub async fn fetch_balance_at(&self, token: Felt, recipient: Felt, block_number: u64) -> Result<Felt, Error> {
+        let call = FunctionCall {
+            contract_address: token,
+            entry_point_selector: selector!("balance_of"),
+            calldata: vec![recipient],
+        };
+
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.call(call, BlockId::Number(block_number)).await));
+
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "token_balance_of");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "token_balance_of");
+
+        result?.first().cloned().ok_or(Error::ContractNotFound)
+    }
+
+    /// Fetch the number of the latest block
+    #[instrument(name = "fetch_block_number", skip(self))]
+    pub async fn fetch_block_number(&self) -> Result<u64, Error> {
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.block_number().await));
+
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "block_number");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "block_number");
+
+        Ok(result?)
+    }
+
+    /// Fetch the header of the latest accepted block
+    #[instrument(name = "fetch_latest_block_header", skip(self))]
+    pub async fn fetch_latest_block_header(&self) -> Result<BlockHeader, Error> {
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_block_with_tx_hashes(BlockId::Tag(BlockTag::Latest)).await));
+
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "get_block_with_tx_hashes");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "get_block_with_tx_hashes");
+
+        match result? {
+            MaybePreConfirmedBlockWithTxHashes::Block(block) => Ok(BlockHeader {
+                number: block.block_number,
+                hash: block.block_hash,
+                parent_hash: block.parent_hash,
+            }),
+            MaybePreConfirmedBlockWithTxHashes::PreConfirmedBlock(_) => Err(Error::Internal("latest block is not accepted".to_string())),
+        }
+    }
+
+    /// Fetch the nonce of the given `user` at the given block
+    #[instrument(name = "fetch_nonce_at", skip(self))]
+    pub async fn fetch_nonce_at(&self, user: ContractAddress, block_number: u64) -> Result<Felt, Error> {
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_nonce(BlockId::Number(block_number), user).await));
+
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "get_nonce");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "get_nonce");
+
+        Ok(result?)
+    }
+
+    /// Fetch the nonce of the given `user`
+    #[instrument(name = "fetch_nonce", skip(self))]
+    pub async fn fetch_nonce(&self, user: ContractAddress) -> Result<Felt, Error> {
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_nonce(BlockId::Tag(BlockTag::PreConfirmed), user).await));
+
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "get_nonce");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "get_nonce");
+
+        Ok(result?)
+    }
+
+    /// Execute the given `call`
+    #[instrument(name = "call", skip(self))]
+    pub async fn call(&self, call: &FunctionCall) -> Result<Vec<Felt>, Error> {
+        let block = BlockId::Tag(BlockTag::PreConfirmed);
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.call(call, block).await));
+
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "call");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "call");
+
+        Ok(result?)
+    }
+
+    /// Estimates the `transactions` and returns their [`FeeEstimate`]
+    #[instrument(name = "estimate_transactions", skip(self))]
+    pub async fn estimate_transactions(&self, transactions: &[BroadcastedTransaction]) -> Result<Vec<FeeEstimate>, Error> {
+        let block = BlockId::Tag(BlockTag::PreConfirmed);
+
+        // Estimate locally first, any failure falls back on the remote endpoints which remain authoritative
+        if let Some(local) = &self.local_estimation {
+            let (result, duration) = measure_duration!(local.estimate(transactions).await);
+            metric!(histogram[starknet_rpc] = duration.as_millis(), method = "estimate_transactions_local");
+
+            match result {
+                Ok(estimates) => return Ok(estimates),
+                Err(error) => {
+                    tracing::warn!("Local estimation failed, falling back on remote estimation: {}", error);
+                    metric!(counter[starknet_local_estimation_fallback] = 1);
+                },
+            }
+        }
+
+        // Estimate fees
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.estimate_fee(transactions, vec![SkipValidate], block).await));
+
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "estimate_transactions");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "estimate_transactions");
+
+        Ok(result?)
+    }
+
+    /// Estimates the fee of the L1 handler triggered by the given L1 `message`
+    #[instrument(name = "estimate_message_fee", skip(self))]
+    pub async fn estimate_message_fee(&self, message: &MsgFromL1) -> Result<MessageFeeEstimate, Error> {
+        let block = BlockId::Tag(BlockTag::PreConfirmed);
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.estimate_message_fee(message, block).await));
+
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "estimate_message_fee");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "estimate_message_fee");
+
+        Ok(result?)
+    }
+
+    /// Returns the status of the L2 transactions triggered by the messages sent in the L1 transaction with `hash`
+    #[instrument(name = "get_messages_status", skip(self))]
+    pub async fn get_messages_status(&self, hash: Hash256) -> Result<Vec<MessageStatus>, Error> {
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_messages_status(hash).await));
+
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "get_messages_status");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "get_messages_status");
+
+        Ok(result?)
+    }
+
+    /// Returns the receipt of the transaction with `hash`
+    #[instrument(name = "fetch_class", skip(self))]
+    pub async fn fetch_class(&self, class_hash: Felt) -> Result<ContractClass, Error> {
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_class(BlockId::Tag(BlockTag::Latest), class_hash).await));
+
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "get_class");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "get_class");
+
+        Ok(ContractClass::from_class(result?))
+    }
+
+    /// Returns the class hash of the contract deployed at `address`
+    #[instrument(name = "fetch_class_hash_at", skip(self))]
+    pub async fn fetch_class_hash_at(&self, address: ContractAddress) -> Result<Felt, Error> {
+        let (result, duration) = measure_duration!(log_if_error!(
+            self.inner
+                .get_class_hash_at(BlockId::Tag(BlockTag::PreConfirmed), address)
+                .await
+        ));
+
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "get_class_hash_at");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "get_class_hash_at");
+
+        Ok(result?)
+    }
+
+    /// Returns the receipt of the transaction with `hash`
+    #[instrument(name = "get_transaction_receipt", skip(self))]
+    pub async fn get_transaction_receipt(&self, hash: Felt) -> Result<TransactionReceiptWithBlockInfo, Error> {
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_transaction_receipt(hash).await));
+
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "get_transaction_receipt");
+        metric!(on error result => counter [starknet_rpc_error ] = 1, method = "get_transaction_receipt");
+
+        Ok(result?)
+    }
+
+    /// Returns the status of the transaction with `hash`, served from the cache when it was fetched recently
+    #[instrument(name = "get_transaction_status", skip(self))]
+    pub async fn get_transaction_status(&self, hash: Felt) -> Result<TransactionStatus, Error> {
+        if let Some(status) = self.statuses.get_if_not_stale(&hash) {
+            metric!(counter[starknet_rpc_cache_hit] = 1, method = "get_transaction_status");
+            return Ok(status);
+        }
+
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_transaction_status(hash).await));
+
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "get_transaction_status");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "get_transaction_status");
+
+        let status = result?;
+        self.statuses.insert(hash, status.clone(), status_retention(&status));
+
+        Ok(status)
+    }
+
+    /// Forget the status of the transaction with `hash`, so that it is fetched again on the next request. Used when
+    /// the cached status may no longer hold, typically after a reorg.
+    pub fn invalidate_transaction_status(&self, hash: Felt) {
+        self.statuses.remove(&hash);
+    }
+
+    /// Wait until the transaction with `hash` is accepted on L2, polling its status every `poll_interval`. Returns
+    /// true if it succeeded and false if it was reverted, fails when it is still not accepted after `timeout`.
+    pub async fn wait_for_acceptance(&self, hash: Felt, poll_interval: Duration, timeout: Duration) -> Result<bool, Error> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if let Ok(TransactionStatus::AcceptedOnL2(result) | TransactionStatus::AcceptedOnL1(result)) = self.get_transaction_status(hash).await {
+                return Ok(matches!(result, ExecutionResult::Succeeded));
+            }
+
+            if tokio::time::Instant::now() + poll_interval > deadline {
+                return Err(Error::Starknet(format!(
+                    "transaction {} not accepted after {:?}",
+                    hash.to_fixed_hex_string(),
+                    timeout
+                )));
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Returns the transaction with `hash`
+    #[instrument(name = "get_transaction", skip(self))]
+    pub async fn get_transaction(&self, hash: Felt) -> Result<Transaction, Error> {
+        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_transaction_by_hash(hash).await));
+
+        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "get_transaction");
+        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "get_transaction");
+
+        Ok(result?)
+    }
+}
+
+/// Statuses which can no longer change are kept longer in the cache
+fn status_retention(status: &TransactionStatus) -> Duration {
+    match status {
+        TransactionStatus::AcceptedOnL1(_) => FINAL_TRANSACTION_STATUS_TTL,
+        _ => TRANSACTION_STATUS_TTL,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use starknet::core::types::{BroadcastedTransaction, ExecutionResult, FeeEstimate, Felt, StarknetError, TransactionStatus};
+
+    use crate::chain::{status_retention, FINAL_TRANSACTION_STATUS_TTL, TRANSACTION_STATUS_TTL};
+    use crate::estimation::LocalEstimator;
+    use crate::testing::provider::MockProvider;
+    use crate::transaction::Calls;
+    use crate::{ChainID, Client, Error};
+
+    struct StubEstimator(Option<FeeEstimate>);
+
+    #[async_trait]
+    impl LocalEstimator for StubEstimator {
+        async fn estimate(&self, transactions: &[BroadcastedTransaction]) -> Result<Vec<FeeEstimate>, Error> {
+            match &self.0 {
+                Some(estimate) => Ok(vec![estimate.clone(); transactions.len()]),
+                None => Err(Error::Internal("cannot estimate locally".to_string())),
+            }
+        }
+    }
+
+    fn an_estimate(overall_fee: u128) -> FeeEstimate {
+        serde_json::from_value(serde_json::json!({
+            "l1_gas_consumed": "0x0",
+            "l1_gas_price": "0x1",
+            "l2_gas_consumed": "0x0",
+            "l2_gas_price": "0x1",
+            "l1_data_gas_consumed": "0x0",
+            "l1_data_gas_price": "0x1",
+            "overall_fee": format!("{:#x}", overall_fee),
+            "unit": "FRI"
+        }))
+        .unwrap()
+    }
+
+    fn a_client(provider: MockProvider, local: Option<FeeEstimate>) -> Client {
+        Client::mock(ChainID::Sepolia, provider).with_local_estimator(Arc::new(StubEstimator(local)))
+    }
+
+    #[tokio::test]
+    async fn local_estimations_spare_the_endpoints() {
+        // The provider fails every estimation it is asked for
+        let client = a_client(MockProvider::new(), Some(an_estimate(10)));
+
+        let transaction = Calls::empty().as_transaction(Felt::ONE, Felt::ZERO, 0);
+        let estimates = client.estimate_transactions(&[transaction]).await.unwrap();
+        assert_eq!(estimates, vec![an_estimate(10)]);
+    }
+
+    #[tokio::test]
+    async fn failed_local_estimations_fall_back_on_the_endpoints() {
+        let provider = MockProvider::new();
+        provider.on_estimate_fee(an_estimate(20));
+        let client = a_client(provider, None);
+
+        let transaction = Calls::empty().as_transaction(Felt::ONE, Felt::ZERO, 0);
+        let estimates = client.estimate_transactions(&[transaction]).await.unwrap();
+        assert_eq!(estimates, vec![an_estimate(20)]);
+    }
+
+    #[tokio::test]
+    async fn errors_of_the_endpoints_are_returned_when_the_local_estimation_fails() {
+        let provider = MockProvider::new();
+        provider.on_estimate_fee_error(|| StarknetError::ContractNotFound);
+        let client = a_client(provider, None);
+
+        let transaction = Calls::empty().as_transaction(Felt::ONE, Felt::ZERO, 0);
+        let result = client.estimate_transactions(&[transaction]).await;
+        assert!(matches!(result, Err(Error::ContractNotFound)));
+    }
+
+    #[test]
+    fn final_statuses_are_kept_longer() {
+        assert_eq!(
+            status_retention(&TransactionStatus::AcceptedOnL1(ExecutionResult::Succeeded)),
+            FINAL_TRANSACTION_STATUS_TTL
+        );
+        assert_eq!(status_retention(&TransactionStatus::AcceptedOnL2(ExecutionResult::Succeeded)), TRANSACTION_STATUS_TTL);
+        assert_eq!(status_retention(&TransactionStatus::Received), TRANSACTION_STATUS_TTL);
+    }
+}
@@ -1,24 +1,40 @@
-use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use async_trait::async_trait;
+use paymaster_common::cache::ExpirableCache;
 use paymaster_common::service::fallback::{Error, FailurePredicate, WithFallback};
+use paymaster_common::{egress, metric};
 use starknet::core::types::{
     BlockHashAndNumber, BlockId, BroadcastedDeclareTransaction, BroadcastedDeployAccountTransaction, BroadcastedInvokeTransaction, BroadcastedTransaction,
     ConfirmedBlockId, ContractClass, ContractStorageKeys, DeclareTransactionResult, DeployAccountTransactionResult, EventFilter, EventsPage, FeeEstimate, Felt,
     FunctionCall, Hash256, InvokeTransactionResult, MaybePreConfirmedBlockWithReceipts, MaybePreConfirmedBlockWithTxHashes, MaybePreConfirmedBlockWithTxs,
-    MaybePreConfirmedStateUpdate, MessageFeeEstimate, MessageStatus, MsgFromL1, SimulatedTransaction, SimulationFlag, SimulationFlagForEstimateFee, StorageProof,
-    SyncStatusType, Transaction, TransactionReceiptWithBlockInfo, TransactionStatus, TransactionTrace, TransactionTraceWithHash,
+    MaybePreConfirmedStateUpdate, MessageFeeEstimate, MessageStatus, MsgFromL1, SimulatedTransaction, SimulationFlag, SimulationFlagForEstimateFee, StarknetError,
+    StorageProof, SyncStatusType, Transaction, TransactionReceiptWithBlockInfo, TransactionStatus, TransactionTrace, TransactionTraceWithHash,
 };
 use starknet::providers::jsonrpc::{HttpTransport, JsonRpcClientError};
 use starknet::providers::{JsonRpcClient, Provider, ProviderError, ProviderRequestData, ProviderResponseData, Url};
 use tracing::instrument;
 
+use crate::transaction::invoke_transaction_hash;
+use crate::EndpointOptions;
+
+/// Duration during which a submission whose outcome is unknown is checked before being submitted again
+const UNCERTAIN_SUBMISSION_RETENTION: Duration = Duration::from_secs(600);
+
 macro_rules! call_with_fallback {
     ($self: ident . $method: ident ( $($arg: expr),* )) => {
         $self
-            .0
-            .call(|x| async move { x.$method( $($arg),* ).await })
+            .endpoints
+            .call(|x| async move {
+                x.record_use();
+                match &x.client {
+                    StarknetRPCClient::Http(x) => x.$method( $($arg),* ).await,
+                    #[cfg(feature = "testing")]
+                    StarknetRPCClient::Mock(x) => x.$method( $($arg),* ).await,
+                }
+            })
             .await
             .map_err(|e| match e {
                 Error::Inner(e) => {
@@ -34,58 +50,189 @@ macro_rules! call_with_fallback {
 }
 
 #[derive(Clone)]
-struct StarknetRPCClient(JsonRpcClient<HttpTransport>);
+enum StarknetRPCClient {
+    Http(JsonRpcClient<HttpTransport>),
+
+    /// In-memory provider used by the unit tests
+    #[cfg(feature = "testing")]
+    Mock(crate::testing::provider::MockProvider),
+}
 
 impl StarknetRPCClient {
-    fn new(endpoint: &str, timeout: u64) -> Self {
-        Url::parse(endpoint)
-            .map(|endpoint| {
-                HttpTransport::new_with_client(
-                    endpoint,
-                    reqwest::Client::builder()
-                        .timeout(Duration::from_secs(timeout))
-                        .connect_timeout(Duration::from_secs(5))
-                        .tcp_keepalive(Some(Duration::from_secs(30)))
-                        .build()
-                        .expect("failed to build Starknet HTTP client"),
-                )
-            })
-            .map(JsonRpcClient::new)
-            .map(Self)
-            .expect("invalid client")
+    fn new(endpoint: &str, timeout: u64, options: Option<&EndpointOptions>) -> Result<Self, crate::Error> {
+        let mut url = Url::parse(endpoint).map_err(|e| crate::Error::InvalidEndpoint(format!("{}: {}", endpoint, e)))?;
+        egress::ensure_allowed(endpoint).map_err(|e| crate::Error::InvalidEndpoint(e.to_string()))?;
+
+        let mut builder = reqwest::Client::builder()
+            .timeout(Duration::from_secs(timeout))
+            .connect_timeout(Duration::from_secs(5))
+            .tcp_keepalive(Some(Duration::from_secs(30)));
+
+        let options = options.cloned().unwrap_or_default();
+        if let Some(proxy) = &options.proxy {
+            let proxy = reqwest::Proxy::all(proxy).map_err(|e| crate::Error::InvalidEndpoint(format!("{}: invalid proxy {}", endpoint, e)))?;
+            builder = builder.proxy(proxy);
+        }
+
+        // Credentials given in the url are sent as basic authentication
+        if let Some(credentials) = &options.basic_auth {
+            url.set_username(&credentials.username)
+                .and_then(|_| url.set_password(Some(&credentials.password)))
+                .map_err(|_| crate::Error::InvalidEndpoint(format!("{}: cannot carry credentials", endpoint)))?;
+        }
+
+        let client = egress::apply(builder)
+            .build()
+            .map_err(|e| crate::Error::Internal(format!("failed to build Starknet HTTP client: {}", e)))?;
+
+        let mut transport = HttpTransport::new_with_client(url, client);
+        for (name, value) in options.headers {
+            transport = transport.with_header(name, value);
+        }
+
+        Ok(Self::Http(JsonRpcClient::new(transport)))
+    }
+}
+
+/// Returns the class of the errors which make the requests fall back on the next endpoint
+fn failure_class(err: &ProviderError) -> Option<&'static str> {
+    match err {
+        ProviderError::RateLimited => Some("rate_limited"),
+        ProviderError::Other(_) => Some("transport"),
+        _ => None,
     }
 }
 
-impl Deref for StarknetRPCClient {
-    type Target = JsonRpcClient<HttpTransport>;
+/// Usage of the endpoints shared by the clones of a client, reported so that the silent failovers to the
+/// fallback endpoints, which usually degrade the latency, are noticed
+#[derive(Default)]
+struct EndpointUsage {
+    /// Position of the endpoint which served the last request, the primary endpoint being 0
+    active: AtomicUsize,
+
+    /// Class of the last failure of the primary endpoint
+    primary_failure: Mutex<Option<&'static str>>,
+}
+
+#[derive(Clone)]
+struct StarknetEndpoint {
+    position: usize,
+    client: StarknetRPCClient,
+    usage: Arc<EndpointUsage>,
+}
+
+impl StarknetEndpoint {
+    fn name(&self) -> String {
+        match self.position {
+            0 => "primary".to_string(),
+            x => format!("fallback_{}", x),
+        }
+    }
+
+    fn record_use(&self) {
+        let previous = self.usage.active.swap(self.position, Ordering::Relaxed);
+        if previous != self.position {
+            metric!(gauge[starknet_rpc_active_endpoint] = self.position as u64);
+            tracing::warn!(endpoint = %self.name(), "starknet requests switched endpoint");
+        }
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+        if self.position > 0 {
+            let reason = self.usage.primary_failure.lock().ok().and_then(|x| *x).unwrap_or("unknown");
+            metric!(counter[starknet_rpc_fallback_used] = 1, endpoint = self.name(), reason = reason);
+        }
     }
 }
 
-impl FailurePredicate<ProviderError> for StarknetRPCClient {
+impl FailurePredicate<ProviderError> for StarknetEndpoint {
     fn is_err(&self, err: &ProviderError) -> bool {
-        match err {
-            ProviderError::RateLimited => true,
-            ProviderError::Other(_) => true,
-            _ => false,
+        let class = failure_class(err);
+        if let Some(class) = class {
+            metric!(counter[starknet_rpc_endpoint_failure] = 1, endpoint = self.name(), class = class);
+            if self.position == 0 {
+                if let Ok(mut failure) = self.usage.primary_failure.lock() {
+                    *failure = Some(class);
+                }
+            }
         }
+
+        class.is_some()
     }
 }
 
 #[derive(Clone)]
-pub struct StarknetClient(WithFallback<StarknetRPCClient>);
+pub struct StarknetClient {
+    endpoints: WithFallback<StarknetEndpoint>,
+    usage: Arc<EndpointUsage>,
+    count: usize,
+
+    /// Chain of the endpoints, needed to compute the hash of the invoke transactions before submitting them
+    chain_id: Option<Felt>,
+
+    /// Invoke transactions whose submission failed without telling whether an endpoint accepted them, indexed by
+    /// hash. They may have been accepted by an endpoint which timed out before answering.
+    uncertain_submissions: ExpirableCache<Felt, ()>,
+}
 
 impl StarknetClient {
-    pub fn new(endpoint: &str, timeout: u64) -> Self {
-        Self(WithFallback::new().with(StarknetRPCClient::new(endpoint, timeout)))
+    /// Creates a client bound to the given endpoint, sending its requests with the given options. Fails when the
+    /// endpoint or its proxy is not a valid URL
+    pub fn new(endpoint: &str, timeout: u64, options: Option<&EndpointOptions>) -> Result<Self, crate::Error> {
+        Ok(Self::with_client(StarknetRPCClient::new(endpoint, timeout, options)?))
+    }
+
+    /// Adds an endpoint the requests fall back on when the previous ones fail
+    pub fn with_fallback(mut self, endpoint: &str, timeout: u64, options: Option<&EndpointOptions>) -> Result<Self, crate::Error> {
+        let endpoint = StarknetEndpoint {
+            position: self.count,
+            client: StarknetRPCClient::new(endpoint, timeout, options)?,
+            usage: self.usage.clone(),
+        };
+
+        self.endpoints = self.endpoints.with(endpoint);
+        self.count += 1;
+        Ok(self)
+    }
+
+    /// Client backed by the given in-memory provider
+    #[cfg(feature = "testing")]
+    pub fn mock(provider: crate::testing::provider::MockProvider) -> Self {
+        Self::with_client(StarknetRPCClient::Mock(provider))
+    }
+
+    fn with_client(client: StarknetRPCClient) -> Self {
+        let usage = Arc::new(EndpointUsage::default());
+        let primary = StarknetEndpoint {
+            position: 0,
+            client,
+            usage: usage.clone(),
+        };
+
+        metric!(gauge[starknet_rpc_active_endpoint] = 0u64);
+        Self {
+            endpoints: WithFallback::new().with(primary),
+            usage,
+            count: 1,
+            chain_id: None,
+            uncertain_submissions: ExpirableCache::new(10_000),
+        }
     }
 
-    pub fn with_fallback(mut self, endpoint: &str, timeout: u64) -> Self {
-        self.0 = self.0.with(StarknetRPCClient::new(endpoint, timeout));
+    /// Makes the submission of the invoke transactions idempotent: a transaction submitted again after a failure
+    /// which may have hidden its acceptance (timeout, rate limit, failover) is only sent if the chain does not
+    /// know it yet
+    pub fn with_chain_id(mut self, chain_id: Felt) -> Self {
+        self.chain_id = Some(chain_id);
         self
     }
+
+    // Returns true if the chain knows the transaction, fails when it cannot be told
+    async fn is_known(&self, transaction_hash: Felt) -> Result<bool, ProviderError> {
+        match self.get_transaction_status(transaction_hash).await {
+            Ok(_) => Ok(true),
+            Err(ProviderError::StarknetError(StarknetError::TransactionHashNotFound)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
 }
 
 #[async_trait]
@@ -312,7 +459,37 @@ impl Provider for StarknetClient {
     where
         I: AsRef<BroadcastedInvokeTransaction> + Send + Sync,
     {
-        call_with_fallback!(self.add_invoke_transaction(invoke_transaction))
+        let Some(chain_id) = self.chain_id else {
+            return call_with_fallback!(self.add_invoke_transaction(invoke_transaction));
+        };
+
+        // The status is checked before submitting again a transaction whose first submission may have been accepted
+        let transaction_hash = invoke_transaction_hash(chain_id, invoke_transaction.as_ref());
+        let resubmission = self.uncertain_submissions.get_if_not_stale(&transaction_hash).is_some();
+        if resubmission && self.is_known(transaction_hash).await? {
+            metric!(counter[starknet_rpc_duplicate_submission] = 1, outcome = "skipped");
+            self.uncertain_submissions.remove(&transaction_hash);
+            return Ok(InvokeTransactionResult { transaction_hash });
+        }
+
+        match call_with_fallback!(self.add_invoke_transaction(invoke_transaction)) {
+            Ok(result) => {
+                self.uncertain_submissions.remove(&transaction_hash);
+                Ok(result)
+            },
+            Err(ProviderError::StarknetError(StarknetError::DuplicateTx)) if resubmission => {
+                metric!(counter[starknet_rpc_duplicate_submission] = 1, outcome = "duplicate");
+                self.uncertain_submissions.remove(&transaction_hash);
+                Ok(InvokeTransactionResult { transaction_hash })
+            },
+            Err(e) => {
+                if failure_class(&e).is_some() {
+                    self.uncertain_submissions
+                        .insert(transaction_hash, (), UNCERTAIN_SUBMISSION_RETENTION);
+                }
+                Err(e)
+            },
+        }
     }
 
     /// Submits a new transaction to be added to the chain.
@@ -399,3 +576,86 @@ impl Provider for StarknetClient {
         call_with_fallback!(self.simulate_transaction(block_id, transaction, simulation_flags))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use paymaster_common::service::fallback::FailurePredicate;
+    use starknet::core::types::{BroadcastedInvokeTransaction, DataAvailabilityMode, Felt, ResourceBounds, ResourceBoundsMapping, StarknetError};
+    use starknet::providers::{Provider, ProviderError};
+
+    use crate::client::{StarknetClient, StarknetEndpoint, StarknetRPCClient};
+    use crate::testing::provider::MockProvider;
+    use crate::transaction::invoke_transaction_hash;
+    use crate::ChainID;
+
+    #[test]
+    fn failovers_are_recorded() {
+        let client = StarknetClient::new("http://localhost:5050", 1, None)
+            .unwrap()
+            .with_fallback("http://localhost:5051", 1, None)
+            .unwrap();
+        assert_eq!(client.count, 2);
+
+        let primary = StarknetEndpoint {
+            position: 0,
+            client: StarknetRPCClient::new("http://localhost:5050", 1, None).unwrap(),
+            usage: client.usage.clone(),
+        };
+        let fallback = StarknetEndpoint { position: 1, ..primary.clone() };
+
+        // Errors returned by Starknet itself do not make the requests fall back
+        assert!(!primary.is_err(&ProviderError::StarknetError(StarknetError::BlockNotFound)));
+        assert_eq!(*client.usage.primary_failure.lock().unwrap(), None);
+
+        assert!(primary.is_err(&ProviderError::RateLimited));
+        assert_eq!(*client.usage.primary_failure.lock().unwrap(), Some("rate_limited"));
+
+        fallback.record_use();
+        assert_eq!(client.usage.active.load(Ordering::Relaxed), 1);
+        primary.record_use();
+        assert_eq!(client.usage.active.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn uncertain_submissions_are_not_sent_blindly() {
+        let provider = MockProvider::default();
+        let client = StarknetClient::mock(provider.clone()).with_chain_id(ChainID::Sepolia.as_felt());
+
+        let bounds = || ResourceBounds {
+            max_amount: 1_000,
+            max_price_per_unit: 1_000,
+        };
+        let transaction = BroadcastedInvokeTransaction {
+            sender_address: Felt::from(0x1234),
+            calldata: vec![],
+            signature: vec![],
+            nonce: Felt::ZERO,
+            resource_bounds: ResourceBoundsMapping {
+                l1_gas: bounds(),
+                l1_data_gas: bounds(),
+                l2_gas: bounds(),
+            },
+            tip: 0,
+            paymaster_data: vec![],
+            account_deployment_data: vec![],
+            nonce_data_availability_mode: DataAvailabilityMode::L1,
+            fee_data_availability_mode: DataAvailabilityMode::L1,
+            is_query: false,
+        };
+
+        client.add_invoke_transaction(&transaction).await.unwrap();
+        assert_eq!(provider.submitted_transactions().len(), 1);
+
+        // The status of a transaction whose submission may have been accepted must be known before sending it again
+        let transaction_hash = invoke_transaction_hash(ChainID::Sepolia.as_felt(), &transaction);
+        client
+            .uncertain_submissions
+            .insert(transaction_hash, (), Duration::from_secs(60));
+
+        assert!(client.add_invoke_transaction(&transaction).await.is_err());
+        assert_eq!(provider.submitted_transactions().len(), 1);
+    }
+}
@@ -1,3 +1,7 @@
+use paymaster_common::validation::{Validate, ValidationReport};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::core::serde::unsigned_field_element::UfeHex;
 use starknet::core::types::Felt;
 use starknet::macros::felt;
 
@@ -46,6 +50,7 @@ impl Token {
         }
     }
 
+    /// Returns USDC as deployed on Starknet, see [`ChainTokens`] for the chains with their own deployment
     pub const fn usdc(chain_id: &ChainID) -> Token {
         match chain_id {
             ChainID::Mainnet => Token {
@@ -62,3 +67,72 @@ impl Token {
         }
     }
 }
+
+/// Addresses of the well-known tokens of a chain. Forks and appchains running under a custom chain id can deploy
+/// their own tokens, whose addresses are configured in place of the ones of Starknet.
+#[serde_as]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainTokens {
+    #[serde_as(as = "UfeHex")]
+    pub eth: Felt,
+
+    /// Token in which the fees are paid to the sequencer
+    #[serde_as(as = "UfeHex")]
+    pub strk: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub usdc: Felt,
+}
+
+impl ChainTokens {
+    /// Returns the tokens deployed on Starknet, the Sepolia ones for the unknown chain ids
+    pub const fn starknet(chain_id: &ChainID) -> Self {
+        Self {
+            eth: Token::ETH_ADDRESS,
+            strk: Token::STRK_ADDRESS,
+            usdc: Token::usdc(chain_id).address,
+        }
+    }
+
+    /// Returns the tokens configured for the given chain, or the ones deployed on Starknet when none were
+    pub fn resolve(chain_id: &ChainID, configured: Option<&ChainTokens>) -> Self {
+        configured.copied().unwrap_or_else(|| Self::starknet(chain_id))
+    }
+}
+
+impl Validate for ChainTokens {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.eth != Felt::ZERO, "eth", "must not be zero");
+        report.ensure(self.strk != Felt::ZERO, "strk", "must not be zero");
+        report.ensure(self.usdc != Felt::ZERO, "usdc", "must not be zero");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+    use starknet::macros::short_string;
+
+    use crate::constants::{ChainTokens, Token};
+    use crate::ChainID;
+
+    #[test]
+    fn custom_chains_use_their_configured_tokens() {
+        let chain_id = ChainID::Unknown(short_string!("SN_APPCHAIN_TEST"));
+        assert_eq!(ChainTokens::resolve(&chain_id, None).strk, Token::STRK_ADDRESS);
+
+        let configured = ChainTokens {
+            eth: Felt::from(0x1),
+            strk: Felt::from(0x2),
+            usdc: Felt::from(0x3),
+        };
+        let tokens = ChainTokens::resolve(&chain_id, Some(&configured));
+
+        assert_eq!(tokens.eth, Felt::from(0x1));
+        assert_eq!(tokens.strk, Felt::from(0x2));
+        assert_eq!(tokens.usdc, Felt::from(0x3));
+
+        // The chains without configured tokens keep the tokens deployed on Starknet
+        assert_eq!(ChainTokens::resolve(&ChainID::Mainnet, None).usdc, Token::usdc(&ChainID::Mainnet).address);
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::BTreeSet;
+
+use starknet::core::types::{Call, Felt, FunctionCall};
+use starknet::macros::selector;
+
+use crate::transaction::{CalldataBuilder, Calls};
+use crate::{Client, ContractAddress, Error};
+
+/// Forwarder contract through which the relayers execute the transactions. Only the addresses
+/// whitelisted on the forwarder are allowed to call it and the whitelist can only be updated
+/// by the owner of the forwarder (i.e. the master account).
+#[derive(Debug, Clone, Copy)]
+pub struct Forwarder {
+    pub address: ContractAddress,
+}
+
+impl Forwarder {
+    /// Selectors of the functions of the forwarder restricted to its owner, which no sponsored call should target
+    pub const ADMIN_SELECTORS: [Felt; 6] = [
+        selector!("set_whitelisted_address"),
+        selector!("set_gas_fees_recipient"),
+        selector!("transfer_ownership"),
+        selector!("renounce_ownership"),
+        selector!("upgrade"),
+        selector!("upgrade_class"),
+    ];
+
+    pub fn new(address: ContractAddress) -> Self {
+        Self { address }
+    }
+
+    /// Returns true if the call targets one of the admin functions of the forwarder
+    pub fn is_admin_call(&self, call: &Call) -> bool {
+        call.to == self.address && Self::ADMIN_SELECTORS.contains(&call.selector)
+    }
+
+    /// Returns the call which whitelists the given address, or removes it from the whitelist when `whitelisted` is false
+    pub fn set_whitelisted_address(&self, address: ContractAddress, whitelisted: bool) -> Call {
+        Call {
+            to: self.address,
+            selector: selector!("set_whitelisted_address"),
+            calldata: CalldataBuilder::new().encode(&address).encode(&Felt::from(whitelisted)).build(),
+        }
+    }
+
+    /// Returns true if the given address is whitelisted on the forwarder
+    pub async fn is_whitelisted(&self, client: &Client, address: ContractAddress) -> Result<bool, Error> {
+        let result = client
+            .call(&FunctionCall {
+                contract_address: self.address,
+                entry_point_selector: selector!("is_whitelisted"),
+                calldata: vec![address],
+            })
+            .await?;
+
+        Ok(result.first().is_some_and(|x| *x != Felt::ZERO))
+    }
+
+    /// Returns the given addresses which are not whitelisted on the forwarder, each address once and in order
+    pub async fn not_whitelisted(&self, client: &Client, addresses: &[ContractAddress]) -> Result<Vec<ContractAddress>, Error> {
+        let mut missing = vec![];
+        for address in addresses.iter().copied().collect::<BTreeSet<_>>() {
+            if !self.is_whitelisted(client, address).await? {
+                missing.push(address);
+            }
+        }
+
+        Ok(missing)
+    }
+
+    /// Returns the calls which whitelist the addresses in `whitelist` and remove the ones in `blacklist` from the whitelist.
+    /// They must be executed by the owner of the forwarder.
+    pub fn update_whitelist(&self, whitelist: &[ContractAddress], blacklist: &[ContractAddress]) -> Calls {
+        let calls = whitelist
+            .iter()
+            .map(|x| self.set_whitelisted_address(*x, true))
+            .chain(blacklist.iter().map(|x| self.set_whitelisted_address(*x, false)))
+            .collect();
+
+        Calls::new(calls)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::{Call, Felt};
+    use starknet::macros::selector;
+
+    use crate::contract::forwarder::Forwarder;
+
+    #[test]
+    fn whitelist_call_is_built_properly() {
+        let forwarder = Forwarder::new(Felt::ONE);
+
+        let call = forwarder.set_whitelisted_address(Felt::TWO, false);
+        assert_eq!(call.to, Felt::ONE);
+        assert_eq!(call.selector, selector!("set_whitelisted_address"));
+        assert_eq!(call.calldata, vec![Felt::TWO, Felt::ZERO]);
+    }
+
+    #[test]
+    fn whitelist_update_whitelists_before_blacklisting() {
+        let forwarder = Forwarder::new(Felt::ONE);
+
+        let calls = forwarder.update_whitelist(&[Felt::TWO], &[Felt::THREE]);
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].calldata, vec![Felt::TWO, Felt::ONE]);
+        assert_eq!(calls[1].calldata, vec![Felt::THREE, Felt::ZERO]);
+    }
+
+    #[test]
+    fn admin_calls_are_detected() {
+        let forwarder = Forwarder::new(Felt::ONE);
+
+        assert!(forwarder.is_admin_call(&forwarder.set_whitelisted_address(Felt::TWO, true)));
+
+        let call = |to: Felt, selector: Felt| Call { to, selector, calldata: vec![] };
+        assert!(!forwarder.is_admin_call(&call(Felt::ONE, selector!("is_whitelisted"))));
+        assert!(!forwarder.is_admin_call(&call(Felt::TWO, selector!("upgrade"))));
+    }
+}
@@ -1,4 +1,6 @@
 pub mod abi;
+#[cfg(feature = "native")]
+pub mod forwarder;
 
 use starknet::core::types::ContractClass as StarknetContractClass;
 
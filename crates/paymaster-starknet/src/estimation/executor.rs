@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use blockifier::blockifier_versioned_constants::VersionedConstants;
+use blockifier::bouncer::BouncerConfig;
+use blockifier::context::{BlockContext, ChainInfo, FeeTokenAddresses};
+use blockifier::execution::contract_class::{CompiledClassV1, RunnableCompiledClass};
+use blockifier::state::cached_state::CachedState;
+use blockifier::state::errors::StateError;
+use blockifier::state::state_api::{StateReader, StateResult};
+use blockifier::transaction::account_transaction::{AccountTransaction, ExecutionFlags};
+use blockifier::transaction::transactions::ExecutableTransaction;
+use cairo_lang_starknet_classes::casm_contract_class::CasmContractClass;
+use cairo_lang_starknet_classes::contract_class::ContractClass as SierraContractClass;
+use serde_json::json;
+use starknet::core::types::{
+    BlockId, BlockTag, BroadcastedInvokeTransactionV3, BroadcastedTransaction, ContractClass, DataAvailabilityMode, FeeEstimate, Felt, L1DataAvailabilityMode,
+    MaybePreConfirmedBlockWithTxHashes, PriceUnit, ResourceBounds, SierraEntryPoint,
+};
+use starknet::core::utils::parse_cairo_short_string;
+use starknet::providers::Provider;
+use starknet_api::block::{BlockInfo, BlockNumber, BlockTimestamp, GasPrice, GasPriceVector, GasPrices, NonzeroGasPrice};
+use starknet_api::contract_class::SierraVersion;
+use starknet_api::core::{ChainId, ClassHash, CompiledClassHash, ContractAddress, Nonce};
+use starknet_api::data_availability::DataAvailabilityMode as ApiDataAvailabilityMode;
+use starknet_api::executable_transaction::{AccountTransaction as ApiAccountTransaction, InvokeTransaction as ApiInvokeTransaction};
+use starknet_api::execution_resources::GasAmount;
+use starknet_api::hash::StarkHash;
+use starknet_api::state::StorageKey;
+use starknet_api::transaction::fields::{
+    AccountDeploymentData, AllResourceBounds, Calldata, PaymasterData, ResourceBounds as ApiResourceBounds, Tip, TransactionSignature, ValidResourceBounds,
+};
+use starknet_api::transaction::{InvokeTransaction, InvokeTransactionV3};
+use tokio::runtime::Handle;
+
+use crate::client::StarknetClient;
+use crate::constants::ChainTokens;
+use crate::estimation::{LocalEstimator, RemoteState};
+use crate::{ChainID, Error, LocalEstimationConfiguration};
+
+/// Estimates the transactions by executing them with the blockifier, the execution engine of the sequencer, on the
+/// state read from the node. Only the invoke v3 transactions are executed locally, the others fail so that they are
+/// estimated by the remote endpoints.
+pub struct BlockifierEstimator {
+    client: StarknetClient,
+    chain_id: ChainID,
+    tokens: ChainTokens,
+
+    state: Arc<RemoteState>,
+
+    /// Classes compiled to CASM indexed by class hash, compiling them is far more expensive than executing
+    compiled_classes: Arc<Mutex<HashMap<Felt, RunnableCompiledClass>>>,
+    compiled_class_cache_size: usize,
+}
+
+impl BlockifierEstimator {
+    pub fn new(client: StarknetClient, chain_id: ChainID, tokens: ChainTokens, configuration: &LocalEstimationConfiguration) -> Self {
+        Self {
+            state: Arc::new(RemoteState::new(client.clone())),
+            client,
+            chain_id,
+            tokens,
+            compiled_classes: Arc::new(Mutex::new(HashMap::new())),
+            compiled_class_cache_size: configuration.compiled_class_cache_size,
+        }
+    }
+
+    // Builds the context of the block following the latest one, on which the transactions are executed
+    async fn block_context(&self) -> Result<BlockContext, Error> {
+        let block = match self.client.get_block_with_tx_hashes(BlockId::Tag(BlockTag::Latest)).await? {
+            MaybePreConfirmedBlockWithTxHashes::Block(block) => block,
+            MaybePreConfirmedBlockWithTxHashes::PreConfirmedBlock(_) => return Err(Error::Internal("latest block is not accepted".to_string())),
+        };
+        self.state.pin(block.block_number);
+
+        let block_info = BlockInfo {
+            block_number: BlockNumber(block.block_number + 1),
+            block_timestamp: BlockTimestamp(block.timestamp),
+            sequencer_address: contract_address(block.sequencer_address)?,
+            gas_prices: GasPrices {
+                eth_gas_prices: GasPriceVector {
+                    l1_gas_price: gas_price(block.l1_gas_price.price_in_wei)?,
+                    l1_data_gas_price: gas_price(block.l1_data_gas_price.price_in_wei)?,
+                    l2_gas_price: gas_price(block.l2_gas_price.price_in_wei)?,
+                },
+                strk_gas_prices: GasPriceVector {
+                    l1_gas_price: gas_price(block.l1_gas_price.price_in_fri)?,
+                    l1_data_gas_price: gas_price(block.l1_data_gas_price.price_in_fri)?,
+                    l2_gas_price: gas_price(block.l2_gas_price.price_in_fri)?,
+                },
+            },
+            use_kzg_da: block.l1_da_mode == L1DataAvailabilityMode::Blob,
+        };
+
+        let chain_id = parse_cairo_short_string(&self.chain_id.as_felt()).map_err(|e| Error::Internal(e.to_string()))?;
+        let chain_info = ChainInfo {
+            chain_id: ChainId::from(chain_id),
+            fee_token_addresses: FeeTokenAddresses {
+                strk_fee_token_address: contract_address(self.tokens.strk)?,
+                eth_fee_token_address: contract_address(self.tokens.eth)?,
+            },
+            is_l3: false,
+        };
+
+        Ok(BlockContext::new(
+            block_info,
+            chain_info,
+            VersionedConstants::latest_constants().clone(),
+            BouncerConfig::max(),
+        ))
+    }
+}
+
+#[async_trait]
+impl LocalEstimator for BlockifierEstimator {
+    async fn estimate(&self, transactions: &[BroadcastedTransaction]) -> Result<Vec<FeeEstimate>, Error> {
+        let context = self.block_context().await?;
+        let transactions = transactions
+            .iter()
+            .map(|x| match x {
+                BroadcastedTransaction::Invoke(transaction) => invoke_transaction(transaction, &context.chain_info().chain_id),
+                _ => Err(Error::Internal("only invoke transactions are estimated locally".to_string())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // The blockifier reads the state synchronously, the reads are hence made from a blocking thread
+        let reader = BlockingStateReader {
+            runtime: Handle::current(),
+            state: self.state.clone(),
+            compiled_classes: self.compiled_classes.clone(),
+            compiled_class_cache_size: self.compiled_class_cache_size,
+        };
+
+        tokio::task::spawn_blocking(move || {
+            let mut state = CachedState::new(reader);
+            transactions
+                .into_iter()
+                .map(|transaction| {
+                    let info = transaction
+                        .execute(&mut state, &context)
+                        .map_err(|e| Error::Internal(e.to_string()))?;
+                    if let Some(error) = info.revert_error {
+                        return Err(Error::Internal(format!("transaction reverted {}", error)));
+                    }
+
+                    let prices = context.block_info().gas_prices.strk_gas_prices;
+                    Ok(FeeEstimate {
+                        l1_gas_consumed: info.receipt.gas.l1_gas.0,
+                        l1_gas_price: prices.l1_gas_price.get().0,
+                        l2_gas_consumed: info.receipt.gas.l2_gas.0,
+                        l2_gas_price: prices.l2_gas_price.get().0,
+                        l1_data_gas_consumed: info.receipt.gas.l1_data_gas.0,
+                        l1_data_gas_price: prices.l1_data_gas_price.get().0,
+                        overall_fee: info.receipt.fee.0,
+                        unit: PriceUnit::Fri,
+                    })
+                })
+                .collect()
+        })
+        .await
+        .map_err(|e| Error::Internal(e.to_string()))?
+    }
+}
+
+/// [`StateReader`] of the blockifier reading the state from the node
+struct BlockingStateReader {
+    runtime: Handle,
+    state: Arc<RemoteState>,
+
+    compiled_classes: Arc<Mutex<HashMap<Felt, RunnableCompiledClass>>>,
+    compiled_class_cache_size: usize,
+}
+
+impl BlockingStateReader {
+    fn compile(&self, class_hash: Felt) -> StateResult<RunnableCompiledClass> {
+        let class = match self.runtime.block_on(self.state.class(class_hash)) {
+            Ok(class) => class,
+            Err(Error::ClassNotFound) => return Err(StateError::UndeclaredClassHash(ClassHash(api_felt(class_hash)))),
+            Err(e) => return Err(StateError::StateReadError(e.to_string())),
+        };
+        let ContractClass::Sierra(class) = class.as_ref() else {
+            return Err(StateError::StateReadError("legacy classes are not executed locally".to_string()));
+        };
+
+        let sierra_program: Vec<StarkHash> = class.sierra_program.iter().copied().map(api_felt).collect();
+        let sierra_version = SierraVersion::extract_from_program(&sierra_program).map_err(|e| StateError::StateReadError(e.to_string()))?;
+
+        let sierra: SierraContractClass = serde_json::from_value(json!({
+            "sierra_program": class.sierra_program.iter().map(|x| x.to_hex_string()).collect::<Vec<_>>(),
+            "contract_class_version": class.contract_class_version,
+            "entry_points_by_type": {
+                "EXTERNAL": entry_points(&class.entry_points_by_type.external),
+                "L1_HANDLER": entry_points(&class.entry_points_by_type.l1_handler),
+                "CONSTRUCTOR": entry_points(&class.entry_points_by_type.constructor),
+            },
+        }))
+        .map_err(|e| StateError::StateReadError(e.to_string()))?;
+
+        let casm = CasmContractClass::from_contract_class(sierra, false, usize::MAX).map_err(|e| StateError::StateReadError(e.to_string()))?;
+        let compiled = CompiledClassV1::try_from((casm, sierra_version)).map_err(|e| StateError::StateReadError(e.to_string()))?;
+
+        Ok(RunnableCompiledClass::V1(compiled))
+    }
+}
+
+impl StateReader for BlockingStateReader {
+    fn get_storage_at(&self, contract_address: ContractAddress, key: StorageKey) -> StateResult<StarkHash> {
+        let value = self
+            .runtime
+            .block_on(self.state.storage(felt(*contract_address.0.key()), felt(*key.0.key())));
+        value.map(api_felt).map_err(|e| StateError::StateReadError(e.to_string()))
+    }
+
+    fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        let value = self.runtime.block_on(self.state.nonce(felt(*contract_address.0.key())));
+        value
+            .map(|x| Nonce(api_felt(x)))
+            .map_err(|e| StateError::StateReadError(e.to_string()))
+    }
+
+    fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        let value = self.runtime.block_on(self.state.class_hash(felt(*contract_address.0.key())));
+        value
+            .map(|x| ClassHash(api_felt(x)))
+            .map_err(|e| StateError::StateReadError(e.to_string()))
+    }
+
+    fn get_compiled_class(&self, class_hash: ClassHash) -> StateResult<RunnableCompiledClass> {
+        let class_hash = felt(class_hash.0);
+        if let Some(class) = self.compiled_classes.lock().unwrap().get(&class_hash) {
+            return Ok(class.clone());
+        }
+
+        let class = self.compile(class_hash)?;
+
+        let mut compiled_classes = self.compiled_classes.lock().unwrap();
+        if compiled_classes.len() >= self.compiled_class_cache_size {
+            compiled_classes.clear();
+        }
+        compiled_classes.insert(class_hash, class.clone());
+
+        Ok(class)
+    }
+
+    fn get_compiled_class_hash(&self, _class_hash: ClassHash) -> StateResult<CompiledClassHash> {
+        // Only read when declaring a class, which is never estimated locally
+        Err(StateError::StateReadError("compiled class hashes are not read locally".to_string()))
+    }
+}
+
+fn invoke_transaction(transaction: &BroadcastedInvokeTransactionV3, chain_id: &ChainId) -> Result<AccountTransaction, Error> {
+    let da_mode = |x: DataAvailabilityMode| match x {
+        DataAvailabilityMode::L1 => ApiDataAvailabilityMode::L1,
+        DataAvailabilityMode::L2 => ApiDataAvailabilityMode::L2,
+    };
+
+    let transaction = InvokeTransaction::V3(InvokeTransactionV3 {
+        resource_bounds: ValidResourceBounds::AllResources(AllResourceBounds {
+            l1_gas: resource_bounds(&transaction.resource_bounds.l1_gas),
+            l2_gas: resource_bounds(&transaction.resource_bounds.l2_gas),
+            l1_data_gas: resource_bounds(&transaction.resource_bounds.l1_data_gas),
+        }),
+        tip: Tip(transaction.tip),
+        signature: TransactionSignature(transaction.signature.iter().copied().map(api_felt).collect::<Vec<_>>().into()),
+        nonce: Nonce(api_felt(transaction.nonce)),
+        sender_address: contract_address(transaction.sender_address)?,
+        calldata: Calldata(Arc::new(transaction.calldata.iter().copied().map(api_felt).collect())),
+        nonce_data_availability_mode: da_mode(transaction.nonce_data_availability_mode),
+        fee_data_availability_mode: da_mode(transaction.fee_data_availability_mode),
+        paymaster_data: PaymasterData(transaction.paymaster_data.iter().copied().map(api_felt).collect()),
+        account_deployment_data: AccountDeploymentData(transaction.account_deployment_data.iter().copied().map(api_felt).collect()),
+    });
+    let transaction = ApiInvokeTransaction::create(transaction, chain_id).map_err(|e| Error::Internal(e.to_string()))?;
+
+    // Estimated like the remote endpoints do, i.e. as a query whose validation is skipped and whose fee is not
+    // charged
+    Ok(AccountTransaction {
+        tx: ApiAccountTransaction::Invoke(transaction),
+        execution_flags: ExecutionFlags {
+            only_query: true,
+            charge_fee: false,
+            validate: false,
+            strict_nonce_check: false,
+        },
+    })
+}
+
+fn resource_bounds(bounds: &ResourceBounds) -> ApiResourceBounds {
+    ApiResourceBounds {
+        max_amount: GasAmount(bounds.max_amount),
+        max_price_per_unit: GasPrice(bounds.max_price_per_unit),
+    }
+}
+
+fn entry_points(entry_points: &[SierraEntryPoint]) -> serde_json::Value {
+    entry_points
+        .iter()
+        .map(|x| json!({ "selector": x.selector.to_hex_string(), "function_idx": x.function_idx }))
+        .collect()
+}
+
+fn gas_price(price: Felt) -> Result<NonzeroGasPrice, Error> {
+    let price = u128::try_from(price).map_err(|e| Error::Internal(e.to_string()))?;
+    Ok(NonzeroGasPrice::new(GasPrice(price)).unwrap_or(NonzeroGasPrice::MIN))
+}
+
+fn contract_address(value: Felt) -> Result<ContractAddress, Error> {
+    ContractAddress::try_from(api_felt(value)).map_err(|e| Error::Internal(e.to_string()))
+}
+
+// The blockifier may depend on another version of the field element than starknet-rs
+fn api_felt(value: Felt) -> StarkHash {
+    StarkHash::from_bytes_be(&value.to_bytes_be())
+}
+
+fn felt(value: StarkHash) -> Felt {
+    Felt::from_bytes_be(&value.to_bytes_be())
+}
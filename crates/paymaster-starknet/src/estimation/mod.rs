@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+use starknet::core::types::{BroadcastedTransaction, FeeEstimate};
+
+use crate::Error;
+
+mod state;
+pub use state::RemoteState;
+
+#[cfg(feature = "local-estimation")]
+mod executor;
+#[cfg(feature = "local-estimation")]
+pub use executor::BlockifierEstimator;
+
+/// Backend estimating the transactions in process rather than on the remote endpoints. The remote endpoints
+/// remain authoritative: whenever the backend fails, the transactions are estimated remotely.
+#[async_trait]
+pub trait LocalEstimator: Send + Sync {
+    /// Estimates the `transactions`, executed one after the other, and returns their [`FeeEstimate`]
+    async fn estimate(&self, transactions: &[BroadcastedTransaction]) -> Result<Vec<FeeEstimate>, Error>;
+}
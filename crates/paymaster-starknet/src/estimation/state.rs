@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use starknet::core::types::{BlockId, ContractClass, Felt, StarknetError};
+use starknet::providers::{Provider, ProviderError};
+
+use crate::client::StarknetClient;
+use crate::Error;
+
+#[derive(Default)]
+struct Snapshot {
+    block_number: u64,
+
+    storage: HashMap<(Felt, Felt), Felt>,
+    nonces: HashMap<Felt, Felt>,
+    class_hashes: HashMap<Felt, Felt>,
+}
+
+/// State of the chain read from the node at a pinned block. The values read are kept until the state is pinned on
+/// another block, so that the estimations made on the same block only read each value once. Classes never change
+/// and are kept across blocks.
+pub struct RemoteState {
+    client: StarknetClient,
+
+    snapshot: Mutex<Snapshot>,
+    classes: Mutex<HashMap<Felt, Arc<ContractClass>>>,
+}
+
+impl RemoteState {
+    pub fn new(client: StarknetClient) -> Self {
+        Self {
+            client,
+            snapshot: Mutex::new(Snapshot::default()),
+            classes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Pins the reads on `block_number`, dropping the values read at another block
+    pub fn pin(&self, block_number: u64) {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        if snapshot.block_number != block_number {
+            *snapshot = Snapshot {
+                block_number,
+                ..Default::default()
+            };
+        }
+    }
+
+    /// Returns the block on which the reads are pinned
+    pub fn block_number(&self) -> u64 {
+        self.snapshot.lock().unwrap().block_number
+    }
+
+    /// Returns the value of the storage `key` of the contract at `address`, zero if the contract is not deployed
+    pub async fn storage(&self, address: Felt, key: Felt) -> Result<Felt, Error> {
+        if let Some(value) = self.snapshot.lock().unwrap().storage.get(&(address, key)) {
+            return Ok(*value);
+        }
+
+        let block = self.block_number();
+        let value = or_zero(self.client.get_storage_at(address, key, BlockId::Number(block)).await)?;
+        self.record(block, |x| x.storage.insert((address, key), value));
+
+        Ok(value)
+    }
+
+    /// Returns the nonce of the contract at `address`, zero if the contract is not deployed
+    pub async fn nonce(&self, address: Felt) -> Result<Felt, Error> {
+        if let Some(value) = self.snapshot.lock().unwrap().nonces.get(&address) {
+            return Ok(*value);
+        }
+
+        let block = self.block_number();
+        let value = or_zero(self.client.get_nonce(BlockId::Number(block), address).await)?;
+        self.record(block, |x| x.nonces.insert(address, value));
+
+        Ok(value)
+    }
+
+    /// Returns the class hash of the contract at `address`, zero if the contract is not deployed
+    pub async fn class_hash(&self, address: Felt) -> Result<Felt, Error> {
+        if let Some(value) = self.snapshot.lock().unwrap().class_hashes.get(&address) {
+            return Ok(*value);
+        }
+
+        let block = self.block_number();
+        let value = or_zero(self.client.get_class_hash_at(BlockId::Number(block), address).await)?;
+        self.record(block, |x| x.class_hashes.insert(address, value));
+
+        Ok(value)
+    }
+
+    /// Returns the class with `class_hash`
+    pub async fn class(&self, class_hash: Felt) -> Result<Arc<ContractClass>, Error> {
+        if let Some(class) = self.classes.lock().unwrap().get(&class_hash) {
+            return Ok(class.clone());
+        }
+
+        let block = self.block_number();
+        let class = Arc::new(self.client.get_class(BlockId::Number(block), class_hash).await?);
+        self.classes.lock().unwrap().insert(class_hash, class.clone());
+
+        Ok(class)
+    }
+
+    // Records a value read at `block`, unless the state was pinned on another block in the meantime
+    fn record<T>(&self, block: u64, f: impl FnOnce(&mut Snapshot) -> T) {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        if snapshot.block_number == block {
+            f(&mut snapshot);
+        }
+    }
+}
+
+// Contracts which are not deployed read as zero, as they do in the sequencer
+fn or_zero(result: Result<Felt, ProviderError>) -> Result<Felt, Error> {
+    match result {
+        Ok(value) => Ok(value),
+        Err(ProviderError::StarknetError(StarknetError::ContractNotFound)) => Ok(Felt::ZERO),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use crate::client::StarknetClient;
+    use crate::estimation::RemoteState;
+    use crate::testing::provider::MockProvider;
+
+    #[tokio::test]
+    async fn values_are_read_once_per_block() {
+        let provider = MockProvider::new();
+        provider.set_nonce(Felt::ONE, Felt::from(5));
+        provider.set_storage(Felt::ONE, Felt::TWO, Felt::from(10));
+
+        let state = RemoteState::new(StarknetClient::mock(provider.clone()));
+        state.pin(1);
+        assert_eq!(state.nonce(Felt::ONE).await.unwrap(), Felt::from(5));
+        assert_eq!(state.storage(Felt::ONE, Felt::TWO).await.unwrap(), Felt::from(10));
+
+        // Values changed by the next block are only seen once the state is pinned on it
+        provider.set_nonce(Felt::ONE, Felt::from(6));
+        provider.set_storage(Felt::ONE, Felt::TWO, Felt::from(11));
+        assert_eq!(state.nonce(Felt::ONE).await.unwrap(), Felt::from(5));
+        assert_eq!(state.storage(Felt::ONE, Felt::TWO).await.unwrap(), Felt::from(10));
+
+        state.pin(2);
+        assert_eq!(state.nonce(Felt::ONE).await.unwrap(), Felt::from(6));
+        assert_eq!(state.storage(Felt::ONE, Felt::TWO).await.unwrap(), Felt::from(11));
+    }
+
+    #[tokio::test]
+    async fn contracts_not_deployed_read_as_zero() {
+        let state = RemoteState::new(StarknetClient::mock(MockProvider::new()));
+        state.pin(1);
+
+        assert_eq!(state.nonce(Felt::ONE).await.unwrap(), Felt::ZERO);
+        assert_eq!(state.class_hash(Felt::ONE).await.unwrap(), Felt::ZERO);
+    }
+}
@@ -1,51 +1,53 @@
+use std::collections::HashMap;
 use std::fmt::{Debug, Display};
 
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
-use starknet::accounts::{AccountError, ArgentAccountFactory, ExecutionEncoding, SingleOwnerAccount};
+use starknet::accounts::AccountError;
 use starknet::core::serde::unsigned_field_element::UfeHex;
 use starknet::core::types::typed_data::TypedDataError;
-use starknet::core::types::SimulationFlagForEstimateFee::SkipValidate;
-use starknet::core::types::{
-    BlockId, BlockTag, BroadcastedTransaction, ContractExecutionError, FeeEstimate, Felt, FunctionCall, MaybePreConfirmedBlockWithTxs, StarknetError, Transaction,
-    TransactionReceiptWithBlockInfo, TransactionStatus,
-};
-use starknet::macros::selector;
-use starknet::providers::{Provider, ProviderError};
-use starknet::signers::{LocalWallet, SigningKey};
+use starknet::core::types::{ContractExecutionError, Felt, StarknetError};
+use starknet::providers::ProviderError;
 use thiserror::Error;
-use tracing::instrument;
 
 pub mod constants;
 pub mod contract;
 pub mod math;
+#[cfg(feature = "native")]
+pub mod probe;
 pub mod transaction;
 pub mod types;
 pub mod values;
 
+mod block;
+pub use block::BlockHeader;
+
 mod gas;
 pub use gas::BlockGasPrice;
 pub use tracing;
 
 mod network;
+use crate::constants::ChainTokens;
 pub use network::ChainID;
-use paymaster_common::service::fallback;
-use paymaster_common::{measure_duration, metric};
-
-use crate::client::StarknetClient;
-use crate::constants::ClassHash;
-use crate::contract::ContractClass;
+use paymaster_common::validation::{Validate, ValidationReport};
 
 #[cfg(feature = "testing")]
 pub mod testing;
 
+#[cfg(feature = "native")]
 mod client;
 
+#[cfg(feature = "native")]
+pub mod estimation;
+
+#[cfg(feature = "native")]
+mod chain;
+#[cfg(feature = "native")]
+pub use chain::{Client, StarknetAccount};
+
 pub const DEFAULT_SEPOLIA_RPC_ENDPOINT: &str = "https://rpc.starknet-testnet.lava.build/rpc/v0_9";
 pub const DEFAULT_MAINNET_RPC_ENDPOINT: &str = "https://rpc.starknet.lava.build/rpc/v0_9";
 
-pub type StarknetAccount = SingleOwnerAccount<StarknetClient, LocalWallet>;
-
 #[macro_export]
 macro_rules! log_if_error {
     ($e: expr) => {
@@ -73,6 +75,12 @@ pub struct StarknetAccountConfiguration {
     pub private_key: Felt,
 }
 
+impl Validate for StarknetAccountConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.address != Felt::ZERO, "address", "must not be zero");
+    }
+}
+
 pub type Signature = Vec<Felt>;
 pub type ContractAddress = Felt;
 
@@ -93,6 +101,12 @@ pub enum Error {
     #[error("contract not found")]
     ContractNotFound,
 
+    #[error("class not found")]
+    ClassNotFound,
+
+    #[error("transaction not found")]
+    TransactionNotFound,
+
     #[error("contract error {0}")]
     Contract(String),
 
@@ -108,6 +122,9 @@ pub enum Error {
     #[error("starknet error {0}")]
     Starknet(String),
 
+    #[error("invalid endpoint {0}")]
+    InvalidEndpoint(String),
+
     #[error("Execution error {0:?}")]
     Execution(ContractExecutionError),
 
@@ -115,6 +132,23 @@ pub enum Error {
     ValidationFailure(String),
 }
 
+/// Messages reported by the network when the address of a deployment is already taken
+const DEPLOYMENT_CONFLICT_MESSAGES: [&str; 2] = ["unavailable for deployment", "already deployed"];
+
+impl Error {
+    /// Returns true if the error reports that a contract is already deployed at the address of a deployment
+    pub fn is_deployment_conflict(&self) -> bool {
+        let message = match self {
+            Error::Execution(e) => format!("{:?}", e),
+            Error::Contract(e) | Error::Starknet(e) | Error::ValidationFailure(e) => e.clone(),
+            _ => return false,
+        };
+
+        let message = message.to_lowercase();
+        DEPLOYMENT_CONFLICT_MESSAGES.iter().any(|x| message.contains(x))
+    }
+}
+
 impl From<ProviderError> for Error {
     fn from(value: ProviderError) -> Self {
         match value {
@@ -122,6 +156,8 @@ impl From<ProviderError> for Error {
             ProviderError::StarknetError(StarknetError::TransactionExecutionError(e)) => Error::Execution(e.execution_error),
             ProviderError::StarknetError(StarknetError::ContractError(e)) => Error::Execution(e.revert_error),
             ProviderError::StarknetError(StarknetError::ContractNotFound) => Error::ContractNotFound,
+            ProviderError::StarknetError(StarknetError::ClassHashNotFound) => Error::ClassNotFound,
+            ProviderError::StarknetError(StarknetError::TransactionHashNotFound) => Error::TransactionNotFound,
             ProviderError::StarknetError(StarknetError::ValidationFailure(error)) => Error::ValidationFailure(format!("ValidationFailure: {:?}", error)),
             ProviderError::Other(e) => Error::Internal(e.to_string()),
             ProviderError::RateLimited => Error::Internal("RateLimited".to_string()),
@@ -131,15 +167,6 @@ impl From<ProviderError> for Error {
     }
 }
 
-impl From<fallback::Error<ProviderError>> for Error {
-    fn from(value: fallback::Error<ProviderError>) -> Self {
-        match value {
-            fallback::Error::Rejected => Self::Internal("could not connect to endpoint".to_string()),
-            fallback::Error::Inner(e) => e.into(),
-        }
-    }
-}
-
 impl<T: Display + Debug> From<AccountError<T>> for Error {
     fn from(value: AccountError<T>) -> Self {
         match value {
@@ -157,177 +184,136 @@ pub struct Configuration {
 
     #[serde(default)]
     pub fallbacks: Vec<String>,
-}
-
-#[derive(Clone)]
-pub struct Client {
-    chain_id: ChainID,
-
-    inner: StarknetClient,
-}
-
-impl Client {
-    pub fn new(configuration: &Configuration) -> Self {
-        let mut client = StarknetClient::new(&configuration.endpoint, configuration.timeout);
-        for fallback in &configuration.fallbacks {
-            client = client.with_fallback(fallback, configuration.timeout);
-        }
-
-        Self {
-            chain_id: configuration.chain_id,
-            inner: client,
-        }
-    }
-
-    /// Returns the chain_id on which this client is bound
-    pub fn chain_id(&self) -> &ChainID {
-        &self.chain_id
-    }
-
-    /// Initialize an account using the given account configuration
-    pub fn initialize_account(&self, account: &StarknetAccountConfiguration) -> StarknetAccount {
-        let signing_key = LocalWallet::from_signing_key(SigningKey::from_secret_scalar(account.private_key));
-
-        let mut account = StarknetAccount::new(self.inner.clone(), signing_key, account.address, self.chain_id.as_felt(), ExecutionEncoding::New);
-        account.set_block_id(BlockId::Tag(BlockTag::PreConfirmed));
-        account
-    }
-
-    /// Initialize an argent account using the given account configuration.
-    pub async fn initialize_argent_account(&self, private_key: Felt) -> ArgentAccountFactory<LocalWallet, StarknetClient> {
-        let class_hash = ClassHash::ARGENT_ACCOUNT;
-        let signer = LocalWallet::from(SigningKey::from_secret_scalar(private_key));
 
-        ArgentAccountFactory::new(class_hash, self.chain_id.as_felt(), None, signer, self.inner.clone())
-            .await
-            .unwrap()
-    }
-
-    /// Fetch the gas price at the latest block. Price is given in wei
-    #[instrument(name = "fetch_block_gas_price", skip(self))]
-    pub async fn fetch_block_gas_price(&self) -> Result<BlockGasPrice, Error> {
-        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_block_with_txs(BlockId::Tag(BlockTag::Latest)).await));
-        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "fetch_block_gas_price");
-        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "fetch_block_gas_price");
-
-        let prices = match result? {
-            MaybePreConfirmedBlockWithTxs::Block(block) => (block.l1_gas_price.price_in_fri, block.l1_data_gas_price.price_in_fri, block.l2_gas_price.price_in_fri),
-            MaybePreConfirmedBlockWithTxs::PreConfirmedBlock(block) => {
-                (block.l1_gas_price.price_in_fri, block.l1_data_gas_price.price_in_fri, block.l2_gas_price.price_in_fri)
-            },
-        };
-
-        Ok(BlockGasPrice {
-            l1_gas_price: prices.0,
-            l1_data_gas_price: prices.1,
-            l2_gas_price: prices.2,
-        })
-    }
-
-    /// Fetch the median tip at the latest block
-    #[instrument(name = "fetch_block_median_tip", skip(self))]
-    pub async fn fetch_block_median_tip(&self) -> Result<u64, Error> {
-        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_block_with_txs(BlockId::Tag(BlockTag::Latest)).await));
-        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "fetch_block_median_tip");
-        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "fetch_block_median_tip");
-        Ok(result?.median_tip())
-    }
-
-    /// Call `balance_of(recipient)` on the given `token` address
-    #[instrument(name = "fetch_balance", skip(self))]
-    pub async fn fetch_balance(&self, token: Felt, recipient: Felt) -> Result<Felt, Error> {
-        let call = FunctionCall {
-            contract_address: token,
-            entry_point_selector: selector!("balance_of"),
-            calldata: vec![recipient],
-        };
+    /// Estimate the transactions in process before falling back on the endpoints above, which cuts the
+    /// estimation latency and spares the quota of the remote providers.
+    #[serde(default)]
+    pub local_estimation: Option<LocalEstimationConfiguration>,
 
-        let (result, duration) = measure_duration!(log_if_error!(self.inner.call(call, BlockId::Tag(BlockTag::PreConfirmed)).await));
+    /// Headers, credentials and proxy of the endpoints above, indexed by their URL. Required by the providers only
+    /// reachable through an authenticated gateway.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub endpoint_options: HashMap<String, EndpointOptions>,
 
-        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "token_balance_of");
-        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "token_balance_of");
+    /// Addresses of the ETH, STRK and USDC tokens of the chain, required by the forks and appchains which deployed
+    /// their own. The tokens of Starknet are used when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<ChainTokens>,
+}
 
-        result?.first().cloned().ok_or(Error::ContractNotFound)
+impl Configuration {
+    /// Returns the options of the given endpoint, if any
+    pub fn options_of(&self, endpoint: &str) -> Option<&EndpointOptions> {
+        self.endpoint_options.get(endpoint)
     }
 
-    /// Fetch the nonce of the given `user`
-    #[instrument(name = "fetch_nonce", skip(self))]
-    pub async fn fetch_nonce(&self, user: ContractAddress) -> Result<Felt, Error> {
-        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_nonce(BlockId::Tag(BlockTag::PreConfirmed), user).await));
-
-        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "get_nonce");
-        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "get_nonce");
-
-        Ok(result?)
+    /// Returns the tokens of the chain, the configured ones if any
+    pub fn tokens(&self) -> ChainTokens {
+        ChainTokens::resolve(&self.chain_id, self.tokens.as_ref())
     }
+}
 
-    /// Execute the given `call`
-    #[instrument(name = "call", skip(self))]
-    pub async fn call(&self, call: &FunctionCall) -> Result<Vec<Felt>, Error> {
-        let block = BlockId::Tag(BlockTag::PreConfirmed);
-        let (result, duration) = measure_duration!(log_if_error!(self.inner.call(call, block).await));
-
-        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "call");
-        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "call");
-
-        Ok(result?)
+impl Validate for Configuration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure_url("endpoint", &self.endpoint);
+        report.ensure(self.timeout > 0, "timeout", "must be greater than 0");
+        for (i, fallback) in self.fallbacks.iter().enumerate() {
+            report.ensure_url(&format!("fallbacks[{}]", i), fallback);
+        }
+        if let Some(local_estimation) = &self.local_estimation {
+            report.ensure(cfg!(feature = "local-estimation"), "local_estimation", "requires the local-estimation feature");
+            report.field("local_estimation", local_estimation);
+        }
+        for (endpoint, options) in &self.endpoint_options {
+            let is_configured = *endpoint == self.endpoint || self.fallbacks.contains(endpoint);
+            report.ensure(is_configured, &format!("endpoint_options[{}]", endpoint), "does not match any endpoint");
+            report.field(&format!("endpoint_options[{}]", endpoint), options);
+        }
+        if let Some(tokens) = &self.tokens {
+            report.field("tokens", tokens);
+        }
     }
+}
 
-    /// Estimates the `transactions` and returns their [`FeeEstimate`]
-    #[instrument(name = "estimate_transactions", skip(self))]
-    pub async fn estimate_transactions(&self, transactions: &[BroadcastedTransaction]) -> Result<Vec<FeeEstimate>, Error> {
-        let block = BlockId::Tag(BlockTag::PreConfirmed);
+/// Options applied to the requests sent to an endpoint
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EndpointOptions {
+    /// HTTP headers sent with every request, typically the api key of the provider
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
 
-        // Estimate fees
-        let (result, duration) = measure_duration!(log_if_error!(self.inner.estimate_fee(transactions, vec![SkipValidate], block).await));
+    #[serde(default)]
+    pub basic_auth: Option<BasicAuthConfiguration>,
 
-        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "estimate_transactions");
-        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "estimate_transactions");
+    /// Proxy through which the endpoint is reached (`http://`, `https://` or `socks5://`)
+    #[serde(default)]
+    pub proxy: Option<String>,
+}
 
-        Ok(result?)
+impl Validate for EndpointOptions {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        for name in self.headers.keys() {
+            let is_valid = !name.is_empty() && name.bytes().all(|x| x.is_ascii_graphic() && x != b':');
+            report.ensure(is_valid, &format!("headers[{}]", name), "is not a valid header name");
+        }
+        if let Some(proxy) = &self.proxy {
+            report.ensure_url("proxy", proxy);
+        }
     }
+}
 
-    /// Returns the receipt of the transaction with `hash`
-    #[instrument(name = "fetch_class", skip(self))]
-    pub async fn fetch_class(&self, class_hash: Felt) -> Result<ContractClass, Error> {
-        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_class(BlockId::Tag(BlockTag::Latest), class_hash).await));
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BasicAuthConfiguration {
+    pub username: String,
+    pub password: String,
+}
 
-        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "get_class");
-        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "get_class");
+/// Estimation of the transactions in process, on the state read from the endpoints
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LocalEstimationConfiguration {
+    /// Number of classes kept compiled in memory
+    pub compiled_class_cache_size: usize,
+}
 
-        Ok(ContractClass::from_class(result?))
+impl Validate for LocalEstimationConfiguration {
+    fn validate_into(&self, report: &mut ValidationReport) {
+        report.ensure(self.compiled_class_cache_size > 0, "compiled_class_cache_size", "must be greater than 0");
     }
+}
 
-    /// Returns the receipt of the transaction with `hash`
-    #[instrument(name = "get_transaction_receipt", skip(self))]
-    pub async fn get_transaction_receipt(&self, hash: Felt) -> Result<TransactionReceiptWithBlockInfo, Error> {
-        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_transaction_receipt(hash).await));
-
-        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "get_transaction_receipt");
-        metric!(on error result => counter [starknet_rpc_error ] = 1, method = "get_transaction_receipt");
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
 
-        Ok(result?)
-    }
+    use paymaster_common::validation::Validate;
+    use starknet::core::types::ContractExecutionError;
 
-    /// Returns the status of the transaction with `hash`
-    #[instrument(name = "get_transaction_status", skip(self))]
-    pub async fn get_transaction_status(&self, hash: Felt) -> Result<TransactionStatus, Error> {
-        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_transaction_status(hash).await));
+    use crate::{ChainID, Configuration, Error, LocalEstimationConfiguration};
 
-        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "get_transaction_status");
-        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "get_transaction_status");
+    #[test]
+    fn deployment_conflicts_are_detected() {
+        let conflict = Error::Execution(ContractExecutionError::Message(
+            "Requested contract address 0x123 is unavailable for deployment.".to_string(),
+        ));
+        assert!(conflict.is_deployment_conflict());
+        assert!(Error::Starknet("contract already deployed".to_string()).is_deployment_conflict());
 
-        Ok(result?)
+        assert!(!Error::Execution(ContractExecutionError::Message("insufficient balance".to_string())).is_deployment_conflict());
+        assert!(!Error::ContractNotFound.is_deployment_conflict());
     }
 
-    /// Returns the transaction with `hash`
-    #[instrument(name = "get_transaction", skip(self))]
-    pub async fn get_transaction(&self, hash: Felt) -> Result<Transaction, Error> {
-        let (result, duration) = measure_duration!(log_if_error!(self.inner.get_transaction_by_hash(hash).await));
-
-        metric!(histogram[starknet_rpc] = duration.as_millis(), method = "get_transaction");
-        metric!(on error result => counter [ starknet_rpc_error ] = 1, method = "get_transaction");
+    #[test]
+    fn local_estimation_requires_the_feature() {
+        let configuration = Configuration {
+            chain_id: ChainID::Sepolia,
+            endpoint: "http://localhost:5050".to_string(),
+            timeout: 10,
+            fallbacks: vec![],
+            local_estimation: Some(LocalEstimationConfiguration { compiled_class_cache_size: 100 }),
+            endpoint_options: HashMap::new(),
+            tokens: None,
+        };
 
-        Ok(result?)
+        assert_eq!(configuration.validate_all().is_ok(), cfg!(feature = "local-estimation"));
     }
 }
@@ -0,0 +1,250 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{Felt, StarknetError};
+use starknet::providers::{Provider, ProviderError};
+use tracing::{info, warn};
+
+use crate::client::StarknetClient;
+use crate::{ChainID, Configuration, EndpointOptions};
+
+/// Version of the Starknet JSON-RPC specification the paymaster is built against
+pub const REQUIRED_SPEC_VERSION: &str = "0.9";
+
+/// Features the endpoints are probed for at startup
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ProbeConfiguration {
+    /// Skip the probing of the endpoints at startup
+    #[serde(default)]
+    pub disabled: bool,
+
+    /// Require the endpoints to support the trace API
+    #[serde(default)]
+    pub require_trace: bool,
+}
+
+/// Capabilities of an endpoint as observed when probing it. Fields are not set when the endpoint did not answer.
+#[derive(Clone, Debug)]
+pub struct EndpointCapabilities {
+    pub endpoint: String,
+    pub chain_id: Option<Felt>,
+    pub spec_version: Option<String>,
+    pub supports_trace: bool,
+    pub latency: Option<Duration>,
+}
+
+impl EndpointCapabilities {
+    /// Probe the given endpoint for its chain id, spec version, trace support and latency
+    pub async fn probe(endpoint: &str, timeout: u64, options: Option<&EndpointOptions>) -> Self {
+        let Ok(client) = StarknetClient::new(endpoint, timeout, options) else {
+            return Self::unreachable(endpoint);
+        };
+
+        let started_at = Instant::now();
+        let chain_id = client.chain_id().await.ok();
+        let latency = chain_id.map(|_| started_at.elapsed());
+
+        let spec_version = client.spec_version().await.ok();
+
+        // The trace API is supported when looking up an unknown transaction fails with the proper error
+        let supports_trace = match client.trace_transaction(Felt::ZERO).await {
+            Ok(_) | Err(ProviderError::StarknetError(StarknetError::TransactionHashNotFound)) => true,
+            Err(_) => false,
+        };
+
+        Self {
+            endpoint: endpoint.to_string(),
+            chain_id,
+            spec_version,
+            supports_trace,
+            latency,
+        }
+    }
+
+    /// Capabilities of an endpoint which could not be reached
+    fn unreachable(endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            chain_id: None,
+            spec_version: None,
+            supports_trace: false,
+            latency: None,
+        }
+    }
+
+    pub fn is_reachable(&self) -> bool {
+        self.latency.is_some()
+    }
+
+    /// Returns the reasons why the endpoint cannot serve the given chain. An unreachable endpoint is not considered
+    /// misconfigured since it may only be temporarily down.
+    pub fn misconfigurations(&self, chain_id: &ChainID, configuration: &ProbeConfiguration) -> Vec<String> {
+        let mut result = vec![];
+
+        if let Some(actual) = self.chain_id {
+            if actual != chain_id.as_felt() {
+                result.push(format!("chain id {} does not match {}", actual.to_hex_string(), chain_id.as_felt().to_hex_string()));
+            }
+        }
+
+        if let Some(version) = &self.spec_version {
+            if !is_compatible_spec_version(version) {
+                result.push(format!("spec version {} is not compatible with {}", version, REQUIRED_SPEC_VERSION));
+            }
+        }
+
+        if configuration.require_trace && self.is_reachable() && !self.supports_trace {
+            result.push("trace api is not supported".to_string());
+        }
+
+        result
+    }
+}
+
+fn is_compatible_spec_version(version: &str) -> bool {
+    version == REQUIRED_SPEC_VERSION || version.starts_with(&format!("{}.", REQUIRED_SPEC_VERSION))
+}
+
+/// Capabilities of the primary and fallback endpoints of a chain
+#[derive(Clone, Debug)]
+pub struct CapabilityMatrix {
+    pub chain_id: ChainID,
+    pub primary: EndpointCapabilities,
+    pub fallbacks: Vec<EndpointCapabilities>,
+}
+
+impl CapabilityMatrix {
+    pub async fn probe(configuration: &Configuration) -> Self {
+        let mut fallbacks = vec![];
+        for fallback in &configuration.fallbacks {
+            fallbacks.push(EndpointCapabilities::probe(fallback, configuration.timeout, configuration.options_of(fallback)).await);
+        }
+
+        Self {
+            chain_id: configuration.chain_id,
+            primary: EndpointCapabilities::probe(&configuration.endpoint, configuration.timeout, configuration.options_of(&configuration.endpoint)).await,
+            fallbacks,
+        }
+    }
+
+    /// Log one line per endpoint with its capabilities
+    pub fn log(&self) {
+        let endpoints = [("primary", &self.primary)]
+            .into_iter()
+            .chain(self.fallbacks.iter().map(|x| ("fallback", x)));
+
+        for (role, capabilities) in endpoints {
+            if !capabilities.is_reachable() {
+                warn!(chain_id = %self.chain_id.as_identifier(), role, endpoint = %capabilities.endpoint, "endpoint is unreachable");
+                continue;
+            }
+
+            info!(
+                chain_id = %self.chain_id.as_identifier(),
+                role,
+                endpoint = %capabilities.endpoint,
+                remote_chain_id = %capabilities.chain_id.map(|x| x.to_hex_string()).unwrap_or_default(),
+                spec_version = %capabilities.spec_version.clone().unwrap_or_default(),
+                trace = capabilities.supports_trace,
+                latency_ms = capabilities.latency.map(|x| x.as_millis()).unwrap_or_default(),
+                "endpoint capabilities"
+            );
+        }
+    }
+
+    /// Returns the configuration restricted to the endpoints able to serve the chain. The misconfigured fallbacks are
+    /// removed while a misconfigured primary endpoint is an error.
+    pub fn resolve(&self, configuration: &Configuration, probe: &ProbeConfiguration) -> Result<Configuration, String> {
+        let issues = self.primary.misconfigurations(&self.chain_id, probe);
+        if !issues.is_empty() {
+            return Err(format!("endpoint {} is misconfigured: {}", self.primary.endpoint, issues.join(", ")));
+        }
+
+        let mut fallbacks = vec![];
+        for fallback in &self.fallbacks {
+            let issues = fallback.misconfigurations(&self.chain_id, probe);
+            if issues.is_empty() {
+                fallbacks.push(fallback.endpoint.clone());
+            } else {
+                warn!(endpoint = %fallback.endpoint, "fallback is misconfigured and will not be used: {}", issues.join(", "));
+            }
+        }
+
+        Ok(Configuration {
+            fallbacks,
+            ..configuration.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use starknet::core::types::Felt;
+
+    use crate::probe::{CapabilityMatrix, EndpointCapabilities, ProbeConfiguration};
+    use crate::{ChainID, Client, Configuration};
+
+    fn capabilities(endpoint: &str, chain_id: Felt, spec_version: &str, supports_trace: bool) -> EndpointCapabilities {
+        EndpointCapabilities {
+            endpoint: endpoint.to_string(),
+            chain_id: Some(chain_id),
+            spec_version: Some(spec_version.to_string()),
+            supports_trace,
+            latency: Some(Duration::from_millis(10)),
+        }
+    }
+
+    #[test]
+    fn misconfigured_fallbacks_are_removed() {
+        let configuration = Configuration {
+            chain_id: ChainID::Sepolia,
+            endpoint: "http://primary".to_string(),
+            timeout: 10,
+            fallbacks: vec!["http://mainnet".to_string(), "http://old".to_string(), "http://valid".to_string()],
+            local_estimation: None,
+            endpoint_options: Default::default(),
+            tokens: None,
+        };
+
+        let matrix = CapabilityMatrix {
+            chain_id: ChainID::Sepolia,
+            primary: capabilities("http://primary", ChainID::Sepolia.as_felt(), "0.9.0", false),
+            fallbacks: vec![
+                capabilities("http://mainnet", ChainID::Mainnet.as_felt(), "0.9.0", true),
+                capabilities("http://old", ChainID::Sepolia.as_felt(), "0.8.1", true),
+                capabilities("http://valid", ChainID::Sepolia.as_felt(), "0.9", true),
+            ],
+        };
+
+        let result = matrix.resolve(&configuration, &ProbeConfiguration::default()).unwrap();
+        assert_eq!(result.fallbacks, vec!["http://valid".to_string()]);
+
+        let result = matrix.resolve(
+            &configuration,
+            &ProbeConfiguration {
+                require_trace: true,
+                ..Default::default()
+            },
+        );
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn invalid_endpoints_are_rejected_without_panicking() {
+        let configuration = Configuration {
+            chain_id: ChainID::Sepolia,
+            endpoint: "not an endpoint".to_string(),
+            timeout: 10,
+            fallbacks: vec![],
+            local_estimation: None,
+            endpoint_options: Default::default(),
+            tokens: None,
+        };
+        assert!(Client::new(&configuration).is_err());
+
+        let capabilities = EndpointCapabilities::probe("not an endpoint", 10, None).await;
+        assert!(!capabilities.is_reachable());
+    }
+}
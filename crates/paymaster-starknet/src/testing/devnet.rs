@@ -0,0 +1,205 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use starknet::core::types::Felt;
+
+use crate::ChainID;
+
+/// How the devnet produces its blocks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlockGeneration {
+    /// A block is mined for every transaction
+    #[default]
+    Transaction,
+
+    /// Transactions stay in the pending block until [`Devnet::create_block`] is called
+    Demand,
+
+    /// A block is mined every given number of seconds
+    Interval(u64),
+}
+
+impl BlockGeneration {
+    fn args(&self) -> Vec<String> {
+        let mode = match self {
+            Self::Transaction => "transaction".to_string(),
+            Self::Demand => "demand".to_string(),
+            Self::Interval(seconds) => seconds.to_string(),
+        };
+
+        vec!["--block-generation-on".to_string(), mode]
+    }
+}
+
+/// State of another network the devnet is forked from
+#[derive(Debug, Clone)]
+pub struct Fork {
+    /// RPC endpoint of the network to fork
+    pub endpoint: String,
+
+    /// Block to fork from, the latest block when not set
+    pub block_number: Option<u64>,
+
+    pub chain_id: ChainID,
+}
+
+impl Fork {
+    pub fn mainnet(endpoint: &str, block_number: Option<u64>) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            block_number,
+            chain_id: ChainID::Mainnet,
+        }
+    }
+}
+
+/// Options used to start the devnet of a test environment
+#[derive(Debug, Clone, Default)]
+pub struct DevnetOptions {
+    pub block_generation: BlockGeneration,
+
+    /// When set, the devnet starts from the state of the given network instead of the paymaster CI state
+    pub fork: Option<Fork>,
+}
+
+impl DevnetOptions {
+    pub fn is_default(&self) -> bool {
+        self.block_generation == BlockGeneration::Transaction && self.fork.is_none()
+    }
+
+    /// Command line arguments given to the devnet
+    pub fn args(&self) -> Vec<String> {
+        let mut args = self.block_generation.args();
+        if let Some(fork) = &self.fork {
+            args.extend(["--fork-network".to_string(), fork.endpoint.clone()]);
+            if let Some(block_number) = fork.block_number {
+                args.extend(["--fork-block".to_string(), block_number.to_string()]);
+            }
+        }
+
+        args
+    }
+}
+
+/// Gas prices set on the devnet. Prices which are not set are left unchanged.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GasPrices {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_price_wei: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_price_fri: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_gas_price_wei: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_gas_price_fri: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub l2_gas_price_wei: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub l2_gas_price_fri: Option<u128>,
+}
+
+/// Client of the devnet specific methods used to control the chain during the tests
+#[derive(Clone)]
+pub struct Devnet {
+    endpoint: String,
+    http: reqwest::Client,
+}
+
+impl Devnet {
+    pub fn new(endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response: Value = self
+            .http
+            .post(&self.endpoint)
+            .body(request.to_string())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        if let Some(error) = response.get("error") {
+            panic!("devnet method {} failed: {}", method, error);
+        }
+
+        response["result"].clone()
+    }
+
+    /// Mine a block including the pending transactions and returns its hash
+    pub async fn create_block(&self) -> Felt {
+        let result = self.call("devnet_createBlock", json!({})).await;
+
+        Felt::from_hex(result["block_hash"].as_str().unwrap()).unwrap()
+    }
+
+    /// Set the timestamp of the next block, mining a block right away when `generate_block` is set
+    pub async fn set_time(&self, timestamp: u64, generate_block: bool) {
+        self.call("devnet_setTime", json!({ "time": timestamp, "generate_block": generate_block }))
+            .await;
+    }
+
+    /// Move the time of the devnet forward by the given number of seconds and mine a block
+    pub async fn increase_time(&self, seconds: u64) {
+        self.call("devnet_increaseTime", json!({ "time": seconds })).await;
+    }
+
+    /// Set the gas prices of the next blocks, mining a block right away when `generate_block` is set
+    pub async fn set_gas_prices(&self, prices: &GasPrices, generate_block: bool) {
+        let mut params = serde_json::to_value(prices).unwrap();
+        params["generate_block"] = json!(generate_block);
+
+        self.call("devnet_setGasPrice", params).await;
+    }
+
+    /// Mint the given amount of STRK (in FRI) to `address`
+    pub async fn mint(&self, address: Felt, amount: u128) {
+        self.call("devnet_mint", json!({ "address": address.to_fixed_hex_string(), "amount": amount, "unit": "FRI" }))
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::devnet::{BlockGeneration, DevnetOptions, Fork, GasPrices};
+
+    #[test]
+    fn options_are_converted_to_devnet_arguments() {
+        assert!(DevnetOptions::default().is_default());
+
+        let options = DevnetOptions {
+            block_generation: BlockGeneration::Demand,
+            fork: Some(Fork::mainnet("https://rpc.starknet.io", Some(1_000_000))),
+        };
+        assert_eq!(
+            options.args(),
+            vec![
+                "--block-generation-on",
+                "demand",
+                "--fork-network",
+                "https://rpc.starknet.io",
+                "--fork-block",
+                "1000000"
+            ]
+        );
+
+        assert_eq!(BlockGeneration::Interval(5).args(), vec!["--block-generation-on", "5"]);
+    }
+
+    #[test]
+    fn only_set_gas_prices_are_sent() {
+        let prices = GasPrices {
+            l2_gas_price_fri: Some(42),
+            ..Default::default()
+        };
+
+        assert_eq!(serde_json::to_value(prices).unwrap(), serde_json::json!({ "l2_gas_price_fri": 42 }));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod devnet;
+pub mod provider;
 pub mod transaction;
 
 use std::ops::Deref;
@@ -8,10 +10,11 @@ use starknet::core::types::Felt;
 use starknet::macros::felt;
 use testcontainers::core::{IntoContainerPort, WaitFor};
 use testcontainers::runners::AsyncRunner;
-use testcontainers::{ContainerAsync, GenericImage};
+use testcontainers::{ContainerAsync, GenericImage, ImageExt};
 use tokio::time;
 
 use crate::constants::Token;
+use crate::testing::devnet::{Devnet, DevnetOptions, GasPrices};
 use crate::transaction::TokenTransfer;
 use crate::{ChainID, Client, Configuration, StarknetAccountConfiguration};
 
@@ -22,6 +25,9 @@ pub struct TestEnvironment {
 
     pub client: Client,
 
+    /// Controls the block production, time and gas prices of the devnet
+    pub devnet: Devnet,
+
     #[allow(dead_code)]
     pub container: StarknetContainer,
 }
@@ -67,36 +73,67 @@ impl TestEnvironment {
     pub const USDC: Felt = Token::usdc(&Self::CHAIN_ID).address;
 
     pub async fn new() -> Self {
-        let container = Self::start_starknet().await;
+        Self::with_options(DevnetOptions::default()).await
+    }
+
+    /// Start a devnet with the given block generation mode, optionally forked from another network. A forked
+    /// devnet does not contain the contracts of the paymaster CI state.
+    pub async fn with_options(options: DevnetOptions) -> Self {
+        let container = Self::start_starknet(&options).await;
         let endpoint = format!("http://localhost:{}", container.get_host_port_ipv4(5050).await.unwrap());
 
         let configuration = Configuration {
-            chain_id: Self::CHAIN_ID,
+            chain_id: options.fork.as_ref().map(|x| x.chain_id).unwrap_or(Self::CHAIN_ID),
             timeout: 10,
             endpoint,
             fallbacks: vec![],
+            local_estimation: None,
+            endpoint_options: Default::default(),
+            tokens: None,
         };
 
         Self {
-            client: Client::new(&configuration),
+            client: Client::new(&configuration).unwrap(),
+            devnet: Devnet::new(&configuration.endpoint),
             container,
             configuration,
         }
     }
 
-    async fn start_starknet() -> StarknetContainer {
-        GenericImage::new("avnulabs/paymaster-ci-starknet", "0.5.0")
-            .with_exposed_port(5050.tcp())
-            .with_wait_for(WaitFor::message_on_stdout("Starknet Devnet"))
-            .start()
-            .await
-            .unwrap()
+    async fn start_starknet(options: &DevnetOptions) -> StarknetContainer {
+        let image = match options.fork {
+            None => GenericImage::new("avnulabs/paymaster-ci-starknet", "0.5.0"),
+            Some(_) => GenericImage::new("shardlabs/starknet-devnet-rs", "0.5.0"),
+        }
+        .with_exposed_port(5050.tcp())
+        .with_wait_for(WaitFor::message_on_stdout("Starknet Devnet"));
+
+        if options.is_default() {
+            return image.start().await.unwrap();
+        }
+
+        image.with_cmd(options.args()).start().await.unwrap()
     }
 
     pub fn configuration(&self) -> Configuration {
         self.configuration.clone()
     }
 
+    /// Mine a block including the pending transactions, required when the block generation is on demand
+    pub async fn mine_block(&self) -> Felt {
+        self.devnet.create_block().await
+    }
+
+    /// Move the time of the devnet forward, for instance to expire the time bounds of a transaction
+    pub async fn advance_time(&self, duration: Duration) {
+        self.devnet.increase_time(duration.as_secs()).await
+    }
+
+    /// Set the gas prices of the next blocks, for instance to simulate a fee surge
+    pub async fn set_gas_prices(&self, prices: &GasPrices) {
+        self.devnet.set_gas_prices(prices, true).await
+    }
+
     pub async fn transfer_token<A>(&self, account: &A, transfer: &TokenTransfer)
     where
         A: Account + ConnectedAccount,
@@ -0,0 +1,448 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use starknet::core::types::{
+    BlockHashAndNumber, BlockId, BroadcastedDeclareTransaction, BroadcastedDeployAccountTransaction, BroadcastedInvokeTransaction, BroadcastedTransaction,
+    ConfirmedBlockId, ContractClass, ContractStorageKeys, DeclareTransactionResult, DeployAccountTransactionResult, EventFilter, EventsPage, FeeEstimate, Felt,
+    FunctionCall, Hash256, InvokeTransactionResult, MaybePreConfirmedBlockWithReceipts, MaybePreConfirmedBlockWithTxHashes, MaybePreConfirmedBlockWithTxs,
+    MaybePreConfirmedStateUpdate, MessageFeeEstimate, MessageStatus, MsgFromL1, SimulatedTransaction, SimulationFlag, SimulationFlagForEstimateFee, StarknetError,
+    StorageProof, SyncStatusType, Transaction, TransactionReceiptWithBlockInfo, TransactionStatus, TransactionTrace, TransactionTraceWithHash,
+};
+use starknet::providers::{Provider, ProviderError, ProviderImplError, ProviderRequestData, ProviderResponseData};
+use thiserror::Error;
+
+/// Error returned by the [`MockProvider`] when a method is called without a programmed response
+#[derive(Error, Debug)]
+#[error("{0} is not mocked")]
+pub struct NotMocked(pub &'static str);
+
+impl ProviderImplError for NotMocked {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Builds the error returned by a programmed failure
+type ErrorFactory = Arc<dyn Fn() -> StarknetError + Send + Sync>;
+
+fn not_mocked<T>(method: &'static str) -> Result<T, ProviderError> {
+    Err(ProviderError::Other(Box::new(NotMocked(method))))
+}
+
+#[derive(Default)]
+struct State {
+    chain_id: Option<Felt>,
+    block_number: u64,
+
+    nonces: HashMap<Felt, Felt>,
+    class_hashes: HashMap<Felt, Felt>,
+    storage: HashMap<(Felt, Felt), Felt>,
+    calls: HashMap<(Felt, Felt), Result<Vec<Felt>, ErrorFactory>>,
+    fee_estimate: Option<Result<FeeEstimate, ErrorFactory>>,
+    receipts: HashMap<Felt, TransactionReceiptWithBlockInfo>,
+
+    // Transactions submitted through the provider, in order
+    submitted: Vec<BroadcastedInvokeTransaction>,
+}
+
+/// In-memory [`Provider`] whose responses are programmed by the test. Calling a method without a programmed
+/// response fails with [`NotMocked`]; reads of unknown contracts or transactions fail as a node would.
+#[derive(Clone, Default)]
+pub struct MockProvider {
+    state: Arc<RwLock<State>>,
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&State) -> T) -> T {
+        f(&self.state.read().unwrap())
+    }
+
+    fn write<T>(&self, f: impl FnOnce(&mut State) -> T) -> T {
+        f(&mut self.state.write().unwrap())
+    }
+
+    pub fn with_chain_id(self, chain_id: Felt) -> Self {
+        self.write(|x| x.chain_id = Some(chain_id));
+        self
+    }
+
+    pub fn set_block_number(&self, block_number: u64) {
+        self.write(|x| x.block_number = block_number);
+    }
+
+    /// Set the nonce of the account at `address`, which is also considered as deployed
+    pub fn set_nonce(&self, address: Felt, nonce: Felt) {
+        self.write(|x| {
+            x.nonces.insert(address, nonce);
+            x.class_hashes.entry(address).or_insert(Felt::ONE);
+        });
+    }
+
+    pub fn set_class_hash(&self, address: Felt, class_hash: Felt) {
+        self.write(|x| x.class_hashes.insert(address, class_hash));
+    }
+
+    pub fn set_storage(&self, address: Felt, key: Felt, value: Felt) {
+        self.write(|x| x.storage.insert((address, key), value));
+    }
+
+    /// Set the result of calling `selector` on the contract at `address`, whatever the calldata
+    pub fn on_call(&self, address: Felt, selector: Felt, result: Vec<Felt>) {
+        self.write(|x| x.calls.insert((address, selector), Ok(result)));
+    }
+
+    /// Make the calls of `selector` on the contract at `address` fail with the given error
+    pub fn on_call_error(&self, address: Felt, selector: Felt, error: impl Fn() -> StarknetError + Send + Sync + 'static) {
+        self.write(|x| x.calls.insert((address, selector), Err(Arc::new(error))));
+    }
+
+    /// Set the fee estimated for any transaction
+    pub fn on_estimate_fee(&self, estimate: FeeEstimate) {
+        self.write(|x| x.fee_estimate = Some(Ok(estimate)));
+    }
+
+    /// Make the fee estimations fail with the given error
+    pub fn on_estimate_fee_error(&self, error: impl Fn() -> StarknetError + Send + Sync + 'static) {
+        self.write(|x| x.fee_estimate = Some(Err(Arc::new(error))));
+    }
+
+    pub fn set_receipt(&self, transaction_hash: Felt, receipt: TransactionReceiptWithBlockInfo) {
+        self.write(|x| x.receipts.insert(transaction_hash, receipt));
+    }
+
+    /// Returns the invoke transactions submitted so far
+    pub fn submitted_transactions(&self) -> Vec<BroadcastedInvokeTransaction> {
+        self.read(|x| x.submitted.clone())
+    }
+}
+
+#[async_trait]
+impl Provider for MockProvider {
+    async fn spec_version(&self) -> Result<String, ProviderError> {
+        Ok("0.9.0".to_string())
+    }
+
+    async fn get_block_with_tx_hashes<B>(&self, _block_id: B) -> Result<MaybePreConfirmedBlockWithTxHashes, ProviderError>
+    where
+        B: AsRef<BlockId> + Send + Sync,
+    {
+        not_mocked("get_block_with_tx_hashes")
+    }
+
+    async fn get_block_with_txs<B>(&self, _block_id: B) -> Result<MaybePreConfirmedBlockWithTxs, ProviderError>
+    where
+        B: AsRef<BlockId> + Send + Sync,
+    {
+        not_mocked("get_block_with_txs")
+    }
+
+    async fn get_block_with_receipts<B>(&self, _block_id: B) -> Result<MaybePreConfirmedBlockWithReceipts, ProviderError>
+    where
+        B: AsRef<BlockId> + Send + Sync,
+    {
+        not_mocked("get_block_with_receipts")
+    }
+
+    async fn get_state_update<B>(&self, _block_id: B) -> Result<MaybePreConfirmedStateUpdate, ProviderError>
+    where
+        B: AsRef<BlockId> + Send + Sync,
+    {
+        not_mocked("get_state_update")
+    }
+
+    async fn get_storage_at<A, K, B>(&self, contract_address: A, key: K, _block_id: B) -> Result<Felt, ProviderError>
+    where
+        A: AsRef<Felt> + Send + Sync,
+        K: AsRef<Felt> + Send + Sync,
+        B: AsRef<BlockId> + Send + Sync,
+    {
+        let key = (*contract_address.as_ref(), *key.as_ref());
+        Ok(self.read(|x| x.storage.get(&key).copied().unwrap_or_default()))
+    }
+
+    async fn get_messages_status(&self, _transaction_hash: Hash256) -> Result<Vec<MessageStatus>, ProviderError> {
+        not_mocked("get_messages_status")
+    }
+
+    async fn get_transaction_status<H>(&self, _transaction_hash: H) -> Result<TransactionStatus, ProviderError>
+    where
+        H: AsRef<Felt> + Send + Sync,
+    {
+        not_mocked("get_transaction_status")
+    }
+
+    async fn get_transaction_by_hash<H>(&self, _transaction_hash: H) -> Result<Transaction, ProviderError>
+    where
+        H: AsRef<Felt> + Send + Sync,
+    {
+        not_mocked("get_transaction_by_hash")
+    }
+
+    async fn get_transaction_by_block_id_and_index<B>(&self, _block_id: B, _index: u64) -> Result<Transaction, ProviderError>
+    where
+        B: AsRef<BlockId> + Send + Sync,
+    {
+        not_mocked("get_transaction_by_block_id_and_index")
+    }
+
+    async fn get_transaction_receipt<H>(&self, transaction_hash: H) -> Result<TransactionReceiptWithBlockInfo, ProviderError>
+    where
+        H: AsRef<Felt> + Send + Sync,
+    {
+        self.read(|x| x.receipts.get(transaction_hash.as_ref()).cloned())
+            .ok_or(ProviderError::StarknetError(StarknetError::TransactionHashNotFound))
+    }
+
+    async fn get_class<B, H>(&self, _block_id: B, _class_hash: H) -> Result<ContractClass, ProviderError>
+    where
+        B: AsRef<BlockId> + Send + Sync,
+        H: AsRef<Felt> + Send + Sync,
+    {
+        not_mocked("get_class")
+    }
+
+    async fn get_class_hash_at<B, A>(&self, _block_id: B, contract_address: A) -> Result<Felt, ProviderError>
+    where
+        B: AsRef<BlockId> + Send + Sync,
+        A: AsRef<Felt> + Send + Sync,
+    {
+        self.read(|x| x.class_hashes.get(contract_address.as_ref()).copied())
+            .ok_or(ProviderError::StarknetError(StarknetError::ContractNotFound))
+    }
+
+    async fn get_class_at<B, A>(&self, _block_id: B, _contract_address: A) -> Result<ContractClass, ProviderError>
+    where
+        B: AsRef<BlockId> + Send + Sync,
+        A: AsRef<Felt> + Send + Sync,
+    {
+        not_mocked("get_class_at")
+    }
+
+    async fn get_block_transaction_count<B>(&self, _block_id: B) -> Result<u64, ProviderError>
+    where
+        B: AsRef<BlockId> + Send + Sync,
+    {
+        not_mocked("get_block_transaction_count")
+    }
+
+    async fn call<R, B>(&self, request: R, _block_id: B) -> Result<Vec<Felt>, ProviderError>
+    where
+        R: AsRef<FunctionCall> + Send + Sync,
+        B: AsRef<BlockId> + Send + Sync,
+    {
+        let request = request.as_ref();
+        match self.read(|x| x.calls.get(&(request.contract_address, request.entry_point_selector)).cloned()) {
+            Some(result) => result.map_err(|e| ProviderError::StarknetError(e())),
+            None => not_mocked("call"),
+        }
+    }
+
+    async fn estimate_fee<R, S, B>(&self, request: R, _simulation_flags: S, _block_id: B) -> Result<Vec<FeeEstimate>, ProviderError>
+    where
+        R: AsRef<[BroadcastedTransaction]> + Send + Sync,
+        S: AsRef<[SimulationFlagForEstimateFee]> + Send + Sync,
+        B: AsRef<BlockId> + Send + Sync,
+    {
+        match self.read(|x| x.fee_estimate.clone()) {
+            Some(Ok(estimate)) => Ok(vec![estimate; request.as_ref().len()]),
+            Some(Err(e)) => Err(ProviderError::StarknetError(e())),
+            None => not_mocked("estimate_fee"),
+        }
+    }
+
+    async fn estimate_message_fee<M, B>(&self, _message: M, _block_id: B) -> Result<MessageFeeEstimate, ProviderError>
+    where
+        M: AsRef<MsgFromL1> + Send + Sync,
+        B: AsRef<BlockId> + Send + Sync,
+    {
+        not_mocked("estimate_message_fee")
+    }
+
+    async fn block_number(&self) -> Result<u64, ProviderError> {
+        Ok(self.read(|x| x.block_number))
+    }
+
+    async fn block_hash_and_number(&self) -> Result<BlockHashAndNumber, ProviderError> {
+        let block_number = self.read(|x| x.block_number);
+
+        Ok(BlockHashAndNumber {
+            block_hash: Felt::from(block_number),
+            block_number,
+        })
+    }
+
+    async fn chain_id(&self) -> Result<Felt, ProviderError> {
+        match self.read(|x| x.chain_id) {
+            Some(chain_id) => Ok(chain_id),
+            None => not_mocked("chain_id"),
+        }
+    }
+
+    async fn syncing(&self) -> Result<SyncStatusType, ProviderError> {
+        Ok(SyncStatusType::NotSyncing)
+    }
+
+    async fn get_events(&self, _filter: EventFilter, _continuation_token: Option<String>, _chunk_size: u64) -> Result<EventsPage, ProviderError> {
+        Ok(EventsPage {
+            events: vec![],
+            continuation_token: None,
+        })
+    }
+
+    async fn get_nonce<B, A>(&self, _block_id: B, contract_address: A) -> Result<Felt, ProviderError>
+    where
+        B: AsRef<BlockId> + Send + Sync,
+        A: AsRef<Felt> + Send + Sync,
+    {
+        self.read(|x| x.nonces.get(contract_address.as_ref()).copied())
+            .ok_or(ProviderError::StarknetError(StarknetError::ContractNotFound))
+    }
+
+    async fn get_storage_proof<B, H, A, K>(
+        &self,
+        _block_id: B,
+        _class_hashes: H,
+        _contract_addresses: A,
+        _contracts_storage_keys: K,
+    ) -> Result<StorageProof, ProviderError>
+    where
+        B: AsRef<ConfirmedBlockId> + Send + Sync,
+        H: AsRef<[Felt]> + Send + Sync,
+        A: AsRef<[Felt]> + Send + Sync,
+        K: AsRef<[ContractStorageKeys]> + Send + Sync,
+    {
+        not_mocked("get_storage_proof")
+    }
+
+    /// Record the transaction and increment the nonce of its sender. The transaction hash is derived from the
+    /// number of transactions submitted so far.
+    async fn add_invoke_transaction<I>(&self, invoke_transaction: I) -> Result<InvokeTransactionResult, ProviderError>
+    where
+        I: AsRef<BroadcastedInvokeTransaction> + Send + Sync,
+    {
+        let transaction = invoke_transaction.as_ref().clone();
+        let transaction_hash = self.write(|x| {
+            let nonce = x.nonces.entry(transaction.sender_address).or_default();
+            *nonce += Felt::ONE;
+
+            x.submitted.push(transaction);
+            Felt::from(x.submitted.len())
+        });
+
+        Ok(InvokeTransactionResult { transaction_hash })
+    }
+
+    async fn add_declare_transaction<D>(&self, _declare_transaction: D) -> Result<DeclareTransactionResult, ProviderError>
+    where
+        D: AsRef<BroadcastedDeclareTransaction> + Send + Sync,
+    {
+        not_mocked("add_declare_transaction")
+    }
+
+    async fn add_deploy_account_transaction<D>(&self, _deploy_account_transaction: D) -> Result<DeployAccountTransactionResult, ProviderError>
+    where
+        D: AsRef<BroadcastedDeployAccountTransaction> + Send + Sync,
+    {
+        not_mocked("add_deploy_account_transaction")
+    }
+
+    async fn trace_transaction<H>(&self, _transaction_hash: H) -> Result<TransactionTrace, ProviderError>
+    where
+        H: AsRef<Felt> + Send + Sync,
+    {
+        not_mocked("trace_transaction")
+    }
+
+    async fn simulate_transactions<B, T, S>(&self, _block_id: B, _transactions: T, _simulation_flags: S) -> Result<Vec<SimulatedTransaction>, ProviderError>
+    where
+        B: AsRef<BlockId> + Send + Sync,
+        T: AsRef<[BroadcastedTransaction]> + Send + Sync,
+        S: AsRef<[SimulationFlag]> + Send + Sync,
+    {
+        not_mocked("simulate_transactions")
+    }
+
+    async fn trace_block_transactions<B>(&self, _block_id: B) -> Result<Vec<TransactionTraceWithHash>, ProviderError>
+    where
+        B: AsRef<ConfirmedBlockId> + Send + Sync,
+    {
+        not_mocked("trace_block_transactions")
+    }
+
+    async fn batch_requests<R>(&self, _requests: R) -> Result<Vec<ProviderResponseData>, ProviderError>
+    where
+        R: AsRef<[ProviderRequestData]> + Send + Sync,
+    {
+        not_mocked("batch_requests")
+    }
+
+    async fn estimate_fee_single<R, S, B>(&self, request: R, simulation_flags: S, block_id: B) -> Result<FeeEstimate, ProviderError>
+    where
+        R: AsRef<BroadcastedTransaction> + Send + Sync,
+        S: AsRef<[SimulationFlagForEstimateFee]> + Send + Sync,
+        B: AsRef<BlockId> + Send + Sync,
+    {
+        let mut estimates = self
+            .estimate_fee([request.as_ref().clone()], simulation_flags, block_id)
+            .await?;
+
+        Ok(estimates.remove(0))
+    }
+
+    async fn simulate_transaction<B, T, S>(&self, _block_id: B, _transaction: T, _simulation_flags: S) -> Result<SimulatedTransaction, ProviderError>
+    where
+        B: AsRef<BlockId> + Send + Sync,
+        T: AsRef<BroadcastedTransaction> + Send + Sync,
+        S: AsRef<[SimulationFlag]> + Send + Sync,
+    {
+        not_mocked("simulate_transaction")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::{BlockId, BlockTag, FunctionCall, StarknetError};
+    use starknet::macros::selector;
+    use starknet::providers::{Provider, ProviderError};
+
+    use crate::constants::Token;
+    use crate::testing::provider::MockProvider;
+    use crate::Client;
+
+    #[tokio::test]
+    async fn responses_are_programmable() {
+        let provider = MockProvider::new();
+        provider.on_call(Token::STRK_ADDRESS, selector!("balance_of"), vec![42u32.into(), 0u32.into()]);
+
+        let call = FunctionCall {
+            contract_address: Token::STRK_ADDRESS,
+            entry_point_selector: selector!("balance_of"),
+            calldata: vec![],
+        };
+        let result = provider.call(&call, BlockId::Tag(BlockTag::Latest)).await.unwrap();
+        assert_eq!(result, vec![42u32.into(), 0u32.into()]);
+
+        let result = provider.get_nonce(BlockId::Tag(BlockTag::Latest), Token::ETH_ADDRESS).await;
+        assert!(matches!(result, Err(ProviderError::StarknetError(StarknetError::ContractNotFound))));
+
+        let result = provider.get_class(BlockId::Tag(BlockTag::Latest), Token::ETH_ADDRESS).await;
+        assert!(matches!(result, Err(ProviderError::Other(_))));
+    }
+
+    #[tokio::test]
+    async fn client_can_be_backed_by_the_mock() {
+        let provider = MockProvider::new();
+        provider.on_call(Token::STRK_ADDRESS, selector!("balance_of"), vec![42u32.into(), 0u32.into()]);
+
+        let client = Client::mock(crate::ChainID::Sepolia, provider);
+        let balance = client.fetch_balance(Token::STRK_ADDRESS, Token::ETH_ADDRESS).await.unwrap();
+
+        assert_eq!(balance, 42u32.into());
+    }
+}
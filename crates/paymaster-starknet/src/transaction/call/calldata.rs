@@ -189,6 +189,12 @@ impl SequentialCalldataDecoder {
             let to = parse_next_value(call_stack, "to")?;
             let selector = parse_next_value(call_stack, "selector")?;
             let length: usize = parse_next_value(call_stack, "length")?;
+
+            // The length comes from the client, allocating it upfront would let a single felt exhaust the memory
+            if length > call_stack.len() {
+                return Err(Error::CalldataDecoding("calldata missing".to_string()));
+            }
+
             let mut calldata = Vec::with_capacity(length);
             for _ in 0..length {
                 calldata.push(parse_next_value(call_stack, "calldata")?);
@@ -210,8 +216,60 @@ impl SequentialCalldataDecoder {
 
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use super::*;
 
+    fn arbitrary_felt() -> impl Strategy<Value = Felt> {
+        prop_oneof![
+            any::<u8>().prop_map(Felt::from),
+            any::<u64>().prop_map(Felt::from),
+            any::<[u8; 32]>().prop_map(|x| Felt::from_bytes_be(&x)),
+        ]
+    }
+
+    fn arbitrary_call() -> impl Strategy<Value = Call> {
+        (arbitrary_felt(), arbitrary_felt(), prop::collection::vec(arbitrary_felt(), 0..8)).prop_map(|(to, selector, calldata)| Call { to, selector, calldata })
+    }
+
+    proptest! {
+        #[test]
+        fn decoding_arbitrary_calldata_never_panics(calldata in prop::collection::vec(arbitrary_felt(), 0..32)) {
+            let _ = SequentialCalldataDecoder::new(&calldata);
+        }
+
+        #[test]
+        fn encoded_calls_are_decoded_back(calls in prop::collection::vec(arbitrary_call(), 0..8)) {
+            let calldata = calls.iter().fold(CalldataBuilder::new(), |builder, call| builder.encode(call)).build();
+
+            let decoder = SequentialCalldataDecoder::new(&calldata).unwrap();
+            prop_assert_eq!(decoder.len(), calls.len());
+            for (decoded, call) in decoder.iter().zip(&calls) {
+                prop_assert_eq!(decoded.to, call.to);
+                prop_assert_eq!(decoded.selector, call.selector);
+                prop_assert_eq!(&decoded.calldata, &call.calldata);
+            }
+        }
+
+        #[test]
+        fn arrays_are_prefixed_with_their_length(values in prop::collection::vec(arbitrary_felt(), 0..32)) {
+            let calldata = CalldataBuilder::new().encode(&values).build();
+
+            prop_assert_eq!(calldata[0], Felt::from(values.len()));
+            prop_assert_eq!(&calldata[1..], &values[..]);
+        }
+    }
+
+    #[test]
+    fn error_when_length_exceeds_calldata() {
+        let calldata = vec![Felt::from(123u64), Felt::from(456u64), Felt::from(u64::MAX)];
+
+        match SequentialCalldataDecoder::new(&calldata) {
+            Err(Error::CalldataDecoding(msg)) => assert!(msg.contains("calldata missing")),
+            _ => panic!("Expected CalldataDecoding error"),
+        }
+    }
+
     #[test]
     fn decode_empty_calldata() {
         let calldata = vec![];
@@ -0,0 +1,146 @@
+use starknet::accounts::{Account, AccountError, ConnectedAccount};
+use starknet::core::types::{
+    BlockId, BlockTag, BroadcastedInvokeTransactionV3, Call, DataAvailabilityMode, Felt, InvokeTransactionResult, ResourceBounds, ResourceBoundsMapping,
+};
+use starknet::providers::{Provider, ProviderError};
+use starknet::signers::SigningKey;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::transaction::{invoke_transaction_hash, Calls, ExecuteFromOutsideMessage, ExecuteFromOutsideParameters, PaymasterVersion, TimeBounds, TransactionGasEstimate};
+use crate::{ChainID, Error, StarknetAccount};
+
+/// Estimation and execution of the calls with an account of the paymaster
+impl Calls {
+    pub fn with_estimate(self, estimate: TransactionGasEstimate) -> EstimatedCalls {
+        EstimatedCalls { calls: self, estimate }
+    }
+
+    pub async fn estimate(&self, account: &StarknetAccount, tip: Option<u64>) -> Result<EstimatedCalls, Error> {
+        let tip = match tip {
+            None => {
+                let block = account.provider().get_block_with_txs(BlockId::Tag(BlockTag::Latest)).await?;
+                block.median_tip()
+            },
+            Some(tip) => tip,
+        };
+
+        let result = account.execute_v3(self.to_vec()).tip(tip).estimate_fee().await?;
+
+        Ok(self.clone().with_estimate(TransactionGasEstimate::new(result, tip)))
+    }
+
+    pub async fn execute(&self, account: &StarknetAccount, nonce: Felt) -> Result<InvokeTransactionResult, Error> {
+        let result = account.execute_v3(self.to_vec()).nonce(nonce).send().await?;
+
+        Ok(result)
+    }
+
+    /// Wraps the calls into an `execute_from_outside` call of the account `to`, signed with `to_private_key`
+    pub fn as_execute_from_outside_call(&self, caller_address: Felt, to: StarknetAccount, to_private_key: Felt, time_bounds: TimeBounds) -> Result<Call, Error> {
+        let to_address = to.address().clone();
+        // Create execute_from_outside message
+        let execute_from_outside_message = ExecuteFromOutsideMessage::new(
+            PaymasterVersion::V1,
+            ExecuteFromOutsideParameters {
+                chain_id: ChainID::from_felt(to.chain_id())?,
+                caller: caller_address,
+                nonce: Felt::from(Uuid::new_v4().to_u128_le()),
+                calls: self.clone(),
+                time_bounds,
+            },
+        );
+
+        // Convert to typed data for signing
+        let typed_data = execute_from_outside_message.clone().to_typed_data()?;
+
+        // Sign the message with the gas tank's private key
+        let message_hash = typed_data.message_hash(to_address)?;
+        let signing_key = SigningKey::from_secret_scalar(to_private_key);
+        let signature = signing_key
+            .sign(&message_hash)
+            .map_err(|e| Error::Internal(format!("could not sign outside execution: {}", e)))?;
+
+        // Create the execute_from_outside call
+        Ok(execute_from_outside_message.to_call(to.address(), &vec![signature.r, signature.s]))
+    }
+}
+
+#[derive(Debug)]
+pub struct EstimatedCalls {
+    calls: Calls,
+    estimate: TransactionGasEstimate,
+}
+
+impl EstimatedCalls {
+    pub fn calls(&self) -> &Calls {
+        &self.calls
+    }
+
+    pub fn estimate(&self) -> TransactionGasEstimate {
+        self.estimate.clone()
+    }
+
+    /// Returns the hash of the transaction submitted by [`execute`] with the same account and nonce, known before
+    /// it is broadcast so that the transaction can be tracked even when its submission fails ambiguously
+    pub fn transaction_hash(&self, account: &StarknetAccount, nonce: Felt) -> Result<Felt, Error> {
+        let transaction = BroadcastedInvokeTransactionV3 {
+            sender_address: account.address(),
+            calldata: account.encode_calls(&self.calls.to_vec()),
+            signature: vec![],
+            nonce,
+            resource_bounds: ResourceBoundsMapping {
+                l1_gas: ResourceBounds {
+                    max_amount: self.estimate.l1_gas_consumed(),
+                    max_price_per_unit: self.estimate.l1_gas_price()?,
+                },
+                l1_data_gas: ResourceBounds {
+                    max_amount: self.estimate.l1_data_gas_consumed(),
+                    max_price_per_unit: self.estimate.l1_data_gas_price()?,
+                },
+                l2_gas: ResourceBounds {
+                    max_amount: self.estimate.l2_gas_consumed(),
+                    max_price_per_unit: self.estimate.l2_gas_price()?,
+                },
+            },
+            tip: self.estimate.tip(),
+            paymaster_data: vec![],
+            account_deployment_data: vec![],
+            nonce_data_availability_mode: DataAvailabilityMode::L1,
+            fee_data_availability_mode: DataAvailabilityMode::L1,
+            is_query: false,
+        };
+
+        Ok(invoke_transaction_hash(account.chain_id(), &transaction))
+    }
+
+    pub async fn execute(&self, account: &StarknetAccount, nonce: Felt) -> Result<InvokeTransactionResult, Error> {
+        let result = account
+            .execute_v3(self.calls.to_vec())
+            .nonce(nonce)
+            .l1_gas(self.estimate.l1_gas_consumed())
+            .l1_gas_price(self.estimate.l1_gas_price()?)
+            .l2_gas(self.estimate.l2_gas_consumed())
+            .l2_gas_price(self.estimate.l2_gas_price()?)
+            .l1_data_gas(self.estimate.l1_data_gas_consumed())
+            .l1_data_gas_price(self.estimate.l1_data_gas_price()?)
+            .tip(self.estimate.tip())
+            .send()
+            .await;
+
+        match &result {
+            Err(AccountError::Provider(e @ ProviderError::RateLimited)) => {
+                error!("{}", e);
+            },
+            Err(AccountError::Provider(e @ ProviderError::ArrayLengthMismatch)) => {
+                error!("{}", e);
+            },
+            Err(AccountError::Provider(ProviderError::Other(error))) => {
+                error!("{}", error);
+            },
+            _ => {},
+        };
+
+        Ok(result?)
+    }
+}
@@ -62,3 +62,42 @@ impl TokenTransfer {
         }
     }
 }
+
+/// Allowance granted to a spender from within the *execute_from_outside* message of the user. The allowance
+/// is authorized by the signature of the message, so no prior on-chain approval is required.
+#[derive(Debug, Clone, Copy)]
+pub struct TokenPermit {
+    spender: Felt,
+    token: Felt,
+    amount: Felt,
+}
+
+impl TokenPermit {
+    pub fn new(token: Felt, spender: Felt, amount: Felt) -> Self {
+        Self { token, spender, amount }
+    }
+
+    pub fn spender(&self) -> Felt {
+        self.spender
+    }
+
+    pub fn amount(&self) -> Felt {
+        self.amount
+    }
+
+    pub fn token(&self) -> Felt {
+        self.token
+    }
+
+    pub fn to_call(&self) -> Call {
+        Call {
+            to: self.token,
+            selector: selector!("approve"),
+            calldata: CalldataBuilder::new()
+                .encode(&self.spender)
+                .encode(&self.amount)
+                .encode(&Felt::ZERO)
+                .build(),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use starknet::core::types::{Call, Felt};
+use starknet::macros::selector;
+
+use crate::contract::ContractClass;
+use crate::transaction::{CalldataBuilder, Calls, SequentialCalldataDecoder, TimeBounds};
+use crate::{Error, Signature};
+
+/// Entrypoint of the concurrent outside execution exposed by Cartridge Controller accounts in addition to SNIP-9 v2
+pub const CONTROLLER_OUTSIDE_EXECUTION_SELECTOR: Felt = selector!("execute_from_outside_v3");
+
+/// Returns true if the class is a Cartridge Controller account, recognized by its concurrent outside execution
+pub fn is_controller_class(class: &ContractClass) -> bool {
+    class.abi.contains_selector(CONTROLLER_OUTSIDE_EXECUTION_SELECTOR)
+}
+
+/// Nonce of a Controller outside execution. Executions on different channels do not conflict while the mask
+/// allows several executions on the same channel, so that a user can have concurrent outside executions pending.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct ControllerNonce {
+    pub channel: Felt,
+    pub mask: u128,
+}
+
+/// Outside execution of a Cartridge Controller account through `execute_from_outside_v3`
+#[derive(Debug, Clone, Hash)]
+pub struct ControllerOutsideExecution {
+    pub caller: Felt,
+    pub nonce: ControllerNonce,
+    pub time_bounds: TimeBounds,
+    pub calls: Calls,
+}
+
+impl ControllerOutsideExecution {
+    /// Decode the outside execution from a call to `execute_from_outside_v3`, along with its signature
+    pub fn from_call(call: &Call) -> Result<(Self, Signature), Error> {
+        if call.selector != CONTROLLER_OUTSIDE_EXECUTION_SELECTOR {
+            return Err(Error::CalldataDecoding("not a controller outside execution".to_string()));
+        }
+
+        let calldata = &call.calldata;
+        let field = |index: usize, name: &str| {
+            calldata
+                .get(index)
+                .copied()
+                .ok_or_else(|| Error::CalldataDecoding(format!("{name} missing")))
+        };
+        let integer = |index: usize, name: &str| -> Result<u64, Error> {
+            field(index, name)?
+                .try_into()
+                .map_err(|_| Error::CalldataDecoding(format!("{name} invalid")))
+        };
+
+        let caller = field(0, "caller")?;
+        let nonce = ControllerNonce {
+            channel: field(1, "nonce channel")?,
+            mask: field(2, "nonce mask")?
+                .try_into()
+                .map_err(|_| Error::CalldataDecoding("nonce mask invalid".to_string()))?,
+        };
+        let time_bounds = TimeBounds {
+            execute_after: integer(3, "execute_after")?,
+            execute_before: integer(4, "execute_before")?,
+        };
+
+        // Calls are followed by the signature, the end of the calls is found by walking through them
+        let calls_len = integer(5, "calls_len")? as usize;
+        let mut offset = 6;
+        for _ in 0..calls_len {
+            let length = integer(offset + 2, "calldata_len")? as usize;
+            offset = offset
+                .checked_add(3 + length)
+                .filter(|x| *x <= calldata.len())
+                .ok_or(Error::CalldataDecoding("calldata missing".to_string()))?;
+        }
+        let calls = SequentialCalldataDecoder::new(&calldata[6..offset])?
+            .iter()
+            .map(|x| Call {
+                to: x.to,
+                selector: x.selector,
+                calldata: x.calldata.clone(),
+            })
+            .collect();
+
+        let signature_len = integer(offset, "signature_len")? as usize;
+        let signature = calldata
+            .get(offset + 1..)
+            .filter(|x| x.len() == signature_len)
+            .ok_or(Error::CalldataDecoding("signature invalid".to_string()))?
+            .to_vec();
+
+        let execution = Self {
+            caller,
+            nonce,
+            time_bounds,
+            calls: Calls::new(calls),
+        };
+
+        Ok((execution, signature))
+    }
+
+    pub fn to_call(&self, user: Felt, signature: &[Felt]) -> Call {
+        Call {
+            to: user,
+            selector: CONTROLLER_OUTSIDE_EXECUTION_SELECTOR,
+            calldata: CalldataBuilder::new()
+                .encode(&self.caller)
+                .encode(&self.nonce.channel)
+                .encode(&Felt::from(self.nonce.mask))
+                .encode(&self.time_bounds)
+                .encode(&self.calls)
+                .encode(&signature)
+                .build(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::{Call, Felt};
+    use starknet::macros::selector;
+
+    use crate::transaction::controller::{ControllerNonce, ControllerOutsideExecution, CONTROLLER_OUTSIDE_EXECUTION_SELECTOR};
+    use crate::transaction::{Calls, TimeBounds};
+
+    #[test]
+    fn controller_outside_execution_round_trips() {
+        let execution = ControllerOutsideExecution {
+            caller: Felt::from(0x123),
+            nonce: ControllerNonce {
+                channel: Felt::from(7),
+                mask: 0b100,
+            },
+            time_bounds: TimeBounds {
+                execute_after: 1,
+                execute_before: 2,
+            },
+            calls: Calls::new(vec![Call {
+                to: Felt::from(0x456),
+                selector: selector!("transfer"),
+                calldata: vec![Felt::ONE, Felt::TWO, Felt::ZERO],
+            }]),
+        };
+
+        let call = execution.to_call(Felt::from(0x789), &[Felt::from(0xDEAD)]);
+        assert_eq!(call.selector, CONTROLLER_OUTSIDE_EXECUTION_SELECTOR);
+
+        let (decoded, signature) = ControllerOutsideExecution::from_call(&call).unwrap();
+        assert_eq!(decoded.caller, Felt::from(0x123));
+        assert_eq!(decoded.nonce, execution.nonce);
+        assert_eq!(decoded.time_bounds.execute_before, 2);
+        assert_eq!(decoded.calls.len(), 1);
+        assert_eq!(decoded.calls[0].calldata, vec![Felt::ONE, Felt::TWO, Felt::ZERO]);
+        assert_eq!(signature, vec![Felt::from(0xDEAD)]);
+    }
+
+    #[test]
+    fn truncated_controller_outside_execution_is_rejected() {
+        let call = Call {
+            to: Felt::ONE,
+            selector: CONTROLLER_OUTSIDE_EXECUTION_SELECTOR,
+            calldata: vec![Felt::ONE, Felt::TWO, Felt::THREE, Felt::ZERO, Felt::ONE, Felt::TWO, Felt::ONE],
+        };
+
+        assert!(ControllerOutsideExecution::from_call(&call).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use starknet::core::types::{BroadcastedInvokeTransactionV3, DataAvailabilityMode, Felt, ResourceBounds};
+use starknet::macros::felt;
+use starknet_crypto::poseidon_hash_many;
+
+/// Prefix of the hash of the invoke transactions, `invoke` encoded as a short string
+const PREFIX_INVOKE: Felt = felt!("0x696e766f6b65");
+
+/// Version of the transactions which are only simulated or estimated, 2^128 + 3
+const QUERY_VERSION_THREE: Felt = felt!("0x100000000000000000000000000000003");
+
+/// Returns the hash of a v3 invoke transaction on the given chain, as computed by the sequencer (SNIP-8). Known
+/// before the transaction is broadcast, it does not depend on the signature.
+pub fn invoke_transaction_hash(chain_id: Felt, transaction: &BroadcastedInvokeTransactionV3) -> Felt {
+    let bounds = &transaction.resource_bounds;
+    let fee_hash = poseidon_hash_many(&[
+        Felt::from(transaction.tip),
+        resource_bound(b"L1_GAS", &bounds.l1_gas),
+        resource_bound(b"L2_GAS", &bounds.l2_gas),
+        resource_bound(b"L1_DATA", &bounds.l1_data_gas),
+    ]);
+
+    poseidon_hash_many(&[
+        PREFIX_INVOKE,
+        if transaction.is_query { QUERY_VERSION_THREE } else { Felt::THREE },
+        transaction.sender_address,
+        fee_hash,
+        poseidon_hash_many(&transaction.paymaster_data),
+        chain_id,
+        transaction.nonce,
+        data_availability_modes(transaction.nonce_data_availability_mode, transaction.fee_data_availability_mode),
+        poseidon_hash_many(&transaction.account_deployment_data),
+        poseidon_hash_many(&transaction.calldata),
+    ])
+}
+
+// Name of the resource on 7 bytes, followed by its max amount on 8 bytes and its max price on 16 bytes
+fn resource_bound(name: &[u8], bound: &ResourceBounds) -> Felt {
+    let mut buffer = [0u8; 32];
+    buffer[8 - name.len()..8].copy_from_slice(name);
+    buffer[8..16].copy_from_slice(&bound.max_amount.to_be_bytes());
+    buffer[16..].copy_from_slice(&bound.max_price_per_unit.to_be_bytes());
+
+    Felt::from_bytes_be(&buffer)
+}
+
+fn data_availability_modes(nonce: DataAvailabilityMode, fee: DataAvailabilityMode) -> Felt {
+    let mode = |x: DataAvailabilityMode| match x {
+        DataAvailabilityMode::L1 => 0u64,
+        DataAvailabilityMode::L2 => 1u64,
+    };
+
+    Felt::from((mode(nonce) << 32) + mode(fee))
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::accounts::Account;
+    use starknet::core::types::{BroadcastedInvokeTransactionV3, Call, DataAvailabilityMode, Felt, ResourceBounds, ResourceBoundsMapping};
+    use starknet::macros::selector;
+
+    use crate::testing::provider::MockProvider;
+    use crate::transaction::hash::invoke_transaction_hash;
+    use crate::transaction::CalldataBuilder;
+    use crate::{ChainID, Client, StarknetAccountConfiguration};
+
+    #[test]
+    fn hash_matches_the_one_signed_by_the_account() {
+        let client = Client::mock(ChainID::Sepolia, MockProvider::default());
+        let account = client.initialize_account(&StarknetAccountConfiguration {
+            address: Felt::from(0x1234),
+            private_key: Felt::from(0x5678),
+        });
+
+        let calls = vec![Call {
+            to: Felt::from(0x42),
+            selector: selector!("transfer"),
+            calldata: vec![Felt::ONE, Felt::from(100), Felt::ZERO],
+        }];
+        let resource_bounds = ResourceBoundsMapping {
+            l1_gas: ResourceBounds {
+                max_amount: 10,
+                max_price_per_unit: 1_000,
+            },
+            l1_data_gas: ResourceBounds {
+                max_amount: 200,
+                max_price_per_unit: 30,
+            },
+            l2_gas: ResourceBounds {
+                max_amount: 1_000_000,
+                max_price_per_unit: 8_000_000_000,
+            },
+        };
+
+        let expected = account
+            .execute_v3(calls.clone())
+            .nonce(Felt::from(7))
+            .l1_gas(resource_bounds.l1_gas.max_amount)
+            .l1_gas_price(resource_bounds.l1_gas.max_price_per_unit)
+            .l2_gas(resource_bounds.l2_gas.max_amount)
+            .l2_gas_price(resource_bounds.l2_gas.max_price_per_unit)
+            .l1_data_gas(resource_bounds.l1_data_gas.max_amount)
+            .l1_data_gas_price(resource_bounds.l1_data_gas.max_price_per_unit)
+            .tip(5)
+            .prepared()
+            .unwrap()
+            .transaction_hash(false);
+
+        let transaction = BroadcastedInvokeTransactionV3 {
+            sender_address: Felt::from(0x1234),
+            calldata: CalldataBuilder::new().encode(&calls).build(),
+            signature: vec![],
+            nonce: Felt::from(7),
+            resource_bounds,
+            tip: 5,
+            paymaster_data: vec![],
+            account_deployment_data: vec![],
+            nonce_data_availability_mode: DataAvailabilityMode::L1,
+            fee_data_availability_mode: DataAvailabilityMode::L1,
+            is_query: false,
+        };
+
+        assert_eq!(invoke_transaction_hash(ChainID::Sepolia.as_felt(), &transaction), expected);
+    }
+}
@@ -14,9 +14,16 @@ mod gas;
 pub use gas::TransactionGasEstimate;
 use paymaster_common::enum_dispatch;
 
+pub mod controller;
+mod hash;
+pub use hash::invoke_transaction_hash;
+#[cfg(feature = "native")]
+pub mod multisig;
+mod session;
 mod time;
 mod version;
 
+pub use session::{session_message_hash, Session, SessionAuthorization, SignerSignature, SESSION_MAGIC};
 pub use time::TimeBounds;
 pub use version::{PaymasterVersion, SupportedVersion};
 
@@ -127,6 +134,13 @@ impl ExecuteFromOutsideMessage {
             Self::V2(message) => &message.nonce,
         }
     }
+
+    pub fn time_bounds(&self) -> &TimeBounds {
+        match self {
+            Self::V1(message) => &message.time_bounds,
+            Self::V2(message) => &message.time_bounds,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Hash)]
@@ -440,3 +454,97 @@ mod unknown_chain_id_smoke {
         assert!(matches!(parsed.chain_id, ChainID::Unknown(f) if f == custom));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use serde_json::Value as JsonValue;
+    use starknet::core::types::{Call, Felt, TypedData};
+
+    use crate::transaction::{Calls, ExecuteFromOutsideMessage, ExecuteFromOutsideParameters, PaymasterVersion, TimeBounds};
+    use crate::ChainID;
+
+    fn arbitrary_felt() -> impl Strategy<Value = Felt> {
+        prop_oneof![any::<u64>().prop_map(Felt::from), any::<[u8; 31]>().prop_map(|x| Felt::from_bytes_be_slice(&x)),]
+    }
+
+    fn arbitrary_parameters() -> impl Strategy<Value = ExecuteFromOutsideParameters> {
+        let call =
+            (arbitrary_felt(), arbitrary_felt(), prop::collection::vec(arbitrary_felt(), 0..4)).prop_map(|(to, selector, calldata)| Call { to, selector, calldata });
+
+        (
+            prop_oneof![Just(ChainID::Sepolia), Just(ChainID::Mainnet)],
+            arbitrary_felt(),
+            arbitrary_felt(),
+            any::<u64>(),
+            any::<u64>(),
+            prop::collection::vec(call, 0..4),
+        )
+            .prop_map(|(chain_id, caller, nonce, execute_after, execute_before, calls)| ExecuteFromOutsideParameters {
+                chain_id,
+                caller,
+                nonce,
+                time_bounds: TimeBounds { execute_after, execute_before },
+                calls: Calls::new(calls),
+            })
+    }
+
+    fn arbitrary_version() -> impl Strategy<Value = PaymasterVersion> {
+        prop_oneof![Just(PaymasterVersion::V1), Just(PaymasterVersion::V2)]
+    }
+
+    fn arbitrary_json() -> impl Strategy<Value = JsonValue> {
+        let leaf = prop_oneof![
+            Just(JsonValue::Null),
+            any::<bool>().prop_map(JsonValue::from),
+            any::<i64>().prop_map(JsonValue::from),
+            ".{0,16}".prop_map(JsonValue::from),
+            arbitrary_felt().prop_map(|x| JsonValue::from(x.to_hex_string())),
+        ];
+
+        leaf.prop_recursive(3, 16, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(JsonValue::from),
+                prop::collection::btree_map("[A-Za-z ]{0,8}", inner, 0..4).prop_map(|x| JsonValue::Object(x.into_iter().collect())),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn messages_are_decoded_back_from_their_typed_data(version in arbitrary_version(), parameters in arbitrary_parameters()) {
+            let typed_data = ExecuteFromOutsideMessage::new(version, parameters.clone()).to_typed_data().unwrap();
+            let message = ExecuteFromOutsideMessage::from_typed_data(&typed_data).unwrap();
+
+            prop_assert_eq!(message.nonce(), &parameters.nonce);
+            prop_assert_eq!(message.time_bounds().execute_after, parameters.time_bounds.execute_after);
+            prop_assert_eq!(message.time_bounds().execute_before, parameters.time_bounds.execute_before);
+            prop_assert_eq!(message.calls().len(), parameters.calls.len());
+            for (decoded, call) in message.calls().iter().zip(parameters.calls.iter()) {
+                prop_assert_eq!(decoded.to, call.to);
+                prop_assert_eq!(decoded.selector, call.selector);
+                prop_assert_eq!(&decoded.calldata, &call.calldata);
+            }
+        }
+
+        #[test]
+        fn decoding_tampered_typed_data_never_panics(
+            version in arbitrary_version(),
+            parameters in arbitrary_parameters(),
+            field in any::<prop::sample::Index>(),
+            replacement in arbitrary_json(),
+        ) {
+            let typed_data = ExecuteFromOutsideMessage::new(version, parameters).to_typed_data().unwrap();
+
+            let mut value = serde_json::to_value(&typed_data).unwrap();
+            let message = value["message"].as_object_mut().unwrap();
+            let key = message.keys().nth(field.index(message.len())).cloned().unwrap();
+            message.insert(key, replacement);
+
+            // Tampered values which are not even valid typed data are rejected before reaching the decoder
+            if let Ok(tampered) = serde_json::from_value::<TypedData>(value) {
+                let _ = ExecuteFromOutsideMessage::from_typed_data(&tampered);
+            }
+        }
+    }
+}
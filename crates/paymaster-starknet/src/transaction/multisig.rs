@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use starknet::accounts::{Account, ConnectedAccount};
+use starknet::core::serde::unsigned_field_element::UfeHex;
+use starknet::core::types::{BroadcastedInvokeTransactionV3, DataAvailabilityMode, Felt, InvokeTransactionResult, ResourceBounds, ResourceBoundsMapping};
+use starknet::providers::Provider;
+use starknet::signers::SigningKey;
+
+use crate::transaction::{CalldataBuilder, Calls, EstimatedCalls, TransactionGasEstimate};
+use crate::{Client, Error, Signature, StarknetAccount};
+
+/// L2 gas consumed by the multisig to check the signature of one of its signers. The estimate of a proposal skips the
+/// validation since a single signer cannot produce a signature accepted by the multisig.
+const SIGNER_VALIDATION_L2_GAS: u64 = 20_000_000;
+
+/// Factor applied to the estimated gas prices, a proposal waiting hours for its signers must remain executable
+/// when the prices rise in the meantime. Only the actual prices are paid.
+const PROPOSAL_PRICE_MARGIN: u128 = 2;
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MultisigSignature {
+    /// Public key of the signer
+    #[serde_as(as = "UfeHex")]
+    pub signer: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub r: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub s: Felt,
+}
+
+/// Transaction of a multisig account (e.g. Argent multisig) waiting for the approval of its signers. The nonce,
+/// the resource bounds and the tip are fixed when the proposal is created so that every signer signs the same hash.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MultisigProposal {
+    #[serde_as(as = "UfeHex")]
+    pub account: Felt,
+
+    #[serde_as(as = "UfeHex")]
+    pub transaction_hash: Felt,
+
+    pub calls: Calls,
+
+    #[serde_as(as = "UfeHex")]
+    pub nonce: Felt,
+
+    pub resource_bounds: ResourceBoundsMapping,
+    pub tip: u64,
+
+    pub signatures: Vec<MultisigSignature>,
+}
+
+impl MultisigProposal {
+    /// Estimate the `calls` of the multisig `account`. The validation is skipped since a single signer cannot
+    /// produce a signature accepted by the multisig.
+    pub async fn estimate(client: &Client, account: Felt, calls: &Calls, nonce: Felt) -> Result<EstimatedCalls, Error> {
+        let tip = client.fetch_block_median_tip().await?;
+        let estimate = client
+            .estimate_transactions(&[calls.as_transaction(account, nonce, tip)])
+            .await?
+            .pop()
+            .ok_or(Error::Internal("missing estimate".to_string()))?;
+
+        Ok(calls.clone().with_estimate(TransactionGasEstimate::new(estimate, tip)))
+    }
+
+    /// Create the proposal which executes the estimated `calls` from the multisig `account` with the given `nonce`.
+    /// The resource bounds cover the validation of the signatures of `threshold` signers, which the estimate skips,
+    /// and the rise of the gas prices while the signers approve the proposal.
+    pub fn new(account: &StarknetAccount, calls: &EstimatedCalls, nonce: Felt, threshold: usize) -> Result<Self, Error> {
+        let estimate = calls.estimate();
+        let resource_bounds = Self::resource_bounds(&estimate, threshold)?;
+
+        let transaction_hash = account
+            .execute_v3(calls.calls().to_vec())
+            .nonce(nonce)
+            .l1_gas(resource_bounds.l1_gas.max_amount)
+            .l1_gas_price(resource_bounds.l1_gas.max_price_per_unit)
+            .l2_gas(resource_bounds.l2_gas.max_amount)
+            .l2_gas_price(resource_bounds.l2_gas.max_price_per_unit)
+            .l1_data_gas(resource_bounds.l1_data_gas.max_amount)
+            .l1_data_gas_price(resource_bounds.l1_data_gas.max_price_per_unit)
+            .tip(estimate.tip())
+            .prepared()
+            .map_err(|_| Error::Internal("multisig proposal is incomplete".to_string()))?
+            .transaction_hash(false);
+
+        Ok(Self {
+            account: account.address(),
+            transaction_hash,
+            calls: calls.calls().clone(),
+            nonce,
+            resource_bounds,
+            tip: estimate.tip(),
+            signatures: vec![],
+        })
+    }
+
+    fn resource_bounds(estimate: &TransactionGasEstimate, threshold: usize) -> Result<ResourceBoundsMapping, Error> {
+        let validation = SIGNER_VALIDATION_L2_GAS * threshold as u64;
+
+        Ok(ResourceBoundsMapping {
+            l1_gas: ResourceBounds {
+                max_amount: estimate.l1_gas_consumed(),
+                max_price_per_unit: estimate.l1_gas_price()? * PROPOSAL_PRICE_MARGIN,
+            },
+            l1_data_gas: ResourceBounds {
+                max_amount: estimate.l1_data_gas_consumed(),
+                max_price_per_unit: estimate.l1_data_gas_price()? * PROPOSAL_PRICE_MARGIN,
+            },
+            l2_gas: ResourceBounds {
+                max_amount: estimate.l2_gas_consumed() + validation,
+                max_price_per_unit: estimate.l2_gas_price()? * PROPOSAL_PRICE_MARGIN,
+            },
+        })
+    }
+
+    /// Sign the proposal with the key of one of the signers. Signing twice with the same key replaces the previous signature
+    pub fn sign(&mut self, key: &SigningKey) -> Result<(), Error> {
+        let signer = key.verifying_key().scalar();
+        let signature = key.sign(&self.transaction_hash).map_err(|e| Error::Internal(e.to_string()))?;
+
+        self.signatures.retain(|x| x.signer != signer);
+        self.signatures.push(MultisigSignature {
+            signer,
+            r: signature.r,
+            s: signature.s,
+        });
+
+        Ok(())
+    }
+
+    /// Returns true if the proposal has been signed by at least `threshold` signers
+    pub fn is_approved(&self, threshold: usize) -> bool {
+        self.signatures.len() >= threshold
+    }
+
+    /// Returns the signature of the transaction expected by the multisig, that is the signatures ordered by signer
+    pub fn signature(&self) -> Signature {
+        let mut signatures = self.signatures.clone();
+        signatures.sort_by_key(|x| x.signer);
+
+        signatures.into_iter().flat_map(|x| [x.signer, x.r, x.s]).collect()
+    }
+
+    /// Send the transaction of the proposal using the provider of `account`. The proposal must be approved
+    pub async fn submit(&self, account: &StarknetAccount) -> Result<InvokeTransactionResult, Error> {
+        let transaction = BroadcastedInvokeTransactionV3 {
+            sender_address: self.account,
+            calldata: CalldataBuilder::new().encode(&self.calls.to_vec()).build(),
+            signature: self.signature(),
+            nonce: self.nonce,
+            resource_bounds: self.resource_bounds.clone(),
+            tip: self.tip,
+            paymaster_data: vec![],
+            account_deployment_data: vec![],
+            nonce_data_availability_mode: DataAvailabilityMode::L1,
+            fee_data_availability_mode: DataAvailabilityMode::L1,
+            is_query: false,
+        };
+
+        Ok(account.provider().add_invoke_transaction(transaction).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::{FeeEstimate, Felt, ResourceBounds, ResourceBoundsMapping};
+    use starknet::signers::SigningKey;
+
+    use crate::transaction::multisig::{MultisigProposal, PROPOSAL_PRICE_MARGIN, SIGNER_VALIDATION_L2_GAS};
+    use crate::transaction::{Calls, TransactionGasEstimate};
+
+    fn a_proposal() -> MultisigProposal {
+        let bounds = ResourceBounds {
+            max_amount: 0,
+            max_price_per_unit: 0,
+        };
+
+        MultisigProposal {
+            account: Felt::ONE,
+            transaction_hash: Felt::from(42),
+            calls: Calls::empty(),
+            nonce: Felt::ZERO,
+            resource_bounds: ResourceBoundsMapping {
+                l1_gas: bounds.clone(),
+                l1_data_gas: bounds.clone(),
+                l2_gas: bounds,
+            },
+            tip: 0,
+            signatures: vec![],
+        }
+    }
+
+    #[test]
+    fn resource_bounds_cover_the_validation_and_a_price_rise() {
+        let estimate: FeeEstimate = serde_json::from_str(
+            r#"{
+                "l1_gas_consumed": "0x0",
+                "l1_gas_price": "0xa",
+                "l2_gas_consumed": "0xf4240",
+                "l2_gas_price": "0x64",
+                "l1_data_gas_consumed": "0x80",
+                "l1_data_gas_price": "0x14",
+                "overall_fee": "0x5f5ea00",
+                "unit": "FRI"
+            }"#,
+        )
+        .unwrap();
+        let estimate = TransactionGasEstimate::new(estimate, 0);
+
+        let bounds = MultisigProposal::resource_bounds(&estimate, 2).unwrap();
+        assert_eq!(bounds.l2_gas.max_amount, estimate.l2_gas_consumed() + 2 * SIGNER_VALIDATION_L2_GAS);
+        assert_eq!(bounds.l2_gas.max_price_per_unit, estimate.l2_gas_price().unwrap() * PROPOSAL_PRICE_MARGIN);
+        assert_eq!(bounds.l1_data_gas.max_amount, estimate.l1_data_gas_consumed());
+        assert_eq!(bounds.l1_data_gas.max_price_per_unit, estimate.l1_data_gas_price().unwrap() * PROPOSAL_PRICE_MARGIN);
+    }
+
+    #[test]
+    fn signatures_are_ordered_by_signer_and_deduplicated() {
+        let key_1 = SigningKey::from_secret_scalar(Felt::from(1234));
+        let key_2 = SigningKey::from_secret_scalar(Felt::from(5678));
+
+        let mut proposal = a_proposal();
+        proposal.sign(&key_1).unwrap();
+        proposal.sign(&key_2).unwrap();
+        proposal.sign(&key_1).unwrap();
+
+        assert!(proposal.is_approved(2));
+        assert!(!proposal.is_approved(3));
+
+        let signature = proposal.signature();
+        assert_eq!(signature.len(), 6);
+        assert!(signature[0] < signature[3]);
+    }
+}
@@ -0,0 +1,149 @@
+use starknet::core::types::Felt;
+use starknet::macros::short_string;
+use starknet_crypto::poseidon_hash_many;
+
+use crate::transaction::{AsCalldata, CalldataBuilder};
+use crate::Signature;
+
+/// Magic value prefixing the signature of a transaction signed with a session key. Session-based accounts rely on
+/// it to deserialize the rest of the signature as a session token rather than as a signature of the owner.
+pub const SESSION_MAGIC: Felt = short_string!("session-token");
+
+/// Session registered on a Cartridge Controller or on an Argent account, serialized like the `Session` struct
+/// of the account contract
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Session {
+    pub expires_at: u64,
+
+    /// Root of the Merkle tree of the policies (Controller) or of the allowed methods (Argent) of the session
+    pub policies_root: Felt,
+    pub metadata_hash: Felt,
+    pub session_key_guid: Felt,
+
+    /// Guid of the guardian co-signing the transactions of the session. Only part of the Cartridge Controller
+    /// sessions, the Argent sessions are always co-signed by the guardian of the account.
+    pub guardian_key_guid: Option<Felt>,
+}
+
+impl AsCalldata for Session {
+    fn encode(&self) -> Vec<Felt> {
+        let mut calldata = vec![Felt::from(self.expires_at), self.policies_root, self.metadata_hash, self.session_key_guid];
+        calldata.extend(self.guardian_key_guid);
+
+        calldata
+    }
+}
+
+/// Signature serialized like the `SignerSignature` enum of the accounts, in which the signature is prefixed by the
+/// type of the signer and its public key
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum SignerSignature {
+    Starknet { signer: Felt, r: Felt, s: Felt },
+}
+
+impl AsCalldata for SignerSignature {
+    fn encode(&self) -> Vec<Felt> {
+        match self {
+            Self::Starknet { signer, r, s } => vec![Felt::ZERO, *signer, *r, *s],
+        }
+    }
+}
+
+/// Session token which authorizes a session key to sign on behalf of the owner of the account. The session key
+/// and the guardian both sign the session message hash, which the account derives from the hash of the outside
+/// execution and from the session.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct SessionAuthorization {
+    pub session: Session,
+
+    /// Whether the account stores the authorization once checked, so that the next transactions of the session
+    /// do not pay for its validation again
+    pub cache_authorization: bool,
+
+    /// Signature of the session by the owner of the account
+    pub authorization: Vec<Felt>,
+
+    pub session_signature: SignerSignature,
+    pub guardian_signature: SignerSignature,
+
+    /// Merkle proofs that each call of the outside execution is allowed by the policies of the session
+    pub proofs: Vec<Vec<Felt>>,
+}
+
+impl SessionAuthorization {
+    /// Assemble the signature expected by the account, i.e. the serialized `SessionToken` prefixed by the
+    /// [`SESSION_MAGIC`]
+    pub fn signature(&self) -> Signature {
+        CalldataBuilder::new()
+            .encode(&SESSION_MAGIC)
+            .encode(&self.session)
+            .encode(&if self.cache_authorization { Felt::ONE } else { Felt::ZERO })
+            .encode(&self.authorization)
+            .encode(&self.session_signature)
+            .encode(&self.guardian_signature)
+            .encode(&self.proofs)
+            .build()
+    }
+}
+
+/// Returns the hash signed by the session key and by the guardian for the outside execution with `message_hash`, given
+/// the off-chain message hash of the session as computed by the account
+pub fn session_message_hash(message_hash: Felt, session_hash: Felt, cache_authorization: bool) -> Felt {
+    poseidon_hash_many(&[message_hash, session_hash, if cache_authorization { Felt::ONE } else { Felt::ZERO }])
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use crate::transaction::session::{Session, SessionAuthorization, SignerSignature, SESSION_MAGIC};
+
+    fn a_session_authorization(guardian_key_guid: Option<Felt>) -> SessionAuthorization {
+        SessionAuthorization {
+            session: Session {
+                expires_at: 100,
+                policies_root: Felt::from(11),
+                metadata_hash: Felt::from(12),
+                session_key_guid: Felt::from(13),
+                guardian_key_guid,
+            },
+            cache_authorization: true,
+            authorization: vec![Felt::from(20), Felt::from(21)],
+            session_signature: SignerSignature::Starknet {
+                signer: Felt::from(30),
+                r: Felt::from(31),
+                s: Felt::from(32),
+            },
+            guardian_signature: SignerSignature::Starknet {
+                signer: Felt::from(40),
+                r: Felt::from(41),
+                s: Felt::from(42),
+            },
+            proofs: vec![vec![Felt::from(50)], vec![]],
+        }
+    }
+
+    #[test]
+    fn controller_session_signature_is_assembled_properly() {
+        let signature = a_session_authorization(Some(Felt::from(14))).signature();
+
+        let expected: Vec<Felt> = [100u64, 11, 12, 13, 14, 1, 2, 20, 21, 0, 30, 31, 32, 0, 40, 41, 42, 2, 1, 50, 0]
+            .into_iter()
+            .map(Felt::from)
+            .collect();
+        assert_eq!(signature[0], SESSION_MAGIC);
+        assert_eq!(signature[1..], expected);
+    }
+
+    #[test]
+    fn argent_session_signature_has_no_guardian_key_guid() {
+        let signature = a_session_authorization(None).signature();
+
+        let expected: Vec<Felt> = [100u64, 11, 12, 13, 1, 2, 20, 21, 0, 30, 31, 32, 0, 40, 41, 42, 2, 1, 50, 0]
+            .into_iter()
+            .map(Felt::from)
+            .collect();
+        assert_eq!(signature[0], SESSION_MAGIC);
+        assert_eq!(signature[1..], expected);
+    }
+}
@@ -22,6 +22,14 @@ impl TimeBounds {
         self.execute_after <= now && now < self.execute_before
     }
 
+    /// Returns true when the bounds end before `latency` has elapsed, in which case a transaction submitted now
+    /// would most likely be included too late and revert
+    pub fn expires_within(&self, latency: Duration) -> bool {
+        let now = Utc::now().timestamp() as u64;
+
+        now.saturating_add(latency.as_secs()) >= self.execute_before
+    }
+
     pub fn valid_for(validity: Duration) -> Self {
         Self {
             execute_after: 1,
@@ -38,3 +46,18 @@ impl AsCalldata for TimeBounds {
             .build()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::transaction::TimeBounds;
+
+    #[test]
+    fn expiry_accounts_for_inclusion_latency() {
+        let bounds = TimeBounds::valid_for(Duration::from_secs(60));
+
+        assert!(!bounds.expires_within(Duration::from_secs(30)));
+        assert!(bounds.expires_within(Duration::from_secs(120)));
+    }
+}
@@ -1,12 +1,18 @@
 use std::collections::HashMap;
 
+#[cfg(feature = "native")]
 use paymaster_common::concurrency::ConcurrentExecutor;
+#[cfg(feature = "native")]
 use paymaster_common::task;
-use starknet::core::types::{Felt, FunctionCall};
+use starknet::core::types::Felt;
+#[cfg(feature = "native")]
+use starknet::core::types::FunctionCall;
 use starknet::macros::selector;
 
 use crate::contract::ContractClass;
-use crate::{Client, Error};
+#[cfg(feature = "native")]
+use crate::Client;
+use crate::Error;
 
 const PAYMASTER_V1_INTERFACE_ID: Felt = Felt::from_raw([492161624466288994, 7331630999786889399, 16029490553032031222, 10189501558710363126]);
 const PAYMASTER_V2_INTERFACE_ID: Felt = Felt::from_raw([150957962276023817, 11215169228216991143, 16086434234789672676, 1434039593026997526]);
@@ -55,7 +61,10 @@ impl PaymasterVersion {
             PaymasterVersion::V2 => selector!("execute_from_outside_v2"),
         }
     }
+}
 
+#[cfg(feature = "native")]
+impl PaymasterVersion {
     #[rustfmt::skip]
     pub async fn fetch_supported_version(starknet: &Client, user: Felt) -> Result<SupportedVersion, Error> {
         let results = ConcurrentExecutor::new(starknet.clone(), 8)
@@ -50,7 +50,7 @@ impl<'a> TypedValueDecoder<'a> {
     pub fn decode_array(&self) -> Result<ArrayValueDecoder<'_>, Error> {
         match self.0 {
             Value::Array(value) => Ok(ArrayValueDecoder { element: 0, value }),
-            _ => Err(Error::TypedDataDecoding("value is not an object".to_string())),
+            _ => Err(Error::TypedDataDecoding("value is not an array".to_string())),
         }
     }
 }
@@ -1,6 +1,7 @@
+#[cfg(feature = "http-client")]
 pub use paymaster_rpc::client::{Client, Error};
 pub use paymaster_rpc::{
-    BuildTransactionRequest, BuildTransactionResponse, DeployAndInvokeTransaction, DeployTransaction, DeploymentParameters, ExecutableInvokeParameters,
-    ExecutableTransactionParameters, ExecuteRequest, ExecuteResponse, ExecutionParameters, FeeEstimate, FeeMode, InvokeParameters, InvokeTransaction, TimeBounds,
-    TransactionParameters,
+    BuildTransactionRequest, BuildTransactionResponse, ClientNonce, DeployAndInvokeTransaction, DeployTransaction, DeploymentParameters, ExecutableInvokeParameters,
+    ExecutableTransactionParameters, ExecuteRequest, ExecuteResponse, ExecutionParameters, ExecutionTimings, FeeEstimate, FeeMode, Finality, FinalityLevel,
+    FinalityStatus, InvokeParameters, InvokeTransaction, PaymasterAPIClient, Session, SessionAuthorization, SignerSignature, TimeBounds, TransactionParameters,
 };